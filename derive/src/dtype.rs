@@ -0,0 +1,231 @@
+//! Implementation of the `dtype!` macro.
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{braced, bracketed, parenthesized, Token};
+
+/// The input to `dtype!`, or the dtype of a single field.
+enum DTypeExpr {
+    Plain(syn::LitStr),
+    Record(Vec<FieldExpr>),
+    Array(Box<DTypeExpr>, Vec<syn::LitInt>),
+}
+
+struct FieldExpr {
+    name: String,
+    span: Span,
+    dtype: DTypeExpr,
+}
+
+impl Parse for DTypeExpr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+        if lookahead.peek(syn::LitStr) {
+            Ok(DTypeExpr::Plain(input.parse()?))
+        } else if lookahead.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            let fields = content.parse_terminated::<_, Token![,]>(FieldExpr::parse)?;
+            Ok(DTypeExpr::Record(fields.into_iter().collect()))
+        } else if lookahead.peek(syn::token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            let inner = content.parse::<DTypeExpr>()?;
+            if let DTypeExpr::Array(..) = inner {
+                return Err(content.error("expected a type string or a record"));
+            }
+            content.parse::<Token![,]>()?;
+            let dims;
+            bracketed!(dims in content);
+            let dims = dims.parse_terminated::<_, Token![,]>(|input| input.parse::<syn::LitInt>())?;
+            for dim in &dims {
+                dim.base10_parse::<u64>()?;
+            }
+            let _ = content.parse::<Option<Token![,]>>()?;
+            Ok(DTypeExpr::Array(Box::new(inner), dims.into_iter().collect()))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+impl Parse for FieldExpr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+        let (name, span) = if lookahead.peek(syn::LitStr) {
+            let lit = input.parse::<syn::LitStr>()?;
+            (lit.value(), lit.span())
+        } else if lookahead.peek(syn::Ident::peek_any) {
+            let ident = input.call(syn::Ident::parse_any)?;
+            (super::unraw(&ident), ident.span())
+        } else {
+            return Err(lookahead.error());
+        };
+        input.parse::<Token![:]>()?;
+        let dtype = input.parse()?;
+        Ok(FieldExpr { name, span, dtype })
+    }
+}
+
+pub(crate) fn expand(input: TokenStream) -> syn::Result<TokenStream> {
+    let dtype = syn::parse2::<DTypeExpr>(input)?;
+    if let DTypeExpr::Array(..) = dtype {
+        return Err(syn::Error::new(Span::call_site(), "array dtypes are only possible as the type of a field"));
+    }
+    let code = gen_dtype(&dtype)?;
+
+    Ok(quote! {{
+        #[allow(unknown_lints)]
+        #[allow(clippy::useless_attribute)]
+        #[allow(rust_2018_idioms)]
+        extern crate npyz as _npyz;
+
+        #code
+    }})
+}
+
+fn gen_dtype(dtype: &DTypeExpr) -> syn::Result<TokenStream> {
+    match dtype {
+        DTypeExpr::Plain(lit) => {
+            let TypeStrParts { endianness, type_char, size, time_units } = {
+                parse_type_str(&lit.value()).map_err(|msg| syn::Error::new(lit.span(), msg))?
+            };
+            let endianness = match endianness {
+                "Native" => quote!{ _npyz::Endianness::of_machine() },
+                _ => {
+                    let endianness = syn::Ident::new(endianness, Span::call_site());
+                    quote!{ _npyz::Endianness::#endianness }
+                },
+            };
+            let type_char = syn::Ident::new(type_char, Span::call_site());
+            let time_units = match time_units {
+                Some(units) => {
+                    let units = syn::Ident::new(units, Span::call_site());
+                    quote!{ ::std::option::Option::Some(_npyz::TimeUnits::#units) }
+                },
+                None => quote!{ ::std::option::Option::None },
+            };
+            Ok(quote!{
+                _npyz::DType::Plain(_npyz::TypeStr::__from_validated_parts(
+                    #endianness,
+                    _npyz::TypeChar::#type_char,
+                    #size,
+                    #time_units,
+                ))
+            })
+        },
+        DTypeExpr::Record(fields) => {
+            let mut names = std::collections::HashSet::new();
+            let mut field_codes = vec![];
+            for FieldExpr { name, span, dtype } in fields {
+                if !names.insert(name) {
+                    return Err(syn::Error::new(*span, format!("duplicate field name {:?}", name)));
+                }
+                let dtype = gen_dtype(dtype)?;
+                field_codes.push(quote!{
+                    _npyz::Field {
+                        name: ::std::string::ToString::to_string(#name),
+                        dtype: #dtype,
+                    }
+                });
+            }
+            Ok(quote!{ _npyz::DType::Record(::std::vec![ #(#field_codes),* ]) })
+        },
+        DTypeExpr::Array(inner, dims) => {
+            let mut code = gen_dtype(inner)?;
+            for dim in dims.iter().rev() {
+                let dim = dim.base10_parse::<u64>()?;
+                code = quote!{ _npyz::DType::Array(#dim, ::std::boxed::Box::new(#code)) };
+            }
+            Ok(code)
+        },
+    }
+}
+
+/// Variant names of the components of a type string.
+struct TypeStrParts {
+    endianness: &'static str,
+    type_char: &'static str,
+    size: u64,
+    time_units: Option<&'static str>,
+}
+
+// This mirrors the validation of `TypeStr` in the main crate, which we cannot depend on.
+fn parse_type_str(input: &str) -> Result<TypeStrParts, String> {
+    let syntax_error = || format!("invalid type string {:?}", input);
+
+    let mut chars = input.chars();
+    let endianness = match chars.next() {
+        Some('<') => "Little",
+        Some('>') => "Big",
+        Some('|') => "Irrelevant",
+        Some('=') => "Native",
+        _ => return Err(syntax_error()),
+    };
+    let (type_char, valid_sizes): (_, Option<&[u64]>) = match chars.next() {
+        Some('b') => ("Bool", Some(&[1])),
+        Some('i') => ("Int", Some(&[1, 2, 4, 8])),
+        Some('u') => ("Uint", Some(&[1, 2, 4, 8])),
+        Some('f') => ("Float", Some(&[2, 4, 8, 16])),
+        Some('c') => ("Complex", Some(&[8, 16, 32])),
+        Some('m') => ("TimeDelta", Some(&[8])),
+        Some('M') => ("DateTime", Some(&[8])),
+        Some('S') | Some('a') => ("ByteStr", None),
+        Some('U') => ("UnicodeStr", None),
+        Some('V') => ("RawData", None),
+        _ => return Err(syntax_error()),
+    };
+
+    let remainder = chars.as_str();
+    let size_end = remainder.bytes().position(|b| !b.is_ascii_digit()).unwrap_or(remainder.len());
+    if size_end == 0 {
+        return Err(syntax_error());
+    }
+    let (size, remainder) = remainder.split_at(size_end);
+    let size = size.parse::<u64>().map_err(|e| format!("invalid type string {:?}: {}", input, e))?;
+
+    let time_units = match remainder {
+        "" => None,
+        _ => match remainder.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            Some("Y") => Some("Year"),
+            Some("M") => Some("Month"),
+            Some("W") => Some("Week"),
+            Some("D") => Some("Day"),
+            Some("h") => Some("Hour"),
+            Some("m") => Some("Minute"),
+            Some("s") => Some("Second"),
+            Some("ms") => Some("Millisecond"),
+            Some("us") => Some("Microsecond"),
+            Some("ns") => Some("Nanosecond"),
+            Some("ps") => Some("Picosecond"),
+            Some("fs") => Some("Femtosecond"),
+            Some("as") => Some("Attosecond"),
+            _ => return Err(syntax_error()),
+        },
+    };
+
+    let requires_endianness = match type_char {
+        "UnicodeStr" => true,
+        "ByteStr" | "RawData" => false,
+        _ => size != 1,
+    };
+    if requires_endianness && endianness == "Irrelevant" {
+        return Err(format!("type string {:?} has invalid endianness", input));
+    }
+    if let Some(valid_sizes) = valid_sizes {
+        if !valid_sizes.contains(&size) {
+            return Err(format!("type string {:?} has invalid size. Valid sizes are: {:?}", input, valid_sizes));
+        }
+    }
+    let has_units = matches!(type_char, "TimeDelta" | "DateTime");
+    if has_units && time_units.is_none() {
+        return Err(format!("type string {:?} is missing time units", input));
+    }
+    if !has_units && time_units.is_some() {
+        return Err(format!("unexpected time units in type string {:?}", input));
+    }
+
+    Ok(TypeStrParts { endianness, type_char, size, time_units })
+}
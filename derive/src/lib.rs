@@ -7,12 +7,16 @@ Using this crate, it is enough to `#[derive(npyz::Serialize, npyz::Deserialize)]
 serialize and deserialize it. All of the fields must implement [`Serialize`](../npyz/trait.Serialize.html)
 and [`Deserialize`](../npyz/trait.Deserialize.html) respectively.
 
+This crate also provides the [`dtype!`] macro for writing a `DType` as a literal.
+
 */
 
 use proc_macro::{TokenStream as TokenStream1};
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::TokenStream;
 use quote::quote;
 
+mod dtype;
+
 #[proc_macro_derive(Serialize)]
 pub fn npy_serialize(input: TokenStream1) -> TokenStream1 {
     // Parse the string representation
@@ -49,6 +53,27 @@ pub fn npy_auto_serialize(input: TokenStream1) -> TokenStream1 {
     expanded.into()
 }
 
+/// Construct an `npyz::DType` from a literal, with type strings validated at compile time.
+///
+/// The input is either a type string, or a record in braces whose fields are written as `name: dtype`.
+/// Field names may be identifiers or string literals.  A field can be made into an array field
+/// by writing `(dtype, [dims...])`.
+///
+/// ```ignore
+/// let dtype = npyz::dtype!({
+///     id: "<i8",
+///     pos: ("<f4", [3]),
+///     "has spaces": { a: "|u1", b: "<M8[ns]" },
+/// });
+/// ```
+#[proc_macro]
+pub fn dtype(input: TokenStream1) -> TokenStream1 {
+    match dtype::expand(input.into()) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 struct FieldData {
     idents: Vec<syn::Ident>,
     idents_str: Vec<String>,
@@ -65,7 +90,7 @@ impl FieldData {
         let idents: Vec<syn::Ident> = fields.iter().map(|f| {
             f.ident.clone().expect("Tuple structs not supported")
         }).collect();
-        let idents_str = idents.iter().map(|t| unraw(t)).collect::<Vec<_>>();
+        let idents_str = idents.iter().map(unraw).collect::<Vec<_>>();
 
         let types: Vec<TokenStream> = fields.iter().map(|f| {
             let ty = &f.ty;
@@ -86,7 +111,7 @@ fn impl_npy_serialize(ast: &syn::DeriveInput) -> TokenStream {
 
     let idents_1 = idents;
 
-    wrap_in_const("Serialize", &name, quote! {
+    wrap_in_const(quote! {
         use ::std::io;
 
         #vis struct GeneratedWriter #ty_generics #where_clause {
@@ -137,7 +162,7 @@ fn impl_npy_deserialize(ast: &syn::DeriveInput) -> TokenStream {
 
     let idents_1 = idents;
 
-    wrap_in_const("Deserialize", &name, quote! {
+    wrap_in_const(quote! {
         use ::std::io;
 
         #vis struct GeneratedReader #ty_generics #where_clause {
@@ -157,7 +182,7 @@ fn impl_npy_deserialize(ast: &syn::DeriveInput) -> TokenStream {
             fn read_one<R: io::Read>(&self, mut reader: R) -> io::Result<Self::Value> {
                 #(
                     let func = <<#types as _npyz::Deserialize>::TypeReader as _npyz::TypeRead>::read_one;
-                    let #idents = func(&self.readers.#idents_1, &mut reader)?;
+                    let #idents = func(&self.readers.#idents_1, &mut reader).map_err(|e| _npyz::Error::__in_field(e, #idents_str))?;
                 )*
                 io::Result::Ok(#name { #( #idents ),* })
             }
@@ -184,7 +209,7 @@ fn impl_npy_auto_serialize(ast: &syn::DeriveInput) -> TokenStream {
 
     let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
 
-    wrap_in_const("AutoSerialize", &name, quote! {
+    wrap_in_const(quote! {
         impl #impl_generics _npyz::AutoSerialize for #name #ty_generics #where_clause {
             fn default_dtype() -> _npyz::DType {
                 _npyz::DType::Record(::std::vec![#(
@@ -239,21 +264,12 @@ fn gen_field_dtypes_struct(
 }
 
 // from the wonderful folks working on serde
-fn wrap_in_const(
-    trait_: &str,
-    ty: &syn::Ident,
-    code: TokenStream,
-) -> TokenStream {
-    let dummy_const = syn::Ident::new(
-        &format!("__IMPL_npy_{}_FOR_{}", trait_, unraw(ty)),
-        Span::call_site(),
-    );
-
+fn wrap_in_const(code: TokenStream) -> TokenStream {
     quote! {
-        #[allow(non_upper_case_globals, unused_attributes, unused_qualifications)]
-        const #dummy_const: () = {
+        #[allow(unused_attributes, unused_qualifications)]
+        const _: () = {
             #[allow(unknown_lints)]
-            #[cfg_attr(feature = "cargo-clippy", allow(useless_attribute))]
+            #[allow(clippy::useless_attribute)]
             #[allow(rust_2018_idioms)]
             extern crate npyz as _npyz;
 
@@ -42,7 +42,7 @@ fn read_a_file() -> ZipResult<()> {
 
     // here's how you would iterate over a file with unknown arrays
     for file_name in zip.file_names() {
-        if let Some(array_name) = npz::array_name_from_file_name(&file_name) {
+        if let Some(array_name) = npz::array_name_from_file_name(file_name) {
             println!("Found array: {}", array_name);
         }
     }
@@ -0,0 +1,144 @@
+/*
+ * C API of npyz, for reading and writing NPY files.
+ *
+ * Build the library with:
+ *     cargo rustc --release --features capi --crate-type cdylib
+ *
+ * Data is passed as raw bytes in the dtype of the file, which is described by its descr as it
+ * appears in the header, e.g. '<f8' or [('x', '<i4'), ('y', '<f4')].
+ *
+ * Functions that can fail return NULL or a negative number; npyz_last_error() then gives the
+ * message of the error.
+ */
+
+#ifndef NPYZ_H
+#define NPYZ_H
+
+/* Generated by cbindgen from src/capi.rs; do not edit.  Regenerate it with the command in cbindgen.toml. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// An NPY file opened for reading with [`npyz_reader_open`].
+typedef struct NpyzReader NpyzReader;
+
+// An NPY file being written, created with [`npyz_writer_create`].
+typedef struct NpyzWriter NpyzWriter;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Get the message of the last error on this thread, or `NULL` if there was none.
+//
+// The string is owned by the library, and stays valid until the next failing call on this thread.
+const char *npyz_last_error(void);
+
+// Open an NPY file and read its header.
+//
+// Returns `NULL` on failure.  The reader must be freed with [`npyz_reader_close`].
+//
+// # Safety
+//
+// `path` must be `NULL` or a NUL-terminated string.
+struct NpyzReader *npyz_reader_open(const char *path);
+
+// Get the dtype of the file as a `descr` string.  The string is owned by the reader.
+//
+// # Safety
+//
+// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+const char *npyz_reader_descr(const struct NpyzReader *reader);
+
+// Get the number of dimensions of the array.
+//
+// # Safety
+//
+// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+size_t npyz_reader_ndim(const struct NpyzReader *reader);
+
+// Get the shape of the array, an array of [`npyz_reader_ndim`] numbers owned by the reader.
+//
+// # Safety
+//
+// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+const uint64_t *npyz_reader_shape(const struct NpyzReader *reader);
+
+// Returns 1 if the data is in Fortran order, or 0 if it is in C order.
+//
+// # Safety
+//
+// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+int npyz_reader_fortran_order(const struct NpyzReader *reader);
+
+// Get the number of bytes in each record.
+//
+// # Safety
+//
+// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+size_t npyz_reader_item_size(const struct NpyzReader *reader);
+
+// Get the total number of records in the file.
+//
+// # Safety
+//
+// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+uint64_t npyz_reader_len(const struct NpyzReader *reader);
+
+// Read the raw bytes of up to `max_records` of the next records, in the order they are stored.
+//
+// Returns the number of records read, which is 0 once all have been read, or -1 on failure.
+//
+// # Safety
+//
+// `reader` must come from [`npyz_reader_open`] and not yet be closed, and `buf` must have room for
+// `max_records` times [`npyz_reader_item_size`] bytes.
+int64_t npyz_reader_read(struct NpyzReader *reader, void *buf, uint64_t max_records);
+
+// Close a reader, freeing it.  Does nothing if `reader` is `NULL`.
+//
+// # Safety
+//
+// `reader` must be `NULL` or come from [`npyz_reader_open`] and not yet be closed.
+void npyz_reader_close(struct NpyzReader *reader);
+
+// Create an NPY file with a dtype given as a `descr` string, and write its header.
+//
+// The shape has `ndim` dimensions, and the data is in Fortran order if `fortran_order` is nonzero.
+// Returns `NULL` on failure.  The writer must be freed with [`npyz_writer_finish`].
+//
+// # Safety
+//
+// `path` and `descr` must be `NULL` or NUL-terminated strings, and `shape` must point to `ndim` numbers.
+struct NpyzWriter *npyz_writer_create(const char *path,
+                                      const char *descr,
+                                      const uint64_t *shape,
+                                      size_t ndim,
+                                      int fortran_order);
+
+// Write the raw bytes of `n_records` records, in the order they are stored.
+//
+// Returns 0 on success, or -1 on failure, including when this would write more records than the
+// shape holds.
+//
+// # Safety
+//
+// `writer` must come from [`npyz_writer_create`] and not yet be finished, and `data` must point to
+// `n_records` records of the item size of the dtype.
+int npyz_writer_write(struct NpyzWriter *writer, const void *data, uint64_t n_records);
+
+// Finish writing the file, and free the writer.
+//
+// Returns 0 on success, or -1 on failure, including when fewer records were written than the shape
+// holds.  The writer is freed either way.
+//
+// # Safety
+//
+// `writer` must come from [`npyz_writer_create`] and not yet be finished.
+int npyz_writer_finish(struct NpyzWriter *writer);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* NPYZ_H */
@@ -0,0 +1,209 @@
+//! A growable array with a chosen alignment.
+
+use std::alloc::{self, Layout};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// A contiguous growable array, like a `Vec<T>`, whose buffer has a chosen alignment.
+///
+/// This is returned by [`NpyFile::into_aligned_vec`][`crate::NpyFile::into_aligned_vec`], for data that will be
+/// used with APIs that require more alignment than `T` does, such as `O_DIRECT` I/O (typically 4096 bytes)
+/// or SIMD kernels (typically 32 or 64 bytes).  The alignment is kept when the array grows.
+///
+/// It derefs to `[T]`.  A `Vec<T>` cannot be used instead, because a `Vec` must be deallocated with
+/// the alignment of `T`.
+pub struct AlignedVec<T> {
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+    align: usize,
+    _marker: PhantomData<T>,
+}
+
+// An AlignedVec owns its elements, like a Vec.
+unsafe impl<T: Send> Send for AlignedVec<T> {}
+unsafe impl<T: Sync> Sync for AlignedVec<T> {}
+
+impl<T> AlignedVec<T> {
+    /// Construct an empty array whose buffer will be aligned to `align` bytes, or to the alignment of `T`
+    /// if that is larger.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn new(align: usize) -> Self {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        let align = align.max(std::mem::align_of::<T>());
+        // zero-sized values never need to be allocated
+        let capacity = if std::mem::size_of::<T>() == 0 { usize::MAX } else { 0 };
+        AlignedVec { ptr: dangling(align), len: 0, capacity, align, _marker: PhantomData }
+    }
+
+    /// Construct an empty array with room for `capacity` elements.  See [`Self::new`].
+    pub fn with_capacity(capacity: usize, align: usize) -> Self {
+        let mut out = Self::new(align);
+        out.reserve_exact(capacity);
+        out
+    }
+
+    /// Get the alignment of the buffer in bytes.
+    pub fn alignment(&self) -> usize {
+        self.align
+    }
+
+    /// Get the number of elements the array can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Append an element.
+    pub fn push(&mut self, value: T) {
+        if self.len == self.capacity {
+            self.reserve_exact(self.capacity.max(4));
+        }
+        // SAFETY: There is room for another element.
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    /// Make room for at least `additional` more elements, without allocating more than needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new size overflows `isize`.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let new_capacity = self.len.checked_add(additional).expect("capacity overflow");
+        if new_capacity <= self.capacity {
+            return;
+        }
+        let new_layout = self.layout(new_capacity);
+        let ptr = match self.allocated_size() {
+            // SAFETY: The layout has a nonzero size, because T is not zero-sized and new_capacity > 0.
+            0 => unsafe { alloc::alloc(new_layout) },
+            // SAFETY: The pointer was allocated with this layout, and the new size is nonzero and fits in isize.
+            _ => unsafe { alloc::realloc(self.ptr.as_ptr() as *mut u8, self.layout(self.capacity), new_layout.size()) },
+        };
+        self.ptr = NonNull::new(ptr as *mut T).unwrap_or_else(|| alloc::handle_alloc_error(new_layout));
+        self.capacity = new_capacity;
+    }
+
+    /// Set the number of elements.
+    ///
+    /// # Safety
+    ///
+    /// `len` must be at most the capacity, and the elements up to `len` must be initialized.
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    /// Get a pointer to the buffer.
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    fn layout(&self, capacity: usize) -> Layout {
+        Layout::array::<T>(capacity).and_then(|layout| layout.align_to(self.align)).expect("capacity overflow")
+    }
+
+    fn allocated_size(&self) -> usize {
+        self.capacity * std::mem::size_of::<T>()
+    }
+}
+
+// A well-aligned pointer for an empty buffer, as in `NonNull::dangling`.
+fn dangling<T>(align: usize) -> NonNull<T> {
+    NonNull::new(align as *mut T).expect("alignment is nonzero")
+}
+
+impl<T> Drop for AlignedVec<T> {
+    fn drop(&mut self) {
+        // SAFETY: The first `len` elements are initialized, and the buffer was allocated with this layout.
+        unsafe {
+            std::ptr::drop_in_place(std::ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), self.len));
+            if self.allocated_size() > 0 {
+                alloc::dealloc(self.ptr.as_ptr() as *mut u8, self.layout(self.capacity));
+            }
+        }
+    }
+}
+
+impl<T> Deref for AlignedVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: The first `len` elements are initialized.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for AlignedVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // SAFETY: The first `len` elements are initialized, and self is borrowed mutably.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Clone> Clone for AlignedVec<T> {
+    fn clone(&self) -> Self {
+        let mut out = Self::with_capacity(self.len, self.align);
+        self.iter().for_each(|x| out.push(x.clone()));
+        out
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for AlignedVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for AlignedVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Eq> Eq for AlignedVec<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment() {
+        for align in [1, 8, 64, 4096] {
+            let mut vec = AlignedVec::<u8>::new(align);
+            for x in 0..100 {
+                vec.push(x);
+                assert_eq!(vec.as_ptr() as usize % align, 0);
+            }
+            assert_eq!(&vec[..], &(0..100).collect::<Vec<_>>()[..]);
+            assert_eq!(vec.clone(), vec);
+        }
+        assert_eq!(AlignedVec::<u64>::new(1).alignment(), std::mem::align_of::<u64>());
+        assert_eq!(AlignedVec::<u64>::new(4096).as_ptr() as usize % 4096, 0);
+    }
+
+    #[test]
+    fn drops_elements() {
+        let counter = std::rc::Rc::new(());
+        let mut vec = AlignedVec::with_capacity(2, 64);
+        for _ in 0..10 {
+            vec.push(counter.clone());
+        }
+        assert_eq!(std::rc::Rc::strong_count(&counter), 11);
+        drop(vec);
+        assert_eq!(std::rc::Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn zero_sized() {
+        let mut vec = AlignedVec::new(32);
+        for _ in 0..5 {
+            vec.push(());
+        }
+        assert_eq!(vec.len(), 5);
+    }
+}
@@ -0,0 +1,130 @@
+//! `arbitrary` impls for dtypes, shapes and small arrays.
+//!
+//! _This module is only available with the **`"arbitrary"`** feature._
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::generate::small::{self, MAX_ARRAY_FIELD_LEN, MAX_DEPTH, MAX_DIM_LEN, MAX_FIELDS, MAX_NDIM, MAX_STRING_SIZE};
+use crate::generate::{SmallArray, SmallShape};
+use crate::header::DType;
+use crate::type_str::TypeStr;
+
+fn type_str(u: &mut Unstructured<'_>, extended_precision: bool) -> Result<TypeStr> {
+    let type_char = *u.choose(small::TYPE_CHARS)?;
+    let size = match small::sizes(type_char, extended_precision) {
+        Some(sizes) => *u.choose(&sizes)?,
+        None => u.int_in_range(0..=MAX_STRING_SIZE)?,
+    };
+    let time_units = match type_char.has_units() {
+        true => Some(*u.choose(small::TIME_UNITS)?),
+        false => None,
+    };
+    let endianness = *u.choose(small::endiannesses(type_char, size))?;
+    Ok(small::type_str(endianness, type_char, size, time_units))
+}
+
+fn record(u: &mut Unstructured<'_>, extended_precision: bool, depth: u32) -> Result<DType> {
+    let num_fields = u.int_in_range(1..=MAX_FIELDS)?;
+    let fields = (0..num_fields).map(|_| {
+        Ok((*u.choose(small::FIELD_NAMES)?, field(u, extended_precision, depth)?))
+    }).collect::<Result<Vec<_>>>()?;
+    Ok(small::record(fields))
+}
+
+fn field(u: &mut Unstructured<'_>, extended_precision: bool, depth: u32) -> Result<DType> {
+    if depth == 0 {
+        return Ok(DType::Plain(type_str(u, extended_precision)?));
+    }
+    Ok(match u.int_in_range(0..=3)? {
+        0 => DType::Array(u.int_in_range(1..=MAX_ARRAY_FIELD_LEN)?, Box::new(field(u, extended_precision, depth - 1)?)),
+        1 => record(u, extended_precision, depth - 1)?,
+        _ => DType::Plain(type_str(u, extended_precision)?),
+    })
+}
+
+fn dtype(u: &mut Unstructured<'_>, extended_precision: bool) -> Result<DType> {
+    match u.arbitrary()? {
+        true => record(u, extended_precision, MAX_DEPTH),
+        false => Ok(DType::Plain(type_str(u, extended_precision)?)),
+    }
+}
+
+/// _This impl is only available with the **`"arbitrary"`** feature._
+impl<'a> Arbitrary<'a> for TypeStr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        type_str(u, true)
+    }
+}
+
+/// Generates the dtype of a file: a type string, or a record whose fields may be records or arrays.
+///
+/// _This impl is only available with the **`"arbitrary"`** feature._
+impl<'a> Arbitrary<'a> for DType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        dtype(u, true)
+    }
+}
+
+/// _This impl is only available with the **`"arbitrary"`** feature._
+impl<'a> Arbitrary<'a> for SmallShape {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let ndim = u.int_in_range(0..=MAX_NDIM)?;
+        let shape = (0..ndim).map(|_| u.int_in_range(0..=MAX_DIM_LEN)).collect::<Result<_>>()?;
+        Ok(SmallShape(shape))
+    }
+}
+
+/// _This impl is only available with the **`"arbitrary"`** feature._
+impl<'a> Arbitrary<'a> for SmallArray {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let dtype = dtype(u, false)?;
+        let SmallShape(shape) = u.arbitrary()?;
+        Ok(small::array(dtype, shape, u.arbitrary()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NpyFile;
+
+    // Deterministic bytes that are not all the same, so that many different values are generated.
+    fn bytes(seed: u64) -> Vec<u8> {
+        let mut rng = crate::shuffle::SplitMix64(seed);
+        (0..256).map(|_| rng.next() as u8).collect()
+    }
+
+    #[test]
+    fn dtypes_roundtrip() {
+        let mut records = 0;
+        for seed in 0..200 {
+            let bytes = bytes(seed);
+            let dtype = DType::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            if let DType::Record(_) = dtype {
+                records += 1;
+            }
+            assert_eq!(DType::parse(&dtype.descr()).unwrap(), dtype);
+        }
+        assert!(records > 50);
+    }
+
+    #[test]
+    fn small_arrays() {
+        for seed in 0..200 {
+            let bytes = bytes(seed);
+            let array = SmallArray::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let npy = NpyFile::new(&array.bytes[..]).unwrap();
+            assert_eq!(npy.dtype(), array.dtype);
+            assert_eq!(npy.shape(), array.shape);
+            assert!(array.shape.len() <= MAX_NDIM);
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        // `arbitrary` falls back to the first choice when the data runs out
+        let dtype = DType::arbitrary(&mut Unstructured::new(&[])).unwrap();
+        assert_eq!(dtype, DType::parse("'<b1'").unwrap());
+        assert_eq!(SmallShape::arbitrary(&mut Unstructured::new(&[])).unwrap(), SmallShape(vec![]));
+    }
+}
@@ -0,0 +1,430 @@
+//! Reading NPZ files from a tokio [`AsyncRead`] + [`AsyncSeek`].
+//!
+//! The zip crate has no async interface, so the central directory and the local headers are parsed
+//! here, and deflated members are inflated with `flate2`.
+
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use flate2::{Crc, Decompress, FlushDecompress, Status};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, ReadBuf};
+
+use crate::async_read::AsyncNpyFile;
+use crate::error::Error;
+use crate::read::ReadOptions;
+use crate::read_at::truncated;
+
+const LOCAL_HEADER_SIGNATURE: &[u8; 4] = b"PK\x03\x04";
+const LOCAL_HEADER_LEN: usize = 30;
+const CENTRAL_HEADER_SIGNATURE: &[u8; 4] = b"PK\x01\x02";
+const CENTRAL_HEADER_LEN: usize = 46;
+const EOCD_SIGNATURE: &[u8; 4] = b"PK\x05\x06";
+const EOCD_LEN: usize = 22;
+const ZIP64_LOCATOR_SIGNATURE: &[u8; 4] = b"PK\x06\x07";
+const ZIP64_LOCATOR_LEN: usize = 20;
+const ZIP64_EOCD_SIGNATURE: &[u8; 4] = b"PK\x06\x06";
+const ZIP64_EOCD_LEN: usize = 56;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+// how many compressed bytes an entry reads at once
+const INPUT_CHUNK: usize = 1 << 16;
+
+/// Interface for reading an NPZ file from a tokio [`AsyncRead`] + [`AsyncSeek`].
+///
+/// This is an async counterpart of [`NpzArchive`][`crate::npz::NpzArchive`].  The central directory
+/// is read when the archive is created, and each array is then read in chunks through an
+/// [`AsyncNpyReader`][`crate::AsyncNpyReader`], so the archive never needs to be in memory in full.
+///
+/// Only stored and deflated members are supported, which covers everything written by `np.savez`,
+/// `np.savez_compressed` and [`NpzWriter`][`crate::npz::NpzWriter`].  Encrypted members are not supported.
+/// When several members have the same name, the last one is used, like numpy.
+///
+/// *This is only available with the **`"async"`** feature.*
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+/// use npyz::npz::AsyncNpzArchive;
+///
+/// let file = tokio::fs::File::open("test-data/compressed.npz").await?;
+/// let mut npz = AsyncNpzArchive::new(file).await?;
+/// let npy = npz.by_name("ints").await?.expect("no such array");
+/// println!("{:?}", npy.shape());
+/// let ints = npy.into_vec::<i64>().await?;
+/// # let _ = ints;
+/// # Ok(()) })
+/// # }
+/// ```
+pub struct AsyncNpzArchive<R: AsyncRead + AsyncSeek + Unpin> {
+    reader: R,
+    options: ReadOptions,
+    // file names in the order they are stored, with the entry used for each
+    names: Vec<String>,
+    entries: HashMap<String, CentralEntry>,
+}
+
+// What the central directory says about a member.
+#[derive(Debug, Clone)]
+struct CentralEntry {
+    flags: u16,
+    method: u16,
+    crc: u32,
+    compressed_size: u64,
+    size: u64,
+    local_header_offset: u64,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncNpzArchive<R> {
+    /// Read the central directory of an `npz` archive.
+    pub async fn new(mut reader: R) -> io::Result<Self> {
+        let mut names = vec![];
+        let mut entries = HashMap::new();
+        for (name, entry) in read_central_directory(&mut reader).await? {
+            if entries.insert(name.clone(), entry).is_none() {
+                names.push(name);
+            }
+        }
+        Ok(AsyncNpzArchive { reader, options: ReadOptions::default(), names, entries })
+    }
+
+    /// Set the options used when reading the arrays.
+    pub fn with_read_options(mut self, options: ReadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Get the options used when reading the arrays.
+    pub fn read_options(&self) -> &ReadOptions {
+        &self.options
+    }
+
+    /// Get the names of all arrays in the NPZ file, in the order they are stored.
+    pub fn array_names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().filter_map(|name| crate::npz::array_name_from_file_name(name))
+    }
+
+    /// Read the header of an array, and construct an [`AsyncNpyFile`] for reading its data.
+    ///
+    /// Returns `Ok(None)` if there is no array with this name.
+    pub async fn by_name(&mut self, name: &str) -> io::Result<Option<AsyncNpyFile<AsyncNpzEntry<'_, R>>>> {
+        let file_name = crate::npz::file_name_from_array_name(name);
+        let entry = match self.entries.get(&file_name) {
+            Some(entry) => entry.clone(),
+            None => return Ok(None),
+        };
+        let result = async {
+            let entry = open_entry(&mut self.reader, &entry).await?;
+            AsyncNpyFile::with_options(entry, &self.options).await
+        }.await;
+        match result {
+            Ok(npy) => Ok(Some(npy.with_member_name(name))),
+            Err(e) => Err(Error::in_member(e, name)),
+        }
+    }
+
+    /// Get the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+fn invalid_zip(msg: &str) -> io::Error {
+    Error::InvalidData(format!("invalid zip archive: {}", msg)).into()
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes(bytes[pos..pos + 2].try_into().unwrap())
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap())
+}
+
+async fn read_at(reader: &mut (impl AsyncRead + AsyncSeek + Unpin), offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    reader.seek(io::SeekFrom::Start(offset)).await?;
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes).await.map_err(truncated)?;
+    Ok(bytes)
+}
+
+async fn read_central_directory(reader: &mut (impl AsyncRead + AsyncSeek + Unpin)) -> io::Result<Vec<(String, CentralEntry)>> {
+    // The end of central directory record is followed by a comment of at most 0xffff bytes.
+    let file_len = reader.seek(io::SeekFrom::End(0)).await?;
+    let tail_start = file_len.saturating_sub((EOCD_LEN + 0xffff) as u64);
+    let tail = read_at(reader, tail_start, (file_len - tail_start) as usize).await?;
+    let eocd_pos = (0..(tail.len() + 1).saturating_sub(EOCD_LEN)).rev()
+        .find(|&pos| &tail[pos..pos + 4] == EOCD_SIGNATURE)
+        .ok_or_else(|| invalid_zip("no end of central directory record"))?;
+    let eocd = &tail[eocd_pos..];
+    let mut count = u16_at(eocd, 10) as u64;
+    let mut directory_len = u32_at(eocd, 12) as u64;
+    let mut directory_start = u32_at(eocd, 16) as u64;
+
+    let eocd_offset = tail_start + eocd_pos as u64;
+    if eocd_offset >= ZIP64_LOCATOR_LEN as u64 {
+        let locator = read_at(reader, eocd_offset - ZIP64_LOCATOR_LEN as u64, ZIP64_LOCATOR_LEN).await?;
+        if &locator[..4] == ZIP64_LOCATOR_SIGNATURE {
+            let eocd64 = read_at(reader, u64_at(&locator, 8), ZIP64_EOCD_LEN).await?;
+            if &eocd64[..4] != ZIP64_EOCD_SIGNATURE {
+                return Err(invalid_zip("bad zip64 end of central directory record"));
+            }
+            count = u64_at(&eocd64, 32);
+            directory_len = u64_at(&eocd64, 40);
+            directory_start = u64_at(&eocd64, 48);
+        }
+    }
+
+    if directory_start.checked_add(directory_len).map_or(true, |end| end > file_len) {
+        return Err(invalid_zip("the central directory is out of bounds"));
+    }
+    let directory = read_at(reader, directory_start, directory_len as usize).await?;
+    // (every header takes at least CENTRAL_HEADER_LEN bytes, so `count` can't be used to allocate too much)
+    let mut entries = Vec::with_capacity(count.min((directory.len() / CENTRAL_HEADER_LEN) as u64) as usize);
+    let mut pos = 0;
+    for _ in 0..count {
+        let header = directory.get(pos..pos + CENTRAL_HEADER_LEN)
+            .filter(|header| &header[..4] == CENTRAL_HEADER_SIGNATURE)
+            .ok_or_else(|| invalid_zip("bad central directory header"))?;
+        let name_len = u16_at(header, 28) as usize;
+        let extra_len = u16_at(header, 30) as usize;
+        let comment_len = u16_at(header, 32) as usize;
+        let name_start = pos + CENTRAL_HEADER_LEN;
+        let extra_start = name_start + name_len;
+        let end = extra_start + extra_len + comment_len;
+        if end > directory.len() {
+            return Err(invalid_zip("bad central directory header"));
+        }
+        let name = String::from_utf8_lossy(&directory[name_start..extra_start]).into_owned();
+
+        let mut entry = CentralEntry {
+            flags: u16_at(header, 8),
+            method: u16_at(header, 10),
+            crc: u32_at(header, 16),
+            compressed_size: u32_at(header, 20) as u64,
+            size: u32_at(header, 24) as u64,
+            local_header_offset: u32_at(header, 42) as u64,
+        };
+        apply_zip64_extra(&mut entry, &directory[extra_start..extra_start + extra_len])?;
+        entries.push((name, entry));
+        pos = end;
+    }
+    Ok(entries)
+}
+
+// Replace the sizes and offset that don't fit in 32 bits by those in the zip64 extra field.
+fn apply_zip64_extra(entry: &mut CentralEntry, mut extra: &[u8]) -> io::Result<()> {
+    while extra.len() >= 4 {
+        let id = u16_at(extra, 0);
+        let len = u16_at(extra, 2) as usize;
+        let data = extra.get(4..4 + len).ok_or_else(|| invalid_zip("bad extra field"))?;
+        if id == ZIP64_EXTRA_ID {
+            let mut values = data.chunks_exact(8).map(|chunk| u64_at(chunk, 0));
+            // the values are only present for the fields that are saturated, in this order
+            for field in [&mut entry.size, &mut entry.compressed_size, &mut entry.local_header_offset] {
+                if *field == u32::MAX as u64 {
+                    *field = values.next().ok_or_else(|| invalid_zip("bad zip64 extra field"))?;
+                }
+            }
+        }
+        extra = &extra[4 + len..];
+    }
+    Ok(())
+}
+
+async fn open_entry<'a, R: AsyncRead + AsyncSeek + Unpin>(reader: &'a mut R, entry: &CentralEntry) -> io::Result<AsyncNpzEntry<'a, R>> {
+    if entry.flags & 1 != 0 {
+        return Err(Error::InvalidInput("encrypted arrays are not supported".to_string()).into());
+    }
+    let inflate = match entry.method {
+        STORED => None,
+        DEFLATED => Some(Decompress::new(false)),
+        method => return Err(Error::InvalidData(format!("unsupported compression method {}", method)).into()),
+    };
+    let local = read_at(reader, entry.local_header_offset, LOCAL_HEADER_LEN).await?;
+    if &local[..4] != LOCAL_HEADER_SIGNATURE {
+        return Err(invalid_zip("bad local header"));
+    }
+    // (the sizes in the local header may be missing, so those of the central directory are used)
+    let skip = u16_at(&local, 26) as i64 + u16_at(&local, 28) as i64;
+    reader.seek(io::SeekFrom::Current(skip)).await?;
+    Ok(AsyncNpzEntry {
+        reader,
+        remaining: entry.compressed_size,
+        input: vec![],
+        start: 0,
+        inflate,
+        crc: Crc::new(),
+        expected_crc: entry.crc,
+        expected_size: entry.size,
+        done: false,
+    })
+}
+
+/// The contents of a member of an [`AsyncNpzArchive`], decompressed as they are read.
+///
+/// The CRC and size are checked against the central directory once the end is reached.
+///
+/// *This is only available with the **`"async"`** feature.*
+pub struct AsyncNpzEntry<'a, R> {
+    reader: &'a mut R,
+    // compressed bytes that have not been read from `reader`
+    remaining: u64,
+    // compressed bytes that have been read, of which those before `start` have been used
+    input: Vec<u8>,
+    start: usize,
+    // `None` for a stored member
+    inflate: Option<Decompress>,
+    crc: Crc,
+    expected_crc: u32,
+    expected_size: u64,
+    done: bool,
+}
+
+impl<R> AsyncNpzEntry<'_, R> {
+    // Decompress what is in `input` into `buf`, returning the number of bytes produced.
+    fn output(&mut self, buf: &mut ReadBuf<'_>) -> io::Result<usize> {
+        let input = &self.input[self.start..];
+        let produced = match &mut self.inflate {
+            None => {
+                let count = input.len().min(buf.remaining());
+                self.crc.update(&input[..count]);
+                buf.put_slice(&input[..count]);
+                self.start += count;
+                if self.remaining == 0 && self.start == self.input.len() {
+                    self.done = true;
+                }
+                count
+            },
+            Some(inflate) => {
+                let (total_in, total_out) = (inflate.total_in(), inflate.total_out());
+                let out = buf.initialize_unfilled();
+                let status = inflate.decompress(input, out, FlushDecompress::None)
+                    .map_err(|e| Error::InvalidData(format!("bad deflate stream: {}", e)))?;
+                let produced = (inflate.total_out() - total_out) as usize;
+                self.crc.update(&out[..produced]);
+                buf.advance(produced);
+                self.start += (inflate.total_in() - total_in) as usize;
+                if status == Status::StreamEnd {
+                    self.done = true;
+                }
+                produced
+            },
+        };
+        if self.done {
+            self.check()?;
+        }
+        Ok(produced)
+    }
+
+    fn check(&self) -> io::Result<()> {
+        if self.crc.amount() as u64 != self.expected_size & u32::MAX as u64 || self.crc.sum() != self.expected_crc {
+            return Err(Error::InvalidData("the CRC or size of the member does not match the central directory".to_string()).into());
+        }
+        Ok(())
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncNpzEntry<'_, R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.done || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            let has_input = this.start < this.input.len() || (this.inflate.is_some() && this.remaining == 0);
+            if has_input && (this.output(buf)? > 0 || this.done) {
+                return Poll::Ready(Ok(()));
+            }
+            if this.remaining == 0 {
+                if this.inflate.is_some() {
+                    return Poll::Ready(Err(Error::Truncated.into()));
+                }
+                this.done = true;
+                this.check()?;
+                return Poll::Ready(Ok(()));
+            }
+
+            // read more compressed bytes, after those that were not used yet
+            this.input.drain(..this.start);
+            this.start = 0;
+            let old_len = this.input.len();
+            let want = (INPUT_CHUNK as u64).min(this.remaining) as usize;
+            this.input.resize(old_len + want, 0);
+            let mut input = ReadBuf::new(&mut this.input[old_len..]);
+            let result = Pin::new(&mut *this.reader).poll_read(cx, &mut input);
+            let count = input.filled().len();
+            this.input.truncate(old_len + count);
+            match result {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(())) if count == 0 => return Poll::Ready(Err(Error::Truncated.into())),
+                Poll::Ready(Ok(())) => this.remaining -= count as u64,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_read::tests::block_on;
+    #[cfg(feature = "npz")]
+    use crate::npz::NpzArchive;
+
+    #[test]
+    #[cfg(feature = "npz")]
+    fn same_as_sync() {
+        for path in ["test-data/compressed.npz", "test-data/uncompressed.npz"] {
+            let bytes = std::fs::read(path).unwrap();
+            let mut sync = NpzArchive::new(io::Cursor::new(&bytes)).unwrap();
+            block_on(async {
+                for buffer_size in [1, 1 << 16] {
+                    let options = ReadOptions::new().buffer_size(buffer_size);
+                    let mut npz = AsyncNpzArchive::new(io::Cursor::new(&bytes)).await.unwrap().with_read_options(options);
+                    let mut names = npz.array_names().collect::<Vec<_>>();
+                    let mut expected = sync.array_names().collect::<Vec<_>>();
+                    names.sort();
+                    expected.sort();
+                    assert_eq!(names, expected);
+
+                    let npy = npz.by_name("ints").await.unwrap().unwrap();
+                    assert_eq!(npy.shape(), sync.by_name("ints").unwrap().unwrap().shape());
+                    let expected = sync.by_name("ints").unwrap().unwrap().into_vec::<i64>().unwrap();
+                    assert_eq!(npy.into_vec::<i64>().await.unwrap(), expected);
+
+                    let npy = npz.by_name("floats").await.unwrap().unwrap();
+                    let expected = sync.by_name("floats").unwrap().unwrap().into_vec::<f64>().unwrap();
+                    assert_eq!(npy.into_vec::<f64>().await.unwrap(), expected);
+
+                    assert!(npz.by_name("missing").await.unwrap().is_none());
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn corrupt() {
+        let bytes = std::fs::read("test-data/uncompressed.npz").unwrap();
+        block_on(async {
+            let err = AsyncNpzArchive::new(io::Cursor::new(&bytes[..100])).await.err().unwrap();
+            assert!(matches!(Error::from(err), Error::InvalidData(_)));
+
+            // flip a bit in the data of the first array, which only the CRC can catch
+            let mut bytes = bytes.clone();
+            let data_start = bytes.windows(6).position(|w| w == b"\x93NUMPY").unwrap() + 128;
+            bytes[data_start] ^= 1;
+            let mut npz = AsyncNpzArchive::new(io::Cursor::new(&bytes)).await.unwrap();
+            let err = npz.by_name("ints").await.unwrap().unwrap().into_vec::<i64>().await.unwrap_err();
+            assert!(err.to_string().contains("CRC"), "{}", err);
+        });
+    }
+}
@@ -0,0 +1,341 @@
+//! Reading NPY files from a tokio [`AsyncRead`].
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::error::Error;
+use crate::read::{record_error, NpyFile, NpyHeader, ReadOptions};
+use crate::read_at::truncated;
+use crate::serialize::{Deserialize, DTypeError, TypeRead};
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+impl NpyFile<io::Empty> {
+    /// Read the header of an `npy` file from a tokio [`AsyncRead`], and construct an [`AsyncNpyFile`]
+    /// for reading the data.
+    ///
+    /// Only the header is read here.  The data is read in chunks of [`ReadOptions::buffer_size`] as the
+    /// elements are taken from [`AsyncNpyFile::data`], so the file never needs to be in memory in full.
+    ///
+    /// _This function is only available with the **`"async"`** feature._
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+    /// let file = tokio::fs::File::open("test-data/c-order.npy").await?;
+    /// let npy = npyz::NpyFile::new_async(file).await?;
+    /// assert_eq!(npy.shape(), &[2, 3, 4]);
+    ///
+    /// let mut data = npy.data::<i64>()?;
+    /// let mut total = 0;
+    /// while let Some(x) = data.next().await {
+    ///     total += x?;
+    /// }
+    /// # let _ = total;
+    /// # Ok(()) })
+    /// # }
+    /// ```
+    pub async fn new_async<R: AsyncRead + Unpin>(reader: R) -> io::Result<AsyncNpyFile<R>> {
+        AsyncNpyFile::with_options(reader, &ReadOptions::default()).await
+    }
+
+    /// Like [`Self::new_async`], enforcing the limits in [`ReadOptions`].
+    ///
+    /// _This function is only available with the **`"async"`** feature._
+    pub async fn new_async_with_options<R: AsyncRead + Unpin>(reader: R, options: &ReadOptions) -> io::Result<AsyncNpyFile<R>> {
+        AsyncNpyFile::with_options(reader, options).await
+    }
+}
+
+/// An NPY file whose header has been read from a tokio [`AsyncRead`].  See [`NpyFile::new_async`].
+///
+/// This derefs to the [`NpyHeader`], like [`NpyFile`].
+///
+/// *This is only available with the **`"async"`** feature.*
+pub struct AsyncNpyFile<R: AsyncRead + Unpin> {
+    header: NpyHeader,
+    reader: R,
+    buffer_size: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncNpyFile<R> {
+    pub(crate) async fn with_options(mut reader: R, options: &ReadOptions) -> io::Result<Self> {
+        let header = read_header(&mut reader, options).await?;
+        Ok(AsyncNpyFile { header, reader, buffer_size: options.get_buffer_size() })
+    }
+
+    // Name errors after an array in an NPZ file.
+    pub(crate) fn with_member_name(mut self, name: &str) -> Self {
+        self.header.set_member(name);
+        self
+    }
+
+    /// Access the underlying [`NpyHeader`] object.
+    pub fn header(&self) -> &NpyHeader {
+        &self.header
+    }
+
+    /// Produce an [`AsyncNpyReader`] to begin reading elements, if `T` can be deserialized from the file's dtype.
+    pub fn data<T: Deserialize>(self) -> Result<AsyncNpyReader<T, R>, DTypeError> {
+        let AsyncNpyFile { header, reader, buffer_size } = self;
+        let type_reader = T::reader(&header.dtype())?;
+        Ok(AsyncNpyReader { header, type_reader, reader, buffer_size, buf: vec![], pos: 0, index: 0, failed: false })
+    }
+
+    /// Read all elements into a flat `Vec`, in the order they are stored as.
+    pub async fn into_vec<T: Deserialize>(self) -> io::Result<Vec<T>> {
+        let dtype = self.header.dtype();
+        let mut reader = self.data::<T>().map_err(|e| Error::dtype_mismatch::<T>(&dtype, e))?;
+        // not reserved up front, since the header could claim any length
+        let mut out = vec![];
+        while let Some(value) = reader.next().await {
+            out.push(value?);
+        }
+        Ok(out)
+    }
+}
+
+impl<R: AsyncRead + Unpin> std::ops::Deref for AsyncNpyFile<R> {
+    type Target = NpyHeader;
+
+    fn deref(&self) -> &NpyHeader {
+        &self.header
+    }
+}
+
+// Read exactly the bytes of the header, and parse them.
+async fn read_header(reader: &mut (impl AsyncRead + Unpin), options: &ReadOptions) -> io::Result<NpyHeader> {
+    let parse = |bytes: &[u8]| NpyHeader::from_reader_with_options(bytes, options);
+
+    let mut bytes = vec![0; MAGIC.len() + 2];
+    reader.read_exact(&mut bytes).await.map_err(truncated)?;
+    let len_size = match (&bytes[..MAGIC.len()] == MAGIC, bytes[MAGIC.len()]) {
+        (true, 1) => 2,
+        (true, 2 | 3) => 4,
+        // let the parser report what is wrong
+        _ => return parse(&bytes),
+    };
+    let start = bytes.len();
+    bytes.resize(start + len_size, 0);
+    reader.read_exact(&mut bytes[start..]).await.map_err(truncated)?;
+    let len = bytes[start..].iter().rev().fold(0usize, |acc, &byte| acc << 8 | byte as usize);
+    options.check_header_len(len)?;
+
+    let start = bytes.len();
+    bytes.resize(start + len, 0);
+    reader.read_exact(&mut bytes[start..]).await.map_err(truncated)?;
+    parse(&bytes)
+}
+
+/// Reads elements of type `T` from the data of an [`AsyncNpyFile`].
+///
+/// This is an async counterpart of [`NpyReader`][`crate::NpyReader`].  Elements are taken with
+/// [`Self::next`], which reads the next chunk of the file whenever the previous one is used up.
+///
+/// *This is only available with the **`"async"`** feature.*
+pub struct AsyncNpyReader<T: Deserialize, R: AsyncRead + Unpin> {
+    header: NpyHeader,
+    type_reader: T::TypeReader,
+    reader: R,
+    buffer_size: usize,
+    // bytes read from the file, of which those before `pos` have been decoded
+    buf: Vec<u8>,
+    pos: usize,
+    // the record at the start of `buf[pos..]`
+    index: u64,
+    failed: bool,
+}
+
+impl<T: Deserialize, R: AsyncRead + Unpin> AsyncNpyReader<T, R> {
+    /// Get the header of the file.
+    pub fn header(&self) -> &NpyHeader {
+        &self.header
+    }
+
+    /// Get the number of elements that have not been read yet.
+    pub fn len(&self) -> u64 {
+        self.header.len() - self.index
+    }
+
+    /// Returns `true` if all elements have been read.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read the next element, or return `None` after the last one.
+    ///
+    /// After an error, this returns `None`.
+    pub async fn next(&mut self) -> Option<io::Result<T>> {
+        if self.failed || self.is_empty() {
+            return None;
+        }
+        let result = self.read_next().await;
+        self.failed = result.is_err();
+        Some(result)
+    }
+
+    async fn read_next(&mut self) -> io::Result<T> {
+        let size = self.header.item_size();
+        if self.buf.len() - self.pos < size {
+            self.fill(size).await.map_err(|e| record_error(&self.header, self.index, e))?;
+        }
+        let bytes = &self.buf[self.pos..self.pos + size];
+        let value = self.type_reader.read_one(bytes).map_err(|e| record_error(&self.header, self.index, e))?;
+        self.pos += size;
+        self.index += 1;
+        Ok(value)
+    }
+
+    // Read until at least `size` bytes are buffered, without reading past the end of the data.
+    async fn fill(&mut self, size: usize) -> io::Result<()> {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        // (this can't overflow; it was checked when constructing the header)
+        let remaining = self.len() * size as u64;
+        while self.buf.len() < size {
+            let old_len = self.buf.len();
+            let want = (self.buffer_size.max(size) as u64).min(remaining - old_len as u64) as usize;
+            self.buf.resize(old_len + want, 0);
+            let count = match self.reader.read(&mut self.buf[old_len..]).await {
+                Ok(count) => count,
+                Err(e) => {
+                    self.buf.truncate(old_len);
+                    return Err(e);
+                },
+            };
+            self.buf.truncate(old_len + count);
+            if count == 0 {
+                return Err(Error::Truncated.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the underlying reader, positioned after the elements that have been read.
+    ///
+    /// Bytes that were read ahead into the buffer are lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<T: Deserialize, R: AsyncRead + AsyncSeek + Unpin> AsyncNpyReader<T, R> {
+    /// Move to the element at the given index in the order they are stored, so that it is the next
+    /// one returned by [`Self::next`].
+    ///
+    /// This seeks relative to the current position, so the data need not begin at the start of the reader.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+    /// let file = tokio::fs::File::open("test-data/c-order.npy").await?;
+    /// let mut data = npyz::NpyFile::new_async(file).await?.data::<i64>()?;
+    /// data.seek_to(23).await?;
+    /// assert_eq!(data.next().await.unwrap()?, 6);
+    /// assert!(data.next().await.is_none());
+    /// # Ok(()) })
+    /// # }
+    /// ```
+    pub async fn seek_to(&mut self, index: u64) -> io::Result<()> {
+        let len = self.header.len();
+        if index > len {
+            return Err(Error::IndexOutOfBounds { index, len }.into());
+        }
+        let size = self.header.item_size() as i64;
+        // the reader is at the end of the buffered bytes
+        let buffered = (self.buf.len() - self.pos) as i64;
+        let offset = (index as i64 - self.index as i64) * size - buffered;
+        self.reader.seek(io::SeekFrom::Current(offset)).await?;
+        self.buf.clear();
+        self.pos = 0;
+        self.index = index;
+        self.failed = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    fn c_order() -> Vec<u8> {
+        std::fs::read("test-data/c-order.npy").unwrap()
+    }
+
+    #[test]
+    fn same_as_sync() {
+        let bytes = c_order();
+        let expected = NpyFile::new(&bytes[..]).unwrap().into_vec::<i64>().unwrap();
+        for buffer_size in [1, 7, 8, 1 << 16] {
+            let options = ReadOptions::new().buffer_size(buffer_size);
+            let npy = block_on(NpyFile::new_async_with_options(&bytes[..], &options)).unwrap();
+            assert_eq!(npy.shape(), &[2, 3, 4]);
+            assert_eq!(block_on(npy.into_vec::<i64>()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn does_not_read_past_the_data() {
+        let mut bytes = c_order();
+        bytes.extend_from_slice(b"trailing");
+        block_on(async {
+            let mut data = NpyFile::new_async(&bytes[..]).await.unwrap().data::<i64>().unwrap();
+            while let Some(x) = data.next().await {
+                x.unwrap();
+            }
+            assert_eq!(data.into_inner(), b"trailing");
+        });
+    }
+
+    #[test]
+    fn seek() {
+        let bytes = c_order();
+        block_on(async {
+            let file = std::io::Cursor::new(&bytes);
+            let mut data = NpyFile::new_async(file).await.unwrap().data::<i64>().unwrap();
+            // (the values are 1, 1, 1, 1, 2, 2, 2, 2, ... 6)
+            assert_eq!(data.next().await.unwrap().unwrap(), 1);
+            data.seek_to(10).await.unwrap();
+            assert_eq!(data.next().await.unwrap().unwrap(), 3);
+            assert_eq!(data.len(), 13);
+            data.seek_to(4).await.unwrap();
+            assert_eq!(data.next().await.unwrap().unwrap(), 2);
+            data.seek_to(24).await.unwrap();
+            assert!(data.next().await.is_none());
+            assert!(data.seek_to(25).await.is_err());
+        });
+    }
+
+    #[test]
+    fn errors() {
+        let bytes = c_order();
+        block_on(async {
+            let err = NpyFile::new_async(&bytes[..20]).await.err().unwrap();
+            assert!(matches!(Error::from(err), Error::Truncated));
+
+            let err = NpyFile::new_async(&b"not an npy file"[..]).await.err().unwrap();
+            assert_eq!(err.to_string(), NpyFile::new(&b"not an npy file"[..]).err().unwrap().to_string());
+
+            let options = ReadOptions::new().max_header_len(10);
+            let err = NpyFile::new_async_with_options(&bytes[..], &options).await.err().unwrap();
+            assert!(matches!(Error::from(err), Error::LimitExceeded { .. }));
+
+            let mut data = NpyFile::new_async(&bytes[..bytes.len() - 4]).await.unwrap().data::<i64>().unwrap();
+            for _ in 0..23 {
+                data.next().await.unwrap().unwrap();
+            }
+            let err = data.next().await.unwrap().unwrap_err();
+            assert!(matches!(Error::from(err).root(), Error::Truncated));
+            assert!(data.next().await.is_none());
+
+            let npy = NpyFile::new_async(&bytes[..]).await.unwrap();
+            let err = npy.into_vec::<f32>().await.unwrap_err();
+            assert!(matches!(Error::from(err).root(), Error::DTypeMismatch { .. }));
+        });
+    }
+}
@@ -0,0 +1,237 @@
+//! Collecting records that arrive one at a time into blocks.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::serialize::Serialize;
+use crate::write::NpyWriter;
+
+/// A wrapper around an [`NpyWriter`] that collects records into blocks of a fixed length and writes each
+/// block at once.
+///
+/// This is meant for producers that generate records one at a time at a high rate, such as telemetry.
+/// Writing a block uses [`NpyWriter::extend_from_slice`], which copies the bytes of primitive records in
+/// the native byte order without serializing them one by one.
+///
+/// A block is written when it is full.  With a [`max_delay`][Self::max_delay], the pending records are also
+/// written and [flushed][`NpyWriter::flush`] once the oldest of them has waited that long, so that they reach
+/// the output in bounded time even when records arrive slowly.  The delay is only checked when a record is
+/// [pushed][Self::push] or by [`Self::flush_if_due`], so a producer that may go quiet should call the latter
+/// periodically.
+///
+/// [`Self::try_push`] never writes anything.  It hands the record back when the block is full, so that a
+/// producer can decide for itself when to spend time on I/O, e.g. by dropping or queueing records under load.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::WriterBuilder;
+///
+/// let mut out = vec![];
+/// let writer = npyz::WriteOptions::new().default_dtype().shape(&[3]).writer(&mut out).begin_nd()?;
+/// let mut batched = npyz::BatchedWriter::new(writer, 2);
+/// batched.try_push(1_u16).unwrap();
+/// batched.try_push(2).unwrap();
+/// assert_eq!(batched.try_push(3), Err(3));
+/// batched.flush()?;
+/// batched.try_push(3).unwrap();
+/// batched.finish()?;
+///
+/// assert_eq!(npyz::NpyFile::new(&out[..])?.into_vec::<u16>()?, vec![1, 2, 3]);
+/// # Ok(()) }
+/// ```
+///
+/// Dropping a `BatchedWriter` writes the pending records and finishes the file, ignoring any errors.
+pub struct BatchedWriter<T: Serialize, W: Write> {
+    // `None` once finished
+    writer: Option<NpyWriter<T, W>>,
+    block: Vec<T>,
+    block_len: usize,
+    max_delay: Option<Duration>,
+    oldest: Option<Instant>,
+}
+
+impl<T: Serialize, W: Write> BatchedWriter<T, W> {
+    /// Collect the records for `writer` into blocks of `block_len` records.  A `block_len` of 0 is treated as 1.
+    pub fn new(writer: NpyWriter<T, W>, block_len: usize) -> Self {
+        let block_len = block_len.max(1);
+        BatchedWriter {
+            writer: Some(writer),
+            block: Vec::with_capacity(block_len),
+            block_len,
+            max_delay: None,
+            oldest: None,
+        }
+    }
+
+    /// Write and flush the pending records once the oldest of them has waited for `delay`.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = Some(delay);
+        self
+    }
+
+    /// The number of records that have not been written yet.
+    pub fn pending(&self) -> usize {
+        self.block.len()
+    }
+
+    /// Whether the block is full.  While it is, [`Self::try_push`] fails.
+    pub fn is_full(&self) -> bool {
+        self.block.len() >= self.block_len
+    }
+
+    /// Whether the block is full, or the oldest pending record has waited for the [`max_delay`][Self::max_delay].
+    pub fn is_due(&self) -> bool {
+        self.is_full() || self.is_overdue()
+    }
+
+    fn is_overdue(&self) -> bool {
+        match (self.oldest, self.max_delay) {
+            (Some(oldest), Some(delay)) => oldest.elapsed() >= delay,
+            _ => false,
+        }
+    }
+
+    /// Add a record, writing the block if it is then [due][Self::is_due].
+    pub fn push(&mut self, record: T) -> io::Result<()> {
+        if self.is_full() {
+            self.write_block()?;
+        }
+        self.add(record);
+        if self.is_overdue() {
+            self.flush()
+        } else if self.is_full() {
+            self.write_block()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Add a record without writing anything.
+    ///
+    /// If the block is full, the record is given back in `Err`.  Call [`Self::flush`] (or
+    /// [`Self::flush_if_due`]) to make room.
+    pub fn try_push(&mut self, record: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(record);
+        }
+        self.add(record);
+        Ok(())
+    }
+
+    fn add(&mut self, record: T) {
+        if self.block.is_empty() {
+            self.oldest = Some(Instant::now());
+        }
+        self.block.push(record);
+    }
+
+    /// Write and flush the pending records if they are [due][Self::is_due].  Returns whether they were.
+    pub fn flush_if_due(&mut self) -> io::Result<bool> {
+        if !self.is_due() {
+            return Ok(false);
+        }
+        self.flush()?;
+        Ok(true)
+    }
+
+    /// Write the pending records and [flush][`NpyWriter::flush`] the writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.writer_mut().flush()
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if !self.block.is_empty() {
+            let writer = self.writer.as_mut().expect("(BUG!) writer used after finish");
+            writer.extend_from_slice(&self.block)?;
+            self.block.clear();
+            self.oldest = None;
+        }
+        Ok(())
+    }
+
+    fn writer_mut(&mut self) -> &mut NpyWriter<T, W> {
+        self.writer.as_mut().expect("(BUG!) writer used after finish")
+    }
+
+    /// Write the pending records and [finish][`NpyWriter::finish`] the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.writer.take().expect("(BUG!) writer used after finish").finish()
+    }
+}
+
+impl<T: Serialize, W: Write> Drop for BatchedWriter<T, W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.write_block();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NpyFile, WriteOptions, WriterBuilder};
+
+    // counts the calls to `write`
+    struct Counting<'a> {
+        out: &'a mut Vec<u8>,
+        writes: usize,
+    }
+
+    impl Write for Counting<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            self.out.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn blocks() -> io::Result<()> {
+        let mut out = vec![];
+        let mut counting = Counting { out: &mut out, writes: 0 };
+        let writer = WriteOptions::new().default_dtype().shape(&[1001]).writer(&mut counting).begin_nd()?;
+        let mut batched = BatchedWriter::new(writer, 100);
+        for i in 0..1000_u32 {
+            batched.push(i)?;
+        }
+        assert_eq!(batched.pending(), 0);
+        batched.push(1000)?;
+        assert_eq!(batched.pending(), 1);
+        batched.finish()?;
+        // the header, ten blocks, and the rest; not one per record
+        assert!(counting.writes < 20, "{}", counting.writes);
+        assert_eq!(NpyFile::new(&out[..])?.into_vec::<u32>()?, (0..=1000).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn try_push_and_delay() -> io::Result<()> {
+        let mut out = vec![];
+        let writer = WriteOptions::new().default_dtype().shape(&[5]).writer(&mut out).begin_nd()?;
+        let mut batched = BatchedWriter::new(writer, 3).max_delay(Duration::from_millis(20));
+        assert!(!batched.flush_if_due()?);
+        for i in 0..3 {
+            batched.try_push(i as f64).unwrap();
+        }
+        assert!(batched.is_full());
+        assert_eq!(batched.try_push(3.0), Err(3.0));
+        assert!(batched.flush_if_due()?);
+        assert_eq!(batched.pending(), 0);
+
+        batched.try_push(3.0).unwrap();
+        assert!(!batched.is_due());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(batched.is_due());
+        batched.push(4.0)?;
+        assert_eq!(batched.pending(), 0);
+        drop(batched);
+        assert_eq!(NpyFile::new(&out[..])?.into_vec::<f64>()?, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        Ok(())
+    }
+}
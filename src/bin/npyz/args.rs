@@ -0,0 +1,73 @@
+//! A minimal parser for command line arguments.
+
+use std::collections::HashMap;
+
+/// Positional arguments and `--options` of a command.
+pub struct Args {
+    positional: Vec<String>,
+    values: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    /// Parse arguments, where `value_options` take a value (`--name value` or `--name=value`) and
+    /// `flag_options` do not.  Single-letter aliases like `-o` can be given as `("o", "output")`.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        value_options: &[&str],
+        flag_options: &[&str],
+        aliases: &[(&str, &str)],
+    ) -> Result<Args, String> {
+        let mut out = Args { positional: vec![], values: HashMap::new(), flags: vec![] };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = if let Some(long) = arg.strip_prefix("--") {
+                match long.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None => (long.to_string(), None),
+                }
+            } else if let Some(short) = arg.strip_prefix('-').filter(|s| !s.is_empty() && !s.starts_with(|c: char| c.is_ascii_digit())) {
+                let name = aliases.iter().find(|(alias, _)| *alias == short).map(|(_, name)| name.to_string());
+                (name.unwrap_or_else(|| short.to_string()), None)
+            } else {
+                out.positional.push(arg);
+                continue;
+            };
+
+            if name == "help" || flag_options.contains(&&name[..]) {
+                if inline_value.is_some() {
+                    return Err(format!("option '--{}' does not take a value", name));
+                }
+                out.flags.push(name);
+            } else if value_options.contains(&&name[..]) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => args.next().ok_or_else(|| format!("option '--{}' requires a value", name))?,
+                };
+                out.values.insert(name, value);
+            } else {
+                return Err(format!("unknown option '{}'", arg));
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|s| &s[..])
+    }
+
+    /// Parse the value of an option, if it was given.
+    pub fn parsed_value<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.value(name).map(|s| s.parse().map_err(|_| format!("invalid value for '--{}': {:?}", name, s))).transpose()
+    }
+
+    /// Get exactly `N` positional arguments.
+    pub fn positional<const N: usize>(&self, usage: &str) -> Result<[&str; N], String> {
+        let strs = self.positional.iter().map(|s| &s[..]).collect::<Vec<_>>();
+        strs.try_into().map_err(|_| usage.to_string())
+    }
+}
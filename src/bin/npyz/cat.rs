@@ -0,0 +1,140 @@
+//! `npyz cat`
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use npyz::{DType, NpyHeader, Order};
+
+use crate::args::Args;
+use crate::value::{self, Value};
+
+const USAGE: &str = "\
+Usage: npyz cat <file.npy> [--format text|csv|json] [--head N]
+
+Print the contents of an NPY file, one line for each entry along the first axis.
+
+Options:
+    --format text    Python-like values (default)
+    --format csv     Comma-separated values, with a header row naming the fields of a record dtype
+    --format json    One JSON value per line (JSON Lines).  Records become objects.
+    --head N         Only print the first N lines";
+
+#[derive(Copy, Clone)]
+enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    let args = Args::parse(args, &["format", "head"], &[], &[("n", "head")])?;
+    if crate::help_requested(&args, USAGE) {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let [path] = args.positional(USAGE)?;
+    let format = match args.value("format").unwrap_or("text") {
+        "text" => Format::Text,
+        "csv" => Format::Csv,
+        "json" => Format::Json,
+        other => return Err(format!("unknown format '{}' (expected text, csv or json)", other).into()),
+    };
+    let head = args.parsed_value::<u64>("head")?;
+
+    let mut file = io::BufReader::new(File::open(path).map_err(|e| format!("{}: {}", path, e))?);
+    if crate::inspect::is_zip(&mut file)? {
+        return Err(format!("{} is an NPZ archive; extract an array from it first", path).into());
+    }
+    let header = NpyHeader::from_reader(&mut file)?;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    match print(&header, file, format, head, &mut out).and_then(|()| out.flush()) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(ExitCode::SUCCESS),
+        result => result.map(|()| ExitCode::SUCCESS).map_err(Into::into),
+    }
+}
+
+fn print(header: &NpyHeader, mut data: impl Read, format: Format, head: Option<u64>, out: &mut impl Write) -> io::Result<()> {
+    let dtype = header.dtype();
+    let item_size = dtype.num_bytes().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "dtype is too large"))?;
+    let (num_rows, row_shape) = match header.shape() {
+        [] => (1, &[][..]),
+        [dim, rest @ ..] => (*dim, rest),
+    };
+    let num_rows = head.map_or(num_rows, |head| head.min(num_rows));
+    let row_len = row_shape.iter().product::<u64>();
+
+    if let (Format::Csv, DType::Record(_)) = (format, &dtype) {
+        writeln!(out, "{}", csv_header(&dtype, row_shape).join(","))?;
+    }
+
+    // elements are printed in C order, which requires random access for fortran order
+    let fortran_data = match header.order() {
+        Order::C => None,
+        Order::Fortran => {
+            let mut bytes = vec![];
+            data.read_to_end(&mut bytes)?;
+            Some(bytes)
+        },
+    };
+
+    let mut buf = vec![0; item_size];
+    let mut flat_index = 0u64;
+    let mut next_element = || -> io::Result<Value> {
+        let bytes = match &fortran_data {
+            None => {
+                data.read_exact(&mut buf)?;
+                &buf[..]
+            },
+            Some(bytes) => {
+                let offset = fortran_offset(flat_index, header.shape(), header.strides()) as usize * item_size;
+                bytes.get(offset..offset + item_size).ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?
+            },
+        };
+        flat_index += 1;
+        Ok(Value::decode(&dtype, bytes))
+    };
+
+    for _ in 0..num_rows {
+        let elements = (0..row_len).map(|_| next_element()).collect::<io::Result<Vec<_>>>()?;
+        let row = Value::nest(&mut elements.into_iter(), row_shape);
+        match format {
+            Format::Text => writeln!(out, "{}", row.to_text())?,
+            Format::Json => writeln!(out, "{}", row.to_json())?,
+            Format::Csv => {
+                let mut cells = vec![];
+                row.to_csv_cells(&mut cells);
+                writeln!(out, "{}", cells.join(","))?;
+            },
+        }
+    }
+    Ok(())
+}
+
+fn csv_header(dtype: &DType, row_shape: &[u64]) -> Vec<String> {
+    let mut names = vec![];
+    let mut index = vec![0; row_shape.len()];
+    for _ in 0..row_shape.iter().product::<u64>() {
+        let prefix = index.iter().map(|i| format!("[{}]", i)).collect::<String>();
+        value::csv_column_names(dtype, &prefix, &mut names);
+        for axis in (0..index.len()).rev() {
+            index[axis] += 1;
+            if index[axis] < row_shape[axis] {
+                break;
+            }
+            index[axis] = 0;
+        }
+    }
+    names
+}
+
+/// Get the position in the file of the element at an index in C order.
+fn fortran_offset(c_index: u64, shape: &[u64], strides: &[u64]) -> u64 {
+    let mut rest = c_index;
+    let mut offset = 0;
+    for (&dim, &stride) in shape.iter().zip(strides).rev() {
+        offset += rest % dim * stride;
+        rest /= dim;
+    }
+    offset
+}
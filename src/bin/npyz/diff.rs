@@ -0,0 +1,62 @@
+//! `npyz diff`
+
+use std::fs::File;
+use std::io;
+use std::process::ExitCode;
+
+use npyz::Tolerance;
+
+use crate::args::Args;
+
+const USAGE: &str = "\
+Usage: npyz diff <a.npy> <b.npy> [--rtol X] [--atol X] [--nan-equal] [--exact] [--quiet]
+
+Compare two NPY files element by element, without loading them into memory.
+
+Numeric elements are equal when |a - b| <= atol + rtol * |b|, like numpy.isclose.  Other
+elements must be bytewise identical.  Exits with status 0 if the arrays are equal, 1 if they
+differ, and 2 on error.
+
+Options:
+    --rtol X       Relative tolerance (default 1e-5)
+    --atol X       Absolute tolerance (default 1e-8)
+    --nan-equal    Treat NaNs at the same index as equal
+    --exact        Require exact equality (same as --rtol 0 --atol 0)
+    --quiet, -q    Print nothing; only set the exit status";
+
+pub fn run(args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    let args = Args::parse(args, &["rtol", "atol"], &["nan-equal", "exact", "quiet"], &[("q", "quiet")])?;
+    if crate::help_requested(&args, USAGE) {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let [path_a, path_b] = args.positional(USAGE)?;
+
+    let mut tolerance = match args.flag("exact") {
+        true => Tolerance::exact(),
+        false => Tolerance::default(),
+    };
+    if let Some(rtol) = args.parsed_value("rtol")? {
+        tolerance.rtol = rtol;
+    }
+    if let Some(atol) = args.parsed_value("atol")? {
+        tolerance.atol = atol;
+    }
+    tolerance.nan_equal = args.flag("nan-equal");
+
+    let open = |path: &str| -> crate::Result<_> {
+        let mut file = io::BufReader::new(File::open(path).map_err(|e| format!("{}: {}", path, e))?);
+        if crate::inspect::is_zip(&mut file)? {
+            return Err(format!("{} is an NPZ archive; extract an array from it first", path).into());
+        }
+        Ok(file)
+    };
+    let comparison = npyz::compare(open(path_a)?, open(path_b)?, tolerance)?;
+
+    if !args.flag("quiet") {
+        println!("{}", comparison);
+    }
+    match comparison.is_equal() {
+        true => Ok(ExitCode::SUCCESS),
+        false => Ok(ExitCode::from(1)),
+    }
+}
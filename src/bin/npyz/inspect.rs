@@ -0,0 +1,105 @@
+//! `npyz inspect`
+
+use std::fs::File;
+use std::io::{self, Read, Seek};
+use std::process::ExitCode;
+
+use npyz::npz::NpzArchive;
+use npyz::NpyHeader;
+
+use crate::args::Args;
+
+const USAGE: &str = "\
+Usage: npyz inspect <file.npy|file.npz>
+
+Show the shape, dtype, memory order, format version and number of elements of an NPY file.
+For an NPZ file, show a table with one row per array.";
+
+pub fn run(args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    let args = Args::parse(args, &[], &[], &[])?;
+    if crate::help_requested(&args, USAGE) {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let [path] = args.positional(USAGE)?;
+
+    let mut file = io::BufReader::new(File::open(path).map_err(|e| format!("{}: {}", path, e))?);
+    if is_zip(&mut file)? {
+        inspect_npz(NpzArchive::new(file)?)?;
+    } else {
+        inspect_npy(&NpyHeader::from_reader(file)?);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Check the magic bytes of a file to see if it is an NPZ archive, rewinding the reader afterwards.
+pub fn is_zip(file: &mut (impl Read + Seek)) -> io::Result<bool> {
+    let mut magic = [0; 4];
+    let is_zip = match file.read_exact(&mut magic) {
+        Ok(()) => &magic == b"PK\x03\x04" || &magic == b"PK\x05\x06",
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => false,
+        Err(e) => return Err(e),
+    };
+    file.rewind()?;
+    Ok(is_zip)
+}
+
+fn inspect_npy(header: &NpyHeader) {
+    let dtype = header.dtype().to_pretty_string().replace('\n', "\n          ");
+    println!("shape:    {}", format_shape(header.shape()));
+    println!("dtype:    {}", dtype);
+    println!("order:    {}", format_order(header.order()));
+    println!("version:  {}", format_version(header));
+    println!("elements: {}", header.len());
+    for (key, value) in header.extra_keys() {
+        println!("extra:    {}: {}", key, value);
+    }
+}
+
+fn inspect_npz<R: Read + Seek>(mut npz: NpzArchive<R>) -> crate::Result<()> {
+    let names = npz.array_names().map(|name| name.to_string()).collect::<Vec<_>>();
+    let mut rows = vec![["NAME", "SHAPE", "DTYPE", "ORDER", "VERSION", "ELEMENTS"].map(String::from)];
+    for name in names {
+        let npy = npz.by_name(&name)?.expect("name came from the archive");
+        let header = npy.header();
+        rows.push([
+            name.clone(),
+            format_shape(header.shape()),
+            header.dtype().to_string(),
+            format_order(header.order()).to_string(),
+            format_version(header),
+            header.len().to_string(),
+        ]);
+    }
+    print_table(&rows);
+    Ok(())
+}
+
+pub fn print_table<const N: usize>(rows: &[[String; N]]) {
+    let widths = (0..N).map(|col| rows.iter().map(|row| row[col].chars().count()).max().unwrap_or(0)).collect::<Vec<_>>();
+    for row in rows {
+        let cells = row.iter().zip(&widths).map(|(cell, &width)| format!("{:width$}", cell, width = width)).collect::<Vec<_>>();
+        println!("{}", cells.join("  ").trim_end());
+    }
+}
+
+/// Format a shape like a Python tuple.
+pub fn format_shape(shape: &[u64]) -> String {
+    match shape {
+        [dim] => format!("({},)", dim),
+        _ => format!("({})", shape.iter().map(|dim| dim.to_string()).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+fn format_order(order: npyz::Order) -> &'static str {
+    match order {
+        npyz::Order::C => "C",
+        npyz::Order::Fortran => "F",
+    }
+}
+
+fn format_version(header: &NpyHeader) -> String {
+    match header.raw_bytes() {
+        Some(raw) => format!("{}.{}", raw[6], raw[7]),
+        None => "?".to_string(),
+    }
+}
@@ -0,0 +1,62 @@
+//! The `npyz` command line tool, for working with NPY and NPZ files without Python.
+//!
+//! _This binary is only built with the **`"cli"`** feature._
+
+use std::process::ExitCode;
+
+mod args;
+mod cat;
+mod diff;
+mod inspect;
+mod npz;
+mod sparse;
+mod value;
+
+use args::Args;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "\
+Usage: npyz <command> [options]
+
+Commands:
+    inspect <file.npy|file.npz>    Show the shape, dtype and layout of an array or of each array in an archive
+    cat <file.npy>                 Print the contents of an array as text, CSV or JSON
+    diff <a.npy> <b.npy>           Compare two arrays within a tolerance
+    npz ls|extract|add             List, extract or add the arrays in an NPZ archive
+    sparse info|convert            Inspect or convert a scipy sparse matrix
+
+Run `npyz <command> --help` for the options of a command.";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+    let result = match command.as_deref() {
+        Some("inspect") => inspect::run(args),
+        Some("cat") => cat::run(args),
+        Some("diff") => diff::run(args),
+        Some("npz") => npz::run(args),
+        Some("sparse") => sparse::run(args),
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        },
+        Some(command) => Err(format!("unknown command '{}'\n\n{}", command, USAGE).into()),
+        None => Err(USAGE.into()),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("npyz: {}", e);
+            ExitCode::from(2)
+        },
+    }
+}
+
+/// Print the usage of a command if `--help` was given.
+fn help_requested(args: &Args, usage: &str) -> bool {
+    if args.flag("help") {
+        println!("{}", usage);
+    }
+    args.flag("help")
+}
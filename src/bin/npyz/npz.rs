@@ -0,0 +1,122 @@
+//! `npyz npz`
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::process::ExitCode;
+
+use npyz::npz::{self, NpzArchive};
+use npyz::zip;
+
+use crate::args::Args;
+
+const USAGE: &str = "\
+Usage: npyz npz <subcommand> [options]
+
+Subcommands:
+    ls <archive.npz>                            List the arrays in an archive with their shapes, dtypes and sizes
+    extract <archive.npz> <name> [-o out.npy]   Copy an array out of an archive (default output: <name>.npy)
+    add <archive.npz> <name> <file.npy>         Add an NPY file to an archive, creating the archive if needed";
+
+const LS_USAGE: &str = "Usage: npyz npz ls <archive.npz>";
+const EXTRACT_USAGE: &str = "Usage: npyz npz extract <archive.npz> <name> [-o|--output out.npy]";
+const ADD_USAGE: &str = "\
+Usage: npyz npz add <archive.npz> <name> <file.npy> [--compress]
+
+Options:
+    --compress    Compress the array, like numpy.savez_compressed";
+
+pub fn run(mut args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    match args.next().as_deref() {
+        Some("ls") => ls(args),
+        Some("extract") => extract(args),
+        Some("add") => add(args),
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        },
+        Some(other) => Err(format!("unknown subcommand '{}'\n\n{}", other, USAGE).into()),
+        None => Err(USAGE.into()),
+    }
+}
+
+fn open_archive(path: &str) -> crate::Result<NpzArchive<BufReader<File>>> {
+    NpzArchive::open(path).map_err(|e| format!("{}: {}", path, e).into())
+}
+
+fn ls(args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    let args = Args::parse(args, &[], &[], &[])?;
+    if crate::help_requested(&args, LS_USAGE) {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let [path] = args.positional(LS_USAGE)?;
+
+    let mut archive = open_archive(path)?;
+    let mut rows = vec![["NAME", "SHAPE", "DTYPE", "SIZE", "COMPRESSED"].map(String::from)];
+    for entry in archive.entries().map_err(|e| format!("{}: {}", path, e))? {
+        let compressed = match entry.compression {
+            zip::CompressionMethod::Stored => "-".to_string(),
+            _ => entry.compressed_size.to_string(),
+        };
+        let shape = crate::inspect::format_shape(&entry.shape);
+        rows.push([entry.name, shape, entry.dtype.to_string(), entry.size.to_string(), compressed]);
+    }
+    crate::inspect::print_table(&rows);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn extract(args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    let args = Args::parse(args, &["output"], &[], &[("o", "output")])?;
+    if crate::help_requested(&args, EXTRACT_USAGE) {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let [path, name] = args.positional(EXTRACT_USAGE)?;
+    let output = args.value("output").map_or_else(|| npz::file_name_from_array_name(name), String::from);
+
+    let mut archive = open_archive(path)?;
+    let mut file = match archive.zip_archive().by_name(&npz::file_name_from_array_name(name)) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Err(format!("{}: no array named '{}'", path, name).into()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut out = BufWriter::new(File::create(&output).map_err(|e| format!("{}: {}", output, e))?);
+    io::copy(&mut file, &mut out)?;
+    out.flush()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn add(args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    let args = Args::parse(args, &[], &["compress"], &[])?;
+    if crate::help_requested(&args, ADD_USAGE) {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let [path, name, input] = args.positional(ADD_USAGE)?;
+
+    // make sure that the input is an NPY file before touching the archive
+    let mut npy = BufReader::new(File::open(input).map_err(|e| format!("{}: {}", input, e))?);
+    npyz::NpyHeader::from_reader(&mut npy).map_err(|e| format!("{}: {}", input, e))?;
+    io::Seek::rewind(&mut npy)?;
+
+    let file_name = npz::file_name_from_array_name(name);
+    let archive_file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let mut zip = match archive_file.metadata()?.len() {
+        0 => zip::ZipWriter::new(archive_file),
+        _ => {
+            let existing = zip::ZipArchive::new(&archive_file).map_err(|e| format!("{}: {}", path, e))?;
+            if existing.file_names().any(|existing| existing == file_name) {
+                return Err(format!("{}: already contains an array named '{}'", path, name).into());
+            }
+            zip::ZipWriter::new_append(archive_file)?
+        },
+    };
+
+    let method = match args.flag("compress") {
+        true => zip::CompressionMethod::Deflated,
+        false => zip::CompressionMethod::Stored,
+    };
+    let large_file = npy.get_ref().metadata()?.len() >= u32::MAX as u64;
+    zip.start_file(file_name, zip::write::FileOptions::default().compression_method(method).large_file(large_file))?;
+    io::copy(&mut npy, &mut zip)?;
+    zip.finish()?;
+    Ok(ExitCode::SUCCESS)
+}
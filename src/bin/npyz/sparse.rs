@@ -0,0 +1,247 @@
+//! `npyz sparse`
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+
+use npyz::npz::{NpzArchive, NpzWriter};
+use npyz::sparse::{Coo, Csc, Csr, Sparse};
+use npyz::{AutoSerialize, DType, Deserialize, TypeChar};
+
+use crate::args::Args;
+
+const USAGE: &str = "\
+Usage: npyz sparse <subcommand> [options]
+
+Subcommands:
+    info <matrix.npz>                              Show the format, shape and number of stored entries of a matrix
+    convert --to coo|csr|csc <in.npz> <out.npz>    Convert a matrix to another format";
+
+const INFO_USAGE: &str = "Usage: npyz sparse info <matrix.npz>";
+const CONVERT_USAGE: &str = "\
+Usage: npyz sparse convert --to coo|csr|csc <in.npz> <out.npz>
+
+Matrices in any format can be converted.  Like scipy, duplicate entries are kept, and explicit
+zeros are dropped only when converting from dia.  Converting to csr or csc sorts the indices.";
+
+pub fn run(mut args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    match args.next().as_deref() {
+        Some("info") => info(args),
+        Some("convert") => convert(args),
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        },
+        Some(other) => Err(format!("unknown subcommand '{}'\n\n{}", other, USAGE).into()),
+        None => Err(USAGE.into()),
+    }
+}
+
+fn open_matrix(path: &str) -> crate::Result<NpzArchive<BufReader<File>>> {
+    let mut npz = NpzArchive::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    if npz.by_name("format")?.is_none() {
+        return Err(format!("{}: not a sparse matrix (no 'format' array)", path).into());
+    }
+    Ok(npz)
+}
+
+fn read_member<T: Deserialize>(npz: &mut NpzArchive<BufReader<File>>, name: &str) -> crate::Result<Vec<T>> {
+    let npy = npz.by_name(name)?.ok_or_else(|| format!("missing array '{}' from sparse matrix", name))?;
+    Ok(npy.into_vec()?)
+}
+
+fn info(args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    let args = Args::parse(args, &[], &[], &[])?;
+    if crate::help_requested(&args, INFO_USAGE) {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let [path] = args.positional(INFO_USAGE)?;
+
+    let mut npz = open_matrix(path)?;
+    let format = String::from_utf8_lossy(&read_member::<Vec<u8>>(&mut npz, "format")?.concat()).into_owned();
+    let shape = read_member::<i64>(&mut npz, "shape")?;
+    let (data_dtype, data_shape) = {
+        let data = npz.by_name("data")?.ok_or("missing array 'data' from sparse matrix")?;
+        (data.dtype(), data.shape().to_vec())
+    };
+    let index_name = match &format[..] {
+        "coo" => "row",
+        "dia" => "offsets",
+        _ => "indices",
+    };
+    let index_dtype = npz.by_name(index_name)?.map(|npy| npy.dtype());
+
+    let stored = data_shape.iter().product::<u64>();
+    println!("format:   {}", format);
+    println!("shape:    {}", crate::inspect::format_shape(&shape.iter().map(|&x| x as u64).collect::<Vec<_>>()));
+    println!("stored:   {}", stored);
+    if let [rows, cols] = shape[..] {
+        if format != "dia" && rows > 0 && cols > 0 {
+            println!("density:  {:.6}", stored as f64 / (rows as f64 * cols as f64));
+        }
+    }
+    println!("data:     {}", data_dtype);
+    if let Some(index_dtype) = index_dtype {
+        println!("{:<9} {}", format!("{}:", index_name), index_dtype);
+    }
+    match (&format[..], &data_shape[..]) {
+        ("bsr", [_, block_rows, block_cols]) => println!("blocks:   {} of size ({}, {})", data_shape[0], block_rows, block_cols),
+        ("dia", [num_offsets, _]) => println!("diags:    {}", num_offsets),
+        _ => {},
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[derive(Copy, Clone)]
+enum Target {
+    Coo,
+    Csr,
+    Csc,
+}
+
+fn convert(args: impl Iterator<Item = String>) -> crate::Result<ExitCode> {
+    let args = Args::parse(args, &["to"], &[], &[])?;
+    if crate::help_requested(&args, CONVERT_USAGE) {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let [input, output] = args.positional(CONVERT_USAGE)?;
+    let target = match args.value("to") {
+        Some("coo") => Target::Coo,
+        Some("csr") => Target::Csr,
+        Some("csc") => Target::Csc,
+        Some(other) => return Err(format!("cannot convert to '{}' (expected coo, csr or csc)", other).into()),
+        None => return Err(CONVERT_USAGE.into()),
+    };
+
+    let mut npz = open_matrix(input)?;
+    let dtype = npz.by_name("data")?.ok_or("missing array 'data' from sparse matrix")?.dtype();
+    let scalar = match &dtype {
+        DType::Plain(ty) => ty.clone(),
+        _ => return Err(format!("unsupported data dtype: {}", dtype).into()),
+    };
+
+    macro_rules! convert_as {
+        ($T:ty) => { convert_typed::<$T>(&mut npz, target, output) };
+    }
+    match (scalar.type_char(), scalar.size_field()) {
+        (TypeChar::Bool, 1) => convert_as!(bool),
+        (TypeChar::Int, 1) => convert_as!(i8),
+        (TypeChar::Int, 2) => convert_as!(i16),
+        (TypeChar::Int, 4) => convert_as!(i32),
+        (TypeChar::Int, 8) => convert_as!(i64),
+        (TypeChar::Uint, 1) => convert_as!(u8),
+        (TypeChar::Uint, 2) => convert_as!(u16),
+        (TypeChar::Uint, 4) => convert_as!(u32),
+        (TypeChar::Uint, 8) => convert_as!(u64),
+        (TypeChar::Float, 4) => convert_as!(f32),
+        (TypeChar::Float, 8) => convert_as!(f64),
+        _ => return Err(format!("unsupported data dtype: {}", dtype).into()),
+    }?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn convert_typed<T>(npz: &mut NpzArchive<BufReader<File>>, target: Target, output: &str) -> crate::Result<()>
+where
+    T: Deserialize + AutoSerialize + Clone + Default + PartialEq,
+{
+    let matrix = Sparse::<T>::from_npz(npz)?;
+    let shape = match &matrix {
+        Sparse::Coo(m) => m.shape,
+        Sparse::Csr(m) => m.shape,
+        Sparse::Csc(m) => m.shape,
+        Sparse::Dia(m) => m.shape,
+        Sparse::Bsr(m) => m.shape,
+    };
+    let mut entries = triplets(matrix);
+
+    let converted = match target {
+        Target::Coo => {
+            let (row, col, data) = unzip3(entries);
+            Sparse::Coo(Coo { shape, data, row, col })
+        },
+        Target::Csr => {
+            entries.sort_by_key(|&(row, col, _)| (row, col));
+            let indptr = indptr(entries.iter().map(|&(row, _, _)| row), shape[0]);
+            let (_, indices, data) = unzip3(entries);
+            Sparse::Csr(Csr { shape, data, indices, indptr })
+        },
+        Target::Csc => {
+            entries.sort_by_key(|&(row, col, _)| (col, row));
+            let indptr = indptr(entries.iter().map(|&(_, col, _)| col), shape[1]);
+            let (indices, _, data) = unzip3(entries);
+            Sparse::Csc(Csc { shape, data, indices, indptr })
+        },
+    };
+
+    let mut out = NpzWriter::create(output).map_err(|e| format!("{}: {}", output, e))?;
+    converted.try_write_npz(&mut out)?;
+    out.finish()?;
+    Ok(())
+}
+
+/// Get the `(row, col, value)` of every stored entry.
+fn triplets<T: Clone + Default + PartialEq>(matrix: Sparse<T>) -> Vec<(u64, u64, T)> {
+    match matrix {
+        Sparse::Coo(m) => m.row.into_iter().zip(m.col).zip(m.data).map(|((row, col), x)| (row, col, x)).collect(),
+        Sparse::Csr(m) => compressed_triplets(&m.indptr, m.indices, m.data).collect(),
+        Sparse::Csc(m) => compressed_triplets(&m.indptr, m.indices, m.data).map(|(col, row, x)| (row, col, x)).collect(),
+        Sparse::Dia(m) => {
+            let length = m.data.len().checked_div(m.offsets.len()).unwrap_or(0);
+            let mut out = vec![];
+            for (k, &offset) in m.offsets.iter().enumerate() {
+                for col in 0..length as u64 {
+                    let row = col as i64 - offset;
+                    let x = &m.data[k * length + col as usize];
+                    if 0 <= row && (row as u64) < m.shape[0] && col < m.shape[1] && *x != T::default() {
+                        out.push((row as u64, col, x.clone()));
+                    }
+                }
+            }
+            out
+        },
+        Sparse::Bsr(m) => {
+            let [block_rows, block_cols] = m.blocksize;
+            let block_len = block_rows * block_cols;
+            let mut out = vec![];
+            for (block_row, bounds) in m.indptr.windows(2).enumerate() {
+                for block in bounds[0]..bounds[1] {
+                    let block_col = m.indices[block] as usize;
+                    for (i, x) in m.data[block * block_len..(block + 1) * block_len].iter().enumerate() {
+                        let row = block_row * block_rows + i / block_cols;
+                        let col = block_col * block_cols + i % block_cols;
+                        out.push((row as u64, col as u64, x.clone()));
+                    }
+                }
+            }
+            out
+        },
+    }
+}
+
+/// Get `(major, minor, value)` for each entry of a CSR or CSC matrix.
+fn compressed_triplets<T>(indptr: &[usize], indices: Vec<u64>, data: Vec<T>) -> impl Iterator<Item = (u64, u64, T)> {
+    let majors = indptr.windows(2).enumerate().flat_map(|(major, bounds)| (bounds[0]..bounds[1]).map(move |_| major as u64)).collect::<Vec<_>>();
+    majors.into_iter().zip(indices).zip(data).map(|((major, minor), x)| (major, minor, x))
+}
+
+/// Build the `indptr` of a compressed matrix from the sorted major index of each entry.
+fn indptr(majors: impl Iterator<Item = u64>, num_majors: u64) -> Vec<usize> {
+    let mut indptr = vec![0; num_majors as usize + 1];
+    for major in majors {
+        indptr[major as usize + 1] += 1;
+    }
+    for i in 1..indptr.len() {
+        indptr[i] += indptr[i - 1];
+    }
+    indptr
+}
+
+fn unzip3<T>(entries: Vec<(u64, u64, T)>) -> (Vec<u64>, Vec<u64>, Vec<T>) {
+    let mut out = (Vec::with_capacity(entries.len()), Vec::with_capacity(entries.len()), Vec::with_capacity(entries.len()));
+    for (a, b, x) in entries {
+        out.0.push(a);
+        out.1.push(b);
+        out.2.push(x);
+    }
+    out
+}
@@ -0,0 +1,315 @@
+//! Decoding elements of any dtype into values that can be printed.
+
+use std::fmt::Write;
+
+use npyz::{DType, Endianness, TimeUnits, TypeChar, TypeStr};
+
+/// The value of an element (or of a part of one).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    Complex(f64, f64),
+    /// A `datetime64` or `timedelta64`, with `None` for NaT.
+    Time(Option<i64>, Option<TimeUnits>),
+    /// A byte string, without trailing NULs.
+    Bytes(Vec<u8>),
+    Str(String),
+    /// Raw bytes of a `V` type, or of a type that has no other interpretation (e.g. `f16`).
+    Raw(Vec<u8>),
+    Array(Vec<Value>),
+    /// Named fields of a record.  (unnamed padding fields are omitted)
+    Record(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Decode an element from exactly `dtype.num_bytes()` bytes.
+    pub fn decode(dtype: &DType, bytes: &[u8]) -> Value {
+        match dtype {
+            DType::Plain(ty) => decode_scalar(ty, bytes),
+            DType::Array(len, inner) => {
+                let size = inner.num_bytes().expect("size was checked by the caller");
+                Value::Array((0..*len as usize).map(|i| Value::decode(inner, &bytes[i * size..(i + 1) * size])).collect())
+            },
+            DType::Record(fields) => {
+                let mut offset = 0;
+                let mut out = vec![];
+                for field in fields {
+                    let size = field.dtype.num_bytes().expect("size was checked by the caller");
+                    if !field.name.is_empty() {
+                        out.push((field.name.clone(), Value::decode(&field.dtype, &bytes[offset..offset + size])));
+                    }
+                    offset += size;
+                }
+                Value::Record(out)
+            },
+        }
+    }
+
+    /// Nest a flat list of values in C order into arrays of the given shape.
+    pub fn nest(values: &mut impl Iterator<Item = Value>, shape: &[u64]) -> Value {
+        match shape {
+            [] => values.next().expect("not enough values"),
+            [dim, rest @ ..] => Value::Array((0..*dim).map(|_| Value::nest(values, rest)).collect()),
+        }
+    }
+
+    /// Format similarly to Python's `repr`.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out);
+        out
+    }
+
+    fn write_text(&self, out: &mut String) {
+        match self {
+            Value::Bool(b) => out.push_str(if *b { "True" } else { "False" }),
+            Value::Int(x) => write!(out, "{}", x).unwrap(),
+            Value::Uint(x) => write!(out, "{}", x).unwrap(),
+            Value::Float(x) => out.push_str(&format_float(*x)),
+            Value::Complex(re, im) => write!(out, "({}{}{}j)", format_float(*re), if *im < 0.0 { "" } else { "+" }, format_float(*im)).unwrap(),
+            Value::Time(None, _) => out.push_str("NaT"),
+            Value::Time(Some(x), Some(units)) => write!(out, "{} {}", x, units).unwrap(),
+            Value::Time(Some(x), None) => write!(out, "{}", x).unwrap(),
+            Value::Bytes(bytes) => {
+                out.push_str("b'");
+                for &b in bytes {
+                    match b {
+                        b'\\' => out.push_str("\\\\"),
+                        b'\'' => out.push_str("\\'"),
+                        0x20..=0x7e => out.push(b as char),
+                        _ => write!(out, "\\x{:02x}", b).unwrap(),
+                    }
+                }
+                out.push('\'');
+            },
+            Value::Str(s) => {
+                out.push('\'');
+                for c in s.chars() {
+                    match c {
+                        '\\' => out.push_str("\\\\"),
+                        '\'' => out.push_str("\\'"),
+                        c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+                        c => out.push(c),
+                    }
+                }
+                out.push('\'');
+            },
+            Value::Raw(bytes) => write!(out, "{}", hex(bytes)).unwrap(),
+            Value::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.write_text(out);
+                }
+                out.push(']');
+            },
+            Value::Record(fields) => {
+                out.push('(');
+                for (i, (_, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    value.write_text(out);
+                }
+                if fields.len() == 1 {
+                    out.push(',');
+                }
+                out.push(')');
+            },
+        }
+    }
+
+    /// Format as JSON.  Values that JSON cannot represent (NaN, infinities and NaT) become `null`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Value::Bool(b) => write!(out, "{}", b).unwrap(),
+            Value::Int(x) => write!(out, "{}", x).unwrap(),
+            Value::Uint(x) => write!(out, "{}", x).unwrap(),
+            Value::Float(x) => out.push_str(&json_float(*x)),
+            Value::Complex(re, im) => write!(out, "[{}, {}]", json_float(*re), json_float(*im)).unwrap(),
+            Value::Time(None, _) => out.push_str("null"),
+            Value::Time(Some(x), _) => write!(out, "{}", x).unwrap(),
+            Value::Bytes(bytes) => json_string(out, &String::from_utf8_lossy(bytes)),
+            Value::Str(s) => json_string(out, s),
+            Value::Raw(bytes) => json_string(out, &hex(bytes)),
+            Value::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.write_json(out);
+                }
+                out.push(']');
+            },
+            Value::Record(fields) => {
+                out.push('{');
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    json_string(out, name);
+                    out.push_str(": ");
+                    value.write_json(out);
+                }
+                out.push('}');
+            },
+        }
+    }
+
+    /// Flatten into CSV cells, one per scalar.
+    pub fn to_csv_cells(&self, out: &mut Vec<String>) {
+        match self {
+            Value::Array(items) => items.iter().for_each(|item| item.to_csv_cells(out)),
+            Value::Record(fields) => fields.iter().for_each(|(_, value)| value.to_csv_cells(out)),
+            Value::Bool(b) => out.push(b.to_string()),
+            Value::Complex(re, im) => out.push(format!("{}{}{}j", format_float(*re), if *im < 0.0 { "" } else { "+" }, format_float(*im))),
+            Value::Bytes(bytes) => out.push(csv_escape(&String::from_utf8_lossy(bytes))),
+            Value::Str(s) => out.push(csv_escape(s)),
+            Value::Time(Some(x), _) => out.push(x.to_string()),
+            other => out.push(other.to_text()),
+        }
+    }
+}
+
+/// Names of the CSV columns produced by [`Value::to_csv_cells`] for an element of a dtype.
+pub fn csv_column_names(dtype: &DType, prefix: &str, out: &mut Vec<String>) {
+    match dtype {
+        DType::Plain(_) => out.push(csv_escape(prefix)),
+        DType::Array(len, inner) => {
+            for i in 0..*len {
+                csv_column_names(inner, &format!("{}[{}]", prefix, i), out);
+            }
+        },
+        DType::Record(fields) => {
+            for field in fields.iter().filter(|field| !field.name.is_empty()) {
+                let name = match prefix {
+                    "" => field.name.clone(),
+                    _ => format!("{}.{}", prefix, field.name),
+                };
+                csv_column_names(&field.dtype, &name, out);
+            }
+        },
+    }
+}
+
+fn decode_scalar(ty: &TypeStr, bytes: &[u8]) -> Value {
+    let big_endian = ty.endianness() == Endianness::Big;
+    let size = bytes.len();
+    match (ty.type_char(), size) {
+        (TypeChar::Bool, 1) => Value::Bool(bytes[0] != 0),
+        (TypeChar::Int, 1 | 2 | 4 | 8) => Value::Int(read_int(bytes, big_endian)),
+        (TypeChar::Uint, 1 | 2 | 4 | 8) => Value::Uint(read_bits(bytes, big_endian)),
+        (TypeChar::Float, 2 | 4 | 8) => Value::Float(read_float(bytes, big_endian)),
+        (TypeChar::Complex, 8 | 16) => {
+            let (re, im) = bytes.split_at(size / 2);
+            Value::Complex(read_float(re, big_endian), read_float(im, big_endian))
+        },
+        (TypeChar::DateTime | TypeChar::TimeDelta, 8) => {
+            let x = read_int(bytes, big_endian);
+            Value::Time(if x == i64::MIN { None } else { Some(x) }, ty.time_units())
+        },
+        (TypeChar::ByteStr, _) => {
+            let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            Value::Bytes(bytes[..len].to_vec())
+        },
+        (TypeChar::UnicodeStr, _) => {
+            let chars = bytes.chunks_exact(4).map(|c| read_bits(c, big_endian) as u32);
+            let mut s = chars.map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER)).collect::<String>();
+            s.truncate(s.trim_end_matches('\0').len());
+            Value::Str(s)
+        },
+        _ => Value::Raw(bytes.to_vec()),
+    }
+}
+
+fn read_bits(bytes: &[u8], big_endian: bool) -> u64 {
+    let fold = |acc: u64, &byte: &u8| acc << 8 | byte as u64;
+    match big_endian {
+        true => bytes.iter().fold(0, fold),
+        false => bytes.iter().rev().fold(0, fold),
+    }
+}
+
+fn read_int(bytes: &[u8], big_endian: bool) -> i64 {
+    let shift = 64 - 8 * bytes.len() as u32;
+    ((read_bits(bytes, big_endian) << shift) as i64) >> shift
+}
+
+fn read_float(bytes: &[u8], big_endian: bool) -> f64 {
+    let bits = read_bits(bytes, big_endian);
+    // narrow floats are converted through their shortest representation, so that e.g. 0.1f32 prints as 0.1
+    let narrow = |x: f32| x.to_string().parse().unwrap_or(x as f64);
+    match bytes.len() {
+        2 => narrow(half_to_f64(bits as u16) as f32),
+        4 => narrow(f32::from_bits(bits as u32)),
+        8 => f64::from_bits(bits),
+        _ => unreachable!(),
+    }
+}
+
+fn half_to_f64(bits: u16) -> f64 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as f64;
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        0x1f if mantissa == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent as i32 - 15),
+    }
+}
+
+fn format_float(x: f64) -> String {
+    match x {
+        _ if x.is_nan() => "nan".to_string(),
+        f64::INFINITY => "inf".to_string(),
+        f64::NEG_INFINITY => "-inf".to_string(),
+        _ => format!("{:?}", x),
+    }
+}
+
+fn json_float(x: f64) -> String {
+    match x.is_finite() {
+        true => format!("{:?}", x),
+        false => "null".to_string(),
+    }
+}
+
+fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn csv_escape(s: &str) -> String {
+    match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,280 @@
+//! Converting NPY files to the opposite byte order.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::NpyHeader;
+
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Copy an NPY file from `reader` to `writer`, converting every element to the opposite byte order.
+///
+/// The `descr` of the header is updated to match (e.g. `'<f8'` becomes `'>f8'`), while the shape,
+/// order and [extra keys][`NpyHeader::extra_keys`] are carried over.  The data is swapped bytewise
+/// without being decoded, so this works for any dtype, including records and subarrays.  Types whose
+/// bytes do not depend on the byte order (e.g. `|u1`, `|S5`) are copied unchanged.
+///
+/// The reader must initially be at the beginning of an NPY file.  Any bytes following the data are not copied.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let little_endian = std::fs::read("test-data/plain.npy")?;
+/// let mut big_endian = vec![];
+/// npyz::byteswap(&little_endian[..], &mut big_endian)?;
+///
+/// let npy = npyz::NpyFile::new(&big_endian[..])?;
+/// assert_eq!(npy.dtype().descr(), "'>f8'");
+/// assert_eq!(npy.into_vec::<f64>()?, npyz::NpyFile::new(&little_endian[..])?.into_vec::<f64>()?);
+/// # Ok(()) }
+/// ```
+pub fn byteswap(mut reader: impl Read, mut writer: impl Write) -> io::Result<()> {
+    let header = NpyHeader::from_reader(&mut reader)?;
+    let plan = SwapPlan::new(&header.dtype())?;
+    writer.write_all(&swapped_header_bytes(&header)?)?;
+
+    let mut remaining = plan.data_len(&header)?;
+    let mut buf = vec![0; plan.chunk_len()];
+    while remaining > 0 {
+        let len = buf.len().min(remaining as usize);
+        reader.read_exact(&mut buf[..len]).map_err(truncated)?;
+        plan.apply(&mut buf[..len]);
+        writer.write_all(&buf[..len])?;
+        remaining -= len as u64;
+    }
+    Ok(())
+}
+
+/// Rewrite an NPY file on the filesystem with the opposite byte order.
+///
+/// See [`byteswap`] for what this does to the contents.  When the new header has the same length as
+/// the old one (which it always does for files written by numpy or by this crate), the file is modified
+/// in place without any additional disk space.  Otherwise, the result is written to a temporary file in
+/// the same directory, which then replaces the original.
+///
+/// The file is checked for truncation before any of it is modified.
+pub fn byteswap_file(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+    let header = NpyHeader::from_reader(io::BufReader::new(&mut file))?;
+    let plan = SwapPlan::new(&header.dtype())?;
+    let old_header_len = header.raw_bytes().expect("header was read from a file").len() as u64;
+    let new_header = swapped_header_bytes(&header)?;
+    let data_len = plan.data_len(&header)?;
+    if file.metadata()?.len() < old_header_len + data_len {
+        return Err(Error::Truncated.into());
+    }
+
+    if new_header.len() as u64 != old_header_len {
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(".byteswap-tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        file.seek(SeekFrom::Start(0))?;
+        let result = (|| {
+            let mut out = io::BufWriter::new(File::create(&temp_path)?);
+            byteswap(io::BufReader::new(file), &mut out)?;
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()
+        })();
+        return match result {
+            Ok(()) => fs::rename(&temp_path, path),
+            Err(e) => {
+                let _ = fs::remove_file(&temp_path);
+                Err(e)
+            },
+        };
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&new_header)?;
+
+    let mut position = old_header_len;
+    let mut buf = vec![0; plan.chunk_len()];
+    while position < old_header_len + data_len {
+        let len = buf.len().min((old_header_len + data_len - position) as usize);
+        file.seek(SeekFrom::Start(position))?;
+        file.read_exact(&mut buf[..len]).map_err(truncated)?;
+        plan.apply(&mut buf[..len]);
+        file.seek(SeekFrom::Start(position))?;
+        file.write_all(&buf[..len])?;
+        position += len as u64;
+    }
+    file.flush()
+}
+
+fn swapped_header_bytes(header: &NpyHeader) -> io::Result<Vec<u8>> {
+    let dtype = header.dtype().swapped_byte_order();
+    crate::write::header_bytes(&dtype, header.order(), header.shape(), header.extra_keys())
+}
+
+fn truncated(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => Error::Truncated.into(),
+        _ => err,
+    }
+}
+
+/// The byte ranges within an item that must be reversed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SwapPlan {
+    item_size: usize,
+    runs: Vec<SwapRun>,
+}
+
+/// `count` adjacent values of `unit` bytes each, starting at `offset`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SwapRun {
+    offset: usize,
+    unit: usize,
+    count: usize,
+}
+
+impl SwapPlan {
+    fn new(dtype: &DType) -> io::Result<Self> {
+        let item_size = dtype.num_bytes().ok_or_else(|| Error::InvalidData(format!("dtype is too large: {}", dtype.descr())))?;
+        let mut runs = vec![];
+        add_runs(dtype, 0, &mut runs);
+        Ok(SwapPlan { item_size, runs })
+    }
+
+    fn data_len(&self, header: &NpyHeader) -> io::Result<u64> {
+        header.len().checked_mul(self.item_size as u64)
+            .ok_or_else(|| Error::InvalidData(format!("shape {:?} is too large", header.shape())).into())
+    }
+
+    // A buffer length that holds a whole number of items.
+    fn chunk_len(&self) -> usize {
+        match self.item_size {
+            0 => 0,
+            size => size * (CHUNK_SIZE / size).max(1),
+        }
+    }
+
+    // Swap a whole number of items in place.
+    fn apply(&self, bytes: &mut [u8]) {
+        if self.runs.is_empty() {
+            return;
+        }
+        for item in bytes.chunks_exact_mut(self.item_size) {
+            for &SwapRun { offset, unit, count } in &self.runs {
+                for value in item[offset..offset + unit * count].chunks_exact_mut(unit) {
+                    value.reverse();
+                }
+            }
+        }
+    }
+}
+
+fn add_runs(dtype: &DType, offset: usize, runs: &mut Vec<SwapRun>) {
+    match dtype {
+        DType::Plain(ty) => {
+            if !ty.has_byte_order() {
+                return;
+            }
+            // the unit of swapping is the same as the alignment, e.g. each half of a complex number
+            let unit = ty.alignment();
+            let count = ty.num_bytes().expect("size was checked by the caller") / unit;
+            match runs.last_mut() {
+                Some(last) if last.unit == unit && last.offset + last.unit * last.count == offset => last.count += count,
+                _ => runs.push(SwapRun { offset, unit, count }),
+            }
+        },
+        DType::Array(len, inner) => {
+            let size = inner.num_bytes().expect("size was checked by the caller");
+            for i in 0..*len as usize {
+                add_runs(inner, offset + i * size, runs);
+            }
+        },
+        DType::Record(fields) => {
+            let offsets = dtype.field_offsets().expect("size was checked by the caller");
+            for (field, field_offset) in fields.iter().zip(offsets) {
+                add_runs(&field.dtype, offset + field_offset, runs);
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(descr: &str) -> SwapPlan {
+        SwapPlan::new(&DType::parse(descr).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn runs() {
+        assert_eq!(plan("'<f8'").runs, vec![SwapRun { offset: 0, unit: 8, count: 1 }]);
+        assert_eq!(plan("'|u1'").runs, vec![]);
+        assert_eq!(plan("'<c16'").runs, vec![SwapRun { offset: 0, unit: 8, count: 2 }]);
+        assert_eq!(plan("'<U3'").runs, vec![SwapRun { offset: 0, unit: 4, count: 3 }]);
+        assert_eq!(plan("[('a', '<i4', (3,)), ('b', '|S2'), ('c', '>i2'), ('d', '<i2')]").runs, vec![
+            SwapRun { offset: 0, unit: 4, count: 3 },
+            SwapRun { offset: 14, unit: 2, count: 2 },
+        ]);
+    }
+
+    #[test]
+    fn apply() {
+        let plan = plan("[('a', '<u2'), ('b', '|u1'), ('c', '<c8')]");
+        let mut bytes = (0..22).collect::<Vec<u8>>();
+        plan.apply(&mut bytes);
+        assert_eq!(bytes, vec![1, 0, 2, 6, 5, 4, 3, 10, 9, 8, 7, 12, 11, 13, 17, 16, 15, 14, 21, 20, 19, 18]);
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("npyz-byteswap-{}-{}.npy", name, std::process::id()))
+    }
+
+    #[test]
+    fn file_in_place() -> io::Result<()> {
+        let original = fs::read("test-data/c-order.npy")?;
+        let path = temp_path("in-place");
+        fs::write(&path, &original)?;
+
+        byteswap_file(&path)?;
+        let swapped = fs::read(&path)?;
+        assert_eq!(swapped.len(), original.len());
+        let npy = crate::NpyFile::new(&swapped[..])?;
+        assert_eq!(npy.dtype().descr(), "'>i8'");
+        assert_eq!(npy.shape(), &[2, 3, 4]);
+        assert_eq!(npy.into_vec::<i64>()?, crate::NpyFile::new(&original[..])?.into_vec::<i64>()?);
+
+        // swapping twice restores the data, though the header may be formatted differently
+        byteswap_file(&path)?;
+        let restored = fs::read(&path)?;
+        assert_eq!(crate::NpyFile::new(&restored[..])?.dtype().descr(), "'<i8'");
+        assert_eq!(restored[128..], original[128..]);
+        fs::remove_file(&path)
+    }
+
+    #[test]
+    fn file_with_different_header_len() -> io::Result<()> {
+        // pad the header to 192 bytes instead of 128
+        let mut original = fs::read("test-data/plain.npy")?;
+        let header_len = u16::from_le_bytes([original[8], original[9]]) as usize;
+        original.splice(10 + header_len - 1..10 + header_len - 1, vec![b' '; 64]);
+        original[8..10].copy_from_slice(&(header_len as u16 + 64).to_le_bytes());
+
+        let path = temp_path("header-len");
+        fs::write(&path, &original)?;
+        byteswap_file(&path)?;
+        let npy = crate::NpyFile::new(File::open(&path)?)?;
+        assert_eq!(npy.dtype().descr(), "'>f8'");
+        assert_eq!(npy.into_vec::<f64>()?, crate::NpyFile::new(&original[..])?.into_vec::<f64>()?);
+        fs::remove_file(&path)
+    }
+
+    #[test]
+    fn file_truncated() -> io::Result<()> {
+        let original = fs::read("test-data/plain.npy")?;
+        let path = temp_path("truncated");
+        fs::write(&path, &original[..original.len() - 1])?;
+        assert_eq!(byteswap_file(&path).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(fs::read(&path)?, &original[..original.len() - 1]);
+        fs::remove_file(&path)
+    }
+}
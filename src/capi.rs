@@ -0,0 +1,359 @@
+//! A C API for reading and writing NPY files from other languages.
+//!
+//! The declarations are in `include/npyz.h`, which is generated from this module by
+//! [cbindgen](https://github.com/mozilla/cbindgen) with the settings in `cbindgen.toml`.
+//! To build a shared library, enable the feature and ask for a `cdylib` (or a `staticlib`):
+//!
+//! ```text
+//! cargo rustc --release --features capi --crate-type cdylib
+//! ```
+//!
+//! The API works with the raw bytes of the data, in the dtype of the file, which is described by
+//! its `descr` as it appears in the header (e.g. `'<f8'` or `[('x', '<i4'), ('y', '<f4')]`; the
+//! quotes around a plain type may be left out when writing).  Functions that can fail
+//! return `NULL` or a negative number, and the message of the error can then be retrieved with
+//! [`npyz_last_error`].
+//!
+//! *This is only available with the **`"capi"`** feature.*
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::{NpyHeader, Order};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(err: impl std::fmt::Display) {
+    // an interior NUL in the message would be surprising, but should not lose the whole message
+    let message = CString::new(err.to_string().replace('\0', "\\0")).expect("NULs were removed");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+// Convert a result for C, recording the error.
+fn report<T>(result: io::Result<T>, on_error: T) -> T {
+    result.unwrap_or_else(|err| {
+        set_error(err);
+        on_error
+    })
+}
+
+unsafe fn path_from_c<'a>(path: *const c_char) -> io::Result<&'a str> {
+    if path.is_null() {
+        return Err(Error::InvalidInput("the path is NULL".to_string()).into());
+    }
+    CStr::from_ptr(path).to_str().map_err(|_| Error::InvalidInput("the path is not UTF-8".to_string()).into())
+}
+
+/// Get the message of the last error on this thread, or `NULL` if there was none.
+///
+/// The string is owned by the library, and stays valid until the next failing call on this thread.
+#[no_mangle]
+pub extern "C" fn npyz_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+}
+
+/// An NPY file opened for reading with [`npyz_reader_open`].
+pub struct NpyzReader {
+    header: NpyHeader,
+    descr: CString,
+    reader: BufReader<File>,
+    remaining: u64,
+}
+
+/// Open an NPY file and read its header.
+///
+/// Returns `NULL` on failure.  The reader must be freed with [`npyz_reader_close`].
+///
+/// # Safety
+///
+/// `path` must be `NULL` or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_reader_open(path: *const c_char) -> *mut NpyzReader {
+    let result = (|| {
+        let mut reader = BufReader::new(File::open(path_from_c(path)?)?);
+        let header = NpyHeader::from_reader(&mut reader)?;
+        let descr = CString::new(header.dtype().descr()).expect("descr has no NULs");
+        let remaining = header.len();
+        Ok(Box::into_raw(Box::new(NpyzReader { header, descr, reader, remaining })))
+    })();
+    report(result, std::ptr::null_mut())
+}
+
+/// Get the dtype of the file as a `descr` string.  The string is owned by the reader.
+///
+/// # Safety
+///
+/// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_reader_descr(reader: *const NpyzReader) -> *const c_char {
+    (*reader).descr.as_ptr()
+}
+
+/// Get the number of dimensions of the array.
+///
+/// # Safety
+///
+/// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_reader_ndim(reader: *const NpyzReader) -> usize {
+    (*reader).header.shape().len()
+}
+
+/// Get the shape of the array, an array of [`npyz_reader_ndim`] numbers owned by the reader.
+///
+/// # Safety
+///
+/// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_reader_shape(reader: *const NpyzReader) -> *const u64 {
+    (*reader).header.shape().as_ptr()
+}
+
+/// Returns 1 if the data is in Fortran order, or 0 if it is in C order.
+///
+/// # Safety
+///
+/// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_reader_fortran_order(reader: *const NpyzReader) -> c_int {
+    ((*reader).header.order() == Order::Fortran) as c_int
+}
+
+/// Get the number of bytes in each record.
+///
+/// # Safety
+///
+/// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_reader_item_size(reader: *const NpyzReader) -> usize {
+    (*reader).header.item_size()
+}
+
+/// Get the total number of records in the file.
+///
+/// # Safety
+///
+/// `reader` must come from [`npyz_reader_open`] and not yet be closed.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_reader_len(reader: *const NpyzReader) -> u64 {
+    (*reader).header.len()
+}
+
+/// Read the raw bytes of up to `max_records` of the next records, in the order they are stored.
+///
+/// Returns the number of records read, which is 0 once all have been read, or -1 on failure.
+///
+/// # Safety
+///
+/// `reader` must come from [`npyz_reader_open`] and not yet be closed, and `buf` must have room for
+/// `max_records` times [`npyz_reader_item_size`] bytes.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_reader_read(reader: *mut NpyzReader, buf: *mut c_void, max_records: u64) -> i64 {
+    let reader = &mut *reader;
+    let records = max_records.min(reader.remaining);
+    let result = (|| {
+        let len = records.checked_mul(reader.header.item_size() as u64)
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or_else(|| Error::InvalidInput("too many records for one read".to_string()))?;
+        if len > 0 {
+            let buf = std::slice::from_raw_parts_mut(buf as *mut u8, len);
+            reader.reader.read_exact(buf).map_err(crate::read_at::truncated)?;
+        }
+        reader.remaining -= records;
+        Ok(records as i64)
+    })();
+    report(result, -1)
+}
+
+/// Close a reader, freeing it.  Does nothing if `reader` is `NULL`.
+///
+/// # Safety
+///
+/// `reader` must be `NULL` or come from [`npyz_reader_open`] and not yet be closed.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_reader_close(reader: *mut NpyzReader) {
+    if !reader.is_null() {
+        drop(Box::from_raw(reader));
+    }
+}
+
+/// An NPY file being written, created with [`npyz_writer_create`].
+pub struct NpyzWriter {
+    writer: BufWriter<File>,
+    item_size: usize,
+    remaining: u64,
+}
+
+/// Create an NPY file with a dtype given as a `descr` string, and write its header.
+///
+/// The shape has `ndim` dimensions, and the data is in Fortran order if `fortran_order` is nonzero.
+/// Returns `NULL` on failure.  The writer must be freed with [`npyz_writer_finish`].
+///
+/// # Safety
+///
+/// `path` and `descr` must be `NULL` or NUL-terminated strings, and `shape` must point to `ndim` numbers.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_writer_create(
+    path: *const c_char,
+    descr: *const c_char,
+    shape: *const u64,
+    ndim: usize,
+    fortran_order: c_int,
+) -> *mut NpyzWriter {
+    let result = (|| {
+        if descr.is_null() || (shape.is_null() && ndim > 0) {
+            return Err(Error::InvalidInput("the descr or shape is NULL".to_string()).into());
+        }
+        let descr = CStr::from_ptr(descr).to_str().map_err(|_| Error::InvalidInput("the descr is not UTF-8".to_string()))?;
+        let dtype = DType::parse(descr).or_else(|_| DType::parse(&format!("'{}'", descr)))?;
+        let shape = match ndim {
+            0 => &[][..],
+            _ => std::slice::from_raw_parts(shape, ndim),
+        };
+        let order = Order::from_fortran_order(fortran_order != 0);
+        let header = crate::write::header_bytes(&dtype, order, shape, &[])?;
+        let item_size = dtype.num_bytes().ok_or_else(|| Error::InvalidInput("the dtype is too large".to_string()))?;
+        // like `NpyHeader::from_parts`, so that the data of the file can be addressed by an i64
+        let remaining = shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim))
+            .filter(|&len| len.checked_mul(item_size as u64).is_some_and(|bytes| bytes <= i64::MAX as u64))
+            .ok_or_else(|| Error::InvalidInput("the shape is too large".to_string()))?;
+
+        let mut writer = BufWriter::new(File::create(path_from_c(path)?)?);
+        writer.write_all(&header)?;
+        Ok(Box::into_raw(Box::new(NpyzWriter { writer, item_size, remaining })))
+    })();
+    report(result, std::ptr::null_mut())
+}
+
+/// Write the raw bytes of `n_records` records, in the order they are stored.
+///
+/// Returns 0 on success, or -1 on failure, including when this would write more records than the
+/// shape holds.
+///
+/// # Safety
+///
+/// `writer` must come from [`npyz_writer_create`] and not yet be finished, and `data` must point to
+/// `n_records` records of the item size of the dtype.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_writer_write(writer: *mut NpyzWriter, data: *const c_void, n_records: u64) -> c_int {
+    let writer = &mut *writer;
+    let result = (|| {
+        if n_records > writer.remaining {
+            let msg = format!("cannot write {} records, only {} remain", n_records, writer.remaining);
+            return Err(Error::InvalidInput(msg).into());
+        }
+        let len = n_records.checked_mul(writer.item_size as u64)
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or_else(|| Error::InvalidInput("too many records for one write".to_string()))?;
+        if len > 0 {
+            writer.writer.write_all(std::slice::from_raw_parts(data as *const u8, len))?;
+        }
+        writer.remaining -= n_records;
+        Ok(0)
+    })();
+    report(result, -1)
+}
+
+/// Finish writing the file, and free the writer.
+///
+/// Returns 0 on success, or -1 on failure, including when fewer records were written than the shape
+/// holds.  The writer is freed either way.
+///
+/// # Safety
+///
+/// `writer` must come from [`npyz_writer_create`] and not yet be finished.
+#[no_mangle]
+pub unsafe extern "C" fn npyz_writer_finish(writer: *mut NpyzWriter) -> c_int {
+    let mut writer = Box::from_raw(writer);
+    let result = (|| {
+        if writer.remaining > 0 {
+            return Err(Error::InvalidInput(format!("{} records were not written", writer.remaining)).into());
+        }
+        writer.writer.flush().map(|()| 0)
+    })();
+    report(result, -1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> CString {
+        let path = std::env::temp_dir().join(format!("npyz-capi-{}-{}.npy", name, std::process::id()));
+        CString::new(path.to_str().unwrap()).unwrap()
+    }
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(npyz_last_error()) }.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn write_and_read() {
+        let path = temp_path("roundtrip");
+        let descr = CString::new("[('x', '<i4'), ('y', '>f8')]").unwrap();
+        let mut data = vec![];
+        for i in 0..6i32 {
+            data.extend_from_slice(&i.to_le_bytes());
+            data.extend_from_slice(&(i as f64 / 2.0).to_be_bytes());
+        }
+        unsafe {
+            let writer = npyz_writer_create(path.as_ptr(), descr.as_ptr(), [2, 3].as_ptr(), 2, 0);
+            assert!(!writer.is_null(), "{}", last_error());
+            assert_eq!(npyz_writer_write(writer, data.as_ptr() as *const c_void, 4), 0);
+            assert_eq!(npyz_writer_write(writer, data[48..].as_ptr() as *const c_void, 2), 0);
+            assert_eq!(npyz_writer_finish(writer), 0);
+
+            let reader = npyz_reader_open(path.as_ptr());
+            assert!(!reader.is_null(), "{}", last_error());
+            assert_eq!(CStr::from_ptr(npyz_reader_descr(reader)).to_str().unwrap(), "[('x', '<i4'), ('y', '>f8'), ]");
+            assert_eq!(std::slice::from_raw_parts(npyz_reader_shape(reader), npyz_reader_ndim(reader)), &[2, 3]);
+            assert_eq!(npyz_reader_fortran_order(reader), 0);
+            assert_eq!(npyz_reader_item_size(reader), 12);
+            assert_eq!(npyz_reader_len(reader), 6);
+
+            let mut buf = vec![0u8; 48];
+            assert_eq!(npyz_reader_read(reader, buf.as_mut_ptr() as *mut c_void, 4), 4);
+            assert_eq!(buf, data[..48]);
+            assert_eq!(npyz_reader_read(reader, buf.as_mut_ptr() as *mut c_void, 4), 2);
+            assert_eq!(buf[..24], data[48..]);
+            assert_eq!(npyz_reader_read(reader, buf.as_mut_ptr() as *mut c_void, 4), 0);
+            npyz_reader_close(reader);
+        }
+        std::fs::remove_file(path.to_str().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn errors() {
+        let path = temp_path("errors");
+        let descr = CString::new("<f8").unwrap();
+        unsafe {
+            let missing = CString::new("test-data/does-not-exist.npy").unwrap();
+            assert!(npyz_reader_open(missing.as_ptr()).is_null());
+            assert!(!npyz_last_error().is_null());
+
+            let writer = npyz_writer_create(path.as_ptr(), descr.as_ptr(), [2].as_ptr(), 1, 1);
+            assert!(!writer.is_null(), "{}", last_error());
+            assert_eq!(npyz_writer_write(writer, [0.0f64; 3].as_ptr() as *const c_void, 3), -1);
+            assert_eq!(last_error(), "cannot write 3 records, only 2 remain");
+            assert_eq!(npyz_writer_finish(writer), -1);
+            assert_eq!(last_error(), "2 records were not written");
+
+            // 2^62 records of 8 bytes cannot be addressed
+            assert!(npyz_writer_create(path.as_ptr(), descr.as_ptr(), [1 << 62].as_ptr(), 1, 0).is_null());
+            assert_eq!(last_error(), "the shape is too large");
+            let writer = npyz_writer_create(path.as_ptr(), descr.as_ptr(), [1 << 59].as_ptr(), 1, 0);
+            assert!(!writer.is_null(), "{}", last_error());
+            drop(Box::from_raw(writer));
+
+            let bad = CString::new("not a dtype").unwrap();
+            assert!(npyz_writer_create(path.as_ptr(), bad.as_ptr(), [2].as_ptr(), 1, 0).is_null());
+        }
+        std::fs::remove_file(path.to_str().unwrap()).unwrap();
+    }
+}
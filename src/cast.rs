@@ -0,0 +1,305 @@
+//! Reading numbers as a different type than the one in the file.
+
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+
+use crate::dtype_compat::can_cast_safely;
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::{record_error, NpyFile, NpyReader};
+use crate::serialize::{Deserialize, DTypeError};
+use crate::type_str::{TypeChar, TypeStr};
+
+/// Which conversions may be made by [`NpyFile::data_cast`], after numpy's `casting` argument.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Casting {
+    /// Only allow conversions that can represent every value of the file's dtype, following numpy's
+    /// `casting='safe'`.  (e.g. `<i4` can be read as `i64` or `f64`, and `<f4` as `f64`, but `<i4` cannot
+    /// be read as `u64`)
+    Safe,
+    /// Also allow conversions between integers of any size, and to floats of any size, like numpy's
+    /// `casting='same_kind'`.
+    ///
+    /// Unlike numpy, this also converts between signed and unsigned integers.  Integers are checked one
+    /// at a time, and reading fails with [`Error::InvalidData`] at the first value that does not fit.
+    /// Conversions to floats round to the nearest value.
+    SameKind,
+}
+
+/// The types that [`NpyFile::data_cast`] can convert to: the primitive integers and floats, and `usize`.
+pub trait CastTarget: Sized {
+    #[doc(hidden)]
+    const __TYPE_CHAR: TypeChar;
+    #[doc(hidden)]
+    const __SIZE: u64;
+    #[doc(hidden)]
+    fn __from_int(x: i128) -> Option<Self>;
+    #[doc(hidden)]
+    fn __from_float(x: f64) -> Option<Self>;
+}
+
+macro_rules! impl_cast_target {
+    (int: $($T:ty => $type_char:ident,)*) => {$(
+        impl CastTarget for $T {
+            const __TYPE_CHAR: TypeChar = TypeChar::$type_char;
+            const __SIZE: u64 = std::mem::size_of::<$T>() as u64;
+            fn __from_int(x: i128) -> Option<Self> { <$T>::try_from(x).ok() }
+            fn __from_float(_: f64) -> Option<Self> { None }
+        }
+    )*};
+    (float: $($T:ty,)*) => {$(
+        impl CastTarget for $T {
+            const __TYPE_CHAR: TypeChar = TypeChar::Float;
+            const __SIZE: u64 = std::mem::size_of::<$T>() as u64;
+            fn __from_int(x: i128) -> Option<Self> { Some(x as $T) }
+            fn __from_float(x: f64) -> Option<Self> { Some(x as $T) }
+        }
+    )*};
+}
+
+impl_cast_target! {
+    int: i8 => Int, i16 => Int, i32 => Int, i64 => Int,
+    u8 => Uint, u16 => Uint, u32 => Uint, u64 => Uint, usize => Uint,
+}
+impl_cast_target! { float: f32, f64, }
+
+// A primitive type that may be found in the file.
+trait CastSource: Copy + fmt::Display {
+    fn cast<T: CastTarget>(self) -> Option<T>;
+}
+
+macro_rules! impl_cast_source {
+    ($method:ident as $Wide:ty: $($T:ty,)*) => {$(
+        impl CastSource for $T {
+            fn cast<T: CastTarget>(self) -> Option<T> { T::$method(self as $Wide) }
+        }
+    )*};
+}
+
+impl_cast_source! { __from_int as i128: i8, i16, i32, i64, u8, u16, u32, u64, bool, }
+impl_cast_source! { __from_float as f64: f32, f64, }
+
+fn is_allowed(from: &TypeStr, to: &TypeStr, casting: Casting) -> bool {
+    use TypeChar::*;
+
+    match casting {
+        Casting::Safe => can_cast_safely(from, to),
+        Casting::SameKind => matches!(
+            (from.type_char, to.type_char),
+            (Bool | Int | Uint, Int | Uint | Float) | (Float, Float),
+        ),
+    }
+}
+
+macro_rules! define_cast_reader {
+    ($($Variant:ident($T:ty),)*) => {
+        // Reads the file's own type, so that each value can be converted.
+        enum Source<R: io::Read> {
+            $($Variant(NpyReader<$T, R>),)*
+        }
+
+        impl<R: io::Read> Source<R> {
+            #[allow(clippy::result_large_err)]  // the file is only returned for an error message
+            fn new(npy: NpyFile<R>) -> Result<Self, NpyFile<R>> {
+                $(
+                    let npy = match npy.try_data::<$T>() {
+                        Ok(data) => return Ok(Source::$Variant(data)),
+                        Err(npy) => npy,
+                    };
+                )*
+                Err(npy)
+            }
+
+            fn next<T: CastTarget>(&mut self) -> Option<io::Result<T>> {
+                match self {
+                    $(Source::$Variant(data) => {
+                        let index = data.total_len() - NpyReader::len(data);
+                        data.next().map(|result| result.and_then(|x| cast_value(data, index, x)))
+                    },)*
+                }
+            }
+
+            fn inner(&self) -> &dyn SourceInfo {
+                match self {
+                    $(Source::$Variant(data) => data,)*
+                }
+            }
+
+            fn skip_records(&mut self, count: u64) -> io::Result<()> {
+                match self {
+                    $(Source::$Variant(data) => data.skip_records(count),)*
+                }
+            }
+        }
+    };
+}
+
+define_cast_reader! {
+    I8(i8), I16(i16), I32(i32), I64(i64),
+    U8(u8), U16(u16), U32(u32), U64(u64),
+    F32(f32), F64(f64), Bool(bool),
+}
+
+fn cast_value<S: CastSource + Deserialize, T: CastTarget, R: io::Read>(data: &NpyReader<S, R>, index: u64, x: S) -> io::Result<T> {
+    x.cast().ok_or_else(|| {
+        let err = Error::InvalidData(format!("array contains {}, which does not fit in {}", x, std::any::type_name::<T>()));
+        record_error(data.header(), index, err.into())
+    })
+}
+
+// The parts of an NpyReader that don't depend on its type.
+trait SourceInfo {
+    fn dtype(&self) -> DType;
+    fn total_len(&self) -> u64;
+    fn len(&self) -> u64;
+}
+
+impl<T: Deserialize, R: io::Read> SourceInfo for NpyReader<T, R> {
+    fn dtype(&self) -> DType { NpyReader::dtype(self) }
+    fn total_len(&self) -> u64 { NpyReader::total_len(self) }
+    fn len(&self) -> u64 { NpyReader::len(self) }
+}
+
+/// Iterator returned by [`NpyFile::data_cast`], which reads numbers of the file's dtype and converts them to `T`.
+///
+/// Like [`NpyReader`], this is an [`ExactSizeIterator`] (on 64-bit platforms) of `io::Result<T>`.
+pub struct CastReader<T: CastTarget, R: io::Read> {
+    source: Source<R>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: CastTarget, R: io::Read> CastReader<T, R> {
+    pub(crate) fn new(npy: NpyFile<R>, casting: Casting) -> io::Result<Self> {
+        let dtype = npy.dtype();
+        let target = TypeStr::with_auto_endianness(T::__TYPE_CHAR, T::__SIZE, None);
+        let reason = match &dtype {
+            DType::Plain(type_str) if is_allowed(type_str, &target, casting) => {
+                match Source::new(npy) {
+                    Ok(source) => return Ok(CastReader { source, _marker: PhantomData }),
+                    Err(_) => DTypeError::custom(format!("cannot read numbers of type {}", type_str)),
+                }
+            },
+            DType::Plain(type_str) => DTypeError::custom(format!("cannot cast {} to {} with {:?} casting", type_str, target, casting)),
+            _ => DTypeError::custom("only scalar dtypes can be cast"),
+        };
+        Err(Error::dtype_mismatch::<T>(&dtype, reason).into())
+    }
+
+    /// Get the dtype as written in the file.
+    pub fn dtype(&self) -> DType {
+        self.source.inner().dtype()
+    }
+
+    /// Returns the total number of records, including those that have already been read.
+    pub fn total_len(&self) -> u64 {
+        self.source.inner().total_len()
+    }
+
+    /// Get the remaining number of records that have not been read.
+    pub fn len(&self) -> u64 {
+        self.source.inner().len()
+    }
+
+    /// Returns `true` if there are no records left to read.  (i.e. [`Self::len`] is zero)
+    pub fn is_empty(&self) -> bool {
+        CastReader::len(self) == 0
+    }
+
+    /// Advance the read cursor past the next `count` records without converting them.  See [`NpyReader::skip_records`].
+    pub fn skip_records(&mut self, count: u64) -> io::Result<()> {
+        self.source.skip_records(count)
+    }
+}
+
+impl<T: CastTarget, R: io::Read> Iterator for CastReader<T, R> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.source.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = CastReader::len(self);
+        (len.try_into().unwrap_or(usize::MAX), usize::try_from(len).ok())
+    }
+}
+
+// (like NpyReader, only where the length of an array always fits in a `usize`)
+#[cfg(target_pointer_width = "64")]
+impl<T: CastTarget, R: io::Read> ExactSizeIterator for CastReader<T, R> {}
+
+#[cfg(test)]
+#[deny(unused)]
+mod tests {
+    use super::*;
+    use crate::WriterBuilder;
+
+    fn npy_bytes<T: crate::Serialize>(descr: &str, values: &[T]) -> Vec<u8> {
+        let dtype = DType::parse(descr).unwrap();
+        let mut bytes = vec![];
+        let mut writer = crate::WriteOptions::new().dtype(dtype).shape(&[values.len() as u64]).writer(&mut bytes).begin_nd().unwrap();
+        writer.extend(values).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    fn cast<T: CastTarget>(bytes: &[u8], casting: Casting) -> io::Result<Vec<T>> {
+        NpyFile::new(bytes)?.into_vec_cast::<T>(casting)
+    }
+
+    #[test]
+    fn safe() {
+        let ints = npy_bytes("'>i4'", &[-1_i32, 2, i32::MAX]);
+        assert_eq!(cast::<i64>(&ints, Casting::Safe).unwrap(), vec![-1, 2, i32::MAX as i64]);
+        assert_eq!(cast::<f64>(&ints, Casting::Safe).unwrap(), vec![-1.0, 2.0, i32::MAX as f64]);
+        assert!(cast::<u64>(&ints, Casting::Safe).is_err());
+        assert!(cast::<i16>(&ints, Casting::Safe).is_err());
+        assert!(cast::<f32>(&ints, Casting::Safe).is_err());
+
+        let floats = npy_bytes("'<f4'", &[0.5_f32, -3.25]);
+        assert_eq!(cast::<f64>(&floats, Casting::Safe).unwrap(), vec![0.5, -3.25]);
+        assert!(cast::<i64>(&floats, Casting::Safe).is_err());
+
+        let bools = npy_bytes("'|b1'", &[true, false]);
+        assert_eq!(cast::<u8>(&bools, Casting::Safe).unwrap(), vec![1, 0]);
+    }
+
+    #[test]
+    fn same_kind() {
+        let ints = npy_bytes("'<i8'", &[3_i64, 300]);
+        assert_eq!(cast::<u64>(&ints, Casting::SameKind).unwrap(), vec![3, 300]);
+        assert_eq!(cast::<usize>(&ints, Casting::SameKind).unwrap(), vec![3, 300]);
+        assert_eq!(cast::<f32>(&ints, Casting::SameKind).unwrap(), vec![3.0, 300.0]);
+
+        let err = cast::<u8>(&ints, Casting::SameKind).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("contains 300, which does not fit in u8"), "{}", err);
+
+        let negative = npy_bytes("'<i4'", &[-1_i32]);
+        assert!(cast::<u32>(&negative, Casting::SameKind).is_err());
+
+        let floats = npy_bytes("'<f8'", &[0.1_f64]);
+        assert_eq!(cast::<f32>(&floats, Casting::SameKind).unwrap(), vec![0.1_f32]);
+        assert!(cast::<i64>(&floats, Casting::SameKind).is_err());
+    }
+
+    #[test]
+    fn unsupported_dtypes() {
+        let times = npy_bytes("'<M8[s]'", &[1_i64]);
+        assert!(cast::<i64>(&times, Casting::SameKind).is_err());
+
+        let complex = npy_bytes("'<c16'", &[(1.0_f64, 0.0_f64)]);
+        assert!(cast::<f64>(&complex, Casting::SameKind).is_err());
+    }
+
+    #[test]
+    fn reader() {
+        let ints = npy_bytes("'<u2'", &[1_u16, 2, 3, 4]);
+        let mut reader = NpyFile::new(&ints[..]).unwrap().data_cast::<i32>(Casting::Safe).unwrap();
+        assert_eq!(reader.dtype(), DType::parse("'<u2'").unwrap());
+        reader.skip_records(1).unwrap();
+        assert_eq!(reader.len(), 3);
+        assert_eq!(reader.collect::<io::Result<Vec<_>>>().unwrap(), vec![2, 3, 4]);
+    }
+}
@@ -0,0 +1,407 @@
+//! An append-only log of named NPY arrays in a single file.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::read::{NpyFile, ReadOptions};
+use crate::serialize::AutoSerialize;
+use crate::write::{WriteOptions, WriterBuilder};
+
+const MAGIC: &[u8; 8] = b"NPYZLOG\x01";
+const FOOTER_MAGIC: &[u8; 8] = b"NPYZIDX\x01";
+// payload length, timestamp, name length
+const RECORD_HEADER_LEN: u64 = 8 + 8 + 4;
+// index start, footer magic
+const TRAILER_LEN: u64 = 8 + 8;
+
+/// A file that arrays are appended to, each with a name and a timestamp, for cheap periodic checkpoints.
+///
+/// Rewriting an NPZ file to add an array costs as much as writing all of the arrays in it.  Appending to a
+/// `CheckpointLog` only writes the new array, followed by a small index of all snapshots, so the cost of a
+/// checkpoint does not grow with the number of earlier checkpoints.  Every snapshot stays readable, so
+/// the log also records the history of each array.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// # let path = std::env::temp_dir().join(format!("npyz-checkpoint-doctest-{}.log", std::process::id()));
+/// # let _ = std::fs::remove_file(&path);
+/// let mut log = npyz::CheckpointLog::open(&path)?;
+/// for step in 0..3 {
+///     let weights = vec![step as f32; 10];
+///     log.append("weights", &[2, 5], &weights)?;
+/// }
+/// log.append("step", &[], &[3_u64])?;
+///
+/// let log = npyz::CheckpointLog::open(&path)?;
+/// assert_eq!(log.entries().len(), 4);
+/// let latest = log.latest("weights").unwrap();
+/// assert_eq!(log.read(latest)?.into_vec::<f32>()?, vec![2.0; 10]);
+/// # std::fs::remove_file(&path)?;
+/// # Ok(()) }
+/// ```
+///
+/// # Format
+///
+/// The file begins with the 8 bytes `NPYZLOG\x01`.  Each snapshot is a record made of the length of its
+/// NPY data (a little-endian `u64`), its timestamp in nanoseconds since the Unix epoch (`u64`), the length
+/// of its name (`u32`), the name in UTF-8, and the complete NPY file.  After the last record comes an index
+/// with the location of every record, and finally the offset of the index (`u64`) and the 8 bytes `NPYZIDX\x01`.
+///
+/// Each append overwrites the index.  If the index is missing or damaged, e.g. because the process was
+/// killed during an append, [`Self::open`] scans the records instead, and the next append discards
+/// anything after the last complete record.
+pub struct CheckpointLog {
+    file: File,
+    entries: Vec<CheckpointEntry>,
+    // the end of the last complete record
+    data_end: u64,
+    options: ReadOptions,
+}
+
+/// A snapshot in a [`CheckpointLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointEntry {
+    name: String,
+    timestamp: SystemTime,
+    // the location of the NPY data
+    offset: u64,
+    len: u64,
+}
+
+impl CheckpointEntry {
+    /// Get the name the array was appended under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the time when the array was appended.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// Get the length in bytes of the NPY data, including its header.
+    pub fn npy_len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl CheckpointLog {
+    /// Open a log for reading and appending, creating it if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let file_len = file.seek(SeekFrom::End(0))?;
+        if file_len == 0 {
+            file.write_all(MAGIC)?;
+            let mut log = CheckpointLog { file, entries: vec![], data_end: MAGIC.len() as u64, options: ReadOptions::default() };
+            log.write_index()?;
+            return Ok(log);
+        }
+
+        let mut magic = [0; 8];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut magic).map_err(|_| bad_log("the file is too short"))?;
+        if &magic != MAGIC {
+            return Err(bad_log("the file does not begin with the magic bytes"));
+        }
+        let (entries, data_end) = match read_index(&mut file, file_len)? {
+            Some(found) => found,
+            None => scan_records(&mut file, file_len)?,
+        };
+        Ok(CheckpointLog { file, entries, data_end, options: ReadOptions::default() })
+    }
+
+    /// Set the options used when reading the arrays in the log.
+    pub fn with_read_options(mut self, options: ReadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Get all snapshots, in the order they were appended.
+    pub fn entries(&self) -> &[CheckpointEntry] {
+        &self.entries
+    }
+
+    /// Get the most recent snapshot with the given name.
+    pub fn latest(&self, name: &str) -> Option<&CheckpointEntry> {
+        self.entries.iter().rev().find(|entry| entry.name == name)
+    }
+
+    /// Append an array with the given shape, timestamped with the current time, and return its entry.
+    ///
+    /// The index is rewritten and the file is synced to disk before this returns.  If an error occurs,
+    /// the log is left as it was before.
+    pub fn append<T: AutoSerialize>(&mut self, name: &str, shape: &[u64], data: &[T]) -> io::Result<&CheckpointEntry> {
+        self.append_with(name, |writer| {
+            let mut npy = WriteOptions::new().default_dtype().shape(shape).writer(writer).begin_nd()?;
+            npy.extend_from_slice(data)?;
+            npy.finish()
+        })
+    }
+
+    /// Append an NPY file without decoding its elements, timestamped with the current time, and return its entry.
+    ///
+    /// This works for any dtype.  See [`NpyFile::copy_raw_to`].
+    pub fn append_npy<R: Read>(&mut self, name: &str, npy: NpyFile<R>) -> io::Result<&CheckpointEntry> {
+        self.append_with(name, |writer| npy.copy_raw_to(writer))
+    }
+
+    /// Read a snapshot.
+    pub fn read(&self, entry: &CheckpointEntry) -> io::Result<NpyFile<io::Take<io::BufReader<&File>>>> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let reader = io::BufReader::with_capacity(self.options.get_buffer_size(), file).take(entry.len);
+        let npy = NpyFile::with_options(reader, &self.options).map_err(|e| Error::in_member(e, &entry.name))?;
+        Ok(npy.with_member_name(&entry.name))
+    }
+
+    fn append_with(&mut self, name: &str, write_npy: impl FnOnce(&mut BufWriter<&File>) -> io::Result<()>) -> io::Result<&CheckpointEntry> {
+        let name_len = u32::try_from(name.len()).map_err(|_| Error::InvalidInput("the name is too long".to_string()))?;
+        let timestamp = SystemTime::now();
+        let result = (|| {
+            // discard the index, and anything left by an earlier failure
+            self.file.set_len(self.data_end)?;
+            let mut file = &self.file;
+            file.seek(SeekFrom::Start(self.data_end))?;
+
+            let mut writer = BufWriter::new(file);
+            // (the length is filled in below, so that an interrupted record is never complete)
+            writer.write_all(&u64::MAX.to_le_bytes())?;
+            writer.write_all(&nanos_since_epoch(timestamp).to_le_bytes())?;
+            writer.write_all(&name_len.to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+            write_npy(&mut writer)?;
+            writer.flush()?;
+
+            let offset = self.data_end + RECORD_HEADER_LEN + name.len() as u64;
+            let end = file.stream_position()?;
+            file.seek(SeekFrom::Start(self.data_end))?;
+            file.write_all(&(end - offset).to_le_bytes())?;
+            Ok(CheckpointEntry { name: name.to_string(), timestamp, offset, len: end - offset })
+        })();
+
+        match result {
+            Ok(entry) => {
+                self.data_end = entry.offset + entry.len;
+                self.entries.push(entry);
+                self.write_index()?;
+                Ok(self.entries.last().expect("just pushed"))
+            },
+            Err(e) => {
+                // put the index back, so that the log can be opened without scanning it
+                let _ = self.file.set_len(self.data_end).and_then(|()| self.write_index());
+                Err(e)
+            },
+        }
+    }
+
+    fn write_index(&mut self) -> io::Result<()> {
+        let mut index = vec![];
+        index.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        for entry in &self.entries {
+            index.extend_from_slice(&entry.offset.to_le_bytes());
+            index.extend_from_slice(&entry.len.to_le_bytes());
+            index.extend_from_slice(&nanos_since_epoch(entry.timestamp).to_le_bytes());
+            index.extend_from_slice(&(entry.name.len() as u32).to_le_bytes());
+            index.extend_from_slice(entry.name.as_bytes());
+        }
+        index.extend_from_slice(&self.data_end.to_le_bytes());
+        index.extend_from_slice(FOOTER_MAGIC);
+
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(self.data_end))?;
+        file.write_all(&index)?;
+        self.file.set_len(self.data_end + index.len() as u64)?;
+        self.file.sync_data()
+    }
+}
+
+impl std::fmt::Debug for CheckpointLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("CheckpointLog").field("entries", &self.entries).finish_non_exhaustive()
+    }
+}
+
+// Read the index at the end of the file, or return `None` if it is missing or damaged.
+fn read_index(file: &mut File, file_len: u64) -> io::Result<Option<(Vec<CheckpointEntry>, u64)>> {
+    if file_len < MAGIC.len() as u64 + TRAILER_LEN {
+        return Ok(None);
+    }
+    let mut trailer = [0; TRAILER_LEN as usize];
+    file.seek(SeekFrom::Start(file_len - TRAILER_LEN))?;
+    file.read_exact(&mut trailer)?;
+    let index_start = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+    if &trailer[8..] != FOOTER_MAGIC || index_start < MAGIC.len() as u64 || index_start > file_len - TRAILER_LEN {
+        return Ok(None);
+    }
+
+    let mut index = vec![0; (file_len - TRAILER_LEN - index_start) as usize];
+    file.seek(SeekFrom::Start(index_start))?;
+    file.read_exact(&mut index)?;
+    let parse = || -> Option<Vec<CheckpointEntry>> {
+        let mut index = &index[..];
+        let count = take_u64(&mut index)?;
+        let mut entries = vec![];
+        for _ in 0..count {
+            let offset = take_u64(&mut index)?;
+            let len = take_u64(&mut index)?;
+            let timestamp = UNIX_EPOCH + Duration::from_nanos(take_u64(&mut index)?);
+            let name_len = take_u32(&mut index)? as usize;
+            let name = String::from_utf8(take(&mut index, name_len)?.to_vec()).ok()?;
+            if offset.checked_add(len)? > index_start {
+                return None;
+            }
+            entries.push(CheckpointEntry { name, timestamp, offset, len });
+        }
+        index.is_empty().then_some(entries)
+    };
+    Ok(parse().map(|entries| (entries, index_start)))
+}
+
+// Find the complete records by reading them from the start of the file.
+fn scan_records(file: &mut File, file_len: u64) -> io::Result<(Vec<CheckpointEntry>, u64)> {
+    let mut reader = io::BufReader::new(&*file);
+    let mut pos = reader.seek(SeekFrom::Start(MAGIC.len() as u64))?;
+    let mut entries = vec![];
+    loop {
+        let mut header = [0; RECORD_HEADER_LEN as usize];
+        if file_len - pos < RECORD_HEADER_LEN {
+            break;
+        }
+        reader.read_exact(&mut header)?;
+        let len = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let nanos = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let name_len = u32::from_le_bytes(header[16..].try_into().unwrap()) as u64;
+        let offset = pos + RECORD_HEADER_LEN + name_len;
+        let end = match offset.checked_add(len) {
+            Some(end) if end <= file_len => end,
+            _ => break,
+        };
+        let mut name = vec![0; name_len as usize];
+        reader.read_exact(&mut name)?;
+        let name = match String::from_utf8(name) {
+            Ok(name) => name,
+            Err(_) => break,
+        };
+        entries.push(CheckpointEntry { name, timestamp: UNIX_EPOCH + Duration::from_nanos(nanos), offset, len });
+        pos = reader.seek(SeekFrom::Start(end))?;
+    }
+    Ok((entries, pos))
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if bytes.len() < len {
+        return None;
+    }
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+    Some(head)
+}
+
+fn take_u64(bytes: &mut &[u8]) -> Option<u64> {
+    take(bytes, 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn take_u32(bytes: &mut &[u8]) -> Option<u32> {
+    take(bytes, 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn nanos_since_epoch(time: SystemTime) -> u64 {
+    // (times before the epoch are recorded as the epoch)
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+}
+
+fn bad_log(reason: &str) -> io::Error {
+    Error::InvalidData(format!("not a checkpoint log: {}", reason)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("npyz-checkpoint-{}-{}.log", name, std::process::id()))
+    }
+
+    #[test]
+    fn append_and_reopen() -> io::Result<()> {
+        let path = temp_path("reopen");
+        let _ = std::fs::remove_file(&path);
+        let mut log = CheckpointLog::open(&path)?;
+        assert!(log.entries().is_empty());
+        for step in 0..5_i64 {
+            let entry = log.append("x", &[3], &[step, step + 1, step + 2])?;
+            assert_eq!(entry.name(), "x");
+        }
+        log.append("y", &[2, 1], &[1.5_f64, 2.5])?;
+        let bytes = std::fs::read("test-data/structured.npy")?;
+        log.append_npy("structured", NpyFile::new(&bytes[..])?)?;
+        drop(log);
+
+        let log = CheckpointLog::open(&path)?;
+        let names = log.entries().iter().map(|entry| entry.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["x", "x", "x", "x", "x", "y", "structured"]);
+        assert!(log.entries().windows(2).all(|w| w[0].timestamp() <= w[1].timestamp()));
+        assert_eq!(log.read(&log.entries()[1])?.into_vec::<i64>()?, vec![1, 2, 3]);
+        assert_eq!(log.read(log.latest("x").unwrap())?.into_vec::<i64>()?, vec![4, 5, 6]);
+        assert_eq!(log.read(log.latest("y").unwrap())?.shape(), &[2, 1]);
+        let mut copy = vec![];
+        log.read(log.latest("structured").unwrap())?.copy_raw_to(&mut copy)?;
+        assert_eq!(copy, bytes);
+        assert!(log.latest("z").is_none());
+        std::fs::remove_file(&path)
+    }
+
+    #[test]
+    fn recovers_without_index() -> io::Result<()> {
+        let path = temp_path("recover");
+        let _ = std::fs::remove_file(&path);
+        let mut log = CheckpointLog::open(&path)?;
+        log.append("a", &[2], &[1_u8, 2])?;
+        log.append("b", &[1], &[3_u8])?;
+        let data_end = log.data_end;
+        drop(log);
+
+        // an interrupted append: the index is gone, and the last record is incomplete
+        let mut file = OpenOptions::new().write(true).open(&path)?;
+        file.set_len(data_end)?;
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(&u64::MAX.to_le_bytes())?;
+        file.write_all(&[0; 15])?;
+        drop(file);
+
+        let mut log = CheckpointLog::open(&path)?;
+        assert_eq!(log.entries().len(), 2);
+        assert_eq!(log.read(log.latest("b").unwrap())?.into_vec::<u8>()?, vec![3]);
+        log.append("c", &[1], &[4_u8])?;
+        drop(log);
+
+        let log = CheckpointLog::open(&path)?;
+        assert_eq!(log.entries().iter().map(|entry| entry.name()).collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(log.read(log.latest("c").unwrap())?.into_vec::<u8>()?, vec![4]);
+        std::fs::remove_file(&path)
+    }
+
+    #[test]
+    fn failed_append() -> io::Result<()> {
+        let path = temp_path("failed");
+        let _ = std::fs::remove_file(&path);
+        let mut log = CheckpointLog::open(&path)?;
+        log.append("a", &[2], &[1_i32, 2])?;
+        // the shape does not match the data
+        assert!(log.append("b", &[3], &[1_i32, 2]).is_err());
+        assert_eq!(log.entries().len(), 1);
+        log.append("c", &[1], &[3_i32])?;
+        drop(log);
+
+        let log = CheckpointLog::open(&path)?;
+        assert_eq!(log.entries().iter().map(|entry| entry.name()).collect::<Vec<_>>(), ["a", "c"]);
+        assert_eq!(log.read(log.latest("a").unwrap())?.into_vec::<i32>()?, vec![1, 2]);
+
+        std::fs::write(&path, b"not a log")?;
+        assert_eq!(CheckpointLog::open(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path)
+    }
+}
@@ -0,0 +1,221 @@
+//! Adding fields to a structured NPY file without reading it into memory.
+
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+
+use crate::dynamic::{self, DynValue};
+use crate::error::Error;
+use crate::header::{DType, Field};
+use crate::read::{NpyFile, RawRecords};
+
+const WRITE_CHUNK_SIZE: usize = 1 << 16;
+
+/// The values of a field to add with [`append_columns`].
+pub struct Column<'a> {
+    name: String,
+    dtype: DType,
+    source: Source<'a>,
+}
+
+enum Source<'a> {
+    Npy(Box<RawRecords<Box<dyn Read + 'a>>>),
+    Values(Box<dyn Iterator<Item = DynValue> + 'a>),
+}
+
+impl<'a> Column<'a> {
+    /// Take the values of a field from another NPY file, which must have the same shape as the input.
+    ///
+    /// The field gets the dtype of the file.  Its data is copied without being decoded.
+    pub fn from_npy<R: Read + 'a>(name: &str, npy: NpyFile<R>) -> Self {
+        Column { name: name.to_string(), dtype: npy.dtype(), source: Source::Npy(Box::new(npy.boxed().raw_records())) }
+    }
+
+    /// Compute the values of a field, one for each record of the input in the order they are stored.
+    ///
+    /// Each value is checked against the dtype as described for [`DynValue`].
+    pub fn from_values(name: &str, dtype: DType, values: impl IntoIterator<Item = DynValue> + 'a) -> Self {
+        Column { name: name.to_string(), dtype, source: Source::Values(Box::new(values.into_iter())) }
+    }
+
+    fn check(&self, header: &crate::NpyHeader, existing: &mut HashSet<String>) -> io::Result<()> {
+        if self.name.is_empty() {
+            return Err(Error::InvalidInput("the name of a column must not be empty".to_string()).into());
+        }
+        if !existing.insert(self.name.clone()) {
+            return Err(Error::InvalidInput(format!("a field named '{}' already exists", self.name)).into());
+        }
+        match &self.source {
+            Source::Npy(records) => {
+                let column = records.header();
+                if column.shape() != header.shape() || (column.order() != header.order() && header.shape().len() > 1) {
+                    return Err(Error::InvalidInput(format!(
+                        "column '{}' has shape {:?} ({:?} order), but the input has shape {:?} ({:?} order)",
+                        self.name, column.shape(), column.order(), header.shape(), header.order(),
+                    )).into());
+                }
+            },
+            Source::Values(_) => dynamic::check_supported(&self.dtype)?,
+        }
+        Ok(())
+    }
+
+    fn append_next(&mut self, index: u64, out: &mut Vec<u8>) -> io::Result<()> {
+        match &mut self.source {
+            Source::Npy(records) => match records.next_record()? {
+                Some(record) => out.extend_from_slice(record),
+                None => return Err(Error::Truncated.into()),
+            },
+            Source::Values(values) => match values.next() {
+                Some(value) => dynamic::encode(&self.dtype, &value, out).map_err(|e| Error::__in_field(e, &self.name))?,
+                None => {
+                    let msg = format!("column '{}' has only {} values", self.name, index);
+                    return Err(Error::InvalidInput(msg).into());
+                },
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Copy a structured NPY file, adding fields at the end of each record.
+///
+/// The input must have a record dtype.  Its records are copied without being decoded, a small buffer at
+/// a time, together with the values of each [`Column`], so this works for any size of file.  The output
+/// has the shape, order and [extra keys][`crate::NpyHeader::extra_keys`] of the input.
+///
+/// An error is returned if a column has a different number of values than the input has records, if
+/// a value does not fit the dtype of its column, or if a field name is used twice.  Errors in the
+/// values of a column are only found as they are written, so the output is incomplete after an error.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{Column, DType, DynValue};
+///
+/// let dtype = DType::parse("[('x', '<f8'), ('y', '<f8')]")?;
+/// let points = (0..5).map(|i| DynValue::Record(vec![(i as f64).into(), (2.0 * i as f64).into()]));
+/// let mut input = vec![];
+/// npyz::write_dyn(&mut input, &dtype, &[5], points)?;
+///
+/// // a column of computed values, and one from another file
+/// let ids = (100..105).map(DynValue::Uint);
+/// let mut weights = vec![];
+/// npyz::write_dyn(&mut weights, &DType::parse("'<f4'")?, &[5], (0..5).map(|i| DynValue::Float(i as f64 / 4.0)))?;
+/// let columns = vec![
+///     Column::from_values("id", DType::parse("'<u4'")?, ids),
+///     Column::from_npy("weight", npyz::NpyFile::new(&weights[..])?),
+/// ];
+/// let mut output = vec![];
+/// npyz::append_columns(&input[..], &mut output, columns)?;
+///
+/// let npy = npyz::NpyFile::new(&output[..])?;
+/// assert_eq!(npy.dtype(), DType::parse("[('x', '<f8'), ('y', '<f8'), ('id', '<u4'), ('weight', '<f4')]")?);
+/// # Ok(()) }
+/// ```
+pub fn append_columns<'a>(input: impl Read, mut output: impl Write, columns: impl IntoIterator<Item = Column<'a>>) -> io::Result<()> {
+    let mut records = NpyFile::new(input)?.raw_records();
+    let header = records.header().clone();
+    let mut fields = match header.dtype() {
+        DType::Record(fields) => fields,
+        dtype => return Err(Error::InvalidInput(format!("expected a structured array, got dtype {}", dtype.descr())).into()),
+    };
+    let mut columns = columns.into_iter().collect::<Vec<_>>();
+    let mut names = fields.iter().map(|field| field.name.clone()).filter(|name| !name.is_empty()).collect();
+    for column in &columns {
+        column.check(&header, &mut names)?;
+        fields.push(Field { name: column.name.clone(), dtype: column.dtype.clone() });
+    }
+    let dtype = DType::Record(fields);
+    output.write_all(&crate::write::header_bytes(&dtype, header.order(), header.shape(), header.extra_keys())?)?;
+
+    let mut buf = vec![];
+    let mut index = 0;
+    while let Some(record) = records.next_record()? {
+        buf.extend_from_slice(record);
+        for column in &mut columns {
+            column.append_next(index, &mut buf).map_err(|e| Error::at_record(e, index, None))?;
+        }
+        if buf.len() >= WRITE_CHUNK_SIZE {
+            output.write_all(&buf)?;
+            buf.clear();
+        }
+        index += 1;
+    }
+    output.write_all(&buf)?;
+
+    for column in &mut columns {
+        if let Source::Values(values) = &mut column.source {
+            if values.next().is_some() {
+                let msg = format!("column '{}' has more than {} values", column.name, index);
+                return Err(Error::InvalidInput(msg).into());
+            }
+        }
+    }
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dtype: &str, shape: &[u64], rows: Vec<DynValue>) -> Vec<u8> {
+        let mut out = vec![];
+        crate::write_dyn(&mut out, &DType::parse(dtype).unwrap(), shape, rows).unwrap();
+        out
+    }
+
+    fn records(n: i64) -> Vec<u8> {
+        write("[('a', '<i4'), ('b', '|S2')]", &[n as u64], (0..n).map(|i| DynValue::Record(vec![i.into(), DynValue::Bytes(vec![b'x'; (i % 3) as usize])])).collect())
+    }
+
+    #[test]
+    fn append() {
+        // enough records for several chunks of output
+        let n = 20_000;
+        let input = records(n);
+        let ids = write("'>u8'", &[n as u64], (0..n as u64).map(DynValue::Uint).collect());
+        let columns = vec![
+            Column::from_npy("id", NpyFile::new(&ids[..]).unwrap()),
+            Column::from_values("flag", DType::parse("'|b1'").unwrap(), (0..n).map(|i| DynValue::Bool(i % 2 == 0))),
+        ];
+        let mut output = vec![];
+        append_columns(&input[..], &mut output, columns).unwrap();
+
+        let npy = NpyFile::new(&output[..]).unwrap();
+        assert_eq!(npy.dtype(), DType::parse("[('a', '<i4'), ('b', '|S2'), ('id', '>u8'), ('flag', '|b1')]").unwrap());
+        assert_eq!(npy.shape(), &[n as u64]);
+        let mut records = npy.raw_records();
+        let mut index = 0u64;
+        while let Some(record) = records.next_record().unwrap() {
+            assert_eq!(&record[..4], &(index as i32).to_le_bytes());
+            assert_eq!(&record[6..14], &index.to_be_bytes());
+            assert_eq!(record[14], 1 - (index % 2) as u8);
+            index += 1;
+        }
+        assert_eq!(index, n as u64);
+    }
+
+    #[test]
+    fn errors() {
+        let input = records(3);
+        let check = |columns: Vec<Column<'_>>, expected: &str| {
+            let err = append_columns(&input[..], &mut vec![], columns).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+        };
+        let int = || DType::parse("'<i4'").unwrap();
+        let values = |n: i64| (0..n).map(DynValue::Int).collect::<Vec<_>>();
+
+        check(vec![Column::from_values("c", int(), values(2))], "only 2 values");
+        check(vec![Column::from_values("c", int(), values(4))], "more than 3 values");
+        check(vec![Column::from_values("a", int(), values(3))], "'a' already exists");
+        check(vec![Column::from_values("", int(), values(3))], "must not be empty");
+        check(vec![Column::from_values("c", int(), values(3)), Column::from_values("c", int(), values(3))], "'c' already exists");
+        check(vec![Column::from_values("c", int(), vec![DynValue::Int(0), DynValue::Str("x".into()), DynValue::Int(2)])], "field 'c'");
+
+        let other = write("'<i4'", &[4], values(4));
+        check(vec![Column::from_npy("c", NpyFile::new(&other[..]).unwrap())], "has shape [4]");
+
+        let plain = write("'<i4'", &[3], values(3));
+        let err = append_columns(&plain[..], &mut vec![], vec![]).unwrap_err();
+        assert!(err.to_string().contains("expected a structured array"), "{}", err);
+    }
+}
@@ -0,0 +1,596 @@
+//! Comparing the contents of two NPY files.
+
+use std::fmt;
+use std::io;
+
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::{NpyHeader, Order};
+use crate::type_str::{Endianness, TypeChar, TypeStr};
+
+/// Tolerance used by [`compare`] to decide whether two numeric elements are equal.
+///
+/// Two elements `a` and `b` are considered equal when `|a - b| <= atol + rtol * |b|`,
+/// which is the same test performed by `numpy.isclose`.
+///
+/// The [`Default`] impl uses numpy's defaults of `rtol = 1e-5`, `atol = 1e-8` and `nan_equal = false`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tolerance {
+    /// Relative tolerance.  This is scaled by the magnitude of the element in the second file.
+    pub rtol: f64,
+    /// Absolute tolerance.
+    pub atol: f64,
+    /// If `true`, NaN in both files at the same index is considered equal.  NaT (not-a-time) in
+    /// `datetime64` and `timedelta64` data is treated like NaN, so by default it is never equal.
+    pub nan_equal: bool,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance { rtol: 1e-5, atol: 1e-8, nan_equal: false }
+    }
+}
+
+impl Tolerance {
+    /// A tolerance that requires numeric elements to be exactly equal.
+    pub fn exact() -> Self {
+        Tolerance { rtol: 0.0, atol: 0.0, nan_equal: false }
+    }
+}
+
+/// The value of a single element, as reported in a [`Mismatch`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementValue {
+    /// A boolean, integer, or time value.
+    Int(i128),
+    /// A float value.  (NaT in time values also becomes NaN)
+    Real(f64),
+    /// A complex value as `(re, im)`.
+    Complex(f64, f64),
+    /// The raw bytes of an element that has no numeric interpretation. (e.g. strings and records)
+    Bytes(Vec<u8>),
+}
+
+impl fmt::Display for ElementValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElementValue::Int(x) => write!(f, "{}", x),
+            ElementValue::Real(x) => write!(f, "{}", x),
+            ElementValue::Complex(re, im) => write!(f, "{}{:+}j", re, im),
+            ElementValue::Bytes(bytes) => write!(f, "{:02x?}", bytes),
+        }
+    }
+}
+
+/// A single pair of unequal elements found by [`compare`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// The index of the element along each axis.
+    pub index: Vec<u64>,
+    /// The element from the first file.
+    pub a: ElementValue,
+    /// The element from the second file.
+    pub b: ElementValue,
+    /// The absolute difference between the elements.
+    ///
+    /// This is infinite if either of them is NaN, or if they are non-numeric.
+    pub abs_diff: f64,
+}
+
+/// The result of [`compare`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    /// The dtypes of the two files, if they are different.
+    pub dtype_difference: Option<(DType, DType)>,
+    /// The shapes of the two files, if they are different.
+    ///
+    /// When this is `Some`, no elements are compared.
+    pub shape_difference: Option<(Vec<u64>, Vec<u64>)>,
+    /// The number of elements that were compared.
+    pub num_compared: u64,
+    /// The number of elements that were not equal under the tolerance.
+    pub num_mismatches: u64,
+    /// The first mismatch, in C order.
+    pub first_mismatch: Option<Mismatch>,
+    /// The mismatch with the largest [`Mismatch::abs_diff`].  (ties go to the earliest in C order)
+    pub worst_mismatch: Option<Mismatch>,
+}
+
+impl Comparison {
+    /// Returns `true` if the files have the same dtype and shape, and all elements are equal under the tolerance.
+    pub fn is_equal(&self) -> bool {
+        self.dtype_difference.is_none() && self.shape_difference.is_none() && self.num_mismatches == 0
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_equal() {
+            return write!(f, "arrays are equal ({} elements compared)", self.num_compared);
+        }
+
+        let mut lines = vec![];
+        if let Some((a, b)) = &self.dtype_difference {
+            lines.push(format!("dtype differs: {} vs {}", a.descr(), b.descr()));
+        }
+        if let Some((a, b)) = &self.shape_difference {
+            lines.push(format!("shape differs: {:?} vs {:?}", a, b));
+        }
+        if self.num_mismatches > 0 {
+            lines.push(format!("{} of {} elements differ", self.num_mismatches, self.num_compared));
+        }
+        for (label, mismatch) in [("first", &self.first_mismatch), ("worst", &self.worst_mismatch)] {
+            if let Some(Mismatch { index, a, b, abs_diff }) = mismatch {
+                lines.push(format!("{} mismatch at {:?}: {} vs {} (abs diff {})", label, index, a, b, abs_diff));
+            }
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Compare the contents of two NPY files.
+///
+/// Both readers must initially be at the beginning of an NPY file.  Elements are streamed from
+/// both files in lockstep, so the files do not need to fit in memory.
+///
+/// **The exception is when the files have the same shape but a different [`Order`].**  The elements of `b` are
+/// then needed in a different order than they are stored, so all of the data of `b` is read into memory
+/// before comparing.
+///
+/// Numeric dtypes of different types or byte orders are compared by value (the difference is still
+/// reported in [`Comparison::dtype_difference`]).  Integers are compared exactly, even beyond the precision
+/// of `f64`.  `datetime64` and `timedelta64` values are only compared against the same type in the same
+/// units.  Other dtypes are compared bytewise, and only if the dtypes are identical.
+///
+/// Returns [`Error::InvalidInput`] if the dtype is an extended precision float or complex number (`f16` or `c32`)
+/// and the tolerance is not [`Tolerance::exact`], as these can only be compared bytewise.
+///
+/// ```
+/// # use npyz::WriterBuilder;
+/// # fn to_bytes(data: &[f64]) -> std::io::Result<Vec<u8>> {
+/// #     let mut buf = vec![];
+/// #     let mut writer = npyz::WriteOptions::new().default_dtype().shape(&[data.len() as u64]).writer(&mut buf).begin_nd()?;
+/// #     writer.extend(data)?;
+/// #     writer.finish()?;
+/// #     Ok(buf)
+/// # }
+/// # fn main() -> std::io::Result<()> {
+/// let a = to_bytes(&[1.0, 2.0, 3.0])?;
+/// let b = to_bytes(&[1.0, 2.0, 3.5])?;
+///
+/// let report = npyz::compare(&a[..], &b[..], npyz::Tolerance::default())?;
+/// assert!(!report.is_equal());
+/// assert_eq!(report.num_mismatches, 1);
+/// assert_eq!(report.first_mismatch.unwrap().index, vec![2]);
+/// # Ok(()) }
+/// ```
+pub fn compare(mut a: impl io::Read, mut b: impl io::Read, tolerance: Tolerance) -> io::Result<Comparison> {
+    let header_a = NpyHeader::from_reader(&mut a)?;
+    let header_b = NpyHeader::from_reader(&mut b)?;
+
+    let dtype_a = header_a.dtype();
+    let dtype_b = header_b.dtype();
+    let mut comparison = Comparison {
+        dtype_difference: None,
+        shape_difference: None,
+        num_compared: 0,
+        num_mismatches: 0,
+        first_mismatch: None,
+        worst_mismatch: None,
+    };
+    if dtype_a != dtype_b {
+        comparison.dtype_difference = Some((dtype_a.clone(), dtype_b.clone()));
+    }
+    if header_a.shape() != header_b.shape() {
+        comparison.shape_difference = Some((header_a.shape().to_vec(), header_b.shape().to_vec()));
+        return Ok(comparison);
+    }
+
+    let kind = match (ElementKind::of(&dtype_a), ElementKind::of(&dtype_b)) {
+        // ticks are only comparable between the same kind of time in the same units
+        (Some(ElementKind::Time { .. }), _) | (_, Some(ElementKind::Time { .. })) if !same_time_type(&dtype_a, &dtype_b) => {
+            return Ok(comparison);
+        },
+        (Some(a), Some(b)) => (a, b),
+        _ if dtype_a == dtype_b => {
+            // (extended precision floats have no ElementKind)
+            if is_float(&dtype_a) && tolerance != Tolerance::exact() {
+                let msg = format!("dtype {} can only be compared bytewise, which requires Tolerance::exact()", dtype_a.descr());
+                return Err(Error::InvalidInput(msg).into());
+            }
+            (ElementKind::Bytes, ElementKind::Bytes)
+        },
+        // incomparable elements, the dtype difference says it all
+        _ => return Ok(comparison),
+    };
+    let size_a = item_size(&dtype_a)?;
+    let size_b = item_size(&dtype_b)?;
+
+    // If the orders differ, we must be able to jump around in b.
+    let b_data = match header_a.order() == header_b.order() {
+        true => None,
+        false => {
+            let mut data = vec![];
+            io::Read::read_to_end(&mut b, &mut data)?;
+            Some(data)
+        },
+    };
+
+    let mut buf_a = vec![0; size_a];
+    let mut buf_b = vec![0; size_b];
+    for index_a in 0..header_a.len() {
+        a.read_exact(&mut buf_a)?;
+        let index = unravel_index(index_a, header_a.shape(), header_a.strides());
+        let bytes_b = match &b_data {
+            None => {
+                b.read_exact(&mut buf_b)?;
+                &buf_b[..]
+            },
+            Some(data) => {
+                let index_b = index.iter().zip(header_b.strides()).map(|(i, s)| i * s).sum::<u64>() as usize;
+                data.get(index_b * size_b..(index_b + 1) * size_b).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer")
+                })?
+            },
+        };
+
+        let value_a = kind.0.decode(&buf_a);
+        let value_b = kind.1.decode(bytes_b);
+        comparison.num_compared += 1;
+        if let Some(abs_diff) = element_difference(&value_a, &value_b, tolerance) {
+            comparison.num_mismatches += 1;
+            let mismatch = Mismatch { index, a: value_a, b: value_b, abs_diff };
+            if comparison.worst_mismatch.as_ref().map_or(true, |worst| is_worse(&mismatch, worst, header_a.order())) {
+                comparison.worst_mismatch = Some(mismatch.clone());
+            }
+            if comparison.first_mismatch.as_ref().map_or(true, |first| mismatch.index < first.index) {
+                comparison.first_mismatch = Some(mismatch);
+            }
+        }
+    }
+    Ok(comparison)
+}
+
+fn is_float(dtype: &DType) -> bool {
+    dtype.as_scalar().is_some_and(|ty| matches!(ty.type_char(), TypeChar::Float | TypeChar::Complex))
+}
+
+fn same_time_type(a: &DType, b: &DType) -> bool {
+    match (a.as_scalar(), b.as_scalar()) {
+        (Some(a), Some(b)) => a.type_char() == b.type_char() && a.time_units == b.time_units,
+        _ => false,
+    }
+}
+
+fn item_size(dtype: &DType) -> io::Result<usize> {
+    dtype.num_bytes().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "dtype is larger than usize!")
+    })
+}
+
+// In fortran order, elements are not visited in C order, so "ties go to the earliest" must be checked explicitly.
+fn is_worse(new: &Mismatch, old: &Mismatch, order: Order) -> bool {
+    match order {
+        Order::C => new.abs_diff > old.abs_diff,
+        Order::Fortran => new.abs_diff > old.abs_diff || (new.abs_diff == old.abs_diff && new.index < old.index),
+    }
+}
+
+fn unravel_index(flat: u64, shape: &[u64], strides: &[u64]) -> Vec<u64> {
+    shape.iter().zip(strides).map(|(&dim, &stride)| flat / stride % dim).collect()
+}
+
+/// Returns `None` if the elements are equal, else the absolute difference.
+fn element_difference(a: &ElementValue, b: &ElementValue, tolerance: Tolerance) -> Option<f64> {
+    let (a, b) = match (a, b) {
+        (ElementValue::Bytes(a), ElementValue::Bytes(b)) => {
+            return if a == b { None } else { Some(f64::INFINITY) };
+        },
+        // not through f64, which cannot represent every i64 and u64
+        (&ElementValue::Int(a), &ElementValue::Int(b)) => {
+            let abs_diff = a.abs_diff(b) as f64;
+            return match a == b || abs_diff <= tolerance.atol + tolerance.rtol * b.unsigned_abs() as f64 {
+                true => None,
+                false => Some(abs_diff),
+            };
+        },
+        (a, b) => (as_complex(a), as_complex(b)),
+    };
+
+    let a_nan = a.0.is_nan() || a.1.is_nan();
+    let b_nan = b.0.is_nan() || b.1.is_nan();
+    if a_nan || b_nan {
+        return match a_nan && b_nan && tolerance.nan_equal {
+            true => None,
+            false => Some(f64::INFINITY),
+        };
+    }
+    if a == b {
+        return None;  // includes matching infinities
+    }
+
+    let abs_diff = (a.0 - b.0).hypot(a.1 - b.1);
+    let b_magnitude = b.0.hypot(b.1);
+    match abs_diff <= tolerance.atol + tolerance.rtol * b_magnitude {
+        true => None,
+        false => Some(abs_diff),
+    }
+}
+
+fn as_complex(value: &ElementValue) -> (f64, f64) {
+    match *value {
+        ElementValue::Int(x) => (x as f64, 0.0),
+        ElementValue::Real(x) => (x, 0.0),
+        ElementValue::Complex(re, im) => (re, im),
+        ElementValue::Bytes(_) => unreachable!(),
+    }
+}
+
+/// How to interpret the bytes of a single element.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum ElementKind {
+    Bool,
+    Int { size: usize, big_endian: bool },
+    Uint { size: usize, big_endian: bool },
+    // `datetime64` or `timedelta64`, with NaT decoded as NaN
+    Time { big_endian: bool },
+    Float { size: usize, big_endian: bool },
+    Complex { size: usize, big_endian: bool },
+    Bytes,
+}
+
+impl ElementKind {
+    /// Get the numeric interpretation of a dtype, if it has one.
+    pub(crate) fn of(dtype: &DType) -> Option<ElementKind> {
+        let &TypeStr { type_char, size, endianness, .. } = dtype.as_scalar()?;
+        let size = size as usize;
+        let big_endian = endianness == Endianness::Big;
+        match (type_char, size) {
+            (TypeChar::Bool, 1) => Some(ElementKind::Bool),
+            (TypeChar::Int, 1 | 2 | 4 | 8) => Some(ElementKind::Int { size, big_endian }),
+            (TypeChar::TimeDelta | TypeChar::DateTime, 8) => Some(ElementKind::Time { big_endian }),
+            (TypeChar::Uint, 1 | 2 | 4 | 8) => Some(ElementKind::Uint { size, big_endian }),
+            (TypeChar::Float, 2 | 4 | 8) => Some(ElementKind::Float { size, big_endian }),
+            (TypeChar::Complex, 8 | 16) => Some(ElementKind::Complex { size: size / 2, big_endian }),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn decode(self, bytes: &[u8]) -> ElementValue {
+        match self {
+            ElementKind::Bool => ElementValue::Int(bytes[0] as i128),
+            ElementKind::Int { size, big_endian } => {
+                let bits = read_bits(&bytes[..size], big_endian);
+                let shift = 64 - 8 * size as u32;
+                ElementValue::Int(((bits << shift) as i64 >> shift) as i128)
+            },
+            ElementKind::Uint { size, big_endian } => ElementValue::Int(read_bits(&bytes[..size], big_endian) as i128),
+            ElementKind::Time { big_endian } => match read_bits(&bytes[..8], big_endian) as i64 {
+                i64::MIN => ElementValue::Real(f64::NAN),
+                x => ElementValue::Int(x as i128),
+            },
+            ElementKind::Float { size, big_endian } => ElementValue::Real(read_float(&bytes[..size], big_endian)),
+            ElementKind::Complex { size, big_endian } => {
+                let re = read_float(&bytes[..size], big_endian);
+                let im = read_float(&bytes[size..2 * size], big_endian);
+                ElementValue::Complex(re, im)
+            },
+            ElementKind::Bytes => ElementValue::Bytes(bytes.to_vec()),
+        }
+    }
+}
+
+fn read_bits(bytes: &[u8], big_endian: bool) -> u64 {
+    let fold = |acc: u64, &byte: &u8| acc << 8 | byte as u64;
+    match big_endian {
+        true => bytes.iter().fold(0, fold),
+        false => bytes.iter().rev().fold(0, fold),
+    }
+}
+
+fn read_float(bytes: &[u8], big_endian: bool) -> f64 {
+    let bits = read_bits(bytes, big_endian);
+    match bytes.len() {
+        2 => f16_to_f64(bits as u16),
+        4 => f32::from_bits(bits as u32) as f64,
+        8 => f64::from_bits(bits),
+        _ => unreachable!(),
+    }
+}
+
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as f64;
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        0x1f if mantissa == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent as i32 - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::write::{to_bytes_1d, WriteOptions, WriterBuilder};
+
+    fn bytes_nd<T: crate::Serialize>(dtype: &str, shape: &[u64], order: Order, data: &[T]) -> Vec<u8> {
+        let mut buf = vec![];
+        let mut writer = {
+            WriteOptions::new().dtype(DType::parse(dtype).unwrap())
+                .shape(shape).order(order).writer(&mut buf).begin_nd().unwrap()
+        };
+        writer.extend(data).unwrap();
+        writer.finish().unwrap();
+        buf
+    }
+
+    // for dtypes that have no rust type
+    fn raw_1d(dtype: &str, data: &[u8]) -> Vec<u8> {
+        let dtype = DType::parse(dtype).unwrap();
+        let len = data.len() / dtype.num_bytes().unwrap();
+        let mut buf = crate::write::header_bytes(&dtype, Order::C, &[len as u64], &[]).unwrap();
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    #[test]
+    fn equal_within_tolerance() {
+        let a = to_bytes_1d(&[1.0, 2.0, 1e10]).unwrap();
+        let b = to_bytes_1d(&[1.0, 2.0 + 1e-9, 1e10 + 1.0]).unwrap();
+        let report = compare(&a[..], &b[..], Tolerance::default()).unwrap();
+        assert!(report.is_equal(), "{}", report);
+        assert_eq!(report.num_compared, 3);
+
+        let report = compare(&a[..], &b[..], Tolerance::exact()).unwrap();
+        assert_eq!(report.num_mismatches, 2);
+    }
+
+    #[test]
+    fn first_and_worst() {
+        let a = bytes_nd("'<f8'", &[2, 2], Order::C, &[0.0, 0.0, 0.0, 0.0]);
+        let b = bytes_nd("'<f8'", &[2, 2], Order::C, &[0.0, 1.0, 5.0, 1.0]);
+        let report = compare(&a[..], &b[..], Tolerance::default()).unwrap();
+        assert_eq!(report.num_mismatches, 3);
+        assert_eq!(report.first_mismatch.unwrap().index, vec![0, 1]);
+
+        let worst = report.worst_mismatch.unwrap();
+        assert_eq!(worst.index, vec![1, 0]);
+        assert_eq!(worst.b, ElementValue::Real(5.0));
+        assert_eq!(worst.abs_diff, 5.0);
+    }
+
+    #[test]
+    fn nan_handling() {
+        let a = to_bytes_1d(&[f64::NAN, 1.0]).unwrap();
+        let b = to_bytes_1d(&[f64::NAN, f64::NAN]).unwrap();
+
+        let report = compare(&a[..], &b[..], Tolerance { nan_equal: true, ..Tolerance::default() }).unwrap();
+        assert_eq!(report.num_mismatches, 1);
+        assert_eq!(report.first_mismatch.unwrap().index, vec![1]);
+
+        let report = compare(&a[..], &b[..], Tolerance::default()).unwrap();
+        assert_eq!(report.num_mismatches, 2);
+        assert_eq!(report.worst_mismatch.unwrap().abs_diff, f64::INFINITY);
+    }
+
+    #[test]
+    fn nat_is_like_nan() {
+        let a = bytes_nd("'<M8[s]'", &[2], Order::C, &[i64::MIN, 5]);
+        let b = bytes_nd("'>M8[s]'", &[2], Order::C, &[i64::MIN, 5]);
+        assert_eq!(compare(&a[..], &b[..], Tolerance::exact()).unwrap().num_mismatches, 1);
+        let report = compare(&a[..], &b[..], Tolerance { nan_equal: true, ..Tolerance::exact() }).unwrap();
+        assert_eq!(report.num_mismatches, 0);
+    }
+
+    #[test]
+    fn different_numeric_dtypes() {
+        let a = bytes_nd("'>i2'", &[3], Order::C, &[1_i16, -2, 3]);
+        let b = bytes_nd("'<f4'", &[3], Order::C, &[1.0_f32, -2.0, 3.0]);
+        let report = compare(&a[..], &b[..], Tolerance::exact()).unwrap();
+        assert!(report.dtype_difference.is_some());
+        assert_eq!(report.num_mismatches, 0);
+        assert!(!report.is_equal());
+    }
+
+    #[test]
+    fn large_integers_exact() {
+        let a = bytes_nd("'<i8'", &[2], Order::C, &[9007199254740993_i64, -9007199254740993]);
+        let b = bytes_nd("'<i8'", &[2], Order::C, &[9007199254740992_i64, -9007199254740993]);
+        let report = compare(&a[..], &b[..], Tolerance::exact()).unwrap();
+        assert_eq!(report.num_mismatches, 1);
+        let mismatch = report.first_mismatch.unwrap();
+        assert_eq!(mismatch.a, ElementValue::Int(9007199254740993));
+        assert_eq!(mismatch.abs_diff, 1.0);
+
+        let a = bytes_nd("'<u8'", &[1], Order::C, &[u64::MAX]);
+        let b = bytes_nd("'>i8'", &[1], Order::C, &[i64::MAX]);
+        let report = compare(&a[..], &b[..], Tolerance::exact()).unwrap();
+        assert_eq!(report.num_mismatches, 1);
+        let report = compare(&a[..], &b[..], Tolerance { rtol: 1.0, ..Tolerance::exact() }).unwrap();
+        assert_eq!(report.num_mismatches, 0);
+    }
+
+    #[test]
+    fn half_precision() {
+        // 1.0, 1.0009765625 (the next f16 after 1), NaN
+        let a = raw_1d("'<f2'", &[0x00, 0x3c, 0x01, 0x3c, 0x00, 0x7e]);
+        let b = raw_1d("'>f2'", &[0x3c, 0x00, 0x3c, 0x00, 0x7e, 0x00]);
+        let report = compare(&a[..], &b[..], Tolerance::exact()).unwrap();
+        assert_eq!(report.num_mismatches, 2);
+        assert_eq!(report.first_mismatch.unwrap().a, ElementValue::Real(1.0009765625));
+
+        let tolerance = Tolerance { rtol: 1e-3, atol: 0.0, nan_equal: true };
+        assert_eq!(compare(&a[..], &b[..], tolerance).unwrap().num_mismatches, 0);
+    }
+
+    #[test]
+    fn time_units() {
+        let ticks = |values: &[i64]| values.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+        let seconds = raw_1d("'<M8[s]'", &ticks(&[1, 2]));
+        let millis = raw_1d("'<M8[ms]'", &ticks(&[1000, 2000]));
+        let same_ticks = raw_1d("'<M8[ms]'", &ticks(&[1, 2]));
+        let delta = raw_1d("'<m8[s]'", &ticks(&[1, 2]));
+        let int = raw_1d("'<i8'", &ticks(&[1, 2]));
+
+        // (same instants, different units; different instants, same ticks; not datetimes)
+        for other in [&millis, &same_ticks, &delta, &int] {
+            for (a, b) in [(&seconds, other), (other, &seconds)] {
+                let report = compare(&a[..], &b[..], Tolerance::default()).unwrap();
+                assert!(!report.is_equal(), "{}", report);
+                assert!(report.dtype_difference.is_some());
+                assert_eq!(report.num_compared, 0);
+            }
+        }
+
+        let big_endian = raw_1d("'>M8[s]'", &[1, 2].iter().flat_map(|x: &i64| x.to_be_bytes()).collect::<Vec<_>>());
+        let report = compare(&seconds[..], &big_endian[..], Tolerance::exact()).unwrap();
+        assert_eq!(report.num_compared, 2);
+        assert_eq!(report.num_mismatches, 0);
+    }
+
+    #[test]
+    fn extended_precision_requires_exact() {
+        let a = raw_1d("'<f16'", &[0; 16]);
+        let report = compare(&a[..], &a[..], Tolerance::exact()).unwrap();
+        assert!(report.is_equal());
+
+        let err = compare(&a[..], &a[..], Tolerance::default()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("requires Tolerance::exact()"), "{}", err);
+    }
+
+    #[test]
+    fn different_shapes() {
+        let a = bytes_nd("'<i4'", &[2, 3], Order::C, &[0, 1, 2, 3, 4, 5]);
+        let b = bytes_nd("'<i4'", &[3, 2], Order::C, &[0, 1, 2, 3, 4, 5]);
+        let report = compare(&a[..], &b[..], Tolerance::default()).unwrap();
+        assert_eq!(report.shape_difference, Some((vec![2, 3], vec![3, 2])));
+        assert_eq!(report.num_compared, 0);
+    }
+
+    #[test]
+    fn different_orders() {
+        let a = bytes_nd("'<i4'", &[2, 3], Order::C, &[0, 1, 2, 10, 11, 12]);
+        let b = bytes_nd("'<i4'", &[2, 3], Order::Fortran, &[0, 10, 1, 11, 2, 99]);
+        let report = compare(&a[..], &b[..], Tolerance::exact()).unwrap();
+        assert_eq!(report.num_compared, 6);
+        assert_eq!(report.num_mismatches, 1);
+        assert_eq!(report.first_mismatch.unwrap().index, vec![1, 2]);
+    }
+
+    #[test]
+    fn strings_bytewise() {
+        let a = bytes_nd("'|S3'", &[2], Order::C, &[b"abc".to_vec(), b"de".to_vec()]);
+        let b = bytes_nd("'|S3'", &[2], Order::C, &[b"abc".to_vec(), b"df".to_vec()]);
+        let report = compare(&a[..], &b[..], Tolerance::default()).unwrap();
+        assert_eq!(report.num_mismatches, 1);
+        assert_eq!(report.first_mismatch.unwrap().a, ElementValue::Bytes(b"de\0".to_vec()));
+
+        let c = bytes_nd("'|S4'", &[2], Order::C, &[b"abc".to_vec(), b"de".to_vec()]);
+        let report = compare(&a[..], &c[..], Tolerance::default()).unwrap();
+        assert!(report.dtype_difference.is_some());
+        assert_eq!(report.num_compared, 0);
+    }
+}
@@ -0,0 +1,260 @@
+//! Writing NPY files inside a compressed stream.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::Error;
+
+/// A compression format and level for a [`CompressedWriter`].
+///
+/// *The variants are only available with the **`"gzip"`** and **`"zstd"`** features, respectively.*
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Gzip (`.gz`), with a level from 0 (no compression) to 9 (best compression).
+    #[cfg(feature = "gzip")]
+    Gzip(u32),
+    /// Zstandard (`.zst`), with a level from 1 to 22, or 0 for the library's default (currently 3).
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl Compression {
+    /// Choose the compression from the extension of a path, with the default level.
+    ///
+    /// This recognizes `.gz` (level 6) and `.zst` (level 3) for the features that are enabled.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            #[cfg(feature = "gzip")]
+            "gz" => Some(Compression::Gzip(6)),
+            #[cfg(feature = "zstd")]
+            "zst" => Some(Compression::Zstd(3)),
+            _ => None,
+        }
+    }
+}
+
+/// A [`Write`] adapter that compresses everything written to it, for shipping a single array compressed
+/// without the overhead of a one-member NPZ file.
+///
+/// Give it (or a `&mut` reference to it) to [`WriterBuilder::writer`][`crate::WriterBuilder::writer`].  Because the
+/// compressed stream cannot go back to fill in the header, it does not implement [`Seek`][`io::Seek`],
+/// so the shape must be given up front and [`WriterBuilder::begin_nd`][`crate::WriterBuilder::begin_nd`] used
+/// rather than [`WriterBuilder::begin_1d`][`crate::WriterBuilder::begin_1d`].
+///
+/// Call [`Self::finish`] after the [`NpyWriter`][`crate::NpyWriter`] is finished to write the end of the
+/// compressed stream.  This also happens when it is dropped, but in that case, errors are ignored.
+///
+/// To compress on a background thread while the data is serialized, wrap it in a
+/// [`WriteBehind`][`crate::WriteBehind`].
+///
+/// The files can be read again by wrapping the source in a decoder such as `flate2::read::GzDecoder`
+/// or `zstd::Decoder`, since [`NpyFile::new`][`crate::NpyFile::new`] accepts any [`io::Read`].
+///
+/// *This is only available with the **`"gzip"`** or **`"zstd"`** feature.*
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::WriterBuilder;
+///
+/// # #[cfg(feature = "gzip")] let compression = npyz::Compression::Gzip(6);
+/// # #[cfg(not(feature = "gzip"))] let compression = npyz::Compression::Zstd(3);
+/// let mut compressed = npyz::CompressedWriter::new(vec![], compression)?;
+/// let mut writer = npyz::WriteOptions::new().default_dtype().shape(&[1000]).writer(&mut compressed).begin_nd()?;
+/// writer.extend(0..1000_i64)?;
+/// writer.finish()?;
+/// let bytes = compressed.finish()?;
+/// assert!(bytes.len() < 8 * 1000);
+/// # Ok(()) }
+/// ```
+pub struct CompressedWriter<W: Write> {
+    // `None` once finished
+    encoder: Option<Encoder<W>>,
+}
+
+enum Encoder<W: Write> {
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl CompressedWriter<BufWriter<File>> {
+    /// Create a file, compressed according to its extension (see [`Compression::from_path`]).
+    ///
+    /// Returns [`Error::InvalidInput`] if the extension is not recognized.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let compression = Compression::from_path(path).ok_or_else(|| {
+            Error::InvalidInput(format!("cannot choose a compression for {}", path.display()))
+        })?;
+        Self::new(BufWriter::new(File::create(path)?), compression)
+    }
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Begin a compressed stream.
+    pub fn new(writer: W, compression: Compression) -> io::Result<Self> {
+        let encoder = match compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip(level) => {
+                if level > 9 {
+                    return Err(Error::InvalidInput(format!("gzip level {} is not between 0 and 9", level)).into());
+                }
+                Encoder::Gzip(flate2::write::GzEncoder::new(writer, flate2::Compression::new(level)))
+            },
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => Encoder::Zstd(zstd::stream::write::Encoder::new(writer, level)?),
+        };
+        Ok(CompressedWriter { encoder: Some(encoder) })
+    }
+
+    /// Get the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        match self.encoder() {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.get_ref(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.get_ref(),
+        }
+    }
+
+    /// Write the end of the compressed stream, and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut writer = match self.encoder.take().expect("only taken here") {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    fn encoder(&self) -> &Encoder<W> {
+        self.encoder.as_ref().expect("only taken by finish")
+    }
+
+    fn encoder_mut(&mut self) -> &mut (dyn Write + '_) {
+        match self.encoder.as_mut().expect("only taken by finish") {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e,
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder_mut().write(buf)
+    }
+
+    /// Compress everything written so far and flush it to the underlying writer.  This does not end
+    /// the compressed stream.
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for CompressedWriter<W> {
+    fn drop(&mut self) {
+        // Ignore the errors
+        let _ = match &mut self.encoder {
+            #[cfg(feature = "gzip")]
+            Some(Encoder::Gzip(e)) => e.try_finish(),
+            #[cfg(feature = "zstd")]
+            Some(Encoder::Zstd(e)) => e.do_finish(),
+            None => Ok(()),
+        };
+    }
+}
+
+impl<W: Write> std::fmt::Debug for CompressedWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let format = match &self.encoder {
+            #[cfg(feature = "gzip")]
+            Some(Encoder::Gzip(_)) => "gzip",
+            #[cfg(feature = "zstd")]
+            Some(Encoder::Zstd(_)) => "zstd",
+            None => "finished",
+        };
+        f.debug_struct("CompressedWriter").field("format", &format).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NpyFile, WriteOptions, WriterBuilder};
+
+    fn compressions() -> Vec<Compression> {
+        vec![
+            #[cfg(feature = "gzip")]
+            Compression::Gzip(0),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip(9),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(0),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(19),
+        ]
+    }
+
+    fn decompress(bytes: &[u8], compression: Compression) -> Vec<u8> {
+        let mut out = vec![];
+        match compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip(_) => io::copy(&mut flate2::read::GzDecoder::new(bytes), &mut out).unwrap(),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(_) => io::copy(&mut zstd::Decoder::new(bytes).unwrap(), &mut out).unwrap(),
+        };
+        out
+    }
+
+    #[test]
+    fn roundtrip() -> io::Result<()> {
+        for compression in compressions() {
+            let mut compressed = CompressedWriter::new(vec![], compression)?;
+            let mut writer = WriteOptions::new().default_dtype().shape(&[20, 5]).buffer_size(64).writer(&mut compressed).begin_nd()?;
+            writer.extend(0..100_u16)?;
+            writer.finish()?;
+            let bytes = compressed.finish()?;
+
+            let decompressed = decompress(&bytes, compression);
+            let npy = NpyFile::new(&decompressed[..])?;
+            assert_eq!(npy.shape(), &[20, 5]);
+            assert_eq!(npy.into_vec::<u16>()?, (0..100).collect::<Vec<_>>());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn finished_on_drop() -> io::Result<()> {
+        for compression in compressions() {
+            let mut bytes = vec![];
+            let compressed = CompressedWriter::new(&mut bytes, compression)?;
+            let mut writer = WriteOptions::new().default_dtype().shape(&[3]).writer(compressed).begin_nd()?;
+            writer.extend([1.0_f64, 2.0, 3.0])?;
+            drop(writer);
+
+            let decompressed = decompress(&bytes, compression);
+            let npy = NpyFile::new(&decompressed[..])?;
+            assert_eq!(npy.into_vec::<f64>()?, vec![1.0, 2.0, 3.0]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn from_path() {
+        assert_eq!(Compression::from_path("a.npy"), None);
+        assert_eq!(Compression::from_path("a"), None);
+        #[cfg(feature = "gzip")]
+        assert_eq!(Compression::from_path("dir/a.npy.gz"), Some(Compression::Gzip(6)));
+        #[cfg(feature = "zstd")]
+        assert_eq!(Compression::from_path("a.npy.zst"), Some(Compression::Zstd(3)));
+        #[cfg(feature = "gzip")]
+        assert_eq!(CompressedWriter::new(vec![], Compression::Gzip(10)).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
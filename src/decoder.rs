@@ -0,0 +1,254 @@
+//! Decoding NPY files from chunks of bytes that are pushed in as they arrive.
+
+use std::collections::VecDeque;
+use std::io;
+
+use crate::error::Error;
+use crate::read::{NpyFile, NpyHeader, NpyReader, ReadOptions};
+use crate::serialize::Deserialize;
+
+/// Decodes an NPY file from chunks of bytes that are handed to it, rather than pulled from a reader.
+///
+/// This allows NPY files to be read from sources that cannot implement [`std::io::Read`] without blocking,
+/// such as an async stream.  Bytes are given to [`Self::push`] in chunks of any size, and every element
+/// whose bytes have arrived in full can be taken with [`Self::decode_ready`], so that only a partial
+/// element (and the header, until it is complete) is buffered between chunks.
+///
+/// This does not depend on any async runtime, so it works with any source of chunks.  The following
+/// reads from a `tokio::io::AsyncRead` (though with the **`"async"`** feature, `NpyFile::new_async`
+/// does the same with less code):
+///
+/// ```ignore
+/// use tokio::io::AsyncReadExt;
+///
+/// let mut file = tokio::fs::File::open("big.npy").await?;
+/// let mut decoder = npyz::NpyDecoder::<f32>::new();
+/// let mut chunk = vec![0; 64 * 1024];
+/// let mut total = 0.0;
+/// loop {
+///     let len = file.read(&mut chunk).await?;
+///     if len == 0 {
+///         break;
+///     }
+///     decoder.push(&chunk[..len])?;
+///     total += decoder.decode_ready()?.iter().sum::<f32>();
+/// }
+/// decoder.finish()?;
+/// ```
+///
+/// Decoding a file that is pushed a few bytes at a time:
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let bytes = std::fs::read("test-data/c-order.npy")?;
+///
+/// let mut decoder = npyz::NpyDecoder::<i64>::new();
+/// let mut data = vec![];
+/// for chunk in bytes.chunks(7) {
+///     decoder.push(chunk)?;
+///     data.extend(decoder.decode_ready()?);
+/// }
+/// decoder.finish()?;
+/// assert_eq!(data, npyz::NpyFile::new(&bytes[..])?.into_vec::<i64>()?);
+/// # Ok(()) }
+/// ```
+///
+/// NPZ files cannot be decoded this way, because the list of arrays in a zip archive is at its end.
+/// With the **`"async"`** feature, `npz::AsyncNpzArchive` reads them from a tokio reader that can seek.
+pub struct NpyDecoder<T: Deserialize> {
+    options: ReadOptions,
+    state: State<T>,
+}
+
+// the header is only buffered until the first push that completes it
+#[allow(clippy::large_enum_variant)]
+enum State<T: Deserialize> {
+    // the bytes received so far, until they contain the whole header
+    Header(Vec<u8>),
+    Data {
+        reader: NpyReader<T, VecDeque<u8>>,
+        // records whose bytes are all in the reader
+        ready: u64,
+        // bytes after the last record
+        trailing: u64,
+    },
+}
+
+impl<T: Deserialize> NpyDecoder<T> {
+    /// Construct a decoder with the default [`ReadOptions`].
+    pub fn new() -> Self {
+        Self::with_options(ReadOptions::default())
+    }
+
+    /// Construct a decoder that checks the header against the given [`ReadOptions`].
+    pub fn with_options(options: ReadOptions) -> Self {
+        NpyDecoder { options, state: State::Header(vec![]) }
+    }
+
+    /// Get the header, once all of its bytes have been pushed.
+    pub fn header(&self) -> Option<&NpyHeader> {
+        match &self.state {
+            State::Header(_) => None,
+            State::Data { reader, .. } => Some(reader.header()),
+        }
+    }
+
+    /// Give the decoder the next bytes of the file.
+    ///
+    /// Fails if the header is invalid, or if the dtype cannot be read as `T`.
+    pub fn push(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match &mut self.state {
+            State::Header(buf) => {
+                buf.extend_from_slice(bytes);
+                // a short magic string would be reported as missing, rather than as truncated
+                if buf.len() < 8 {
+                    return Ok(());
+                }
+                let mut remaining = &buf[..];
+                let header = match NpyHeader::from_reader_with_options(&mut remaining, &self.options) {
+                    Ok(header) => header,
+                    // wait for the rest of the header
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                    Err(e) => return Err(e),
+                };
+                let rest = remaining.to_vec();
+                let dtype = header.dtype();
+                let npy = NpyFile::with_header(header, VecDeque::new()).with_buffer_size(self.options.get_buffer_size());
+                let reader = npy.data::<T>().map_err(|e| Error::dtype_mismatch::<T>(&dtype, e))?;
+                self.state = State::Data { reader, ready: 0, trailing: 0 };
+                self.push(&rest)
+            },
+            State::Data { reader, ready, trailing } => {
+                let item_size = reader.header().item_size() as u64;
+                let remaining = NpyReader::len(reader);
+                let pending = reader.reader_mut();
+                pending.extend(bytes);
+                // zero-sized records are all ready as soon as the header is
+                let available = match item_size {
+                    0 => remaining,
+                    _ => (pending.len() as u64 / item_size).min(remaining),
+                };
+                *ready = available;
+                *trailing = pending.len() as u64 - available * item_size;
+                Ok(())
+            },
+        }
+    }
+
+    /// Decode every element whose bytes have all been pushed, and that has not already been decoded.
+    pub fn decode_ready(&mut self) -> io::Result<Vec<T>> {
+        match &mut self.state {
+            State::Header(_) => Ok(vec![]),
+            State::Data { reader, ready, .. } => {
+                let count = std::mem::take(ready);
+                reader.by_ref().take(count as usize).collect()
+            },
+        }
+    }
+
+    /// Returns the number of elements that have not been decoded, including those whose bytes have not arrived.
+    ///
+    /// This is `None` until the header is complete.
+    pub fn remaining(&self) -> Option<u64> {
+        match &self.state {
+            State::Header(_) => None,
+            State::Data { reader, .. } => Some(NpyReader::len(reader)),
+        }
+    }
+
+    /// Check that the file was complete, after all of its bytes have been pushed and its elements decoded.
+    ///
+    /// Fails with [`Error::Truncated`] if the header or any element is incomplete, or if elements
+    /// remain that were not taken with [`Self::decode_ready`]; and with [`Error::InvalidData`] if bytes
+    /// were pushed after the end of the data.
+    pub fn finish(self) -> io::Result<()> {
+        match self.state {
+            State::Header(_) => Err(Error::Truncated.into()),
+            State::Data { reader, .. } if !reader.is_empty() => Err(Error::Truncated.into()),
+            State::Data { trailing: 0, .. } => Ok(()),
+            State::Data { trailing, .. } => Err(Error::InvalidData(format!("{} trailing bytes after the data", trailing)).into()),
+        }
+    }
+}
+
+impl<T: Deserialize> Default for NpyDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c_order() -> Vec<u8> {
+        std::fs::read("test-data/c-order.npy").unwrap()
+    }
+
+    fn decode_in_chunks(bytes: &[u8], chunk_size: usize) -> io::Result<Vec<i64>> {
+        let mut decoder = NpyDecoder::<i64>::new();
+        let mut data = vec![];
+        for chunk in bytes.chunks(chunk_size) {
+            decoder.push(chunk)?;
+            data.extend(decoder.decode_ready()?);
+        }
+        decoder.finish()?;
+        Ok(data)
+    }
+
+    #[test]
+    fn chunk_sizes() {
+        let bytes = c_order();
+        let expected = NpyFile::new(&bytes[..]).unwrap().into_vec::<i64>().unwrap();
+        for chunk_size in [1, 3, 8, 100, bytes.len()] {
+            assert_eq!(decode_in_chunks(&bytes, chunk_size).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn header_and_remaining() {
+        let bytes = c_order();
+        let mut decoder = NpyDecoder::<i64>::new();
+        decoder.push(&bytes[..10]).unwrap();
+        assert!(decoder.header().is_none());
+        assert_eq!(decoder.remaining(), None);
+        assert_eq!(decoder.decode_ready().unwrap(), vec![]);
+
+        let header_len = bytes.len() - 24 * 8;
+        decoder.push(&bytes[10..header_len + 12]).unwrap();
+        assert_eq!(decoder.header().unwrap().shape(), &[2, 3, 4]);
+        assert_eq!(decoder.remaining(), Some(24));
+        assert_eq!(decoder.decode_ready().unwrap().len(), 1);
+        assert_eq!(decoder.remaining(), Some(23));
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = c_order();
+        let err = decode_in_chunks(&bytes[..bytes.len() - 3], 5).unwrap_err();
+        assert!(matches!(Error::from(err), Error::Truncated));
+
+        let err = decode_in_chunks(&bytes[..20], 5).unwrap_err();
+        assert!(matches!(Error::from(err), Error::Truncated));
+
+        // elements that were never decoded
+        let mut decoder = NpyDecoder::<i64>::new();
+        decoder.push(&bytes).unwrap();
+        assert!(matches!(Error::from(decoder.finish().unwrap_err()), Error::Truncated));
+    }
+
+    #[test]
+    fn trailing_bytes() {
+        let mut bytes = c_order();
+        bytes.extend_from_slice(&[0; 5]);
+        let err = decode_in_chunks(&bytes, 4).unwrap_err();
+        assert!(matches!(Error::from(err), Error::InvalidData(msg) if msg.contains("5 trailing bytes")));
+    }
+
+    #[test]
+    fn wrong_dtype() {
+        let mut decoder = NpyDecoder::<f32>::new();
+        let err = decoder.push(&c_order()).unwrap_err();
+        assert!(matches!(Error::from(err), Error::DTypeMismatch { .. }));
+    }
+}
@@ -0,0 +1,168 @@
+//! Reporting of recoverable oddities in files.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Something unusual about a file that does not prevent it from being read.
+///
+/// These are reported to the callback given to [`ReadOptions::on_diagnostic`][`crate::ReadOptions::on_diagnostic`].
+/// They can indicate that a file was produced by a buggy or nonstandard writer.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use std::sync::{Arc, Mutex};
+/// use npyz::{Diagnostic, ReadOptions};
+///
+/// let found = Arc::new(Mutex::new(vec![]));
+/// let options = ReadOptions::new().on_diagnostic({
+///     let found = found.clone();
+///     move |diagnostic: &Diagnostic| found.lock().unwrap().push(diagnostic.clone())
+/// });
+///
+/// let bytes = std::fs::read("test-data/c-order.npy")?;
+/// let _ = npyz::NpyFile::with_options(&bytes[..], &options)?;
+/// assert_eq!(*found.lock().unwrap(), vec![]);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Diagnostic {
+    /// The header dict has a key other than `'descr'`, `'fortran_order'` and `'shape'`.
+    UnknownHeaderKey {
+        /// The key.
+        key: String,
+    },
+    /// The header is not padded the way numpy pads it, i.e. with spaces and a newline up to a multiple of 64 bytes.
+    NonCanonicalPadding {
+        /// The total length of the header, including the magic string.
+        header_len: usize,
+    },
+    /// The shape is valid, but unlikely to be intended.
+    SuspiciousShape {
+        /// The shape.
+        shape: Vec<u64>,
+        /// Why it is suspicious.
+        reason: &'static str,
+    },
+    /// A compressed sparse matrix has indices that are not sorted within each row (or column).
+    ///
+    /// scipy permits this, but many algorithms require sorted indices.
+    UnsortedSparseIndices {
+        /// The sparse format, e.g. `"csr"`.
+        format: &'static str,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::UnknownHeaderKey { key } => write!(f, "unknown header key {:?}", key),
+            Diagnostic::NonCanonicalPadding { header_len } => write!(f, "non-canonical header padding (header is {} bytes)", header_len),
+            Diagnostic::SuspiciousShape { shape, reason } => write!(f, "suspicious shape {:?}: {}", shape, reason),
+            Diagnostic::UnsortedSparseIndices { format } => write!(f, "{} matrix has unsorted indices", format),
+        }
+    }
+}
+
+/// Callback for diagnostics, stored in `ReadOptions`.
+#[derive(Clone)]
+pub(crate) struct DiagnosticSink(Arc<dyn Fn(&Diagnostic) + Send + Sync>);
+
+impl DiagnosticSink {
+    pub(crate) fn new(func: impl Fn(&Diagnostic) + Send + Sync + 'static) -> Self {
+        DiagnosticSink(Arc::new(func))
+    }
+
+    pub(crate) fn emit(&self, diagnostic: Diagnostic) {
+        (self.0)(&diagnostic)
+    }
+}
+
+impl fmt::Debug for DiagnosticSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("DiagnosticSink(..)")
+    }
+}
+
+impl PartialEq for DiagnosticSink {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for DiagnosticSink {}
+
+// Whether the header is padded the way numpy pads it.
+pub(crate) fn has_canonical_padding(raw_bytes: &[u8]) -> bool {
+    let canonical_padding = match raw_bytes.strip_suffix(b"\n") {
+        Some(text) => text.iter().rev().take_while(|b| b.is_ascii_whitespace()).all(|&b| b == b' '),
+        None => false,
+    };
+    raw_bytes.len() % 64 == 0 && canonical_padding
+}
+
+// Diagnostics about the shape and layout of a header.
+pub(crate) fn check_header(raw_bytes: &[u8], shape: &[u64], item_size: usize, mut emit: impl FnMut(Diagnostic)) {
+    if !has_canonical_padding(raw_bytes) {
+        emit(Diagnostic::NonCanonicalPadding { header_len: raw_bytes.len() });
+    }
+
+    let num_bytes = shape.iter().try_fold(item_size as u64, |acc, &dim| acc.checked_mul(dim));
+    if num_bytes.map_or(true, |n| n > 1 << 40) {
+        emit(Diagnostic::SuspiciousShape { shape: shape.to_vec(), reason: "the data would be larger than 1 TiB" });
+    }
+    if shape.len() > 32 {
+        emit(Diagnostic::SuspiciousShape { shape: shape.to_vec(), reason: "numpy versions before 2.0 support at most 32 dimensions" });
+    }
+}
+
+// Check that indices are sorted within each segment delimited by `indptr`.
+#[cfg_attr(not(feature = "npz"), allow(dead_code))]
+pub(crate) fn indices_are_sorted(indices: &[u64], indptr: &[usize]) -> bool {
+    indptr.windows(2).all(|w| {
+        indices.get(w[0]..w[1]).map_or(true, |segment| segment.windows(2).all(|pair| pair[0] < pair[1]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_diagnostics(raw_bytes: &[u8], shape: &[u64]) -> Vec<Diagnostic> {
+        let mut out = vec![];
+        check_header(raw_bytes, shape, 8, |d| out.push(d));
+        out
+    }
+
+    #[test]
+    fn padding() {
+        let mut canonical = b"\x93NUMPY\x01\x00\x76\x00{'descr': '<f8', 'fortran_order': False, 'shape': (3,), }".to_vec();
+        canonical.resize(127, b' ');
+        canonical.push(b'\n');
+        assert_eq!(header_diagnostics(&canonical, &[3]), vec![]);
+
+        let mut tabs = canonical.clone();
+        tabs[120] = b'\t';
+        assert_eq!(header_diagnostics(&tabs, &[3]), vec![Diagnostic::NonCanonicalPadding { header_len: 128 }]);
+
+        let short = &canonical[..100];
+        assert_eq!(header_diagnostics(short, &[3]), vec![Diagnostic::NonCanonicalPadding { header_len: 100 }]);
+    }
+
+    #[test]
+    fn shapes() {
+        let mut raw = vec![b' '; 63];
+        raw.push(b'\n');
+        assert_eq!(header_diagnostics(&raw, &[1 << 20, 1 << 16]), vec![]);
+        assert!(matches!(header_diagnostics(&raw, &[1 << 20, 1 << 18])[..], [Diagnostic::SuspiciousShape { .. }]));
+        assert!(matches!(header_diagnostics(&raw, &[1; 33])[..], [Diagnostic::SuspiciousShape { .. }]));
+        assert_eq!(header_diagnostics(&raw, &[0, 1 << 40]), vec![]);
+    }
+
+    #[test]
+    fn sorted_indices() {
+        assert!(indices_are_sorted(&[0, 2, 1, 3], &[0, 2, 4]));
+        assert!(!indices_are_sorted(&[0, 2, 3, 1], &[0, 2, 4]));
+        assert!(!indices_are_sorted(&[1, 1], &[0, 2]));
+    }
+}
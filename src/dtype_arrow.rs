@@ -0,0 +1,226 @@
+//! Conversion between [`DType`] and Arrow's [`DataType`].
+//!
+//! _This module is only available with the **`"arrow"`** feature._
+
+use std::fmt;
+use std::sync::Arc;
+
+use arrow_schema::{DataType, Field as ArrowField, TimeUnit};
+
+use crate::header::{DType, Field};
+use crate::type_str::{TimeUnits, TypeChar, TypeStr};
+
+/// Error returned when a type has no counterpart in [`DType::to_arrow`] or [`DType::from_arrow`].
+#[derive(Debug, Clone)]
+pub struct ArrowTypeError(ErrorKind);
+
+#[derive(Debug, Clone)]
+enum ErrorKind {
+    UnsupportedTypeStr(TypeStr),
+    UnsupportedDataType(DataType),
+    TopLevelArray,
+    ArrayTooLong(u64),
+}
+
+impl std::error::Error for ArrowTypeError {}
+
+impl fmt::Display for ArrowTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            ErrorKind::UnsupportedTypeStr(ty) => write!(f, "type string '{}' has no equivalent arrow type", ty),
+            ErrorKind::UnsupportedDataType(ty) => write!(f, "arrow type {} has no equivalent dtype", ty),
+            ErrorKind::TopLevelArray => write!(f, "array dtypes are only possible as the type of a field"),
+            ErrorKind::ArrayTooLong(len) => write!(f, "array length {} is too large for an arrow FixedSizeList", len),
+        }
+    }
+}
+
+impl DType {
+    /// Get the Arrow type of a column holding elements of this dtype.
+    ///
+    /// _This method is only available with the **`"arrow"`** feature._
+    ///
+    /// | dtype | Arrow type |
+    /// | --- | --- |
+    /// | `b1`, `i1`–`i8`, `u1`–`u8`, `f2`–`f8` | `Boolean`, `Int8`–`Int64`, `UInt8`–`UInt64`, `Float16`–`Float64` |
+    /// | `M8[s]`, `M8[ms]`, `M8[us]`, `M8[ns]` | `Timestamp` with the same unit and no time zone |
+    /// | `m8[s]`, `m8[ms]`, `m8[us]`, `m8[ns]` | `Duration` with the same unit |
+    /// | `S<n>` | `Binary` |
+    /// | `U<n>` | `Utf8` |
+    /// | `V<n>` | `FixedSizeBinary(n)` |
+    /// | record | `Struct`, omitting unnamed padding fields |
+    /// | array field | `FixedSizeList` |
+    ///
+    /// Arrow data is always in native byte order, so the endianness of the dtype is not represented.
+    /// All fields are non-nullable.  Complex numbers, `f16` and datetimes in other units are not supported.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use npyz::DType;
+    /// use npyz::arrow_schema::{DataType, TimeUnit};
+    ///
+    /// let dtype = DType::parse("[('id', '>u4'), ('time', '<M8[ms]'), ('name', '|S8')]")?;
+    /// let DataType::Struct(fields) = dtype.to_arrow()? else { panic!() };
+    /// assert_eq!(fields[0].data_type(), &DataType::UInt32);
+    /// assert_eq!(fields[1].data_type(), &DataType::Timestamp(TimeUnit::Millisecond, None));
+    /// assert_eq!(fields[2].data_type(), &DataType::Binary);
+    /// # Ok(()) }
+    /// ```
+    pub fn to_arrow(&self) -> Result<DataType, ArrowTypeError> {
+        match self {
+            DType::Array(..) => Err(ArrowTypeError(ErrorKind::TopLevelArray)),
+            _ => dtype_to_arrow(self),
+        }
+    }
+
+    /// Get a dtype whose elements can hold the values of an Arrow column.
+    ///
+    /// _This method is only available with the **`"arrow"`** feature._
+    ///
+    /// This is the inverse of [`DType::to_arrow`], producing types in native byte order.  Records are
+    /// packed without padding.  Variable-width types such as `Binary` and `Utf8` are not supported, because
+    /// a dtype requires a fixed width.  The time zone of a `Timestamp` is discarded.
+    pub fn from_arrow(data_type: &DataType) -> Result<DType, ArrowTypeError> {
+        let plain = |type_char, size, time_units| Ok(DType::Plain(TypeStr::with_auto_endianness(type_char, size, time_units)));
+        match data_type {
+            DataType::Boolean => plain(TypeChar::Bool, 1, None),
+            DataType::Int8 => plain(TypeChar::Int, 1, None),
+            DataType::Int16 => plain(TypeChar::Int, 2, None),
+            DataType::Int32 => plain(TypeChar::Int, 4, None),
+            DataType::Int64 => plain(TypeChar::Int, 8, None),
+            DataType::UInt8 => plain(TypeChar::Uint, 1, None),
+            DataType::UInt16 => plain(TypeChar::Uint, 2, None),
+            DataType::UInt32 => plain(TypeChar::Uint, 4, None),
+            DataType::UInt64 => plain(TypeChar::Uint, 8, None),
+            DataType::Float16 => plain(TypeChar::Float, 2, None),
+            DataType::Float32 => plain(TypeChar::Float, 4, None),
+            DataType::Float64 => plain(TypeChar::Float, 8, None),
+            DataType::Timestamp(unit, _) => plain(TypeChar::DateTime, 8, Some(time_units_from_arrow(*unit))),
+            DataType::Duration(unit) => plain(TypeChar::TimeDelta, 8, Some(time_units_from_arrow(*unit))),
+            DataType::FixedSizeBinary(size) if *size >= 0 => plain(TypeChar::RawData, *size as u64, None),
+            DataType::FixedSizeList(item, len) if *len >= 0 => {
+                let inner = DType::from_arrow(item.data_type())?;
+                Ok(DType::Array(*len as u64, Box::new(inner)))
+            },
+            DataType::Struct(fields) => {
+                let fields = fields.iter().map(|field| {
+                    let dtype = DType::from_arrow(field.data_type())?;
+                    Ok(Field { name: field.name().clone(), dtype })
+                }).collect::<Result<_, _>>()?;
+                Ok(DType::Record(fields))
+            },
+            _ => Err(ArrowTypeError(ErrorKind::UnsupportedDataType(data_type.clone()))),
+        }
+    }
+}
+
+fn dtype_to_arrow(dtype: &DType) -> Result<DataType, ArrowTypeError> {
+    match dtype {
+        DType::Plain(ty) => type_str_to_arrow(ty),
+        DType::Array(len, inner) => {
+            let len = i32::try_from(*len).map_err(|_| ArrowTypeError(ErrorKind::ArrayTooLong(*len)))?;
+            let item = ArrowField::new_list_field(dtype_to_arrow(inner)?, false);
+            Ok(DataType::FixedSizeList(Arc::new(item), len))
+        },
+        DType::Record(fields) => {
+            let fields = fields.iter().filter(|field| !field.name.is_empty()).map(|field| {
+                Ok(ArrowField::new(field.name.clone(), dtype_to_arrow(&field.dtype)?, false))
+            }).collect::<Result<Vec<_>, _>>()?;
+            Ok(DataType::Struct(fields.into()))
+        },
+    }
+}
+
+fn type_str_to_arrow(ty: &TypeStr) -> Result<DataType, ArrowTypeError> {
+    let unsupported = || ArrowTypeError(ErrorKind::UnsupportedTypeStr(ty.clone()));
+    Ok(match (ty.type_char, ty.size) {
+        (TypeChar::Bool, _) => DataType::Boolean,
+        (TypeChar::Int, 1) => DataType::Int8,
+        (TypeChar::Int, 2) => DataType::Int16,
+        (TypeChar::Int, 4) => DataType::Int32,
+        (TypeChar::Int, 8) => DataType::Int64,
+        (TypeChar::Uint, 1) => DataType::UInt8,
+        (TypeChar::Uint, 2) => DataType::UInt16,
+        (TypeChar::Uint, 4) => DataType::UInt32,
+        (TypeChar::Uint, 8) => DataType::UInt64,
+        (TypeChar::Float, 2) => DataType::Float16,
+        (TypeChar::Float, 4) => DataType::Float32,
+        (TypeChar::Float, 8) => DataType::Float64,
+        (TypeChar::DateTime, _) => {
+            let unit = ty.time_units.and_then(time_units_to_arrow).ok_or_else(unsupported)?;
+            DataType::Timestamp(unit, None)
+        },
+        (TypeChar::TimeDelta, _) => {
+            let unit = ty.time_units.and_then(time_units_to_arrow).ok_or_else(unsupported)?;
+            DataType::Duration(unit)
+        },
+        (TypeChar::ByteStr, _) => DataType::Binary,
+        (TypeChar::UnicodeStr, _) => DataType::Utf8,
+        (TypeChar::RawData, size) => DataType::FixedSizeBinary(i32::try_from(size).map_err(|_| unsupported())?),
+        _ => return Err(unsupported()),
+    })
+}
+
+fn time_units_to_arrow(units: TimeUnits) -> Option<TimeUnit> {
+    match units {
+        TimeUnits::Second => Some(TimeUnit::Second),
+        TimeUnits::Millisecond => Some(TimeUnit::Millisecond),
+        TimeUnits::Microsecond => Some(TimeUnit::Microsecond),
+        TimeUnits::Nanosecond => Some(TimeUnit::Nanosecond),
+        _ => None,
+    }
+}
+
+fn time_units_from_arrow(unit: TimeUnit) -> TimeUnits {
+    match unit {
+        TimeUnit::Second => TimeUnits::Second,
+        TimeUnit::Millisecond => TimeUnits::Millisecond,
+        TimeUnit::Microsecond => TimeUnits::Microsecond,
+        TimeUnit::Nanosecond => TimeUnits::Nanosecond,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn native(descr: &str) -> DType {
+        DType::parse(&descr.replace('=', if cfg!(target_endian = "little") { "<" } else { ">" })).unwrap()
+    }
+
+    #[test]
+    fn roundtrip() {
+        for descr in [
+            "'|b1'", "'|i1'", "'=i8'", "'|u1'", "'=u2'", "'=f2'", "'=f8'",
+            "'=M8[ns]'", "'=m8[s]'", "'|V5'",
+            "[('a', '=i4'), ('b', [('c', '=f4', (2, 3))])]",
+        ] {
+            let dtype = native(descr);
+            let arrow = dtype.to_arrow().unwrap();
+            assert_eq!(DType::from_arrow(&arrow).unwrap(), dtype, "{}", descr);
+        }
+    }
+
+    #[test]
+    fn lossy() {
+        assert_eq!(native("'>i4'").to_arrow().unwrap(), DataType::Int32);
+        assert_eq!(native("'|S4'").to_arrow().unwrap(), DataType::Binary);
+        assert_eq!(native("'<U4'").to_arrow().unwrap(), DataType::Utf8);
+
+        let padded = native("[('a', '|u1'), ('', '|V3'), ('b', '=i4')]").to_arrow().unwrap();
+        assert_eq!(DType::from_arrow(&padded).unwrap(), native("[('a', '|u1'), ('b', '=i4')]"));
+
+        let timestamp = DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+        assert_eq!(DType::from_arrow(&timestamp).unwrap(), native("'=M8[us]'"));
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(native("'<c16'").to_arrow().is_err());
+        assert!(native("'<f16'").to_arrow().is_err());
+        assert!(native("'<M8[D]'").to_arrow().is_err());
+        assert!(DType::Array(3, Box::new(native("'=i4'"))).to_arrow().is_err());
+        assert!(DType::from_arrow(&DataType::Utf8).is_err());
+        assert!(DType::from_arrow(&DataType::Null).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! Validated construction of [`DType`] values.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::header::{DType, Field};
+use crate::type_str::{Endianness, TimeUnits, TypeChar, TypeStr};
+
+/// Builder for a scalar [`DType`], created by [`DType::scalar`].
+///
+/// ```
+/// # fn main() -> Result<(), npyz::DTypeBuildError> {
+/// use npyz::{DType, Endianness, TypeChar, TimeUnits};
+///
+/// let dtype = DType::scalar(TypeChar::Int, 4).endianness(Endianness::Big).build()?;
+/// assert_eq!(dtype.descr(), "'>i4'");
+///
+/// let dtype = DType::scalar(TypeChar::DateTime, 8).endianness(Endianness::Little).time_units(TimeUnits::Second).build()?;
+/// assert_eq!(dtype.descr(), "'<M8[s]'");
+///
+/// // invalid sizes are caught
+/// assert!(DType::scalar(TypeChar::Int, 3).build().is_err());
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScalarDTypeBuilder {
+    type_char: TypeChar,
+    size: u64,
+    endianness: Option<Endianness>,
+    time_units: Option<TimeUnits>,
+}
+
+/// Builder for a structured [`DType`], created by [`DType::record`].
+///
+/// Fields are laid out in the order they are added.  Fields may optionally be given an explicit
+/// byte offset, in which case the gap since the previous field is filled with an unnamed `|V` padding
+/// field, the same way numpy represents such dtypes in the header of an NPY file.
+///
+/// ```
+/// # fn main() -> Result<(), npyz::DTypeBuildError> {
+/// use npyz::{DType, TypeChar};
+///
+/// let int = DType::scalar(TypeChar::Int, 4).build()?;
+/// let float = DType::scalar(TypeChar::Float, 8).build()?;
+///
+/// let dtype = DType::record()
+///     .field("a", int.clone())
+///     .field_at("b", 8, float)
+///     .array_field("c", int.clone(), &[2, 3])
+///     .build()?;
+/// assert_eq!(dtype.num_bytes(), Some(40));
+///
+/// // overlapping fields are caught
+/// let result = DType::record().field("a", int.clone()).field_at("b", 2, int).build();
+/// assert!(result.is_err());
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RecordDTypeBuilder {
+    fields: Vec<(String, Option<u64>, DType)>,
+    item_size: Option<u64>,
+    aligned: bool,
+}
+
+/// Error returned when a [`ScalarDTypeBuilder`] or [`RecordDTypeBuilder`] describes an invalid [`DType`].
+#[derive(Debug, Clone)]
+pub struct DTypeBuildError(ErrorKind);
+
+#[derive(Debug, Clone)]
+enum ErrorKind {
+    InvalidTypeStr(crate::ParseTypeStrError),
+    EmptyName,
+    DuplicateName(String),
+    Overlap { name: String, offset: u64, end_of_previous: u64 },
+    ItemSizeTooSmall { item_size: u64, required: u64 },
+    TooLarge,
+}
+
+impl std::error::Error for DTypeBuildError {}
+
+impl fmt::Display for DTypeBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            ErrorKind::InvalidTypeStr(e) => write!(f, "{}", e),
+            ErrorKind::EmptyName => write!(f, "record fields must have a non-empty name"),
+            ErrorKind::DuplicateName(name) => write!(f, "duplicate field name {:?}", name),
+            ErrorKind::Overlap { name, offset, end_of_previous } => write!(
+                f, "field {:?} at offset {} overlaps the previous field, which ends at offset {}",
+                name, offset, end_of_previous,
+            ),
+            ErrorKind::ItemSizeTooSmall { item_size, required } => write!(
+                f, "item size {} is too small for the fields, which require {} bytes",
+                item_size, required,
+            ),
+            ErrorKind::TooLarge => write!(f, "dtype is larger than usize!"),
+        }
+    }
+}
+
+impl DType {
+    /// Begin building a scalar `DType` from a type character and a size field.
+    ///
+    /// Unless specified, the endianness defaults to the machine endianness for types where it matters.
+    pub fn scalar(type_char: TypeChar, size: u64) -> ScalarDTypeBuilder {
+        ScalarDTypeBuilder { type_char, size, endianness: None, time_units: None }
+    }
+
+    /// Begin building a structured `DType`.
+    pub fn record() -> RecordDTypeBuilder {
+        RecordDTypeBuilder::default()
+    }
+}
+
+impl ScalarDTypeBuilder {
+    /// Set the endianness.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = Some(endianness);
+        self
+    }
+
+    /// Set the time units.  These are required for [`TypeChar::TimeDelta`] and [`TypeChar::DateTime`].
+    pub fn time_units(mut self, time_units: TimeUnits) -> Self {
+        self.time_units = Some(time_units);
+        self
+    }
+
+    /// Build the [`TypeStr`], validating it the same way as [`str::parse`] would.
+    pub fn build_type_str(&self) -> Result<TypeStr, DTypeBuildError> {
+        let ScalarDTypeBuilder { type_char, size, endianness, time_units } = *self;
+        let endianness = endianness.unwrap_or_else(|| {
+            match type_char.requires_endianness(size) {
+                true => Endianness::of_machine(),
+                false => Endianness::Irrelevant,
+            }
+        });
+        let type_str = TypeStr { endianness, type_char, size, time_units }.validate();
+        type_str.map_err(|e| DTypeBuildError(ErrorKind::InvalidTypeStr(e)))
+    }
+
+    /// Build the [`DType`].
+    pub fn build(&self) -> Result<DType, DTypeBuildError> {
+        self.build_type_str().map(DType::Plain)
+    }
+}
+
+impl RecordDTypeBuilder {
+    /// Add a field immediately after the previous one.
+    pub fn field(mut self, name: impl Into<String>, dtype: DType) -> Self {
+        self.fields.push((name.into(), None, dtype));
+        self
+    }
+
+    /// Add a field at a specific byte offset from the beginning of the record.
+    ///
+    /// The offset must not lie before the end of the previous field.
+    pub fn field_at(mut self, name: impl Into<String>, offset: u64, dtype: DType) -> Self {
+        self.fields.push((name.into(), Some(offset), dtype));
+        self
+    }
+
+    /// Add a field whose type is a (possibly multidimensional) array of `dtype`.
+    ///
+    /// This is equivalent to the 3-tuple form `(name, dtype, shape)` in a numpy dtype description.
+    pub fn array_field(self, name: impl Into<String>, dtype: DType, shape: &[u64]) -> Self {
+        let dtype = shape.iter().rev().fold(dtype, |inner, &dim| DType::Array(dim, Box::new(inner)));
+        self.field(name, dtype)
+    }
+
+    /// Set the total size of a record, adding padding after the last field.
+    ///
+    /// By default, a record ends immediately after its last field.
+    pub fn item_size(mut self, item_size: u64) -> Self {
+        self.item_size = Some(item_size);
+        self
+    }
+
+    /// Lay out fields the way numpy does for `align=True`, which is also how a C compiler would lay out
+    /// an equivalent struct.
+    ///
+    /// Fields without an explicit offset are placed at the next multiple of their [`DType::alignment`],
+    /// and the size of the record is rounded up to a multiple of the largest alignment.
+    pub fn aligned(mut self) -> Self {
+        self.aligned = true;
+        self
+    }
+
+    /// Validate the fields and build the [`DType`].
+    pub fn build(&self) -> Result<DType, DTypeBuildError> {
+        let mut fields = vec![];
+        let mut names = HashSet::new();
+        let mut end = 0u64;
+        for (name, offset, dtype) in &self.fields {
+            if name.is_empty() {
+                return Err(DTypeBuildError(ErrorKind::EmptyName));
+            }
+            if !names.insert(name) {
+                return Err(DTypeBuildError(ErrorKind::DuplicateName(name.clone())));
+            }
+            let offset = match (offset, self.aligned) {
+                (Some(offset), _) => *offset,
+                (None, false) => end,
+                (None, true) => round_up(end, dtype.alignment() as u64).ok_or(DTypeBuildError(ErrorKind::TooLarge))?,
+            };
+            if offset < end {
+                let name = name.clone();
+                return Err(DTypeBuildError(ErrorKind::Overlap { name, offset, end_of_previous: end }));
+            }
+            fields.extend(padding_field(offset - end));
+
+            let size = dtype.num_bytes().ok_or(DTypeBuildError(ErrorKind::TooLarge))?;
+            end = offset.checked_add(size as u64).ok_or(DTypeBuildError(ErrorKind::TooLarge))?;
+            fields.push(Field { name: name.clone(), dtype: dtype.clone() });
+        }
+
+        if self.aligned {
+            let alignment = self.fields.iter().map(|(_, _, dtype)| dtype.alignment()).max().unwrap_or(1);
+            let item_size = round_up(end, alignment as u64).ok_or(DTypeBuildError(ErrorKind::TooLarge))?;
+            fields.extend(padding_field(item_size - end));
+            end = item_size;
+        }
+        if let Some(item_size) = self.item_size {
+            if item_size < end {
+                return Err(DTypeBuildError(ErrorKind::ItemSizeTooSmall { item_size, required: end }));
+            }
+            fields.extend(padding_field(item_size - end));
+        }
+
+        let dtype = DType::Record(fields);
+        dtype.num_bytes().ok_or(DTypeBuildError(ErrorKind::TooLarge))?;
+        Ok(dtype)
+    }
+}
+
+fn round_up(x: u64, multiple: u64) -> Option<u64> {
+    Some(x.checked_add(multiple - 1)? / multiple * multiple)
+}
+
+fn padding_field(size: u64) -> Option<Field> {
+    match size {
+        0 => None,
+        _ => Some(Field {
+            name: String::new(),
+            dtype: DType::Plain(TypeStr { endianness: Endianness::Irrelevant, type_char: TypeChar::RawData, size, time_units: None }),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(size: u64) -> DType {
+        DType::scalar(TypeChar::Int, size).endianness(Endianness::Little).build().unwrap()
+    }
+
+    #[test]
+    fn scalar() {
+        assert_eq!(int(4), DType::parse("'<i4'").unwrap());
+        assert_eq!(DType::scalar(TypeChar::ByteStr, 3).build().unwrap(), DType::parse("'|S3'").unwrap());
+        assert_eq!(DType::scalar(TypeChar::Int, 1).build().unwrap(), DType::parse("'|i1'").unwrap());
+
+        assert!(DType::scalar(TypeChar::Float, 3).build().is_err());
+        assert!(DType::scalar(TypeChar::TimeDelta, 8).build().is_err());
+        assert!(DType::scalar(TypeChar::Int, 8).time_units(TimeUnits::Day).build().is_err());
+        assert!(DType::scalar(TypeChar::Int, 8).endianness(Endianness::Irrelevant).build().is_err());
+    }
+
+    #[test]
+    fn record_with_offsets() {
+        let dtype = {
+            DType::record()
+                .field("a", int(4))
+                .field_at("b", 8, int(2))
+                .array_field("c", int(1), &[2, 3])
+                .item_size(24)
+                .build().unwrap()
+        };
+        let expected = DType::parse("[('a', '<i4'), ('', '|V4'), ('b', '<i2'), ('c', '<i1', (2, 3)), ('', '|V8')]").unwrap();
+        assert_eq!(dtype, expected);
+        assert_eq!(dtype.num_bytes(), Some(24));
+    }
+
+    #[test]
+    fn record_aligned() {
+        let dtype = {
+            DType::record()
+                .field("a", int(1))
+                .field("b", int(4))
+                .field("c", int(2))
+                .aligned()
+                .build().unwrap()
+        };
+        let expected = DType::parse("[('a', '<i1'), ('', '|V3'), ('b', '<i4'), ('c', '<i2'), ('', '|V2')]").unwrap();
+        assert_eq!(dtype, expected);
+        assert_eq!(dtype.field_offsets(), Some(vec![0, 1, 4, 8, 10]));
+        assert_eq!(dtype.alignment(), 4);
+        assert!(dtype.is_aligned());
+    }
+
+    #[test]
+    fn record_errors() {
+        assert!(DType::record().field("a", int(4)).field_at("b", 3, int(4)).build().is_err());
+        assert!(DType::record().field("a", int(4)).field("a", int(4)).build().is_err());
+        assert!(DType::record().field("", int(4)).build().is_err());
+        assert!(DType::record().field("a", int(4)).item_size(3).build().is_err());
+        assert!(DType::record().field("a", int(4)).field_at("b", u64::MAX, int(4)).build().is_err());
+
+        assert!(DType::record().field("a", int(4)).field_at("b", 4, int(4)).item_size(8).build().is_ok());
+    }
+}
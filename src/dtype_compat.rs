@@ -0,0 +1,207 @@
+//! Checking whether one [`DType`] can stand in for another.
+
+use crate::header::DType;
+use crate::type_str::{TimeUnits, TypeChar, TypeStr};
+
+/// Controls which differences are tolerated by [`DType::compatible_with`].
+///
+/// The [`Default`] policy is the same as [`CompatPolicy::exact`].  The constructors can be combined
+/// by setting the other fields, e.g. `CompatPolicy { allow_field_subset: true, ..CompatPolicy::safe_cast() }`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CompatPolicy {
+    /// Ignore differences in byte order.
+    pub ignore_endianness: bool,
+    /// Allow scalar types that can be converted to the expected type without loss, following numpy's
+    /// `casting='safe'` rules. (e.g. `<i2` is compatible with `<i8` and `<f4`, but not `<u8`)
+    ///
+    /// This implies `ignore_endianness`.
+    pub allow_safe_cast: bool,
+    /// Allow records to contain fields that aren't expected, and to have the expected fields in any order.
+    pub allow_field_subset: bool,
+}
+
+impl CompatPolicy {
+    /// Require the types to be identical.
+    pub fn exact() -> Self {
+        CompatPolicy::default()
+    }
+
+    /// Require the types to be identical except for byte order.
+    pub fn ignore_endianness() -> Self {
+        CompatPolicy { ignore_endianness: true, ..CompatPolicy::default() }
+    }
+
+    /// Allow scalars to be safely widened to the expected type.  See [`CompatPolicy::allow_safe_cast`].
+    pub fn safe_cast() -> Self {
+        CompatPolicy { ignore_endianness: true, allow_safe_cast: true, ..CompatPolicy::default() }
+    }
+
+    /// Allow records to contain extra fields.  See [`CompatPolicy::allow_field_subset`].
+    pub fn field_subset() -> Self {
+        CompatPolicy { allow_field_subset: true, ..CompatPolicy::default() }
+    }
+}
+
+impl DType {
+    /// Check whether data of this dtype (e.g. the dtype of a file) can be used where `expected` is wanted.
+    ///
+    /// Which differences are permitted depends on the [`CompatPolicy`].  Records must always have the expected
+    /// field names, and arrays must always have the expected shape.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::{DType, CompatPolicy};
+    ///
+    /// let file_dtype = DType::parse("[('id', '>i4'), ('extra', '|S4'), ('x', '<f4')]")?;
+    /// let expected = DType::parse("[('x', '<f8'), ('id', '<i8')]")?;
+    ///
+    /// assert!(!file_dtype.compatible_with(&expected, CompatPolicy::exact()));
+    /// assert!(!file_dtype.compatible_with(&expected, CompatPolicy::safe_cast()));
+    /// assert!(file_dtype.compatible_with(&expected, CompatPolicy { allow_field_subset: true, ..CompatPolicy::safe_cast() }));
+    /// # Ok(()) }
+    /// ```
+    pub fn compatible_with(&self, expected: &DType, policy: CompatPolicy) -> bool {
+        match (self, expected) {
+            (DType::Plain(actual), DType::Plain(expected)) => type_str_compatible_with(actual, expected, policy),
+            (DType::Array(actual_n, actual), DType::Array(expected_n, expected)) => {
+                actual_n == expected_n && actual.compatible_with(expected, policy)
+            },
+            (DType::Record(actual), DType::Record(expected)) => {
+                if policy.allow_field_subset {
+                    expected.iter().all(|expected| {
+                        actual.iter().find(|actual| actual.name == expected.name)
+                            .is_some_and(|actual| actual.dtype.compatible_with(&expected.dtype, policy))
+                    })
+                } else {
+                    actual.len() == expected.len() && actual.iter().zip(expected).all(|(actual, expected)| {
+                        actual.name == expected.name && actual.dtype.compatible_with(&expected.dtype, policy)
+                    })
+                }
+            },
+            _ => false,
+        }
+    }
+}
+
+fn type_str_compatible_with(actual: &TypeStr, expected: &TypeStr, policy: CompatPolicy) -> bool {
+    if actual == expected {
+        return true;
+    }
+    if !(policy.ignore_endianness || policy.allow_safe_cast) {
+        return false;
+    }
+    if policy.allow_safe_cast {
+        return can_cast_safely(actual, expected);
+    }
+    // the endianness of single-byte types may be written either as '|' or not
+    let ignore_endianness = |ty: &TypeStr| (ty.type_char, ty.size, ty.time_units);
+    ignore_endianness(actual) == ignore_endianness(expected)
+}
+
+pub(crate) fn can_cast_safely(from: &TypeStr, to: &TypeStr) -> bool {
+    use TypeChar::*;
+
+    // Size of a float that can hold every value of an integer
+    let float_size_for_int = |int_size: u64| match int_size {
+        8 => 8,  // numpy considers this safe
+        _ => 2 * int_size,
+    };
+
+    match (from.type_char, to.type_char) {
+        (Bool, Bool | Int | Uint | Float | Complex) => true,
+
+        (Uint, Uint) | (Int, Int) | (Float, Float) | (Complex, Complex) => from.size <= to.size,
+        (Uint, Int) => from.size < to.size,
+        (Uint | Int, Float) => float_size_for_int(from.size) <= to.size,
+        (Uint | Int, Complex) => float_size_for_int(from.size) <= to.size / 2,
+        (Float, Complex) => from.size <= to.size / 2,
+
+        (DateTime, DateTime) | (TimeDelta, TimeDelta) => {
+            let (from_units, to_units) = (from.time_units.unwrap(), to.time_units.unwrap());
+            from_units == to_units || (time_units_rank(from_units) < time_units_rank(to_units) && (
+                // years and months do not have a fixed length, so for timedeltas they can only convert to each other
+                from.type_char == DateTime || time_units_rank(to_units) <= time_units_rank(TimeUnits::Month)
+            ))
+        },
+
+        (ByteStr, ByteStr | UnicodeStr) | (UnicodeStr, UnicodeStr) => from.size <= to.size,
+        (RawData, RawData) => from.size == to.size,
+
+        _ => false,
+    }
+}
+
+// Coarsest units have the lowest rank.
+fn time_units_rank(units: TimeUnits) -> u32 {
+    match units {
+        TimeUnits::Year => 0,
+        TimeUnits::Month => 1,
+        TimeUnits::Week => 2,
+        TimeUnits::Day => 3,
+        TimeUnits::Hour => 4,
+        TimeUnits::Minute => 5,
+        TimeUnits::Second => 6,
+        TimeUnits::Millisecond => 7,
+        TimeUnits::Microsecond => 8,
+        TimeUnits::Nanosecond => 9,
+        TimeUnits::Picosecond => 10,
+        TimeUnits::Femtosecond => 11,
+        TimeUnits::Attosecond => 12,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(actual: &str, expected: &str, policy: CompatPolicy) -> bool {
+        let actual = DType::parse(actual).unwrap();
+        let expected = DType::parse(expected).unwrap();
+        actual.compatible_with(&expected, policy)
+    }
+
+    #[test]
+    fn endianness() {
+        assert!(check("'<i4'", "'<i4'", CompatPolicy::exact()));
+        assert!(!check("'>i4'", "'<i4'", CompatPolicy::exact()));
+        assert!(check("'>i4'", "'<i4'", CompatPolicy::ignore_endianness()));
+        assert!(check("'|u1'", "'<u1'", CompatPolicy::ignore_endianness()));
+        assert!(!check("'>i4'", "'<i8'", CompatPolicy::ignore_endianness()));
+        assert!(check("[('a', '>f8', (3,))]", "[('a', '<f8', (3,))]", CompatPolicy::ignore_endianness()));
+        assert!(!check("[('a', '>f8', (3,))]", "[('a', '<f8', (2,))]", CompatPolicy::ignore_endianness()));
+    }
+
+    #[test]
+    fn safe_cast() {
+        let safe = |a, b| check(a, b, CompatPolicy::safe_cast());
+        assert!(safe("'>i2'", "'<i8'"));
+        assert!(!safe("'<i8'", "'<i2'"));
+        assert!(!safe("'<i2'", "'<u8'"));
+        assert!(safe("'<u2'", "'<i4'"));
+        assert!(!safe("'<u4'", "'<i4'"));
+        assert!(safe("'<i2'", "'<f4'"));
+        assert!(!safe("'<i4'", "'<f4'"));
+        assert!(safe("'<i8'", "'<f8'"));
+        assert!(safe("'<f4'", "'<c8'"));
+        assert!(!safe("'<f8'", "'<c8'"));
+        assert!(safe("'|b1'", "'<f8'"));
+        assert!(!safe("'<f4'", "'|b1'"));
+        assert!(safe("'|S3'", "'<U5'"));
+        assert!(!safe("'<U3'", "'|S5'"));
+        assert!(safe("'<M8[s]'", "'<M8[ns]'"));
+        assert!(!safe("'<M8[ns]'", "'<M8[s]'"));
+        assert!(safe("'<M8[Y]'", "'<M8[D]'"));
+        assert!(!safe("'<m8[Y]'", "'<m8[D]'"));
+        assert!(!safe("'<m8[s]'", "'<M8[s]'"));
+    }
+
+    #[test]
+    fn field_subset() {
+        let actual = "[('a', '<i4'), ('b', '<f8'), ('c', '|S2')]";
+        assert!(check(actual, "[('c', '|S2'), ('a', '<i4')]", CompatPolicy::field_subset()));
+        assert!(!check(actual, "[('c', '|S2'), ('d', '<i4')]", CompatPolicy::field_subset()));
+        assert!(!check(actual, "[('c', '|S2'), ('a', '<i4')]", CompatPolicy::exact()));
+        assert!(!check(actual, "[('a', '<i8')]", CompatPolicy::field_subset()));
+        assert!(check(actual, "[('a', '<i8')]", CompatPolicy { allow_field_subset: true, ..CompatPolicy::safe_cast() }));
+    }
+}
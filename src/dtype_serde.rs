@@ -0,0 +1,239 @@
+//! `serde` support for [`DType`].
+//!
+//! _This module is only available with the **`"serde"`** feature._
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::header::{DType, Field};
+use crate::type_str::TypeStr;
+
+/// A [JSON Schema](https://json-schema.org/) describing the serialized form of a [`DType`].
+///
+/// The format mirrors the `descr` of numpy:
+///
+/// * A scalar dtype is its type string, e.g. `"<i4"`.
+/// * A record dtype is a list of fields.  Each field is an object with a `"name"`, a `"dtype"`,
+///   and an optional `"shape"` which makes the field an array.
+///
+/// ```json
+/// [
+///     {"name": "id", "dtype": "<i8"},
+///     {"name": "pos", "dtype": "<f4", "shape": [3]},
+///     {"name": "meta", "dtype": [{"name": "flag", "dtype": "|b1"}]}
+/// ]
+/// ```
+///
+/// This format is stable and will only change in a major version bump of `npyz`.
+pub const DTYPE_JSON_SCHEMA: &str = r##"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "$id": "https://docs.rs/npyz/schema/dtype.json",
+  "title": "npyz DType",
+  "$ref": "#/$defs/dtype",
+  "$defs": {
+    "dtype": {
+      "oneOf": [
+        { "$ref": "#/$defs/type_str" },
+        { "type": "array", "items": { "$ref": "#/$defs/field" } }
+      ]
+    },
+    "type_str": {
+      "type": "string",
+      "pattern": "^[<>|=][biufcmMSaUV][0-9]+(\\[[A-Za-z]+\\])?$"
+    },
+    "field": {
+      "type": "object",
+      "properties": {
+        "name": { "type": "string" },
+        "dtype": { "$ref": "#/$defs/dtype" },
+        "shape": { "type": "array", "items": { "type": "integer", "minimum": 0 } }
+      },
+      "required": ["name", "dtype"],
+      "additionalProperties": false
+    }
+  }
+}"##;
+
+/// _This impl is only available with the **`"serde"`** feature._
+impl Serialize for TypeStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// _This impl is only available with the **`"serde"`** feature._
+impl<'de> Deserialize<'de> for TypeStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TypeStrVisitor;
+
+        impl<'de> Visitor<'de> for TypeStrVisitor {
+            type Value = TypeStr;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a numpy type string")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<TypeStr, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(TypeStrVisitor)
+    }
+}
+
+/// Serializes in the format described by [`DTYPE_JSON_SCHEMA`].
+///
+/// _This impl is only available with the **`"serde"`** feature._
+///
+/// # Errors
+///
+/// [`DType::Array`] can only be serialized as the dtype of a field.  Serializing it on its own is an error.
+impl Serialize for DType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DType::Plain(ty) => ty.serialize(serializer),
+            DType::Record(fields) => serializer.collect_seq(fields),
+            DType::Array(..) => Err(serde::ser::Error::custom("array dtypes can only be serialized as part of a record")),
+        }
+    }
+}
+
+/// _This impl is only available with the **`"serde"`** feature._
+impl Serialize for Field {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut shape = vec![];
+        let mut dtype = &self.dtype;
+        while let DType::Array(dim, inner) = dtype {
+            shape.push(*dim);
+            dtype = inner;
+        }
+
+        let mut map = serializer.serialize_map(Some(if shape.is_empty() { 2 } else { 3 }))?;
+        map.serialize_entry("name", &self.name)?;
+        map.serialize_entry("dtype", dtype)?;
+        if !shape.is_empty() {
+            map.serialize_entry("shape", &shape)?;
+        }
+        map.end()
+    }
+}
+
+/// Deserializes from the format described by [`DTYPE_JSON_SCHEMA`].
+///
+/// _This impl is only available with the **`"serde"`** feature._
+impl<'de> Deserialize<'de> for DType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DTypeVisitor;
+
+        impl<'de> Visitor<'de> for DTypeVisitor {
+            type Value = DType;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a numpy type string or a list of fields")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<DType, E> {
+                s.parse().map(DType::Plain).map_err(E::custom)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DType, A::Error> {
+                let mut fields = vec![];
+                while let Some(field) = seq.next_element::<Field>()? {
+                    fields.push(field);
+                }
+                Ok(DType::Record(fields))
+            }
+        }
+
+        deserializer.deserialize_any(DTypeVisitor)
+    }
+}
+
+/// _This impl is only available with the **`"serde"`** feature._
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldVisitor;
+
+        impl<'de> Visitor<'de> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a field with a name and a dtype")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Field, A::Error> {
+                let mut name = None;
+                let mut dtype = None;
+                let mut shape = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match &key[..] {
+                        "name" if name.is_none() => name = Some(map.next_value::<String>()?),
+                        "dtype" if dtype.is_none() => dtype = Some(map.next_value::<DType>()?),
+                        "shape" if shape.is_none() => shape = Some(map.next_value::<Vec<u64>>()?),
+                        "name" | "dtype" | "shape" => return Err(de::Error::custom(format_args!("duplicate field `{}`", key))),
+                        _ => return Err(de::Error::unknown_field(&key, &["name", "dtype", "shape"])),
+                    }
+                }
+                let name = name.ok_or_else(|| de::Error::missing_field("name"))?;
+                let mut dtype = dtype.ok_or_else(|| de::Error::missing_field("dtype"))?;
+                for dim in shape.unwrap_or_default().into_iter().rev() {
+                    dtype = DType::Array(dim, Box::new(dtype));
+                }
+                Ok(Field { name, dtype })
+            }
+        }
+
+        deserializer.deserialize_map(FieldVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_test::{assert_tokens, assert_de_tokens_error, assert_ser_tokens_error, Token};
+
+    fn field_tokens(name: &'static str, dtype: &'static str, shape: Option<&[u64]>) -> Vec<Token> {
+        let mut tokens = vec![Token::Map { len: Some(2 + shape.is_some() as usize) }];
+        tokens.extend([Token::Str("name"), Token::Str(name), Token::Str("dtype"), Token::Str(dtype)]);
+        if let Some(shape) = shape {
+            tokens.extend([Token::Str("shape"), Token::Seq { len: Some(shape.len()) }]);
+            tokens.extend(shape.iter().map(|&dim| Token::U64(dim)));
+            tokens.push(Token::SeqEnd);
+        }
+        tokens.push(Token::MapEnd);
+        tokens
+    }
+
+    #[test]
+    fn roundtrip() {
+        assert_tokens(&DType::parse("'<U3'").unwrap(), &[Token::Str("<U3")]);
+        assert_tokens(&DType::parse("'>M8[ns]'").unwrap(), &[Token::Str(">M8[ns]")]);
+
+        let dtype = DType::parse("[('id', '<i8'), ('pos', '<f4', (3, 2))]").unwrap();
+        let mut tokens = vec![Token::Seq { len: Some(2) }];
+        tokens.extend(field_tokens("id", "<i8", None));
+        tokens.extend(field_tokens("pos", "<f4", Some(&[3, 2])));
+        tokens.push(Token::SeqEnd);
+        assert_tokens(&dtype, &tokens);
+    }
+
+    #[test]
+    fn errors() {
+        assert_de_tokens_error::<DType>(&[Token::Str("<i3")], "Type string '<i3' has invalid size. Valid sizes are: [1, 2, 4, 8]");
+        assert_de_tokens_error::<DType>(
+            &[Token::Seq { len: Some(1) }, Token::Map { len: Some(1) }, Token::Str("name"), Token::Str("a"), Token::MapEnd],
+            "missing field `dtype`",
+        );
+        assert_de_tokens_error::<DType>(
+            &[Token::Seq { len: Some(1) }, Token::Map { len: Some(1) }, Token::Str("extra")],
+            "unknown field `extra`, expected one of `name`, `dtype`, `shape`",
+        );
+        assert_ser_tokens_error(
+            &DType::Array(3, Box::new(DType::parse("'<i4'").unwrap())), &[],
+            "array dtypes can only be serialized as part of a record",
+        );
+    }
+}
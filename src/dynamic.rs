@@ -0,0 +1,686 @@
+//! Reading and writing arrays whose dtype is only known at runtime.
+
+use std::io::{self, Read, Write};
+
+use crate::error::Error;
+use crate::header::{DType, Field};
+use crate::read::Order;
+use crate::serialize::{Deserialize, DTypeError, TypeRead};
+use crate::type_str::{Endianness, TypeChar, TypeStr};
+
+/// A value of any dtype, for use with [`write_dyn`] and [`NpyFile::data_dyn`][crate::NpyFile::data_dyn].
+///
+/// Each variant can be written to the following kinds of dtype, and is read from those that are
+/// listed first: (e.g. `Int` is read from `i` types, and `Uint` from `u` types)
+///
+/// * `Bool`: `b1`.
+/// * `Int` and `Uint`: any integer type (`i`, `u`) that can hold the value, and the floats `f4` and `f8`.
+/// * `Float`: `f4` and `f8`.
+/// * `Complex`: `c8` and `c16`, as `(re, im)`.
+/// * `Time`: `datetime64` and `timedelta64` (`M8`, `m8`) of any unit, with `None` for NaT.
+/// * `Bytes`: `S` types of at least its length (it is padded with NULs, which are removed when reading),
+///   and `V` types of exactly its length.
+/// * `Str`: `U` types of at least its length in code points.  (reading fails on surrogates)
+/// * `Array`: subarray dtypes, with one value per element.
+/// * `Record`: record dtypes, with one value per field, in order.  (including unnamed padding fields)
+///
+/// Half and extended precision floats (`f2`, `f16`, `c32`) are not supported.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynValue {
+    /// A boolean.
+    Bool(bool),
+    /// A signed integer.
+    Int(i64),
+    /// An unsigned integer.
+    Uint(u64),
+    /// A float.
+    Float(f64),
+    /// A complex number as `(re, im)`.
+    Complex(f64, f64),
+    /// A `datetime64` or `timedelta64` in the units of the dtype, or `None` for NaT.
+    Time(Option<i64>),
+    /// A byte string or raw data.
+    Bytes(Vec<u8>),
+    /// A unicode string.
+    Str(String),
+    /// The elements of a subarray.
+    Array(Vec<DynValue>),
+    /// The fields of a record.
+    Record(Vec<DynValue>),
+}
+
+impl DynValue {
+    // for error messages
+    fn kind(&self) -> &'static str {
+        match self {
+            DynValue::Bool(_) => "a bool",
+            DynValue::Int(_) => "a signed integer",
+            DynValue::Uint(_) => "an unsigned integer",
+            DynValue::Float(_) => "a float",
+            DynValue::Complex(..) => "a complex number",
+            DynValue::Time(_) => "a time",
+            DynValue::Bytes(_) => "bytes",
+            DynValue::Str(_) => "a string",
+            DynValue::Array(_) => "an array",
+            DynValue::Record(_) => "a record",
+        }
+    }
+}
+
+impl From<bool> for DynValue {
+    fn from(x: bool) -> Self { DynValue::Bool(x) }
+}
+
+impl From<i64> for DynValue {
+    fn from(x: i64) -> Self { DynValue::Int(x) }
+}
+
+impl From<u64> for DynValue {
+    fn from(x: u64) -> Self { DynValue::Uint(x) }
+}
+
+impl From<f64> for DynValue {
+    fn from(x: f64) -> Self { DynValue::Float(x) }
+}
+
+impl From<String> for DynValue {
+    fn from(x: String) -> Self { DynValue::Str(x) }
+}
+
+impl From<&str> for DynValue {
+    fn from(x: &str) -> Self { DynValue::Str(x.to_string()) }
+}
+
+/// Write a complete NPY file in C order, with a dtype that is only known at runtime.
+///
+/// Each value in `rows` is one element of the array.  It is checked against the dtype as described
+/// for [`DynValue`] before it is written; if it does not fit, [`Error::InvalidInput`] is returned, with
+/// the index of the record and the field in its [context][`Error::context`].  It is also an error if the
+/// number of rows differs from the number of elements in the shape.  Because the data is written as it
+/// is validated, the output is incomplete after an error.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{DType, DynValue};
+///
+/// // e.g. from the header of a CSV file
+/// let dtype = DType::parse("[('name', '<U8'), ('count', '<i4'), ('mean', '<f8')]")?;
+/// let rows = vec![
+///     DynValue::Record(vec!["apples".into(), 3i64.into(), 0.5.into()]),
+///     DynValue::Record(vec!["pears".into(), 7i64.into(), 1.25.into()]),
+/// ];
+/// let mut bytes = vec![];
+/// npyz::write_dyn(&mut bytes, &dtype, &[2], rows)?;
+///
+/// let npy = npyz::NpyFile::new(&bytes[..])?;
+/// assert_eq!(npy.dtype(), dtype);
+///
+/// let bad = vec![DynValue::Record(vec!["cherries".into(), 3e9.into(), 0.0.into()])];
+/// assert!(npyz::write_dyn(&mut vec![], &dtype, &[1], bad).is_err());
+/// # Ok(()) }
+/// ```
+pub fn write_dyn(mut writer: impl Write, dtype: &DType, shape: &[u64], rows: impl IntoIterator<Item = DynValue>) -> io::Result<()> {
+    check_supported(dtype)?;
+    if let DType::Array(..) = dtype {
+        let msg = "a subarray dtype can only be used for a field of a record; add its dimensions to the shape instead";
+        return Err(Error::InvalidInput(msg.to_string()).into());
+    }
+    let num_records = match shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim)) {
+        Some(n) => n,
+        None => return Err(Error::InvalidInput(format!("shape {:?} is too large", shape)).into()),
+    };
+    writer.write_all(&crate::write::header_bytes(dtype, Order::C, shape, &[])?)?;
+
+    let mut buf = vec![];
+    let mut index = 0;
+    for row in rows {
+        if index == num_records {
+            return Err(Error::InvalidInput(format!("more than {} rows for shape {:?}", num_records, shape)).into());
+        }
+        buf.clear();
+        encode(dtype, &row, &mut buf).map_err(|e| Error::at_record(e, index, None))?;
+        writer.write_all(&buf)?;
+        index += 1;
+    }
+    if index < num_records {
+        return Err(Error::InvalidInput(format!("expected {} rows for shape {:?}, got {}", num_records, shape, index)).into());
+    }
+    writer.flush()
+}
+
+impl DType {
+    /// Choose a dtype that can hold every one of the sample values, e.g. to import data without a schema.
+    ///
+    /// The samples can then be written with this dtype by [`write_dyn`].  The dtype is chosen as follows:
+    ///
+    /// * integers: the narrowest integer type that holds all of them, which is unsigned if they are all
+    ///   [`DynValue::Uint`].  Like in numpy, a mix of negative integers and integers beyond the range
+    ///   of `i8` gives `f8`.
+    /// * integers and floats: `f8`.
+    /// * complex numbers: `c16`.
+    /// * booleans: `b1`.
+    /// * byte strings and unicode strings: `S` or `U` with the length of the longest one.  (at least 1)
+    /// * arrays: a subarray of the common length, whose element type is inferred from all of their elements.
+    ///   (a subarray can only be written as a field of a record)
+    /// * records: a record with fields named `f0`, `f1`, ... (as numpy names them), each inferred from
+    ///   the corresponding value of every record.
+    ///
+    /// Without any samples, the result is `f8`, like numpy's default.  Scalar types have the native byte order.
+    ///
+    /// Returns [`Error::InvalidInput`] if the values cannot share a dtype, e.g. strings and numbers,
+    /// arrays of different lengths, or records with different numbers of fields.  Time values are also
+    /// an error, because their units cannot be inferred.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::{DType, DynValue};
+    ///
+    /// let rows = vec![
+    ///     DynValue::Record(vec!["apples".into(), 3i64.into(), 2i64.into()]),
+    ///     DynValue::Record(vec!["kiwis".into(), (-300i64).into(), 1.5.into()]),
+    /// ];
+    /// let dtype = DType::infer(&rows)?;
+    /// assert_eq!(dtype.to_string().replace('>', "<"), "dtype([('f0', '<U6'), ('f1', '<i2'), ('f2', '<f8')])");
+    ///
+    /// let mut bytes = vec![];
+    /// npyz::write_dyn(&mut bytes, &dtype, &[2], rows)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn infer(samples: &[DynValue]) -> io::Result<DType> {
+        if samples.iter().any(|value| matches!(value, DynValue::Array(_))) {
+            let mut len = None;
+            let mut elements = vec![];
+            for value in samples {
+                match value {
+                    DynValue::Array(items) if len.is_none() || len == Some(items.len()) => {
+                        len = Some(items.len());
+                        elements.extend(items.iter().cloned());
+                    },
+                    DynValue::Array(items) => {
+                        let msg = format!("cannot infer a dtype for arrays of lengths {} and {}", len.unwrap(), items.len());
+                        return Err(Error::InvalidInput(msg).into());
+                    },
+                    other => return Err(incompatible(&samples[0], other)),
+                }
+            }
+            return Ok(DType::Array(len.unwrap() as u64, Box::new(DType::infer(&elements)?)));
+        }
+
+        if samples.iter().any(|value| matches!(value, DynValue::Record(_))) {
+            let mut columns: Option<Vec<Vec<DynValue>>> = None;
+            for value in samples {
+                let values = match value {
+                    DynValue::Record(values) => values,
+                    other => return Err(incompatible(&samples[0], other)),
+                };
+                let columns = columns.get_or_insert_with(|| vec![vec![]; values.len()]);
+                if columns.len() != values.len() {
+                    let msg = format!("cannot infer a dtype for records of {} and {} fields", columns.len(), values.len());
+                    return Err(Error::InvalidInput(msg).into());
+                }
+                columns.iter_mut().zip(values).for_each(|(column, value)| column.push(value.clone()));
+            }
+            let fields = columns.unwrap().iter().enumerate()
+                .map(|(i, column)| Ok(Field { name: format!("f{}", i), dtype: DType::infer(column)? }))
+                .collect::<io::Result<_>>()?;
+            return Ok(DType::Record(fields));
+        }
+
+        let first = match samples.first() {
+            Some(first) => first,
+            None => return Ok(DType::new_scalar(TypeStr::with_auto_endianness(TypeChar::Float, 8, None))),
+        };
+        let (type_char, size) = match first {
+            DynValue::Bool(_) | DynValue::Complex(..) | DynValue::Bytes(_) | DynValue::Str(_) => {
+                let mut len = 0;
+                for value in samples {
+                    match (first, value) {
+                        (DynValue::Bool(_), DynValue::Bool(_)) | (DynValue::Complex(..), DynValue::Complex(..)) => {},
+                        (DynValue::Bytes(_), DynValue::Bytes(bytes)) => len = len.max(bytes.len()),
+                        (DynValue::Str(_), DynValue::Str(s)) => len = len.max(s.chars().count()),
+                        _ => return Err(incompatible(first, value)),
+                    }
+                }
+                match first {
+                    DynValue::Bool(_) => (TypeChar::Bool, 1),
+                    DynValue::Complex(..) => (TypeChar::Complex, 16),
+                    DynValue::Bytes(_) => (TypeChar::ByteStr, len.max(1) as u64),
+                    _ => (TypeChar::UnicodeStr, len.max(1) as u64),
+                }
+            },
+            DynValue::Int(_) | DynValue::Uint(_) | DynValue::Float(_) => infer_number(samples)?,
+            DynValue::Time(_) => {
+                return Err(Error::InvalidInput("cannot infer the units of a time".to_string()).into());
+            },
+            DynValue::Array(_) | DynValue::Record(_) => unreachable!("(BUG!) these were handled above"),
+        };
+        Ok(DType::new_scalar(TypeStr::with_auto_endianness(type_char, size, None)))
+    }
+}
+
+fn infer_number(samples: &[DynValue]) -> io::Result<(TypeChar, u64)> {
+    let (mut min, mut max) = (0i128, 0i128);
+    let (mut signed, mut float) = (false, false);
+    for value in samples {
+        match *value {
+            DynValue::Int(x) => {
+                signed = true;
+                min = min.min(x as i128);
+                max = max.max(x as i128);
+            },
+            DynValue::Uint(x) => max = max.max(x as i128),
+            DynValue::Float(_) => float = true,
+            _ => return Err(incompatible(&samples[0], value)),
+        }
+    }
+    if float || (min < 0 && max > i64::MAX as i128) {
+        return Ok((TypeChar::Float, 8));
+    }
+    let size = [1, 2, 4, 8].into_iter().find(|&size: &u32| match signed {
+        true => min >= -(1i128 << (8 * size - 1)) && max < 1i128 << (8 * size - 1),
+        false => max < 1i128 << (8 * size),
+    });
+    match (signed, size) {
+        (true, Some(size)) => Ok((TypeChar::Int, size as u64)),
+        // non-negative `Int`s mixed with `Uint`s that only fit in `u8`
+        (true, None) => Ok((TypeChar::Uint, 8)),
+        (false, size) => Ok((TypeChar::Uint, size.expect("every u64 fits") as u64)),
+    }
+}
+
+fn incompatible(a: &DynValue, b: &DynValue) -> io::Error {
+    Error::InvalidInput(format!("cannot infer a dtype for both {} and {}", a.kind(), b.kind())).into()
+}
+
+pub(crate) fn check_supported(dtype: &DType) -> io::Result<()> {
+    match unsupported_type(dtype) {
+        Some(ty) => Err(Error::InvalidInput(format!("unsupported type {}", ty)).into()),
+        None => Ok(()),
+    }
+}
+
+fn unsupported_type(dtype: &DType) -> Option<&TypeStr> {
+    match dtype {
+        DType::Plain(ty) => match (ty.type_char(), ty.size_field()) {
+            (TypeChar::Float, 2 | 16) | (TypeChar::Complex, 32) => Some(ty),
+            _ => None,
+        },
+        DType::Array(_, inner) => unsupported_type(inner),
+        DType::Record(fields) => fields.iter().find_map(|field| unsupported_type(&field.dtype)),
+    }
+}
+
+#[doc(hidden)]
+pub struct DynValueReader {
+    dtype: DType,
+    size: usize,
+}
+
+impl TypeRead for DynValueReader {
+    type Value = DynValue;
+
+    fn read_one<R: Read>(&self, mut reader: R) -> io::Result<DynValue> {
+        let mut buf = vec![0; self.size];
+        reader.read_exact(&mut buf)?;
+        decode(&self.dtype, &buf)
+    }
+}
+
+/// Reads any dtype, except for half and extended precision floats.
+impl Deserialize for DynValue {
+    type TypeReader = DynValueReader;
+
+    fn reader(dtype: &DType) -> Result<Self::TypeReader, DTypeError> {
+        if let Some(ty) = unsupported_type(dtype) {
+            return Err(DTypeError::custom(format_args!("unsupported type {}", ty)));
+        }
+        let size = dtype.num_bytes().ok_or_else(|| DTypeError::custom("dtype is larger than usize"))?;
+        Ok(DynValueReader { dtype: dtype.clone(), size })
+    }
+}
+
+fn decode(dtype: &DType, bytes: &[u8]) -> io::Result<DynValue> {
+    match dtype {
+        DType::Plain(ty) => decode_scalar(ty, bytes),
+        DType::Array(len, inner) => {
+            let size = bytes.len() / (*len).max(1) as usize;
+            let items = (0..*len as usize).map(|i| decode(inner, &bytes[i * size..(i + 1) * size]));
+            Ok(DynValue::Array(items.collect::<io::Result<_>>()?))
+        },
+        DType::Record(fields) => {
+            let mut offset = 0;
+            let mut values = Vec::with_capacity(fields.len());
+            for field in fields {
+                let size = field.dtype.num_bytes().expect("(BUG!) checked by the size of the record");
+                let value = decode(&field.dtype, &bytes[offset..offset + size]).map_err(|e| Error::__in_field(e, &field.name))?;
+                values.push(value);
+                offset += size;
+            }
+            Ok(DynValue::Record(values))
+        },
+    }
+}
+
+fn decode_scalar(ty: &TypeStr, bytes: &[u8]) -> io::Result<DynValue> {
+    let endianness = ty.endianness();
+    let half = bytes.len() / 2;
+    Ok(match ty.type_char() {
+        TypeChar::Bool => match bytes[0] {
+            0 => DynValue::Bool(false),
+            1 => DynValue::Bool(true),
+            b => return Err(Error::InvalidData(format!("invalid value for bool: {}", b)).into()),
+        },
+        TypeChar::Int => DynValue::Int(read_int(endianness, bytes)),
+        TypeChar::Uint => DynValue::Uint(read_uint(endianness, bytes)),
+        TypeChar::Float => DynValue::Float(read_float(endianness, bytes)),
+        TypeChar::Complex => DynValue::Complex(read_float(endianness, &bytes[..half]), read_float(endianness, &bytes[half..])),
+        TypeChar::TimeDelta | TypeChar::DateTime => {
+            DynValue::Time(Some(read_int(endianness, bytes)).filter(|&x| x != i64::MIN))
+        },
+        TypeChar::ByteStr => {
+            let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            DynValue::Bytes(bytes[..end].to_vec())
+        },
+        TypeChar::RawData => DynValue::Bytes(bytes.to_vec()),
+        TypeChar::UnicodeStr => {
+            let mut string = bytes.chunks_exact(4)
+                .map(|unit| {
+                    let unit = read_uint(endianness, unit) as u32;
+                    char::from_u32(unit).ok_or_else(|| Error::InvalidData(format!("invalid UTF-32 code unit: {:x}", unit)))
+                })
+                .collect::<Result<String, _>>()?;
+            string.truncate(string.trim_end_matches('\0').len());
+            DynValue::Str(string)
+        },
+    })
+}
+
+// Reverse bytes in the given byte order into little-endian order, padded to 8 bytes.
+fn read_ordered(endianness: Endianness, bytes: &[u8]) -> [u8; 8] {
+    let mut little = [0; 8];
+    little[..bytes.len()].copy_from_slice(bytes);
+    if Endianness::Little.requires_swap(endianness) {
+        little[..bytes.len()].reverse();
+    }
+    little
+}
+
+fn read_uint(endianness: Endianness, bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(read_ordered(endianness, bytes))
+}
+
+fn read_int(endianness: Endianness, bytes: &[u8]) -> i64 {
+    // sign-extend
+    let shift = 64 - 8 * bytes.len() as u32;
+    (read_uint(endianness, bytes) as i64) << shift >> shift
+}
+
+fn read_float(endianness: Endianness, bytes: &[u8]) -> f64 {
+    let little = read_ordered(endianness, bytes);
+    match bytes.len() {
+        4 => f32::from_le_bytes(little[..4].try_into().unwrap()) as f64,
+        8 => f64::from_le_bytes(little),
+        _ => unreachable!("(BUG!) size was checked"),
+    }
+}
+
+pub(crate) fn encode(dtype: &DType, value: &DynValue, out: &mut Vec<u8>) -> io::Result<()> {
+    match (dtype, value) {
+        (DType::Plain(ty), _) => encode_scalar(ty, value, out),
+        (DType::Array(len, inner), DynValue::Array(items)) => {
+            if items.len() as u64 != *len {
+                return Err(Error::InvalidInput(format!("expected {} array elements, got {}", len, items.len())).into());
+            }
+            items.iter().try_for_each(|item| encode(inner, item, out))
+        },
+        (DType::Record(fields), DynValue::Record(values)) => {
+            if values.len() != fields.len() {
+                return Err(Error::InvalidInput(format!("expected {} fields, got {}", fields.len(), values.len())).into());
+            }
+            for (field, value) in fields.iter().zip(values) {
+                encode(&field.dtype, value, out).map_err(|e| Error::__in_field(e, &field.name))?;
+            }
+            Ok(())
+        },
+        (DType::Array(..), _) => Err(mismatch("an array", value)),
+        (DType::Record(..), _) => Err(mismatch("a record", value)),
+    }
+}
+
+fn encode_scalar(ty: &TypeStr, value: &DynValue, out: &mut Vec<u8>) -> io::Result<()> {
+    let size = ty.size_field() as usize;
+    let endianness = ty.endianness();
+    match (ty.type_char(), value) {
+        (TypeChar::Bool, &DynValue::Bool(b)) => out.push(b as u8),
+        (TypeChar::Int, &DynValue::Int(x)) => push_int(endianness, size, x as i128, ty, out)?,
+        (TypeChar::Int, &DynValue::Uint(x)) => push_int(endianness, size, x as i128, ty, out)?,
+        (TypeChar::Uint, &DynValue::Int(x)) => push_uint(endianness, size, x as i128, ty, out)?,
+        (TypeChar::Uint, &DynValue::Uint(x)) => push_uint(endianness, size, x as i128, ty, out)?,
+        (TypeChar::Float, &DynValue::Float(x)) => push_float(endianness, size, x, out),
+        (TypeChar::Float, &DynValue::Int(x)) => push_float(endianness, size, x as f64, out),
+        (TypeChar::Float, &DynValue::Uint(x)) => push_float(endianness, size, x as f64, out),
+        (TypeChar::Complex, &DynValue::Complex(re, im)) => {
+            push_float(endianness, size / 2, re, out);
+            push_float(endianness, size / 2, im, out);
+        },
+        (TypeChar::TimeDelta | TypeChar::DateTime, &DynValue::Time(x)) => {
+            push_ordered(endianness, &x.unwrap_or(i64::MIN).to_le_bytes(), out);
+        },
+        (TypeChar::ByteStr, DynValue::Bytes(bytes)) => {
+            if bytes.len() > size {
+                return Err(Error::InvalidInput(format!("{} bytes do not fit in {}", bytes.len(), ty)).into());
+            }
+            out.extend_from_slice(bytes);
+            out.resize(out.len() + size - bytes.len(), 0);
+        },
+        (TypeChar::RawData, DynValue::Bytes(bytes)) => {
+            if bytes.len() != size {
+                return Err(Error::InvalidInput(format!("expected {} bytes for {}, got {}", size, ty, bytes.len())).into());
+            }
+            out.extend_from_slice(bytes);
+        },
+        (TypeChar::UnicodeStr, DynValue::Str(s)) => {
+            let len = s.chars().count();
+            if len > size {
+                return Err(Error::InvalidInput(format!("a string of {} characters does not fit in {}", len, ty)).into());
+            }
+            s.chars().for_each(|c| push_ordered(endianness, &(c as u32).to_le_bytes(), out));
+            out.resize(out.len() + 4 * (size - len), 0);
+        },
+        _ => return Err(mismatch(&format!("a value of type {}", ty), value)),
+    }
+    Ok(())
+}
+
+fn mismatch(expected: &str, found: &DynValue) -> io::Error {
+    Error::InvalidInput(format!("expected {}, got {}", expected, found.kind())).into()
+}
+
+fn push_int(endianness: Endianness, size: usize, x: i128, ty: &TypeStr, out: &mut Vec<u8>) -> io::Result<()> {
+    let bits = 8 * size as u32;
+    if x < -(1i128 << (bits - 1)) || x >= 1i128 << (bits - 1) {
+        return Err(Error::InvalidInput(format!("{} is out of range for {}", x, ty)).into());
+    }
+    push_ordered(endianness, &(x as i64).to_le_bytes()[..size], out);
+    Ok(())
+}
+
+fn push_uint(endianness: Endianness, size: usize, x: i128, ty: &TypeStr, out: &mut Vec<u8>) -> io::Result<()> {
+    if x < 0 || x >= 1i128 << (8 * size as u32) {
+        return Err(Error::InvalidInput(format!("{} is out of range for {}", x, ty)).into());
+    }
+    push_ordered(endianness, &(x as u64).to_le_bytes()[..size], out);
+    Ok(())
+}
+
+fn push_float(endianness: Endianness, size: usize, x: f64, out: &mut Vec<u8>) {
+    match size {
+        4 => push_ordered(endianness, &(x as f32).to_le_bytes(), out),
+        8 => push_ordered(endianness, &x.to_le_bytes(), out),
+        _ => unreachable!("(BUG!) size was checked"),
+    }
+}
+
+// Append little-endian bytes in the given byte order.
+fn push_ordered(endianness: Endianness, little: &[u8], out: &mut Vec<u8>) {
+    let start = out.len();
+    out.extend_from_slice(little);
+    if Endianness::Little.requires_swap(endianness) {
+        out[start..].reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NpyFile;
+
+    fn write(dtype: &str, shape: &[u64], rows: Vec<DynValue>) -> io::Result<Vec<u8>> {
+        let mut out = vec![];
+        let dtype = match dtype.starts_with('[') {
+            true => DType::parse(dtype).unwrap(),
+            false => DType::new_scalar(dtype.parse().unwrap()),
+        };
+        write_dyn(&mut out, &dtype, shape, rows)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn scalars() {
+        let bytes = write(">i2", &[2, 2], vec![1i64.into(), (-2i64).into(), 3u64.into(), 32767i64.into()]).unwrap();
+        assert_eq!(NpyFile::new(&bytes[..]).unwrap().into_vec::<i16>().unwrap(), vec![1, -2, 3, 32767]);
+
+        let bytes = write("<f4", &[3], vec![0.5.into(), 2i64.into(), 3u64.into()]).unwrap();
+        assert_eq!(NpyFile::new(&bytes[..]).unwrap().into_vec::<f32>().unwrap(), vec![0.5, 2.0, 3.0]);
+
+        let bytes = write("<M8[s]", &[2], vec![DynValue::Time(Some(5)), DynValue::Time(None)]).unwrap();
+        assert_eq!(NpyFile::new(&bytes[..]).unwrap().into_vec::<i64>().unwrap(), vec![5, i64::MIN]);
+
+        let bytes = write("|S3", &[2], vec![DynValue::Bytes(b"ab".to_vec()), DynValue::Bytes(b"abc".to_vec())]).unwrap();
+        assert_eq!(NpyFile::new(&bytes[..]).unwrap().into_vec::<Vec<u8>>().unwrap(), vec![b"ab".to_vec(), b"abc".to_vec()]);
+
+        let bytes = write(">U3", &[1], vec!["é!".into()]).unwrap();
+        assert_eq!(NpyFile::new(&bytes[..]).unwrap().into_vec::<String>().unwrap(), vec!["é!".to_string()]);
+    }
+
+    #[test]
+    fn records() {
+        let rows = (0..3).map(|i| DynValue::Record(vec![
+            DynValue::Uint(i),
+            DynValue::Array(vec![DynValue::Float(i as f64), DynValue::Float(-(i as f64))]),
+            DynValue::Bool(i % 2 == 0),
+        ]));
+        let mut bytes = vec![];
+        let dtype = DType::parse("[('id', '<u4'), ('pos', '>f8', (2,)), ('ok', '|b1')]").unwrap();
+        write_dyn(&mut bytes, &dtype, &[3], rows).unwrap();
+
+        let npy = NpyFile::new(&bytes[..]).unwrap();
+        assert_eq!(npy.dtype(), dtype);
+        let data = &bytes[bytes.len() - 3 * 21..];
+        assert_eq!(&data[21..25], &1u32.to_le_bytes());
+        assert_eq!(&data[25..33], &1f64.to_be_bytes());
+        assert_eq!(&data[33..41], &(-1f64).to_be_bytes());
+        assert_eq!(data[41], 0);
+    }
+
+    #[test]
+    fn read() {
+        let read = |bytes: Vec<u8>| NpyFile::new(&bytes[..]).unwrap().data_dyn().unwrap().collect::<io::Result<Vec<_>>>();
+        let roundtrip = |dtype: &str, rows: Vec<DynValue>| {
+            assert_eq!(read(write(dtype, &[rows.len() as u64], rows.clone()).unwrap()).unwrap(), rows, "{}", dtype);
+        };
+        roundtrip(">i2", vec![1i64.into(), (-2i64).into(), (i16::MIN as i64).into()]);
+        roundtrip("|i1", vec![(-128i64).into(), 127i64.into()]);
+        roundtrip("<u8", vec![u64::MAX.into(), 0u64.into()]);
+        roundtrip("<f4", vec![0.5.into(), (-2.0).into()]);
+        roundtrip(">c16", vec![DynValue::Complex(1.0, -2.5)]);
+        roundtrip("|b1", vec![true.into(), false.into()]);
+        roundtrip(">m8[us]", vec![DynValue::Time(Some(-5)), DynValue::Time(None)]);
+        roundtrip("|S3", vec![DynValue::Bytes(b"ab".to_vec()), DynValue::Bytes(b"a\0c".to_vec())]);
+        roundtrip("|V2", vec![DynValue::Bytes(vec![0, 0])]);
+        roundtrip(">U3", vec!["é!".into(), "".into()]);
+        roundtrip("[('id', '<u4'), ('pos', '>f8', (2,)), ('', '|V3'), ('name', '<U2')]", vec![
+            DynValue::Record(vec![7u64.into(), DynValue::Array(vec![1.5.into(), 2.5.into()]), DynValue::Bytes(vec![0; 3]), "ab".into()]),
+        ]);
+
+        let mut bytes = write("[('a', '<i4'), ('ok', '|b1')]", &[1], vec![DynValue::Record(vec![1i64.into(), true.into()])]).unwrap();
+        *bytes.last_mut().unwrap() = 2;
+        let err = Error::from(read(bytes).unwrap_err());
+        assert!(matches!(err.root(), Error::InvalidData(msg) if msg.contains("bool")));
+        assert_eq!(err.context().unwrap().field(), &["ok".to_string()][..]);
+
+        let bytes = crate::write::header_bytes(&DType::parse("'<f2'").unwrap(), Order::C, &[0], &[]).unwrap();
+        let err = NpyFile::new(&bytes[..]).unwrap().data_dyn().err().unwrap();
+        assert!(matches!(Error::from(err), Error::DTypeMismatch { .. }));
+    }
+
+    #[test]
+    fn infer() {
+        let infer = |values: Vec<DynValue>| DType::infer(&values).unwrap().descr().replace('>', "<");
+        assert_eq!(infer(vec![]), "'<f8'");
+        assert_eq!(infer(vec![true.into(), false.into()]), "'|b1'");
+        assert_eq!(infer(vec![1u64.into(), 255u64.into()]), "'|u1'");
+        assert_eq!(infer(vec![1u64.into(), 256u64.into()]), "'<u2'");
+        assert_eq!(infer(vec![1i64.into(), 127u64.into()]), "'|i1'");
+        assert_eq!(infer(vec![(-129i64).into()]), "'<i2'");
+        assert_eq!(infer(vec![i64::MIN.into(), 1u64.into()]), "'<i8'");
+        assert_eq!(infer(vec![1i64.into(), u64::MAX.into()]), "'<u8'");
+        assert_eq!(infer(vec![(-1i64).into(), u64::MAX.into()]), "'<f8'");
+        assert_eq!(infer(vec![1i64.into(), 0.5.into()]), "'<f8'");
+        assert_eq!(infer(vec![DynValue::Complex(1.0, 2.0)]), "'<c16'");
+        assert_eq!(infer(vec![DynValue::Bytes(vec![]), DynValue::Bytes(b"abc".to_vec())]), "'|S3'");
+        assert_eq!(infer(vec!["".into()]), "'<U1'");
+        assert_eq!(infer(vec!["é".into(), "ab".into()]), "'<U2'");
+
+        let array = |items: Vec<DynValue>| DynValue::Array(items);
+        let dtype = DType::infer(&[array(vec![1u64.into(), 2u64.into()]), array(vec![3u64.into(), 300u64.into()])]).unwrap();
+        assert_eq!(dtype, DType::Array(2, Box::new(DType::parse("'<u2'").unwrap().to_native_endian())));
+
+        let rows = vec![
+            DynValue::Record(vec!["a".into(), 1i64.into()]),
+            DynValue::Record(vec!["bc".into(), 0.5.into()]),
+        ];
+        assert_eq!(infer(rows), "[('f0', '<U2'), ('f1', '<f8'), ]");
+    }
+
+    #[test]
+    fn infer_invalid() {
+        let check = |values: Vec<DynValue>, expected: &str| {
+            let err = DType::infer(&values).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(err.to_string().contains(expected), "{}", err);
+        };
+        check(vec![1i64.into(), "a".into()], "a signed integer and a string");
+        check(vec![true.into(), 1i64.into()], "a bool and a signed integer");
+        check(vec![DynValue::Bytes(vec![]), "a".into()], "bytes and a string");
+        check(vec![DynValue::Time(Some(1))], "units");
+        check(vec![DynValue::Array(vec![]), DynValue::Array(vec![1i64.into()])], "lengths 0 and 1");
+        check(vec![DynValue::Record(vec![]), 1i64.into()], "a record and a signed integer");
+        check(vec![DynValue::Record(vec![]), DynValue::Record(vec![1i64.into()])], "0 and 1 fields");
+    }
+
+    #[test]
+    fn invalid() {
+        let check = |dtype: &str, shape: &[u64], rows: Vec<DynValue>, expected: &str| {
+            let err = write(dtype, shape, rows).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(err.to_string().contains(expected), "{}", err);
+        };
+        check("<i1", &[1], vec![128i64.into()], "out of range");
+        check("<u2", &[1], vec![(-1i64).into()], "out of range");
+        check("<i4", &[1], vec![0.5.into()], "got a float");
+        check("|S2", &[1], vec![DynValue::Bytes(b"abc".to_vec())], "do not fit");
+        check("<U2", &[1], vec!["abc".into()], "does not fit");
+        check("|V2", &[1], vec![DynValue::Bytes(b"a".to_vec())], "expected 2 bytes");
+        check("<f2", &[1], vec![0.5.into()], "unsupported");
+        check("<i4", &[2], vec![1i64.into()], "expected 2 rows");
+        check("[('a', '<i4', (2,))]", &[1], vec![DynValue::Record(vec![DynValue::Array(vec![1i64.into()])])], "expected 2 array elements");
+        check("<i4", &[1], vec![1i64.into(), 2i64.into()], "more than 1 rows");
+        check("[('a', '<i4'), ('b', '<i4')]", &[1], vec![DynValue::Record(vec![1i64.into()])], "expected 2 fields");
+
+        let rows = vec![DynValue::Record(vec![1i64.into()]), DynValue::Record(vec!["x".into()])];
+        let err = Error::from(write("[('a', '<i4')]", &[2], rows).unwrap_err());
+        let context = err.context().unwrap();
+        assert_eq!((context.index(), context.field()), (Some(1), &["a".to_string()][..]));
+    }
+}
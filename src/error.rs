@@ -0,0 +1,349 @@
+//! The [`Error`] type.
+
+use std::fmt;
+use std::io;
+
+use crate::header::DType;
+use crate::serialize::DTypeError;
+
+/// The ways in which reading or writing an NPY or NPZ file can fail.
+///
+/// For compatibility, the functions in this crate return [`io::Error`].  Errors that originate in
+/// `npyz` carry an `Error` inside of them, which can be recovered using `Error::from`:
+///
+/// ```
+/// use npyz::Error;
+///
+/// let bytes = b"\x93NUMPY\x09\x00";
+/// let err = npyz::NpyFile::new(&bytes[..]).err().unwrap();
+/// match Error::from(err) {
+///     Error::UnsupportedVersion { major, minor } => assert_eq!((major, minor), (9, 0)),
+///     other => panic!("unexpected error: {}", other),
+/// }
+/// ```
+///
+/// Converting an `Error` back into an [`io::Error`] preserves it, so the conversion can be done at any
+/// point, even after passing the error through code that only knows about [`io::Error`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An error from the underlying reader or writer.
+    Io(io::Error),
+    /// The dtype of an array cannot be used with a Rust type.
+    DTypeMismatch {
+        /// The name of the Rust type.
+        expected: &'static str,
+        /// The dtype of the array.
+        found: DType,
+        /// Why they are incompatible.
+        reason: DTypeError,
+    },
+    /// The header of an NPY file is invalid.
+    BadHeader(String),
+    /// The file ended before all of the data described by the header could be read.
+    Truncated,
+    /// The NPY format version is not supported by this crate.
+    UnsupportedVersion {
+        /// Major version number.
+        major: u8,
+        /// Minor version number.
+        minor: u8,
+    },
+    /// A header exceeds one of the limits in [`ReadOptions`][`crate::ReadOptions`].
+    LimitExceeded {
+        /// What was limited, e.g. `"header length"`.
+        what: &'static str,
+        /// The value found in the file.
+        value: u64,
+        /// The limit.
+        max: u64,
+    },
+    /// An index is out of bounds, e.g. in [`NpyReader::try_read_at`][`crate::NpyReader::try_read_at`].
+    IndexOutOfBounds {
+        /// The index.
+        index: u64,
+        /// The number of elements.
+        len: u64,
+    },
+    /// The contents of an array are invalid, e.g. a string with invalid UTF-8, or a sparse matrix
+    /// with inconsistent parts.
+    InvalidData(String),
+    /// An argument was invalid, e.g. an extra header key that conflicts with a standard one.
+    InvalidInput(String),
+    /// The zip container of an NPZ file is invalid.
+    ///
+    /// _This variant is only available with the **`"npz"`** feature._
+    #[cfg(feature = "npz")]
+    Zip(zip::result::ZipError),
+    /// Another error, along with information about where it occurred.
+    ///
+    /// Use [`Error::root`] to look past this variant.
+    Context {
+        /// Where the error occurred.
+        context: ErrorContext,
+        /// The error.
+        source: Box<Error>,
+    },
+}
+
+/// Describes where an [`Error`] occurred while reading.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    member: Option<String>,
+    index: Option<u64>,
+    field: Vec<String>,
+    byte_offset: Option<u64>,
+}
+
+impl ErrorContext {
+    /// The name of the array within an NPZ archive.
+    pub fn member(&self) -> Option<&str> {
+        self.member.as_deref()
+    }
+
+    /// The flat index of the record being read.
+    pub fn index(&self) -> Option<u64> {
+        self.index
+    }
+
+    /// The path to the field being read within a record, e.g. `["position", "x"]`.
+    ///
+    /// This is only available for types that implement [`Deserialize`][`crate::Deserialize`] through the derive macro.
+    pub fn field(&self) -> &[String] {
+        &self.field
+    }
+
+    /// The byte offset from the beginning of the NPY file to the record being read.
+    ///
+    /// This is only available when the header was read from the same reader as the data.
+    pub fn byte_offset(&self) -> Option<u64> {
+        self.byte_offset
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(member) = &self.member {
+            parts.push(format!("array '{}'", member));
+        }
+        if let Some(index) = self.index {
+            parts.push(format!("record {}", index));
+        }
+        if !self.field.is_empty() {
+            parts.push(format!("field '{}'", self.field.join(".")));
+        }
+        if let Some(offset) = self.byte_offset {
+            parts.push(format!("byte offset {}", offset));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl Error {
+    pub(crate) fn dtype_mismatch<T: ?Sized>(found: &DType, reason: DTypeError) -> Self {
+        Error::DTypeMismatch { expected: std::any::type_name::<T>(), found: found.clone(), reason }
+    }
+
+    /// Get the underlying error, skipping over any [`Error::Context`].
+    ///
+    /// ```
+    /// use npyz::Error;
+    ///
+    /// # use npyz::WriterBuilder;
+    /// # let mut bytes = vec![];
+    /// # let mut writer = npyz::WriteOptions::new().default_dtype().writer(&mut bytes).shape(&[3]).begin_nd().unwrap();
+    /// # writer.extend([1i32, 2, 3]).unwrap();
+    /// # writer.finish().unwrap();
+    /// // `bytes` holds an NPY file with 3 elements, but we only give it part of the data
+    /// let err = npyz::NpyFile::new(&bytes[..bytes.len() - 1]).unwrap().into_vec::<i32>().unwrap_err();
+    /// let err = Error::from(err);
+    /// assert!(matches!(err.root(), Error::Truncated));
+    /// assert_eq!(err.context().unwrap().index(), Some(2));
+    /// ```
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    /// Get the information about where this error occurred, if any is available.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Add context to an error, keeping any context that it already has.
+    pub(crate) fn add_context(err: io::Error, update: impl FnOnce(&mut ErrorContext)) -> io::Error {
+        let (mut context, source) = match Error::from(err) {
+            Error::Context { context, source } => (context, source),
+            other => (ErrorContext::default(), Box::new(other)),
+        };
+        update(&mut context);
+        Error::Context { context, source }.into()
+    }
+
+    pub(crate) fn in_member(err: io::Error, member: &str) -> io::Error {
+        Error::add_context(err, |c| { c.member.get_or_insert_with(|| member.to_string()); })
+    }
+
+    pub(crate) fn at_record(err: io::Error, index: u64, byte_offset: Option<u64>) -> io::Error {
+        Error::add_context(err, |c| {
+            c.index.get_or_insert(index);
+            if c.byte_offset.is_none() {
+                c.byte_offset = byte_offset;
+            }
+        })
+    }
+
+    // used by derives
+    #[doc(hidden)]
+    pub fn __in_field(err: io::Error, field: &str) -> io::Error {
+        Error::add_context(err, |c| c.field.insert(0, field.to_string()))
+    }
+
+    fn io_kind(&self) -> io::ErrorKind {
+        match self {
+            Error::Io(e) => e.kind(),
+            Error::Context { source, .. } => source.io_kind(),
+            Error::Truncated => io::ErrorKind::UnexpectedEof,
+            Error::InvalidInput(_) | Error::IndexOutOfBounds { .. } => io::ErrorKind::InvalidInput,
+            _ => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::DTypeMismatch { reason, .. } => write!(f, "{}", reason),
+            Error::BadHeader(msg) => write!(f, "{}", msg),
+            Error::Truncated => write!(f, "unexpected end of file"),
+            Error::UnsupportedVersion { major, minor } => write!(f, "unsupported version: ({}, {})", major, minor),
+            Error::LimitExceeded { what, value, max } => write!(f, "{} of {} exceeds the limit of {}", what, value, max),
+            Error::IndexOutOfBounds { index, len } => write!(f, "index {} is out of bounds for length {}", index, len),
+            Error::InvalidData(msg) => write!(f, "{}", msg),
+            Error::InvalidInput(msg) => write!(f, "{}", msg),
+            #[cfg(feature = "npz")]
+            Error::Zip(e) => write!(f, "{}", e),
+            Error::Context { context, source } => write!(f, "{} (at {})", source, context),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::DTypeMismatch { reason, .. } => Some(reason),
+            #[cfg(feature = "npz")]
+            Error::Zip(e) => Some(e),
+            Error::Context { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        match err {
+            Error::Io(e) => e,
+            err => io::Error::new(err.io_kind(), err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    /// Recover the `Error` inside an [`io::Error`] produced by this crate, or wrap any other [`io::Error`]
+    /// in [`Error::Io`].
+    fn from(err: io::Error) -> Error {
+        if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            *err.into_inner().unwrap().downcast::<Error>().unwrap()
+        } else {
+            Error::Io(err)
+        }
+    }
+}
+
+#[cfg(feature = "npz")]
+impl From<zip::result::ZipError> for Error {
+    fn from(err: zip::result::ZipError) -> Error {
+        match err {
+            zip::result::ZipError::Io(e) => Error::Io(e),
+            err => Error::Zip(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NpyFile;
+
+    fn npy_bytes() -> Vec<u8> {
+        crate::write::to_bytes_1d(&[1i32, 2, 3]).unwrap()
+    }
+
+    #[test]
+    fn io_roundtrip() {
+        let io_err: io::Error = Error::BadHeader("oops".to_string()).into();
+        assert_eq!(io_err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(io_err.to_string(), "oops");
+        assert!(matches!(Error::from(io_err), Error::BadHeader(msg) if msg == "oops"));
+
+        let io_err = io::Error::new(io::ErrorKind::PermissionDenied, "foreign");
+        let err = Error::from(io_err);
+        assert!(matches!(&err, Error::Io(_)));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn nested_context() {
+        let err: io::Error = Error::InvalidData("bad".to_string()).into();
+        let err = Error::__in_field(err, "y");
+        let err = Error::__in_field(err, "pos");
+        let err = Error::at_record(err, 3, Some(140));
+        let err = Error::in_member(err, "points");
+        let err = Error::from(err);
+        assert!(matches!(err.root(), Error::InvalidData(_)));
+        assert_eq!(err.to_string(), "bad (at array 'points', record 3, field 'pos.y', byte offset 140)");
+    }
+
+    #[test]
+    fn header_errors() {
+        let err = NpyFile::new(&b"\x93NUMPZ\x01\x00"[..]).err().unwrap();
+        assert!(matches!(Error::from(err), Error::BadHeader(_)));
+
+        let err = NpyFile::new(&b"\x93NUMPY\x04\x00"[..]).err().unwrap();
+        assert!(matches!(Error::from(err), Error::UnsupportedVersion { major: 4, minor: 0 }));
+
+        let bytes = npy_bytes();
+        let err = NpyFile::new(&bytes[..20]).err().unwrap();
+        assert!(matches!(Error::from(err), Error::Truncated));
+    }
+
+    #[test]
+    fn data_errors() {
+        let bytes = npy_bytes();
+        let err = NpyFile::new(&bytes[..bytes.len() - 1]).unwrap().into_vec::<i32>().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = Error::from(err);
+        assert!(matches!(err.root(), Error::Truncated));
+        let header_len = bytes.len() as u64 - 12;
+        assert_eq!(err.context().unwrap().byte_offset(), Some(header_len + 8));
+        assert_eq!(err.to_string(), format!("unexpected end of file (at record 2, byte offset {})", header_len + 8));
+
+        let err = NpyFile::new(&bytes[..]).unwrap().into_vec::<f64>().unwrap_err();
+        match Error::from(err) {
+            Error::DTypeMismatch { expected, found, .. } => {
+                assert_eq!(expected, "f64");
+                assert_eq!(found, <i32 as crate::AutoSerialize>::default_dtype());
+            },
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+}
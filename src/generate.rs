@@ -0,0 +1,404 @@
+//! Generating reproducible random arrays, e.g. as fixtures for tests and benchmarks.
+//!
+//! The values depend only on the dtype, shape and seed, so the same call always produces the same file, on any
+//! platform.  They are drawn as follows:
+//!
+//! * integers (`i`, `u`) and raw data (`V`): uniformly random bytes, i.e. any value of the type;
+//! * booleans (`b`): `False` or `True`;
+//! * floats (`f2`, `f4`, `f8`) and both parts of complex numbers (`c8`, `c16`): uniformly in `[0, 1)`;
+//! * datetimes and timedeltas (`M`, `m`): integers in `[0, 2**32)` of the given unit;
+//! * strings (`S`, `U`): ASCII letters and digits, of a random length up to the size of the type;
+//! * records and arrays: each field or element in turn.
+//!
+//! Extended precision floats (`f16` and `c32`) are not supported.
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! let dtype = npyz::DType::parse("[('id', '<u4'), ('pos', '<f8', (3,)), ('name', '|S8')]")?;
+//! let bytes = npyz::generate::random(&dtype, &[100], 42)?;
+//! assert_eq!(bytes, npyz::generate::random(&dtype, &[100], 42)?);
+//!
+//! let npy = npyz::NpyFile::new(&bytes[..])?;
+//! assert_eq!(npy.shape(), &[100]);
+//! assert_eq!(npy.dtype(), dtype);
+//! # Ok(()) }
+//! ```
+
+use std::io::{self, Read, Write};
+
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::Order;
+use crate::shuffle::SplitMix64;
+use crate::type_str::{Endianness, TypeChar, TypeStr};
+
+const CHUNK_SIZE: usize = 1 << 16;
+const STRING_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// Generate a complete NPY file of random values in C order.  See the [module docs][self] for the distribution.
+///
+/// Returns [`Error::InvalidInput`] if the dtype has an unsupported type, or the shape is too large.
+pub fn random(dtype: &DType, shape: &[u64], seed: u64) -> io::Result<Vec<u8>> {
+    let mut out = vec![];
+    write_random(&mut out, dtype, shape, seed)?;
+    Ok(out)
+}
+
+/// Write a complete NPY file of random values in C order, without holding all of it in memory.
+///
+/// This writes the same bytes as [`random`].
+pub fn write_random(mut writer: impl Write, dtype: &DType, shape: &[u64], seed: u64) -> io::Result<()> {
+    io::copy(&mut RandomNpy::new(dtype, shape, seed)?, &mut writer)?;
+    writer.flush()
+}
+
+/// Add an array of random values in C order to an NPZ archive.
+///
+/// The array holds the same bytes as [`random`].
+///
+/// *This is only available with the **`"npz"`** feature.*
+#[cfg(feature = "npz")]
+pub fn write_random_to_npz<W: io::Write + io::Seek>(
+    npz: &mut crate::npz::NpzWriter<W>,
+    name: &str,
+    options: zip::write::FileOptions,
+    dtype: &DType,
+    shape: &[u64],
+    seed: u64,
+) -> io::Result<()> {
+    npz.copy_array(name, options, crate::NpyFile::new(RandomNpy::new(dtype, shape, seed)?)?)
+}
+
+/// Reads as an NPY file of random values, generating them a chunk at a time.
+struct RandomNpy {
+    dtype: DType,
+    remaining: u64,
+    records_per_chunk: u64,
+    rng: SplitMix64,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl RandomNpy {
+    fn new(dtype: &DType, shape: &[u64], seed: u64) -> io::Result<Self> {
+        check_supported(dtype)?;
+        let num_records = shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim));
+        let record_size = dtype.num_bytes();
+        let (num_records, record_size) = match (num_records, record_size) {
+            (Some(n), Some(size)) if n.checked_mul(size as u64).is_some() => (n, size),
+            _ => return Err(Error::InvalidInput(format!("shape {:?} is too large for dtype {}", shape, dtype.descr())).into()),
+        };
+        let header = crate::write::header_bytes(dtype, Order::C, shape, &[])?;
+        Ok(RandomNpy {
+            dtype: dtype.clone(),
+            remaining: num_records,
+            records_per_chunk: (CHUNK_SIZE / record_size.max(1)).max(1) as u64,
+            rng: SplitMix64(seed),
+            buf: header,
+            pos: 0,
+        })
+    }
+}
+
+impl Read for RandomNpy {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() && self.remaining > 0 {
+            self.buf.clear();
+            self.pos = 0;
+            let count = self.records_per_chunk.min(self.remaining);
+            for _ in 0..count {
+                gen_value(&self.dtype, &mut self.rng, &mut self.buf);
+            }
+            self.remaining -= count;
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn check_supported(dtype: &DType) -> io::Result<()> {
+    match dtype {
+        DType::Plain(ty) => {
+            let supported = match (ty.type_char(), ty.size_field()) {
+                (TypeChar::Float, size) => matches!(size, 2 | 4 | 8),
+                (TypeChar::Complex, size) => matches!(size, 8 | 16),
+                (TypeChar::TimeDelta | TypeChar::DateTime, _) => true,
+                (TypeChar::Bool | TypeChar::Int | TypeChar::Uint, _) => true,
+                (TypeChar::ByteStr | TypeChar::UnicodeStr | TypeChar::RawData, _) => true,
+            };
+            match supported {
+                true => Ok(()),
+                false => Err(Error::InvalidInput(format!("cannot generate random values of type {}", ty)).into()),
+            }
+        },
+        DType::Array(_, inner) => check_supported(inner),
+        DType::Record(fields) => fields.iter().try_for_each(|field| check_supported(&field.dtype)),
+    }
+}
+
+fn gen_value(dtype: &DType, rng: &mut SplitMix64, out: &mut Vec<u8>) {
+    match dtype {
+        DType::Plain(ty) => gen_scalar(ty, rng, out),
+        DType::Array(n, inner) => (0..*n).for_each(|_| gen_value(inner, rng, out)),
+        DType::Record(fields) => fields.iter().for_each(|field| gen_value(&field.dtype, rng, out)),
+    }
+}
+
+fn gen_scalar(ty: &TypeStr, rng: &mut SplitMix64, out: &mut Vec<u8>) {
+    let size = ty.size_field() as usize;
+    let start = out.len();
+    match ty.type_char() {
+        TypeChar::Bool => out.push((rng.next() & 1) as u8),
+        TypeChar::Int | TypeChar::Uint | TypeChar::RawData => {
+            // the bytes are random, so their order doesn't matter
+            while out.len() - start < size {
+                let take = (size - (out.len() - start)).min(8);
+                out.extend_from_slice(&rng.next().to_le_bytes()[..take]);
+            }
+        },
+        TypeChar::Float => push_float(ty.endianness(), size, rng, out),
+        TypeChar::Complex => {
+            push_float(ty.endianness(), size / 2, rng, out);
+            push_float(ty.endianness(), size / 2, rng, out);
+        },
+        TypeChar::TimeDelta | TypeChar::DateTime => push_ordered(ty.endianness(), &(rng.next() >> 32).to_ne_bytes(), out),
+        TypeChar::ByteStr => {
+            let len = rng.below(size as u64 + 1) as usize;
+            out.extend((0..len).map(|_| random_char(rng)));
+            out.resize(start + size, 0);
+        },
+        TypeChar::UnicodeStr => {
+            let len = rng.below(size as u64 + 1) as usize;
+            for _ in 0..len {
+                push_ordered(ty.endianness(), &(random_char(rng) as u32).to_ne_bytes(), out);
+            }
+            out.resize(start + 4 * size, 0);
+        },
+    }
+}
+
+fn random_char(rng: &mut SplitMix64) -> u8 {
+    STRING_CHARS[rng.below(STRING_CHARS.len() as u64) as usize]
+}
+
+// A float in [0, 1) of the given size.
+fn push_float(endianness: Endianness, size: usize, rng: &mut SplitMix64, out: &mut Vec<u8>) {
+    match size {
+        2 => push_ordered(endianness, &f16_bits_in_unit_interval(rng.next() >> 54).to_ne_bytes(), out),
+        4 => push_ordered(endianness, &((rng.next() >> 40) as f32 / (1u64 << 24) as f32).to_ne_bytes(), out),
+        8 => push_ordered(endianness, &((rng.next() >> 11) as f64 / (1u64 << 53) as f64).to_ne_bytes(), out),
+        _ => unreachable!("(BUG!) size was checked"),
+    }
+}
+
+// The bits of the f16 equal to `k / 1024`, for `k < 1024`.
+fn f16_bits_in_unit_interval(k: u64) -> u16 {
+    if k == 0 {
+        return 0;
+    }
+    // k = 2**e * 1.m, so the value is 2**(e - 10) * 1.m
+    let e = 63 - k.leading_zeros() as u64;
+    let exponent = e + 15 - 10;
+    let mantissa = (k << (10 - e)) & 0x3ff;
+    ((exponent << 10) | mantissa) as u16
+}
+
+// Append native-endian bytes in the given byte order.
+fn push_ordered(endianness: Endianness, native: &[u8], out: &mut Vec<u8>) {
+    let start = out.len();
+    out.extend_from_slice(native);
+    if Endianness::of_machine().requires_swap(endianness) {
+        out[start..].reverse();
+    }
+}
+
+/// A shape of at most 3 dimensions, each of length at most 5, for property tests.
+///
+/// It implements `proptest::arbitrary::Arbitrary` with the **`"proptest"`** feature, and `arbitrary::Arbitrary`
+/// with the **`"arbitrary"`** feature.  Those features also implement these traits for [`DType`] (generating
+/// the dtype of a file: a type string or a record, with nested records and array fields) and [`TypeStr`].
+///
+/// _This type is only available with the **`"proptest"`** or **`"arbitrary"`** feature._
+#[cfg(any(feature = "proptest", feature = "arbitrary"))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SmallShape(pub Vec<u64>);
+
+/// A small NPY file of [`random`] values, for property tests.
+///
+/// The dtype is any that can be generated (see [`SmallShape`]) other than the extended precision floats, and the shape
+/// is a [`SmallShape`].
+///
+/// ```
+/// # #[cfg(feature = "proptest")] {
+/// use npyz::proptest::prelude::*;
+///
+/// let mut runner = npyz::proptest::test_runner::TestRunner::default();
+/// runner.run(&any::<npyz::generate::SmallArray>(), |array| {
+///     let npy = npyz::NpyFile::new(&array.bytes[..]).unwrap();
+///     prop_assert_eq!(npy.dtype(), array.dtype);
+///     prop_assert_eq!(npy.shape(), array.shape);
+///     Ok(())
+/// }).unwrap();
+/// # }
+/// ```
+///
+/// _This type is only available with the **`"proptest"`** or **`"arbitrary"`** feature._
+#[cfg(any(feature = "proptest", feature = "arbitrary"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmallArray {
+    /// The dtype of the array.
+    pub dtype: DType,
+    /// The shape of the array.
+    pub shape: Vec<u64>,
+    /// The complete NPY file, in C order.
+    pub bytes: Vec<u8>,
+}
+
+#[cfg(any(feature = "proptest", feature = "arbitrary"))]
+pub(crate) mod small {
+    use super::*;
+    use crate::header::Field;
+    use crate::type_str::TimeUnits;
+
+    pub(crate) const MAX_NDIM: usize = 3;
+    pub(crate) const MAX_DIM_LEN: u64 = 5;
+    /// Size of the `S`, `U` and `V` types.
+    pub(crate) const MAX_STRING_SIZE: u64 = 8;
+    /// Depth of records and array fields inside a record.
+    pub(crate) const MAX_DEPTH: u32 = 2;
+    pub(crate) const MAX_FIELDS: usize = 4;
+    pub(crate) const MAX_ARRAY_FIELD_LEN: u64 = 3;
+
+    pub(crate) const TYPE_CHARS: &[TypeChar] = &[
+        TypeChar::Bool, TypeChar::Int, TypeChar::Uint, TypeChar::Float, TypeChar::Complex,
+        TypeChar::TimeDelta, TypeChar::DateTime, TypeChar::ByteStr, TypeChar::UnicodeStr, TypeChar::RawData,
+    ];
+    pub(crate) const TIME_UNITS: &[TimeUnits] = &[
+        TimeUnits::Year, TimeUnits::Month, TimeUnits::Week, TimeUnits::Day, TimeUnits::Hour, TimeUnits::Minute,
+        TimeUnits::Second, TimeUnits::Millisecond, TimeUnits::Microsecond, TimeUnits::Nanosecond,
+        TimeUnits::Picosecond, TimeUnits::Femtosecond, TimeUnits::Attosecond,
+    ];
+    pub(crate) const FIELD_NAMES: &[&str] = &["a", "b", "x", "id", "pos", "value", "名前"];
+
+    /// The sizes that can be generated for a type char, or `None` for any size up to [`MAX_STRING_SIZE`].
+    pub(crate) fn sizes(type_char: TypeChar, extended_precision: bool) -> Option<Vec<u64>> {
+        let sizes = type_char.valid_sizes()?.iter().copied();
+        Some(match (type_char, extended_precision) {
+            (TypeChar::Float, false) => sizes.filter(|&size| size != 16).collect(),
+            (TypeChar::Complex, false) => sizes.filter(|&size| size != 32).collect(),
+            _ => sizes.collect(),
+        })
+    }
+
+    pub(crate) fn endiannesses(type_char: TypeChar, size: u64) -> &'static [Endianness] {
+        match type_char.requires_endianness(size) {
+            true => &[Endianness::Little, Endianness::Big],
+            false => &[Endianness::Little, Endianness::Big, Endianness::Irrelevant],
+        }
+    }
+
+    pub(crate) fn type_str(endianness: Endianness, type_char: TypeChar, size: u64, time_units: Option<TimeUnits>) -> TypeStr {
+        TypeStr { endianness, type_char, size, time_units }.validate().expect("(BUG!) generated an invalid type string")
+    }
+
+    /// Make a record from names chosen from [`FIELD_NAMES`], renaming duplicates.
+    pub(crate) fn record(fields: Vec<(&str, DType)>) -> DType {
+        let mut names = Vec::<String>::new();
+        let fields = fields.into_iter().enumerate().map(|(index, (name, dtype))| {
+            let name = match names.iter().any(|n| n == name) {
+                true => format!("{}{}", name, index),
+                false => name.to_string(),
+            };
+            names.push(name.clone());
+            Field { name, dtype }
+        }).collect();
+        DType::Record(fields)
+    }
+
+    pub(crate) fn array(dtype: DType, shape: Vec<u64>, seed: u64) -> SmallArray {
+        let bytes = random(&dtype, &shape, seed).expect("(BUG!) generated an unsupported dtype");
+        SmallArray { dtype, shape, bytes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NpyFile;
+
+    #[test]
+    fn reproducible() {
+        let dtype = DType::parse("'<i8'").unwrap();
+        let a = random(&dtype, &[10, 20], 1).unwrap();
+        assert_eq!(a, random(&dtype, &[10, 20], 1).unwrap());
+        assert_ne!(a, random(&dtype, &[10, 20], 2).unwrap());
+
+        let values = NpyFile::new(&a[..]).unwrap().into_vec::<i64>().unwrap();
+        assert_eq!(values.len(), 200);
+        assert!(values.iter().any(|&x| x < 0) && values.iter().any(|&x| x > 0));
+    }
+
+    #[test]
+    fn floats() {
+        for descr in ["'<f4'", "'>f4'", "'<f8'", "'>f8'"] {
+            let bytes = random(&DType::parse(descr).unwrap(), &[1000], 3).unwrap();
+            let npy = NpyFile::new(&bytes[..]).unwrap();
+            let values = match npy.dtype().num_bytes() {
+                Some(4) => npy.into_vec::<f32>().unwrap().into_iter().map(f64::from).collect(),
+                _ => npy.into_vec::<f64>().unwrap(),
+            };
+            assert!(values.iter().all(|&x| (0.0..1.0).contains(&x)), "{}", descr);
+            assert!(values.iter().sum::<f64>() > 400.0, "{}", descr);
+        }
+        // every f16 in the unit interval, checked against the f64 with the same value
+        for k in 0..1024 {
+            let bits = f16_bits_in_unit_interval(k);
+            let exponent = (bits >> 10) as i32;
+            let value = match exponent {
+                0 => 0.0,
+                _ => (1.0 + (bits & 0x3ff) as f64 / 1024.0) * 2f64.powi(exponent - 15),
+            };
+            assert_eq!(value, k as f64 / 1024.0);
+        }
+    }
+
+    #[test]
+    fn records_and_strings() {
+        let dtype = DType::parse("[('flag', '|b1'), ('name', '|S6'), ('label', '>U3'), ('t', '<M8[s]'), ('c', '<c16', (2,))]").unwrap();
+        let bytes = random(&dtype, &[50], 4).unwrap();
+        let npy = NpyFile::new(&bytes[..]).unwrap();
+        assert_eq!(npy.dtype(), dtype);
+        let mut records = npy.raw_records();
+        let mut lengths = std::collections::HashSet::new();
+        while let Some(record) = records.next_record().unwrap() {
+            assert!(record[0] <= 1);
+            let name = &record[1..7];
+            let len = name.iter().position(|&b| b == 0).unwrap_or(6);
+            assert!(name[..len].iter().all(|b| STRING_CHARS.contains(b)));
+            assert!(name[len..].iter().all(|&b| b == 0));
+            lengths.insert(len);
+            assert!(record[7..19].chunks(4).all(|c| c[..3] == [0, 0, 0]));
+            assert!(record[23..27] == [0; 4]);
+        }
+        assert!(lengths.len() > 3);
+    }
+
+    #[test]
+    fn unsupported() {
+        let err = random(&DType::parse("'<f16'").unwrap(), &[1], 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = random(&DType::parse("'<u8'").unwrap(), &[u64::MAX, 2], 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn streaming() {
+        let dtype = DType::parse("'<u2'").unwrap();
+        let mut out = vec![];
+        write_random(&mut out, &dtype, &[100_000], 5).unwrap();
+        assert_eq!(out, random(&dtype, &[100_000], 5).unwrap());
+        assert_eq!(NpyFile::new(&out[..]).unwrap().len(), 100_000);
+    }
+}
@@ -0,0 +1,409 @@
+//! Summarizing structured NPY files by the values of a key field.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, Write};
+
+use crate::compare::{ElementKind, ElementValue};
+use crate::error::Error;
+use crate::header::{DType, Field};
+use crate::read::{NpyFile, Order, RawRecords};
+use crate::type_str::{TypeChar, TypeStr};
+
+const WRITE_CHUNK_SIZE: usize = 1 << 16;
+
+/// A summary of each group computed by [`group_by`].
+///
+/// Each aggregation adds one field to the output: `Count` adds a field `count` of type `<u8`, and the
+/// others add a field of type `<f8` named after the aggregation and the field it reads, e.g. `sum_x`
+/// for `Sum("x")`.  The fields they read must have a boolean, integer or float type (except `f2` and
+/// `f16`); the values are converted to `f64`, so very large integers are rounded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Aggregation {
+    /// The number of records in the group.
+    Count,
+    /// The sum of a field.
+    Sum(String),
+    /// The mean of a field.
+    Mean(String),
+    /// The minimum of a field.  NaNs are ignored, as in numpy's `nanmin`.
+    Min(String),
+    /// The maximum of a field.  NaNs are ignored, as in numpy's `nanmax`.
+    Max(String),
+}
+
+impl Aggregation {
+    fn field(&self) -> Option<&str> {
+        match self {
+            Aggregation::Count => None,
+            Aggregation::Sum(field) | Aggregation::Mean(field) | Aggregation::Min(field) | Aggregation::Max(field) => Some(field),
+        }
+    }
+
+    fn output_field(&self) -> Field {
+        let (name, dtype) = match self {
+            Aggregation::Count => ("count".to_string(), "<u8"),
+            Aggregation::Sum(field) => (format!("sum_{}", field), "<f8"),
+            Aggregation::Mean(field) => (format!("mean_{}", field), "<f8"),
+            Aggregation::Min(field) => (format!("min_{}", field), "<f8"),
+            Aggregation::Max(field) => (format!("max_{}", field), "<f8"),
+        };
+        Field { name, dtype: DType::new_scalar(dtype.parse().expect("valid type string")) }
+    }
+}
+
+/// Group the records of a structured NPY file by the value of a key field, writing one record per
+/// group to a new 1-D file.  This is like SQL's `SELECT key, ... GROUP BY key`.
+///
+/// Each output record has the key followed by one field for each [`Aggregation`], in order.  The groups
+/// are sorted by key, in the same order as numpy's `sort`.  The input is read once, a small buffer at a
+/// time, keeping the state of each group in memory; for inputs with so many groups that this is a
+/// problem, sort the input by key and use [`group_by_sorted`].  The number of groups is returned.
+/// Multidimensional inputs are treated as a sequence of records in the order they are stored.
+///
+/// The key must be a field with a scalar type other than a complex number or the floats `f2` and `f16`.
+/// Keys are equal if their values are, so e.g. `-0.0` and `0.0` are the same group, as are strings
+/// that differ only in trailing NULs.  An error is returned, before anything is written, if the input is
+/// not structured, a field is missing or has an unsupported type, or an output field name is used twice.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{Aggregation, DType, DynValue};
+///
+/// let events = DType::parse("[('user', '<U8'), ('bytes', '<u4')]")?;
+/// let mut input = vec![];
+/// npyz::write_dyn(&mut input, &events, &[4], vec![
+///     DynValue::Record(vec!["bob".into(), 100u64.into()]),
+///     DynValue::Record(vec!["ann".into(), 20u64.into()]),
+///     DynValue::Record(vec!["bob".into(), 300u64.into()]),
+///     DynValue::Record(vec!["ann".into(), 40u64.into()]),
+/// ])?;
+///
+/// let aggregations = [Aggregation::Count, Aggregation::Mean("bytes".to_string())];
+/// let mut output = vec![];
+/// assert_eq!(npyz::group_by(&input[..], "user", &aggregations, &mut output)?, 2);
+///
+/// let npy = npyz::NpyFile::new(&output[..])?;
+/// assert_eq!(npy.dtype(), DType::parse("[('user', '<U8'), ('count', '<u8'), ('mean_bytes', '<f8')]")?);
+/// # Ok(()) }
+/// ```
+pub fn group_by(input: impl Read, key: &str, aggregations: &[Aggregation], mut output: impl Write) -> io::Result<u64> {
+    let mut plan = Plan::new(NpyFile::new(input)?.raw_records(), key, aggregations)?;
+    // the key of each group, and its state
+    let mut groups = BTreeMap::<Vec<u8>, (Vec<u8>, State)>::new();
+    while let Some(sort_key) = plan.next()? {
+        let (_, state) = groups.entry(sort_key).or_insert_with(|| (plan.key().to_vec(), State::new(aggregations)));
+        plan.update(state);
+    }
+
+    let header = crate::write::header_bytes(&plan.output_dtype, Order::C, &[groups.len() as u64], &[])?;
+    output.write_all(&header)?;
+    let mut buf = vec![];
+    for (key, state) in groups.values() {
+        plan.push_output(key, state, &mut buf);
+        if buf.len() >= WRITE_CHUNK_SIZE {
+            output.write_all(&buf)?;
+            buf.clear();
+        }
+    }
+    output.write_all(&buf)?;
+    output.flush()?;
+    Ok(groups.len() as u64)
+}
+
+/// Like [`group_by`], for an input that is sorted by the key.
+///
+/// Only the state of the current group is kept in memory, so this works for any number of groups.  The
+/// keys must be sorted in ascending order, like numpy's `sort(order=key)`; this is checked as the input
+/// is read.  The output must be seekable, to fill in the length of the array once it is known.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{Aggregation, DType, DynValue};
+///
+/// let readings = DType::parse("[('sensor', '<i8'), ('value', '<f4')]")?;
+/// let rows = (0..1000i64).map(|i| DynValue::Record(vec![(i / 10).into(), (i as f64).into()]));
+/// let mut input = vec![];
+/// npyz::write_dyn(&mut input, &readings, &[1000], rows)?;
+///
+/// let aggregations = [Aggregation::Min("value".to_string()), Aggregation::Max("value".to_string())];
+/// let mut output = std::io::Cursor::new(vec![]);
+/// assert_eq!(npyz::group_by_sorted(&input[..], "sensor", &aggregations, &mut output)?, 100);
+/// # Ok(()) }
+/// ```
+pub fn group_by_sorted(input: impl Read, key: &str, aggregations: &[Aggregation], mut output: impl Write + Seek) -> io::Result<u64> {
+    let mut plan = Plan::new(NpyFile::new(input)?.raw_records(), key, aggregations)?;
+    let start = output.stream_position()?;
+    let (header, shape_offset) = crate::write::header_bytes_unknown_len(&plan.output_dtype, &[])?;
+    output.write_all(&header)?;
+
+    let mut buf = vec![];
+    let mut count = 0;
+    // the sort key and key of the current group, and its state
+    let mut group: Option<(Vec<u8>, Vec<u8>, State)> = None;
+    let mut index = 0;
+    while let Some(sort_key) = plan.next()? {
+        match &mut group {
+            Some((last, _, state)) if *last == sort_key => plan.update(state),
+            Some((last, _, _)) if sort_key < *last => {
+                let msg = format!("the input is not sorted by '{}'", key);
+                return Err(Error::at_record(Error::InvalidData(msg).into(), index, None));
+            },
+            _ => {
+                let mut state = State::new(aggregations);
+                plan.update(&mut state);
+                let new = (sort_key, plan.key().to_vec(), state);
+                if let Some((_, key, state)) = group.replace(new) {
+                    plan.push_output(&key, &state, &mut buf);
+                    count += 1;
+                }
+            },
+        }
+        if buf.len() >= WRITE_CHUNK_SIZE {
+            output.write_all(&buf)?;
+            buf.clear();
+        }
+        index += 1;
+    }
+    if let Some((_, key, state)) = group {
+        plan.push_output(&key, &state, &mut buf);
+        count += 1;
+    }
+    output.write_all(&buf)?;
+    crate::write::write_unknown_len(&mut output, start + shape_offset, count)?;
+    output.flush()?;
+    Ok(count)
+}
+
+// The fields read from each record, and how the output is built.
+struct Plan<'a, R: Read> {
+    records: RawRecords<R>,
+    aggregations: &'a [Aggregation],
+    key_offset: usize,
+    key_type: TypeStr,
+    // the offset and kind of the field read by each aggregation, other than `Count`
+    inputs: Vec<Option<(usize, ElementKind)>>,
+    output_dtype: DType,
+    // the current record
+    record: Vec<u8>,
+}
+
+impl<'a, R: Read> Plan<'a, R> {
+    fn new(records: RawRecords<R>, key: &str, aggregations: &'a [Aggregation]) -> io::Result<Self> {
+        let dtype = records.dtype();
+        let fields = match &dtype {
+            DType::Record(fields) => fields.clone(),
+            _ => return Err(Error::InvalidInput(format!("expected a structured array, got dtype {}", dtype.descr())).into()),
+        };
+        let offsets = dtype.field_offsets().expect("size was checked when reading the header");
+        let find = |name: &str| match fields.iter().position(|field| field.name == name) {
+            Some(index) => Ok((offsets[index], &fields[index])),
+            None => Err(io::Error::from(Error::InvalidInput(format!("the input has no field '{}'", name)))),
+        };
+
+        let (key_offset, key_field) = find(key)?;
+        let key_type = match &key_field.dtype {
+            DType::Plain(ty) if crate::join::can_sort(ty) => ty.clone(),
+            other => {
+                let msg = format!("the key '{}' has type {}, which cannot be grouped by", key, other.descr());
+                return Err(Error::InvalidInput(msg).into());
+            },
+        };
+        let mut inputs = vec![];
+        let mut output_fields = vec![key_field.clone()];
+        for aggregation in aggregations {
+            inputs.push(match aggregation.field() {
+                Some(name) => {
+                    let (offset, field) = find(name)?;
+                    match (&field.dtype, ElementKind::of(&field.dtype)) {
+                        (DType::Plain(ty), Some(kind)) if matches!(ty.type_char(), TypeChar::Bool | TypeChar::Int | TypeChar::Uint | TypeChar::Float) => {
+                            Some((offset, kind))
+                        },
+                        _ => {
+                            let msg = format!("field '{}' has type {}, which cannot be aggregated", name, field.dtype.descr());
+                            return Err(Error::InvalidInput(msg).into());
+                        },
+                    }
+                },
+                None => None,
+            });
+            let field = aggregation.output_field();
+            if output_fields.iter().any(|other| other.name == field.name) {
+                return Err(Error::InvalidInput(format!("the output would have two fields named '{}'", field.name)).into());
+            }
+            output_fields.push(field);
+        }
+        Ok(Plan {
+            records, aggregations, key_offset, key_type, inputs,
+            output_dtype: DType::Record(output_fields), record: vec![],
+        })
+    }
+
+    // Read the next record, returning the sort key of its group.
+    fn next(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.records.next_record()? {
+            Some(record) => {
+                self.record.clear();
+                self.record.extend_from_slice(record);
+            },
+            None => return Ok(None),
+        }
+        Ok(Some(crate::join::sort_key(&self.key_type, self.key())))
+    }
+
+    // the key of the current record
+    fn key(&self) -> &[u8] {
+        let size = self.key_type.num_bytes().expect("scalar");
+        &self.record[self.key_offset..self.key_offset + size]
+    }
+
+    // Add the current record to a group.
+    fn update(&self, state: &mut State) {
+        state.count += 1;
+        for ((aggregation, input), value) in self.aggregations.iter().zip(&self.inputs).zip(&mut state.values) {
+            let x = match input {
+                Some((offset, kind)) => match kind.decode(&self.record[*offset..]) {
+                    ElementValue::Int(x) => x as f64,
+                    ElementValue::Real(x) => x,
+                    _ => unreachable!("(BUG!) kind was checked"),
+                },
+                None => continue,
+            };
+            match aggregation {
+                Aggregation::Count => {},
+                Aggregation::Sum(_) | Aggregation::Mean(_) => *value += x,
+                Aggregation::Min(_) => *value = value.min(x),
+                Aggregation::Max(_) => *value = value.max(x),
+            }
+        }
+    }
+
+    fn push_output(&self, key: &[u8], state: &State, out: &mut Vec<u8>) {
+        out.extend_from_slice(key);
+        for (aggregation, &value) in self.aggregations.iter().zip(&state.values) {
+            match aggregation {
+                Aggregation::Count => out.extend_from_slice(&state.count.to_le_bytes()),
+                Aggregation::Mean(_) => out.extend_from_slice(&(value / state.count as f64).to_le_bytes()),
+                Aggregation::Sum(_) | Aggregation::Min(_) | Aggregation::Max(_) => out.extend_from_slice(&value.to_le_bytes()),
+            }
+        }
+    }
+}
+
+// The running values of one group.
+struct State {
+    count: u64,
+    // one for each aggregation: a sum, or the minimum or maximum so far (NaN before the first number)
+    values: Vec<f64>,
+}
+
+impl State {
+    fn new(aggregations: &[Aggregation]) -> Self {
+        let values = aggregations.iter().map(|aggregation| match aggregation {
+            Aggregation::Min(_) | Aggregation::Max(_) => f64::NAN,
+            _ => 0.0,
+        }).collect();
+        State { count: 0, values }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{write_dyn, DynValue};
+
+    fn write(dtype: &str, rows: Vec<Vec<DynValue>>) -> Vec<u8> {
+        let mut out = vec![];
+        let shape = [rows.len() as u64];
+        write_dyn(&mut out, &DType::parse(dtype).unwrap(), &shape, rows.into_iter().map(DynValue::Record)).unwrap();
+        out
+    }
+
+    // the bytes after the header
+    fn data(bytes: &[u8]) -> &[u8] {
+        let npy = NpyFile::new(bytes).unwrap();
+        &bytes[bytes.len() - npy.len() as usize * npy.dtype().num_bytes().unwrap()..]
+    }
+
+    fn all() -> Vec<Aggregation> {
+        let x = || "x".to_string();
+        vec![Aggregation::Count, Aggregation::Sum(x()), Aggregation::Mean(x()), Aggregation::Min(x()), Aggregation::Max(x())]
+    }
+
+    #[test]
+    fn hashed_and_sorted() {
+        let rows = |keys: &[i64]| keys.iter().enumerate().map(|(i, &k)| vec![k.into(), (i as f64).into()]).collect();
+        let unsorted = write("[('k', '>i2'), ('x', '<f4')]", rows(&[3, -1, 3, 0, -1, 3]));
+        let sorted = write("[('k', '>i2'), ('x', '<f4')]", rows(&[-1, -1, 0, 3, 3, 3]));
+
+        let mut output = vec![];
+        assert_eq!(group_by(&unsorted[..], "k", &all(), &mut output).unwrap(), 3);
+        let expected = write("[('k', '>i2'), ('count', '<u8'), ('sum_x', '<f8'), ('mean_x', '<f8'), ('min_x', '<f8'), ('max_x', '<f8')]", vec![
+            vec![(-1i64).into(), 2u64.into(), 5.0.into(), 2.5.into(), 1.0.into(), 4.0.into()],
+            vec![0i64.into(), 1u64.into(), 3.0.into(), 3.0.into(), 3.0.into(), 3.0.into()],
+            vec![3i64.into(), 3u64.into(), 7.0.into(), (7.0 / 3.0).into(), 0.0.into(), 5.0.into()],
+        ]);
+        assert_eq!(NpyFile::new(&output[..]).unwrap().dtype(), NpyFile::new(&expected[..]).unwrap().dtype());
+        assert_eq!(data(&output), data(&expected));
+
+        // same groups from sorted input, but the values are in a different order
+        let mut output = io::Cursor::new(vec![]);
+        assert_eq!(group_by_sorted(&sorted[..], "k", &[Aggregation::Count], &mut output).unwrap(), 3);
+        let expected = write("[('k', '>i2'), ('count', '<u8')]", vec![
+            vec![(-1i64).into(), 2u64.into()],
+            vec![0i64.into(), 1u64.into()],
+            vec![3i64.into(), 3u64.into()],
+        ]);
+        assert_eq!(data(output.get_ref()), data(&expected));
+        assert_eq!(NpyFile::new(&output.get_ref()[..]).unwrap().shape(), &[3]);
+    }
+
+    #[test]
+    fn string_keys_and_nans() {
+        let input = write("[('k', '|S3'), ('x', '<f8')]", vec![
+            vec![DynValue::Bytes(b"b".to_vec()), f64::NAN.into()],
+            vec![DynValue::Bytes(b"a".to_vec()), 1.0.into()],
+            vec![DynValue::Bytes(b"b".to_vec()), 2.0.into()],
+        ]);
+        let mut output = vec![];
+        let aggregations = [Aggregation::Min("x".to_string()), Aggregation::Sum("x".to_string())];
+        assert_eq!(group_by(&input[..], "k", &aggregations, &mut output).unwrap(), 2);
+        let data = data(&output);
+        let field = |record: usize, i: usize| f64::from_le_bytes(data[19 * record + 3 + 8 * i..][..8].try_into().unwrap());
+        assert_eq!(&data[..3], b"a\0\0");
+        assert_eq!(&data[19..22], b"b\0\0");
+        assert_eq!(field(1, 0), 2.0);
+        assert!(field(1, 1).is_nan());
+    }
+
+    #[test]
+    fn empty() {
+        let input = write("[('k', '<u4'), ('x', '|b1')]", vec![]);
+        let mut output = io::Cursor::new(vec![]);
+        assert_eq!(group_by_sorted(&input[..], "k", &all(), &mut output).unwrap(), 0);
+        assert_eq!(NpyFile::new(&output.get_ref()[..]).unwrap().shape(), &[0]);
+    }
+
+    #[test]
+    fn errors() {
+        let ints = write("[('k', '<i4'), ('x', '<i4'), ('s', '|S1'), ('c', '<c8')]", vec![
+            vec![2i64.into(), 0i64.into(), DynValue::Bytes(vec![]), DynValue::Complex(0.0, 0.0)],
+            vec![1i64.into(), 0i64.into(), DynValue::Bytes(vec![]), DynValue::Complex(0.0, 0.0)],
+        ]);
+        let check = |key: &str, aggregations: &[Aggregation], expected: &str| {
+            let err = group_by_sorted(&ints[..], key, aggregations, io::Cursor::new(vec![])).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+        };
+        check("k", &all(), "the input is not sorted by 'k' (at record 1)");
+        check("y", &[], "the input has no field 'y'");
+        check("k", &[Aggregation::Sum("y".to_string())], "the input has no field 'y'");
+        check("c", &[], "cannot be grouped by");
+        check("k", &[Aggregation::Max("s".to_string())], "field 's' has type '|S1', which cannot be aggregated");
+        check("k", &[Aggregation::Count, Aggregation::Count], "two fields named 'count'");
+
+        let mut plain = vec![];
+        write_dyn(&mut plain, &DType::parse("'<i4'").unwrap(), &[0], vec![]).unwrap();
+        let err = group_by(&plain[..], "k", &[], vec![]).unwrap_err();
+        assert!(err.to_string().contains("expected a structured array"), "{}", err);
+    }
+}
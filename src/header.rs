@@ -7,6 +7,8 @@ pub use py_literal::Value;
 use byteorder::{LittleEndian, ReadBytesExt};
 use num_bigint::Sign;
 
+use crate::error::Error;
+use crate::read::ReadOptions;
 use crate::type_str::TypeStr;
 
 /// Representation of a Numpy type
@@ -80,19 +82,68 @@ impl DType {
         }
     }
 
+    /// Render this dtype the way `repr(numpy.dtype)` would.
+    ///
+    /// This produces the multi-line form, with one field per line for records.  The [`Display`][`fmt::Display`]
+    /// impl produces the same text on a single line (and uses the multi-line form for `{:#}`).
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let dtype = npyz::DType::parse("[('a', '>i4'), ('b', '|S3', (2,))]")?;
+    /// assert_eq!(dtype.to_string(), "dtype([('a', '>i4'), ('b', 'S3', (2,))])");
+    /// assert_eq!(dtype.to_pretty_string(), "\
+    /// dtype([('a', '>i4'),
+    ///        ('b', 'S3', (2,))])");
+    ///
+    /// let dtype = npyz::DType::parse("'|u1'")?;
+    /// assert_eq!(dtype.to_string(), "dtype('uint8')");
+    /// # Ok(()) }
+    /// ```
+    pub fn to_pretty_string(&self) -> String {
+        format!("{:#}", self)
+    }
+
     // Create from description AST
     pub(crate) fn from_descr(descr: &Value) -> io::Result<Self> {
         use DType::*;
         match descr {
             Value::String(string) => Ok(Self::new_scalar(convert_string_to_type_str(string)?)),
             Value::List(list) => Ok(Record(convert_list_to_record_fields(list)?)),
-            _ => Err(invalid_data("must be string or list")),
+            Value::Dict(dict) => convert_dict_to_record(dict),
+            _ => Err(invalid_data("must be string, list or dict")),
         }
     }
 
-    // not part of stable API, but needed by the serialize_array test
-    #[doc(hidden)]
+    /// Parse a dtype from the Python literal that numpy uses for `descr`.
+    ///
+    /// This accepts the forms that can appear as the `'descr'` of an NPY header, regardless of whether
+    /// it came from a file:
+    ///
+    /// * A type string, e.g. `'<i4'`.
+    /// * A list of `(name, dtype)` or `(name, dtype, shape)` fields.  The name may also be a
+    ///   `(title, name)` pair, in which case the title is discarded.
+    /// * A dict with `'names'` and `'formats'`, and optionally `'offsets'`, `'itemsize'`, `'titles'`
+    ///   and `'aligned'`.  This is the form numpy produces for dtypes with explicit offsets.
+    ///   A format may be a `(dtype, shape)` tuple.  Since a [`DType::Record`] lists its fields in the
+    ///   order they are laid out, the `'offsets'` must be increasing and the fields must not overlap;
+    ///   numpy dtypes whose fields are out of order in memory are rejected.
+    ///
+    /// Nested dtypes may use any of these forms.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::DType;
+    ///
+    /// let dtype = DType::parse("{'names': ['a', 'b'], 'formats': ['<u2', ('<f4', (2,))], 'offsets': [0, 4], 'itemsize': 16}")?;
+    /// assert_eq!(dtype, DType::parse("[('a', '<u2'), ('', '|V2'), ('b', '<f4', (2,)), ('', '|V4')]")?);
+    ///
+    /// let dtype = DType::parse("[(('Temperature (K)', 'temp'), '<f8')]")?;
+    /// assert_eq!(dtype, DType::parse("[('temp', '<f8')]")?);
+    /// # Ok(()) }
+    /// ```
     pub fn parse(source: &str) -> io::Result<Self> {
+        let options = ReadOptions::default();
+        options.check("dtype nesting depth", literal_depth(source.as_bytes()), options.max_literal_depth())?;
         let descr = parse_header_text_to_io_result(source.as_bytes())?;
         Self::from_descr(&descr)
     }
@@ -110,21 +161,284 @@ impl DType {
         }
     }
 
-    /// Get the number of bytes that each item of this type occupies.
+    /// Get the number of bytes that each item of this type occupies.  (numpy's `dtype.itemsize`)
     ///
     /// If this value overflows the plaform's `usize` datatype, returns `None`.
+    #[doc(alias = "itemsize")]
     pub fn num_bytes(&self) -> Option<usize> {
         match self {
             DType::Plain(ty) => ty.num_bytes(),
             DType::Array(n, inner) => inner.num_bytes()?.checked_mul(usize::try_from(*n).ok()?),
             DType::Record(fields) => {
-                fields.iter().map(|field| field.dtype.num_bytes())
-                    .fold(Some(0), |a, b| a?.checked_add(b?))
+                fields.iter().try_fold(0usize, |a, field| a.checked_add(field.dtype.num_bytes()?))
+            },
+        }
+    }
+
+    /// Get the byte offset of each field of a [`DType::Record`], relative to the start of the record.
+    ///
+    /// Returns `None` if this is not a record, or if [`Self::num_bytes`] would return `None`.
+    pub fn field_offsets(&self) -> Option<Vec<usize>> {
+        let fields = match self {
+            DType::Record(fields) => fields,
+            _ => return None,
+        };
+        let mut offset = 0usize;
+        fields.iter().map(|field| {
+            let this_offset = offset;
+            offset = offset.checked_add(field.dtype.num_bytes()?)?;
+            Some(this_offset)
+        }).collect()
+    }
+
+    /// Get the alignment of this type in bytes, following the rules numpy uses for `align=True`.
+    ///
+    /// For a [`DType::Record`], this is the largest alignment of any of its fields, which is the alignment
+    /// numpy would give the record if it were constructed with `align=True`.  (`DType` does not record
+    /// whether the dtype was aligned; use [`Self::is_aligned`] to check the field offsets)
+    pub fn alignment(&self) -> usize {
+        match self {
+            DType::Plain(ty) => ty.alignment(),
+            DType::Array(_, inner) => inner.alignment(),
+            DType::Record(fields) => fields.iter().map(|field| field.dtype.alignment()).max().unwrap_or(1),
+        }
+    }
+
+    /// Returns `true` if every field of every nested record lies at an offset that is a multiple
+    /// of its [`alignment`][`Self::alignment`], and every record's size is a multiple of its alignment.
+    ///
+    /// This is the layout produced by numpy for `align=True`, and the layout a C compiler would use for
+    /// an equivalent struct.  Non-record types are always aligned.
+    pub fn is_aligned(&self) -> bool {
+        match self {
+            DType::Plain(_) => true,
+            DType::Array(_, inner) => inner.is_aligned(),
+            DType::Record(fields) => {
+                let (offsets, size) = match (self.field_offsets(), self.num_bytes()) {
+                    (Some(offsets), Some(size)) => (offsets, size),
+                    _ => return false,
+                };
+                size % self.alignment() == 0 && fields.iter().zip(offsets).all(|(field, offset)| {
+                    offset % field.dtype.alignment() == 0 && field.dtype.is_aligned()
+                })
+            },
+        }
+    }
+
+    /// Returns `true` if every scalar in this dtype is stored in the byte order of the current machine.
+    ///
+    /// See [`TypeStr::is_native_endian`].
+    pub fn is_native_endian(&self) -> bool {
+        match self {
+            DType::Plain(ty) => ty.is_native_endian(),
+            DType::Array(_, inner) => inner.is_native_endian(),
+            DType::Record(fields) => fields.iter().all(|field| field.dtype.is_native_endian()),
+        }
+    }
+
+    /// Apply [`TypeStr::normalize_byte_order`] to every scalar in this dtype.
+    ///
+    /// Two dtypes that describe the same memory layout and byte order are equal after normalization.
+    pub fn normalize_byte_order(&self) -> DType {
+        self.map_type_strs(&TypeStr::normalize_byte_order)
+    }
+
+    /// Apply [`TypeStr::to_native_endian`] to every scalar in this dtype.
+    pub fn to_native_endian(&self) -> DType {
+        self.map_type_strs(&TypeStr::to_native_endian)
+    }
+
+    // Get the same type with the opposite byte order for every scalar that has one.
+    pub(crate) fn swapped_byte_order(&self) -> DType {
+        self.map_type_strs(&TypeStr::swapped_byte_order)
+    }
+
+    fn map_type_strs(&self, func: &dyn Fn(&TypeStr) -> TypeStr) -> DType {
+        match self {
+            DType::Plain(ty) => DType::Plain(func(ty)),
+            DType::Array(n, inner) => DType::Array(*n, Box::new(inner.map_type_strs(func))),
+            DType::Record(fields) => DType::Record(fields.iter().map(|field| Field {
+                name: field.name.clone(),
+                dtype: field.dtype.map_type_strs(func),
+            }).collect()),
+        }
+    }
+
+    /// Traverse this type and all nested types in depth-first order, starting with `self`.
+    ///
+    /// Each node gives the path of field names leading to it and its byte offset from the start of
+    /// the item.  The elements of a [`DType::Array`] are not expanded; its element type is visited
+    /// once, at the offset of the first element.
+    ///
+    /// Returns `None` if [`Self::num_bytes`] would return `None`.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let dtype = npyz::DType::parse("[('a', '<i4'), ('b', [('c', '<f8')], (2,))]")?;
+    /// let nodes = dtype.walk().unwrap().map(|node| (node.path, node.offset)).collect::<Vec<_>>();
+    /// assert_eq!(nodes, vec![
+    ///     (vec![], 0),
+    ///     (vec!["a"], 0),
+    ///     (vec!["b"], 4),
+    ///     (vec!["b"], 4),  // the element type of the array
+    ///     (vec!["b", "c"], 4),
+    /// ]);
+    /// # Ok(()) }
+    /// ```
+    pub fn walk(&self) -> Option<DTypeWalk<'_>> {
+        self.num_bytes()?;
+        Some(DTypeWalk { stack: vec![DTypeNode { path: vec![], offset: 0, dtype: self }] })
+    }
+}
+
+/// A type visited by [`DType::walk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DTypeNode<'a> {
+    /// Names of the fields leading to this type from the root.
+    pub path: Vec<&'a str>,
+    /// Byte offset of this type from the start of the root.
+    pub offset: usize,
+    /// The type itself.
+    pub dtype: &'a DType,
+}
+
+/// Iterator returned by [`DType::walk`].
+#[derive(Debug, Clone)]
+pub struct DTypeWalk<'a> {
+    stack: Vec<DTypeNode<'a>>,
+}
+
+impl<'a> Iterator for DTypeWalk<'a> {
+    type Item = DTypeNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        match node.dtype {
+            DType::Plain(_) => {},
+            DType::Array(_, inner) => {
+                self.stack.push(DTypeNode { path: node.path.clone(), offset: node.offset, dtype: inner });
+            },
+            DType::Record(fields) => {
+                // num_bytes() of the root was checked, so the offsets can't fail
+                let offsets = node.dtype.field_offsets().unwrap();
+                for (field, offset) in fields.iter().zip(offsets).rev() {
+                    let mut path = node.path.clone();
+                    path.push(&field.name[..]);
+                    self.stack.push(DTypeNode { path, offset: node.offset + offset, dtype: &field.dtype });
+                }
+            },
+        }
+        Some(node)
+    }
+}
+
+/// Formats like `repr(numpy.dtype)`.  Use `{:#}` for one field per line.
+impl fmt::Display for DType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "dtype(")?;
+        match self {
+            DType::Plain(ty) => write!(f, "'{}'", numpy_scalar_name(ty))?,
+            DType::Array(..) => {
+                let (shape, elem_ty) = extract_full_array_shape(self);
+                write!(f, "({}, {})", ReprDType(elem_ty), ReprShape(&shape))?;
+            },
+            DType::Record(fields) => {
+                // align continuation lines with the opening bracket
+                let separator = if f.alternate() { ",\n       " } else { ", " };
+                write!(f, "[")?;
+                for (index, field) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "{}", separator)?;
+                    }
+                    write!(f, "{}", ReprField(field))?;
+                }
+                write!(f, "]")?;
+            },
+        }
+        write!(f, ")")
+    }
+}
+
+/// Formats a dtype as it appears inside the repr of a record. (no `dtype(...)` wrapper)
+struct ReprDType<'a>(&'a DType);
+struct ReprField<'a>(&'a Field);
+struct ReprShape<'a>(&'a [u64]);
+
+impl fmt::Display for ReprDType<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            DType::Plain(ty) => write!(f, "'{}'", type_str_without_pipe(ty)),
+            DType::Array(..) => {
+                let (shape, elem_ty) = extract_full_array_shape(self.0);
+                write!(f, "({}, {})", ReprDType(elem_ty), ReprShape(&shape))
+            },
+            DType::Record(fields) => {
+                write!(f, "[")?;
+                for (index, field) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", ReprField(field))?;
+                }
+                write!(f, "]")
+            },
+        }
+    }
+}
+
+impl fmt::Display for ReprField<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Field { name, dtype } = self.0;
+        let name = PyUtf8StringLiteral(name);
+        match dtype {
+            DType::Array(..) => {
+                let (shape, elem_ty) = extract_full_array_shape(dtype);
+                write!(f, "({}, {}, {})", name, ReprDType(elem_ty), ReprShape(&shape))
+            },
+            _ => write!(f, "({}, {})", name, ReprDType(dtype)),
+        }
+    }
+}
+
+impl fmt::Display for ReprShape<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            [dim] => write!(f, "({},)", dim),
+            dims => {
+                let dims = dims.iter().map(|dim| dim.to_string()).collect::<Vec<_>>();
+                write!(f, "({})", dims.join(", "))
             },
         }
     }
 }
 
+// numpy omits the '|' in reprs
+fn type_str_without_pipe(ty: &TypeStr) -> String {
+    let string = ty.to_string();
+    match string.strip_prefix('|') {
+        Some(rest) => rest.to_string(),
+        None => string,
+    }
+}
+
+// numpy uses names like 'int32' for scalar dtypes of native byte order, but only at the top level
+fn numpy_scalar_name(ty: &TypeStr) -> String {
+    use crate::type_str::{Endianness, TypeChar};
+
+    if ty.endianness == Endianness::of_machine() || ty.endianness == Endianness::Irrelevant {
+        let bits = ty.size * 8;
+        match ty.type_char {
+            TypeChar::Bool => return "bool".to_string(),
+            TypeChar::Int => return format!("int{}", bits),
+            TypeChar::Uint => return format!("uint{}", bits),
+            TypeChar::Float => return format!("float{}", bits),
+            TypeChar::Complex => return format!("complex{}", bits),
+            _ => {},
+        }
+    }
+    type_str_without_pipe(ty)
+}
+
 fn convert_list_to_record_fields(values: &[Value]) -> io::Result<Vec<Field>> {
     values.iter()
         .map(|value| match *value {
@@ -142,6 +456,10 @@ fn convert_tuple_to_record_field(tuple: &[Value]) -> io::Result<Field> {
     }
     let name = match &tuple[0] {
         Value::String(name) => name.clone(),
+        Value::Tuple(title_and_name) => match &title_and_name[..] {
+            [Value::String(_title), Value::String(name)] => name.clone(),
+            _ => return Err(invalid_data("titled field must be a (title, name) pair of strings")),
+        },
         _ => return Err(invalid_data("list entry must contain a string for id")),
     };
 
@@ -156,10 +474,65 @@ fn convert_tuple_to_record_field(tuple: &[Value]) -> io::Result<Field> {
     Ok(Field { name, dtype })
 }
 
+fn convert_dict_to_record(dict: &[(Value, Value)]) -> io::Result<DType> {
+    let get = |key: &str| dict.iter().find(|(k, _)| matches!(k, Value::String(k) if k == key)).map(|(_, v)| v);
+    if let Some((key, _)) = dict.iter().find(|(k, _)| {
+        !matches!(k, Value::String(k) if ["names", "formats", "offsets", "itemsize", "titles", "aligned"].contains(&&k[..]))
+    }) {
+        return Err(invalid_data(format_args!("unexpected key in dtype dict: {}", key)));
+    }
+
+    let names = get("names").and_then(convert_value_to_sequence).ok_or_else(|| invalid_data("dtype dict must have a list of 'names'"))?;
+    let formats = get("formats").and_then(convert_value_to_sequence).ok_or_else(|| invalid_data("dtype dict must have a list of 'formats'"))?;
+    let offsets = match get("offsets") {
+        Some(offsets) => Some(convert_value_to_sequence(offsets).ok_or_else(|| invalid_data("'offsets' must be a list"))?),
+        None => None,
+    };
+    if formats.len() != names.len() || offsets.is_some_and(|offsets| offsets.len() != names.len()) {
+        return Err(invalid_data("'names', 'formats' and 'offsets' must have the same length"));
+    }
+
+    let mut builder = DType::record();
+    for (index, (name, format)) in names.iter().zip(formats).enumerate() {
+        let name = match name {
+            Value::String(name) => name.clone(),
+            _ => return Err(invalid_data("'names' must contain strings")),
+        };
+        let dtype = match format {
+            Value::Tuple(tuple) if tuple.len() == 2 => {
+                let shape = convert_value_to_shape(&tuple[1])?;
+                shape.into_iter().rev().try_fold(DType::from_descr(&tuple[0])?, |inner, dim| {
+                    io::Result::Ok(DType::Array(dim, Box::new(inner)))
+                })?
+            },
+            _ => DType::from_descr(format)?,
+        };
+        builder = match offsets {
+            Some(offsets) => {
+                let offset = convert_value_to_shape_integer(&offsets[index])?;
+                if index > 0 && offset < convert_value_to_shape_integer(&offsets[index - 1])? {
+                    return Err(invalid_data("'offsets' must be increasing; records with fields out of order are not supported"));
+                }
+                builder.field_at(name, offset, dtype)
+            },
+            None => builder.field(name, dtype),
+        };
+    }
+    if let Some(item_size) = get("itemsize") {
+        builder = builder.item_size(convert_value_to_shape_integer(item_size)?);
+    }
+    match get("aligned") {
+        Some(Value::Boolean(true)) if offsets.is_none() => builder = builder.aligned(),
+        Some(Value::Boolean(_)) | None => {},
+        Some(_) => return Err(invalid_data("'aligned' must be a bool")),
+    }
+    builder.build().map_err(invalid_data)
+}
+
 fn convert_value_to_sequence(field: &Value) -> Option<&[Value]> {
     match field {
-        &Value::List(ref lengths) => Some(lengths),
-        &Value::Tuple(ref lengths) => Some(lengths),
+        Value::List(lengths) => Some(lengths),
+        Value::Tuple(lengths) => Some(lengths),
         _ => None
     }
 }
@@ -193,6 +566,14 @@ pub(crate) fn convert_value_to_shape(field: &Value) -> io::Result<Vec<u64>> {
         .ok_or(invalid_data("shape must be list or tuple"))?
 }
 
+/// Whether `descr` is numpy's object dtype, whose data is a pickle.
+pub(crate) fn is_object_descr(descr: &Value) -> bool {
+    match descr.as_string() {
+        Some(string) => string.trim_start_matches(['|', '<', '>', '=']) == "O",
+        None => false,
+    }
+}
+
 fn convert_string_to_type_str(string: &str) -> io::Result<TypeStr> {
     match string.parse() {
         Ok(ty) => Ok(ty),
@@ -201,18 +582,70 @@ fn convert_string_to_type_str(string: &str) -> io::Result<TypeStr> {
 }
 
 fn invalid_data(message: impl ToString) -> io::Error {
-    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+    Error::BadHeader(message.to_string()).into()
 }
 
-pub(crate) fn read_header(r: &mut dyn io::Read) -> io::Result<Value> {
-    let PreHeader { version_props, header_size } = read_pre_header(r)?;
+fn truncated(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => Error::Truncated.into(),
+        _ => err,
+    }
+}
+
+/// Read the header, returning the parsed dict along with the exact bytes of the header.
+/// (everything from the magic string up to and including the newline)
+pub(crate) fn read_header(r: &mut dyn io::Read, options: &ReadOptions) -> io::Result<(Value, Vec<u8>)> {
+    let PreHeader { version, version_props, header_size } = read_pre_header(r)?;
+    options.check_header_len(header_size)?;
 
     // FIXME: properly account for encoding
     let _ = version_props.encoding;
     let mut header_text = vec![0; header_size];
-    r.read_exact(&mut header_text)?;
+    r.read_exact(&mut header_text).map_err(truncated)?;
+    if options.is_strict_header() && version_props.encoding == HeaderEncoding::Ascii && !header_text.is_ascii() {
+        return Err(invalid_data(format_args!("header of a version {}.{} file is not ASCII", version.0, version.1)));
+    }
 
-    parse_header_text_to_io_result(&header_text)
+    options.check("header nesting depth", literal_depth(&header_text), options.max_literal_depth())?;
+    let value = parse_header_text_to_io_result(&header_text)?;
+
+    // the pre-header can be reconstructed exactly from what we've read
+    let mut raw_bytes = Vec::with_capacity(version_props.bytes_before_text() + header_size);
+    raw_bytes.extend(b"\x93NUMPY");
+    raw_bytes.extend([version.0, version.1]);
+    match version_props.header_size_type {
+        HeaderSizeType::U16 => raw_bytes.extend((header_size as u16).to_le_bytes()),
+        HeaderSizeType::U32 => raw_bytes.extend((header_size as u32).to_le_bytes()),
+    }
+    raw_bytes.extend(header_text);
+    Ok((value, raw_bytes))
+}
+
+// Maximum nesting of brackets in a Python literal, so that deeply nested input can be rejected
+// before it reaches the (recursive) parser.
+fn literal_depth(text: &[u8]) -> usize {
+    let (mut depth, mut max_depth) = (0usize, 0);
+    let mut quote = None;
+    let mut bytes = text.iter();
+    while let Some(&b) = bytes.next() {
+        match (quote, b) {
+            (Some(_), b'\\') => { bytes.next(); },
+            (Some(q), b) if b == q => quote = None,
+            (Some(_), _) => {},
+            (None, b'\'' | b'"') => quote = Some(b),
+            (None, b'(' | b'[' | b'{') => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            },
+            (None, b')' | b']' | b'}') => depth = depth.saturating_sub(1),
+            (None, _) => {},
+        }
+    }
+    max_depth
+}
+
+pub(crate) fn parse_python_literal(source: &str) -> io::Result<Value> {
+    parse_header_text_to_io_result(source.as_bytes())
 }
 
 fn parse_header_text_to_io_result(bytes: &[u8]) -> io::Result<Value> {
@@ -223,10 +656,11 @@ fn parse_header_text_to_io_result(bytes: &[u8]) -> io::Result<Value> {
     std::str::from_utf8(without_newline)
         .map_err(|_| invalid_data("could not parse utf-8"))?
         .parse()
-        .map_err(|e: ParseError| invalid_data(format_args!("could not parse Python expression: {}", e.to_string())))
+        .map_err(|e: ParseError| invalid_data(format_args!("could not parse Python expression: {}", e)))
 }
 
 struct PreHeader {
+    version: (u8, u8),
     version_props: VersionProps,
     header_size: usize,
 }
@@ -236,11 +670,11 @@ fn read_pre_header(r: &mut dyn io::Read) -> io::Result<PreHeader> {
     let version_props = get_version_props(version)?;
 
     let header_size = match version_props.header_size_type {
-        HeaderSizeType::U32 => r.read_u32::<LittleEndian>()? as usize,
-        HeaderSizeType::U16 => r.read_u16::<LittleEndian>()? as usize,
+        HeaderSizeType::U32 => r.read_u32::<LittleEndian>().map_err(truncated)? as usize,
+        HeaderSizeType::U16 => r.read_u16::<LittleEndian>().map_err(truncated)? as usize,
     };
 
-    Ok(PreHeader { version_props, header_size })
+    Ok(PreHeader { version, version_props, header_size })
 }
 
 fn read_magic_and_version(r: &mut dyn io::Read) -> io::Result<(u8, u8)> {
@@ -292,7 +726,7 @@ pub(crate) fn get_version_props(version: (u8, u8)) -> io::Result<VersionProps> {
         (1, 0) => Ok(VersionProps { header_size_type: U16, encoding: Ascii }),
         (2, 0) => Ok(VersionProps { header_size_type: U32, encoding: Ascii }),
         (3, 0) => Ok(VersionProps { header_size_type: U32, encoding: Utf8 }),
-        _ => Err(invalid_data(format_args!("unsupported version: ({}, {})", version.0, version.1))),
+        (major, minor) => Err(Error::UnsupportedVersion { major, minor }.into()),
     }
 }
 
@@ -300,7 +734,7 @@ pub(crate) fn get_version_props(version: (u8, u8)) -> io::Result<VersionProps> {
 ///
 /// Unlike the [`Display`] impl for [`py_literal`], the string is encoded in
 /// UTF-8 (supported by NPY version 3), resulting in fewer escapes.
-struct PyUtf8StringLiteral<'a>(&'a str);
+pub(crate) struct PyUtf8StringLiteral<'a>(pub(crate) &'a str);
 
 impl fmt::Display for PyUtf8StringLiteral<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -328,6 +762,7 @@ pub(crate) fn get_minimal_version(required_props: VersionProps) -> (u8, u8) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::type_str::Endianness;
     use std::error::Error;
 
     type TestResult = std::result::Result<(), Box<dyn Error>>;
@@ -472,6 +907,93 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn numpy_repr() -> TestResult {
+        let dtype = DType::parse("[('a', '<u2'), ('b', [('c', '>f4')], (2, 3)), ('d', '|V3')]")?;
+        assert_eq!(dtype.to_string(), "dtype([('a', '<u2'), ('b', [('c', '>f4')], (2, 3)), ('d', 'V3')])");
+        assert_eq!(dtype.to_pretty_string(), [
+            "dtype([('a', '<u2'),",
+            "       ('b', [('c', '>f4')], (2, 3)),",
+            "       ('d', 'V3')])",
+        ].join("\n"));
+
+        assert_eq!(DType::parse("'|b1'")?.to_string(), "dtype('bool')");
+        assert_eq!(DType::parse("'|S3'")?.to_string(), "dtype('S3')");
+        assert_eq!(DType::parse("'<M8[ns]'")?.to_string(), "dtype('<M8[ns]')");
+        let non_native = match Endianness::of_machine() {
+            Endianness::Little => ">",
+            _ => "<",
+        };
+        let dtype = DType::Plain(format!("{}f8", non_native).parse()?);
+        assert_eq!(dtype.to_string(), format!("dtype('{}f8')", non_native));
+        Ok(())
+    }
+
+    #[test]
+    fn layout_queries() -> TestResult {
+        let dtype = DType::parse("[('a', '<u2'), ('b', '<c16'), ('c', '|S3', (2,)), ('d', [('e', '<U1')])]")?;
+        assert_eq!(dtype.num_bytes(), Some(28));
+        assert_eq!(dtype.field_offsets(), Some(vec![0, 2, 18, 24]));
+        assert_eq!(dtype.alignment(), 8);
+        assert!(!dtype.is_aligned());
+        assert_eq!(DType::Plain("<i4".parse()?).field_offsets(), None);
+
+        let dtype = DType::parse("[('a', '<u2'), ('', '|V6'), ('b', '<c16'), ('c', '|S3', (2,)), ('', '|V2')]")?;
+        assert!(dtype.is_aligned());
+        assert_eq!(dtype.walk().unwrap().count(), 7);
+        Ok(())
+    }
+
+    #[test]
+    fn byte_order() -> TestResult {
+        let dtype = DType::parse("[('a', '=i4'), ('b', '<u1'), ('c', [('d', '>S2')], (2,))]")?;
+        assert!(dtype.is_native_endian());
+        assert_eq!(dtype.to_native_endian(), dtype.normalize_byte_order());
+        assert_eq!(dtype.normalize_byte_order(), DType::parse(&format!(
+            "[('a', '{}i4'), ('b', '|u1'), ('c', [('d', '|S2')], (2,))]",
+            crate::Endianness::of_machine().to_str(),
+        ))?);
+
+        let dtype = DType::parse("[('a', '<i4'), ('b', '>i4')]")?;
+        assert!(!dtype.is_native_endian());
+        assert!(dtype.to_native_endian().is_native_endian());
+        Ok(())
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let deep = format!("{}'<i4'{}", "[('a', ".repeat(10_000), ")]".repeat(10_000));
+        assert!(DType::parse(&deep).is_err());
+        assert_eq!(literal_depth(b"{'descr': [('a)', '<i4', (2,))], 'x': \"[[\\\"\"}"), 4);
+    }
+
+    #[test]
+    fn dict_description() -> TestResult {
+        // fields out of order in memory can't be represented
+        let err = DType::parse("{'names': ['a', 'b'], 'formats': ['<i4', [('c', '|u1')]], 'offsets': [4, 0], 'itemsize': 8}").unwrap_err();
+        assert!(err.to_string().contains("'offsets' must be increasing"), "{}", err);
+        let err = DType::parse("{'names': ['a', 'b'], 'formats': ['<i4', '|u1'], 'offsets': [0, 2]}").unwrap_err();
+        assert!(err.to_string().contains("overlaps"), "{}", err);
+
+        let dtype = DType::parse("{'names': ['a', 'b'], 'formats': ['|u1', ('<i2', [2, 1])], 'titles': [None, 'B'], 'aligned': True}")?;
+        assert_eq!(dtype, DType::parse("[('a', '|u1'), ('', '|V1'), ('b', '<i2', (2, 1))]")?);
+
+        let dtype = DType::parse("[('x', {'names': ['y'], 'formats': ['<f8'], 'offsets': [8], 'itemsize': 24})]")?;
+        assert_eq!(dtype.num_bytes(), Some(24));
+
+        assert!(DType::parse("{'names': ['a'], 'formats': ['<i4', '<i4']}").is_err());
+        assert!(DType::parse("{'names': ['a'], 'formats': ['<i4'], 'shape': (2,)}").is_err());
+        assert!(DType::parse("{'formats': ['<i4']}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn titled_fields() -> TestResult {
+        assert_eq!(DType::parse("[(('title', 'a'), '<i4', (2,))]")?, DType::parse("[('a', '<i4', (2,))]")?);
+        assert!(DType::parse("[(('title',), '<i4')]").is_err());
+        Ok(())
+    }
+
     #[test]
     fn errors_on_value_variants_that_cannot_be_converted() {
         let no_dtype = Value::Boolean(false);
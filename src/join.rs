@@ -0,0 +1,344 @@
+//! Joining structured NPY files on a key field without reading them into memory.
+
+use std::io::{self, Read, Seek, Write};
+
+use crate::error::Error;
+use crate::header::{DType, Field};
+use crate::read::{NpyFile, RawRecords};
+use crate::type_str::{Endianness, TypeChar, TypeStr};
+
+const WRITE_CHUNK_SIZE: usize = 1 << 16;
+
+/// Join two structured NPY files that are sorted by the same key field, writing the records with
+/// matching keys to a new 1-D file.  This is an inner join, like SQL's `JOIN ... USING (key)`.
+///
+/// Each output record has the fields of the left record followed by the fields of the right record,
+/// except for its key.  For each key, every left record is joined with every right record, in the
+/// order they are stored; the number of output records is returned.  Both files are read once, a small
+/// buffer at a time, so the memory used is bounded by the number of right records that share a key.
+/// Multidimensional inputs are treated as a sequence of records in the order they are stored.
+///
+/// The key must be a field with a scalar type in both files, of the same kind: integers of any size and
+/// signedness can be joined with each other, as can floats, or strings of different lengths (trailing
+/// NULs are ignored, as in numpy), but e.g. integers cannot be joined with floats.  Complex numbers and
+/// the floats `f2` and `f16` are not supported.  Datetimes and timedeltas must have the same units.  The keys must be sorted in
+/// ascending order, like numpy's `sort(order=key)`; this is checked as the files are read.
+///
+/// The output must be seekable, to fill in the length of the array once it is known.  An error is
+/// returned, before anything is written, if the files are not structured, the key is missing or its types
+/// differ, or another field has the same name in both files.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{DType, DynValue};
+///
+/// let people = DType::parse("[('id', '<u4'), ('name', '<U8')]")?;
+/// let orders = DType::parse("[('id', '<u8'), ('amount', '<f8')]")?;
+/// let mut left = vec![];
+/// npyz::write_dyn(&mut left, &people, &[3], vec![
+///     DynValue::Record(vec![1u64.into(), "ann".into()]),
+///     DynValue::Record(vec![2u64.into(), "bob".into()]),
+///     DynValue::Record(vec![4u64.into(), "cy".into()]),
+/// ])?;
+/// let mut right = vec![];
+/// npyz::write_dyn(&mut right, &orders, &[3], vec![
+///     DynValue::Record(vec![2u64.into(), 9.5.into()]),
+///     DynValue::Record(vec![2u64.into(), 3.0.into()]),
+///     DynValue::Record(vec![3u64.into(), 1.0.into()]),
+/// ])?;
+///
+/// let mut output = std::io::Cursor::new(vec![]);
+/// assert_eq!(npyz::merge_join(&left[..], &right[..], &mut output, "id")?, 2);
+///
+/// let npy = npyz::NpyFile::new(&output.get_ref()[..])?;
+/// assert_eq!(npy.shape(), &[2]);
+/// assert_eq!(npy.dtype(), DType::parse("[('id', '<u4'), ('name', '<U8'), ('amount', '<f8')]")?);
+/// # Ok(()) }
+/// ```
+pub fn merge_join(left: impl Read, right: impl Read, mut output: impl Write + Seek, key: &str) -> io::Result<u64> {
+    let mut left = Side::new(NpyFile::new(left)?.raw_records(), key, "left")?;
+    let mut right = Side::new(NpyFile::new(right)?.raw_records(), key, "right")?;
+    if !same_kind(&left.key_type, &right.key_type) {
+        return Err(Error::InvalidInput(format!(
+            "cannot join key '{}' of type {} with type {}", key, left.key_type, right.key_type,
+        )).into());
+    }
+    let mut fields = left.fields.clone();
+    for (index, field) in right.fields.iter().enumerate() {
+        if index == right.key_index {
+            continue;
+        }
+        if !field.name.is_empty() && fields.iter().any(|other| other.name == field.name) {
+            return Err(Error::InvalidInput(format!("field '{}' is in both files", field.name)).into());
+        }
+        fields.push(field.clone());
+    }
+
+    let start = output.stream_position()?;
+    let (header, shape_offset) = crate::write::header_bytes_unknown_len(&DType::Record(fields), &[])?;
+    output.write_all(&header)?;
+
+    let right_size = right.records.item_size();
+    let (key_start, key_end) = (right.key_offset, right.key_offset + right.key_type.num_bytes().expect("scalar"));
+    // the right records with the key of the current left record, without their keys
+    let group_record_size = right_size - (key_end - key_start);
+    let mut group = vec![];
+    let mut group_len = 0;
+    let mut group_key = None;
+    let mut buf = vec![];
+    let mut count = 0;
+    while let Some((left_key, left_record)) = left.next()? {
+        if group_key.as_ref() != Some(&left_key) {
+            group.clear();
+            group_len = 0;
+            group_key = None;
+            while let Some(right_key) = right.peek_key()? {
+                if right_key > &left_key[..] {
+                    break;
+                }
+                let (right_key, right_record) = right.next()?.expect("a key was peeked");
+                if right_key == left_key {
+                    group.extend_from_slice(&right_record[..key_start]);
+                    group.extend_from_slice(&right_record[key_end..]);
+                    group_len += 1;
+                    group_key = Some(right_key);
+                }
+            }
+        }
+        for i in 0..group_len {
+            buf.extend_from_slice(&left_record);
+            buf.extend_from_slice(&group[i * group_record_size..(i + 1) * group_record_size]);
+        }
+        count += group_len as u64;
+        if buf.len() >= WRITE_CHUNK_SIZE {
+            output.write_all(&buf)?;
+            buf.clear();
+        }
+    }
+    output.write_all(&buf)?;
+    crate::write::write_unknown_len(&mut output, start + shape_offset, count)?;
+    output.flush()?;
+    Ok(count)
+}
+
+// One of the files being joined.
+struct Side<R: Read> {
+    records: RawRecords<R>,
+    which: &'static str,
+    key: String,
+    fields: Vec<Field>,
+    key_index: usize,
+    key_offset: usize,
+    key_type: TypeStr,
+    // the next record and its key, if it was peeked
+    peeked: Option<(Vec<u8>, Vec<u8>)>,
+    last_key: Option<Vec<u8>>,
+    index: u64,
+}
+
+impl<R: Read> Side<R> {
+    fn new(records: RawRecords<R>, key: &str, which: &'static str) -> io::Result<Self> {
+        let dtype = records.dtype();
+        let fields = match &dtype {
+            DType::Record(fields) => fields.clone(),
+            _ => return Err(Error::InvalidInput(format!("the {} file is not structured (dtype {})", which, dtype.descr())).into()),
+        };
+        let key_index = match fields.iter().position(|field| field.name == key) {
+            Some(index) => index,
+            None => return Err(Error::InvalidInput(format!("the {} file has no field '{}'", which, key)).into()),
+        };
+        let key_type = match &fields[key_index].dtype {
+            DType::Plain(ty) if can_sort(ty) => ty.clone(),
+            other => {
+                let msg = format!("the key '{}' has type {}, which cannot be joined on", key, other.descr());
+                return Err(Error::InvalidInput(msg).into());
+            },
+        };
+        let key_offset = dtype.field_offsets().expect("size was checked when reading the header")[key_index];
+        Ok(Side {
+            records, which, key: key.to_string(), fields, key_index, key_offset, key_type,
+            peeked: None, last_key: None, index: 0,
+        })
+    }
+
+    fn peek_key(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.peeked.is_none() {
+            self.peeked = self.read()?;
+        }
+        Ok(self.peeked.as_ref().map(|(key, _)| &key[..]))
+    }
+
+    fn next(&mut self) -> io::Result<Option<(Vec<u8>, Vec<u8>)>> {
+        match self.peeked.take() {
+            Some(peeked) => Ok(Some(peeked)),
+            None => self.read(),
+        }
+    }
+
+    fn read(&mut self) -> io::Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let record = match self.records.next_record()? {
+            Some(record) => record.to_vec(),
+            None => return Ok(None),
+        };
+        let size = self.key_type.num_bytes().expect("scalar");
+        let key = sort_key(&self.key_type, &record[self.key_offset..self.key_offset + size]);
+        if self.last_key.as_ref().is_some_and(|last| key < *last) {
+            let msg = format!("the {} file is not sorted by '{}'", self.which, self.key);
+            return Err(Error::at_record(Error::InvalidData(msg).into(), self.index, None));
+        }
+        self.last_key = Some(key.clone());
+        self.index += 1;
+        Ok(Some((key, record)))
+    }
+}
+
+fn same_kind(a: &TypeStr, b: &TypeStr) -> bool {
+    let kind = |ty: &TypeStr| match ty.type_char() {
+        TypeChar::Uint => TypeChar::Int,
+        type_char => type_char,
+    };
+    kind(a) == kind(b) && a.time_units() == b.time_units()
+}
+
+// Whether `sort_key` supports a type.
+pub(crate) fn can_sort(ty: &TypeStr) -> bool {
+    !matches!((ty.type_char(), ty.size_field()), (TypeChar::Complex, _) | (TypeChar::Float, 2 | 16))
+}
+
+// Encode a key so that the bytes compare in the same order as the values.
+pub(crate) fn sort_key(ty: &TypeStr, bytes: &[u8]) -> Vec<u8> {
+    let mut native = bytes.to_vec();
+    if Endianness::of_machine().requires_swap(ty.endianness()) {
+        match ty.type_char() {
+            TypeChar::UnicodeStr => native.chunks_mut(4).for_each(|unit| unit.reverse()),
+            TypeChar::ByteStr | TypeChar::RawData => {},
+            _ => native.reverse(),
+        }
+    }
+    let int = |x: i128| ((x as u128) ^ (1 << 127)).to_be_bytes().to_vec();
+    match (ty.type_char(), native.len()) {
+        (TypeChar::Bool, _) => native,
+        (TypeChar::Int | TypeChar::TimeDelta | TypeChar::DateTime, 1) => int(native[0] as i8 as i128),
+        (TypeChar::Int | TypeChar::TimeDelta | TypeChar::DateTime, 2) => int(i16::from_ne_bytes([native[0], native[1]]) as i128),
+        (TypeChar::Int | TypeChar::TimeDelta | TypeChar::DateTime, 4) => int(i32::from_ne_bytes(native[..].try_into().unwrap()) as i128),
+        (TypeChar::Int | TypeChar::TimeDelta | TypeChar::DateTime, _) => int(i64::from_ne_bytes(native[..].try_into().unwrap()) as i128),
+        (TypeChar::Uint, 1) => int(native[0] as i128),
+        (TypeChar::Uint, 2) => int(u16::from_ne_bytes([native[0], native[1]]) as i128),
+        (TypeChar::Uint, 4) => int(u32::from_ne_bytes(native[..].try_into().unwrap()) as i128),
+        (TypeChar::Uint, _) => int(u64::from_ne_bytes(native[..].try_into().unwrap()) as i128),
+        (TypeChar::Float, 4) => float(f32::from_ne_bytes(native[..].try_into().unwrap()) as f64),
+        (TypeChar::Float, 8) => float(f64::from_ne_bytes(native[..].try_into().unwrap())),
+        (TypeChar::ByteStr, _) => {
+            let len = native.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            native.truncate(len);
+            native
+        },
+        (TypeChar::UnicodeStr, _) => {
+            let mut out = native.chunks(4).flat_map(|unit| u32::from_ne_bytes(unit.try_into().unwrap()).to_be_bytes()).collect::<Vec<_>>();
+            while out.ends_with(&[0; 4]) {
+                out.truncate(out.len() - 4);
+            }
+            out
+        },
+        (TypeChar::RawData, _) => native,
+        (TypeChar::Float | TypeChar::Complex, _) => unreachable!("(BUG!) checked by can_sort"),
+    }
+}
+
+// The bits of a float, ordered like `f64::total_cmp` except that -0.0 equals 0.0.
+fn float(x: f64) -> Vec<u8> {
+    let bits = (x + 0.0).to_bits() as i64;
+    let ordered = bits ^ (((bits >> 63) as u64) >> 1) as i64;
+    ((ordered as u64) ^ (1 << 63)).to_be_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{write_dyn, DynValue};
+
+    fn write(dtype: &str, rows: Vec<Vec<DynValue>>) -> Vec<u8> {
+        let mut out = vec![];
+        let shape = [rows.len() as u64];
+        write_dyn(&mut out, &DType::parse(dtype).unwrap(), &shape, rows.into_iter().map(DynValue::Record)).unwrap();
+        out
+    }
+
+    fn join(left: &[u8], right: &[u8], key: &str) -> io::Result<Vec<u8>> {
+        let mut output = io::Cursor::new(vec![]);
+        let count = merge_join(left, right, &mut output, key)?;
+        assert_eq!(NpyFile::new(&output.get_ref()[..])?.len(), count);
+        Ok(output.into_inner())
+    }
+
+    // the bytes after the header
+    fn data(bytes: &[u8]) -> &[u8] {
+        let npy = NpyFile::new(bytes).unwrap();
+        &bytes[bytes.len() - npy.len() as usize * npy.dtype().num_bytes().unwrap()..]
+    }
+
+    #[test]
+    fn many_to_many() {
+        let left = write("[('k', '>i2'), ('a', '|u1')]", vec![
+            vec![(-5i64).into(), 0u64.into()],
+            vec![1i64.into(), 1u64.into()],
+            vec![1i64.into(), 2u64.into()],
+            vec![3i64.into(), 3u64.into()],
+            vec![7i64.into(), 4u64.into()],
+        ]);
+        let right = write("[('b', '|u1'), ('k', '<u8')]", vec![
+            vec![10u64.into(), 0u64.into()],
+            vec![11u64.into(), 1u64.into()],
+            vec![12u64.into(), 1u64.into()],
+            vec![13u64.into(), 7u64.into()],
+            vec![14u64.into(), 9u64.into()],
+        ]);
+        let output = join(&left, &right, "k").unwrap();
+        let expected = write("[('k', '>i2'), ('a', '|u1'), ('b', '|u1')]", vec![
+            vec![1i64.into(), 1u64.into(), 11u64.into()],
+            vec![1i64.into(), 1u64.into(), 12u64.into()],
+            vec![1i64.into(), 2u64.into(), 11u64.into()],
+            vec![1i64.into(), 2u64.into(), 12u64.into()],
+            vec![7i64.into(), 4u64.into(), 13u64.into()],
+        ]);
+        assert_eq!(NpyFile::new(&output[..]).unwrap().dtype(), NpyFile::new(&expected[..]).unwrap().dtype());
+        assert_eq!(data(&output), data(&expected));
+    }
+
+    #[test]
+    fn strings_and_floats() {
+        let left = write("[('k', '|S3')]", vec![vec![DynValue::Bytes(b"a".to_vec())], vec![DynValue::Bytes(b"ab".to_vec())]]);
+        let right = write("[('k', '|S2'), ('x', '|i1')]", vec![vec![DynValue::Bytes(b"ab".to_vec()), 5i64.into()]]);
+        assert_eq!(NpyFile::new(&join(&left, &right, "k").unwrap()[..]).unwrap().len(), 1);
+
+        let left = write("[('k', '<U2')]", vec![vec!["a".into()], vec!["é".into()]]);
+        let right = write("[('k', '>U1'), ('x', '|i1')]", vec![vec!["a".into(), 1i64.into()], vec!["é".into(), 2i64.into()]]);
+        assert_eq!(NpyFile::new(&join(&left, &right, "k").unwrap()[..]).unwrap().len(), 2);
+
+        let left = write("[('k', '<f8')]", vec![vec![(-2.5).into()], vec![(-0.0).into()], vec![1.5.into()]]);
+        let right = write("[('k', '>f4'), ('x', '|i1')]", vec![vec![(-2.5).into(), 1i64.into()], vec![0.0.into(), 2i64.into()], vec![1.0.into(), 3i64.into()]]);
+        assert_eq!(NpyFile::new(&join(&left, &right, "k").unwrap()[..]).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn errors() {
+        let check = |left: &[u8], right: &[u8], key: &str, expected: &str| {
+            let err = join(left, right, key).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+        };
+        let ints = write("[('k', '<i4'), ('a', '<i4')]", vec![vec![1i64.into(), 0i64.into()], vec![2i64.into(), 0i64.into()]]);
+        let unsorted = write("[('k', '<i4')]", vec![vec![2i64.into()], vec![1i64.into()]]);
+        let floats = write("[('k', '<f8')]", vec![vec![1.0.into()]]);
+        let complex = write("[('k', '<c16')]", vec![vec![DynValue::Complex(1.0, 0.0)]]);
+        let mut plain = vec![];
+        write_dyn(&mut plain, &DType::parse("'<i4'").unwrap(), &[0], vec![]).unwrap();
+
+        check(&ints, &unsorted, "k", "the right file is not sorted by 'k' (at record 1)");
+        check(&unsorted, &ints, "k", "the left file is not sorted");
+        check(&ints, &floats, "k", "cannot join key 'k' of type <i4 with type <f8");
+        check(&ints, &complex, "k", "cannot be joined on");
+        check(&ints, &ints, "k", "field 'a' is in both files");
+        check(&ints, &floats, "a", "the right file has no field 'a'");
+        check(&plain, &ints, "k", "the left file is not structured");
+    }
+}
@@ -22,12 +22,48 @@ No features are enabled by default.  Here is the list of existing features:
   * **`"complex"`** enables the use of [`num_complex::Complex`].
   * **`"arrayvec"`** enables the use of [`arrayvec::ArrayVec`] and [`arrayvec::ArrayString`]
     as alternatives to `Vec` and `String` for some string types.
-* **`"derive"`** enables derives of traits for working with structured arrays.
+  * **`"serde"`** implements [`serde::Serialize`] and [`serde::Deserialize`] for [`DType`] and [`TypeStr`],
+    using the format described by [`DTYPE_JSON_SCHEMA`].
+  * **`"arrow"`** enables [`DType::to_arrow`] and [`DType::from_arrow`] for converting to and from
+    [`arrow_schema::DataType`].
+  * **`"uom"`** enables the use of [`uom::si::Quantity`] (stored as a number in base units), and
+    [`FieldUnits`] for recording their units in the header and checking them when reading.
+  * **`"proptest"`** and **`"arbitrary"`** implement `proptest::arbitrary::Arbitrary` and
+    `arbitrary::Arbitrary` for [`DType`], [`TypeStr`], [`generate::SmallShape`] and [`generate::SmallArray`],
+    for property testing code that handles NPY files.
+  * **`"ndarray"`** enables [`NpyFile::into_array`] and [`WriterBuilder::write_array`] for reading
+    and writing [`ndarray::Array`]s.
+  * **`"nalgebra"`** enables [`NpyFile::into_dmatrix`] and [`WriterBuilder::write_matrix`] for reading
+    and writing [`nalgebra`] matrices.  With `"npz"`, it also implements conversions between the
+    COO, CSR and CSC types of the [`sparse`] module and those of [`nalgebra_sparse`].
+  * **`"bytes"`** lets a [`VecSink`] append to a [`bytes::BytesMut`], so that a file can be written
+    straight into a buffer that is then frozen into [`bytes::Bytes`] without copying.
+* **`"derive"`** enables derives of traits for working with structured arrays,
+  as well as the [`dtype!`] macro.
 * **`"npz"`** enables adapters for working with NPZ files
   (including scipy sparse matrices),
   adding a public dependency on the `zip` crate.
   This requires opt-in because `zip` has a fair number of transitive dependencies.
   (note that some npz-related helper functions are available even without the feature)
+* **`"npz-crypto"`** enables [`NpzArchive::with_password`][`npz::NpzArchive::with_password`], for reading
+  password-protected NPZ files.  It implies `"npz"`.
+* **`"mmap"`** enables [`NpyMmapMut`], for editing the data of an NPY file in place through a
+  memory map, and [`create_memmap`], for creating one and filling it in.  This is currently only supported on unix platforms.
+* **`"gzip"`** and **`"zstd"`** enable [`CompressedWriter`], for writing a single array to a `.npy.gz` or
+  `.npy.zst` file.
+* **`"io-uring"`** enables [`UringFile`], a file that submits the batched reads of
+  [`NpyReader::read_many_at`] and [`NpyReader::read_ranges_at`] to the kernel through io_uring.
+  This is only supported on Linux (5.6 or later).
+* **`"async"`** enables [`NpyFile::new_async`], for reading NPY files from a `tokio::io::AsyncRead`,
+  and [`npz::AsyncNpzArchive`] for reading NPZ files from one that can also seek.
+* **`"tar"`** enables [`NpyTarReader`], for reading the `.npy` files in a tar archive without extracting
+  them.  Together with `"gzip"`, it also reads `.tar.gz` archives.
+* **`"pickle"`** adds the [`pickle`] module, for reading object arrays (`dtype=object`), which numpy
+  saves as Python pickles.
+* **`"capi"`** adds the [`capi`] module, a C API for reading and writing NPY files from other languages,
+  declared in `include/npyz.h`.
+* **`"cli"`** builds the `npyz` command line tool (`cargo install npyz --features cli`), which can
+  inspect NPY and NPZ files without Python.  It implies `"npz"`.
 
 ## Reading
 
@@ -122,7 +158,11 @@ can be found on the [`crate::type_matchup_docs`] module.
 ## Working with `ndarray`
 
 Using the [`ndarray`](https://docs.rs/ndarray) crate?  No problem!
-At the time, no conversion API is provided by `npyz`, but one can easily be written:
+With the **`"ndarray"`** feature, [`NpyFile::into_array`] reads a file into an array of its shape and
+order, and [`WriterBuilder::write_array`] writes any array or view.
+
+If you would rather not tie your version of `ndarray` to that of `npyz`, the conversions can easily
+be written by hand:
 
 ```rust
 // Example of parsing to an array with fixed NDIM.
@@ -267,16 +307,83 @@ Array { a: 2, b: 3.1, c: 5 }
 #[cfg(feature = "derive")] pub use npyz_derive::*;
 
 mod header;
+mod error;
+mod diagnostics;
+mod dtype_builder;
+mod dtype_compat;
+mod cast;
 mod read;
+mod aligned;
+mod view;
+mod decoder;
 mod write;
 mod type_str;
 mod serialize;
+mod compare;
+mod sanitize;
+mod dynamic;
+mod columns;
+mod join;
+mod group;
+mod shape;
+mod byteswap;
+mod transpose;
+mod shuffle;
+mod rechunk;
+mod read_ahead;
+mod write_behind;
+mod batched;
+mod small;
+mod read_at;
+mod range;
+mod checkpoint;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compress;
+#[cfg(feature = "serde")]
+mod dtype_serde;
+#[cfg(feature = "arrow")]
+mod dtype_arrow;
+#[cfg(feature = "uom")]
+mod units;
+#[cfg(feature = "proptest")]
+mod proptest_impls;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+#[cfg(feature = "ndarray")]
+mod ndarray_feature;
+#[cfg(feature = "nalgebra")]
+mod nalgebra_feature;
 #[cfg(feature = "npz")]
 mod npz_feature;
+#[cfg(feature = "npz")]
+mod npz_manifest;
+#[cfg(feature = "npz")]
+mod npz_labeled;
+#[cfg(feature = "npz")]
+mod npz_salvage;
+#[cfg(feature = "npz")]
+mod npz_stream;
+#[cfg(feature = "npz")]
+mod npz_parallel;
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+#[cfg(feature = "tar")]
+mod tar;
+#[cfg(feature = "async")]
+mod async_read;
+#[cfg(feature = "async")]
+mod async_npz;
+#[cfg(feature = "capi")]
+pub mod capi;
 
 pub mod npz;
 #[cfg(feature = "npz")]
 pub mod sparse;
+#[cfg(feature = "pickle")]
+pub mod pickle;
+pub mod generate;
 
 pub mod type_matchup_docs;
 
@@ -285,16 +392,76 @@ pub mod type_matchup_docs;
 pub use num_complex;
 #[cfg(feature = "arrayvec")]
 pub use arrayvec;
-#[cfg(feature = "zip")]
+#[cfg(feature = "npz")]
 pub use zip;
-
-pub use header::{DType, Field};
+#[cfg(feature = "serde")]
+pub use serde;
+#[cfg(feature = "arrow")]
+pub use arrow_schema;
+#[cfg(feature = "uom")]
+pub use uom;
+#[cfg(feature = "proptest")]
+pub use proptest;
+#[cfg(feature = "arbitrary")]
+pub use arbitrary;
+#[cfg(feature = "ndarray")]
+pub use ndarray;
+#[cfg(feature = "nalgebra")]
+pub use nalgebra;
+#[cfg(feature = "nalgebra")]
+pub use nalgebra_sparse;
+#[cfg(feature = "bytes")]
+pub use bytes;
+
+pub use header::{DType, Field, DTypeNode, DTypeWalk};
+pub use error::{Error, ErrorContext};
+pub use diagnostics::Diagnostic;
+pub use dtype_builder::{ScalarDTypeBuilder, RecordDTypeBuilder, DTypeBuildError};
+pub use dtype_compat::CompatPolicy;
+pub use cast::{Casting, CastReader, CastTarget};
+#[cfg(feature = "serde")]
+pub use dtype_serde::DTYPE_JSON_SCHEMA;
+#[cfg(feature = "arrow")]
+pub use dtype_arrow::ArrowTypeError;
+#[cfg(feature = "uom")]
+pub use units::{FieldUnits, QuantityUnit, UNITS_HEADER_KEY};
 #[allow(deprecated)]
-pub use read::{NpyData, NpyFile, NpyHeader, NpyReader, Order};
+pub use read::{NdIndices, NpyData, NpyFile, NpyHeader, NpyReader, Order, RawRecords, ReadOptions, TiledIndices};
 #[allow(deprecated)]
-pub use write::{to_file, to_file_1d, OutFile, NpyWriter, write_options, WriteOptions, WriterBuilder};
-pub use serialize::FixedSizeBytes;
+pub use write::{to_file, to_file_1d, OutFile, NpyWriter, write_options, WriteOptions, WriterBuilder, FlushPolicy, Preallocate, VecSink, SinkBuffer};
+pub use serialize::{Complex, DateTime64, TimeDelta64, FixedSizeBytes, Truncate};
 pub use serialize::{Serialize, Deserialize, AutoSerialize};
 pub use serialize::{TypeRead, TypeWrite, TypeWriteDyn, TypeReadDyn, DTypeError};
+pub use shape::{Shape, Shape1, Shape2, Shape3};
+pub use compare::{compare, Tolerance, Comparison, Mismatch, ElementValue};
+pub use sanitize::{sanitize, count_invalid, Fix, SanitizeOptions, InvalidCounts};
+pub use dynamic::{write_dyn, DynValue};
+pub use columns::{append_columns, Column};
+pub use join::merge_join;
+pub use group::{group_by, group_by_sorted, Aggregation};
+pub use byteswap::{byteswap, byteswap_file};
+pub use transpose::transpose_file;
+pub use shuffle::shuffle_file;
+pub use rechunk::{rechunk, reshape};
+pub use read_ahead::ReadAhead;
+pub use write_behind::WriteBehind;
+pub use batched::BatchedWriter;
+pub use aligned::AlignedVec;
+pub use view::NpyView;
+pub use decoder::NpyDecoder;
+pub use small::{BatchReader, BatchWriter};
+pub use read_at::{ReadAt, ReadAtCursor, ReadAtMany};
+pub use range::{RangeReader, RangeSource};
+pub use checkpoint::{CheckpointLog, CheckpointEntry};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use compress::{Compression, CompressedWriter};
+#[cfg(all(feature = "mmap", unix))]
+pub use mmap::{create_memmap, NpyMmapMut, Pod};
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub use uring::UringFile;
+#[cfg(feature = "tar")]
+pub use tar::{NpyTarReader, TarMember};
+#[cfg(feature = "async")]
+pub use async_read::{AsyncNpyFile, AsyncNpyReader};
 pub use type_str::{TypeStr, ParseTypeStrError};
 pub use type_str::{Endianness, TypeChar, TimeUnits};
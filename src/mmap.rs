@@ -0,0 +1,358 @@
+//! Editing NPY files through a memory map.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::{NpyHeader, Order};
+use crate::serialize::{AutoSerialize, DTypeError};
+
+/// Types whose values can be viewed directly in the bytes of a file.
+///
+/// *This is only available with the **`"mmap"`** feature.*
+///
+/// # Safety
+///
+/// Every bit pattern of `size_of::<Self>()` bytes must be a valid value, the type must have no padding,
+/// and its in-memory representation must be exactly that of [`AutoSerialize::default_dtype`] in the
+/// native byte order.
+pub unsafe trait Pod: AutoSerialize + Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($T:ty),*) => { $( unsafe impl Pod for $T {} )* };
+}
+
+impl_pod!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, crate::Complex<f32>, crate::Complex<f64>);
+
+/// _This impl is only available with the **`"complex"`** feature._
+#[cfg(feature = "complex")]
+unsafe impl Pod for num_complex::Complex<f32> {}
+/// _This impl is only available with the **`"complex"`** feature._
+#[cfg(feature = "complex")]
+unsafe impl Pod for num_complex::Complex<f64> {}
+
+/// A writable memory map of the data in an NPY file, like numpy's `open_memmap(path, mode='r+')`.
+///
+/// This derefs to `&mut [T]` over all elements in the order they are stored in the file.
+/// (i.e. the reverse of the usual index order if the array is [Fortran order][`Order::Fortran`])
+/// Writes go directly to the page cache, and reach the file at the latest when the map is dropped;
+/// use [`Self::flush`] to write them out sooner and observe errors.
+///
+/// The dtype of the file must be exactly [`T::default_dtype()`][`AutoSerialize::default_dtype`]
+/// in the native byte order.  Files in the other byte order can first be converted with [`crate::byteswap_file`].
+///
+/// *This is only available with the **`"mmap"`** feature, on unix platforms.*
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// # let path = std::env::temp_dir().join(format!("npyz-mmap-doctest-{}.npy", std::process::id()));
+/// npyz::to_file_1d(&path, vec![1.0_f64, 2.0, 3.0])?;
+///
+/// // SAFETY: Nothing else maps or writes to the file while the map is alive.
+/// let mut map = unsafe { npyz::NpyMmapMut::<f64>::open(&path)? };
+/// map[1] = 20.0;
+/// map.flush()?;
+/// drop(map);
+///
+/// let npy = npyz::NpyFile::new(std::fs::File::open(&path)?)?;
+/// assert_eq!(npy.into_vec::<f64>()?, vec![1.0, 20.0, 3.0]);
+/// # std::fs::remove_file(&path)?;
+/// # Ok(()) }
+/// ```
+///
+/// # Safety considerations
+///
+/// As with any writable memory map, modification of the file by another process or by another
+/// handle in this process while it is mapped is undefined behavior, because it changes memory
+/// behind the `&mut [T]`.  This is why the constructors are `unsafe`.  Truncating the file while it
+/// is mapped can cause the process to be killed by `SIGBUS`.
+pub struct NpyMmapMut<T: Pod> {
+    header: NpyHeader,
+    ptr: *mut libc::c_void,
+    map_len: usize,
+    data_offset: usize,
+    len: usize,
+    _file: File,
+    _marker: PhantomData<T>,
+}
+
+// The map behaves like an owned `Box<[T]>`.
+unsafe impl<T: Pod + Send> Send for NpyMmapMut<T> {}
+unsafe impl<T: Pod + Sync> Sync for NpyMmapMut<T> {}
+
+impl<T: Pod> NpyMmapMut<T> {
+    /// Open an existing NPY file for reading and writing, and map its data into memory.
+    ///
+    /// Fails if the dtype does not match `T` (see the type-level docs), if the file is truncated,
+    /// or if the data is not suitably aligned for `T` within the file.
+    ///
+    /// # Safety
+    ///
+    /// While the returned map is alive, the file must not be mapped again (e.g. by a second call to
+    /// this function), written to, or truncated, whether by this process or by any other.
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        // SAFETY: The caller upholds the same contract.
+        unsafe { Self::from_file(file) }
+    }
+
+    /// Map the data of an NPY file that is already open for reading and writing.
+    ///
+    /// The header is read from the beginning of the file, regardless of the current position.
+    ///
+    /// # Safety
+    ///
+    /// The same as for [`Self::open`].  Writing through `file` itself is not allowed either,
+    /// since the map takes ownership of it.
+    pub unsafe fn from_file(mut file: File) -> io::Result<Self> {
+        io::Seek::seek(&mut file, io::SeekFrom::Start(0))?;
+        let header = NpyHeader::from_reader(io::BufReader::new(&mut file))?;
+
+        check_dtype::<T>(&header.dtype())?;
+
+        let data_offset = header.raw_bytes().expect("header was read from a file").len();
+        if data_offset % std::mem::align_of::<T>() != 0 {
+            return Err(Error::InvalidData(format!(
+                "data begins at offset {}, which is not aligned for {}", data_offset, std::any::type_name::<T>(),
+            )).into());
+        }
+        let too_large = || Error::InvalidData(format!("shape {:?} is too large to map into memory", header.shape()));
+        let len = usize::try_from(header.len()).map_err(|_| too_large())?;
+        let map_len = len.checked_mul(std::mem::size_of::<T>())
+            .and_then(|data_len| data_len.checked_add(data_offset))
+            .ok_or_else(too_large)?;
+        if file.metadata()?.len() < map_len as u64 {
+            return Err(Error::Truncated.into());
+        }
+
+        // SAFETY: A null address lets the kernel choose where to map.  The length is nonzero
+        //         because it includes the header, and lies within the file.
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), map_len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED, file.as_raw_fd(), 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(NpyMmapMut { header, ptr, map_len, data_offset, len, _file: file, _marker: PhantomData })
+    }
+
+    /// Get the header of the file.
+    pub fn header(&self) -> &NpyHeader {
+        &self.header
+    }
+
+    /// Get the dtype as written in the file.
+    pub fn dtype(&self) -> DType {
+        self.header.dtype()
+    }
+
+    /// Get the shape as written in the file.
+    pub fn shape(&self) -> &[u64] {
+        self.header.shape()
+    }
+
+    /// Get the order in which the elements are stored.
+    pub fn order(&self) -> Order {
+        self.header.order()
+    }
+
+    /// Synchronously write all modifications to the file.
+    pub fn flush(&self) -> io::Result<()> {
+        // SAFETY: This is the range returned by mmap.
+        match unsafe { libc::msync(self.ptr, self.map_len, libc::MS_SYNC) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+fn check_dtype<T: Pod>(dtype: &DType) -> io::Result<()> {
+    let expected = T::default_dtype().to_native_endian();
+    if dtype.normalize_byte_order() != expected {
+        let reason = match dtype.to_native_endian() == expected {
+            true => format!("the data is not in native byte order (expected {})", expected.descr()),
+            false => format!("expected {}", expected.descr()),
+        };
+        return Err(Error::dtype_mismatch::<T>(dtype, DTypeError::custom(reason)).into());
+    }
+    Ok(())
+}
+
+/// Create an NPY file of the given dtype and shape, and map its data into memory to be filled in,
+/// like numpy's `open_memmap(path, mode='w+')`.
+///
+/// The file is created or truncated, the header is written, and the file is extended to its full size, so
+/// that the data reads as zeros until it is written.  The elements are in [C order][`Order::C`].  They may
+/// be filled in any order; to fill disjoint ranges from several threads, split the slice, e.g. with
+/// [`chunks_mut`][slice::chunks_mut].
+///
+/// The dtype must be `T::default_dtype()` in the native byte order, as for [`NpyMmapMut::open`];
+/// otherwise, [`Error::DTypeMismatch`] is returned before the file is touched.
+///
+/// *This is only available with the **`"mmap"`** feature, on unix platforms.*
+///
+/// # Safety
+///
+/// The same as for [`NpyMmapMut::open`]: while the returned map is alive, the file must not be mapped
+/// again, written to, or truncated, whether by this process or by any other.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::AutoSerialize;
+///
+/// # let path = std::env::temp_dir().join(format!("npyz-create-memmap-doctest-{}.npy", std::process::id()));
+/// // SAFETY: Nothing else maps or writes to the file while the map is alive.
+/// let mut map = unsafe { npyz::create_memmap::<u32>(&path, &u32::default_dtype(), &[4, 1000])? };
+/// std::thread::scope(|scope| {
+///     for (row, values) in map.chunks_mut(1000).enumerate() {
+///         scope.spawn(move || values.iter_mut().for_each(|x| *x = row as u32));
+///     }
+/// });
+/// drop(map);
+///
+/// let npy = npyz::NpyFile::new(std::fs::File::open(&path)?)?;
+/// assert_eq!(npy.shape(), &[4, 1000]);
+/// assert_eq!(npy.into_vec::<u32>()?[2500], 2);
+/// # std::fs::remove_file(&path)?;
+/// # Ok(()) }
+/// ```
+pub unsafe fn create_memmap<T: Pod>(path: impl AsRef<Path>, dtype: &DType, shape: &[u64]) -> io::Result<NpyMmapMut<T>> {
+    check_dtype::<T>(dtype)?;
+    let header = crate::write::header_bytes(dtype, Order::C, shape, &[])?;
+    let data_len = shape.iter().try_fold(std::mem::size_of::<T>() as u64, |len, &dim| len.checked_mul(dim))
+        .and_then(|len| len.checked_add(header.len() as u64))
+        .ok_or_else(|| Error::InvalidInput(format!("shape {:?} is too large", shape)))?;
+
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+    file.write_all(&header)?;
+    file.set_len(data_len)?;
+    // SAFETY: The caller upholds the same contract.
+    unsafe { NpyMmapMut::from_file(file) }
+}
+
+impl<T: Pod> Deref for NpyMmapMut<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: The map covers `len` elements after `data_offset`, which is aligned for T because
+        //         the map itself is page-aligned.  All bit patterns are valid for a Pod type.
+        unsafe { std::slice::from_raw_parts((self.ptr as *const u8).add(self.data_offset) as *const T, self.len) }
+    }
+}
+
+impl<T: Pod> DerefMut for NpyMmapMut<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // SAFETY: See `deref`.  The map is writable and not aliased by any other reference.
+        unsafe { std::slice::from_raw_parts_mut((self.ptr as *mut u8).add(self.data_offset) as *mut T, self.len) }
+    }
+}
+
+impl<T: Pod> Drop for NpyMmapMut<T> {
+    fn drop(&mut self) {
+        // SAFETY: This is the range returned by mmap, and no references to it outlive self.
+        unsafe { libc::munmap(self.ptr, self.map_len) };
+    }
+}
+
+impl<T: Pod> std::fmt::Debug for NpyMmapMut<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("NpyMmapMut")
+            .field("dtype", &self.header.dtype())
+            .field("shape", &self.header.shape())
+            .field("order", &self.header.order())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WriterBuilder;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("npyz-mmap-{}-{}.npy", name, std::process::id()))
+    }
+
+    #[test]
+    fn edit_in_place() -> io::Result<()> {
+        let path = temp_path("edit");
+        std::fs::copy("test-data/c-order.npy", &path)?;
+        {
+            let mut map = unsafe { NpyMmapMut::<i64>::open(&path)? };
+            assert_eq!(map.shape(), &[2, 3, 4]);
+            assert_eq!(map.len(), 24);
+            map.iter_mut().for_each(|x| *x *= 10);
+            map[23] = -1;
+        }
+        let values = crate::NpyFile::new(File::open(&path)?)?.into_vec::<i64>()?;
+        let expected = crate::NpyFile::new(File::open("test-data/c-order.npy")?)?.into_vec::<i64>()?;
+        assert_eq!(values[..23], expected[..23].iter().map(|x| x * 10).collect::<Vec<_>>()[..]);
+        assert_eq!(values[23], -1);
+        std::fs::remove_file(&path)
+    }
+
+    #[test]
+    fn empty() -> io::Result<()> {
+        let path = temp_path("empty");
+        crate::to_file_1d(&path, Vec::<f32>::new())?;
+        assert_eq!(unsafe { NpyMmapMut::<f32>::open(&path)? }.len(), 0);
+        std::fs::remove_file(&path)
+    }
+
+    #[test]
+    fn wrong_dtype() -> io::Result<()> {
+        let path = temp_path("dtype");
+        crate::to_file_1d(&path, vec![1.0_f64])?;
+        let err = unsafe { NpyMmapMut::<i64>::open(&path) }.unwrap_err();
+        assert!(matches!(Error::from(err).root(), Error::DTypeMismatch { .. }));
+
+        let other_endian = match crate::Endianness::of_machine() {
+            crate::Endianness::Little => ">f8",
+            _ => "<f8",
+        };
+        let dtype = DType::new_scalar(other_endian.parse().unwrap());
+        let mut writer = crate::WriteOptions::new().dtype(dtype).shape(&[1]).writer(File::create(&path)?).begin_nd()?;
+        writer.push(&1.0_f64)?;
+        writer.finish()?;
+        let err = unsafe { NpyMmapMut::<f64>::open(&path) }.unwrap_err();
+        assert!(err.to_string().contains("native byte order"), "{}", err);
+        std::fs::remove_file(&path)
+    }
+
+    #[test]
+    fn create() -> io::Result<()> {
+        let path = temp_path("create");
+        std::fs::write(&path, vec![1; 10000])?;
+        {
+            let mut map = unsafe { create_memmap::<f32>(&path, &f32::default_dtype(), &[3, 5])? };
+            assert_eq!(map.shape(), &[3, 5]);
+            assert!(map.iter().all(|&x| x == 0.0));
+            for (i, x) in map.iter_mut().enumerate().rev() {
+                *x = i as f32;
+            }
+        }
+        assert_eq!(std::fs::metadata(&path)?.len(), 128 + 60);
+        let npy = crate::NpyFile::new(File::open(&path)?)?;
+        assert_eq!(npy.order(), Order::C);
+        assert_eq!(npy.into_vec::<f32>()?, (0..15).map(|i| i as f32).collect::<Vec<_>>());
+
+        let err = unsafe { create_memmap::<f64>(&path, &f32::default_dtype(), &[1]) }.unwrap_err();
+        assert!(matches!(Error::from(err).root(), Error::DTypeMismatch { .. }));
+        assert_eq!(std::fs::metadata(&path)?.len(), 128 + 60);
+        std::fs::remove_file(&path)
+    }
+
+    #[test]
+    fn truncated() -> io::Result<()> {
+        let path = temp_path("truncated");
+        let bytes = std::fs::read("test-data/c-order.npy")?;
+        std::fs::write(&path, &bytes[..bytes.len() - 1])?;
+        assert_eq!(unsafe { NpyMmapMut::<i64>::open(&path) }.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        std::fs::remove_file(&path)
+    }
+}
@@ -0,0 +1,212 @@
+//! Conversion between NPY files or sparse matrices and the matrix types of [`nalgebra`] and [`nalgebra_sparse`].
+//!
+//! _This module is only available with the **`"nalgebra"`** feature._
+
+use std::io;
+
+use nalgebra::{DMatrix, Dim, Matrix, RawStorage, Scalar};
+
+use crate::read::{NpyFile, Order};
+use crate::serialize::{Deserialize, Serialize};
+use crate::write::NpyWriter;
+
+impl<R: io::Read> NpyFile<R> {
+    /// Read a 2-D array into an [`nalgebra::DMatrix`], in C or Fortran order as stored.
+    ///
+    /// _This method is only available with the **`"nalgebra"`** feature._
+    ///
+    /// Returns [`Error::InvalidData`][crate::Error::InvalidData] if the array is not 2-dimensional.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::WriterBuilder;
+    ///
+    /// let mut bytes = vec![];
+    /// let mut writer = npyz::WriteOptions::new().default_dtype().shape(&[2, 3]).writer(&mut bytes).begin_nd()?;
+    /// writer.extend([1.0, 2.0, 3.0, 4.0, 5.0, 6.0])?;
+    /// writer.finish()?;
+    ///
+    /// let matrix = npyz::NpyFile::new(&bytes[..])?.into_dmatrix::<f64>()?;
+    /// assert_eq!(matrix, nalgebra::dmatrix![1.0, 2.0, 3.0; 4.0, 5.0, 6.0]);
+    /// # Ok(()) }
+    /// ```
+    pub fn into_dmatrix<T: Deserialize + Scalar>(self) -> io::Result<DMatrix<T>> {
+        let [nrows, ncols] = self.shape_as::<2>()?.dims().map(|n| n as usize);
+        let order = self.order();
+        let data = self.into_vec::<T>()?;
+        Ok(match order {
+            Order::C => DMatrix::from_row_iterator(nrows, ncols, data),
+            Order::Fortran => DMatrix::from_vec(nrows, ncols, data),
+        })
+    }
+}
+
+/// Write the elements of a matrix in the given order.
+pub(crate) fn write_elements<T, R, C, S, W>(writer: &mut NpyWriter<T, W>, matrix: &Matrix<T, R, C, S>, order: Order) -> io::Result<()>
+where
+    T: Serialize,
+    R: Dim,
+    C: Dim,
+    S: RawStorage<T, R, C>,
+    W: io::Write,
+{
+    // nalgebra iterates in column-major order
+    match order {
+        Order::C => (0..matrix.nrows()).try_for_each(|i| {
+            (0..matrix.ncols()).try_for_each(|j| writer.push(&matrix[(i, j)]))
+        }),
+        Order::Fortran => matrix.iter().try_for_each(|x| writer.push(x)),
+    }
+}
+
+#[cfg(feature = "npz")]
+mod sparse {
+    use nalgebra::Scalar;
+    use nalgebra_sparse::{CooMatrix, CscMatrix, CsrMatrix, SparseFormatError};
+
+    use crate::sparse::{Coo, Csc, Csr};
+
+    fn to_usize(indices: Vec<u64>) -> Vec<usize> {
+        indices.into_iter().map(|i| i as usize).collect()
+    }
+
+    fn to_u64(indices: Vec<usize>) -> Vec<u64> {
+        indices.into_iter().map(|i| i as u64).collect()
+    }
+
+    /// Fails if an index is out of bounds or the arrays are inconsistent.
+    /// Duplicate entries are allowed, as in both formats.
+    impl<T: Scalar> TryFrom<Coo<T>> for CooMatrix<T> {
+        type Error = SparseFormatError;
+
+        fn try_from(coo: Coo<T>) -> Result<Self, Self::Error> {
+            let Coo { shape: [nrows, ncols], data, row, col } = coo;
+            CooMatrix::try_from_triplets(nrows as usize, ncols as usize, to_usize(row), to_usize(col), data)
+        }
+    }
+
+    impl<T: Scalar> From<CooMatrix<T>> for Coo<T> {
+        fn from(matrix: CooMatrix<T>) -> Self {
+            let shape = [matrix.nrows() as u64, matrix.ncols() as u64];
+            let (row, col, data) = matrix.disassemble();
+            Coo { shape, data, row: to_u64(row), col: to_u64(col) }
+        }
+    }
+
+    /// Unsorted column indices are sorted, but unlike scipy, `nalgebra_sparse` does not allow
+    /// duplicate entries in a CSR matrix.  To sum them instead, convert through [`Coo`] and [`CooMatrix`].
+    impl<T: Scalar> TryFrom<Csr<T>> for CsrMatrix<T> {
+        type Error = SparseFormatError;
+
+        fn try_from(csr: Csr<T>) -> Result<Self, Self::Error> {
+            let Csr { shape: [nrows, ncols], data, indices, indptr } = csr;
+            CsrMatrix::try_from_unsorted_csr_data(nrows as usize, ncols as usize, indptr, to_usize(indices), data)
+        }
+    }
+
+    impl<T> From<CsrMatrix<T>> for Csr<T> {
+        fn from(matrix: CsrMatrix<T>) -> Self {
+            let shape = [matrix.nrows() as u64, matrix.ncols() as u64];
+            let (indptr, indices, data) = matrix.disassemble();
+            Csr { shape, data, indices: to_u64(indices), indptr }
+        }
+    }
+
+    /// Unsorted row indices are sorted, but unlike scipy, `nalgebra_sparse` does not allow
+    /// duplicate entries in a CSC matrix.  To sum them instead, convert through [`Coo`] and [`CooMatrix`].
+    impl<T: Scalar> TryFrom<Csc<T>> for CscMatrix<T> {
+        type Error = SparseFormatError;
+
+        fn try_from(csc: Csc<T>) -> Result<Self, Self::Error> {
+            let Csc { shape: [nrows, ncols], data, indices, indptr } = csc;
+            CscMatrix::try_from_unsorted_csc_data(nrows as usize, ncols as usize, indptr, to_usize(indices), data)
+        }
+    }
+
+    impl<T> From<CscMatrix<T>> for Csc<T> {
+        fn from(matrix: CscMatrix<T>) -> Self {
+            let shape = [matrix.nrows() as u64, matrix.ncols() as u64];
+            let (indptr, indices, data) = matrix.disassemble();
+            Csc { shape, data, indices: to_u64(indices), indptr }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WriterBuilder;
+    use nalgebra::dmatrix;
+
+    #[test]
+    fn read_orders() {
+        let expected = DMatrix::from_fn(3, 4, |i, j| (10 * i + j) as i32);
+        for order in [Order::C, Order::Fortran] {
+            let mut bytes = vec![];
+            crate::WriteOptions::new().default_dtype().order(order).writer(&mut bytes).write_matrix(&expected).unwrap();
+            let npy = NpyFile::new(&bytes[..]).unwrap();
+            assert_eq!(npy.shape(), &[3, 4]);
+            assert_eq!(npy.order(), order);
+            assert_eq!(npy.into_dmatrix::<i32>().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn write_c_order() {
+        let matrix = dmatrix![1_i64, 2, 3; 4, 5, 6];
+        let mut bytes = vec![];
+        crate::WriteOptions::new().default_dtype().writer(&mut bytes).write_matrix(&matrix).unwrap();
+        assert_eq!(NpyFile::new(&bytes[..]).unwrap().into_vec::<i64>().unwrap(), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn write_view() {
+        let matrix = DMatrix::from_fn(5, 5, |i, j| (10 * i + j) as u8);
+        let view = matrix.view((1, 2), (3, 2));
+        let mut bytes = vec![];
+        crate::WriteOptions::new().default_dtype().writer(&mut bytes).write_matrix(&view).unwrap();
+        let npy = NpyFile::new(&bytes[..]).unwrap();
+        assert_eq!(npy.shape(), &[3, 2]);
+        assert_eq!(npy.into_dmatrix::<u8>().unwrap(), view.clone_owned());
+    }
+
+    #[test]
+    fn wrong_ndim() {
+        let bytes = std::fs::read("test-data/c-order.npy").unwrap();
+        let err = NpyFile::new(&bytes[..]).unwrap().into_dmatrix::<i64>().unwrap_err();
+        assert!(matches!(crate::Error::from(err), crate::Error::InvalidData(msg) if msg.contains("expected a 2-dimensional array")));
+    }
+
+    #[cfg(feature = "npz")]
+    #[test]
+    fn sparse() {
+        use nalgebra_sparse::{CooMatrix, CscMatrix, CsrMatrix, SparseFormatErrorKind};
+        use crate::sparse::{Coo, Csc, Csr};
+
+        // column indices of the first row are unsorted
+        let csr = Csr { shape: [2, 3], data: vec![1, 2, 3], indices: vec![2, 0, 1], indptr: vec![0, 2, 3] };
+        let matrix = CsrMatrix::try_from(csr.clone()).unwrap();
+        assert_eq!(matrix.get_entry(0, 0).unwrap().into_value(), 2);
+        assert_eq!(matrix.get_entry(0, 2).unwrap().into_value(), 1);
+        assert_eq!(matrix.get_entry(1, 1).unwrap().into_value(), 3);
+        let back = Csr::from(matrix);
+        assert_eq!(back.to_dense(), csr.to_dense());
+        assert_eq!(back.indices, vec![0, 2, 1]);
+
+        let csc = csr.to_csc();
+        let matrix = CscMatrix::try_from(csc.clone()).unwrap();
+        assert_eq!(Csc::from(matrix), csc);
+
+        let coo = csr.to_coo();
+        let matrix = CooMatrix::try_from(coo.clone()).unwrap();
+        assert_eq!(Coo::from(matrix), coo);
+
+        let duplicate = Csr { shape: [1, 2], data: vec![1, 2], indices: vec![1, 1], indptr: vec![0, 2] };
+        let err = CsrMatrix::try_from(duplicate).unwrap_err();
+        assert_eq!(err.kind(), &SparseFormatErrorKind::DuplicateEntry);
+
+        let out_of_bounds = Coo { shape: [1, 2], data: vec![1], row: vec![0], col: vec![2] };
+        let err = CooMatrix::try_from(out_of_bounds).unwrap_err();
+        assert_eq!(err.kind(), &SparseFormatErrorKind::IndexOutOfBounds);
+    }
+}
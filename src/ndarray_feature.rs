@@ -0,0 +1,119 @@
+//! Conversion between NPY files and [`ndarray`] arrays.
+//!
+//! _This module is only available with the **`"ndarray"`** feature._
+
+use std::io;
+
+use ndarray::{Array, ArrayBase, Data, Dimension, IxDyn, ShapeBuilder};
+
+use crate::error::Error;
+use crate::read::{NpyFile, Order};
+use crate::serialize::{Deserialize, Serialize};
+use crate::write::NpyWriter;
+
+impl<R: io::Read> NpyFile<R> {
+    /// Read the array into an [`ndarray::Array`] of its shape, in C or Fortran order as stored.
+    ///
+    /// _This method is only available with the **`"ndarray"`** feature._
+    ///
+    /// Returns [`Error::InvalidData`] if `D` has a fixed number of dimensions that differs from the file.
+    /// Use [`ndarray::IxDyn`] to accept any number.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let bytes = std::fs::read("test-data/c-order.npy")?;
+    /// let c_array = npyz::NpyFile::new(&bytes[..])?.into_array::<i64, ndarray::Ix3>()?;
+    /// assert_eq!(c_array.shape(), &[2, 3, 4]);
+    /// assert_eq!(c_array[[1, 2, 3]], 6);
+    ///
+    /// let bytes = std::fs::read("test-data/f-order.npy")?;
+    /// let f_array = npyz::NpyFile::new(&bytes[..])?.into_array::<i64, ndarray::IxDyn>()?;
+    /// assert_eq!(f_array, c_array.into_dyn());
+    /// # Ok(()) }
+    /// ```
+    pub fn into_array<T: Deserialize, D: Dimension>(self) -> io::Result<Array<T, D>> {
+        let shape = self.shape().iter().map(|&n| n as usize).collect::<Vec<_>>();
+        let dim = D::from_dimension(&IxDyn(&shape)).ok_or_else(|| Error::InvalidData(format!(
+            "expected a {}-dimensional array, got shape {:?}", D::NDIM.unwrap_or(0), shape,
+        )))?;
+        let fortran = self.order() == Order::Fortran;
+        let data = self.into_vec::<T>()?;
+        Array::from_shape_vec(dim.set_f(fortran), data).map_err(|e| Error::InvalidData(format!("shape error: {}", e)).into())
+    }
+}
+
+/// Write the elements of an array in the given order.  Contiguous arrays are written as a slice.
+pub(crate) fn write_elements<T, S, D, W>(writer: &mut NpyWriter<T, W>, array: &ArrayBase<S, D>, order: Order) -> io::Result<()>
+where
+    T: Serialize,
+    S: Data<Elem = T>,
+    D: Dimension,
+    W: io::Write,
+{
+    // fortran order is C order of the reversed axes
+    let array = match order {
+        Order::C => array.view(),
+        Order::Fortran => array.t(),
+    };
+    match array.as_slice() {
+        Some(slice) => writer.extend_from_slice(slice),
+        None => array.iter().try_for_each(|x| writer.push(x)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WriterBuilder;
+    use ndarray::{s, Ix2, Ix3};
+
+    fn read_file(path: &str) -> NpyFile<io::Cursor<Vec<u8>>> {
+        NpyFile::new(io::Cursor::new(std::fs::read(path).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn read_orders() {
+        let c = read_file("test-data/c-order.npy").into_array::<i64, Ix3>().unwrap();
+        let f = read_file("test-data/f-order.npy").into_array::<i64, Ix3>().unwrap();
+        assert_eq!(c, f);
+        assert!(c.is_standard_layout());
+        assert!(f.t().is_standard_layout());
+        assert_eq!(c.slice(s![.., 0, 0]).to_vec(), vec![1, 4]);
+    }
+
+    #[test]
+    fn wrong_ndim() {
+        let err = read_file("test-data/c-order.npy").into_array::<i64, Ix2>().unwrap_err();
+        assert!(matches!(Error::from(err), Error::InvalidData(msg) if msg.contains("expected a 2-dimensional array")));
+    }
+
+    #[test]
+    fn write_layouts() {
+        let array = Array::from_shape_fn((3, 4, 5), |(i, j, k)| (100 * i + 10 * j + k) as u32);
+        let views = [
+            array.view(),
+            array.view().reversed_axes(),
+            array.slice(s![..;-1, 1.., ..;2]),
+            array.slice(s![.., 2, ..]).insert_axis(ndarray::Axis(0)),
+        ];
+        for view in views {
+            for order in [Order::C, Order::Fortran] {
+                let mut bytes = vec![];
+                crate::WriteOptions::new().default_dtype().order(order).writer(&mut bytes).write_array(&view).unwrap();
+                let npy = NpyFile::new(&bytes[..]).unwrap();
+                assert_eq!(npy.order(), order);
+                assert_eq!(npy.into_array::<u32, ndarray::IxDyn>().unwrap(), view.into_dyn());
+            }
+        }
+    }
+
+    #[test]
+    fn write_ignores_builder_shape() {
+        let array = ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]);
+        let mut bytes = vec![];
+        crate::WriteOptions::new().default_dtype().shape(&[6]).writer(&mut bytes).write_array(&array).unwrap();
+        let npy = NpyFile::new(&bytes[..]).unwrap();
+        assert_eq!(npy.shape(), &[3, 2]);
+        assert_eq!(npy.into_vec::<f64>().unwrap(), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+}
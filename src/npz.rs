@@ -15,6 +15,8 @@
 
 #[cfg(feature = "npz")]
 pub use crate::npz_feature::*;
+#[cfg(feature = "async")]
+pub use crate::async_npz::{AsyncNpzArchive, AsyncNpzEntry};
 
 /// Get the name of the array that would correspond to the given filename inside a zip file.
 ///
@@ -34,11 +36,7 @@ pub fn array_name_from_file_name(path_in_zip: &str) -> Option<&str> {
         path = &path[..idx];
     }
 
-    if path.ends_with(".npy") {
-        Some(&path[..path.len() - 4])
-    } else {
-        None
-    }
+    path.strip_suffix(".npy")
 }
 
 /// Get the filename in a zip that `np.savez` would use for a keyword argument.
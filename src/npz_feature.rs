@@ -1,52 +1,540 @@
 //! Contents of `crate::npz` that require the `npz` feature, split off into
 //! a separate module so that they can have a single `#[cfg(feature = "npz")]`.
 
+use std::collections::HashMap;
 use std::io;
 use std::path::Path;
 use std::fs::File;
 
-use zip::result::ZipError;
+use zip::result::{InvalidPassword, ZipError, ZipResult};
 
-use crate::read::NpyFile;
-use crate::serialize::Serialize;
-use crate::write::{WriterBuilder, write_options};
+use crate::error::Error;
+use crate::npz_manifest::{self, PendingEntry};
+use crate::npz_parallel::SharedReader;
+use crate::write_behind::WriteBehind;
+use crate::header::DType;
+use crate::read::{NpyFile, NpyHeader, Order, ReadOptions};
+use crate::read_at::{ReadAt, ReadAtCursor};
+use crate::serialize::{Deserialize, Serialize};
+use crate::write::{VecSink, WriterBuilder, write_options};
+
+pub use crate::npz_manifest::{Manifest, ManifestEntry, MANIFEST_FILE_NAME};
+pub use crate::npz_labeled::{coord_array_name, Dim, LabeledArray, LABELED_DATA_NAME, LABELS_FILE_NAME};
+pub use crate::npz_salvage::SalvagedNpz;
+pub use crate::npz_stream::NpzStreamReader;
+pub use crate::npz_parallel::LoadedArray;
 
 /// Interface for reading an NPZ file.
 ///
+/// Cloning an archive (when the reader can be cloned) shares the parsed list of entries, so it is a cheap
+/// way to read the same archive from several threads; see [`Self::from_read_at`].
+///
 /// *This is only available with the **`"npz"`** feature.*
+#[derive(Clone)]
 pub struct NpzArchive<R: io::Read + io::Seek> {
     zip: zip::ZipArchive<R>,
+    options: ReadOptions,
+    // parsed headers of the arrays that have been accessed, by array name
+    headers: HashMap<String, NpyHeader>,
+    duplicate_names: DuplicateNames,
+    // indices of the entries of every file name that occurs more than once, in the order they are stored
+    duplicates: HashMap<String, Vec<usize>>,
+    password: Option<Vec<u8>>,
+}
+
+/// What [`NpzArchive`] does when several entries in the archive have the same name.
+///
+/// Zip files may legally contain the same name more than once, and some writers produce such
+/// archives when an array is "overwritten" by appending it again.  `numpy.load` silently uses the
+/// last one.  Set with [`NpzArchive::with_duplicate_names`].
+///
+/// This only affects [`NpzArchive::by_name`] and [`NpzArchive::header`]; every copy can always be
+/// read with [`NpzArchive::by_name_nth`].
+///
+/// *This is only available with the **`"npz"`** feature.*
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum DuplicateNames {
+    /// Use the last entry with the name, like numpy and scipy.  This is the default.
+    #[default]
+    Last,
+    /// Use the first entry with the name.
+    First,
+    /// Fail with [`std::io::ErrorKind::InvalidData`] when reading a name that occurs more than once.
+    Error,
+}
+
+/// Information about an array in an NPZ file, returned by [`NpzArchive::entries`].
+///
+/// *This is only available with the **`"npz"`** feature.*
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NpzEntry {
+    /// The name of the array. (without `.npy`)
+    pub name: String,
+    /// The dtype in the header.
+    pub dtype: DType,
+    /// The shape in the header.
+    pub shape: Vec<u64>,
+    /// The order in the header.
+    pub order: Order,
+    /// How the NPY file is compressed in the archive.
+    pub compression: zip::CompressionMethod,
+    /// The size in bytes of the NPY file as stored in the archive.
+    pub compressed_size: u64,
+    /// The size in bytes of the NPY file, including the header.
+    pub size: u64,
 }
 
 impl NpzArchive<io::BufReader<File>> {
     /// Open an `npz` archive from the filesystem.
     pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
-        Ok(Self::new(io::BufReader::new(File::open(path)?))?)
+        Self::new(io::BufReader::new(File::open(path)?))
+    }
+
+    /// Open an `npz` archive from the filesystem, using [`ReadOptions`] for the archive and the arrays in it.
+    ///
+    /// The file is read through a buffer of [`ReadOptions::buffer_size`] bytes.
+    pub fn open_with_options(path: impl AsRef<Path>, options: &ReadOptions) -> io::Result<Self> {
+        let reader = io::BufReader::with_capacity(options.get_buffer_size(), File::open(path)?);
+        Ok(Self::new(reader)?.with_read_options(options.clone()))
+    }
+}
+
+impl<T: ReadAt> NpzArchive<ReadAtCursor<T>> {
+    /// Read an `npz` archive from a source with positioned reads, such as a `&File` or an `Arc<File>`.
+    ///
+    /// Clones of the archive each have their own position in the source, so several threads can read
+    /// arrays from the same file at once without any locking.  Reads go straight to the source, so
+    /// this is best suited to reading large arrays.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::npz::NpzArchive;
+    ///
+    /// let file = std::fs::File::open("test-data/uncompressed.npz")?;
+    /// let npz = NpzArchive::from_read_at(&file)?;
+    /// std::thread::scope(|scope| {
+    ///     for name in ["ints", "floats"] {
+    ///         let mut npz = npz.clone();
+    ///         scope.spawn(move || {
+    ///             let npy = npz.by_name(name).unwrap().unwrap();
+    ///             println!("{}: {:?}", name, npy.shape());
+    ///         });
+    ///     }
+    /// });
+    /// # Ok(()) }
+    /// ```
+    pub fn from_read_at(source: T) -> io::Result<Self> {
+        Self::new(ReadAtCursor::new(source))
     }
 }
 
 impl<R: io::Read + io::Seek> NpzArchive<R> {
     /// Wrap around an arbitrary stream.
     pub fn new(reader: R) -> io::Result<Self> {
-        Ok(NpzArchive { zip: zip::ZipArchive::new(reader).map_err(invalid_data)? })
+        let mut zip = zip::ZipArchive::new(reader).map_err(zip_error)?;
+        let duplicates = find_duplicates(&mut zip)?;
+        Ok(NpzArchive {
+            zip,
+            options: ReadOptions::default(),
+            headers: HashMap::new(),
+            duplicate_names: DuplicateNames::default(),
+            duplicates,
+            password: None,
+        })
+    }
+
+    /// Set which entry is read when several entries in the archive have the same name.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::npz::{DuplicateNames, NpzArchive};
+    ///
+    /// let npz = NpzArchive::open("test-data/uncompressed.npz")?;
+    /// let mut npz = npz.with_duplicate_names(DuplicateNames::Error);
+    /// assert_eq!(npz.occurrences("ints"), 1);
+    /// assert!(npz.by_name("ints")?.is_some());
+    /// # Ok(()) }
+    /// ```
+    pub fn with_duplicate_names(mut self, policy: DuplicateNames) -> Self {
+        self.duplicate_names = policy;
+        // a cached header may belong to a different entry under the new policy
+        self.headers.clear();
+        self
+    }
+
+    /// Get the policy for entries with the same name.
+    pub fn duplicate_names(&self) -> DuplicateNames {
+        self.duplicate_names
+    }
+
+    /// Set the password for reading encrypted entries, using ZipCrypto or AES encryption.
+    ///
+    /// Entries that are not encrypted can still be read as usual.  Without a password, reading an
+    /// encrypted entry fails with [`Error::Zip`].  Because of the weak checksum of ZipCrypto, a wrong
+    /// password is not always detected when an entry is opened; it then shows up as an invalid header
+    /// or a CRC mismatch instead.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut npz = npyz::npz::NpzArchive::open("test-data/encrypted.npz")?.with_password("secret");
+    /// let ints = npz.by_name("ints")?.unwrap().into_vec::<i64>()?;
+    /// # let _ = ints;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// Note that the zip crate does not support writing encrypted archives.
+    ///
+    /// *This is only available with the **`"npz-crypto"`** feature.*
+    #[cfg(feature = "npz-crypto")]
+    pub fn with_password(mut self, password: impl AsRef<[u8]>) -> Self {
+        self.password = Some(password.as_ref().to_vec());
+        self
+    }
+
+    /// Set the options used when reading the arrays in the archive.
+    pub fn with_read_options(mut self, options: ReadOptions) -> Self {
+        self.options = options;
+        // the headers must be checked again under the new options
+        self.headers.clear();
+        self
+    }
+
+    /// Get the options used when reading the arrays in the archive.
+    pub fn read_options(&self) -> &ReadOptions {
+        &self.options
     }
 
     /// Get the names of all arrays in the NPZ file.
+    ///
+    /// A name that occurs more than once in the archive is only listed once.
     pub fn array_names(&self) -> impl Iterator<Item = &str> {
         self.zip.file_names().filter_map(crate::npz::array_name_from_file_name)
     }
 
+    /// List the arrays in the NPZ file with their dtypes, shapes and sizes, in the order they are stored.
+    ///
+    /// Only the header of each array is decompressed and parsed.  Unlike [`Self::array_names`], a name that
+    /// occurs more than once is listed for every entry.  The headers are cached like those read by [`Self::header`].
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut npz = npyz::npz::NpzArchive::open("test-data/compressed.npz")?;
+    /// for entry in npz.entries()? {
+    ///     println!("{}: {} {:?}, {} of {} bytes", entry.name, entry.dtype, entry.shape, entry.compressed_size, entry.size);
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn entries(&mut self) -> io::Result<Vec<NpzEntry>> {
+        let mut entries = vec![];
+        for index in 0..self.zip.len() {
+            let file = entry_by_index(&mut self.zip, self.password.as_deref(), index).map_err(zip_error)?;
+            let name = match crate::npz::array_name_from_file_name(file.name()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let is_duplicate = self.duplicates.contains_key(file.name());
+            let (compression, compressed_size, size) = (file.compression(), file.compressed_size(), file.size());
+            let header = match self.headers.get(&name) {
+                Some(header) if !is_duplicate => header.clone(),
+                _ => {
+                    let npy = NpyFile::with_options(file, &self.options).map_err(|e| crate::Error::in_member(e, &name))?;
+                    let header = npy.with_member_name(&name).header().clone();
+                    if !is_duplicate {
+                        self.headers.insert(name.clone(), header.clone());
+                    }
+                    header
+                },
+            };
+            let (dtype, shape, order) = (header.dtype(), header.shape().to_vec(), header.order());
+            entries.push(NpzEntry { name, dtype, shape, order, compression, compressed_size, size });
+        }
+        Ok(entries)
+    }
+
     /// Read the array with the given name.
     ///
     /// If it is not present, `Ok(None)` is returned.
+    ///
+    /// The header of each array is only parsed and validated the first time it is accessed (by this
+    /// method or by [`Self::header`]).  Later calls skip over its bytes and reuse the parsed header,
+    /// so [diagnostics][`ReadOptions::on_diagnostic`] for a header are only emitted once.
+    ///
+    /// If the archive has several entries with this name, the one chosen by the
+    /// [`DuplicateNames`] policy is read.
     pub fn by_name<'a>(&'a mut self, name: &str) -> io::Result<Option<NpyFile<zip::read::ZipFile<'a>>>> {
-        match self.zip.by_name(&crate::npz::file_name_from_array_name(name)) {
-            Ok(file) => Ok(Some(NpyFile::new(file)?)),
-            Err(ZipError::FileNotFound) => Ok(None),
-            Err(ZipError::Io(e)) => Err(e),
-            Err(ZipError::InvalidArchive(s)) => Err(invalid_data(s)),
-            Err(ZipError::UnsupportedArchive(s)) => Err(invalid_data(s)),
+        let file_name = crate::npz::file_name_from_array_name(name);
+        let found = match self.duplicates.get(&file_name) {
+            Some(indices) => match self.duplicate_names {
+                DuplicateNames::Last => entry_by_index(&mut self.zip, self.password.as_deref(), indices[indices.len() - 1]),
+                DuplicateNames::First => entry_by_index(&mut self.zip, self.password.as_deref(), indices[0]),
+                DuplicateNames::Error => {
+                    let msg = format!("the archive has {} entries with this name", indices.len());
+                    return Err(crate::Error::in_member(Error::InvalidData(msg).into(), name));
+                },
+            },
+            None => entry_by_name(&mut self.zip, self.password.as_deref(), &file_name),
+        };
+        let mut file = match found {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(zip_error(e)),
+        };
+        let npy = match self.headers.get(name) {
+            Some(header) => {
+                skip_header(&mut file, header).map_err(|e| crate::Error::in_member(e, name))?;
+                NpyFile::with_header(header.clone(), file).with_buffer_size(self.options.get_buffer_size())
+            },
+            None => {
+                let npy = NpyFile::with_options(file, &self.options).map_err(|e| crate::Error::in_member(e, name))?;
+                let npy = npy.with_member_name(name);
+                self.headers.insert(name.to_string(), npy.header().clone());
+                npy
+            },
+        };
+        Ok(Some(npy))
+    }
+
+    /// Read only the header of the array with the given name, e.g. to inspect its dtype and shape.
+    ///
+    /// If it is not present, `Ok(None)` is returned.  The header is cached, so that a later
+    /// [`Self::by_name`] for the same array does not parse it again.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut npz = npyz::npz::NpzArchive::open("test-data/uncompressed.npz")?;
+    /// let names = npz.array_names().map(String::from).collect::<Vec<_>>();
+    /// for name in &names {
+    ///     let header = npz.header(name)?.unwrap();
+    ///     println!("{}: {:?}", name, header.shape());
+    /// }
+    /// let ints = npz.by_name("ints")?.unwrap().into_vec::<i64>()?;
+    /// # let _ = ints;
+    /// # Ok(()) }
+    /// ```
+    pub fn header(&mut self, name: &str) -> io::Result<Option<&NpyHeader>> {
+        if !self.headers.contains_key(name) && self.by_name(name)?.is_none() {
+            return Ok(None);
+        }
+        Ok(self.headers.get(name))
+    }
+
+    /// Get the number of entries in the archive with the given array name.
+    ///
+    /// This is more than 1 for names that occur several times, and 0 if the array is not present.
+    pub fn occurrences(&self, name: &str) -> usize {
+        let file_name = crate::npz::file_name_from_array_name(name);
+        match self.duplicates.get(&file_name) {
+            Some(indices) => indices.len(),
+            None => self.zip.file_names().filter(|&n| n == file_name).count(),
+        }
+    }
+
+    /// Read the `n`th entry (counting from 0, in the order they are stored) with the given array name,
+    /// regardless of the [`DuplicateNames`] policy.
+    ///
+    /// If there are not more than `n` such entries, `Ok(None)` is returned.  Unlike [`Self::by_name`],
+    /// this always parses the header, and does not cache it.
+    pub fn by_name_nth<'a>(&'a mut self, name: &str, n: usize) -> io::Result<Option<NpyFile<zip::read::ZipFile<'a>>>> {
+        let file_name = crate::npz::file_name_from_array_name(name);
+        let found = match self.duplicates.get(&file_name) {
+            Some(indices) => match indices.get(n) {
+                Some(&index) => entry_by_index(&mut self.zip, self.password.as_deref(), index),
+                None => return Ok(None),
+            },
+            None if n == 0 => entry_by_name(&mut self.zip, self.password.as_deref(), &file_name),
+            None => return Ok(None),
+        };
+        let file = match found {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(crate::Error::in_member(zip_error(e), name)),
+        };
+        let npy = NpyFile::with_options(file, &self.options).map_err(|e| crate::Error::in_member(e, name))?;
+        Ok(Some(npy.with_member_name(name)))
+    }
+
+    /// Read every array in the archive into memory, decompressing and decoding them on multiple threads.
+    ///
+    /// One thread is used per available CPU, each reading whole arrays.  The archive is consumed because
+    /// the threads need their own handles to the underlying reader; reads from it are serialized, so
+    /// this is most effective for compressed archives, where decompression rather than I/O is the bottleneck.
+    /// The threads are started with [`std::thread::scope`], so this needs no feature besides `"npz"`, and
+    /// does not run on (or depend on) a `rayon` thread pool.
+    /// To bound the memory used by the arrays being decoded at once, set [`ReadOptions::memory_budget`].
+    ///
+    /// All arrays must be readable as `T`.  If any array fails to load, the error for the first
+    /// such array (in the order they are stored) is returned.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::WriterBuilder;
+    /// use npyz::npz::{NpzArchive, NpzWriter};
+    ///
+    /// let mut npz = NpzWriter::new(std::io::Cursor::new(vec![]));
+    /// for layer in 0..10 {
+    ///     let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    ///     let mut writer = npz.array::<f32>(&format!("layer{}", layer), options)?.default_dtype().shape(&[100]).begin_nd()?;
+    ///     writer.extend(vec![layer as f32; 100])?;
+    ///     writer.finish()?;
+    /// }
+    /// let bytes = npz.finish()?.into_inner();
+    ///
+    /// let arrays = NpzArchive::new(std::io::Cursor::new(bytes))?.load_all_parallel::<f32>()?;
+    /// assert_eq!(arrays.len(), 10);
+    /// assert_eq!(arrays["layer3"].header.shape(), &[100]);
+    /// assert_eq!(arrays["layer3"].data, vec![3.0; 100]);
+    /// # Ok(()) }
+    /// ```
+    pub fn load_all_parallel<T: Deserialize + Send>(self) -> io::Result<HashMap<String, LoadedArray<T>>>
+    where
+        R: Send,
+    {
+        crate::npz_parallel::load_all(self.zip, &self.options, self.password.as_deref())
+    }
+
+    // Reopen the archive on a reader that can be cloned, to read several of its entries side by side.
+    pub(crate) fn into_shared(self) -> io::Result<NpzArchive<SharedReader<R>>> {
+        let zip = zip::ZipArchive::new(SharedReader::new(self.zip.into_inner())).map_err(zip_error)?;
+        Ok(NpzArchive {
+            zip,
+            options: self.options,
+            headers: self.headers,
+            duplicate_names: self.duplicate_names,
+            duplicates: self.duplicates,
+            password: self.password,
+        })
+    }
+
+    /// Read the [`Manifest`] written by [`NpzWriter::with_manifest`], if the archive has one.
+    pub fn manifest(&mut self) -> io::Result<Option<Manifest>> {
+        let mut file = match entry_by_name(&mut self.zip, self.password.as_deref(), MANIFEST_FILE_NAME) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(zip_error(e)),
+        };
+        let mut text = String::new();
+        io::Read::read_to_string(&mut file, &mut text)?;
+        Manifest::from_json(&text).map(Some)
+    }
+
+    /// Check every array in the archive against the [`Manifest`] written by [`NpzWriter::with_manifest`].
+    ///
+    /// This reads all of the data in the archive.  It fails if there is no manifest, if an array is
+    /// missing from the archive or the manifest, or if the header or SHA-256 of an array differs
+    /// from what was recorded.  On success, the manifest is returned.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::WriterBuilder;
+    /// use npyz::npz::{NpzArchive, NpzWriter};
+    ///
+    /// let mut npz = NpzWriter::new(std::io::Cursor::new(vec![])).with_manifest();
+    /// let mut writer = npz.array::<i32>("x", Default::default())?.default_dtype().shape(&[3]).begin_nd()?;
+    /// writer.extend([1, 2, 3])?;
+    /// writer.finish()?;
+    /// let bytes = npz.finish()?.into_inner();
+    ///
+    /// let manifest = NpzArchive::new(std::io::Cursor::new(bytes))?.verify_manifest()?;
+    /// assert_eq!(manifest.get("x").unwrap().shape, vec![3]);
+    /// # Ok(()) }
+    /// ```
+    pub fn verify_manifest(&mut self) -> io::Result<Manifest> {
+        let manifest = self.manifest()?.ok_or_else(|| Error::InvalidData("the archive has no manifest".to_string()))?;
+        for name in self.array_names().map(String::from).collect::<Vec<_>>() {
+            if manifest.get(&name).is_none() {
+                return Err(Error::in_member(Error::InvalidData("array is not in the manifest".to_string()).into(), &name));
+            }
+        }
+        for entry in &manifest.entries {
+            let file_name = crate::npz::file_name_from_array_name(&entry.name);
+            let file = match entry_by_name(&mut self.zip, self.password.as_deref(), &file_name) {
+                Ok(file) => file,
+                Err(ZipError::FileNotFound) => {
+                    return Err(Error::in_member(Error::InvalidData("array in the manifest is missing".to_string()).into(), &entry.name));
+                },
+                Err(e) => return Err(zip_error(e)),
+            };
+            npz_manifest::verify_entry(entry, file).map_err(|e| Error::in_member(e, &entry.name))?;
         }
+        Ok(manifest)
+    }
+
+    /// Write a copy of the archive without the array of the given name.
+    ///
+    /// The other members are copied without decompressing them, like [`NpzWriter::copy_entry_from`].
+    /// To remove an array from a file, write the copy to a new file and rename it over the original.
+    /// Every entry with this name is removed.  Because the [manifest][`NpzWriter::with_manifest`] would
+    /// no longer match, it is not copied.
+    ///
+    /// Returns [`Error::InvalidInput`] if there is no array with the given name.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::npz::NpzArchive;
+    ///
+    /// let mut npz = NpzArchive::open("test-data/compressed.npz")?;
+    /// let bytes = npz.remove("floats", std::io::Cursor::new(vec![]))?.into_inner();
+    ///
+    /// let mut npz = NpzArchive::new(std::io::Cursor::new(bytes))?;
+    /// assert!(npz.by_name("floats")?.is_none());
+    /// assert!(npz.by_name("ints")?.is_some());
+    /// # Ok(()) }
+    /// ```
+    pub fn remove<W: io::Write + io::Seek>(&mut self, name: &str, writer: W) -> io::Result<W> {
+        self.rewrite(name, None::<NpyFile<io::Empty>>, writer)
+    }
+
+    /// Write a copy of the archive with the array of the given name replaced by the contents of an NPY file.
+    ///
+    /// The new array takes the place of the first entry with this name and keeps its compression method,
+    /// but it is not encrypted.  Other entries with the name are removed.  Otherwise this is like [`Self::remove`].
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::WriterBuilder;
+    /// use npyz::npz::NpzArchive;
+    ///
+    /// let mut new_ints = vec![];
+    /// let mut writer = npyz::WriteOptions::new().default_dtype().writer(&mut new_ints).shape(&[3]).begin_nd()?;
+    /// writer.extend([7_i64, 8, 9])?;
+    /// writer.finish()?;
+    ///
+    /// let mut npz = NpzArchive::open("test-data/compressed.npz")?;
+    /// let bytes = npz.replace("ints", npyz::NpyFile::new(&new_ints[..])?, std::io::Cursor::new(vec![]))?.into_inner();
+    ///
+    /// let mut npz = NpzArchive::new(std::io::Cursor::new(bytes))?;
+    /// assert_eq!(npz.by_name("ints")?.unwrap().into_vec::<i64>()?, vec![7, 8, 9]);
+    /// # Ok(()) }
+    /// ```
+    pub fn replace<W: io::Write + io::Seek, S: io::Read>(&mut self, name: &str, npy: NpyFile<S>, writer: W) -> io::Result<W> {
+        self.rewrite(name, Some(npy), writer)
+    }
+
+    fn rewrite<W: io::Write + io::Seek, S: io::Read>(&mut self, name: &str, mut replacement: Option<NpyFile<S>>, writer: W) -> io::Result<W> {
+        let file_name = crate::npz::file_name_from_array_name(name);
+        if !self.zip.file_names().any(|f| f == file_name) {
+            return Err(Error::InvalidInput(format!("no array named '{}' in the archive", name)).into());
+        }
+
+        let mut npz = NpzWriter::new(writer);
+        for index in 0..self.zip.len() {
+            let file = self.zip.by_index_raw(index).map_err(zip_error)?;
+            if file.name() == MANIFEST_FILE_NAME {
+                continue;
+            }
+            if file.name() != file_name {
+                let member = file.name().to_string();
+                npz.sink()?.zip.raw_copy_file(file).map_err(|e| Error::in_member(zip_error(e), &member))?;
+                continue;
+            }
+            if let Some(npy) = replacement.take() {
+                let options = zip::write::FileOptions::default().compression_method(file.compression());
+                drop(file);
+                npz.copy_array(name, options, npy)?;
+            }
+        }
+        npz.finish()
     }
 
     /// Exposes the underlying [`zip::ZipArchive`].
@@ -55,15 +543,89 @@ impl<R: io::Read + io::Seek> NpzArchive<R> {
     }
 }
 
-fn invalid_data<S: ToString>(s: S) -> io::Error {
-    io::Error::new(io::ErrorKind::InvalidData, s.to_string())
+// Advance past the header of a member whose header was already parsed.
+fn skip_header(file: impl io::Read, header: &NpyHeader) -> io::Result<()> {
+    let len = header.raw_bytes().expect("header was read from the archive").len() as u64;
+    match io::copy(&mut file.take(len), &mut io::sink())? {
+        n if n < len => Err(Error::Truncated.into()),
+        _ => Ok(()),
+    }
+}
+
+// The zip crate only keeps the last entry for each name, so look the others up ourselves.
+fn find_duplicates<R: io::Read + io::Seek>(zip: &mut zip::ZipArchive<R>) -> io::Result<HashMap<String, Vec<usize>>> {
+    let mut indices = HashMap::<String, Vec<usize>>::new();
+    if zip.file_names().count() == zip.len() {
+        return Ok(indices);
+    }
+    for index in 0..zip.len() {
+        let name = zip.by_index_raw(index).map_err(zip_error)?.name().to_string();
+        indices.entry(name).or_default().push(index);
+    }
+    indices.retain(|_, indices| indices.len() > 1);
+    Ok(indices)
+}
+
+// Look up an entry, decrypting it if there is a password.  (which the zip crate ignores for unencrypted entries)
+pub(crate) fn entry_by_name<'a, R: io::Read + io::Seek>(
+    zip: &'a mut zip::ZipArchive<R>,
+    password: Option<&[u8]>,
+    file_name: &str,
+) -> ZipResult<zip::read::ZipFile<'a>> {
+    match password {
+        Some(password) => zip.by_name_decrypt(file_name, password)?.map_err(invalid_password),
+        None => zip.by_name(file_name),
+    }
+}
+
+fn entry_by_index<'a, R: io::Read + io::Seek>(
+    zip: &'a mut zip::ZipArchive<R>,
+    password: Option<&[u8]>,
+    index: usize,
+) -> ZipResult<zip::read::ZipFile<'a>> {
+    match password {
+        Some(password) => zip.by_index_decrypt(index, password)?.map_err(invalid_password),
+        None => zip.by_index(index),
+    }
+}
+
+fn invalid_password(_: InvalidPassword) -> ZipError {
+    ZipError::UnsupportedArchive("invalid password")
+}
+
+fn zip_error(err: ZipError) -> io::Error {
+    crate::Error::from(err).into()
 }
 
 /// Interface for writing an NPZ file.
 ///
 /// *This is only available with the **`"npz"`** feature.*
 pub struct NpzWriter<W: io::Write + io::Seek> {
+    // `None` while an array is being written on a background thread
+    sink: Option<EntrySink<W>>,
+    manifest: Option<ManifestState>,
+    pipeline: Option<Pipeline<W>>,
+    file_options: zip::write::FileOptions,
+    // created by `new_append`, so that a manifest could not cover the existing arrays
+    appending: bool,
+}
+
+struct ManifestState {
+    entries: Vec<ManifestEntry>,
+    written: bool,
+}
+
+// The zip file, along with the manifest entry of the array being written to it.
+struct EntrySink<W: io::Write + io::Seek> {
     zip: zip::ZipWriter<W>,
+    pending: Option<PendingEntry>,
+}
+
+struct Pipeline<W: io::Write + io::Seek> {
+    depth: usize,
+    // `WriteBehind::spawn`, which needs bounds on W that `NpzWriter` does not have
+    spawn: fn(EntrySink<W>, usize) -> WriteBehind<EntrySink<W>>,
+    current: Option<WriteBehind<EntrySink<W>>>,
 }
 
 impl NpzWriter<io::BufWriter<File>> {
@@ -73,10 +635,168 @@ impl NpzWriter<io::BufWriter<File>> {
     }
 }
 
+impl NpzWriter<File> {
+    /// Open an existing `npz` archive on the filesystem to add more arrays to it.  See [`Self::new_append`].
+    ///
+    /// The file is not buffered, so to write arrays of many small elements, set a
+    /// [`buffer_size`][`WriterBuilder::buffer_size`] when beginning them.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::WriterBuilder;
+    /// use npyz::npz::{NpzArchive, NpzWriter};
+    ///
+    /// # let path = std::env::temp_dir().join(format!("npyz-append-doctest-{}.npz", std::process::id()));
+    /// std::fs::copy("test-data/uncompressed.npz", &path)?;
+    /// let mut npz = NpzWriter::append(&path)?;
+    /// let mut writer = npz.array::<i32>("extra", Default::default())?.default_dtype().shape(&[3]).begin_nd()?;
+    /// writer.extend([1, 2, 3])?;
+    /// writer.finish()?;
+    /// npz.finish()?;
+    ///
+    /// let mut npz = NpzArchive::open(&path)?;
+    /// assert_eq!(npz.by_name("extra")?.unwrap().into_vec::<i32>()?, vec![1, 2, 3]);
+    /// assert!(npz.by_name("ints")?.is_some());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn append(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new_append(File::options().read(true).write(true).open(path)?)
+    }
+}
+
+impl<'a> NpzWriter<VecSink<'a>> {
+    /// Create a new, empty `npz` archive that is appended to a `Vec<u8>`.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::WriterBuilder;
+    ///
+    /// let mut bytes = vec![];
+    /// let mut npz = npyz::npz::NpzWriter::new_vec(&mut bytes);
+    /// let mut writer = npz.array::<f32>("x", Default::default())?.default_dtype().shape(&[2]).begin_nd()?;
+    /// writer.extend([1.0, 2.0])?;
+    /// writer.finish()?;
+    /// npz.finish()?;
+    ///
+    /// let mut npz = npyz::npz::NpzArchive::new(std::io::Cursor::new(bytes))?;
+    /// assert_eq!(npz.by_name("x")?.unwrap().into_vec::<f32>()?, vec![1.0, 2.0]);
+    /// # Ok(()) }
+    /// ```
+    pub fn new_vec(vec: &'a mut Vec<u8>) -> Self {
+        Self::new(VecSink::new(vec))
+    }
+}
+
 impl<W: io::Write + io::Seek> NpzWriter<W> {
     /// Begin writing an NPZ file to an arbitrary writer.
     pub fn new(writer: W) -> Self {
-        NpzWriter { zip: zip::ZipWriter::new(writer) }
+        let sink = EntrySink { zip: zip::ZipWriter::new(writer), pending: None };
+        NpzWriter { sink: Some(sink), manifest: None, pipeline: None, file_options: Default::default(), appending: false }
+    }
+
+    /// Open an existing NPZ file to add more arrays to it.
+    ///
+    /// The new arrays are written over the central directory at the end of the file, and a new central
+    /// directory listing both the old and new arrays is written by [`Self::finish`].  The existing arrays
+    /// are neither read nor rewritten, so this takes the same time no matter how large the archive is.
+    ///
+    /// If the process stops before the archive is finished, the file is left without a central directory,
+    /// and can only be recovered with [`SalvagedNpz`][`crate::npz::SalvagedNpz`].  Adding an array with the name of an existing
+    /// one adds a second entry with that name (see [`NpzArchive::with_duplicate_names`]).
+    ///
+    /// A [manifest][`Self::with_manifest`] cannot be extended in place, so this returns [`Error::InvalidInput`]
+    /// if the archive has one, before writing anything.  To add arrays to such an archive, copy it without the
+    /// manifest first, e.g. with [`NpzArchive::remove`], which does not copy it.
+    pub fn new_append(mut readwriter: W) -> io::Result<Self> where W: io::Read {
+        let has_manifest = zip::ZipArchive::new(&mut readwriter).map_err(zip_error)?
+            .file_names().any(|name| name == MANIFEST_FILE_NAME);
+        if has_manifest {
+            let msg = "cannot append to an archive with a manifest, which would no longer match its arrays";
+            return Err(Error::InvalidInput(msg.to_string()).into());
+        }
+        let zip = zip::ZipWriter::new_append(readwriter).map_err(zip_error)?;
+        let sink = EntrySink { zip, pending: None };
+        Ok(NpzWriter { sink: Some(sink), manifest: None, pipeline: None, file_options: Default::default(), appending: true })
+    }
+
+    /// Record the shape, dtype and SHA-256 of each array in a [`Manifest`], stored in the archive
+    /// as [`MANIFEST_FILE_NAME`].
+    ///
+    /// This allows [`NpzArchive::verify_manifest`] to detect corruption or tampering beyond what the
+    /// CRCs of the zip format can.  The manifest is written by [`Self::finish`] (or on drop, ignoring
+    /// errors).  Only arrays written through [`Self::array`] are recorded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the writer was created by [`Self::new_append`], since the manifest would not cover the arrays
+    /// that were already in the archive.
+    pub fn with_manifest(mut self) -> Self {
+        assert!(!self.appending, "a manifest cannot be added to an archive that is appended to");
+        self.manifest = Some(ManifestState { entries: vec![], written: false });
+        self
+    }
+
+    /// Compress and hash the data of each array on a background thread, while the caller serializes it.
+    ///
+    /// Without this, the time to write a compressed archive is the time to serialize the data plus the
+    /// time to compress it; with it, it is closer to the larger of the two.  Up to `depth` chunks of 1 MiB
+    /// may wait to be compressed, after which writing blocks.  See [`WriteBehind`].
+    ///
+    /// Errors from compression or I/O are returned by a later write, by [`NpyWriter::finish`][`crate::NpyWriter::finish`]
+    /// for the array, or by the next call on the `NpzWriter`.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::WriterBuilder;
+    ///
+    /// # let path = std::env::temp_dir().join(format!("npyz-pipeline-doctest-{}.npz", std::process::id()));
+    /// let mut npz = npyz::npz::NpzWriter::create(&path)?.with_pipeline(4);
+    /// let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    /// let mut writer = npz.array::<f64>("x", options)?.default_dtype().shape(&[1000]).begin_nd()?;
+    /// writer.extend((0..1000).map(|x| x as f64))?;
+    /// writer.finish()?;
+    /// npz.finish()?;
+    ///
+    /// let mut npz = npyz::npz::NpzArchive::open(&path)?;
+    /// assert_eq!(npz.by_name("x")?.unwrap().into_vec::<f64>()?.len(), 1000);
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_pipeline(mut self, depth: usize) -> Self where W: Send + 'static {
+        self.pipeline = Some(Pipeline { depth, spawn: WriteBehind::spawn, current: None });
+        self
+    }
+
+    /// Set the compression of the arrays written by [`crate::sparse`], and of [`Self::file_options`].
+    ///
+    /// The level is passed to [`zip::write::FileOptions::compression_level`]; `None` uses the default level
+    /// of the method.  For the equivalent of `np.savez_compressed` or `scipy.sparse.save_npz(compressed=True)`,
+    /// use [`zip::CompressionMethod::Deflated`], and for `np.savez`, use [`zip::CompressionMethod::Stored`].
+    /// Without this, arrays are deflated with the default level.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::WriterBuilder;
+    ///
+    /// let mut bytes = vec![];
+    /// let mut npz = npyz::npz::NpzWriter::new_vec(&mut bytes).with_compression(zip::CompressionMethod::Deflated, Some(9));
+    /// let options = npz.file_options();
+    /// let mut writer = npz.array::<f32>("x", options)?.default_dtype().shape(&[1000]).begin_nd()?;
+    /// writer.extend(vec![0.0; 1000])?;
+    /// writer.finish()?;
+    /// npz.finish()?;
+    /// assert!(bytes.len() < 1000);
+    /// # Ok(()) }
+    /// ```
+    pub fn with_compression(mut self, method: zip::CompressionMethod, level: Option<i32>) -> Self {
+        self.file_options = self.file_options.compression_method(method).compression_level(level);
+        self
+    }
+
+    /// Get the options set by [`Self::with_compression`], for use with [`Self::array`].
+    pub fn file_options(&self) -> zip::write::FileOptions {
+        self.file_options
     }
 
     /// Begin an entry in the NPZ for the corresponding array.
@@ -84,13 +804,164 @@ impl<W: io::Write + io::Seek> NpzWriter<W> {
     /// The returned object implements the [`WriterBuilder`] trait.  You must import this trait
     /// and use its methods to continue configuring the object and begin writing.
     pub fn array<T: Serialize + ?Sized>(&mut self, name: &str, options: zip::write::FileOptions) -> io::Result<NpzWriterBuilder<'_, T, W>> {
-        self.zip.start_file(crate::npz::file_name_from_array_name(name), options)?;
-        Ok(write_options::WriteOptions::new().writer(&mut self.zip))
+        Ok(write_options::WriteOptions::new().writer(self.start_entry(name, options)?))
+    }
+
+    /// Add an array to the archive by copying an NPY file without decoding its elements.
+    ///
+    /// See [`NpyFile::copy_raw_to`].  This can be used to repack archives or to move arrays between
+    /// archives, e.g. with an `NpyFile` from [`NpzArchive::by_name`].
+    pub fn copy_array<R: io::Read>(&mut self, name: &str, options: zip::write::FileOptions, npy: NpyFile<R>) -> io::Result<()> {
+        npy.copy_raw_to(self.start_entry(name, options)?)
+    }
+
+    /// Copy an array from another archive without decompressing it.
+    ///
+    /// The compressed bytes of the zip entry are transferred as they are, keeping the compression method
+    /// and CRC of the original, so this is much faster than [`Self::copy_array`] for merging or repacking
+    /// compressed archives.  The data is not checked against the CRC; [`NpzArchive::by_name`] will do
+    /// that when it is read.  The array is not recorded in the [manifest][`Self::with_manifest`].
+    ///
+    /// Returns [`Error::InvalidInput`] if `archive` has no array with the given name.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::npz::{NpzArchive, NpzWriter};
+    ///
+    /// let mut source = NpzArchive::open("test-data/compressed.npz")?;
+    /// let mut npz = NpzWriter::new(std::io::Cursor::new(vec![]));
+    /// npz.copy_entry_from(&mut source, "ints")?;
+    /// let bytes = npz.finish()?.into_inner();
+    ///
+    /// let mut copy = NpzArchive::new(std::io::Cursor::new(bytes))?;
+    /// let ints = copy.by_name("ints")?.unwrap().into_vec::<i64>()?;
+    /// assert_eq!(ints, source.by_name("ints")?.unwrap().into_vec::<i64>()?);
+    /// # Ok(()) }
+    /// ```
+    pub fn copy_entry_from<R: io::Read + io::Seek>(&mut self, archive: &mut NpzArchive<R>, name: &str) -> io::Result<()> {
+        self.finish_manifest_entry()?;
+        let file_name = crate::npz::file_name_from_array_name(name);
+        let file = match entry_by_name(&mut archive.zip, archive.password.as_deref(), &file_name) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Err(Error::InvalidInput(format!("no array named '{}' in the archive", name)).into()),
+            Err(e) => return Err(crate::Error::in_member(zip_error(e), name)),
+        };
+        let sink = self.sink()?;
+        sink.zip.raw_copy_file_rename(file, file_name).map_err(|e| crate::Error::in_member(zip_error(e), name))
+    }
+
+    /// Write the manifest (if enabled) and the central directory of the zip file, returning the underlying writer.
+    ///
+    /// If this is not called, the archive is finished when the `NpzWriter` is dropped, ignoring any errors.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_manifest()?;
+        self.sink()?.zip.finish().map_err(zip_error)
     }
 
     /// Exposes the underlying [`zip::ZipWriter`].
+    ///
+    /// With [`Self::with_pipeline`], this first waits for the background thread to finish the current array,
+    /// ignoring any errors.
     pub fn zip_writer(&mut self) -> &mut zip::ZipWriter<W> {
-        &mut self.zip
+        let _ = self.sink();
+        &mut self.sink.as_mut().expect("the zip writer is given back even after an error").zip
+    }
+
+    fn start_entry(&mut self, name: &str, options: zip::write::FileOptions) -> io::Result<NpzEntryWriter<'_, W>> {
+        self.finish_manifest_entry()?;
+        let tracked = self.manifest.is_some();
+        let sink = self.sink()?;
+        sink.zip.start_file(crate::npz::file_name_from_array_name(name), options)?;
+        sink.pending = tracked.then(|| PendingEntry::new(name));
+
+        let target = match &mut self.pipeline {
+            Some(pipeline) => {
+                let sink = self.sink.take().expect("sink was just used");
+                EntryTarget::Pipelined(pipeline.current.insert((pipeline.spawn)(sink, pipeline.depth)))
+            },
+            None => EntryTarget::Direct(self.sink.as_mut().expect("sink was just used")),
+        };
+        Ok(NpzEntryWriter { target })
+    }
+
+    // Get the zip writer back from the background thread, if it is there.
+    fn sink(&mut self) -> io::Result<&mut EntrySink<W>> {
+        let mut result = Ok(());
+        if let Some(mut current) = self.pipeline.as_mut().and_then(|pipeline| pipeline.current.take()) {
+            let (sink, finished) = current.finish_();
+            self.sink = sink;
+            result = finished;
+        }
+        result.map(|()| self.sink.as_mut().expect("the zip writer is given back even after an error"))
+    }
+
+    fn finish_manifest_entry(&mut self) -> io::Result<()> {
+        let pending = self.sink()?.pending.take();
+        if let (Some(manifest), Some(pending)) = (&mut self.manifest, pending) {
+            manifest.entries.push(pending.finish()?);
+        }
+        Ok(())
+    }
+
+    fn write_manifest(&mut self) -> io::Result<()> {
+        self.finish_manifest_entry()?;
+        let json = match &mut self.manifest {
+            Some(manifest) if !manifest.written => {
+                manifest.written = true;
+                Manifest { entries: std::mem::take(&mut manifest.entries) }.to_json()
+            },
+            _ => return Ok(()),
+        };
+        let sink = self.sink()?;
+        sink.zip.start_file(MANIFEST_FILE_NAME, Default::default())?;
+        io::Write::write_all(&mut sink.zip, json.as_bytes())
+    }
+}
+
+impl<W: io::Write + io::Seek> Drop for NpzWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.write_manifest();
+    }
+}
+
+impl<W: io::Write + io::Seek> io::Write for EntrySink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.zip.write(buf)?;
+        if let Some(pending) = &mut self.pending {
+            pending.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.zip.flush()
+    }
+}
+
+/// The writer for an array in an NPZ file, which is used by [`NpzWriterBuilder`].
+pub struct NpzEntryWriter<'w, W: io::Write + io::Seek> {
+    target: EntryTarget<'w, W>,
+}
+
+enum EntryTarget<'w, W: io::Write + io::Seek> {
+    Direct(&'w mut EntrySink<W>),
+    // see `NpzWriter::with_pipeline`
+    Pipelined(&'w mut WriteBehind<EntrySink<W>>),
+}
+
+impl<W: io::Write + io::Seek> io::Write for NpzEntryWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.target {
+            EntryTarget::Direct(sink) => sink.write(buf),
+            EntryTarget::Pipelined(sink) => sink.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.target {
+            EntryTarget::Direct(sink) => sink.flush(),
+            EntryTarget::Pipelined(sink) => sink.flush(),
+        }
     }
 }
 
@@ -98,4 +969,4 @@ impl<W: io::Write + io::Seek> NpzWriter<W> {
 ///
 /// Please use the methods of [`WriterBuilder`] to configure this object and begin writing.
 /// (Note that the writer does not impl `io::Seek`, and therefore you cannot use [`WriterBuilder::begin_1d`]).
-pub type NpzWriterBuilder<'w, T, W> = write_options::WithWriter<&'w mut zip::ZipWriter<W>, write_options::WriteOptions<T>>;
+pub type NpzWriterBuilder<'w, T, W> = write_options::WithWriter<NpzEntryWriter<'w, W>, write_options::WriteOptions<T>>;
@@ -0,0 +1,327 @@
+//! Arrays with named dimensions, units and coordinates, stored in an NPZ archive.
+
+use std::fmt::Write as _;
+use std::io;
+
+use zip::result::ZipError;
+
+use crate::error::Error;
+use crate::npz_feature::{NpzArchive, NpzWriter};
+use crate::npz_manifest::{get, json_string, parse_json, Json};
+use crate::read::Order;
+use crate::serialize::{AutoSerialize, Deserialize};
+use crate::write::WriterBuilder;
+
+/// Name of the array that holds the data of a [`LabeledArray`].
+pub const LABELED_DATA_NAME: &str = "data";
+
+/// Name of the member of the zip file that holds the dimensions and units of a [`LabeledArray`].
+///
+/// This does not end in `.npy`, so numpy and [`NpzArchive::array_names`] ignore it.
+pub const LABELS_FILE_NAME: &str = "dims.json";
+
+const FORMAT: &str = "npyz-labels";
+const VERSION: u64 = 1;
+
+/// Get the name of the array that holds the coordinates along a dimension of a [`LabeledArray`].
+pub fn coord_array_name(dim: &str) -> String {
+    format!("coord/{}", dim)
+}
+
+/// A named dimension of a [`LabeledArray`].
+///
+/// *This is only available with the **`"npz"`** feature.*
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Dim {
+    /// The name of the dimension, e.g. `"time"`.
+    pub name: String,
+    /// The units of the coordinates along the dimension, e.g. `"s"`.
+    pub units: Option<String>,
+}
+
+impl Dim {
+    /// Construct a dimension without units.
+    pub fn new(name: &str) -> Self {
+        Dim { name: name.to_string(), units: None }
+    }
+
+    /// Set the units of the coordinates.
+    pub fn with_units(mut self, units: &str) -> Self {
+        self.units = Some(units.to_string());
+        self
+    }
+}
+
+/// An array with named dimensions and units, like an `xarray.DataArray`, stored in an NPZ archive by a
+/// simple convention.
+///
+/// The archive contains:
+///
+/// * an array named [`"data"`][`LABELED_DATA_NAME`], in C order;
+/// * a JSON file [`"dims.json"`][`LABELS_FILE_NAME`] with the name and units of each dimension and the units of
+///   the data, e.g.
+///   `{"format": "npyz-labels", "version": 1, "units": "K", "dims": [{"name": "time", "units": "s"}, {"name": "station", "units": null}]}`;
+/// * optionally, for each dimension, a 1-dimensional array of coordinates named [`"coord/<name>"`][`coord_array_name`]
+///   with one element per index along it.  These are written and read separately with [`Self::write_coord`] and
+///   [`Self::read_coord`], as they can each have a different type.
+///
+/// From Python, the arrays can be read with `np.load` as usual, and the labels with
+/// `json.loads(np.load(path)['dims.json'])` or by opening the zip file.
+///
+/// *This is only available with the **`"npz"`** feature.*
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::npz::{Dim, LabeledArray, NpzArchive, NpzWriter};
+///
+/// let temperature = LabeledArray {
+///     data: vec![280.0, 281.5, 279.0, 283.0, 284.5, 282.0],
+///     shape: vec![2, 3],
+///     dims: vec![Dim::new("time").with_units("s"), Dim::new("station")],
+///     units: Some("K".to_string()),
+/// };
+/// let mut npz = NpzWriter::new(std::io::Cursor::new(vec![]));
+/// temperature.write_npz(&mut npz)?;
+/// temperature.write_coord(&mut npz, "time", &[0.0, 60.0])?;
+/// let bytes = npz.finish()?.into_inner();
+///
+/// let mut npz = NpzArchive::new(std::io::Cursor::new(bytes))?;
+/// let read = LabeledArray::<f64>::from_npz(&mut npz)?;
+/// assert_eq!(read, temperature);
+/// assert_eq!(read.axis("station"), Some(1));
+/// assert_eq!(read.read_coord::<f64, _>(&mut npz, "time")?, Some(vec![0.0, 60.0]));
+/// assert_eq!(read.read_coord::<f64, _>(&mut npz, "station")?, None);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledArray<T> {
+    /// The elements, in C order.
+    pub data: Vec<T>,
+    /// The shape of the array.
+    pub shape: Vec<u64>,
+    /// The dimensions, one per axis.
+    pub dims: Vec<Dim>,
+    /// The units of the data.
+    pub units: Option<String>,
+}
+
+impl<T> LabeledArray<T> {
+    /// Get the index of the axis with the given name.
+    pub fn axis(&self, name: &str) -> Option<usize> {
+        self.dims.iter().position(|dim| dim.name == name)
+    }
+
+    fn axis_len(&self, name: &str) -> io::Result<u64> {
+        match self.axis(name) {
+            Some(axis) => Ok(self.shape[axis]),
+            None => Err(Error::InvalidInput(format!("no dimension named '{}'", name)).into()),
+        }
+    }
+
+    fn labels_to_json(&self) -> String {
+        let mut out = String::new();
+        write!(out, "{{\"format\": \"{}\", \"version\": {}, \"units\": ", FORMAT, VERSION).unwrap();
+        json_optional_string(&mut out, &self.units);
+        out.push_str(", \"dims\": [");
+        for (index, dim) in self.dims.iter().enumerate() {
+            if index > 0 {
+                out.push_str(", ");
+            }
+            out.push_str("{\"name\": ");
+            json_string(&mut out, &dim.name);
+            out.push_str(", \"units\": ");
+            json_optional_string(&mut out, &dim.units);
+            out.push('}');
+        }
+        out.push_str("]}\n");
+        out
+    }
+
+    /// Write the coordinates along a dimension, as the array [`coord/<dim>`][`coord_array_name`].
+    ///
+    /// Returns [`Error::InvalidInput`] if there is no dimension named `dim`, or if `values` does not have
+    /// one element per index along it.
+    pub fn write_coord<C: AutoSerialize, W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>, dim: &str, values: &[C]) -> io::Result<()> {
+        let len = self.axis_len(dim)?;
+        if values.len() as u64 != len {
+            return Err(Error::InvalidInput(format!(
+                "dimension '{}' has length {}, but there are {} coordinates", dim, len, values.len(),
+            )).into());
+        }
+        npz.array::<C>(&coord_array_name(dim), Default::default())?
+            .default_dtype()
+            .shape(&[len])
+            .begin_nd()?
+            .extend_from_slice(values)
+    }
+
+    /// Read the coordinates along a dimension, if the archive has them.
+    ///
+    /// Returns [`Error::InvalidInput`] if there is no dimension named `dim`, and [`Error::InvalidData`] if
+    /// the coordinates are not a 1-dimensional array with one element per index along it.
+    pub fn read_coord<C: Deserialize, R: io::Read + io::Seek>(&self, npz: &mut NpzArchive<R>, dim: &str) -> io::Result<Option<Vec<C>>> {
+        let len = self.axis_len(dim)?;
+        let name = coord_array_name(dim);
+        let npy = match npz.by_name(&name)? {
+            Some(npy) => npy,
+            None => return Ok(None),
+        };
+        if npy.shape() != [len] {
+            let err = Error::InvalidData(format!("expected shape [{}] for the coordinates, got {:?}", len, npy.shape()));
+            return Err(Error::in_member(err.into(), &name));
+        }
+        npy.into_vec().map(Some)
+    }
+}
+
+impl<T: AutoSerialize> LabeledArray<T> {
+    /// Write the data and labels to an archive.
+    ///
+    /// Returns [`Error::InvalidInput`] if there is not one dimension per axis, if the number of elements does
+    /// not match the shape, or if the names of the dimensions are empty or not unique.
+    pub fn write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
+        let invalid = |msg: String| -> io::Error { Error::InvalidInput(msg).into() };
+        if self.dims.len() != self.shape.len() {
+            return Err(invalid(format!("{} dimensions were named for shape {:?}", self.dims.len(), self.shape)));
+        }
+        let expected_len = self.shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim));
+        if expected_len != Some(self.data.len() as u64) {
+            return Err(invalid(format!("{} elements do not match shape {:?}", self.data.len(), self.shape)));
+        }
+        for (index, dim) in self.dims.iter().enumerate() {
+            if dim.name.is_empty() || self.dims[..index].iter().any(|other| other.name == dim.name) {
+                return Err(invalid(format!("dimension names must be nonempty and unique, got {:?}", dim.name)));
+            }
+        }
+
+        npz.array::<T>(LABELED_DATA_NAME, Default::default())?
+            .default_dtype()
+            .shape(&self.shape)
+            .begin_nd()?
+            .extend_from_slice(&self.data)?;
+        let zip = npz.zip_writer();
+        zip.start_file(LABELS_FILE_NAME, Default::default())?;
+        io::Write::write_all(zip, self.labels_to_json().as_bytes())
+    }
+}
+
+impl<T: Deserialize> LabeledArray<T> {
+    /// Read the data and labels from an archive.
+    ///
+    /// Returns [`Error::InvalidData`] if either is missing, if the labels do not have one dimension per axis
+    /// of the data, or if the data is in Fortran order.
+    pub fn from_npz<R: io::Read + io::Seek>(npz: &mut NpzArchive<R>) -> io::Result<Self> {
+        let mut text = String::new();
+        match npz.zip_archive().by_name(LABELS_FILE_NAME) {
+            Ok(mut file) => io::Read::read_to_string(&mut file, &mut text)?,
+            Err(ZipError::FileNotFound) => return Err(Error::InvalidData(format!("missing '{}'", LABELS_FILE_NAME)).into()),
+            Err(e) => return Err(Error::from(e).into()),
+        };
+        let (dims, units) = labels_from_json(&text).map_err(|e| Error::in_member(e, LABELS_FILE_NAME))?;
+
+        let npy = npz.by_name(LABELED_DATA_NAME)?
+            .ok_or_else(|| Error::InvalidData(format!("missing array '{}'", LABELED_DATA_NAME)))?;
+        let shape = npy.shape().to_vec();
+        let bad_data = |msg: String| Error::in_member(Error::InvalidData(msg).into(), LABELED_DATA_NAME);
+        if npy.order() != Order::C {
+            return Err(bad_data("fortran order is not currently supported for labeled arrays".to_string()));
+        }
+        if shape.len() != dims.len() {
+            return Err(bad_data(format!("{} dimensions are named for shape {:?}", dims.len(), shape)));
+        }
+        let data = npy.into_vec()?;
+        Ok(LabeledArray { data, shape, dims, units })
+    }
+}
+
+fn json_optional_string(out: &mut String, s: &Option<String>) {
+    match s {
+        Some(s) => json_string(out, s),
+        None => out.push_str("null"),
+    }
+}
+
+fn labels_from_json(text: &str) -> io::Result<(Vec<Dim>, Option<String>)> {
+    let bad = |msg: String| -> io::Error { Error::InvalidData(format!("invalid labels: {}", msg)).into() };
+    let optional_string = |value: Option<&Json>, what: &str| match value {
+        None | Some(Json::Null) => Ok(None),
+        Some(Json::Str(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(bad(format!("expected a string or null for the units of {}", what))),
+    };
+
+    let value = parse_json(text).map_err(bad)?;
+    let root = value.as_object().ok_or_else(|| bad("expected an object".into()))?;
+    if get(root, "format").and_then(Json::as_str) != Some(FORMAT) {
+        return Err(bad(format!("expected \"format\": \"{}\"", FORMAT)));
+    }
+    match get(root, "version").and_then(Json::as_u64) {
+        Some(VERSION) => {},
+        Some(version) => return Err(bad(format!("unsupported version {}", version))),
+        None => return Err(bad("missing \"version\"".into())),
+    }
+    let units = optional_string(get(root, "units"), "the data")?;
+    let dims = get(root, "dims").and_then(Json::as_array).ok_or_else(|| bad("missing \"dims\"".into()))?;
+    let dims = dims.iter().map(|dim| {
+        let dim = dim.as_object().ok_or_else(|| bad("expected an object for each dimension".into()))?;
+        let name = get(dim, "name").and_then(Json::as_str).ok_or_else(|| bad("missing \"name\" for a dimension".into()))?;
+        let units = optional_string(get(dim, "units"), name)?;
+        Ok(Dim { name: name.to_string(), units })
+    }).collect::<io::Result<_>>()?;
+    Ok((dims, units))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> LabeledArray<i32> {
+        LabeledArray {
+            data: (0..24).collect(),
+            shape: vec![2, 3, 4],
+            dims: vec![Dim::new("a \"quoted\" name").with_units("m/s"), Dim::new("y"), Dim::new("z").with_units("")],
+            units: None,
+        }
+    }
+
+    #[test]
+    fn labels_roundtrip() {
+        let array = example();
+        let (dims, units) = labels_from_json(&array.labels_to_json()).unwrap();
+        assert_eq!((dims, units), (array.dims, array.units));
+
+        assert!(labels_from_json("{\"format\": \"npyz-labels\", \"version\": 2, \"dims\": []}").is_err());
+        assert!(labels_from_json("{\"format\": \"npyz-labels\", \"version\": 1, \"dims\": [{\"units\": \"m\"}]}").is_err());
+        assert!(labels_from_json("{\"format\": \"npyz-labels\", \"version\": 1, \"units\": 3, \"dims\": []}").is_err());
+    }
+
+    #[test]
+    fn invalid_arrays() {
+        let mut npz = NpzWriter::new(io::Cursor::new(vec![]));
+        let mut array = example();
+        array.dims.pop();
+        assert_eq!(array.write_npz(&mut npz).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        let mut array = example();
+        array.data.pop();
+        assert_eq!(array.write_npz(&mut npz).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        let mut array = example();
+        array.dims[2].name = "y".to_string();
+        assert_eq!(array.write_npz(&mut npz).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        let array = example();
+        assert_eq!(array.write_coord(&mut npz, "y", &[1, 2]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(array.write_coord(&mut npz, "w", &[1, 2, 3]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn missing_labels() {
+        let mut npz = NpzWriter::new(io::Cursor::new(vec![]));
+        npz.array::<i32>(LABELED_DATA_NAME, Default::default()).unwrap()
+            .default_dtype().shape(&[1]).begin_nd().unwrap()
+            .push(&1).unwrap();
+        let bytes = npz.finish().unwrap().into_inner();
+        let err = LabeledArray::<i32>::from_npz(&mut NpzArchive::new(io::Cursor::new(bytes)).unwrap()).unwrap_err();
+        assert!(err.to_string().contains(LABELS_FILE_NAME), "{}", err);
+    }
+}
@@ -0,0 +1,442 @@
+//! The integrity manifest of an NPZ archive, written by [`NpzWriter::with_manifest`][`crate::npz::NpzWriter::with_manifest`].
+
+use std::fmt::Write as _;
+use std::io;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::{NpyHeader, Order};
+
+/// Name of the member of the zip file that holds the manifest.
+///
+/// This does not end in `.npy`, so numpy and [`NpzArchive::array_names`][`crate::npz::NpzArchive::array_names`] ignore it.
+pub const MANIFEST_FILE_NAME: &str = "__manifest__.json";
+
+const FORMAT: &str = "npyz-manifest";
+const VERSION: u64 = 1;
+
+/// A record of the arrays in an NPZ archive and the SHA-256 of each, for checking integrity.
+///
+/// See [`NpzWriter::with_manifest`][`crate::npz::NpzWriter::with_manifest`] and
+/// [`NpzArchive::verify_manifest`][`crate::npz::NpzArchive::verify_manifest`].
+///
+/// *This is only available with the **`"npz"`** feature.*
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Manifest {
+    /// The arrays, in the order they were written.
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// An array recorded in a [`Manifest`].
+///
+/// *This is only available with the **`"npz"`** feature.*
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// The name of the array. (without `.npy`)
+    pub name: String,
+    /// The dtype in the header.
+    pub dtype: DType,
+    /// The shape in the header.
+    pub shape: Vec<u64>,
+    /// The order in the header.
+    pub order: Order,
+    /// The SHA-256 of the whole NPY file stored in the archive, including the header.
+    pub sha256: [u8; 32],
+}
+
+impl Manifest {
+    /// Get the entry for an array.
+    pub fn get(&self, name: &str) -> Option<&ManifestEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Serialize as the JSON stored in the archive.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        write!(out, "{{\n  \"format\": \"{}\",\n  \"version\": {},\n  \"arrays\": [", FORMAT, VERSION).unwrap();
+        for (index, entry) in self.entries.iter().enumerate() {
+            out.push_str(if index == 0 { "\n" } else { ",\n" });
+            out.push_str("    {\"name\": ");
+            json_string(&mut out, &entry.name);
+            out.push_str(", \"descr\": ");
+            json_string(&mut out, &entry.dtype.descr());
+            let shape = entry.shape.iter().map(|dim| dim.to_string()).collect::<Vec<_>>();
+            write!(out, ", \"shape\": [{}]", shape.join(", ")).unwrap();
+            let order = match entry.order {
+                Order::C => "C",
+                Order::Fortran => "F",
+            };
+            write!(out, ", \"order\": \"{}\", \"sha256\": \"{}\"}}", order, hex(&entry.sha256)).unwrap();
+        }
+        out.push_str("\n  ]\n}\n");
+        out
+    }
+
+    /// Parse the JSON stored in the archive.
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        let bad = |msg: String| -> io::Error { Error::InvalidData(format!("invalid manifest: {}", msg)).into() };
+
+        let value = parse_json(text).map_err(bad)?;
+        let root = value.as_object().ok_or_else(|| bad("expected an object".into()))?;
+        if get(root, "format").and_then(Json::as_str) != Some(FORMAT) {
+            return Err(bad(format!("expected \"format\": \"{}\"", FORMAT)));
+        }
+        match get(root, "version").and_then(Json::as_u64) {
+            Some(VERSION) => {},
+            Some(version) => return Err(bad(format!("unsupported version {}", version))),
+            None => return Err(bad("missing \"version\"".into())),
+        }
+
+        let arrays = get(root, "arrays").and_then(Json::as_array).ok_or_else(|| bad("missing \"arrays\"".into()))?;
+        let entries = arrays.iter().map(|array| {
+            let array = array.as_object().ok_or_else(|| bad("expected an object for each array".into()))?;
+            let field = |key: &str| get(array, key).ok_or_else(|| bad(format!("missing {:?} for an array", key)));
+            let string = |key: &str| field(key)?.as_str().ok_or_else(|| bad(format!("expected a string for {:?}", key)));
+
+            let name = string("name")?.to_string();
+            let dtype = DType::parse(string("descr")?).map_err(|e| bad(format!("descr of {:?}: {}", name, e)))?;
+            let shape = field("shape")?.as_array()
+                .and_then(|dims| dims.iter().map(Json::as_u64).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| bad(format!("invalid shape for {:?}", name)))?;
+            let order = match string("order")? {
+                "C" => Order::C,
+                "F" => Order::Fortran,
+                other => return Err(bad(format!("invalid order {:?}", other))),
+            };
+            let sha256 = parse_hex(string("sha256")?).ok_or_else(|| bad(format!("invalid sha256 for {:?}", name)))?;
+            Ok(ManifestEntry { name, dtype, shape, order, sha256 })
+        }).collect::<io::Result<_>>()?;
+        Ok(Manifest { entries })
+    }
+}
+
+/// Hashes an NPY file as it is written, keeping the header so that it can be recorded.
+pub(crate) struct PendingEntry {
+    name: String,
+    hasher: Sha256,
+    header_bytes: Vec<u8>,
+}
+
+impl PendingEntry {
+    pub(crate) fn new(name: &str) -> Self {
+        PendingEntry { name: name.to_string(), hasher: Sha256::new(), header_bytes: vec![] }
+    }
+
+    pub(crate) fn update(&mut self, mut bytes: &[u8]) {
+        self.hasher.update(bytes);
+        // the first 12 bytes are enough to know the length of the rest of the header
+        loop {
+            let wanted = header_len(&self.header_bytes).unwrap_or(12);
+            let take = wanted.saturating_sub(self.header_bytes.len()).min(bytes.len());
+            if take == 0 {
+                break;
+            }
+            self.header_bytes.extend(&bytes[..take]);
+            bytes = &bytes[take..];
+        }
+    }
+
+    pub(crate) fn finish(self) -> io::Result<ManifestEntry> {
+        let header = NpyHeader::from_reader(&self.header_bytes[..]).map_err(|e| Error::in_member(e, &self.name))?;
+        Ok(ManifestEntry {
+            name: self.name,
+            dtype: header.dtype(),
+            shape: header.shape().to_vec(),
+            order: header.order(),
+            sha256: self.hasher.finalize().into(),
+        })
+    }
+}
+
+// Total length of the header that begins with these bytes, once enough of it is known.
+fn header_len(prefix: &[u8]) -> Option<usize> {
+    match prefix.get(6)? {
+        1 => Some(10 + u16::from_le_bytes(prefix.get(8..10)?.try_into().unwrap()) as usize),
+        _ => Some(12 + u32::from_le_bytes(prefix.get(8..12)?.try_into().unwrap()) as usize),
+    }
+}
+
+/// Check an array in an archive against its manifest entry.
+pub(crate) fn verify_entry(entry: &ManifestEntry, mut reader: impl io::Read) -> io::Result<()> {
+    let mut hashing = HashingReader { inner: &mut reader, hasher: Sha256::new() };
+    let header = NpyHeader::from_reader(&mut hashing)?;
+    let mismatch = |what: &str| -> io::Error { Error::InvalidData(format!("{} does not match the manifest", what)).into() };
+    if header.dtype() != entry.dtype {
+        return Err(mismatch("dtype"));
+    }
+    if header.shape() != &entry.shape[..] {
+        return Err(mismatch("shape"));
+    }
+    if header.order() != entry.order {
+        return Err(mismatch("order"));
+    }
+    io::copy(&mut hashing, &mut io::sink())?;
+    if <[u8; 32]>::from(hashing.hasher.finalize()) != entry.sha256 {
+        return Err(mismatch("SHA-256"));
+    }
+    Ok(())
+}
+
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: io::Read> io::Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_hex(s: &str) -> Option<[u8; 32]> {
+    if s.len() != 64 || !s.is_ascii() {
+        return None;
+    }
+    let mut out = [0; 32];
+    for (byte, pair) in out.iter_mut().zip(s.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(out)
+}
+
+pub(crate) fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// A JSON parser that supports just enough for manifests and labels.  (no floats, no negative numbers)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Uint(u64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        match self { Json::Uint(x) => Some(*x), _ => None }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self { Json::Str(s) => Some(s), _ => None }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self { Json::Array(items) => Some(items), _ => None }
+    }
+
+    pub(crate) fn as_object(&self) -> Option<&[(String, Json)]> {
+        match self { Json::Object(fields) => Some(fields), _ => None }
+    }
+}
+
+pub(crate) fn get<'a>(object: &'a [(String, Json)], key: &str) -> Option<&'a Json> {
+    object.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+pub(crate) fn parse_json(text: &str) -> Result<Json, String> {
+    JsonParser { text: text.as_bytes(), pos: 0 }.parse_document()
+}
+
+struct JsonParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn parse_document(&mut self) -> Result<Json, String> {
+        let value = self.parse_value(0)?;
+        self.skip_whitespace();
+        match self.pos == self.text.len() {
+            true => Ok(value),
+            false => Err(format!("unexpected trailing data at byte {}", self.pos)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.text.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.text.get(self.pos) {
+            Some(&b) if b == byte => {
+                self.pos += 1;
+                Ok(())
+            },
+            _ => Err(format!("expected '{}' at byte {}", byte as char, self.pos)),
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Json, String> {
+        if depth > 16 {
+            return Err("nested too deeply".into());
+        }
+        self.skip_whitespace();
+        let rest = &self.text[self.pos..];
+        for (word, value) in [("null", Json::Null), ("true", Json::Bool(true)), ("false", Json::Bool(false))] {
+            if rest.starts_with(word.as_bytes()) {
+                self.pos += word.len();
+                return Ok(value);
+            }
+        }
+        match rest.first() {
+            Some(b'"') => self.parse_string().map(Json::Str),
+            Some(b'0'..=b'9') => {
+                let len = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+                self.pos += len;
+                std::str::from_utf8(&rest[..len]).unwrap().parse().map(Json::Uint).map_err(|e| e.to_string())
+            },
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = vec![];
+                self.skip_whitespace();
+                if self.text.get(self.pos) == Some(&b']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.parse_value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.text.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b']')?;
+                Ok(Json::Array(items))
+            },
+            Some(b'{') => {
+                self.pos += 1;
+                let mut fields = vec![];
+                self.skip_whitespace();
+                if self.text.get(self.pos) == Some(&b'}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.parse_string()?;
+                    self.expect(b':')?;
+                    fields.push((key, self.parse_value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.text.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b'}')?;
+                Ok(Json::Object(fields))
+            },
+            _ => Err(format!("unexpected character at byte {}", self.pos)),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut out = vec![];
+        loop {
+            let byte = *self.text.get(self.pos).ok_or("unterminated string")?;
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self.text.get(self.pos).ok_or("unterminated string")?;
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let code = self.text.get(self.pos..self.pos + 4)
+                                .and_then(|hex| u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+                                .ok_or("invalid \\u escape")?;
+                            self.pos += 4;
+                            // surrogate pairs are not needed for anything we write
+                            char::from_u32(code).ok_or("unsupported \\u escape")?
+                        },
+                        _ => return Err(format!("invalid escape at byte {}", self.pos)),
+                    };
+                    out.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+                },
+                _ => out.push(byte),
+            }
+        }
+        String::from_utf8(out).map_err(|_| "string is not UTF-8".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_roundtrip() {
+        let manifest = Manifest {
+            entries: vec![
+                ManifestEntry {
+                    name: "a \"quoted\"\nname".into(),
+                    dtype: DType::parse("[('x', '<i4'), ('y', '>f8', (2,))]").unwrap(),
+                    shape: vec![3, 0],
+                    order: Order::Fortran,
+                    sha256: [0xab; 32],
+                },
+                ManifestEntry {
+                    name: "b".into(),
+                    dtype: DType::parse("'|u1'").unwrap(),
+                    shape: vec![],
+                    order: Order::C,
+                    sha256: [7; 32],
+                },
+            ],
+        };
+        assert_eq!(Manifest::from_json(&manifest.to_json()).unwrap(), manifest);
+        assert_eq!(Manifest::from_json(&Manifest::default().to_json()).unwrap(), Manifest::default());
+    }
+
+    #[test]
+    fn json_errors() {
+        assert!(Manifest::from_json("").is_err());
+        assert!(Manifest::from_json("{\"format\": \"npyz-manifest\", \"version\": 2, \"arrays\": []}").is_err());
+        assert!(Manifest::from_json("{\"format\": \"npyz-manifest\", \"version\": 1, \"arrays\": [{}]}").is_err());
+        assert!(Manifest::from_json("{\"format\": \"npyz-manifest\", \"version\": 1, \"arrays\": []} x").is_err());
+    }
+
+    #[test]
+    fn pending_entry_in_pieces() {
+        let bytes = std::fs::read("test-data/c-order.npy").unwrap();
+        let mut entry = PendingEntry::new("x");
+        for chunk in bytes.chunks(5) {
+            entry.update(chunk);
+        }
+        let entry = entry.finish().unwrap();
+        assert_eq!(entry.shape, vec![2, 3, 4]);
+        assert_eq!(entry.sha256, <[u8; 32]>::from(Sha256::digest(&bytes)));
+    }
+}
@@ -0,0 +1,238 @@
+//! Reading all arrays of an NPZ file on multiple threads.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use zip::ZipArchive;
+
+use crate::read::{NpyFile, NpyHeader, ReadOptions};
+use crate::serialize::Deserialize;
+
+/// An array read by [`NpzArchive::load_all_parallel`][`crate::npz::NpzArchive::load_all_parallel`].
+///
+/// *This is only available with the **`"npz"`** feature.*
+#[derive(Clone)]
+pub struct LoadedArray<T> {
+    /// The header of the array, with its dtype, shape and order.
+    pub header: NpyHeader,
+    /// All elements, in the order they are stored.
+    pub data: Vec<T>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for LoadedArray<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoadedArray")
+            .field("dtype", &self.header.dtype())
+            .field("shape", &self.header.shape())
+            .field("order", &self.header.order())
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+pub(crate) fn load_all<R, T>(zip: ZipArchive<R>, options: &ReadOptions, password: Option<&[u8]>) -> io::Result<HashMap<String, LoadedArray<T>>>
+where
+    R: Read + Seek + Send,
+    T: Deserialize + Send,
+{
+    let names = zip.file_names()
+        .filter_map(|file_name| crate::npz::array_name_from_file_name(file_name).map(|name| (name.to_string(), file_name.to_string())))
+        .collect::<Vec<_>>();
+    // Reopening the archive on a shared reader allows every thread to have its own handle.
+    let zip = ZipArchive::new(SharedReader::new(zip.into_inner())).map_err(|e| io::Error::from(crate::Error::from(e)))?;
+
+    let num_threads = thread::available_parallelism().map_or(1, |n| n.get()).min(names.len());
+    let budget = options.get_memory_budget().map(Budget::new);
+    let next_index = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let handles = (0..num_threads).map(|_| {
+            let mut zip = zip.clone();
+            let (names, next_index, budget) = (&names, &next_index, budget.as_ref());
+            scope.spawn(move || {
+                let mut results = vec![];
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+                    let (name, file_name) = match names.get(index) {
+                        Some(entry) => entry,
+                        None => break,
+                    };
+                    let result = load_one(&mut zip, name, file_name, options, password, budget);
+                    let failed = result.is_err();
+                    results.push((index, result));
+                    if failed {
+                        // make the other threads stop early
+                        next_index.store(names.len(), Ordering::Relaxed);
+                    }
+                }
+                results
+            })
+        }).collect::<Vec<_>>();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e))).collect::<Vec<_>>()
+    });
+
+    // report the error of the first member that failed
+    results.sort_by_key(|&(index, _)| index);
+    let mut out = HashMap::new();
+    for (index, result) in results {
+        out.insert(names[index].0.clone(), result?);
+    }
+    Ok(out)
+}
+
+fn load_one<R: Read + Seek, T: Deserialize>(
+    zip: &mut ZipArchive<R>,
+    name: &str,
+    file_name: &str,
+    options: &ReadOptions,
+    password: Option<&[u8]>,
+    budget: Option<&Budget>,
+) -> io::Result<LoadedArray<T>> {
+    let file = crate::npz_feature::entry_by_name(zip, password, file_name).map_err(|e| crate::Error::in_member(crate::Error::from(e).into(), name))?;
+    let file_size = file.size();
+    let npy = NpyFile::with_options(file, options).map_err(|e| crate::Error::in_member(e, name))?.with_member_name(name);
+    // the decoded elements, or the raw bytes if they take more space
+    let size = npy.len().saturating_mul(std::mem::size_of::<T>() as u64).max(file_size);
+    let _reservation = budget.map(|budget| budget.reserve(usize::try_from(size).unwrap_or(usize::MAX)));
+    let header = npy.header().clone();
+    Ok(LoadedArray { header, data: npy.into_vec()? })
+}
+
+/// A number of bytes shared by the threads, which wait until enough of it is free.
+struct Budget {
+    total: usize,
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Bytes taken from a [`Budget`], which are given back when this is dropped.
+struct Reservation<'a> {
+    budget: &'a Budget,
+    bytes: usize,
+}
+
+impl Budget {
+    fn new(total: usize) -> Self {
+        Budget { total, available: Mutex::new(total), released: Condvar::new() }
+    }
+
+    fn reserve(&self, bytes: usize) -> Reservation<'_> {
+        // a request larger than the budget waits for all of it, so that it runs alone
+        let bytes = bytes.min(self.total);
+        let mut available = self.available.lock().unwrap_or_else(|e| e.into_inner());
+        while *available < bytes {
+            available = self.released.wait(available).unwrap_or_else(|e| e.into_inner());
+        }
+        *available -= bytes;
+        Reservation { budget: self, bytes }
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        *self.budget.available.lock().unwrap_or_else(|e| e.into_inner()) += self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+/// A handle to a reader shared between threads, with its own position.
+pub(crate) struct SharedReader<R> {
+    // the reader and its current position
+    inner: Arc<Mutex<(R, u64)>>,
+    pos: u64,
+}
+
+impl<R: Seek> SharedReader<R> {
+    pub(crate) fn new(mut reader: R) -> Self {
+        // an error here will resurface on the next seek
+        let pos = reader.stream_position().unwrap_or(0);
+        SharedReader { inner: Arc::new(Mutex::new((reader, pos))), pos }
+    }
+}
+
+impl<R> Clone for SharedReader<R> {
+    fn clone(&self) -> Self {
+        SharedReader { inner: self.inner.clone(), pos: self.pos }
+    }
+}
+
+impl<R: Read + Seek> Read for SharedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let (reader, inner_pos) = &mut *guard;
+        if *inner_pos != self.pos {
+            *inner_pos = reader.seek(SeekFrom::Start(self.pos))?;
+        }
+        let len = reader.read(buf)?;
+        *inner_pos += len as u64;
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R: Seek> Seek for SharedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(_) => {
+                let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+                let (reader, inner_pos) = &mut *guard;
+                *inner_pos = reader.seek(pos)?;
+                Some(*inner_pos)
+            },
+        };
+        self.pos = new_pos.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position"))?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_reader() -> io::Result<()> {
+        let bytes = (0..100).collect::<Vec<u8>>();
+        let mut a = SharedReader::new(io::Cursor::new(bytes));
+        let mut b = a.clone();
+        let mut buf = [0; 3];
+        a.seek(SeekFrom::Start(10))?;
+        a.read_exact(&mut buf)?;
+        assert_eq!(buf, [10, 11, 12]);
+        b.read_exact(&mut buf)?;
+        assert_eq!(buf, [0, 1, 2]);
+        a.read_exact(&mut buf)?;
+        assert_eq!(buf, [13, 14, 15]);
+        assert_eq!(b.seek(SeekFrom::End(-1))?, 99);
+        assert_eq!(b.seek(SeekFrom::Current(-9))?, 90);
+        assert!(b.seek(SeekFrom::Current(-91)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn budget() {
+        let budget = Budget::new(100);
+        let in_use = AtomicUsize::new(0);
+        let max_in_use = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for i in 0..8 {
+                let (budget, in_use, max_in_use) = (&budget, &in_use, &max_in_use);
+                scope.spawn(move || {
+                    for bytes in [30, 60, 250, 10 * i] {
+                        let reservation = budget.reserve(bytes);
+                        let now = in_use.fetch_add(reservation.bytes, Ordering::SeqCst) + reservation.bytes;
+                        max_in_use.fetch_max(now, Ordering::SeqCst);
+                        thread::yield_now();
+                        in_use.fetch_sub(reservation.bytes, Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+        assert!(max_in_use.load(Ordering::SeqCst) <= 100);
+        assert_eq!(*budget.available.lock().unwrap(), 100);
+    }
+}
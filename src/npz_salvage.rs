@@ -0,0 +1,168 @@
+//! Recovering arrays from NPZ files whose central directory is missing or damaged.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+use zip::read::{read_zipfile_from_stream, ZipFile};
+
+use crate::read::{NpyFile, ReadOptions};
+
+const LOCAL_HEADER_SIGNATURE: &[u8; 4] = b"PK\x03\x04";
+
+/// Reads the intact arrays of an NPZ file that cannot be opened normally, e.g. because the program
+/// writing it was killed or ran out of disk space before the central directory at the end of the zip
+/// was written.
+///
+/// Instead of reading the central directory, this scans the file for the local header that precedes
+/// each member.  A member is considered intact if it can be decompressed and its CRC matches; damaged
+/// regions are skipped by searching for the next local header.  Only the members that are written
+/// with their sizes in the local header can be recovered, which is the case for archives written by
+/// numpy to a regular file and for archives written by [`NpzWriter`][`crate::npz::NpzWriter`].
+///
+/// *This is only available with the **`"npz"`** feature.*
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::npz::SalvagedNpz;
+///
+/// // cut off the central directory and part of the last array
+/// let bytes = std::fs::read("test-data/uncompressed.npz")?;
+/// let truncated = &bytes[..bytes.len() - 200];
+///
+/// let mut npz = SalvagedNpz::scan(std::io::Cursor::new(truncated))?;
+/// assert_eq!(npz.array_names().collect::<Vec<_>>(), vec!["ints"]);
+/// assert_eq!(npz.by_name("ints")?.unwrap().into_vec::<i64>()?, vec![1, 2, 3, 4]);
+/// assert!(!npz.skipped().is_empty());
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct SalvagedNpz<R: Read + Seek> {
+    reader: R,
+    members: Vec<(String, u64)>,
+    skipped: Vec<Range<u64>>,
+    options: ReadOptions,
+}
+
+impl SalvagedNpz<io::BufReader<File>> {
+    /// Scan an `npz` archive on the filesystem.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::scan(io::BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> SalvagedNpz<R> {
+    /// Scan an arbitrary stream for intact members.
+    ///
+    /// This reads and decompresses every member once.  Errors are only returned for failures of the
+    /// underlying reader; damage to the archive is reported by [`Self::skipped`].
+    pub fn scan(mut reader: R) -> io::Result<Self> {
+        let end = reader.seek(SeekFrom::End(0))?;
+        let mut members = vec![];
+        let mut skipped = vec![];
+
+        let mut offset = 0;
+        while offset < end {
+            match check_member(&mut reader, offset)? {
+                Member::Intact { name, next_offset } => {
+                    members.push((name, offset));
+                    offset = next_offset;
+                },
+                Member::CentralDirectory => break,
+                Member::Damaged => {
+                    let next = find_signature(&mut reader, offset + 1, end)?.unwrap_or(end);
+                    skipped.push(offset..next);
+                    offset = next;
+                },
+            }
+        }
+        Ok(SalvagedNpz { reader, members, skipped, options: ReadOptions::default() })
+    }
+
+    /// Set the options used when reading the arrays.
+    pub fn with_read_options(mut self, options: ReadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Get the names of the intact arrays, in the order they appear in the file.
+    pub fn array_names(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().filter_map(|(name, _)| crate::npz::array_name_from_file_name(name))
+    }
+
+    /// Get the byte ranges of the file that could not be read as intact members.
+    ///
+    /// These are empty for an undamaged archive.  (the central directory is not included)
+    pub fn skipped(&self) -> &[Range<u64>] {
+        &self.skipped
+    }
+
+    /// Read an intact array with the given name.
+    ///
+    /// If it is not present, `Ok(None)` is returned.  If an archive contains multiple members with the
+    /// same name (e.g. from appending), the last one is used, as it is by most zip readers.
+    pub fn by_name(&mut self, name: &str) -> io::Result<Option<NpyFile<ZipFile<'_>>>> {
+        let file_name = crate::npz::file_name_from_array_name(name);
+        let offset = match self.members.iter().rev().find(|(member, _)| *member == file_name) {
+            Some(&(_, offset)) => offset,
+            None => return Ok(None),
+        };
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let file = read_zipfile_from_stream(&mut self.reader)
+            .map_err(|e| io::Error::from(crate::Error::from(e)))?
+            .expect("member was checked by scan");
+        let npy = NpyFile::with_options(file, &self.options).map_err(|e| crate::Error::in_member(e, name))?;
+        Ok(Some(npy.with_member_name(name)))
+    }
+
+    /// Get the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+enum Member {
+    Intact { name: String, next_offset: u64 },
+    CentralDirectory,
+    Damaged,
+}
+
+fn check_member(reader: &mut (impl Read + Seek), offset: u64) -> io::Result<Member> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let name = match read_zipfile_from_stream(reader) {
+        Ok(Some(mut file)) => {
+            let name = file.name().to_string();
+            match io::copy(&mut file, &mut io::sink()) {
+                Ok(_) => name,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => return Err(e),
+                Err(_) => return Ok(Member::Damaged),
+            }
+        },
+        Ok(None) => return Ok(Member::CentralDirectory),
+        Err(zip::result::ZipError::Io(e)) if e.kind() != io::ErrorKind::UnexpectedEof => return Err(e),
+        Err(_) => return Ok(Member::Damaged),
+    };
+    // dropping the ZipFile leaves the reader at the end of its data
+    Ok(Member::Intact { name, next_offset: reader.stream_position()? })
+}
+
+// Find the next local file header at or after `start`.
+fn find_signature(reader: &mut (impl Read + Seek), start: u64, end: u64) -> io::Result<Option<u64>> {
+    let mut buf = vec![0; 1 << 16];
+    let mut pos = start;
+    while pos < end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let len = buf.len().min((end - pos) as usize);
+        reader.read_exact(&mut buf[..len])?;
+        if let Some(index) = buf[..len].windows(4).position(|window| window == LOCAL_HEADER_SIGNATURE) {
+            return Ok(Some(pos + index as u64));
+        }
+        if len < 4 {
+            break;
+        }
+        // overlap in case the signature straddles two chunks
+        pos += (len - 3) as u64;
+    }
+    Ok(None)
+}
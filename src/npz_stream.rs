@@ -0,0 +1,148 @@
+//! Reading NPZ files sequentially from a stream that cannot seek.
+
+use std::io::{self, Read};
+
+use zip::read::{read_zipfile_from_stream, ZipFile};
+
+use crate::read::{NpyFile, ReadOptions};
+
+/// Reads the arrays of an NPZ file one after another from a plain [`io::Read`], such as stdin or a
+/// network connection.
+///
+/// Unlike [`NpzArchive`][`crate::npz::NpzArchive`], this does not use the central directory at the
+/// end of the zip, so the arrays can only be read in the order they are stored, and each one only
+/// once.  Members that are not arrays are skipped.  Only the members that are written with their sizes
+/// in the local header can be read, which is the case for archives written by numpy to a regular file
+/// and for archives written by [`NpzWriter`][`crate::npz::NpzWriter`].  Encrypted members are not supported.
+///
+/// *This is only available with the **`"npz"`** feature.*
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::npz::NpzStreamReader;
+///
+/// let bytes = std::fs::read("test-data/compressed.npz")?;
+/// let mut npz = NpzStreamReader::new(&bytes[..]);
+/// while let Some((name, npy)) = npz.next_array()? {
+///     println!("{}: {:?}", name, npy.shape());
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct NpzStreamReader<R: Read> {
+    reader: Replay<R>,
+    options: ReadOptions,
+    finished: bool,
+}
+
+impl<R: Read> NpzStreamReader<R> {
+    /// Read an NPZ file from the start of a stream.
+    pub fn new(reader: R) -> Self {
+        NpzStreamReader { reader: Replay { buf: vec![], pos: 0, inner: reader }, options: ReadOptions::default(), finished: false }
+    }
+
+    /// Set the options used when reading the arrays.
+    pub fn with_read_options(mut self, options: ReadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Read the header of the next array, returning its name along with an [`NpyFile`] for reading its data.
+    ///
+    /// Returns `Ok(None)` once the central directory (or the end of the stream) is reached.  Any data
+    /// of the previous array that was not read is skipped over.
+    pub fn next_array(&mut self) -> io::Result<Option<(String, NpyFile<ZipFile<'_>>)>> {
+        while !self.finished {
+            // The name is read ahead of the zip crate, so that members which are not arrays can be
+            // skipped without holding on to a `ZipFile` that borrows the reader.
+            let file_name = match self.reader.peek_file_name()? {
+                Some(file_name) => file_name,
+                None => break,
+            };
+            if let Some(name) = crate::npz::array_name_from_file_name(&file_name) {
+                let file = self.reader.next_member()?;
+                let npy = NpyFile::with_options(file, &self.options).map_err(|e| crate::Error::in_member(e, name))?;
+                return Ok(Some((name.to_string(), npy.with_member_name(name))));
+            }
+            // dropping the ZipFile skips over its data
+            self.reader.next_member()?;
+        }
+        self.finished = true;
+        Ok(None)
+    }
+
+    /// Get the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader.inner
+    }
+}
+
+const LOCAL_HEADER_SIGNATURE: &[u8; 4] = b"PK\x03\x04";
+const LOCAL_HEADER_LEN: usize = 30;
+
+// A reader that can give back bytes it has already read.
+#[derive(Debug)]
+struct Replay<R> {
+    buf: Vec<u8>,
+    pos: usize,
+    inner: R,
+}
+
+impl<R: Read> Replay<R> {
+    // Read the file name from the next local header, leaving the header to be read again.
+    //
+    // Returns `None` at the end of the members, i.e. at the central directory or the end of the stream.
+    fn peek_file_name(&mut self) -> io::Result<Option<String>> {
+        debug_assert_eq!(self.pos, self.buf.len(), "all peeked bytes were read");
+        self.buf.clear();
+        self.pos = 0;
+        let complete = self.peek(LOCAL_HEADER_LEN)?;
+        if self.buf.is_empty() || (self.buf.len() >= 4 && &self.buf[..4] != LOCAL_HEADER_SIGNATURE) {
+            self.pos = self.buf.len();
+            return Ok(None);
+        }
+        if !complete {
+            return Err(crate::Error::Truncated.into());
+        }
+        let name_len = u16::from_le_bytes([self.buf[26], self.buf[27]]) as usize;
+        if !self.peek(LOCAL_HEADER_LEN + name_len)? {
+            return Err(crate::Error::Truncated.into());
+        }
+        // non-UTF-8 names are decoded by the zip crate; they are never array names written by numpy
+        Ok(Some(String::from_utf8_lossy(&self.buf[LOCAL_HEADER_LEN..]).into_owned()))
+    }
+
+    fn next_member(&mut self) -> io::Result<ZipFile<'_>> {
+        let file = read_zipfile_from_stream(self).map_err(|e| io::Error::from(crate::Error::from(e)))?;
+        Ok(file.expect("a local header was peeked"))
+    }
+
+    // Fill the buffer up to `len` bytes, returning false if the stream ends first.
+    fn peek(&mut self, len: usize) -> io::Result<bool> {
+        let start = self.buf.len();
+        self.buf.resize(len, 0);
+        let mut filled = start;
+        while filled < len {
+            match self.inner.read(&mut self.buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        }
+        self.buf.truncate(filled);
+        Ok(filled == len)
+    }
+}
+
+impl<R: Read> Read for Replay<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos < self.buf.len() {
+            let n = out.len().min(self.buf.len() - self.pos);
+            out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+            self.pos += n;
+            return Ok(n);
+        }
+        self.inner.read(out)
+    }
+}
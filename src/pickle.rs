@@ -0,0 +1,571 @@
+//! Reading NPY files of object arrays, whose data is a Python pickle.
+//!
+//! _This module is only available with the **`"pickle"`** feature._
+//!
+//! numpy saves arrays with `dtype=object` by pickling them (when called with `allow_pickle=True`).
+//! Such files cannot be read by [`NpyFile`][crate::NpyFile], because their elements are arbitrary Python
+//! objects.  [`ObjectArray`] reads them when the elements are built from common Python types: `None`,
+//! `bool`, `int`, `float`, `complex`, `str`, `bytes`, and lists, tuples and dicts of these.
+//!
+//! Unlike Python's `pickle` module, this never imports or calls anything named in the pickle.  The only
+//! objects it constructs are those listed above and the array itself; any other object is an error.
+//!
+//! ```
+//! # fn main() -> std::io::Result<()> {
+//! use npyz::pickle::{ObjectArray, Value};
+//!
+//! // np.save("object.npy", np.array([None, -7, 2.5, "héllo", ...], dtype=object).reshape(3, 3))
+//! let bytes = std::fs::read("test-data/object.npy")?;
+//! let array = ObjectArray::from_reader(&bytes[..])?;
+//! assert_eq!(array.shape, vec![3, 3]);
+//! assert_eq!(array.data[..3], [Value::None, Value::Int(-7), Value::Float(2.5)]);
+//! assert_eq!(array.data[3].as_str(), Some("héllo"));
+//! # Ok(()) }
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+
+use crate::error::Error;
+use crate::header::{self, read_header, convert_value_to_shape};
+use crate::read::ReadOptions;
+
+/// A Python object from an object array.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Value {
+    /// `None`.
+    None,
+    /// A `bool`.
+    Bool(bool),
+    /// An `int`.  Integers that do not fit in an `i64` are an error.
+    Int(i64),
+    /// A `float`.
+    Float(f64),
+    /// A `complex`, as `(re, im)`.
+    Complex(f64, f64),
+    /// A `str`.
+    Str(String),
+    /// A `bytes`, or a Python 2 `str`.
+    Bytes(Vec<u8>),
+    /// A `list`.
+    List(Vec<Value>),
+    /// A `tuple`.
+    Tuple(Vec<Value>),
+    /// A `dict`, with its items in insertion order.
+    Dict(Vec<(Value, Value)>),
+}
+
+impl Value {
+    /// Get the string, if this is a [`Value::Str`].
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Get the elements, if this is a [`Value::List`] or [`Value::Tuple`].
+    pub fn as_slice(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) | Value::Tuple(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// An object array read from an NPY file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectArray {
+    /// The shape of the array.
+    pub shape: Vec<u64>,
+    /// The elements of the array, in C order.  (numpy pickles the elements in C order even when
+    /// the header says `fortran_order`)
+    pub data: Vec<Value>,
+}
+
+impl ObjectArray {
+    /// Read an NPY file of an object array, with the default [`ReadOptions`].
+    ///
+    /// Returns [`Error::BadHeader`] if the dtype is not `object`, and [`Error::InvalidData`] if the pickle
+    /// contains something other than the types of [`Value`].
+    pub fn from_reader(r: impl io::Read) -> io::Result<Self> {
+        Self::from_reader_with_options(r, &ReadOptions::default())
+    }
+
+    /// Read an NPY file of an object array, checking its header against the given [`ReadOptions`].
+    ///
+    /// The elements are also checked against [`ReadOptions::max_depth`] and [`ReadOptions::max_objects`],
+    /// returning [`Error::LimitExceeded`] if they are nested too deeply or are too many values in total.
+    pub fn from_reader_with_options(mut r: impl io::Read, options: &ReadOptions) -> io::Result<Self> {
+        let (header, _) = read_header(&mut r, options)?;
+        let dict = match header {
+            header::Value::Dict(dict) => dict,
+            _ => return Err(Error::BadHeader("expected a python dict literal".to_string()).into()),
+        };
+        let get = |key: &str| {
+            dict.iter()
+                .find(|(k, _)| k.as_string().map(|k| &k[..]) == Some(key))
+                .map(|(_, v)| v)
+                .ok_or_else(|| Error::BadHeader(format!("dict is missing key '{}'", key)))
+        };
+        let descr = get("descr")?;
+        if !header::is_object_descr(descr) {
+            return Err(Error::BadHeader(format!("expected an object array, got dtype {}", descr)).into());
+        }
+        let shape = convert_value_to_shape(get("shape")?)?;
+        options.check_ndim(shape.len())?;
+
+        let mut machine = Machine::default();
+        let array = machine.run(&mut r)?;
+        let data = machine.unpickle_ndarray(array, options)?;
+        let len = shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim));
+        if len != Some(data.len() as u64) {
+            return Err(Error::InvalidData(format!("{} objects in pickle for shape {:?}", data.len(), shape)).into());
+        }
+        Ok(ObjectArray { shape, data })
+    }
+}
+
+// Objects are stored in an arena, so that the memo and the stack can refer to the same mutable list or dict.
+type Id = usize;
+
+enum Object {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    List(Vec<Id>),
+    Tuple(Vec<Id>),
+    Dict(Vec<(Id, Id)>),
+    Global { module: String, name: String },
+    // `func(*args)` (or `cls.__new__(cls, *args)`), never called; `state` is from a following BUILD
+    Reduce { func: Id, args: Id, state: Option<Id> },
+}
+
+#[derive(Default)]
+struct Machine {
+    objects: Vec<Object>,
+    stack: Vec<Id>,
+    marks: Vec<usize>,
+    memo: HashMap<u64, Id>,
+}
+
+fn invalid_data(message: impl ToString) -> io::Error {
+    Error::InvalidData(message.to_string()).into()
+}
+
+fn truncated(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => Error::Truncated.into(),
+        _ => err,
+    }
+}
+
+// Read a length-prefixed argument, without trusting the length for the allocation.
+fn read_bytes(r: &mut impl io::Read, len: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    io::Read::take(r, len).read_to_end(&mut bytes)?;
+    match bytes.len() as u64 == len {
+        true => Ok(bytes),
+        false => Err(Error::Truncated.into()),
+    }
+}
+
+fn read_string(r: &mut impl io::Read, len: u64) -> io::Result<String> {
+    String::from_utf8(read_bytes(r, len)?).map_err(|e| invalid_data(format_args!("invalid utf-8 in pickle: {}", e)))
+}
+
+fn read_line(r: &mut impl io::Read) -> io::Result<String> {
+    let mut line = vec![];
+    loop {
+        match r.read_u8()? {
+            b'\n' => break,
+            byte => line.push(byte),
+        }
+    }
+    String::from_utf8(line).map_err(|e| invalid_data(format_args!("invalid utf-8 in pickle: {}", e)))
+}
+
+// A little-endian two's complement integer of LONG1 or LONG4.
+fn read_long(r: &mut impl io::Read, len: u64) -> io::Result<i64> {
+    let bytes = read_bytes(r, len)?;
+    let negative = bytes.last().is_some_and(|&b| b & 0x80 != 0);
+    let fill = if negative { 0xff } else { 0 };
+    let (low, high) = bytes.split_at(bytes.len().min(8));
+    let mut buf = [fill; 8];
+    buf[..low.len()].copy_from_slice(low);
+    let value = i64::from_le_bytes(buf);
+    if high.iter().any(|&b| b != fill) || (value < 0) != negative {
+        return Err(invalid_data("integer in pickle does not fit in an i64"));
+    }
+    Ok(value)
+}
+
+impl Machine {
+    fn push(&mut self, object: Object) {
+        self.objects.push(object);
+        self.stack.push(self.objects.len() - 1);
+    }
+
+    fn pop(&mut self) -> io::Result<Id> {
+        match self.marks.last() {
+            Some(&mark) if mark == self.stack.len() => Err(invalid_data("pickle pops past a mark")),
+            _ => self.stack.pop().ok_or_else(|| invalid_data("pickle pops from an empty stack")),
+        }
+    }
+
+    fn top(&self) -> io::Result<Id> {
+        self.stack.last().copied().ok_or_else(|| invalid_data("pickle uses an empty stack"))
+    }
+
+    fn pop_mark(&mut self) -> io::Result<Vec<Id>> {
+        let mark = self.marks.pop().ok_or_else(|| invalid_data("pickle is missing a mark"))?;
+        Ok(self.stack.split_off(mark))
+    }
+
+    fn pop_n(&mut self, n: usize) -> io::Result<Vec<Id>> {
+        let start = self.stack.len().checked_sub(n).ok_or_else(|| invalid_data("pickle pops from an empty stack"))?;
+        if self.marks.last().is_some_and(|&mark| mark > start) {
+            return Err(invalid_data("pickle pops past a mark"));
+        }
+        Ok(self.stack.split_off(start))
+    }
+
+    fn append(&mut self, list: Id, items: Vec<Id>) -> io::Result<()> {
+        match &mut self.objects[list] {
+            Object::List(list) => {
+                list.extend(items);
+                Ok(())
+            },
+            _ => Err(invalid_data("pickle appends to something other than a list")),
+        }
+    }
+
+    fn set_items(&mut self, dict: Id, items: Vec<Id>) -> io::Result<()> {
+        if items.len() % 2 != 0 {
+            return Err(invalid_data("pickle sets a dict item without a value"));
+        }
+        match &mut self.objects[dict] {
+            Object::Dict(dict) => {
+                dict.extend(items.chunks(2).map(|pair| (pair[0], pair[1])));
+                Ok(())
+            },
+            _ => Err(invalid_data("pickle sets an item of something other than a dict")),
+        }
+    }
+
+    fn memo_get(&mut self, key: u64) -> io::Result<()> {
+        let id = *self.memo.get(&key).ok_or_else(|| invalid_data(format_args!("pickle memo has no key {}", key)))?;
+        self.stack.push(id);
+        Ok(())
+    }
+
+    fn memo_put(&mut self, key: u64) -> io::Result<()> {
+        let id = self.top()?;
+        self.memo.insert(key, id);
+        Ok(())
+    }
+
+    /// Run the pickle until STOP, returning the object that it produces.
+    fn run(&mut self, r: &mut impl io::Read) -> io::Result<Id> {
+        self.run_opcodes(r).map_err(truncated)
+    }
+
+    fn run_opcodes(&mut self, r: &mut impl io::Read) -> io::Result<Id> {
+        loop {
+            match r.read_u8()? {
+                // PROTO
+                0x80 => match r.read_u8()? {
+                    2..=5 => {},
+                    protocol => return Err(invalid_data(format_args!("unsupported pickle protocol {}", protocol))),
+                },
+                // FRAME
+                0x95 => { r.read_u64::<LittleEndian>()?; },
+                // STOP
+                b'.' => return self.pop(),
+                b'(' => self.marks.push(self.stack.len()),
+                // POP
+                b'0' => { self.pop()?; },
+                // POP_MARK
+                b'1' => { self.pop_mark()?; },
+
+                b'N' => self.push(Object::None),
+                0x88 => self.push(Object::Bool(true)),
+                0x89 => self.push(Object::Bool(false)),
+                // BININT, BININT1, BININT2
+                b'J' => { let x = r.read_i32::<LittleEndian>()?; self.push(Object::Int(x.into())) },
+                b'K' => { let x = r.read_u8()?; self.push(Object::Int(x.into())) },
+                b'M' => { let x = r.read_u16::<LittleEndian>()?; self.push(Object::Int(x.into())) },
+                // LONG1, LONG4
+                0x8a => { let len = r.read_u8()?; let x = read_long(r, len.into())?; self.push(Object::Int(x)) },
+                0x8b => { let len = r.read_u32::<LittleEndian>()?; let x = read_long(r, len.into())?; self.push(Object::Int(x)) },
+                // BINFLOAT
+                b'G' => { let x = r.read_f64::<BigEndian>()?; self.push(Object::Float(x)) },
+
+                // SHORT_BINUNICODE, BINUNICODE, BINUNICODE8
+                0x8c => { let len = r.read_u8()?; let s = read_string(r, len.into())?; self.push(Object::Str(s)) },
+                b'X' => { let len = r.read_u32::<LittleEndian>()?; let s = read_string(r, len.into())?; self.push(Object::Str(s)) },
+                0x8d => { let len = r.read_u64::<LittleEndian>()?; let s = read_string(r, len)?; self.push(Object::Str(s)) },
+                // SHORT_BINBYTES, BINBYTES, BINBYTES8, BYTEARRAY8, and Python 2's SHORT_BINSTRING, BINSTRING
+                b'C' | b'U' => { let len = r.read_u8()?; let b = read_bytes(r, len.into())?; self.push(Object::Bytes(b)) },
+                b'B' | b'T' => { let len = r.read_u32::<LittleEndian>()?; let b = read_bytes(r, len.into())?; self.push(Object::Bytes(b)) },
+                0x8e | 0x96 => { let len = r.read_u64::<LittleEndian>()?; let b = read_bytes(r, len)?; self.push(Object::Bytes(b)) },
+
+                b']' => self.push(Object::List(vec![])),
+                b')' => self.push(Object::Tuple(vec![])),
+                b'}' => self.push(Object::Dict(vec![])),
+                // LIST, TUPLE, TUPLE1, TUPLE2, TUPLE3
+                b'l' => { let items = self.pop_mark()?; self.push(Object::List(items)) },
+                b't' => { let items = self.pop_mark()?; self.push(Object::Tuple(items)) },
+                0x85 => { let items = self.pop_n(1)?; self.push(Object::Tuple(items)) },
+                0x86 => { let items = self.pop_n(2)?; self.push(Object::Tuple(items)) },
+                0x87 => { let items = self.pop_n(3)?; self.push(Object::Tuple(items)) },
+                // DICT
+                b'd' => {
+                    let items = self.pop_mark()?;
+                    self.push(Object::Dict(vec![]));
+                    self.set_items(self.top()?, items)?;
+                },
+                // APPEND, APPENDS
+                b'a' => { let item = self.pop()?; self.append(self.top()?, vec![item])? },
+                b'e' => { let items = self.pop_mark()?; self.append(self.top()?, items)? },
+                // SETITEM, SETITEMS
+                b's' => { let items = self.pop_n(2)?; self.set_items(self.top()?, items)? },
+                b'u' => { let items = self.pop_mark()?; self.set_items(self.top()?, items)? },
+
+                // BINPUT, LONG_BINPUT, MEMOIZE
+                b'q' => { let key = r.read_u8()?; self.memo_put(key.into())? },
+                b'r' => { let key = r.read_u32::<LittleEndian>()?; self.memo_put(key.into())? },
+                0x94 => self.memo_put(self.memo.len() as u64)?,
+                // BINGET, LONG_BINGET
+                b'h' => { let key = r.read_u8()?; self.memo_get(key.into())? },
+                b'j' => { let key = r.read_u32::<LittleEndian>()?; self.memo_get(key.into())? },
+
+                // GLOBAL, STACK_GLOBAL
+                b'c' => {
+                    let module = read_line(r)?;
+                    let name = read_line(r)?;
+                    self.push(Object::Global { module, name });
+                },
+                0x93 => {
+                    let parts = self.pop_n(2)?;
+                    match (&self.objects[parts[0]], &self.objects[parts[1]]) {
+                        (Object::Str(module), Object::Str(name)) => {
+                            let (module, name) = (module.clone(), name.clone());
+                            self.push(Object::Global { module, name });
+                        },
+                        _ => return Err(invalid_data("pickle has a global whose name is not a string")),
+                    }
+                },
+                // REDUCE, NEWOBJ
+                b'R' | 0x81 => {
+                    let parts = self.pop_n(2)?;
+                    self.push(Object::Reduce { func: parts[0], args: parts[1], state: None });
+                },
+                // BUILD
+                b'b' => {
+                    let new_state = self.pop()?;
+                    let top = self.top()?;
+                    match &mut self.objects[top] {
+                        Object::Reduce { state, .. } => *state = Some(new_state),
+                        _ => return Err(invalid_data("pickle sets the state of a built-in type")),
+                    }
+                },
+                opcode => return Err(invalid_data(format_args!("unsupported pickle opcode {:#04x}", opcode))),
+            }
+        }
+    }
+
+    fn global_name(&self, id: Id) -> Option<(&str, &str)> {
+        match &self.objects[id] {
+            Object::Global { module, name } => Some((module, name)),
+            _ => None,
+        }
+    }
+
+    fn items(&self, id: Id) -> Option<&[Id]> {
+        match &self.objects[id] {
+            Object::Tuple(items) | Object::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Get the elements of an array that was pickled by `ndarray.__reduce__`.
+    fn unpickle_ndarray(&self, id: Id, options: &ReadOptions) -> io::Result<Vec<Value>> {
+        let not_an_array = || invalid_data("pickle does not contain a numpy array");
+        let (func, state) = match self.objects[id] {
+            Object::Reduce { func, state: Some(state), .. } => (func, state),
+            _ => return Err(not_an_array()),
+        };
+        match self.global_name(func) {
+            // "numpy._core.multiarray" since numpy 2.0
+            Some(("numpy.core.multiarray" | "numpy._core.multiarray", "_reconstruct")) => {},
+            _ => return Err(not_an_array()),
+        }
+        // (version, shape, dtype, is_fortran, data)
+        let data = match self.items(state) {
+            Some(&[_, _, _, _, data]) => data,
+            _ => return Err(not_an_array()),
+        };
+        match &self.objects[data] {
+            Object::List(items) => self.values(items, 0, options, &mut 0),
+            _ => Err(invalid_data("object array is not pickled as a list of objects")),
+        }
+    }
+
+    // Objects in the memo can be referred to any number of times, and each reference becomes a separate
+    // value, so both the depth (e.g. of a list that contains itself) and the total number of values are limited.
+    fn values(&self, items: &[Id], depth: usize, options: &ReadOptions, count: &mut usize) -> io::Result<Vec<Value>> {
+        items.iter().map(|&item| self.to_value(item, depth, options, count)).collect()
+    }
+
+    fn to_value(&self, id: Id, depth: usize, options: &ReadOptions, count: &mut usize) -> io::Result<Value> {
+        options.check_depth("object nesting depth", depth)?;
+        *count += 1;
+        options.check_objects(*count)?;
+        let values = |items: &[Id], count: &mut usize| self.values(items, depth + 1, options, count);
+        Ok(match &self.objects[id] {
+            Object::None => Value::None,
+            &Object::Bool(x) => Value::Bool(x),
+            &Object::Int(x) => Value::Int(x),
+            &Object::Float(x) => Value::Float(x),
+            Object::Str(x) => Value::Str(x.clone()),
+            Object::Bytes(x) => Value::Bytes(x.clone()),
+            Object::List(items) => Value::List(values(items, count)?),
+            Object::Tuple(items) => Value::Tuple(values(items, count)?),
+            Object::Dict(items) => Value::Dict({
+                items.iter()
+                    .map(|&(k, v)| Ok((self.to_value(k, depth + 1, options, count)?, self.to_value(v, depth + 1, options, count)?)))
+                    .collect::<io::Result<_>>()?
+            }),
+            Object::Global { module, name } => {
+                return Err(invalid_data(format_args!("unsupported object in pickle: {}.{}", module, name)));
+            },
+            &Object::Reduce { func, args, .. } => {
+                let args = self.items(args).map(|args| values(args, count)).transpose()?;
+                match (self.global_name(func), args.as_deref()) {
+                    // complex(re, im)
+                    (Some(("builtins" | "__builtin__", "complex")), Some([re, im])) => match (re, im) {
+                        (&Value::Float(re), &Value::Float(im)) => Value::Complex(re, im),
+                        _ => return Err(invalid_data("complex in pickle has parts that are not floats")),
+                    },
+                    // bytes in protocol 2, as codecs.encode(str, 'latin1')
+                    (Some(("_codecs", "encode")), Some([Value::Str(s), Value::Str(encoding)])) if encoding == "latin1" => {
+                        Value::Bytes(s.chars().map(|c| c as u32 as u8).collect())
+                    },
+                    (Some((module, name)), _) => {
+                        return Err(invalid_data(format_args!("unsupported object in pickle: {}.{}", module, name)));
+                    },
+                    (None, _) => return Err(invalid_data("unsupported object in pickle")),
+                }
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unpickle(pickle: &[u8]) -> io::Result<Value> {
+        unpickle_with_options(pickle, &ReadOptions::default())
+    }
+
+    fn unpickle_with_options(pickle: &[u8], options: &ReadOptions) -> io::Result<Value> {
+        let mut machine = Machine::default();
+        let id = machine.run(&mut &pickle[..])?;
+        machine.to_value(id, 0, options, &mut 0)
+    }
+
+    #[test]
+    fn numpy_files() {
+        let expected_strings = Value::List(vec![Value::Str("a".into()), Value::Str("b".into())]);
+        let expected = vec![
+            Value::None,
+            Value::Int(-7),
+            Value::Float(2.5),
+            Value::Str("héllo".into()),
+            expected_strings.clone(),
+            Value::Dict(vec![(Value::Str("k".into()), Value::Tuple(vec![Value::Int(1), Value::Bool(true)]))]),
+            Value::Bytes(b"raw".to_vec()),
+            expected_strings,
+            Value::Int(1 << 40),
+        ];
+        // The pickles of `np.array([None, -7, 2.5, "héllo", s, {"k": (1, True)}, b"raw", s, 2**40],
+        // dtype=object).reshape(3, 3)` with `s = ["a", "b"]`, in protocol 3 as by numpy 1, and in
+        // protocol 4 with the module names of numpy 2.
+        for path in ["test-data/object.npy", "test-data/object-numpy2.npy"] {
+            let array = ObjectArray::from_reader(std::fs::File::open(path).unwrap()).unwrap();
+            assert_eq!(array.shape, vec![3, 3]);
+            assert_eq!(array.data, expected);
+        }
+    }
+
+    #[test]
+    fn values() {
+        // pickle.dumps([complex(1, 2), 2**63 - 1, -2**63, True], protocol=2)
+        let pickle = b"\x80\x02]q\x00(c__builtin__\ncomplex\nq\x01G?\xf0\x00\x00\x00\x00\x00\x00G@\x00\x00\x00\x00\x00\x00\x00\x86q\x02Rq\x03\x8a\x08\xff\xff\xff\xff\xff\xff\xff\x7f\x8a\x08\x00\x00\x00\x00\x00\x00\x00\x80\x88e.";
+        assert_eq!(unpickle(pickle).unwrap(), Value::List(vec![
+            Value::Complex(1.0, 2.0), Value::Int(i64::MAX), Value::Int(i64::MIN), Value::Bool(true),
+        ]));
+
+        // pickle.dumps(b'\xff', protocol=2)
+        let pickle = b"\x80\x02c_codecs\nencode\nq\x00X\x02\x00\x00\x00\xc3\xbfq\x01X\x06\x00\x00\x00latin1q\x02\x86q\x03Rq\x04.";
+        assert_eq!(unpickle(pickle).unwrap(), Value::Bytes(vec![0xff]));
+    }
+
+    #[test]
+    fn limits() {
+        // pickle.dumps([[[1]]], protocol=2)
+        let pickle = b"\x80\x02]q\x00]q\x01]q\x02K\x01aaa.";
+        assert!(unpickle_with_options(pickle, &ReadOptions::new().max_depth(3)).is_ok());
+        let err = unpickle_with_options(pickle, &ReadOptions::new().max_depth(2)).unwrap_err();
+        assert!(matches!(Error::from(err), Error::LimitExceeded { what: "object nesting depth", max: 2, .. }));
+
+        // a0 = []; a1 = [a0, a0]; a2 = [a1, a1]; ... pickle.dumps(a40, protocol=2) has 2**41 - 1 values
+        let mut pickle = b"\x80\x02]q\x00".to_vec();
+        for i in 0..40u8 {
+            pickle.extend([b'(', b'h', i, b'h', i, b'l', b'q', i + 1]);
+        }
+        pickle.push(b'.');
+        let options = ReadOptions::new().max_depth(64).max_objects(1000);
+        let err = unpickle_with_options(&pickle, &options).unwrap_err();
+        assert!(matches!(Error::from(err), Error::LimitExceeded { what: "number of objects", max: 1000, .. }));
+
+        // the first 9 levels have 2**10 - 1 values
+        let mut small = pickle[..5 + 9 * 8].to_vec();
+        small.push(b'.');
+        assert!(unpickle_with_options(&small, &ReadOptions::new().max_objects(1023)).is_ok());
+        let err = unpickle_with_options(&small, &ReadOptions::new().max_objects(1022)).unwrap_err();
+        assert!(matches!(Error::from(err), Error::LimitExceeded { what: "number of objects", .. }));
+    }
+
+    #[test]
+    fn errors() {
+        // pickle.dumps(2**63, protocol=2)
+        let err = unpickle(b"\x80\x02\x8a\t\x00\x00\x00\x00\x00\x00\x00\x80\x00.").unwrap_err();
+        assert!(matches!(Error::from(err), Error::InvalidData(msg) if msg.contains("does not fit")));
+
+        // pickle.dumps(datetime.date(2000, 1, 1), protocol=2)
+        let err = unpickle(b"\x80\x02cdatetime\ndate\nq\x00c_codecs\nencode\nq\x01X\x05\x00\x00\x00\x07\xc3\x90\x01\x01q\x02X\x06\x00\x00\x00latin1q\x03\x86q\x04Rq\x05\x85q\x06Rq\x07.").unwrap_err();
+        assert!(matches!(Error::from(err), Error::InvalidData(msg) if msg.contains("datetime.date")));
+
+        // a = []; a.append(a); pickle.dumps(a, protocol=2)
+        let err = unpickle(b"\x80\x02]q\x00h\x00a.").unwrap_err();
+        assert!(matches!(Error::from(err), Error::LimitExceeded { what: "object nesting depth", .. }));
+
+        let err = unpickle(b"\x80\x02]q\x00(K\x01").unwrap_err();
+        assert!(matches!(Error::from(err), Error::Truncated));
+
+        let err = ObjectArray::from_reader(std::fs::File::open("test-data/plain.npy").unwrap()).unwrap_err();
+        assert!(matches!(Error::from(err), Error::BadHeader(msg) if msg.contains("expected an object array")));
+
+        let err = crate::NpyHeader::from_reader(std::fs::File::open("test-data/object.npy").unwrap()).err().unwrap();
+        assert!(matches!(Error::from(err), Error::BadHeader(msg) if msg.contains("npyz::pickle::ObjectArray")));
+    }
+}
@@ -0,0 +1,128 @@
+//! `proptest` strategies for dtypes, shapes and small arrays.
+//!
+//! _This module is only available with the **`"proptest"`** feature._
+
+use proptest::arbitrary::{any, Arbitrary};
+use proptest::collection::vec;
+use proptest::prop_oneof;
+use proptest::sample::select;
+use proptest::strategy::{BoxedStrategy, Just, Strategy};
+
+use crate::generate::small::{self, MAX_ARRAY_FIELD_LEN, MAX_DEPTH, MAX_DIM_LEN, MAX_FIELDS, MAX_NDIM, MAX_STRING_SIZE};
+use crate::generate::{SmallArray, SmallShape};
+use crate::header::DType;
+use crate::type_str::TypeStr;
+
+fn type_str(extended_precision: bool) -> BoxedStrategy<TypeStr> {
+    select(small::TYPE_CHARS).prop_flat_map(move |type_char| {
+        let size = match small::sizes(type_char, extended_precision) {
+            Some(sizes) => select(sizes).boxed(),
+            None => (0..=MAX_STRING_SIZE).boxed(),
+        };
+        let time_units = match type_char.has_units() {
+            true => select(small::TIME_UNITS).prop_map(Some).boxed(),
+            false => Just(None).boxed(),
+        };
+        (size, time_units).prop_flat_map(move |(size, time_units)| {
+            select(small::endiannesses(type_char, size))
+                .prop_map(move |endianness| small::type_str(endianness, type_char, size, time_units))
+        })
+    }).boxed()
+}
+
+fn record(field: BoxedStrategy<DType>) -> BoxedStrategy<DType> {
+    vec((select(small::FIELD_NAMES), field), 1..=MAX_FIELDS).prop_map(small::record).boxed()
+}
+
+fn dtype(extended_precision: bool) -> BoxedStrategy<DType> {
+    let plain = type_str(extended_precision).prop_map(DType::Plain);
+    let field = plain.clone().prop_recursive(MAX_DEPTH, 16, MAX_FIELDS as u32, |inner| prop_oneof![
+        (1..=MAX_ARRAY_FIELD_LEN, inner.clone()).prop_map(|(len, dtype)| DType::Array(len, Box::new(dtype))),
+        record(inner),
+    ]).boxed();
+    prop_oneof![plain, record(field)].boxed()
+}
+
+/// _This impl is only available with the **`"proptest"`** feature._
+impl Arbitrary for TypeStr {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<TypeStr>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        type_str(true)
+    }
+}
+
+/// Generates the dtype of a file: a type string, or a record whose fields may be records or arrays.
+///
+/// _This impl is only available with the **`"proptest"`** feature._
+impl Arbitrary for DType {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<DType>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        dtype(true)
+    }
+}
+
+/// _This impl is only available with the **`"proptest"`** feature._
+impl Arbitrary for SmallShape {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<SmallShape>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        vec(0..=MAX_DIM_LEN, 0..=MAX_NDIM).prop_map(SmallShape).boxed()
+    }
+}
+
+/// _This impl is only available with the **`"proptest"`** feature._
+impl Arbitrary for SmallArray {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<SmallArray>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (dtype(false), any::<SmallShape>(), any::<u64>())
+            .prop_map(|(dtype, SmallShape(shape), seed)| small::array(dtype, shape, seed))
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::proptest;
+
+    use crate::{DType, NpyFile, TypeStr};
+    use crate::generate::{SmallArray, SmallShape};
+
+    proptest! {
+        #[test]
+        fn type_str_roundtrip(type_str: TypeStr) {
+            assert_eq!(type_str.to_string().parse::<TypeStr>().unwrap(), type_str);
+        }
+
+        #[test]
+        fn dtype_roundtrip(dtype: DType) {
+            assert!(!matches!(dtype, DType::Array(..)));
+            assert_eq!(DType::parse(&dtype.descr()).unwrap(), dtype);
+        }
+
+        #[test]
+        fn small_shape(shape: SmallShape) {
+            assert!(shape.0.len() <= 3);
+            assert!(shape.0.iter().all(|&len| len <= 5));
+        }
+
+        #[test]
+        fn small_array(array: SmallArray) {
+            let npy = NpyFile::new(&array.bytes[..]).unwrap();
+            assert_eq!(npy.dtype(), array.dtype);
+            assert_eq!(npy.shape(), array.shape);
+            let mut records = npy.raw_records();
+            let mut count = 0;
+            while records.next_record().unwrap().is_some() {
+                count += 1;
+            }
+            assert_eq!(count, array.shape.iter().product::<u64>());
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Reading files lazily from a source that fetches ranges of bytes, such as an HTTP server.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::error::Error;
+use crate::read_at::ReadAtMany;
+
+const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+
+/// A source of bytes that is read by fetching ranges of it, e.g. a remote file read with HTTP
+/// `Range` requests.
+///
+/// Wrap it in a [`RangeReader`] to read NPY or NPZ files from it.  This crate does not include an
+/// HTTP client; the following is an implementation for [`reqwest`](https://docs.rs/reqwest)'s
+/// blocking client (not compiled here, since `reqwest` is not a dependency of npyz):
+///
+/// ```ignore
+/// struct HttpFile {
+///     client: reqwest::blocking::Client,
+///     url: String,
+/// }
+///
+/// impl npyz::RangeSource for HttpFile {
+///     fn size(&mut self) -> std::io::Result<u64> {
+///         let response = self.client.head(&self.url).send().and_then(|r| r.error_for_status()).map_err(std::io::Error::other)?;
+///         response.content_length().ok_or_else(|| std::io::Error::other("the server did not send a length"))
+///     }
+///
+///     fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<()> {
+///         let range = format!("bytes={}-{}", offset, offset + buf.len() as u64 - 1);
+///         let response = self.client.get(&self.url).header(reqwest::header::RANGE, range).send()
+///             .and_then(|r| r.error_for_status()).map_err(std::io::Error::other)?;
+///         if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+///             return Err(std::io::Error::other("the server does not support range requests"));
+///         }
+///         let bytes = response.bytes().map_err(std::io::Error::other)?;
+///         if bytes.len() != buf.len() {
+///             return Err(std::io::ErrorKind::UnexpectedEof.into());
+///         }
+///         buf.copy_from_slice(&bytes);
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait RangeSource {
+    /// Get the total size of the source in bytes.
+    ///
+    /// This is called once, when a [`RangeReader`] is created.
+    fn size(&mut self) -> io::Result<u64>;
+
+    /// Fill the buffer with the bytes starting at an offset.
+    ///
+    /// [`RangeReader`] never requests bytes past the size, or an empty range.
+    fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()>;
+
+    /// Fill each buffer with the bytes starting at its offset.
+    ///
+    /// This is used for [`NpyReader::read_many_at`][`crate::NpyReader::read_many_at`] and
+    /// [`NpyReader::read_ranges_at`][`crate::NpyReader::read_ranges_at`].  The default implementation
+    /// calls [`Self::read_range`] for each buffer; a source can override it to fetch them together,
+    /// e.g. with a multipart range request.
+    fn read_ranges(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        requests.iter_mut().try_for_each(|(offset, buf)| self.read_range(*offset, buf))
+    }
+}
+
+impl<S: RangeSource + ?Sized> RangeSource for &mut S {
+    fn size(&mut self) -> io::Result<u64> {
+        (**self).size()
+    }
+
+    fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        (**self).read_range(offset, buf)
+    }
+
+    fn read_ranges(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        (**self).read_ranges(requests)
+    }
+}
+
+/// A [`Read`] and [`Seek`] adapter for a [`RangeSource`], which fetches the bytes as they are read.
+///
+/// Small reads are served from a block that is fetched at once (64 KiB by default), so that e.g.
+/// reading the header of an NPY file is a single request, as is reading the central directory of an
+/// NPZ file with [`NpzArchive::new`][`crate::npz::NpzArchive::new`].  Reads of at least a block are
+/// fetched directly.  It also implements [`ReadAtMany`], for reading scattered records with
+/// [`NpyReader::read_many_at`][`crate::NpyReader::read_many_at`] without fetching the rest of the file.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{RangeReader, RangeSource};
+///
+/// // a stand-in for a remote file, which counts the requests made
+/// struct Remote { bytes: Vec<u8>, requests: usize }
+///
+/// impl RangeSource for Remote {
+///     fn size(&mut self) -> std::io::Result<u64> {
+///         Ok(self.bytes.len() as u64)
+///     }
+///
+///     fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<()> {
+///         self.requests += 1;
+///         buf.copy_from_slice(&self.bytes[offset as usize..][..buf.len()]);
+///         Ok(())
+///     }
+/// }
+///
+/// let mut remote = Remote { bytes: std::fs::read("test-data/c-order.npy")?, requests: 0 };
+/// let reader = RangeReader::new(&mut remote)?.with_block_size(128);
+/// let npy = npyz::NpyFile::new(reader)?;
+/// assert_eq!(npy.shape(), &[2, 3, 4]);
+///
+/// let mut data = npy.data::<i64>().unwrap();
+/// assert_eq!(data.read_many_at(&[23, 0])?, vec![6, 1]);
+/// drop(data);
+/// // one block for the header, and one range for each record
+/// assert_eq!(remote.requests, 3);
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct RangeReader<S: RangeSource> {
+    source: S,
+    len: u64,
+    pos: u64,
+    block_size: usize,
+    // the most recently fetched block, and its offset
+    block: Vec<u8>,
+    block_start: u64,
+}
+
+impl<S: RangeSource> RangeReader<S> {
+    /// Create a reader at the start of the source, getting its size.
+    pub fn new(mut source: S) -> io::Result<Self> {
+        let len = source.size()?;
+        Ok(RangeReader { source, len, pos: 0, block_size: DEFAULT_BLOCK_SIZE, block: vec![], block_start: 0 })
+    }
+
+    /// Set the number of bytes fetched at once for small reads.
+    ///
+    /// Larger blocks mean fewer requests for small reads, at the cost of fetching more bytes that may
+    /// not be needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is zero.
+    pub fn with_block_size(mut self, bytes: usize) -> Self {
+        assert!(bytes > 0, "the block size must not be zero");
+        self.block_size = bytes;
+        self
+    }
+
+    /// Get the size of the source in bytes, as it was when the reader was created.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the source has no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get a reference to the source.
+    pub fn get_ref(&self) -> &S {
+        &self.source
+    }
+
+    /// Get the source back.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: RangeSource> Read for RangeReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.len.saturating_sub(self.pos);
+        let len = buf.len().min(usize::try_from(available).unwrap_or(usize::MAX));
+        if len == 0 {
+            return Ok(0);
+        }
+        let cached = self.block_start..self.block_start + self.block.len() as u64;
+        if !cached.contains(&self.pos) {
+            if len >= self.block_size {
+                self.source.read_range(self.pos, &mut buf[..len])?;
+                self.pos += len as u64;
+                return Ok(len);
+            }
+            let block_len = self.block_size.min(usize::try_from(available).unwrap_or(usize::MAX));
+            self.block.resize(block_len, 0);
+            if let Err(e) = self.source.read_range(self.pos, &mut self.block) {
+                self.block.clear();
+                return Err(e);
+            }
+            self.block_start = self.pos;
+        }
+        let start = (self.pos - self.block_start) as usize;
+        let n = len.min(self.block.len() - start);
+        buf[..n].copy_from_slice(&self.block[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<S: RangeSource> Seek for RangeReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        match new {
+            Some(new) => {
+                self.pos = new;
+                Ok(new)
+            },
+            None => Err(Error::InvalidInput("invalid seek to a negative or overflowing position".to_string()).into()),
+        }
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl<S: RangeSource> ReadAtMany for RangeReader<S> {
+    fn read_exact_at_many(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        let mut requests = requests.iter_mut()
+            .filter(|(_, buf)| !buf.is_empty())
+            .map(|(offset, buf)| (*offset, &mut buf[..]))
+            .collect::<Vec<_>>();
+        if requests.iter().any(|(offset, buf)| offset.checked_add(buf.len() as u64).map_or(true, |end| end > self.len)) {
+            return Err(Error::Truncated.into());
+        }
+        self.source.read_ranges(&mut requests)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counted {
+        bytes: Vec<u8>,
+        // the ranges that were requested
+        requests: Vec<(u64, usize)>,
+    }
+
+    impl RangeSource for Counted {
+        fn size(&mut self) -> io::Result<u64> {
+            Ok(self.bytes.len() as u64)
+        }
+
+        fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+            assert!(!buf.is_empty());
+            self.requests.push((offset, buf.len()));
+            buf.copy_from_slice(&self.bytes[offset as usize..][..buf.len()]);
+            Ok(())
+        }
+    }
+
+    fn source(len: usize) -> Counted {
+        Counted { bytes: (0..len).map(|i| i as u8).collect(), requests: vec![] }
+    }
+
+    #[test]
+    fn reads_blocks() {
+        let mut source = source(1000);
+        let mut reader = RangeReader::new(&mut source).unwrap().with_block_size(100);
+        let mut buf = [0; 30];
+        reader.read_exact(&mut buf).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        // crosses into the next block
+        reader.read_exact(&mut buf).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[0], 90);
+
+        // large reads are fetched directly
+        reader.seek(SeekFrom::Start(500)).unwrap();
+        let mut big = [0; 200];
+        reader.read_exact(&mut big).unwrap();
+        assert_eq!(big[199], (699 % 256) as u8);
+
+        // the last block is cut off at the end
+        reader.seek(SeekFrom::End(-10)).unwrap();
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest.len(), 10);
+        assert_eq!(source.requests, vec![(0, 100), (100, 100), (500, 200), (990, 10)]);
+    }
+
+    #[test]
+    fn read_at_many() {
+        let mut source = source(100);
+        let mut reader = RangeReader::new(&mut source).unwrap();
+        let (mut a, mut b) = ([0; 4], [0; 2]);
+        reader.read_exact_at_many(&mut [(10, &mut a[..]), (98, &mut b[..])]).unwrap();
+        assert_eq!((a, b), ([10, 11, 12, 13], [98, 99]));
+
+        let err = reader.read_exact_at_many(&mut [(99, &mut a[..])]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(source.requests, vec![(10, 4), (98, 2)]);
+    }
+}
@@ -1,8 +1,16 @@
 use std::collections::HashMap;
 use std::io;
+use std::ops::Range;
 
 use crate::header::{Value, DType, read_header, convert_value_to_shape};
-use crate::serialize::{Deserialize, TypeRead, DTypeError};
+use crate::diagnostics::{self, Diagnostic, DiagnosticSink};
+use crate::aligned::AlignedVec;
+use crate::error::Error;
+use crate::read_at::{ReadAt, ReadAtCursor, ReadAtMany};
+use crate::shape::Shape;
+use crate::dynamic::DynValue;
+use crate::cast::{Casting, CastReader, CastTarget};
+use crate::serialize::{Deserialize, TypeRead, DTypeError, NativeLayout};
 
 /// Object for reading an `npy` file.
 ///
@@ -123,6 +131,7 @@ use crate::serialize::{Deserialize, TypeRead, DTypeError};
 pub struct NpyFile<R: io::Read> {
     header: NpyHeader,
     reader: R,
+    buffer_size: usize,
 }
 
 /// Represents the parsed header portion of an `npy` file.
@@ -160,6 +169,12 @@ pub struct NpyHeader {
     n_records: u64,
     /// Item size in bytes.
     item_size: usize,
+    /// Keys in the header dict other than the three standard ones, with values as Python literals.
+    extra_keys: Vec<(String, String)>,
+    /// The exact bytes of the header, if it was read from a file.
+    raw_bytes: Option<Vec<u8>>,
+    /// Name of the array in an NPZ file, for error messages.
+    member: Option<String>,
 }
 
 impl NpyHeader {
@@ -169,7 +184,228 @@ impl NpyHeader {
     /// function returns `Ok(_)`, the reader will have been advanced to the
     /// beginning of the raw data bytes.
     pub fn from_reader(r: impl io::Read) -> io::Result<NpyHeader> {
-        NpyHeader::read_and_interpret(r)
+        NpyHeader::read_and_interpret(r, &ReadOptions::default())
+    }
+
+    /// Parse a header from the reader for an NPY file, enforcing the limits in [`ReadOptions`].
+    pub fn from_reader_with_options(r: impl io::Read, options: &ReadOptions) -> io::Result<NpyHeader> {
+        NpyHeader::read_and_interpret(r, options)
+    }
+}
+
+/// Limits on the headers of NPY files, to protect against malicious input, and a callback for
+/// [diagnostics][`Diagnostic`] about files that can be read but look unusual.
+///
+/// Every header is checked against these limits before it is interpreted.  By default, the limits are large
+/// enough for any file that numpy would produce, while preventing an attacker from causing excessive
+/// memory usage or a stack overflow.  Use [`NpyFile::with_options`] or [`NpyHeader::from_reader_with_options`]
+/// to change them.
+///
+/// Exceeding a limit produces [`Error::LimitExceeded`].
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let options = npyz::ReadOptions::new().max_header_len(4096).max_ndim(4);
+/// let bytes = std::fs::read("test-data/c-order.npy")?;
+/// let npy = npyz::NpyFile::with_options(&bytes[..], &options)?;
+/// # let _ = npy;
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOptions {
+    max_header_len: usize,
+    max_ndim: usize,
+    max_fields: usize,
+    max_depth: usize,
+    max_objects: usize,
+    buffer_size: usize,
+    memory_budget: Option<usize>,
+    strict_header: bool,
+    diagnostics: Option<DiagnosticSink>,
+}
+
+pub(crate) const DEFAULT_BUFFER_SIZE: usize = 1 << 16;
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            max_header_len: 1 << 20,
+            max_ndim: 64,
+            max_fields: 100_000,
+            max_depth: 32,
+            max_objects: 10_000_000,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            memory_budget: None,
+            strict_header: false,
+            diagnostics: None,
+        }
+    }
+}
+
+impl ReadOptions {
+    /// Get the default limits.
+    pub fn new() -> Self {
+        ReadOptions::default()
+    }
+
+    /// Set the maximum length in bytes of the header text.  The default is 1 MiB.
+    pub fn max_header_len(mut self, len: usize) -> Self {
+        self.max_header_len = len;
+        self
+    }
+
+    /// Set the maximum number of dimensions in the shape.  The default is 64.
+    pub fn max_ndim(mut self, ndim: usize) -> Self {
+        self.max_ndim = ndim;
+        self
+    }
+
+    /// Set the maximum total number of fields in the dtype, including fields of nested records.
+    /// The default is 100000.
+    pub fn max_fields(mut self, count: usize) -> Self {
+        self.max_fields = count;
+        self
+    }
+
+    /// Set the maximum depth of nested records and arrays in the dtype.  The default is 32.
+    ///
+    /// This also limits the nesting of lists, tuples and dicts in the elements of an
+    /// [`ObjectArray`][`crate::pickle::ObjectArray`].
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    #[cfg_attr(not(feature = "pickle"), allow(dead_code))]
+    pub(crate) fn check_depth(&self, what: &'static str, depth: usize) -> io::Result<()> {
+        self.check(what, depth, self.max_depth)
+    }
+
+    /// Set the maximum total number of Python values built when reading an
+    /// [`ObjectArray`][`crate::pickle::ObjectArray`], counting every element of every list, tuple and dict.
+    /// The default is 10000000.
+    ///
+    /// A pickle can refer to the same object many times, so a small file can describe a huge number of values.
+    pub fn max_objects(mut self, count: usize) -> Self {
+        self.max_objects = count;
+        self
+    }
+
+    #[cfg_attr(not(feature = "pickle"), allow(dead_code))]
+    pub(crate) fn check_objects(&self, count: usize) -> io::Result<()> {
+        self.check("number of objects", count, self.max_objects)
+    }
+
+    /// Set the size in bytes of the buffers used for reading data.  The default is 64 KiB.
+    ///
+    /// This is the amount of data copied at a time by [`NpyFile::into_vec`] when it can read the elements
+    /// without decoding them, and the capacity of the `BufReader` created by functions that open a file
+    /// by path, such as [`NpzArchive::open_with_options`][`crate::npz::NpzArchive::open_with_options`].
+    /// Larger buffers mean fewer calls to the underlying reader, which mostly helps slow or high-latency sources.
+    pub fn buffer_size(mut self, bytes: usize) -> Self {
+        self.buffer_size = bytes;
+        self
+    }
+
+    pub(crate) fn get_buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// Limit the total size in bytes of the arrays that are decoded at the same time.  By default, there is no limit.
+    ///
+    /// This applies to [`NpzArchive::load_all_parallel`][`crate::npz::NpzArchive::load_all_parallel`], where each
+    /// thread waits to start on an array until the arrays being decoded by the other threads, together with the
+    /// new one, fit in the budget.  An array larger than the whole budget is decoded while no other array is.
+    /// This bounds the peak memory used by decompression buffers and partially filled arrays on top of the
+    /// arrays that have already been loaded, at the cost of less parallelism.
+    ///
+    /// The size of an array is estimated from its header and its uncompressed size in the archive.
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    #[cfg_attr(not(feature = "npz"), allow(dead_code))]
+    pub(crate) fn get_memory_budget(&self) -> Option<usize> {
+        self.memory_budget
+    }
+
+    /// Reject headers that do not follow the NPY format specification exactly.  By default, this is off.
+    ///
+    /// Various writers produce headers that numpy can read, but that are not quite as the format specifies.
+    /// By default these are accepted (and some are reported as [`Diagnostic`]s).  In strict mode, reading
+    /// fails with [`Error::BadHeader`] if:
+    ///
+    /// * the dict has keys other than `'descr'`, `'fortran_order'` and `'shape'`, or has a key twice,
+    /// * the shape is not a tuple,
+    /// * the header is not padded with spaces and a newline up to a multiple of 64 bytes, or
+    /// * the header of a version 1.0 or 2.0 file is not ASCII.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let options = npyz::ReadOptions::new().strict_header(true);
+    /// let bytes = std::fs::read("test-data/c-order.npy")?;
+    /// assert!(npyz::NpyFile::with_options(&bytes[..], &options).is_ok());
+    /// # Ok(()) }
+    /// ```
+    pub fn strict_header(mut self, strict: bool) -> Self {
+        self.strict_header = strict;
+        self
+    }
+
+    pub(crate) fn is_strict_header(&self) -> bool {
+        self.strict_header
+    }
+
+    /// Call a function for every [`Diagnostic`] found while reading.
+    ///
+    /// Diagnostics never cause reading to fail.  By default, they are discarded.
+    pub fn on_diagnostic(mut self, func: impl Fn(&Diagnostic) + Send + Sync + 'static) -> Self {
+        self.diagnostics = Some(DiagnosticSink::new(func));
+        self
+    }
+
+    pub(crate) fn emit(&self, diagnostic: Diagnostic) {
+        if let Some(sink) = &self.diagnostics {
+            sink.emit(diagnostic);
+        }
+    }
+
+    pub(crate) fn check_ndim(&self, ndim: usize) -> io::Result<()> {
+        self.check("number of dimensions", ndim, self.max_ndim)
+    }
+
+    pub(crate) fn check(&self, what: &'static str, value: usize, max: usize) -> io::Result<()> {
+        match value <= max {
+            true => Ok(()),
+            false => Err(Error::LimitExceeded { what, value: value as u64, max: max as u64 }.into()),
+        }
+    }
+
+    pub(crate) fn check_header_len(&self, len: usize) -> io::Result<()> {
+        self.check("header length", len, self.max_header_len)
+    }
+
+    // The literal is nested at least two levels per level of the dtype, plus the dict and the list of fields.
+    pub(crate) fn max_literal_depth(&self) -> usize {
+        self.max_depth.saturating_mul(2).saturating_add(2)
+    }
+
+    fn check_dtype(&self, dtype: &DType) -> io::Result<()> {
+        fn visit(dtype: &DType, depth: usize, num_fields: &mut usize) -> usize {
+            match dtype {
+                DType::Plain(_) => depth,
+                DType::Array(_, inner) => visit(inner, depth + 1, num_fields),
+                DType::Record(fields) => {
+                    *num_fields += fields.len();
+                    fields.iter().map(|field| visit(&field.dtype, depth + 1, num_fields)).max().unwrap_or(depth + 1)
+                },
+            }
+        }
+        let mut num_fields = 0;
+        let depth = visit(dtype, 0, &mut num_fields);
+        self.check("dtype nesting depth", depth, self.max_depth)?;
+        self.check("number of fields", num_fields, self.max_fields)
     }
 }
 
@@ -177,12 +413,16 @@ impl NpyHeader {
 /// data portion of an NPY file.
 ///
 /// This type is an iterator of `Result<T>`, with some additional methods for random access
-/// when the underlying reader is seekable.
+/// when the underlying reader is seekable.  It is an [`ExactSizeIterator`] (on 64-bit platforms), and when
+/// the underlying reader is seekable, a [`DoubleEndedIterator`], so that adapters such as [`Iterator::rev`] work.
 pub struct NpyReader<T: Deserialize, R: io::Read> {
     header: NpyHeader,
     type_reader: <T as Deserialize>::TypeReader,
+    buffer_size: usize,
     // stateful parts, put together like this to remind you to always update them in sync
     reader_and_current_index: (R, u64),
+    // the records from this index on have been read from the back
+    end_index: u64,
 }
 
 /// Legacy type for reading `npy` files.
@@ -211,18 +451,250 @@ impl Order {
     pub(crate) fn from_fortran_order(fortran_order: bool) -> Order {
         if fortran_order { Order::Fortran } else { Order::C }
     }
+
+    /// Get the other order.
+    pub fn reversed(self) -> Order {
+        match self {
+            Order::C => Order::Fortran,
+            Order::Fortran => Order::C,
+        }
+    }
+}
+
+const DEFAULT_TILE_SIZE: u64 = 32;
+
+/// Iterator that visits every element of an array in the opposite [`Order`] to how it is stored,
+/// one cache-sized tile at a time.
+///
+/// Each item is a pair `(stored, reordered)` of the element's flat index in its stored order and
+/// its flat index in the other order.  For instance, iterating over a C-order array yields the index
+/// of every element in the data and the position it would have in a Fortran-order copy.  This is
+/// enough to consume the data in the other orientation (or to write a transposed copy) without
+/// either the reads or the writes jumping through memory a whole row apart for every element.
+///
+/// The array is divided into tiles of [`Self::with_tile_size`] elements along each axis.  Tiles are
+/// visited in the reordered order, as are the elements within each tile, so both indices stay within a
+/// small region of memory for a while.  Use a tile size of `1` to visit the elements in exactly the reordered order.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let npy = npyz::NpyFile::new(std::fs::File::open("test-data/c-order.npy")?)?;
+/// let indices = npy.tiled_indices();
+/// let data = npy.into_vec::<i64>()?;
+///
+/// // the same elements as the Fortran-order file, without transposing `data`
+/// let mut fortran = vec![0; data.len()];
+/// for (stored, reordered) in indices {
+///     fortran[reordered as usize] = data[stored as usize];
+/// }
+/// let expected = npyz::NpyFile::new(std::fs::File::open("test-data/f-order.npy")?)?.into_vec::<i64>()?;
+/// assert_eq!(fortran, expected);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TiledIndices {
+    shape: Vec<u64>,
+    stored_strides: Vec<u64>,
+    reordered_strides: Vec<u64>,
+    // axes from fastest to slowest in the reordered order
+    axes: Vec<usize>,
+    tile_size: u64,
+    tile_start: Vec<u64>,
+    offset: Vec<u64>,
+    done: bool,
+}
+
+impl TiledIndices {
+    /// Visit the elements of an array with the given shape, stored in the given order.
+    ///
+    /// Returns `None` if the number of elements overflows a `u64`.
+    pub fn new(shape: &[u64], stored: Order) -> Option<Self> {
+        shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim))?;
+        let stored_strides = strides(stored, shape)?;
+        let reordered_strides = strides(stored.reversed(), shape)?;
+        let axes = match stored.reversed() {
+            Order::C => (0..shape.len()).rev().collect(),
+            Order::Fortran => (0..shape.len()).collect(),
+        };
+        Some(TiledIndices {
+            done: shape.contains(&0),
+            tile_start: vec![0; shape.len()],
+            offset: vec![0; shape.len()],
+            shape: shape.to_vec(),
+            stored_strides,
+            reordered_strides,
+            axes,
+            tile_size: DEFAULT_TILE_SIZE,
+        })
+    }
+
+    /// Set the number of elements along each axis of a tile.  The default is 32.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero, or if iteration has already begun.
+    pub fn with_tile_size(mut self, size: u64) -> Self {
+        assert!(size > 0, "tile size must be nonzero");
+        assert!(self.offset.iter().chain(&self.tile_start).all(|&x| x == 0), "iteration has already begun");
+        self.tile_size = size;
+        self
+    }
+
+    // Advance to the next element within the tile, or else to the first element of the next tile.
+    fn advance(&mut self) {
+        for &axis in &self.axes {
+            let tile_len = self.tile_size.min(self.shape[axis] - self.tile_start[axis]);
+            self.offset[axis] += 1;
+            if self.offset[axis] < tile_len {
+                return;
+            }
+            self.offset[axis] = 0;
+        }
+        for &axis in &self.axes {
+            self.tile_start[axis] += self.tile_size;
+            if self.tile_start[axis] < self.shape[axis] {
+                return;
+            }
+            self.tile_start[axis] = 0;
+        }
+        self.done = true;
+    }
+}
+
+impl Iterator for TiledIndices {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<(u64, u64)> {
+        if self.done {
+            return None;
+        }
+        let (mut stored, mut reordered) = (0, 0);
+        for axis in 0..self.shape.len() {
+            let index = self.tile_start[axis] + self.offset[axis];
+            stored += index * self.stored_strides[axis];
+            reordered += index * self.reordered_strides[axis];
+        }
+        self.advance();
+        Some((stored, reordered))
+    }
+}
+
+impl std::iter::FusedIterator for TiledIndices {}
+
+/// Iterator over the multidimensional index of every element of an array, in the order the elements are stored,
+/// like numpy's `ndindex`.
+///
+/// Each item is the index as one entry per axis.  Zipping with the elements (from
+/// [`NpyFile::into_vec`], [`NpyFile::data`], or anything else in the stored order) pairs each element with
+/// its index; [`Self::with_values`] does just that.  A 0-dimensional array has a single element, at the empty index.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let npy = npyz::NpyFile::new(std::fs::File::open("test-data/c-order.npy")?)?;
+/// for (index, value) in npy.nd_indices().with_values(npy.into_vec::<i64>()?) {
+///     assert_eq!(index.len(), 3);
+///     if index == [1, 2, 3] {
+///         assert_eq!(value, 6);
+///     }
+/// }
+///
+/// let npy = npyz::NpyFile::new(std::fs::File::open("test-data/f-order.npy")?)?;
+/// let mut pairs = npy.nd_indices().with_values(npy.data::<i64>().unwrap());
+/// let (index, value) = pairs.nth(1).unwrap();
+/// assert_eq!((index, value?), (vec![1, 0, 0], 4));
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct NdIndices {
+    shape: Vec<u64>,
+    // axes from fastest to slowest
+    axes: Vec<usize>,
+    index: Vec<u64>,
+    remaining: u64,
+}
+
+impl NdIndices {
+    /// Visit the indices of an array with the given shape, stored in the given order.
+    ///
+    /// Returns `None` if the number of elements overflows a `u64`.
+    pub fn new(shape: &[u64], order: Order) -> Option<Self> {
+        let remaining = shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim))?;
+        let axes = match order {
+            Order::C => (0..shape.len()).rev().collect(),
+            Order::Fortran => (0..shape.len()).collect(),
+        };
+        Some(NdIndices { shape: shape.to_vec(), axes, index: vec![0; shape.len()], remaining })
+    }
+
+    /// Pair each index with the next of `values`, which should be in the stored order.
+    ///
+    /// Iteration stops at the end of the shorter of the two.
+    pub fn with_values<I: IntoIterator>(self, values: I) -> std::iter::Zip<Self, I::IntoIter> {
+        self.zip(values)
+    }
+}
+
+impl Iterator for NdIndices {
+    type Item = Vec<u64>;
+
+    fn next(&mut self) -> Option<Vec<u64>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let current = self.index.clone();
+        for &axis in &self.axes {
+            self.index[axis] += 1;
+            if self.index[axis] < self.shape[axis] {
+                break;
+            }
+            self.index[axis] = 0;
+        }
+        Some(current)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from(self.remaining) {
+            Ok(n) => (n, Some(n)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
 }
 
+impl std::iter::FusedIterator for NdIndices {}
+
 impl<R: io::Read> NpyFile<R> {
     /// Read the header of an `npy` file and construct an `NpyFile` for reading the data.
-    pub fn new(mut reader: R) -> io::Result<Self> {
-        let header = NpyHeader::read_and_interpret(&mut reader)?;
-        Ok(NpyFile { header, reader })
+    pub fn new(reader: R) -> io::Result<Self> {
+        NpyFile::with_options(reader, &ReadOptions::default())
+    }
+
+    /// Read the header of an `npy` file, enforcing the limits in [`ReadOptions`].
+    pub fn with_options(mut reader: R, options: &ReadOptions) -> io::Result<Self> {
+        let header = NpyHeader::read_and_interpret(&mut reader, options)?;
+        Ok(NpyFile { header, reader, buffer_size: options.buffer_size })
     }
 
     /// Construct from a previously parsed header and a reader for the raw data bytes.
     pub fn with_header(header: NpyHeader, data_reader: R) -> Self {
-        NpyFile { header, reader: data_reader }
+        NpyFile { header, reader: data_reader, buffer_size: DEFAULT_BUFFER_SIZE }
+    }
+
+    /// Set the size in bytes of the buffers used for reading data.  See [`ReadOptions::buffer_size`].
+    pub fn with_buffer_size(mut self, bytes: usize) -> Self {
+        self.buffer_size = bytes;
+        self
+    }
+
+    // Name errors after an array in an NPZ file.
+    pub(crate) fn with_member_name(mut self, name: &str) -> Self {
+        self.header.set_member(name);
+        self
+    }
+
+    // Box the reader, e.g. to keep files with different readers together.
+    pub(crate) fn boxed<'a>(self) -> NpyFile<Box<dyn io::Read + 'a>> where R: 'a {
+        NpyFile { header: self.header, reader: Box::new(self.reader), buffer_size: self.buffer_size }
     }
 
     /// Access the underlying [`NpyHeader`] object.
@@ -231,6 +703,16 @@ impl<R: io::Read> NpyFile<R> {
     }
 }
 
+impl<T: ReadAt> NpyFile<ReadAtCursor<T>> {
+    /// Read the header of an `npy` file from a source with positioned reads, such as a `&File`.
+    ///
+    /// The file has its own position in the source, so several threads can each read the same file
+    /// through their own `NpyFile` without any locking.  See [`ReadAtCursor`].
+    pub fn from_read_at(source: T) -> io::Result<Self> {
+        NpyFile::new(ReadAtCursor::new(source))
+    }
+}
+
 // Provided for backwards compatibility.
 impl<R: io::Read> std::ops::Deref for NpyFile<R> {
     type Target = NpyHeader;
@@ -251,6 +733,15 @@ impl NpyHeader {
         &self.shape
     }
 
+    /// Get the shape as a [`Shape`] with exactly `N` dimensions.
+    ///
+    /// Returns [`Error::InvalidData`] if the array has a different number of dimensions.
+    pub fn shape_as<const N: usize>(&self) -> io::Result<Shape<N>> {
+        Shape::try_from(&self.shape[..]).map_err(|_| Error::InvalidData(format!(
+            "expected a {}-dimensional array, got shape {:?}", N, self.shape,
+        )).into())
+    }
+
     /// Get strides for each of the dimensions.
     ///
     /// This is the amount by which the item index changes as you move along each dimension.
@@ -269,53 +760,337 @@ impl NpyHeader {
     pub fn len(&self) -> u64 {
         self.n_records
     }
+
+    /// Returns `true` if the array has no elements.  (i.e. some dimension of [`Self::shape`] is zero)
+    pub fn is_empty(&self) -> bool {
+        self.n_records == 0
+    }
+
+    /// Get the total number of elements in the file.  This is the same as [`Self::len`].
+    pub fn n_records(&self) -> u64 {
+        self.n_records
+    }
+
+    /// Get the number of bytes in each element.  (This is the size of [`Self::dtype`])
+    pub fn item_size(&self) -> usize {
+        self.item_size
+    }
+
+    /// Get the number of bytes of data in the file, after the header.
+    pub fn data_len_bytes(&self) -> u64 {
+        self.n_records * self.item_size as u64
+    }
+
+    /// Get the length in bytes of the header, from the magic string up to the start of the data.
+    ///
+    /// This is `None` for a header that was not read from a file.
+    pub fn header_len(&self) -> Option<u64> {
+        self.raw_bytes.as_ref().map(|raw| raw.len() as u64)
+    }
+
+    /// Get the byte offset of the data from the start of the file, e.g. for memory-mapping it or reading
+    /// records with positioned reads.  A complete file is `data_offset + data_len_bytes` long.
+    ///
+    /// This is the same as [`Self::header_len`], and is `None` for a header that was not read from a file.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let bytes = std::fs::read("test-data/c-order.npy")?;
+    /// let npy = npyz::NpyFile::new(&bytes[..])?;
+    /// let offset = npy.data_offset().unwrap();
+    /// assert_eq!(offset % 64, 0);
+    /// assert_eq!(offset + npy.data_len_bytes(), bytes.len() as u64);
+    /// # Ok(()) }
+    /// ```
+    pub fn data_offset(&self) -> Option<u64> {
+        self.header_len()
+    }
+
+    // Name errors after an array in an NPZ file.
+    pub(crate) fn set_member(&mut self, name: &str) {
+        self.member = Some(name.to_string());
+    }
+
+    /// Get a [`TiledIndices`] for visiting the elements in the opposite order to how they are stored.
+    pub fn tiled_indices(&self) -> TiledIndices {
+        TiledIndices::new(&self.shape, self.order).expect("size was checked when constructing the header")
+    }
+
+    /// Get an [`NdIndices`] for the index of each element, in the order they are stored.
+    pub fn nd_indices(&self) -> NdIndices {
+        NdIndices::new(&self.shape, self.order).expect("size was checked when constructing the header")
+    }
+
+    /// Get any keys of the header dict other than `'descr'`, `'fortran_order'` and `'shape'`, in the order they appeared.
+    ///
+    /// numpy never writes such keys, but other tools might.  Each value is given as the source text of a Python literal.
+    /// Use [`WriterBuilder::preserve_header`][`crate::WriterBuilder::preserve_header`] to write them back out.
+    pub fn extra_keys(&self) -> &[(String, String)] {
+        &self.extra_keys
+    }
+
+    /// Get the exact bytes of the header as they appeared in the file, from the magic string up to the start of the data.
+    ///
+    /// This is `None` for a header that was not read from a file.
+    pub fn raw_bytes(&self) -> Option<&[u8]> {
+        self.raw_bytes.as_deref()
+    }
 }
 
 impl<R: io::Read> NpyFile<R> {
     /// Read all elements into a flat `Vec`, in the order they are stored as.
     ///
     /// This is a convenience wrapper around [`Self::data`] and [`Iterator::collect`].
+    /// When the dtype is a primitive integer, float or complex type in the native byte order,
+    /// the data is instead copied directly into the `Vec` without decoding each element.
     pub fn into_vec<T: Deserialize>(self) -> io::Result<Vec<T>> {
-        match self.data() {
-            Ok(r) => r.collect(),
-            Err(e) => Err(invalid_data(e)),
+        let dtype = self.header.dtype.clone();
+        let member = self.header.member.clone();
+        match self.data::<T>() {
+            Ok(r) => match r.native_layout() {
+                Some(proof) => r.read_native_vec(proof),
+                None => r.collect(),
+            },
+            Err(e) => Err(member_context(&member, Error::dtype_mismatch::<T>(&dtype, e).into())),
         }
     }
 
+    /// Read all elements into an [`AlignedVec`] whose buffer is aligned to `align` bytes.
+    ///
+    /// This is like [`Self::into_vec`], including the bulk copy for primitive numbers in the native
+    /// byte order, but the data goes directly into memory with the requested alignment.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let bytes = std::fs::read("test-data/c-order.npy")?;
+    /// let data = npyz::NpyFile::new(&bytes[..])?.into_aligned_vec::<i64>(4096)?;
+    /// assert_eq!(data.as_ptr() as usize % 4096, 0);
+    /// assert_eq!(&data[..], &npyz::NpyFile::new(&bytes[..])?.into_vec::<i64>()?[..]);
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn into_aligned_vec<T: Deserialize>(self, align: usize) -> io::Result<AlignedVec<T>> {
+        let mut out = AlignedVec::new(align);
+        let dtype = self.header.dtype.clone();
+        let member = self.header.member.clone();
+        let reader = match self.data::<T>() {
+            Ok(r) => r,
+            Err(e) => return Err(member_context(&member, Error::dtype_mismatch::<T>(&dtype, e).into())),
+        };
+        match reader.native_layout() {
+            Some(proof) => reader.read_native_into(proof, &mut out)?,
+            None => {
+                for value in reader {
+                    out.push(value?);
+                }
+            },
+        }
+        Ok(out)
+    }
+
+    /// Copy the array to another NPY file without decoding any elements.
+    ///
+    /// The header is written exactly as it was read (or, for an `NpyFile` constructed by [`Self::with_header`],
+    /// regenerated with the same dtype, shape, order and extra keys), followed by the data bytes.
+    /// Unlike reading and rewriting the elements, this works for any dtype and preserves the formatting of the header.
+    ///
+    /// Exactly the number of data bytes described by the header are read.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let bytes = std::fs::read("test-data/c-order.npy")?;
+    /// let mut copy = vec![];
+    /// npyz::NpyFile::new(&bytes[..])?.copy_raw_to(&mut copy)?;
+    /// assert_eq!(copy, bytes);
+    /// # Ok(()) }
+    /// ```
+    pub fn copy_raw_to(self, mut writer: impl io::Write) -> io::Result<()> {
+        let NpyFile { header, reader, .. } = self;
+        let result = (|| {
+            match header.raw_bytes() {
+                Some(raw_bytes) => writer.write_all(raw_bytes)?,
+                None => writer.write_all(&crate::write::header_bytes(&header.dtype, header.order, &header.shape, &header.extra_keys)?)?,
+            }
+            // (this can't overflow; it was checked when constructing the header)
+            let data_len = header.item_size as u64 * header.n_records;
+            if io::copy(&mut reader.take(data_len), &mut writer)? < data_len {
+                return Err(Error::Truncated.into());
+            }
+            Ok(())
+        })();
+        result.map_err(|e| member_context(&header.member, e))
+    }
+
     /// Produce an [`NpyReader`] to begin reading elements, if `T` can be deserialized from the file's dtype.
     ///
     /// The returned type implements [`Iterator`]`<Item=io::Result<T>>`, and provides additional methods
     /// for random access when `R: Seek`.  See [`NpyReader`] for more details.
     pub fn data<T: Deserialize>(self) -> Result<NpyReader<T, R>, DTypeError> {
-        let NpyFile { reader, header } = self;
+        let NpyFile { reader, header, buffer_size } = self;
         let type_reader = T::reader(&header.dtype)?;
-        Ok(NpyReader { type_reader, header, reader_and_current_index: (reader, 0) })
+        let end_index = header.n_records;
+        Ok(NpyReader { type_reader, header, buffer_size, reader_and_current_index: (reader, 0), end_index })
     }
 
     /// Produce an [`NpyReader`] to begin reading elements, if `T` can be deserialized from the file's dtype.
     ///
     /// This fallible form of the function returns `self` on error, so that you can try again with a different `T`.
+    #[allow(clippy::result_large_err)]  // returning self is the whole point
     pub fn try_data<T: Deserialize>(self) -> Result<NpyReader<T, R>, Self> {
         let type_reader = match T::reader(&self.header.dtype) {
             Ok(r) => r,
             Err(_) => return Err(self),
         };
-        let NpyFile { reader, header } = self;
-        Ok(NpyReader { type_reader, header, reader_and_current_index: (reader, 0) })
+        let NpyFile { reader, header, buffer_size } = self;
+        let end_index = header.n_records;
+        Ok(NpyReader { type_reader, header, buffer_size, reader_and_current_index: (reader, 0), end_index })
+    }
+
+    /// Produce an [`NpyReader`] that decodes each element according to the file's dtype, as a [`DynValue`].
+    ///
+    /// This is for tools that cannot know the element type at compile time.  It is slower than [`Self::data`],
+    /// and fails only for half and extended precision floats.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::DynValue;
+    ///
+    /// // dtype [('a', '<i4'), ('b', '<f4'), ('c', '<i8')]
+    /// let bytes = std::fs::read("test-data/structured.npy")?;
+    /// let values = npyz::NpyFile::new(&bytes[..])?.data_dyn()?.collect::<std::io::Result<Vec<_>>>()?;
+    /// assert_eq!(values[0], DynValue::Record(vec![DynValue::Int(1), DynValue::Float(2.5), DynValue::Int(4)]));
+    /// # Ok(()) }
+    /// ```
+    pub fn data_dyn(self) -> io::Result<NpyReader<DynValue, R>> {
+        let dtype = self.header.dtype.clone();
+        let member = self.header.member.clone();
+        self.data::<DynValue>().map_err(|e| member_context(&member, Error::dtype_mismatch::<DynValue>(&dtype, e).into()))
+    }
+
+    /// Produce a [`CastReader`] that reads numbers of the file's dtype as `T`, if the [`Casting`] allows it.
+    ///
+    /// This is for files whose precision varies, e.g. when some were written with `float32` and others with
+    /// `float64`.  Only plain dtypes of booleans, integers and floats can be cast.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::Casting;
+    ///
+    /// // dtype '<i8'
+    /// let bytes = std::fs::read("test-data/c-order.npy")?;
+    /// let data = npyz::NpyFile::new(&bytes[..])?.data_cast::<f64>(Casting::Safe)?;
+    /// assert_eq!(data.len(), 24);
+    /// assert!(npyz::NpyFile::new(&bytes[..])?.data_cast::<u64>(Casting::Safe).is_err());
+    /// # Ok(()) }
+    /// ```
+    pub fn data_cast<T: CastTarget>(self, casting: Casting) -> io::Result<CastReader<T, R>> {
+        let member = self.header.member.clone();
+        CastReader::new(self, casting).map_err(|e| member_context(&member, e))
+    }
+
+    /// Read all elements as `T`, converting them from the file's dtype.  See [`Self::data_cast`].
+    pub fn into_vec_cast<T: CastTarget>(self, casting: Casting) -> io::Result<Vec<T>> {
+        self.data_cast(casting)?.collect()
+    }
+
+    /// Produce a [`RawRecords`] to read the bytes of each record, without deserializing them.
+    ///
+    /// This works for any dtype, for tools that pass records through without knowing what is in them.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let bytes = std::fs::read("test-data/c-order.npy")?;
+    /// let mut records = npyz::NpyFile::new(&bytes[..])?.raw_records();
+    /// assert_eq!(records.dtype().descr(), "'<i8'");
+    /// let mut count = 0;
+    /// while let Some(record) = records.next_record()? {
+    ///     assert_eq!(record.len(), 8);
+    ///     count += 1;
+    /// }
+    /// assert_eq!(count, 24);
+    /// # Ok(()) }
+    /// ```
+    pub fn raw_records(self) -> RawRecords<R> {
+        let NpyFile { reader, header, buffer_size } = self;
+        // at least one record, and a whole number of them
+        let capacity = match header.item_size {
+            0 => 0,
+            size => (buffer_size / size).max(1) * size,
+        };
+        RawRecords { header, reader, buf: vec![0; capacity], start: 0, end: 0, next_index: 0 }
     }
 }
 
+impl<R: io::Read + io::Seek> NpyFile<R> {
+    /// Read the elements in a hyper-rectangle of the array, skipping the rest of the file by seeking.
+    ///
+    /// This is a convenience wrapper around [`Self::data`] and [`NpyReader::read_slice`].
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// // a 2x3x4 array of the numbers 1 to 6, each repeated 4 times
+    /// let file = std::fs::File::open("test-data/c-order.npy")?;
+    /// let npy = npyz::NpyFile::new(file)?;
+    /// assert_eq!(npy.read_slice::<i64>(&[1..2, 0..3, 1..3])?, vec![4, 4, 5, 5, 6, 6]);
+    /// # Ok(()) }
+    /// ```
+    pub fn read_slice<T: Deserialize>(self, ranges: &[Range<u64>]) -> io::Result<Vec<T>> {
+        let dtype = self.header.dtype.clone();
+        let member = self.header.member.clone();
+        match self.data::<T>() {
+            Ok(mut r) => r.read_slice(ranges),
+            Err(e) => Err(member_context(&member, Error::dtype_mismatch::<T>(&dtype, e).into())),
+        }
+    }
+}
+
+pub(crate) const STANDARD_KEYS: &[&str] = &["descr", "fortran_order", "shape"];
+
+// The checks of `ReadOptions::strict_header` that aren't made while reading the header text.
+fn check_strict_header(entries: &[(String, Value)], raw_bytes: &[u8]) -> io::Result<()> {
+    for (index, (key, _)) in entries.iter().enumerate() {
+        if !STANDARD_KEYS.contains(&&key[..]) {
+            return Err(invalid_data(format_args!("unknown header key '{}'", key)));
+        }
+        if entries[..index].iter().any(|(k, _)| k == key) {
+            return Err(invalid_data(format_args!("duplicate header key '{}'", key)));
+        }
+    }
+    if let Some((_, shape)) = entries.iter().find(|(k, _)| k == "shape") {
+        if !matches!(shape, Value::Tuple(_)) {
+            return Err(invalid_data("shape must be a tuple"));
+        }
+    }
+    if !diagnostics::has_canonical_padding(raw_bytes) {
+        return Err(invalid_data("header is not padded with spaces and a newline to a multiple of 64 bytes"));
+    }
+    Ok(())
+}
+
 impl NpyHeader {
-    fn read_and_interpret(mut r: impl io::Read) -> io::Result<NpyHeader> {
-        let header = read_header(&mut r)?;
+    fn read_and_interpret(mut r: impl io::Read, options: &ReadOptions) -> io::Result<NpyHeader> {
+        let (header, raw_bytes) = read_header(&mut r, options)?;
 
-        let dict = match header {
+        let entries = match header {
             Value::Dict(dict) => dict
                 .into_iter()
                 .map(|(k, v)| Ok((k.as_string().ok_or(invalid_data("key is not string"))?.to_owned(), v)))
-                .collect::<io::Result<HashMap<String, Value>>>()?,
+                .collect::<io::Result<Vec<(String, Value)>>>()?,
             _ => return Err(invalid_data("expected a python dict literal")),
         };
+        if options.strict_header {
+            check_strict_header(&entries, &raw_bytes)?;
+        }
+        let extra_keys: Vec<(String, String)> = {
+            entries.iter()
+                .filter(|(k, _)| !STANDARD_KEYS.contains(&&k[..]))
+                .map(|(k, v)| (k.clone(), v.to_string()))
+                .collect()
+        };
+        let dict = entries.into_iter().collect::<HashMap<String, Value>>();
 
         let expect_key = |key: &str| {
             dict.get(key).ok_or_else(|| invalid_data(format_args!("dict is missing key '{}'", key)))
@@ -327,20 +1102,44 @@ impl NpyHeader {
         };
 
         let shape = convert_value_to_shape(expect_key("shape")?)?;
+        options.check_ndim(shape.len())?;
 
         let descr: &Value = expect_key("descr")?;
+        if crate::header::is_object_descr(descr) {
+            return Err(invalid_data("object arrays (dtype 'O') are saved as Python pickles, which can only be read by `npyz::pickle::ObjectArray` with the \"pickle\" feature"));
+        }
         let dtype = DType::from_descr(descr)?;
-
-        Self::from_parts(dtype, shape, order)
+        options.check_dtype(&dtype)?;
+
+        let mut header = Self::from_parts(dtype, shape, order)?;
+        if options.diagnostics.is_some() {
+            for (key, _) in &extra_keys {
+                options.emit(Diagnostic::UnknownHeaderKey { key: key.clone() });
+            }
+            diagnostics::check_header(&raw_bytes, &header.shape, header.item_size, |d| options.emit(d));
+        }
+        header.extra_keys = extra_keys;
+        header.raw_bytes = Some(raw_bytes);
+        Ok(header)
     }
 
     fn from_parts(dtype: DType, shape: Vec<u64>, order: Order) -> io::Result<NpyHeader> {
-        let n_records = shape.iter().product();
+        let too_large = || invalid_data(format_args!("array is too large: shape {:?} with dtype {}", shape, dtype.descr()));
         let item_size = dtype.num_bytes().ok_or_else(|| {
             invalid_data(format_args!("dtype is larger than usize!"))
         })?;
-        let strides = strides(order, &shape);
-        Ok(NpyHeader { dtype, shape, strides, order, n_records, item_size })
+        let n_records = match shape.contains(&0) {
+            true => 0,
+            false => {
+                let n_records = shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim)).ok_or_else(too_large)?;
+                // byte offsets into the data must fit in a seek offset
+                n_records.checked_mul(item_size as u64).filter(|&n| n <= i64::MAX as u64).ok_or_else(too_large)?;
+                n_records
+            },
+        };
+        // (the strides are computed from partial products, which may overflow even if the total is zero)
+        let strides = strides(order, &shape).ok_or_else(too_large)?;
+        Ok(NpyHeader { dtype, shape, strides, order, n_records, item_size, extra_keys: vec![], raw_bytes: None, member: None })
     }
 }
 
@@ -350,6 +1149,14 @@ impl<T: Deserialize, R: io::Read> NpyReader<T, R> {
         &self.reader_and_current_index.0
     }
 
+    pub(crate) fn reader_mut(&mut self) -> &mut R {
+        &mut self.reader_and_current_index.0
+    }
+
+    pub(crate) fn header(&self) -> &NpyHeader {
+        &self.header
+    }
+
     /// Get the dtype as written in the file.
     pub fn dtype(&self) -> DType {
         self.header.dtype.clone()
@@ -366,9 +1173,21 @@ impl<T: Deserialize, R: io::Read> NpyReader<T, R> {
         self.header.n_records
     }
 
-    /// Get the remaining number of records that lie after the read cursor.
+    /// Get the remaining number of records that lie after the read cursor, and have not been read
+    /// from the back by [`DoubleEndedIterator::next_back`].
     pub fn len(&self) -> u64 {
-        self.header.n_records - self.reader_and_current_index.1
+        self.end_index.saturating_sub(self.reader_and_current_index.1)
+    }
+
+    /// Returns `true` if there are no records left to read.  (i.e. [`Self::len`] is zero)
+    pub fn is_empty(&self) -> bool {
+        NpyReader::len(self) == 0
+    }
+
+    /// Set the size in bytes of the buffers used for reading data.  See [`ReadOptions::buffer_size`].
+    pub fn with_buffer_size(mut self, bytes: usize) -> Self {
+        self.buffer_size = bytes;
+        self
     }
 }
 
@@ -377,15 +1196,26 @@ impl<R: io::Read, T: Deserialize> NpyReader<T, R> where R: io::Seek {
     /// Move the read cursor to the item at the given index.
     ///
     /// Be aware that this will affect [`Self::len`], which is always computed
-    /// from the current position.
+    /// from the current position.  Items that were read from the back by
+    /// [`DoubleEndedIterator::next_back`] can be read again after seeking, and are counted by [`Self::len`].
     /// Seeking to [`Self::total_len`] is well defined.
     ///
     /// # Panics
     ///
-    /// Panics if the index is greater than [`Self::total_len`].
+    /// Panics if the index is greater than [`Self::total_len`].  See [`Self::try_seek_to`].
     pub fn seek_to(&mut self, index: u64) -> io::Result<()> {
         let len = self.total_len();
         assert!(index <= len, "index out of bounds for seeking (the index is {} but the len is {})", index, len);
+        self.try_seek_to(index)
+    }
+
+    /// Move the read cursor to the item at the given index, or return [`Error::IndexOutOfBounds`] if
+    /// the index is greater than [`Self::total_len`].
+    pub fn try_seek_to(&mut self, index: u64) -> io::Result<()> {
+        let len = self.total_len();
+        if index > len {
+            return Err(Error::IndexOutOfBounds { index, len }.into());
+        }
 
         let (reader, current_index) = &mut self.reader_and_current_index;
         let delta = index as i64 - *current_index as i64;
@@ -393,6 +1223,7 @@ impl<R: io::Read, T: Deserialize> NpyReader<T, R> where R: io::Seek {
             reader.seek(io::SeekFrom::Current(delta * self.header.item_size as i64))?;
             *current_index = index;
         }
+        self.end_index = len;
         Ok(())
     }
 
@@ -400,13 +1231,226 @@ impl<R: io::Read, T: Deserialize> NpyReader<T, R> where R: io::Seek {
     ///
     /// # Panics
     ///
-    /// Panics if the index is out of bounds. (`>=` to [`Self::total_len`]).
+    /// Panics if the index is out of bounds. (`>=` to [`Self::total_len`]).  See [`Self::try_read_at`].
     pub fn read_at(&mut self, index: u64) -> io::Result<T> {
         let len = self.total_len();
         assert!(index < len, "index out of bounds for reading (the index is {} but the len is {})", index, len);
+        self.try_read_at(index)
+    }
+
+    /// Read a single item at the given position, or return [`Error::IndexOutOfBounds`] if the index
+    /// is out of bounds.
+    pub fn try_read_at(&mut self, index: u64) -> io::Result<T> {
+        let len = self.total_len();
+        if index >= len {
+            return Err(Error::IndexOutOfBounds { index, len }.into());
+        }
+
+        self.try_seek_to(index)?;
+        self.next().expect("index was checked")
+    }
+
+    /// Read the items in a range of positions, leaving the cursor at the position after it.
+    ///
+    /// With a file in C order, this can fetch whole rows (or blocks of rows) of an n-d array, whose items
+    /// are contiguous.  Returns [`Error::IndexOutOfBounds`] if the range ends after [`Self::total_len`], or
+    /// [`Error::InvalidInput`] if it starts after its end.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let file = std::io::BufReader::new(std::fs::File::open("test-data/c-order.npy")?);
+    /// let mut reader = npyz::NpyFile::new(file)?.data::<i64>().unwrap();
+    /// // the row at [1, 2, ..] of an array of shape [2, 3, 4]
+    /// assert_eq!(reader.read_range(20..24)?, vec![6, 6, 6, 6]);
+    /// assert_eq!(reader.read_range(0..2)?, vec![1, 1]);
+    /// assert_eq!(reader.next().unwrap()?, 1);
+    /// # Ok(()) }
+    /// ```
+    pub fn read_range(&mut self, range: Range<u64>) -> io::Result<Vec<T>> {
+        let len = self.total_len();
+        if range.start > range.end {
+            return Err(Error::InvalidInput(format!("range {:?} starts after its end", range)).into());
+        }
+        if range.end > len {
+            return Err(Error::IndexOutOfBounds { index: range.end, len }.into());
+        }
+
+        self.try_seek_to(range.start)?;
+        self.by_ref().take((range.end - range.start) as usize).collect()
+    }
+
+    /// Read the elements in a hyper-rectangle of the array, given as a range of indices along each axis.
+    ///
+    /// Each contiguous run of elements in the slice is read after seeking to its start, so that the
+    /// rest of the file is never read.  The elements are returned in the order of the file (C or Fortran,
+    /// see [`NpyHeader::order`]), and the read cursor is left after the last of them.
+    ///
+    /// Returns [`Error::InvalidInput`] if the number of ranges is not the number of dimensions or a range
+    /// starts after its end, and [`Error::IndexOutOfBounds`] if a range ends after the length of its axis.
+    pub fn read_slice(&mut self, ranges: &[Range<u64>]) -> io::Result<Vec<T>> {
+        let header = &self.header;
+        if ranges.len() != header.shape.len() {
+            return Err(Error::InvalidInput(format!(
+                "got {} ranges for an array of shape {:?}", ranges.len(), header.shape,
+            )).into());
+        }
+        for (range, &len) in ranges.iter().zip(&header.shape) {
+            if range.start > range.end {
+                return Err(Error::InvalidInput(format!("range {:?} starts after its end", range)).into());
+            }
+            if range.end > len {
+                return Err(Error::IndexOutOfBounds { index: range.end, len }.into());
+            }
+        }
+        let slice_len = ranges.iter().map(|range| range.end - range.start).product::<u64>();
+        if slice_len == 0 {
+            return Ok(vec![]);
+        }
+
+        // axes from the slowest to the fastest varying in the file
+        let mut axes = ranges.iter().cloned().zip(header.shape.iter().copied()).zip(header.strides.iter().copied()).collect::<Vec<_>>();
+        if header.order == Order::Fortran {
+            axes.reverse();
+        }
+        // the elements of the last axis that is not taken in full, and all of the axes after it,
+        // are contiguous in the file
+        let num_full = axes.iter().rev().take_while(|((range, len), _)| *range == (0..*len)).count();
+        let (outer, inner) = axes.split_at((axes.len() - num_full).saturating_sub(1));
+        let run_len = inner.iter().map(|((range, _), _)| range.end - range.start).product::<u64>();
+        let inner_start = inner.iter().map(|((range, _), stride)| range.start * stride).sum::<u64>();
+
+        let mut out = Vec::with_capacity(usize::try_from(slice_len).map_err(|_| {
+            Error::InvalidData(format!("slice {:?} is too large to read into memory", ranges))
+        })?);
+        let mut index = outer.iter().map(|((range, _), _)| range.start).collect::<Vec<_>>();
+        loop {
+            let start = inner_start + index.iter().zip(outer).map(|(i, (_, stride))| i * stride).sum::<u64>();
+            out.extend(self.read_range(start..start + run_len)?);
+
+            // advance to the next run, like an odometer
+            let mut axis = index.len();
+            loop {
+                if axis == 0 {
+                    return Ok(out);
+                }
+                axis -= 1;
+                let ((range, _), _) = &outer[axis];
+                index[axis] += 1;
+                if index[axis] < range.end {
+                    break;
+                }
+                index[axis] = range.start;
+            }
+        }
+    }
+}
+
+/// # Batched random access methods
+impl<R: ReadAtMany, T: Deserialize> NpyReader<T, R> {
+    /// Read the items at several positions, without moving the read cursor.
+    ///
+    /// All of the reads are handed to [`ReadAtMany::read_exact_at_many`] at once, so that a source
+    /// like [`UringFile`][`crate::UringFile`] can have them in flight together.  The items are returned
+    /// in the order of `indices`, which may repeat.  Returns [`Error::IndexOutOfBounds`] if any index is
+    /// `>=` [`Self::total_len`].
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let file = std::fs::File::open("test-data/c-order.npy")?;
+    /// let mut reader = npyz::NpyFile::new(file)?.data::<i64>().unwrap();
+    /// assert_eq!(reader.read_many_at(&[23, 0, 5])?, vec![6, 1, 2]);
+    /// assert_eq!(reader.next().unwrap()?, 1);
+    /// # Ok(()) }
+    /// ```
+    pub fn read_many_at(&mut self, indices: &[u64]) -> io::Result<Vec<T>> {
+        let len = self.total_len();
+        if let Some(&index) = indices.iter().find(|&&index| index >= len) {
+            return Err(Error::IndexOutOfBounds { index, len }.into());
+        }
+        let size = self.header.item_size;
+        let base = self.data_start()?;
+        let offsets = indices.iter().map(|&index| base + index * size as u64);
+
+        match self.native_layout() {
+            Some(proof) => {
+                let mut out = zeroed_records(proof, indices.len());
+                let mut requests = offsets.zip(records_as_bytes_mut(proof, &mut out).chunks_exact_mut(size)).collect::<Vec<_>>();
+                self.read_requests(&mut requests)?;
+                Ok(out)
+            },
+            None => {
+                // (records of a zero-sized dtype have no bytes to read)
+                let mut bytes = vec![0; indices.len() * size];
+                let mut requests = offsets.zip(bytes.chunks_exact_mut(size.max(1))).collect::<Vec<_>>();
+                self.read_requests(&mut requests)?;
+                indices.iter().zip(bytes.chunks(size.max(1)).chain(std::iter::repeat(&[][..])))
+                    .map(|(&index, mut record)| self.type_reader.read_one(&mut record).map_err(|e| record_error(&self.header, index, e)))
+                    .collect()
+            },
+        }
+    }
+
+    /// Read the items in several ranges of positions, without moving the read cursor.
+    ///
+    /// This is the chunked counterpart of [`Self::read_many_at`]: each range is read as one request,
+    /// and all of the requests are handed to the source at once.  Returns [`Error::IndexOutOfBounds`] if a range
+    /// ends after [`Self::total_len`], or [`Error::InvalidInput`] if a range starts after its end.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let file = std::fs::File::open("test-data/c-order.npy")?;
+    /// let mut reader = npyz::NpyFile::new(file)?.data::<i64>().unwrap();
+    /// assert_eq!(reader.read_ranges_at(&[18..22, 2..4])?, vec![vec![5, 5, 6, 6], vec![1, 1]]);
+    /// # Ok(()) }
+    /// ```
+    pub fn read_ranges_at(&mut self, ranges: &[Range<u64>]) -> io::Result<Vec<Vec<T>>> {
+        let len = self.total_len();
+        for range in ranges {
+            if range.start > range.end {
+                return Err(Error::InvalidInput(format!("range {:?} starts after its end", range)).into());
+            }
+            if range.end > len {
+                return Err(Error::IndexOutOfBounds { index: range.end, len }.into());
+            }
+        }
+        let size = self.header.item_size;
+        let base = self.data_start()?;
+        let offsets = ranges.iter().map(|range| base + range.start * size as u64);
+        // the ranges are within the array, so they are no larger than the file they are read from
+        let range_len = |range: &Range<u64>| usize::try_from(range.end - range.start).map_err(|_| {
+            Error::InvalidData(format!("range {:?} is too large to read into memory", range))
+        });
+
+        match self.native_layout() {
+            Some(proof) => {
+                let mut out = ranges.iter().map(|range| Ok(zeroed_records(proof, range_len(range)?))).collect::<Result<Vec<_>, Error>>()?;
+                let mut requests = offsets.zip(out.iter_mut().map(|records| records_as_bytes_mut(proof, records))).collect::<Vec<_>>();
+                self.read_requests(&mut requests)?;
+                Ok(out)
+            },
+            None => {
+                let mut bytes = ranges.iter().map(|range| Ok(vec![0; range_len(range)? * size])).collect::<Result<Vec<_>, Error>>()?;
+                let mut requests = offsets.zip(bytes.iter_mut().map(|bytes| &mut bytes[..])).collect::<Vec<_>>();
+                self.read_requests(&mut requests)?;
+                ranges.iter().zip(&bytes).map(|(range, bytes)| {
+                    let mut bytes = &bytes[..];
+                    (range.start..range.end).map(|index| {
+                        self.type_reader.read_one(&mut bytes).map_err(|e| record_error(&self.header, index, e))
+                    }).collect()
+                }).collect()
+            },
+        }
+    }
 
-        self.seek_to(index)?;
-        self.next().unwrap()
+    // The offset of the first record in the source.
+    fn data_start(&mut self) -> io::Result<u64> {
+        let (reader, current_index) = &mut self.reader_and_current_index;
+        Ok(reader.stream_position()? - *current_index * self.header.item_size as u64)
+    }
+
+    fn read_requests(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        let reader = &mut self.reader_and_current_index.0;
+        reader.read_exact_at_many(requests).map_err(|e| member_context(&self.header.member, e))
     }
 }
 
@@ -414,9 +1458,17 @@ impl<R: io::Read, T: Deserialize> NpyReader<T, R> where R: io::Seek {
 impl<'a, T: Deserialize> NpyData<'a, T> {
     /// Deserialize a NPY file represented as bytes
     pub fn from_bytes(bytes: &'a [u8]) -> io::Result<NpyData<'a, T>> {
-        let inner = NpyFile::new(bytes)?.data().map_err(invalid_data)?;
-
-        assert_eq!(inner.header.item_size as u64 * inner.header.n_records, inner.reader().len() as u64);
+        let file = NpyFile::new(bytes)?;
+        let dtype = file.header.dtype.clone();
+        let inner = file.data().map_err(|e| Error::dtype_mismatch::<T>(&dtype, e))?;
+
+        let expected_len = inner.header.item_size as u64 * inner.header.n_records;
+        let actual_len = inner.reader().len() as u64;
+        if actual_len < expected_len {
+            return Err(Error::Truncated.into());
+        } else if actual_len > expected_len {
+            return Err(Error::InvalidData(format!("{} trailing bytes after the data", actual_len - expected_len)).into());
+        }
         Ok(NpyData { inner })
     }
 
@@ -468,28 +1520,294 @@ impl<'a, T: Deserialize> NpyData<'a, T> {
     /// layout of the data, so if you want to correctly read multi-dimensional arrays you should
     /// switch to [`NpyFile`].
     pub fn to_vec(&self) -> Vec<T> {
-        let mut reader = self.inner.reader().clone();
+        let mut reader = *self.inner.reader();
         (0..self.len()).map(|_| self.inner.type_reader.read_one(&mut reader).unwrap()).collect()
     }
 }
 
-fn strides(order: Order, shape: &[u64]) -> Vec<u64> {
+fn strides(order: Order, shape: &[u64]) -> Option<Vec<u64>> {
     match order {
         Order::C => {
-            let mut strides = prefix_products(shape.iter().rev().copied()).collect::<Vec<_>>();
+            let mut strides = prefix_products(shape.iter().rev().copied())?;
             strides.reverse();
-            strides
+            Some(strides)
         },
-        Order::Fortran => prefix_products(shape.iter().copied()).collect(),
+        Order::Fortran => prefix_products(shape.iter().copied()),
     }
 }
 
-fn prefix_products<I: IntoIterator<Item=u64>>(iter: I) -> impl Iterator<Item=u64> {
-    iter.into_iter().scan(1, |acc, x| { let old = *acc; *acc *= x; Some(old) })
+// The stride of the last axis is never used as a factor, so only the products before it must fit.
+fn prefix_products<I: IntoIterator<Item=u64>>(iter: I) -> Option<Vec<u64>> {
+    let mut acc = Some(1u64);
+    let mut out = vec![];
+    for x in iter {
+        out.push(acc?);
+        acc = acc?.checked_mul(x);
+    }
+    Some(out)
+}
+
+fn member_context(member: &Option<String>, err: io::Error) -> io::Error {
+    match member {
+        Some(member) => Error::in_member(err, member),
+        None => err,
+    }
 }
 
 fn invalid_data<S: ToString>(s: S) -> io::Error {
-    io::Error::new(io::ErrorKind::InvalidData, s.to_string())
+    Error::BadHeader(s.to_string()).into()
+}
+
+impl<T: Deserialize, R: io::Read> NpyReader<T, R> {
+    /// Read the next records into a slice, returning the number of records read.
+    ///
+    /// This reads `min(out.len(), self.len())` records and advances the read cursor past them.
+    /// For the types that [`NpyFile::into_vec`] can copy without decoding (primitive numbers in the native
+    /// byte order), the bytes go straight from the underlying reader into `out` without passing through
+    /// any intermediate buffer.  Other types are decoded one record at a time, as by [`Iterator::next`].
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let bytes = std::fs::read("test-data/c-order.npy")?;
+    /// let mut reader = npyz::NpyFile::new(&bytes[..])?.data::<i64>().unwrap();
+    ///
+    /// let mut buf = [0; 10];
+    /// let mut sum = 0;
+    /// loop {
+    ///     let count = reader.read_into(&mut buf)?;
+    ///     if count == 0 { break; }
+    ///     sum += buf[..count].iter().sum::<i64>();
+    /// }
+    /// assert_eq!(sum, npyz::NpyFile::new(&bytes[..])?.into_vec::<i64>()?.iter().sum());
+    /// # Ok(()) }
+    /// ```
+    pub fn read_into(&mut self, out: &mut [T]) -> io::Result<usize> {
+        let count = out.len().min(usize::try_from(NpyReader::len(self)).unwrap_or(usize::MAX));
+        let out = &mut out[..count];
+        match self.native_layout() {
+            Some(proof) => {
+                let (reader, current_index) = &mut self.reader_and_current_index;
+                let index = *current_index;
+                *current_index += count as u64;
+                read_native_records(proof, reader, &self.header, index, out)?;
+            },
+            None => {
+                for slot in out.iter_mut() {
+                    *slot = self.next().expect("length was checked")?;
+                }
+            },
+        }
+        Ok(count)
+    }
+
+    /// Advance the read cursor past the next `count` records without deserializing them.
+    ///
+    /// The bytes of the records are read and discarded, using the fixed item size of the dtype, which is
+    /// much faster than reading and dropping the elements one by one.  If the reader is seekable,
+    /// [`Self::try_seek_to`] moves the cursor without reading anything.  Returns [`Error::IndexOutOfBounds`] if
+    /// fewer than `count` records remain, and [`Error::Truncated`] if the data ends early.
+    ///
+    /// (This is not named `skip`, which would be shadowed by [`Iterator::skip`].)
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// let bytes = std::fs::read("test-data/c-order.npy")?;
+    /// let mut reader = npyz::NpyFile::new(&bytes[..])?.data::<i64>().unwrap();
+    /// reader.skip_records(20)?;
+    /// assert_eq!(reader.len(), 4);
+    /// assert_eq!(reader.next().unwrap()?, 6);
+    /// # Ok(()) }
+    /// ```
+    pub fn skip_records(&mut self, count: u64) -> io::Result<()> {
+        let (reader, current_index) = &mut self.reader_and_current_index;
+        let start = *current_index;
+        let len = self.end_index;
+        let end = start.checked_add(count).filter(|&end| end <= len).ok_or(Error::IndexOutOfBounds { index: start.saturating_add(count), len })?;
+        *current_index = end;
+
+        let size = self.header.item_size as u64;
+        let num_bytes = count * size;
+        let skipped = io::copy(&mut io::Read::take(reader, num_bytes), &mut io::sink()).map_err(|e| member_context(&self.header.member, e))?;
+        if skipped < num_bytes {
+            let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+            return Err(record_error(&self.header, start + skipped / size, eof));
+        }
+        Ok(())
+    }
+
+    // `Some` if records can be read by copying their bytes into a `T`.
+    fn native_layout(&self) -> Option<NativeLayout> {
+        let size = self.header.item_size;
+        self.type_reader.native_layout().filter(|_| size == std::mem::size_of::<T>() && size > 0)
+    }
+
+    // Read all remaining records by copying their bytes directly into the vector.
+    fn read_native_vec(self, proof: NativeLayout) -> io::Result<Vec<T>> {
+        let mut out = Vec::new();
+        self.read_native_into(proof, &mut out)?;
+        Ok(out)
+    }
+
+    // Read all remaining records by copying their bytes directly onto the end of `out`.
+    fn read_native_into(self, proof: NativeLayout, out: &mut impl NativeBuffer<T>) -> io::Result<()> {
+        let NpyReader { header, buffer_size, reader_and_current_index: (mut reader, start), end_index, .. } = self;
+        let size = header.item_size;
+
+        let too_large = || Error::InvalidData(format!("shape {:?} is too large to read into memory", header.shape));
+        let len = usize::try_from(end_index.saturating_sub(start)).map_err(|_| too_large())?;
+
+        // The buffer only grows as data is actually read, so that a corrupt header cannot cause a huge allocation.
+        let chunk_len = (buffer_size / size).max(1);
+        while out.len() < len {
+            let count = chunk_len.min(len - out.len());
+            if out.capacity() - out.len() < count {
+                out.reserve_exact(out.capacity().max(count).min(len - out.len()));
+            }
+            let old_len = out.len();
+            // SAFETY: The capacity was just reserved, and NativeLayout guarantees that zeroed bytes are a valid T.
+            unsafe {
+                std::ptr::write_bytes(out.as_mut_ptr().add(old_len), 0, count);
+                out.set_len(old_len + count);
+            }
+            read_native_records(proof, &mut reader, &header, start + old_len as u64, &mut out[old_len..])?;
+        }
+        Ok(())
+    }
+}
+
+// The buffers that records can be read into by `NpyReader::read_native_into`.
+trait NativeBuffer<T>: std::ops::DerefMut<Target = [T]> {
+    fn capacity(&self) -> usize;
+    fn reserve_exact(&mut self, additional: usize);
+    fn as_mut_ptr(&mut self) -> *mut T;
+    // SAFETY: The same as for `Vec::set_len`.
+    unsafe fn set_len(&mut self, len: usize);
+}
+
+impl<T> NativeBuffer<T> for Vec<T> {
+    fn capacity(&self) -> usize { Vec::capacity(self) }
+    fn reserve_exact(&mut self, additional: usize) { Vec::reserve_exact(self, additional) }
+    fn as_mut_ptr(&mut self) -> *mut T { Vec::as_mut_ptr(self) }
+    unsafe fn set_len(&mut self, len: usize) { unsafe { Vec::set_len(self, len) } }
+}
+
+impl<T> NativeBuffer<T> for AlignedVec<T> {
+    fn capacity(&self) -> usize { AlignedVec::capacity(self) }
+    fn reserve_exact(&mut self, additional: usize) { AlignedVec::reserve_exact(self, additional) }
+    fn as_mut_ptr(&mut self) -> *mut T { AlignedVec::as_mut_ptr(self) }
+    unsafe fn set_len(&mut self, len: usize) { unsafe { AlignedVec::set_len(self, len) } }
+}
+
+// Decode all records described by `header` from the data bytes of a file, appending them to `out`.
+pub(crate) fn read_all_records<T: Deserialize>(header: &NpyHeader, type_reader: &T::TypeReader, mut bytes: &[u8], out: &mut Vec<T>) -> io::Result<()> {
+    let len = header.n_records;
+    let size = header.item_size;
+    let proof = type_reader.native_layout().filter(|_| size == std::mem::size_of::<T>() && size > 0);
+    match proof {
+        Some(proof) => {
+            // the data is already in memory, so a short file is caught before allocating
+            if (bytes.len() as u64) < len * size as u64 {
+                return Err(record_error(header, (bytes.len() / size) as u64, Error::Truncated.into()));
+            }
+            let len = len as usize;
+            let old_len = out.len();
+            out.reserve(len);
+            // SAFETY: The capacity was just reserved, and NativeLayout guarantees that zeroed bytes are a valid T.
+            unsafe {
+                std::ptr::write_bytes(out.as_mut_ptr().add(old_len), 0, len);
+                out.set_len(old_len + len);
+            }
+            read_native_records(proof, bytes, header, 0, &mut out[old_len..])
+        },
+        None => {
+            for index in 0..len {
+                out.push(type_reader.read_one(&mut bytes).map_err(|e| record_error(header, index, e))?);
+            }
+            Ok(())
+        },
+    }
+}
+
+impl<'a, T: Deserialize> NpyReader<T, &'a [u8]> {
+    // All records, borrowed from the bytes they are read from, if they can be reinterpreted as `T`.
+    //
+    // Fails if the records are not in memory in full, or not aligned for `T`.
+    pub(crate) fn records_as_slice(&self) -> io::Result<Option<&'a [T]>> {
+        if self.native_layout().is_none() {
+            return Ok(None);
+        }
+        let (bytes, start) = self.reader_and_current_index;
+        let expected_len = self.header.data_len_bytes();
+        if start != 0 || (bytes.len() as u64) < expected_len {
+            return Err(Error::Truncated.into());
+        } else if bytes.len() as u64 > expected_len {
+            return Err(Error::InvalidData(format!("{} trailing bytes after the data", bytes.len() as u64 - expected_len)).into());
+        }
+        if bytes.as_ptr() as usize % std::mem::align_of::<T>() != 0 {
+            return Err(Error::InvalidData(format!(
+                "the data at offset {} is not aligned in memory for {}",
+                self.header.data_offset().unwrap_or(0), std::any::type_name::<T>(),
+            )).into());
+        }
+        // SAFETY: The bytes are aligned and hold exactly `n_records` records of `size_of::<T>()` bytes,
+        //         and NativeLayout guarantees that any bytes are a valid T.
+        Ok(Some(unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const T, bytes.len() / self.header.item_size) }))
+    }
+}
+
+// A vector of `len` records whose bytes are all zero.
+fn zeroed_records<T>(_proof: NativeLayout, len: usize) -> Vec<T> {
+    let mut out = Vec::<T>::with_capacity(len);
+    // SAFETY: The capacity was just reserved, and NativeLayout guarantees that zeroed bytes are a valid T.
+    unsafe {
+        std::ptr::write_bytes(out.as_mut_ptr(), 0, len);
+        out.set_len(len);
+    }
+    out
+}
+
+// View records as the bytes they are read from.
+fn records_as_bytes_mut<T>(_proof: NativeLayout, records: &mut [T]) -> &mut [u8] {
+    // SAFETY: NativeLayout guarantees that T has no padding and that any bytes are a valid T.
+    unsafe { std::slice::from_raw_parts_mut(records.as_mut_ptr() as *mut u8, std::mem::size_of_val(records)) }
+}
+
+// Fill `out` with the records beginning at `index` by reading their bytes directly into it.
+//
+// The `NativeLayout` must come from the type reader for `T`.
+fn read_native_records<T>(_proof: NativeLayout, reader: impl io::Read, header: &NpyHeader, index: u64, out: &mut [T]) -> io::Result<()> {
+    let size = header.item_size;
+    assert_eq!(size, std::mem::size_of::<T>());
+    // SAFETY: NativeLayout guarantees that T has no padding and that any bytes are a valid T.
+    let bytes = unsafe { std::slice::from_raw_parts_mut(out.as_mut_ptr() as *mut u8, std::mem::size_of_val(out)) };
+    let filled = read_until_eof(reader, bytes).map_err(|e| record_error(header, index, e))?;
+    if filled < bytes.len() {
+        return Err(record_error(header, index + (filled / size) as u64, Error::Truncated.into()));
+    }
+    Ok(())
+}
+
+// Like `read_exact`, but returns the number of bytes read instead of failing at EOF.
+fn read_until_eof(mut reader: impl io::Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+pub(crate) fn record_error(header: &NpyHeader, index: u64, err: io::Error) -> io::Error {
+    let err = match err.kind() {
+        io::ErrorKind::UnexpectedEof if err.get_ref().is_none() => Error::Truncated.into(),
+        _ => err,
+    };
+    let byte_offset = header.data_offset().map(|offset| offset + index * header.item_size as u64);
+    member_context(&header.member, Error::at_record(err, index, byte_offset))
 }
 
 impl<R, T> Iterator for NpyReader<T, R> where T: Deserialize, R: io::Read {
@@ -497,9 +1815,10 @@ impl<R, T> Iterator for NpyReader<T, R> where T: Deserialize, R: io::Read {
 
     fn next(&mut self) -> Option<Self::Item> {
         let (reader, current_index) = &mut self.reader_and_current_index;
-        if *current_index < self.header.n_records {
+        if *current_index < self.end_index {
             *current_index += 1;
-            return Some(self.type_reader.read_one(reader));
+            let index = *current_index - 1;
+            return Some(self.type_reader.read_one(reader).map_err(|e| record_error(&self.header, index, e)));
         }
         None
     }
@@ -514,6 +1833,147 @@ impl<R, T> Iterator for NpyReader<T, R> where T: Deserialize, R: io::Read {
     }
 }
 
+/// The length is [`NpyReader::len`].  This is only implemented on 64-bit platforms, since elsewhere the
+/// length of an array can exceed `usize::MAX`.
+#[cfg(target_pointer_width = "64")]
+impl<R, T> ExactSizeIterator for NpyReader<T, R> where T: Deserialize, R: io::Read {}
+
+/// Reading from the back seeks to each record and back to the read cursor, so it is slower than reading forwards.
+impl<R, T> DoubleEndedIterator for NpyReader<T, R> where T: Deserialize, R: io::Read + io::Seek {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (reader, current_index) = &mut self.reader_and_current_index;
+        if *current_index >= self.end_index {
+            return None;
+        }
+        self.end_index -= 1;
+        let index = self.end_index;
+        let offset = (index - *current_index) * self.header.item_size as u64;
+        let result = reader.stream_position().and_then(|position| {
+            reader.seek(io::SeekFrom::Start(position + offset))?;
+            let result = self.type_reader.read_one(&mut *reader);
+            reader.seek(io::SeekFrom::Start(position))?;
+            result
+        });
+        Some(result.map_err(|e| record_error(&self.header, index, e)))
+    }
+}
+
+/// Reader returned by [`NpyFile::raw_records`], which reads the bytes of each record without deserializing them.
+///
+/// Each record is [`DType::num_bytes`] long.  The records are read a buffer of
+/// [`ReadOptions::buffer_size`] bytes at a time, and borrowed from that buffer, so this cannot
+/// implement [`Iterator`]; use a `while let` loop over [`Self::next_record`] or [`Self::next_chunk`].
+pub struct RawRecords<R: io::Read> {
+    header: NpyHeader,
+    reader: R,
+    buf: Vec<u8>,
+    // the bytes of the buffered records that have not yet been returned
+    start: usize,
+    end: usize,
+    // the index of the first record that has not been read into the buffer
+    next_index: u64,
+}
+
+impl<R: io::Read> RawRecords<R> {
+    /// Get the dtype as written in the file.
+    pub fn dtype(&self) -> DType {
+        self.header.dtype.clone()
+    }
+
+    /// Get the number of bytes in each record.
+    pub fn item_size(&self) -> usize {
+        self.header.item_size
+    }
+
+    /// Access the underlying [`NpyHeader`] object.
+    pub fn header(&self) -> &NpyHeader {
+        &self.header
+    }
+
+    /// Get the number of records that have not yet been returned.
+    pub fn len(&self) -> u64 {
+        let buffered = match self.header.item_size {
+            0 => 0,
+            size => ((self.end - self.start) / size) as u64,
+        };
+        self.header.n_records - self.next_index + buffered
+    }
+
+    /// Returns `true` if all records have been returned.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read the bytes of the next record, or return `None` after the last one.
+    pub fn next_record(&mut self) -> io::Result<Option<&[u8]>> {
+        let size = self.header.item_size;
+        if size == 0 {
+            return Ok(self.next_empty_records(1));
+        }
+        if self.start == self.end && !self.fill()? {
+            return Ok(None);
+        }
+        self.start += size;
+        Ok(Some(&self.buf[self.start - size..self.start]))
+    }
+
+    /// Read the bytes of one or more of the next records, or return `None` after the last one.
+    ///
+    /// This returns all records that are currently buffered, so it involves less bookkeeping
+    /// than [`Self::next_record`] when the records are processed in bulk, e.g. to hash or copy them.
+    /// The length is always a multiple of [`Self::item_size`].
+    pub fn next_chunk(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.header.item_size == 0 {
+            let remaining = usize::try_from(self.header.n_records - self.next_index).unwrap_or(usize::MAX);
+            return Ok(self.next_empty_records(remaining));
+        }
+        if self.start == self.end && !self.fill()? {
+            return Ok(None);
+        }
+        let chunk = &self.buf[self.start..self.end];
+        self.start = self.end;
+        Ok(Some(chunk))
+    }
+
+    // Records of a zero-sized dtype are never read.
+    fn next_empty_records(&mut self, count: usize) -> Option<&[u8]> {
+        match self.next_index < self.header.n_records && count > 0 {
+            true => {
+                self.next_index += count as u64;
+                Some(&[])
+            },
+            false => None,
+        }
+    }
+
+    // Read the next records into the empty buffer, returning `false` if there are none left.
+    fn fill(&mut self) -> io::Result<bool> {
+        let size = self.header.item_size;
+        let remaining = self.header.n_records - self.next_index;
+        let count = usize::try_from(remaining).unwrap_or(usize::MAX).min(self.buf.len() / size);
+        if count == 0 {
+            return Ok(false);
+        }
+        let len = count * size;
+        let mut filled = 0;
+        while filled < len {
+            match self.reader.read(&mut self.buf[filled..len]) {
+                Ok(0) => {
+                    let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+                    return Err(record_error(&self.header, self.next_index + (filled / size) as u64, eof));
+                },
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(member_context(&self.header.member, e)),
+            }
+        }
+        self.next_index += count as u64;
+        self.start = 0;
+        self.end = len;
+        Ok(true)
+    }
+}
+
 /// A result of NPY file deserialization.
 ///
 /// It is an iterator to offer a lazy interface in case the data don't fit into memory.
@@ -563,12 +2023,151 @@ mod tests {
     use super::*;
     use crate::write::to_bytes_1d;
 
+    #[test]
+    fn test_tiled_indices() {
+        // every element is visited once, and the indices agree on its multi-index
+        for shape in [&[][..], &[5], &[0, 3], &[3, 4], &[70, 33], &[5, 6, 7], &[2, 1, 3, 2]] {
+            for order in [Order::C, Order::Fortran] {
+                let stored_strides = strides(order, shape).unwrap();
+                let reordered_strides = strides(order.reversed(), shape).unwrap();
+                let mut expected = vec![];
+                let len = shape.iter().product::<u64>();
+                for stored in 0..len {
+                    let reordered = (0..shape.len()).map(|axis| stored / stored_strides[axis] % shape[axis] * reordered_strides[axis]).sum::<u64>();
+                    expected.push((stored, reordered));
+                }
+
+                for tile_size in [1, 2, 32] {
+                    let mut actual = TiledIndices::new(shape, order).unwrap().with_tile_size(tile_size).collect::<Vec<_>>();
+                    if tile_size == 1 {
+                        assert!(actual.iter().map(|&(_, reordered)| reordered).eq(0..len));
+                    }
+                    actual.sort();
+                    assert_eq!(actual, expected, "{:?} {:?} {}", shape, order, tile_size);
+                }
+            }
+        }
+        assert!(TiledIndices::new(&[u64::MAX, 2], Order::C).is_none());
+    }
+
+    #[test]
+    fn test_nd_indices() {
+        for shape in [&[][..], &[5], &[0, 3], &[3, 4], &[5, 6, 7], &[2, 1, 3, 2]] {
+            for order in [Order::C, Order::Fortran] {
+                let stored_strides = strides(order, shape).unwrap();
+                let indices = NdIndices::new(shape, order).unwrap();
+                assert_eq!(indices.size_hint().0 as u64, shape.iter().product::<u64>());
+                let flat = indices.map(|index| index.iter().zip(&stored_strides).map(|(i, stride)| i * stride).sum::<u64>());
+                assert!(flat.eq(0..shape.iter().product()), "{:?} {:?}", shape, order);
+            }
+        }
+        let pairs = NdIndices::new(&[2, 2], Order::Fortran).unwrap().with_values("abc".chars()).collect::<Vec<_>>();
+        assert_eq!(pairs, vec![(vec![0, 0], 'a'), (vec![1, 0], 'b'), (vec![0, 1], 'c')]);
+        assert!(NdIndices::new(&[u64::MAX, 2], Order::C).is_none());
+    }
+
     #[test]
     fn test_strides() {
-        assert_eq!(strides(Order::C, &[2, 3, 4]), vec![12, 4, 1]);
-        assert_eq!(strides(Order::C, &[]), vec![]);
-        assert_eq!(strides(Order::Fortran, &[2, 3, 4]), vec![1, 2, 6]);
-        assert_eq!(strides(Order::Fortran, &[]), vec![]);
+        assert_eq!(strides(Order::C, &[2, 3, 4]), Some(vec![12, 4, 1]));
+        assert_eq!(strides(Order::C, &[]), Some(vec![]));
+        assert_eq!(strides(Order::Fortran, &[2, 3, 4]), Some(vec![1, 2, 6]));
+        assert_eq!(strides(Order::Fortran, &[]), Some(vec![]));
+        assert_eq!(strides(Order::C, &[u64::MAX, 2]), Some(vec![2, 1]));
+        assert_eq!(strides(Order::C, &[2, u64::MAX, 2]), None);
+    }
+
+    fn npy_with_header(text: &str) -> Vec<u8> {
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend((text.len() as u16 + 1).to_le_bytes());
+        bytes.extend(text.as_bytes());
+        bytes.push(b'\n');
+        bytes
+    }
+
+    #[test]
+    fn test_read_options() {
+        let limit_exceeded = |bytes: &[u8], options: &ReadOptions| {
+            let err = NpyFile::with_options(bytes, options).err().unwrap();
+            matches!(Error::from(err), Error::LimitExceeded { .. })
+        };
+
+        let bytes = npy_with_header("{'descr': [('a', '<i4'), ('b', [('c', '<i4')])], 'fortran_order': False, 'shape': (0, 0, 0)}");
+        assert!(NpyFile::new(&bytes[..]).is_ok());
+        assert!(limit_exceeded(&bytes, &ReadOptions::new().max_ndim(2)));
+        assert!(limit_exceeded(&bytes, &ReadOptions::new().max_header_len(50)));
+        assert!(limit_exceeded(&bytes, &ReadOptions::new().max_fields(2)));
+        assert!(limit_exceeded(&bytes, &ReadOptions::new().max_depth(1)));
+        assert!(NpyFile::with_options(&bytes[..], &ReadOptions::new().max_fields(3).max_depth(2)).is_ok());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        use std::sync::{Arc, Mutex};
+
+        let found = Arc::new(Mutex::new(vec![]));
+        let options = ReadOptions::new().on_diagnostic({
+            let found = found.clone();
+            move |d| found.lock().unwrap().push(d.clone())
+        });
+
+        let bytes = npy_with_header("{'descr': '<i4', 'fortran_order': False, 'shape': (0,), 'extra': 1}");
+        assert!(NpyFile::with_options(&bytes[..], &options).is_ok());
+        assert_eq!(*found.lock().unwrap(), vec![
+            Diagnostic::UnknownHeaderKey { key: "extra".to_string() },
+            Diagnostic::NonCanonicalPadding { header_len: bytes.len() },
+        ]);
+
+        found.lock().unwrap().clear();
+        let bytes = to_bytes_1d(&[1i32, 2, 3]).unwrap();
+        assert!(NpyFile::with_options(&bytes[..], &options).is_ok());
+        assert_eq!(*found.lock().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_strict_header() {
+        let strict = ReadOptions::new().strict_header(true);
+        let is_bad_header = |bytes: &[u8]| {
+            let err = NpyFile::with_options(bytes, &strict).err().unwrap();
+            matches!(Error::from(err), Error::BadHeader(_))
+        };
+
+        let bytes = to_bytes_1d(&[1i32, 2, 3]).unwrap();
+        assert!(NpyFile::with_options(&bytes[..], &strict).is_ok());
+
+        // npy_with_header does not pad the header
+        let padded = |text: &str| {
+            let len = 10 + text.len() + 1;
+            npy_with_header(&format!("{}{}", text, " ".repeat(len.next_multiple_of(64) - len)))
+        };
+        let bytes = padded("{'descr': '<i4', 'fortran_order': False, 'shape': (0,), }");
+        assert!(NpyFile::with_options(&bytes[..], &strict).is_ok());
+
+        for text in [
+            "{'descr': '<i4', 'fortran_order': False, 'shape': (0,), 'extra': 1}",
+            "{'descr': '<i4', 'fortran_order': False, 'shape': (0,), 'shape': (0,)}",
+            "{'descr': '<i4', 'fortran_order': False, 'shape': [0]}",
+        ] {
+            assert!(NpyFile::new(&padded(text)[..]).is_ok());
+            assert!(is_bad_header(&padded(text)), "{}", text);
+        }
+        let bytes = npy_with_header("{'descr': '<i4', 'fortran_order': False, 'shape': (0,)}");
+        assert!(is_bad_header(&bytes));
+        let bytes = padded("{'descr': '<i4', 'fortran_order': False, 'shape': (0,), '\u{e9}': 0}");
+        assert!(NpyFile::new(&bytes[..]).is_ok());
+        assert!(is_bad_header(&bytes));
+    }
+
+    #[test]
+    fn test_overflowing_shape() {
+        let bytes = npy_with_header("{'descr': '<i4', 'fortran_order': False, 'shape': (4294967296, 4294967296)}");
+        let err = NpyFile::new(&bytes[..]).err().unwrap();
+        assert!(matches!(Error::from(err), Error::BadHeader(_)));
+
+        let bytes = npy_with_header("{'descr': '<i4', 'fortran_order': False, 'shape': (0, 4294967296, 4294967296)}");
+        assert!(NpyFile::new(&bytes[..]).is_err());
+
+        let bytes = npy_with_header("{'descr': '<i4', 'fortran_order': False, 'shape': (4294967296, 4294967296, 0)}");
+        assert_eq!(NpyFile::new(&bytes[..]).unwrap().header().strides(), &[0, 0, 1]);
     }
 
     #[test]
@@ -648,6 +2247,323 @@ mod tests {
     #[should_panic]
     fn test_read_boundary_ng() { check_read_panic_boundary(&[1, 2, 3], 3) }
 
+    #[test]
+    fn test_try_read_at() {
+        let bytes = to_bytes_1d(&[1i32, 2, 3]).unwrap();
+        let mut reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+        assert_eq!(reader.try_read_at(2).unwrap(), 3);
+        assert!(reader.try_seek_to(3).is_ok());
+        let err = reader.try_read_at(3).unwrap_err();
+        assert!(matches!(Error::from(err), Error::IndexOutOfBounds { index: 3, len: 3 }));
+        let err = reader.try_seek_to(4).unwrap_err();
+        assert!(matches!(Error::from(err), Error::IndexOutOfBounds { index: 4, len: 3 }));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_npy_data_wrong_length() {
+        let mut bytes = to_bytes_1d(&[1i32, 2, 3]).unwrap();
+        let err = NpyData::<i32>::from_bytes(&bytes[..bytes.len() - 1]).err().unwrap();
+        assert!(matches!(Error::from(err), Error::Truncated));
+        bytes.push(0);
+        let err = NpyData::<i32>::from_bytes(&bytes[..]).err().unwrap();
+        assert!(matches!(Error::from(err), Error::InvalidData(_)));
+    }
+
+    #[test]
+    fn test_copy_raw_to() {
+        let bytes = to_bytes_1d(&[1i32, 2, 3]).unwrap();
+        let mut copy = vec![];
+        NpyFile::new(&bytes[..]).unwrap().copy_raw_to(&mut copy).unwrap();
+        assert_eq!(copy, bytes);
+
+        // a header without raw bytes is regenerated
+        let mut reader = &bytes[..];
+        let header = NpyHeader::from_reader(&mut reader).unwrap();
+        let mut copy = vec![];
+        let regenerated = NpyHeader::from_parts(header.dtype(), header.shape().to_vec(), header.order()).unwrap();
+        NpyFile::with_header(regenerated, reader).copy_raw_to(&mut copy).unwrap();
+        assert_eq!(NpyFile::new(&copy[..]).unwrap().into_vec::<i32>().unwrap(), vec![1, 2, 3]);
+
+        let err = NpyFile::new(&bytes[..bytes.len() - 1]).unwrap().copy_raw_to(io::sink()).unwrap_err();
+        assert!(matches!(Error::from(err), Error::Truncated));
+    }
+
+    #[test]
+    fn test_into_vec_native_layout() {
+        // large enough to span several chunks
+        let values = (0..300_000).map(|x| x as f64 * 0.5).collect::<Vec<_>>();
+        let bytes = to_bytes_1d(&values).unwrap();
+        assert_eq!(NpyFile::new(&bytes[..]).unwrap().into_vec::<f64>().unwrap(), values);
+
+        // the other byte order goes through the elementwise path
+        let mut swapped = vec![];
+        crate::byteswap(&bytes[..], &mut swapped).unwrap();
+        assert_eq!(NpyFile::new(&swapped[..]).unwrap().into_vec::<f64>().unwrap(), values);
+
+        // the remaining records after a partial read
+        let mut reader = NpyFile::new(&bytes[..]).unwrap().data::<f64>().unwrap();
+        reader.nth(9).unwrap().unwrap();
+        let proof = reader.native_layout().unwrap();
+        assert_eq!(reader.read_native_vec(proof).unwrap(), values[10..]);
+
+        // buffers smaller than an element still read one element at a time
+        for buffer_size in [0, 12, 4096] {
+            let npy = NpyFile::new(&bytes[..]).unwrap().with_buffer_size(buffer_size);
+            assert_eq!(npy.into_vec::<f64>().unwrap(), values);
+        }
+
+        // the index of the first incomplete record is reported
+        let err = NpyFile::new(&bytes[..bytes.len() - 4]).unwrap().into_vec::<f64>().unwrap_err();
+        let err = Error::from(err);
+        assert_eq!(err.context().unwrap().index(), Some(values.len() as u64 - 1));
+        assert!(matches!(err.root(), Error::Truncated));
+    }
+
+    #[test]
+    fn test_into_aligned_vec() {
+        let values = (0..100_000).map(|x| x as f32).collect::<Vec<_>>();
+        let bytes = to_bytes_1d(&values).unwrap();
+        let mut swapped = vec![];
+        crate::byteswap(&bytes[..], &mut swapped).unwrap();
+        for bytes in [&bytes, &swapped] {
+            let data = NpyFile::new(&bytes[..]).unwrap().with_buffer_size(1000).into_aligned_vec::<f32>(256).unwrap();
+            assert_eq!(data.as_ptr() as usize % 256, 0);
+            assert_eq!(&data[..], &values[..]);
+        }
+
+        let err = NpyFile::new(&bytes[..]).unwrap().into_aligned_vec::<i8>(64).unwrap_err();
+        assert!(matches!(Error::from(err).root(), Error::DTypeMismatch { .. }));
+    }
+
+    #[test]
+    fn test_read_into() {
+        let values = (0..100).collect::<Vec<i32>>();
+        let bytes = to_bytes_1d(&values).unwrap();
+        let mut swapped = vec![];
+        crate::byteswap(&bytes[..], &mut swapped).unwrap();
+
+        for bytes in [&bytes, &swapped] {
+            let mut reader = NpyFile::new(&bytes[..]).unwrap().data::<i32>().unwrap();
+            assert_eq!(reader.next().unwrap().unwrap(), 0);
+            let mut buf = [0; 30];
+            let mut out = vec![];
+            loop {
+                match reader.read_into(&mut buf).unwrap() {
+                    0 => break,
+                    count => out.extend_from_slice(&buf[..count]),
+                }
+                assert_eq!(reader.len(), 99 - out.len() as u64);
+            }
+            assert_eq!(out, values[1..]);
+            assert!(reader.next().is_none());
+        }
+
+        let mut reader = NpyFile::new(&bytes[..bytes.len() - 5]).unwrap().data::<i32>().unwrap();
+        let err = reader.read_into(&mut [0; 100]).unwrap_err();
+        assert_eq!(Error::from(err).context().unwrap().index(), Some(98));
+    }
+
+    #[test]
+    fn test_read_many_at() {
+        let values = (0..100).collect::<Vec<i32>>();
+        let bytes = to_bytes_1d(&values).unwrap();
+        let mut swapped = vec![];
+        crate::byteswap(&bytes[..], &mut swapped).unwrap();
+
+        for bytes in [&bytes, &swapped] {
+            let mut reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+            assert_eq!(reader.next().unwrap().unwrap(), 0);
+            assert_eq!(reader.read_many_at(&[99, 0, 42, 42]).unwrap(), vec![99, 0, 42, 42]);
+            assert_eq!(reader.read_ranges_at(&[10..13, 0..0, 98..100]).unwrap(), vec![vec![10, 11, 12], vec![], vec![98, 99]]);
+            assert_eq!(reader.len(), 99);
+
+            assert_eq!(reader.read_range(97..100).unwrap(), vec![97, 98, 99]);
+            assert!(reader.next().is_none());
+            assert_eq!(reader.read_range(5..5).unwrap(), vec![]);
+            assert_eq!(reader.next().unwrap().unwrap(), 5);
+            let err = reader.read_range(99..101).unwrap_err();
+            assert!(matches!(Error::from(err), Error::IndexOutOfBounds { index: 101, len: 100 }));
+
+            let err = reader.read_ranges_at(&[0..1, 0..101]).unwrap_err();
+            assert!(matches!(Error::from(err), Error::IndexOutOfBounds { index: 101, len: 100 }));
+            #[allow(clippy::reversed_empty_ranges)]
+            let err = reader.read_ranges_at(&[0..1, 5..4]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        let mut reader = NpyFile::new(io::Cursor::new(&swapped[..swapped.len() - 1])).unwrap().data::<i32>().unwrap();
+        assert_eq!(reader.read_many_at(&[98]).unwrap(), vec![98]);
+        assert_eq!(reader.read_many_at(&[99]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_read_slice() {
+        use crate::{AutoSerialize, WriterBuilder};
+
+        let shape = [4, 5, 6];
+        for order in [Order::C, Order::Fortran] {
+            let strides = strides(order, &shape).unwrap();
+            let values = (0..120).collect::<Vec<i32>>();
+            let mut bytes = vec![];
+            let mut writer = crate::WriteOptions::new().default_dtype().shape(&shape).order(order).writer(&mut bytes).begin_nd().unwrap();
+            writer.extend(values.iter().copied()).unwrap();
+            writer.finish().unwrap();
+
+            let cases: [[Range<u64>; 3]; 6] = [
+                [0..4, 0..5, 0..6],
+                [1..3, 2..4, 3..5],
+                [2..3, 0..5, 0..6],
+                [0..4, 4..5, 0..6],
+                [3..4, 4..5, 5..6],
+                [0..4, 1..3, 0..0],
+            ];
+            for ranges in cases {
+                // elements of the slice, in the order of the file
+                let expected = (0..120).filter(|&flat| {
+                    (0..3).all(|axis| ranges[axis].contains(&(flat / strides[axis] % shape[axis])))
+                }).map(|flat| flat as i32).collect::<Vec<_>>();
+
+                let mut reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+                assert_eq!(reader.read_slice(&ranges).unwrap(), expected, "{:?} {:?}", order, ranges);
+            }
+
+            let mut reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+            let err = reader.read_slice(&[0..1, 0..1]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            let err = reader.read_slice(&[0..1, 0..6, 0..1]).unwrap_err();
+            assert!(matches!(Error::from(err), Error::IndexOutOfBounds { index: 6, len: 5 }));
+        }
+
+        let bytes = to_bytes_1d(&[7_i32]).unwrap();
+        let header = NpyHeader::from_parts(i32::default_dtype(), vec![], Order::C).unwrap();
+        let npy = NpyFile::with_header(header, io::Cursor::new(&bytes[bytes.len() - 4..]));
+        assert_eq!(npy.read_slice::<i32>(&[]).unwrap(), vec![7]);
+    }
+
+    #[test]
+    fn test_skip_records() {
+        let values = (0..100).collect::<Vec<i32>>();
+        let bytes = to_bytes_1d(&values).unwrap();
+
+        let mut reader = NpyFile::new(&bytes[..]).unwrap().data::<i32>().unwrap();
+        reader.skip_records(0).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), 0);
+        reader.skip_records(50).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), 51);
+        let err = reader.skip_records(49).unwrap_err();
+        assert!(matches!(Error::from(err), Error::IndexOutOfBounds { index: 101, len: 100 }));
+        reader.skip_records(48).unwrap();
+        assert!(reader.next().is_none());
+
+        let mut reader = NpyFile::new(&bytes[..bytes.len() - 6]).unwrap().data::<i32>().unwrap();
+        let err = Error::from(reader.skip_records(100).unwrap_err());
+        assert_eq!(err.context().unwrap().index(), Some(98));
+    }
+
+    #[test]
+    fn test_double_ended() {
+        let values = (0..10).collect::<Vec<i32>>();
+        let bytes = to_bytes_1d(&values).unwrap();
+        let mut swapped = vec![];
+        crate::byteswap(&bytes[..], &mut swapped).unwrap();
+
+        for bytes in [&bytes, &swapped] {
+            let reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+            #[cfg(target_pointer_width = "64")]
+            assert_eq!(ExactSizeIterator::len(&reader), 10);
+            assert_eq!(reader.rev().collect::<io::Result<Vec<_>>>().unwrap(), (0..10).rev().collect::<Vec<_>>());
+
+            let mut reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+            assert_eq!(reader.next_back().unwrap().unwrap(), 9);
+            assert_eq!(reader.next().unwrap().unwrap(), 0);
+            assert_eq!(reader.nth_back(1).unwrap().unwrap(), 7);
+            assert_eq!(reader.len(), 6);
+            assert_eq!(reader.by_ref().take(2).collect::<io::Result<Vec<_>>>().unwrap(), vec![1, 2]);
+            assert_eq!(reader.read_into(&mut [0; 10]).unwrap(), 4);
+            assert!(reader.next_back().is_none());
+            assert!(reader.next().is_none());
+
+            let mut reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+            reader.next_back().unwrap().unwrap();
+            if let Some(proof) = reader.native_layout() {
+                assert_eq!(reader.read_native_vec(proof).unwrap(), (0..9).collect::<Vec<_>>());
+            }
+        }
+
+        let mut reader = NpyFile::new(io::Cursor::new(&bytes[..bytes.len() - 2])).unwrap().data::<i32>().unwrap();
+        let err = Error::from(reader.next_back().unwrap().unwrap_err());
+        assert_eq!(err.context().unwrap().index(), Some(9));
+        assert_eq!(reader.next_back().unwrap().unwrap(), 8);
+        assert_eq!(reader.next().unwrap().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_random_access_after_next_back() {
+        let values = (0..10).collect::<Vec<i32>>();
+        let bytes = to_bytes_1d(&values).unwrap();
+
+        let mut reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+        assert_eq!(reader.next_back().unwrap().unwrap(), 9);
+        assert_eq!(reader.try_read_at(9).unwrap(), 9);
+        assert!(reader.next().is_none());
+        assert_eq!(reader.read_at(9).unwrap(), 9);
+
+        let mut reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+        reader.nth_back(2).unwrap().unwrap();
+        assert_eq!(reader.read_range(0..10).unwrap(), values);
+
+        let mut reader = NpyFile::new(io::Cursor::new(&bytes[..])).unwrap().data::<i32>().unwrap();
+        reader.next_back().unwrap().unwrap();
+        reader.try_seek_to(4).unwrap();
+        assert_eq!(reader.len(), 6);
+        assert_eq!(reader.next_back().unwrap().unwrap(), 9);
+        let err = reader.try_read_at(10).unwrap_err();
+        assert!(matches!(Error::from(err), Error::IndexOutOfBounds { index: 10, len: 10 }));
+    }
+
+    #[test]
+    fn test_raw_records() {
+        let values = (0..100).collect::<Vec<i32>>();
+        let bytes = to_bytes_1d(&values).unwrap();
+        let expected = values.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<u8>>();
+
+        for buffer_size in [0, 4, 10, 1 << 16] {
+            let mut records = NpyFile::new(&bytes[..]).unwrap().with_buffer_size(buffer_size).raw_records();
+            assert_eq!(records.item_size(), 4);
+            let mut out = vec![];
+            out.extend_from_slice(records.next_record().unwrap().unwrap());
+            assert_eq!(records.len(), 99);
+            while let Some(chunk) = records.next_chunk().unwrap() {
+                assert_eq!(chunk.len() % 4, 0);
+                out.extend_from_slice(chunk);
+            }
+            assert_eq!(out, expected);
+            assert!(records.is_empty());
+            assert!(records.next_record().unwrap().is_none());
+        }
+
+        let mut records = NpyFile::new(&bytes[..bytes.len() - 6]).unwrap().with_buffer_size(40).raw_records();
+        let mut count = 0;
+        let err = loop {
+            match records.next_record() {
+                Ok(record) => {
+                    assert!(record.is_some());
+                    count += 1;
+                },
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(count, 90);
+        assert_eq!(Error::from(err).context().unwrap().index(), Some(98));
+
+        let header = NpyHeader::from_parts(DType::Record(vec![]), vec![3], Order::C).unwrap();
+        let mut records = NpyFile::with_header(header, &[][..]).raw_records();
+        assert_eq!(records.next_record().unwrap(), Some(&[][..]));
+        assert_eq!(records.next_chunk().unwrap(), Some(&[][..]));
+        assert!(records.next_chunk().unwrap().is_none());
+    }
+
     #[test]
     fn test_reusing_header() {
         let bytes = to_bytes_1d(&[100, 101, 102, 103, 104, 105, 106]).unwrap();
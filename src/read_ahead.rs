@@ -0,0 +1,193 @@
+//! Reading from a stream on a background thread.
+
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::thread;
+
+const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// A reader that reads ahead of its consumer on a background thread.
+///
+/// While the data of one chunk is being deserialized, the thread reads the following chunks
+/// from the underlying reader, so that the time spent waiting on I/O overlaps with the time spent
+/// decoding.  This is worthwhile for large sequential reads from disks or network streams.
+///
+/// The `depth` is the number of chunks that may be read before they are consumed.  Memory use is
+/// at most about `(depth + 2) * chunk_size`.
+///
+/// `ReadAhead` implements [`BufRead`], so it does not need to be wrapped in an [`io::BufReader`].
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let file = std::fs::File::open("test-data/c-order.npy")?;
+/// let npy = npyz::NpyFile::new(npyz::ReadAhead::spawn(file, 2))?;
+/// assert_eq!(npy.into_vec::<i64>()?.len(), 24);
+/// # Ok(()) }
+/// ```
+///
+/// Errors from the underlying reader are returned by the read that reaches them; after that,
+/// the `ReadAhead` behaves as if it were at EOF.  Dropping it stops the thread once the read in progress
+/// (if any) completes.
+pub struct ReadAhead {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    recycle: Sender<Vec<u8>>,
+    current: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl ReadAhead {
+    /// Start reading ahead by up to `depth` chunks of 1 MiB.
+    pub fn spawn<R: Read + Send + 'static>(reader: R, depth: usize) -> Self {
+        Self::spawn_with_chunk_size(reader, depth, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Start reading ahead by up to `depth` chunks of the given size in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn spawn_with_chunk_size<R: Read + Send + 'static>(reader: R, depth: usize, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be nonzero");
+        let (chunk_sender, chunks) = mpsc::sync_channel(depth);
+        let (recycle, recycled) = mpsc::channel();
+        thread::Builder::new()
+            .name("npyz-read-ahead".to_string())
+            .spawn(move || read_chunks(reader, chunk_size, chunk_sender, recycled))
+            .expect("failed to spawn read-ahead thread");
+        ReadAhead { chunks, recycle, current: vec![], pos: 0, done: false }
+    }
+}
+
+// Runs on the background thread until EOF, an error, or the ReadAhead being dropped.
+fn read_chunks(mut reader: impl Read, chunk_size: usize, chunks: SyncSender<io::Result<Vec<u8>>>, recycled: Receiver<Vec<u8>>) {
+    loop {
+        let mut buf = recycled.try_recv().unwrap_or_default();
+        buf.resize(chunk_size, 0);
+        let mut len = 0;
+        let mut error = None;
+        while len < chunk_size {
+            match reader.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                },
+            }
+        }
+        buf.truncate(len);
+        // data before an error is still delivered
+        if len > 0 && chunks.send(Ok(buf)).is_err() {
+            return;
+        }
+        if let Some(e) = error {
+            let _ = chunks.send(Err(e));
+            return;
+        }
+        if len < chunk_size {
+            return;
+        }
+    }
+}
+
+impl BufRead for ReadAhead {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.current.len() && !self.done {
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) => {
+                    let used = std::mem::replace(&mut self.current, chunk);
+                    let _ = self.recycle.send(used);
+                    self.pos = 0;
+                },
+                Ok(Err(e)) => {
+                    self.done = true;
+                    return Err(e);
+                },
+                // the thread has finished
+                Err(_) => self.done = true,
+            }
+        }
+        Ok(&self.current[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = usize::min(self.pos + amt, self.current.len());
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl fmt::Debug for ReadAhead {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReadAhead")
+            .field("buffered", &(self.current.len() - self.pos))
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_source() -> io::Result<()> {
+        let bytes = (0..100_000).map(|x| (x * 7 % 251) as u8).collect::<Vec<_>>();
+        for (depth, chunk_size) in [(0, 1), (1, 7), (4, 4096), (2, 100_000), (2, 1 << 20)] {
+            let mut out = vec![];
+            ReadAhead::spawn_with_chunk_size(io::Cursor::new(bytes.clone()), depth, chunk_size).read_to_end(&mut out)?;
+            assert_eq!(out, bytes, "depth {} chunk size {}", depth, chunk_size);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn read_npy() -> io::Result<()> {
+        let bytes = std::fs::read("test-data/c-order.npy")?;
+        let npy = crate::NpyFile::new(ReadAhead::spawn_with_chunk_size(io::Cursor::new(bytes.clone()), 2, 64))?;
+        assert_eq!(npy.into_vec::<i64>()?, crate::NpyFile::new(&bytes[..])?.into_vec::<i64>()?);
+        Ok(())
+    }
+
+    #[test]
+    fn error() {
+        struct Failing(usize);
+        impl Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0 {
+                    0 => Err(io::Error::other("disk on fire")),
+                    _ => {
+                        let len = buf.len().min(self.0);
+                        self.0 -= len;
+                        Ok(len)
+                    },
+                }
+            }
+        }
+
+        let mut reader = ReadAhead::spawn_with_chunk_size(Failing(10), 1, 4);
+        let mut buf = [0; 10];
+        reader.read_exact(&mut buf).unwrap();
+        let err = reader.read_to_end(&mut vec![]).unwrap_err();
+        assert_eq!(err.to_string(), "disk on fire");
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn drop_early() {
+        // the thread must stop even though the source never ends
+        let reader = ReadAhead::spawn_with_chunk_size(io::repeat(1), 2, 16);
+        drop(reader);
+    }
+}
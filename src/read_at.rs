@@ -0,0 +1,246 @@
+//! Reading several ranges of bytes from a source at once, and reading with positioned reads.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+use crate::error::Error;
+
+/// Sources that can read the bytes at several offsets in one call, without moving the read cursor.
+///
+/// This is used by [`NpyReader::read_ranges_at`][`crate::NpyReader::read_ranges_at`] and
+/// [`NpyReader::read_many_at`][`crate::NpyReader::read_many_at`].  On unix, a [`File`] reads
+/// each range with a positional read (`pread`).  With the **`"io-uring"`** feature on Linux,
+/// [`UringFile`][`crate::UringFile`] submits all of the reads to the kernel together.
+pub trait ReadAtMany: Read + Seek {
+    /// Fill each buffer with the bytes starting at its offset.
+    ///
+    /// Returns [`Error::Truncated`] if any of the ranges extends past the end of the source.
+    /// The position of the read cursor is the same afterwards.
+    fn read_exact_at_many(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()>;
+}
+
+impl ReadAtMany for File {
+    #[cfg(unix)]
+    fn read_exact_at_many(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        use std::os::unix::fs::FileExt;
+
+        for (offset, buf) in requests {
+            self.read_exact_at(buf, *offset).map_err(truncated)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn read_exact_at_many(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        read_with_seeks(self, requests)
+    }
+}
+
+impl<T: AsRef<[u8]>> ReadAtMany for io::Cursor<T> {
+    fn read_exact_at_many(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        let data = self.get_ref().as_ref();
+        for (offset, buf) in requests {
+            let source = usize::try_from(*offset).ok()
+                .and_then(|start| data.get(start..start.checked_add(buf.len())?))
+                .ok_or(Error::Truncated)?;
+            buf.copy_from_slice(source);
+        }
+        Ok(())
+    }
+}
+
+impl<R: ReadAtMany + ?Sized> ReadAtMany for &mut R {
+    fn read_exact_at_many(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        (**self).read_exact_at_many(requests)
+    }
+}
+
+/// Sources that can read the bytes at an offset through a shared reference, without a read cursor.
+///
+/// Unlike a [`Read`] and [`Seek`] source, one of these can be read by several threads at once without
+/// a mutex.  On unix and Windows, this is implemented for [`File`] with a positioned read (`pread`, or
+/// `ReadFile` with an offset).  Use [`ReadAtCursor`] to read it as a stream, e.g. with
+/// [`NpyFile::from_read_at`][`crate::NpyFile::from_read_at`].
+pub trait ReadAt {
+    /// Read bytes starting at an offset, returning how many were read.
+    ///
+    /// Like [`Read::read`], this may read fewer bytes than requested, and returns 0 at the end of the source.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    /// Get the size of the source in bytes.
+    fn size(&self) -> io::Result<u64>;
+}
+
+#[cfg(any(unix, windows))]
+impl ReadAt for File {
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        // this also moves the file cursor, but `ReadAtCursor` does not use it
+        std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let start = usize::try_from(offset).unwrap_or(usize::MAX).min(self.len());
+        let n = buf.len().min(self.len() - start);
+        buf[..n].copy_from_slice(&self[start..start + n]);
+        Ok(n)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self[..].read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &T {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for Arc<T> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+/// A [`Read`] and [`Seek`] adapter for a [`ReadAt`] source, which keeps its own position.
+///
+/// Each thread can read the same file through its own cursor, e.g. over a `&File` or an
+/// `Arc<File>`, without the reads of one thread moving the position of another.  It also implements
+/// [`ReadAtMany`], so [`NpyReader::read_many_at`][`crate::NpyReader::read_many_at`] reads directly from the source.
+///
+/// Reads go straight to the source, so small reads should be buffered, as they are by
+/// [`NpyReader`][`crate::NpyReader`].
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use std::fs::File;
+///
+/// let file = File::open("test-data/c-order.npy")?;
+/// let sums = std::thread::scope(|scope| {
+///     let threads = (0..4).map(|_| scope.spawn(|| -> std::io::Result<i64> {
+///         let npy = npyz::NpyFile::from_read_at(&file)?;
+///         Ok(npy.into_vec::<i64>()?.iter().sum())
+///     })).collect::<Vec<_>>();
+///     threads.into_iter().map(|thread| thread.join().unwrap()).collect::<std::io::Result<Vec<_>>>()
+/// })?;
+/// assert_eq!(sums, vec![84; 4]);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadAtCursor<T> {
+    source: T,
+    pos: u64,
+}
+
+impl<T: ReadAt> ReadAtCursor<T> {
+    /// Start reading from the beginning of a source.
+    pub fn new(source: T) -> Self {
+        ReadAtCursor { source, pos: 0 }
+    }
+
+    /// Get a reference to the source.
+    pub fn get_ref(&self) -> &T {
+        &self.source
+    }
+
+    /// Get the source back.
+    pub fn into_inner(self) -> T {
+        self.source
+    }
+}
+
+impl<T: ReadAt> Read for ReadAtCursor<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.source.read_at(buf, self.pos)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<T: ReadAt> Seek for ReadAtCursor<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.source.size()?.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        match new {
+            Some(new) => {
+                self.pos = new;
+                Ok(new)
+            },
+            None => Err(Error::InvalidInput("invalid seek to a negative or overflowing position".to_string()).into()),
+        }
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl<T: ReadAt> ReadAtMany for ReadAtCursor<T> {
+    fn read_exact_at_many(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        for (offset, buf) in requests {
+            let mut filled = 0;
+            while filled < buf.len() {
+                match self.source.read_at(&mut buf[filled..], *offset + filled as u64) {
+                    Ok(0) => return Err(Error::Truncated.into()),
+                    Ok(n) => filled += n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Read each range by seeking to it, and then return to the original position.
+#[cfg_attr(unix, allow(dead_code))]
+fn read_with_seeks(reader: &mut (impl Read + Seek), requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+    let position = reader.stream_position()?;
+    let result = requests.iter_mut().try_for_each(|(offset, buf)| {
+        reader.seek(SeekFrom::Start(*offset))?;
+        reader.read_exact(buf).map_err(truncated)
+    });
+    reader.seek(SeekFrom::Start(position))?;
+    result
+}
+
+pub(crate) fn truncated(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => Error::Truncated.into(),
+        _ => err,
+    }
+}
@@ -0,0 +1,201 @@
+//! Splitting and reshaping NPY files without reading them into memory.
+
+use std::io::{self, Read, Write};
+
+use crate::error::Error;
+use crate::read::{NpyHeader, Order};
+
+/// Split an NPY file into several NPY files along its outermost axis.
+///
+/// The outermost axis is the one that varies slowest in the data: the first axis of a C-order array,
+/// or the last axis of a Fortran-order array.  Every chunk except possibly the last has `chunk_len`
+/// elements along it, and the same size as the input along the other axes.  `new_chunk` is called with
+/// the index of each chunk to get a writer for it, and the number of chunks is returned.  An array with
+/// nothing along the outermost axis produces no chunks.
+///
+/// Each chunk has the dtype, order and [extra keys][`NpyHeader::extra_keys`] of the input.
+/// The data is copied without being decoded, a small buffer at a time, so this works for any dtype and
+/// any size of file.  The reader must initially be at the beginning of an NPY file.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use std::fs::File;
+///
+/// # let dir = std::env::temp_dir();
+/// # let chunk_path = |index| dir.join(format!("npyz-rechunk-doctest-{}-{}.npy", std::process::id(), index));
+/// let input = std::io::BufReader::new(File::open("test-data/c-order.npy")?);  // shape [2, 3, 4]
+/// let count = npyz::rechunk(input, 1, |index| Ok(std::io::BufWriter::new(File::create(chunk_path(index))?)))?;
+/// assert_eq!(count, 2);
+///
+/// let first = npyz::NpyFile::new(File::open(chunk_path(0))?)?;
+/// assert_eq!(first.shape(), &[1, 3, 4]);
+/// # for index in 0..count { std::fs::remove_file(chunk_path(index))?; }
+/// # Ok(()) }
+/// ```
+///
+/// # Panics
+///
+/// Panics if `chunk_len` is zero.
+pub fn rechunk<W: Write>(mut reader: impl Read, chunk_len: u64, mut new_chunk: impl FnMut(usize) -> io::Result<W>) -> io::Result<usize> {
+    assert!(chunk_len > 0, "chunk length must be nonzero");
+    let header = NpyHeader::from_reader(&mut reader)?;
+    let shape = header.shape();
+    let outer_axis = match (header.order(), shape.len()) {
+        (_, 0) => return Err(Error::InvalidInput("cannot split a 0-dimensional array".to_string()).into()),
+        (Order::C, _) => 0,
+        (Order::Fortran, ndim) => ndim - 1,
+    };
+    let outer_len = shape[outer_axis];
+    // bytes per index along the outermost axis
+    let stride_bytes = match outer_len {
+        0 => 0,
+        _ => header.len() / outer_len * header.dtype().num_bytes().expect("size was checked when constructing the header") as u64,
+    };
+
+    let mut count = 0;
+    let mut start = 0;
+    while start < outer_len {
+        let len = chunk_len.min(outer_len - start);
+        let mut chunk_shape = shape.to_vec();
+        chunk_shape[outer_axis] = len;
+
+        let mut writer = new_chunk(count)?;
+        writer.write_all(&crate::write::header_bytes(&header.dtype(), header.order(), &chunk_shape, header.extra_keys())?)?;
+        copy_exact(&mut reader, &mut writer, len * stride_bytes)?;
+        writer.flush()?;
+        count += 1;
+        start += len;
+    }
+    Ok(count)
+}
+
+/// Copy an NPY file with a different shape but the same number of elements.
+///
+/// The elements keep their positions in the data, so for a Fortran-order array, this is like numpy's
+/// `reshape(..., order='F')`.  The dtype, order and [extra keys][`NpyHeader::extra_keys`] are kept.
+/// The data is copied without being decoded, a small buffer at a time.
+///
+/// The reader must initially be at the beginning of an NPY file.  An error is returned if the new shape
+/// has a different number of elements, before anything is written.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let bytes = std::fs::read("test-data/c-order.npy")?;  // shape [2, 3, 4]
+/// let mut reshaped = vec![];
+/// npyz::reshape(&bytes[..], &mut reshaped, &[6, 4])?;
+///
+/// let npy = npyz::NpyFile::new(&reshaped[..])?;
+/// assert_eq!(npy.shape(), &[6, 4]);
+/// assert_eq!(npy.into_vec::<i64>()?, npyz::NpyFile::new(&bytes[..])?.into_vec::<i64>()?);
+/// # Ok(()) }
+/// ```
+pub fn reshape(mut reader: impl Read, mut writer: impl Write, shape: &[u64]) -> io::Result<()> {
+    let header = NpyHeader::from_reader(&mut reader)?;
+    let new_len = shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim));
+    if new_len != Some(header.len()) {
+        return Err(Error::InvalidInput(format!(
+            "cannot reshape an array of shape {:?} into shape {:?}", header.shape(), shape,
+        )).into());
+    }
+    writer.write_all(&crate::write::header_bytes(&header.dtype(), header.order(), shape, header.extra_keys())?)?;
+    let item_size = header.dtype().num_bytes().expect("size was checked when constructing the header");
+    copy_exact(reader, &mut writer, header.len() * item_size as u64)?;
+    writer.flush()
+}
+
+fn copy_exact(reader: impl Read, mut writer: impl Write, len: u64) -> io::Result<()> {
+    if io::copy(&mut reader.take(len), &mut writer)? < len {
+        return Err(Error::Truncated.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NpyFile, WriteOptions, WriterBuilder};
+
+    // Split into temporary files, and read them back.
+    fn split(bytes: &[u8], chunk_len: u64) -> io::Result<Vec<Vec<u8>>> {
+        static NEXT_ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let path = |index| std::env::temp_dir().join(format!("npyz-rechunk-{}-{}-{}.npy", std::process::id(), id, index));
+
+        let mut next_index = 0;
+        let result = rechunk(bytes, chunk_len, |index| {
+            assert_eq!(index, next_index);
+            next_index += 1;
+            std::fs::File::create(path(index))
+        });
+        let chunks = (0..next_index).map(|index| {
+            let chunk = std::fs::read(path(index))?;
+            std::fs::remove_file(path(index))?;
+            Ok(chunk)
+        }).collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(result?, chunks.len());
+        Ok(chunks)
+    }
+
+    #[test]
+    fn split_c_order() -> io::Result<()> {
+        let mut bytes = vec![];
+        let mut writer = WriteOptions::new().default_dtype().shape(&[5, 3]).writer(&mut bytes).begin_nd()?;
+        writer.extend(0..15_u32)?;
+        writer.finish()?;
+
+        let chunks = split(&bytes, 2)?;
+        assert_eq!(chunks.len(), 3);
+        let mut data = vec![];
+        for (chunk, expected_rows) in chunks.iter().zip([2, 2, 1]) {
+            let npy = NpyFile::new(&chunk[..])?;
+            assert_eq!(npy.shape(), &[expected_rows, 3]);
+            data.extend(npy.into_vec::<u32>()?);
+        }
+        assert_eq!(data, (0..15).collect::<Vec<_>>());
+        assert_eq!(split(&bytes, 100)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn split_fortran_order() -> io::Result<()> {
+        let bytes = std::fs::read("test-data/f-order.npy")?;
+        let chunks = split(&bytes, 3)?;
+        let shapes = chunks.iter().map(|chunk| Ok(NpyFile::new(&chunk[..])?.shape().to_vec())).collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(shapes, vec![vec![2, 3, 3], vec![2, 3, 1]]);
+        let data = chunks.iter().map(|chunk| NpyFile::new(&chunk[..])?.into_vec::<i64>()).collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(data.concat(), NpyFile::new(&bytes[..])?.into_vec::<i64>()?);
+        Ok(())
+    }
+
+    #[test]
+    fn split_edge_cases() -> io::Result<()> {
+        let mut bytes = vec![];
+        WriteOptions::<f32>::new().default_dtype().shape(&[0, 4]).writer(&mut bytes).begin_nd()?.finish()?;
+        assert!(split(&bytes, 1)?.is_empty());
+
+        let mut bytes = vec![];
+        WriteOptions::<f32>::new().default_dtype().shape(&[3, 0]).writer(&mut bytes).begin_nd()?.finish()?;
+        assert_eq!(split(&bytes, 2)?.len(), 2);
+
+        let mut bytes = vec![];
+        let mut writer = WriteOptions::new().default_dtype().shape(&[]).writer(&mut bytes).begin_nd()?;
+        writer.push(&1.0_f32)?;
+        writer.finish()?;
+        assert_eq!(split(&bytes, 1).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        let bytes = std::fs::read("test-data/c-order.npy")?;
+        assert_eq!(split(&bytes[..bytes.len() - 1], 1).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        Ok(())
+    }
+
+    #[test]
+    fn reshape_errors() -> io::Result<()> {
+        let bytes = std::fs::read("test-data/c-order.npy")?;
+        let mut out = vec![];
+        assert_eq!(reshape(&bytes[..], &mut out, &[5, 5]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(reshape(&bytes[..], &mut out, &[u64::MAX, 2]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+        assert_eq!(reshape(&bytes[..bytes.len() - 1], &mut out, &[24]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+//! Finding and replacing NaN, infinity and NaT in NPY files.
+
+use std::io::{self, Read, Write};
+
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::NpyFile;
+use crate::type_str::{Endianness, TypeChar};
+
+const WRITE_CHUNK_SIZE: usize = 1 << 16;
+const NAT: i64 = i64::MIN;
+
+/// What [`sanitize`] does with one kind of value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Fix<T> {
+    /// Leave the value as it is.
+    Keep,
+    /// Replace the value with another one.
+    Replace(T),
+    /// Fail with [`Error::InvalidData`], with the index of the record and the field in its
+    /// [context][`Error::context`].
+    Error,
+}
+
+/// The treatment of NaN, infinite and NaT values by [`sanitize`].
+///
+/// By default, every value is kept.  Use the setters to choose a [`Fix`] for each kind of value;
+/// e.g. the following does what numpy's `nan_to_num` does for `f8` data:
+///
+/// ```
+/// use npyz::{Fix, SanitizeOptions};
+///
+/// let options = SanitizeOptions::new()
+///     .nan(Fix::Replace(0.0))
+///     .posinf(Fix::Replace(f64::MAX))
+///     .neginf(Fix::Replace(f64::MIN));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SanitizeOptions {
+    nan: Fix<f64>,
+    posinf: Fix<f64>,
+    neginf: Fix<f64>,
+    nat: Fix<i64>,
+}
+
+impl Default for SanitizeOptions {
+    fn default() -> Self {
+        SanitizeOptions { nan: Fix::Keep, posinf: Fix::Keep, neginf: Fix::Keep, nat: Fix::Keep }
+    }
+}
+
+impl SanitizeOptions {
+    /// Construct the default options, which keep every value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set what happens to NaN floats.
+    pub fn nan(mut self, fix: Fix<f64>) -> Self {
+        self.nan = fix;
+        self
+    }
+
+    /// Set what happens to positive infinity.
+    pub fn posinf(mut self, fix: Fix<f64>) -> Self {
+        self.posinf = fix;
+        self
+    }
+
+    /// Set what happens to negative infinity.
+    pub fn neginf(mut self, fix: Fix<f64>) -> Self {
+        self.neginf = fix;
+        self
+    }
+
+    /// Set what happens to NaT (not-a-time) in `datetime64` and `timedelta64` values.  A replacement is
+    /// in the units of each field.
+    pub fn nat(mut self, fix: Fix<i64>) -> Self {
+        self.nat = fix;
+        self
+    }
+}
+
+/// The number of each kind of value found by [`sanitize`] or [`count_invalid`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct InvalidCounts {
+    /// The number of NaN floats.
+    pub nan: u64,
+    /// The number of floats that are positive infinity.
+    pub posinf: u64,
+    /// The number of floats that are negative infinity.
+    pub neginf: u64,
+    /// The number of `datetime64` and `timedelta64` values that are NaT.
+    pub nat: u64,
+}
+
+impl InvalidCounts {
+    /// Get the number of values of all kinds.
+    pub fn total(&self) -> u64 {
+        self.nan + self.posinf + self.neginf + self.nat
+    }
+}
+
+/// Copy an NPY file, replacing or rejecting NaN, infinite and NaT values as set in [`SanitizeOptions`].
+///
+/// Every float (`f4` and `f8`, and each part of `c8` and `c16`) and every `datetime64` and
+/// `timedelta64` in the file is checked, including those in the fields and subarrays of structured
+/// dtypes; other types are copied as they are.  (Half and extended precision floats are not checked.)
+/// A replacement is converted to the type of each value.  The file is read once, a small buffer at a
+/// time, and the number of each kind of value that was found is returned.
+///
+/// With [`Fix::Error`], the output is incomplete after an error.  To only check a file, use [`count_invalid`].
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{DType, Fix, SanitizeOptions};
+///
+/// let values = [1.0, f64::NAN, f64::INFINITY, 4.0].map(npyz::DynValue::Float);
+/// let mut input = vec![];
+/// npyz::write_dyn(&mut input, &DType::parse("'<f8'")?, &[4], values)?;
+///
+/// let mut output = vec![];
+/// let options = SanitizeOptions::new().nan(Fix::Replace(0.0)).posinf(Fix::Replace(1e9));
+/// let counts = npyz::sanitize(&input[..], &mut output, &options)?;
+/// assert_eq!((counts.nan, counts.posinf), (1, 1));
+/// assert_eq!(npyz::NpyFile::new(&output[..])?.into_vec::<f64>()?, vec![1.0, 0.0, 1e9, 4.0]);
+///
+/// let options = SanitizeOptions::new().nan(Fix::Error);
+/// let err = npyz::sanitize(&input[..], std::io::sink(), &options).unwrap_err();
+/// assert_eq!(err.to_string(), "found NaN (at record 1)");
+/// # Ok(()) }
+/// ```
+pub fn sanitize(input: impl Read, mut output: impl Write, options: &SanitizeOptions) -> io::Result<InvalidCounts> {
+    let npy = NpyFile::new(input)?;
+    let header = npy.header().clone();
+    let mut leaves = vec![];
+    collect_leaves(&header.dtype(), 0, &mut vec![], &mut leaves);
+    match header.raw_bytes() {
+        Some(raw) => output.write_all(raw)?,
+        None => output.write_all(&crate::write::header_bytes(&header.dtype(), header.order(), header.shape(), header.extra_keys())?)?,
+    }
+
+    let mut counts = InvalidCounts::default();
+    let mut records = npy.raw_records();
+    let mut buf = vec![];
+    let mut index = 0;
+    while let Some(record) = records.next_record()? {
+        let start = buf.len();
+        buf.extend_from_slice(record);
+        for leaf in &leaves {
+            leaf.fix(&mut buf[start + leaf.offset..], options, &mut counts).map_err(|e| {
+                let e = leaf.path.iter().rev().fold(e, |e, field| Error::__in_field(e, field));
+                Error::at_record(e, index, None)
+            })?;
+        }
+        if buf.len() >= WRITE_CHUNK_SIZE {
+            output.write_all(&buf)?;
+            buf.clear();
+        }
+        index += 1;
+    }
+    output.write_all(&buf)?;
+    output.flush()?;
+    Ok(counts)
+}
+
+/// Count the NaN, infinite and NaT values in an NPY file, which are found as described for [`sanitize`].
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let values = [f64::NAN, 2.0, f64::NEG_INFINITY, f64::NAN].map(npyz::DynValue::Float);
+/// let mut input = vec![];
+/// npyz::write_dyn(&mut input, &npyz::DType::parse("'<f4'")?, &[4], values)?;
+///
+/// let counts = npyz::count_invalid(&input[..])?;
+/// assert_eq!((counts.nan, counts.neginf, counts.total()), (2, 1, 3));
+/// # Ok(()) }
+/// ```
+pub fn count_invalid(input: impl Read) -> io::Result<InvalidCounts> {
+    sanitize(input, io::sink(), &SanitizeOptions::default())
+}
+
+// A value in each record that is checked.
+struct Leaf {
+    path: Vec<String>,
+    offset: usize,
+    kind: LeafKind,
+    big_endian: bool,
+}
+
+#[derive(Copy, Clone)]
+enum LeafKind {
+    F4,
+    F8,
+    Time,
+}
+
+fn collect_leaves(dtype: &DType, offset: usize, path: &mut Vec<String>, out: &mut Vec<Leaf>) {
+    match dtype {
+        DType::Plain(ty) => {
+            let big_endian = ty.endianness() == Endianness::Big;
+            let mut push = |kind, offset| out.push(Leaf { path: path.clone(), offset, kind, big_endian });
+            match (ty.type_char(), ty.size_field()) {
+                (TypeChar::Float, 4) => push(LeafKind::F4, offset),
+                (TypeChar::Float, 8) => push(LeafKind::F8, offset),
+                (TypeChar::Complex, 8) => (0..2).for_each(|i| push(LeafKind::F4, offset + 4 * i)),
+                (TypeChar::Complex, 16) => (0..2).for_each(|i| push(LeafKind::F8, offset + 8 * i)),
+                (TypeChar::DateTime | TypeChar::TimeDelta, _) => push(LeafKind::Time, offset),
+                _ => {},
+            }
+        },
+        DType::Array(len, inner) => {
+            let size = inner.num_bytes().expect("size was checked when reading the header");
+            for i in 0..*len as usize {
+                collect_leaves(inner, offset + i * size, path, out);
+            }
+        },
+        DType::Record(fields) => {
+            let offsets = dtype.field_offsets().expect("size was checked when reading the header");
+            for (field, field_offset) in fields.iter().zip(offsets) {
+                path.push(field.name.clone());
+                collect_leaves(&field.dtype, offset + field_offset, path, out);
+                path.pop();
+            }
+        },
+    }
+}
+
+impl Leaf {
+    fn fix(&self, bytes: &mut [u8], options: &SanitizeOptions, counts: &mut InvalidCounts) -> io::Result<()> {
+        match self.kind {
+            LeafKind::F4 | LeafKind::F8 => {
+                let x = match self.kind {
+                    LeafKind::F4 => f32::from_bits(self.read(bytes, 4) as u32) as f64,
+                    _ => f64::from_bits(self.read(bytes, 8)),
+                };
+                let (fix, count, what) = match x {
+                    x if x.is_nan() => (options.nan, &mut counts.nan, "NaN"),
+                    f64::INFINITY => (options.posinf, &mut counts.posinf, "positive infinity"),
+                    f64::NEG_INFINITY => (options.neginf, &mut counts.neginf, "negative infinity"),
+                    _ => return Ok(()),
+                };
+                *count += 1;
+                match fix {
+                    Fix::Keep => {},
+                    Fix::Replace(value) => match self.kind {
+                        LeafKind::F4 => self.write(bytes, 4, (value as f32).to_bits() as u64),
+                        _ => self.write(bytes, 8, value.to_bits()),
+                    },
+                    Fix::Error => return Err(Error::InvalidData(format!("found {}", what)).into()),
+                }
+            },
+            LeafKind::Time => {
+                if self.read(bytes, 8) as i64 != NAT {
+                    return Ok(());
+                }
+                counts.nat += 1;
+                match options.nat {
+                    Fix::Keep => {},
+                    Fix::Replace(value) => self.write(bytes, 8, value as u64),
+                    Fix::Error => return Err(Error::InvalidData("found NaT".to_string()).into()),
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn read(&self, bytes: &[u8], size: usize) -> u64 {
+        let mut le = [0; 8];
+        le[..size].copy_from_slice(&bytes[..size]);
+        if self.big_endian {
+            le[..size].reverse();
+        }
+        u64::from_le_bytes(le)
+    }
+
+    fn write(&self, bytes: &mut [u8], size: usize, value: u64) {
+        bytes[..size].copy_from_slice(&value.to_le_bytes()[..size]);
+        if self.big_endian {
+            bytes[..size].reverse();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{write_dyn, DynValue};
+
+    fn write(dtype: &str, rows: Vec<DynValue>) -> Vec<u8> {
+        let mut out = vec![];
+        write_dyn(&mut out, &DType::parse(dtype).unwrap(), &[rows.len() as u64], rows).unwrap();
+        out
+    }
+
+    #[test]
+    fn structured() {
+        let row = |x: f64, c: (f64, f64), t: Option<i64>| DynValue::Record(vec![
+            x.into(),
+            DynValue::Array(vec![DynValue::Complex(c.0, c.1), DynValue::Complex(1.0, 1.0)]),
+            DynValue::Time(t),
+            3u64.into(),
+        ]);
+        let dtype = "[('x', '>f4'), ('c', '<c16', (2,)), ('t', '>M8[s]'), ('n', '|u1')]";
+        let input = write(dtype, vec![
+            row(f64::NAN, (f64::INFINITY, f64::NAN), Some(1)),
+            row(1.0, (2.0, 3.0), None),
+        ]);
+        let options = SanitizeOptions::new()
+            .nan(Fix::Replace(-1.0))
+            .posinf(Fix::Replace(1e300))
+            .neginf(Fix::Error)
+            .nat(Fix::Replace(0));
+        let mut output = vec![];
+        let counts = sanitize(&input[..], &mut output, &options).unwrap();
+        assert_eq!(counts, InvalidCounts { nan: 2, posinf: 1, neginf: 0, nat: 1 });
+        assert_eq!(count_invalid(&output[..]).unwrap(), InvalidCounts::default());
+        let expected = write(dtype, vec![
+            row(-1.0, (1e300, -1.0), Some(1)),
+            row(1.0, (2.0, 3.0), Some(0)),
+        ]);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn errors_name_the_field() {
+        let input = write("[('a', '<i4'), ('b', [('c', '<f8')])]", vec![
+            DynValue::Record(vec![1i64.into(), DynValue::Record(vec![1.0.into()])]),
+            DynValue::Record(vec![2i64.into(), DynValue::Record(vec![f64::NEG_INFINITY.into()])]),
+        ]);
+        let err = sanitize(&input[..], io::sink(), &SanitizeOptions::new().neginf(Fix::Error)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = Error::from(err);
+        let context = err.context().unwrap();
+        assert_eq!(context.index(), Some(1));
+        assert_eq!(context.field(), &["b", "c"]);
+
+        let input = write("'<m8[D]'", vec![DynValue::Time(None)]);
+        let err = sanitize(&input[..], io::sink(), &SanitizeOptions::new().nat(Fix::Error)).unwrap_err();
+        assert!(err.to_string().contains("found NaT"), "{}", err);
+    }
+}
@@ -26,19 +26,22 @@ impl DType {
 pub struct ArrayReader<I, const N: usize>{ inner: I }
 pub struct ArrayWriter<I, const N: usize>{ inner: I }
 
-impl<I: TypeRead, const N: usize> TypeRead for ArrayReader<I, N>
-where I::Value: Copy + Default,
-{
+impl<I: TypeRead, const N: usize> TypeRead for ArrayReader<I, N> {
     type Value = [I::Value; N];
 
     #[inline]
     fn read_one<R: io::Read>(&self, mut reader: R) -> io::Result<Self::Value> {
-        let mut value = [I::Value::default(); N];
-        for place in &mut value {
-            *place = self.inner.read_one(&mut reader)?;
+        // (the elements need not be Copy or Default, so they are read into Options; after an error,
+        //  the rest are left as None)
+        let mut error = None;
+        let value = std::array::from_fn::<_, N, _>(|_| match error {
+            Some(_) => None,
+            None => self.inner.read_one(&mut reader).map_err(|e| error = Some(e)).ok(),
+        });
+        match error {
+            Some(e) => Err(e),
+            None => Ok(value.map(|x| x.expect("no error occurred"))),
         }
-
-        Ok(value)
     }
 }
 
@@ -58,14 +61,14 @@ where I::Value: Sized,
     }
 }
 
-impl<T: AutoSerialize + Default + Copy, const N: usize> AutoSerialize for [T; N] {
+impl<T: AutoSerialize, const N: usize> AutoSerialize for [T; N] {
     #[inline]
     fn default_dtype() -> DType {
         DType::Array(N as u64, Box::new(T::default_dtype()))
     }
 }
 
-impl<T: Deserialize + Default + Copy, const N: usize> Deserialize for [T; N] {
+impl<T: Deserialize, const N: usize> Deserialize for [T; N] {
     type TypeReader = ArrayReader<<T as Deserialize>::TypeReader, N>;
 
     #[inline]
@@ -0,0 +1,252 @@
+//! `numpy.datetime64` and `numpy.timedelta64`, with their units.
+
+use std::io;
+use std::time::{Duration, SystemTime};
+
+use crate::header::DType;
+use crate::type_str::{TypeStr, TypeChar, TimeUnits};
+use super::{DTypeError, TypeRead, TypeWrite, Serialize, Deserialize};
+use super::expect_scalar_dtype;
+use super::primitive::{PrimitiveReader, PrimitiveWriter};
+
+/// The value that numpy uses for NaT ("not a time").
+const NAT: i64 = i64::MIN;
+
+impl TimeUnits {
+    // The length of the unit in attoseconds, or `None` for the calendar units (years and months).
+    fn attoseconds(self) -> Option<i128> {
+        let seconds = 1_000_000_000_000_000_000;
+        Some(match self {
+            TimeUnits::Year | TimeUnits::Month => return None,
+            TimeUnits::Week => 7 * 24 * 60 * 60 * seconds,
+            TimeUnits::Day => 24 * 60 * 60 * seconds,
+            TimeUnits::Hour => 60 * 60 * seconds,
+            TimeUnits::Minute => 60 * seconds,
+            TimeUnits::Second => seconds,
+            TimeUnits::Millisecond => seconds / 1_000,
+            TimeUnits::Microsecond => seconds / 1_000_000,
+            TimeUnits::Nanosecond => seconds / 1_000_000_000,
+            TimeUnits::Picosecond => seconds / 1_000_000_000_000,
+            TimeUnits::Femtosecond => seconds / 1_000_000_000_000_000,
+            TimeUnits::Attosecond => 1,
+        })
+    }
+}
+
+// A signed count of units as a sign and a Duration, truncated to nanoseconds.
+fn to_signed_duration(value: i64, units: TimeUnits) -> Option<(bool, Duration)> {
+    if value == NAT {
+        return None;
+    }
+    let attos = (value as i128).checked_mul(units.attoseconds()?)?;
+    let nanos = attos.unsigned_abs() / 1_000_000_000;
+    let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+    Some((attos < 0, Duration::new(secs, (nanos % 1_000_000_000) as u32)))
+}
+
+macro_rules! define_time_type {
+    (
+        $(#[$attr:meta])*
+        $Type:ident, $Reader:ident, $Writer:ident, $type_char:path, $numpy_name:literal
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub struct $Type {
+            /// The number of units, or `i64::MIN` for NaT.
+            pub value: i64,
+            /// The units, from the type string of the dtype.
+            pub units: TimeUnits,
+        }
+
+        impl $Type {
+            /// Construct from a number of units.
+            pub const fn new(value: i64, units: TimeUnits) -> Self {
+                $Type { value, units }
+            }
+
+            /// Construct NaT ("not a time") in the given units.
+            pub const fn nat(units: TimeUnits) -> Self {
+                $Type { value: NAT, units }
+            }
+
+            /// Returns `true` if this is NaT ("not a time").
+            pub const fn is_nat(&self) -> bool {
+                self.value == NAT
+            }
+        }
+
+        #[doc(hidden)]
+        pub struct $Reader { int: PrimitiveReader<i64>, units: TimeUnits }
+        #[doc(hidden)]
+        pub struct $Writer { int: PrimitiveWriter<i64>, units: TimeUnits }
+
+        impl TypeRead for $Reader {
+            type Value = $Type;
+
+            #[inline]
+            fn read_one<R: io::Read>(&self, reader: R) -> io::Result<Self::Value> {
+                let value = self.int.read_one(reader)?;
+                Ok($Type { value, units: self.units })
+            }
+        }
+
+        impl TypeWrite for $Writer {
+            type Value = $Type;
+
+            #[inline]
+            fn write_one<W: io::Write>(&self, writer: W, value: &$Type) -> io::Result<()> {
+                // NaT is the same in every unit
+                if value.units != self.units && !value.is_nat() {
+                    return Err(crate::Error::InvalidInput(format!(
+                        concat!("cannot write a ", $numpy_name, " in units of {} to a dtype in units of {}"),
+                        value.units, self.units,
+                    )).into());
+                }
+                self.int.write_one(writer, &value.value)
+            }
+        }
+
+        impl Deserialize for $Type {
+            type TypeReader = $Reader;
+
+            fn reader(dtype: &DType) -> Result<Self::TypeReader, DTypeError> {
+                match expect_scalar_dtype::<Self>(dtype)? {
+                    &TypeStr { size: 8, endianness, type_char: $type_char, time_units: Some(units) } => {
+                        Ok($Reader { int: PrimitiveReader::new(endianness), units })
+                    },
+                    type_str => Err(DTypeError::bad_scalar::<Self>("read", type_str)),
+                }
+            }
+        }
+
+        impl Serialize for $Type {
+            type TypeWriter = $Writer;
+
+            fn writer(dtype: &DType) -> Result<Self::TypeWriter, DTypeError> {
+                match expect_scalar_dtype::<Self>(dtype)? {
+                    &TypeStr { size: 8, endianness, type_char: $type_char, time_units: Some(units) } => {
+                        Ok($Writer { int: PrimitiveWriter::new(endianness), units })
+                    },
+                    type_str => Err(DTypeError::bad_scalar::<Self>("write", type_str)),
+                }
+            }
+        }
+    };
+}
+
+define_time_type! {
+    /// A `numpy.datetime64`: a number of [`TimeUnits`] since the Unix epoch (1970-01-01T00:00:00).
+    ///
+    /// This can be read from or written to a `M8` dtype of any units.  When writing, the units must
+    /// match those of the dtype, except for NaT.  It does not implement [`AutoSerialize`][crate::AutoSerialize],
+    /// because there is no default for the units.  (`i64` can also be used for the value alone)
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::{DateTime64, TimeUnits, WriterBuilder};
+    ///
+    /// let dtype = npyz::DType::parse("'<M8[s]'").unwrap();
+    /// let times = [DateTime64::new(86400, TimeUnits::Second), DateTime64::nat(TimeUnits::Second)];
+    ///
+    /// let mut bytes = vec![];
+    /// let mut writer = npyz::WriteOptions::new().dtype(dtype).shape(&[2]).writer(&mut bytes).begin_nd()?;
+    /// writer.extend(times)?;
+    /// writer.finish()?;
+    ///
+    /// let read = npyz::NpyFile::new(&bytes[..])?.into_vec::<DateTime64>()?;
+    /// assert_eq!(read, times);
+    /// let expected = std::time::UNIX_EPOCH + std::time::Duration::from_secs(86400);
+    /// assert_eq!(read[0].to_system_time(), Some(expected));
+    /// assert!(read[1].is_nat());
+    /// # Ok(()) }
+    /// ```
+    DateTime64, DateTime64Reader, DateTime64Writer, TypeChar::DateTime, "datetime64"
+}
+
+define_time_type! {
+    /// A `numpy.timedelta64`: a signed number of [`TimeUnits`].
+    ///
+    /// This can be read from or written to a `m8` dtype of any units.  When writing, the units must
+    /// match those of the dtype, except for NaT.  It does not implement [`AutoSerialize`][crate::AutoSerialize],
+    /// because there is no default for the units.  (`i64` can also be used for the value alone)
+    TimeDelta64, TimeDelta64Reader, TimeDelta64Writer, TypeChar::TimeDelta, "timedelta64"
+}
+
+impl DateTime64 {
+    /// Convert to a [`SystemTime`], truncating to nanoseconds.
+    ///
+    /// Returns `None` for NaT, for the calendar units [`TimeUnits::Year`] and [`TimeUnits::Month`],
+    /// and for times that cannot be represented.
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        match to_signed_duration(self.value, self.units)? {
+            (false, duration) => SystemTime::UNIX_EPOCH.checked_add(duration),
+            (true, duration) => SystemTime::UNIX_EPOCH.checked_sub(duration),
+        }
+    }
+}
+
+impl TimeDelta64 {
+    /// Convert to a [`Duration`], truncating to nanoseconds.
+    ///
+    /// Returns `None` for NaT, for negative values, for the calendar units [`TimeUnits::Year`] and
+    /// [`TimeUnits::Month`], and for durations that cannot be represented.
+    pub fn to_duration(&self) -> Option<Duration> {
+        match to_signed_duration(self.value, self.units)? {
+            (false, duration) => Some(duration),
+            (true, duration) if duration.is_zero() => Some(duration),
+            (true, _) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[deny(unused)]
+mod tests {
+    use super::*;
+    use crate::serialize::test_helpers::*;
+
+    #[test]
+    fn read_write() {
+        let be = DType::parse("'>M8[ms]'").unwrap();
+        let le = DType::parse("'<M8[ms]'").unwrap();
+        let time = DateTime64::new(-5, TimeUnits::Millisecond);
+
+        assert_eq!(reader_output::<DateTime64>(&be, &blob![be(-5_i64)]), time);
+        assert_eq!(reader_output::<DateTime64>(&le, &blob![le(-5_i64)]), time);
+        assert_eq!(writer_output::<DateTime64>(&be, &time), blob![be(-5_i64)]);
+        assert_eq!(writer_output::<DateTime64>(&le, &time), blob![le(-5_i64)]);
+
+        let delta = DType::parse("'<m8[D]'").unwrap();
+        assert_eq!(reader_output::<TimeDelta64>(&delta, &blob![le(3_i64)]), TimeDelta64::new(3, TimeUnits::Day));
+        assert_eq!(writer_output::<TimeDelta64>(&delta, &TimeDelta64::nat(TimeUnits::Second)), blob![le(i64::MIN)]);
+    }
+
+    #[test]
+    fn wrong_dtype() {
+        let datetime = DType::parse("'<M8[ns]'").unwrap();
+        let timedelta = DType::parse("'<m8[ns]'").unwrap();
+        let int = DType::parse("'<i8'").unwrap();
+        reader_expect_err::<DateTime64>(&timedelta);
+        reader_expect_err::<DateTime64>(&int);
+        reader_expect_err::<TimeDelta64>(&datetime);
+        writer_expect_err::<TimeDelta64>(&int);
+
+        let err = DateTime64::writer(&datetime).unwrap()
+            .write_one(vec![], &DateTime64::new(1, TimeUnits::Microsecond)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn conversions() {
+        let second = Duration::from_secs(1);
+        assert_eq!(DateTime64::new(-1, TimeUnits::Second).to_system_time(), SystemTime::UNIX_EPOCH.checked_sub(second));
+        assert_eq!(DateTime64::new(1500, TimeUnits::Picosecond).to_system_time(), Some(SystemTime::UNIX_EPOCH + Duration::from_nanos(1)));
+        assert_eq!(DateTime64::new(1, TimeUnits::Year).to_system_time(), None);
+        assert_eq!(DateTime64::nat(TimeUnits::Second).to_system_time(), None);
+
+        assert_eq!(TimeDelta64::new(2, TimeUnits::Week).to_duration(), Some(Duration::from_secs(14 * 86400)));
+        assert_eq!(TimeDelta64::new(-2, TimeUnits::Week).to_duration(), None);
+        assert_eq!(TimeDelta64::new(i64::MAX, TimeUnits::Week).to_duration(), None);
+        assert_eq!(TimeDelta64::new(i64::MAX, TimeUnits::Attosecond).to_duration(), Some(Duration::from_nanos(i64::MAX as u64 / 1_000_000_000)));
+    }
+}
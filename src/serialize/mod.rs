@@ -9,6 +9,7 @@ mod test_helpers;
 
 pub use traits::{Serialize, Deserialize, AutoSerialize};
 pub use traits::{TypeRead, TypeWrite, TypeWriteDyn, TypeReadDyn, DTypeError};
+pub(crate) use traits::NativeLayout;
 use traits::{helper, ErrorKind};
 #[macro_use]
 mod traits;
@@ -16,15 +17,20 @@ mod traits;
 pub use slice::*;
 mod slice;
 
-pub use primitive::*;
+pub use primitive::Complex;
 mod primitive;
 
-pub use array_member::*;
+pub use datetime::{DateTime64, TimeDelta64};
+mod datetime;
+
 mod array_member;
 
+#[cfg(feature = "uom")]
+mod quantity;
+
 // helpers
 fn invalid_data<T: ToString>(message: T) -> io::Error {
-    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+    crate::Error::InvalidData(message.to_string()).into()
 }
 
 fn expect_scalar_dtype<T: ?Sized>(dtype: &DType) -> Result<&TypeStr, DTypeError> {
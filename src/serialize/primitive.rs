@@ -4,15 +4,18 @@ use std::io;
 use std::marker::PhantomData;
 
 #[cfg(feature = "complex")]
-use num_complex::Complex;
+use num_complex::Complex as NumComplex;
 
 use crate::header::DType;
 use crate::type_str::{TypeStr, Endianness, TypeChar};
 use super::{DTypeError, TypeRead, TypeWrite, Serialize, Deserialize, AutoSerialize};
-use super::{expect_scalar_dtype, invalid_data};
+use super::{expect_scalar_dtype, invalid_data, NativeLayout};
 
 /// Implementation detail of reading and writing for primitive types.
 pub trait PrimitiveReadWrite: Sized {
+    /// Whether every bit pattern is a valid value, so that values in the native byte order can be copied directly.
+    #[doc(hidden)]
+    const PLAIN_BYTES: bool = false;
     #[doc(hidden)]
     fn primitive_read_one<R: io::Read>(reader: R, swap_bytes: bool) -> io::Result<Self>;
     #[doc(hidden)]
@@ -22,6 +25,8 @@ pub trait PrimitiveReadWrite: Sized {
 macro_rules! derive_int_primitive_read_write {
     ($($int:ident)*) => {$(
         impl PrimitiveReadWrite for $int {
+            const PLAIN_BYTES: bool = true;
+
             #[inline]
             fn primitive_read_one<R: io::Read>(mut reader: R, swap_bytes: bool) -> io::Result<$int> {
                 use std::mem::size_of;
@@ -54,6 +59,8 @@ macro_rules! derive_int_primitive_read_write {
 macro_rules! derive_float_primitive_read_write {
     ($float:ident as $int:ident) => {
         impl PrimitiveReadWrite for $float {
+            const PLAIN_BYTES: bool = true;
+
             #[inline]
             fn primitive_read_one<R: io::Read>(reader: R, swap_bytes: bool) -> io::Result<$float> {
                 let bits = <$int>::primitive_read_one(reader, swap_bytes)?;
@@ -127,6 +134,14 @@ impl<T: PrimitiveReadWrite> TypeRead for PrimitiveReader<T> {
     fn read_one<R: io::Read>(&self, reader: R) -> io::Result<Self::Value> {
         T::primitive_read_one(reader, self.swap_bytes)
     }
+
+    #[inline(always)]
+    fn native_layout(&self) -> Option<NativeLayout> {
+        match T::PLAIN_BYTES && !self.swap_bytes {
+            true => Some(NativeLayout(())),
+            false => None,
+        }
+    }
 }
 
 impl<T: PrimitiveReadWrite> TypeWrite for PrimitiveWriter<T> {
@@ -136,37 +151,164 @@ impl<T: PrimitiveReadWrite> TypeWrite for PrimitiveWriter<T> {
     fn write_one<W: io::Write>(&self, writer: W, value: &Self::Value) -> io::Result<()> {
         value.primitive_write_one(writer, self.swap_bytes)
     }
+
+    #[inline(always)]
+    fn native_layout(&self) -> Option<NativeLayout> {
+        match T::PLAIN_BYTES && !self.swap_bytes {
+            true => Some(NativeLayout(())),
+            false => None,
+        }
+    }
+}
+
+/// A complex number, for reading and writing the `c8` and `c16` dtypes without the **`"complex"`** feature.
+///
+/// This has the same layout as `num_complex::Complex` (which can also be used, with that feature),
+/// and converts to and from it.  Pairs `(F, F)` of the real and imaginary parts can be used as well,
+/// but cannot be read or written as a bulk copy.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{Complex, WriterBuilder};
+///
+/// let mut bytes = vec![];
+/// let mut writer = npyz::WriteOptions::new().default_dtype().shape(&[2]).writer(&mut bytes).begin_nd()?;
+/// writer.extend([Complex::new(1.0_f64, 2.0), Complex::new(3.0, -1.0)])?;
+/// writer.finish()?;
+///
+/// let npy = npyz::NpyFile::new(&bytes[..])?;
+/// assert_eq!(npy.dtype().descr(), "'<c16'");
+/// assert_eq!(npy.into_vec::<(f64, f64)>()?, vec![(1.0, 2.0), (3.0, -1.0)]);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct Complex<F> {
+    /// The real part.
+    pub re: F,
+    /// The imaginary part.
+    pub im: F,
 }
 
+impl<F> Complex<F> {
+    /// Create a complex number from its real and imaginary parts.
+    pub const fn new(re: F, im: F) -> Self {
+        Complex { re, im }
+    }
+}
+
+impl<F> From<(F, F)> for Complex<F> {
+    fn from((re, im): (F, F)) -> Self {
+        Complex { re, im }
+    }
+}
+
+impl<F> From<Complex<F>> for (F, F) {
+    fn from(Complex { re, im }: Complex<F>) -> Self {
+        (re, im)
+    }
+}
+
+/// _This impl is only available with the **`"complex"`** feature._
 #[cfg(feature = "complex")]
-#[doc(hidden)]
-pub struct ComplexReader<F> { pub(super) float: PrimitiveReader<F> }
+impl<F> From<NumComplex<F>> for Complex<F> {
+    fn from(NumComplex { re, im }: NumComplex<F>) -> Self {
+        Complex { re, im }
+    }
+}
+
+/// _This impl is only available with the **`"complex"`** feature._
 #[cfg(feature = "complex")]
+impl<F> From<Complex<F>> for NumComplex<F> {
+    fn from(Complex { re, im }: Complex<F>) -> Self {
+        NumComplex { re, im }
+    }
+}
+
+/// Implementation detail of reading and writing for the floats in complex numbers.
+#[doc(hidden)]
+pub trait ComplexFloat: PrimitiveReadWrite {
+    const SIZE: u64;
+}
+
+impl ComplexFloat for f32 {
+    const SIZE: u64 = 4;
+}
+
+impl ComplexFloat for f64 {
+    const SIZE: u64 = 8;
+}
+
+/// Implementation detail of reading and writing for the types that represent complex numbers.
 #[doc(hidden)]
-pub struct ComplexWriter<F> { pub(super) float: PrimitiveWriter<F> }
+pub trait ComplexParts: Sized {
+    type Float: ComplexFloat;
+    /// Whether this is `repr(C)` with the real part followed by the imaginary part, and no padding.
+    const REPR_C: bool;
+    fn from_parts(re: Self::Float, im: Self::Float) -> Self;
+    fn parts(&self) -> (&Self::Float, &Self::Float);
+}
+
+impl<F: ComplexFloat> ComplexParts for Complex<F> {
+    type Float = F;
+    const REPR_C: bool = true;
+    fn from_parts(re: F, im: F) -> Self { Complex { re, im } }
+    fn parts(&self) -> (&F, &F) { (&self.re, &self.im) }
+}
 
 #[cfg(feature = "complex")]
-impl<F: PrimitiveReadWrite> TypeRead for ComplexReader<F> {
-    type Value = Complex<F>;
+impl<F: ComplexFloat> ComplexParts for NumComplex<F> {
+    type Float = F;
+    const REPR_C: bool = true;
+    fn from_parts(re: F, im: F) -> Self { NumComplex { re, im } }
+    fn parts(&self) -> (&F, &F) { (&self.re, &self.im) }
+}
+
+// (tuples have no guaranteed layout)
+impl<F: ComplexFloat> ComplexParts for (F, F) {
+    type Float = F;
+    const REPR_C: bool = false;
+    fn from_parts(re: F, im: F) -> Self { (re, im) }
+    fn parts(&self) -> (&F, &F) { (&self.0, &self.1) }
+}
+
+#[doc(hidden)]
+pub struct ComplexReader<C: ComplexParts> { float: PrimitiveReader<C::Float> }
+#[doc(hidden)]
+pub struct ComplexWriter<C: ComplexParts> { float: PrimitiveWriter<C::Float> }
+
+impl<C: ComplexParts> TypeRead for ComplexReader<C> {
+    type Value = C;
 
     #[inline]
     fn read_one<R: io::Read>(&self, mut reader: R) -> io::Result<Self::Value> {
         let re = self.float.read_one(&mut reader)?;
         let im = self.float.read_one(&mut reader)?;
-        Ok(Complex { re, im })
+        Ok(C::from_parts(re, im))
+    }
+
+    // A repr(C) struct with two fields of the same type has no padding.
+    #[inline(always)]
+    fn native_layout(&self) -> Option<NativeLayout> {
+        self.float.native_layout().filter(|_| C::REPR_C)
     }
 }
 
-#[cfg(feature = "complex")]
-impl<F: PrimitiveReadWrite> TypeWrite for ComplexWriter<F> {
-    type Value = Complex<F>;
+impl<C: ComplexParts> TypeWrite for ComplexWriter<C> {
+    type Value = C;
 
     #[inline]
-    fn write_one<W: io::Write>(&self, mut writer: W, value: &Complex<F>) -> io::Result<()> {
-        self.float.write_one(&mut writer, &value.re)?;
-        self.float.write_one(&mut writer, &value.im)?;
+    fn write_one<W: io::Write>(&self, mut writer: W, value: &C) -> io::Result<()> {
+        let (re, im) = value.parts();
+        self.float.write_one(&mut writer, re)?;
+        self.float.write_one(&mut writer, im)?;
         Ok(())
     }
+
+    #[inline(always)]
+    fn native_layout(&self) -> Option<NativeLayout> {
+        self.float.native_layout().filter(|_| C::REPR_C)
+    }
 }
 
 macro_rules! impl_primitive_serializable {
@@ -240,17 +382,14 @@ impl_primitive_serializable! {
 }
 
 macro_rules! impl_complex_serializable {
-    ( $( [ $size:literal $float:ident ] )+ ) => { $(
-        #[cfg(feature = "complex")]
-        /// _This impl is only available with the **`"complex"`** feature._
-        impl Deserialize for Complex<$float> {
-            type TypeReader = ComplexReader<$float>;
+    ( $( $(#[$attr:meta])* $ty:ty; )+ ) => { $(
+        $(#[$attr])*
+        impl<F: ComplexFloat> Deserialize for $ty {
+            type TypeReader = ComplexReader<Self>;
 
             fn reader(dtype: &DType) -> Result<Self::TypeReader, DTypeError> {
-                const SIZE: u64 = 2 * $size;
-
                 match expect_scalar_dtype::<Self>(dtype)? {
-                    &TypeStr { size: SIZE, endianness, type_char: TypeChar::Complex, .. } => {
+                    &TypeStr { size, endianness, type_char: TypeChar::Complex, .. } if size == 2 * F::SIZE => {
                         Ok(ComplexReader { float: PrimitiveReader::new(endianness) })
                     },
                     type_str => Err(DTypeError::bad_scalar::<Self>("read", type_str)),
@@ -258,16 +397,13 @@ macro_rules! impl_complex_serializable {
             }
         }
 
-        #[cfg(feature = "complex")]
-        /// _This impl is only available with the **`"complex"`** feature._
-        impl Serialize for Complex<$float> {
-            type TypeWriter = ComplexWriter<$float>;
+        $(#[$attr])*
+        impl<F: ComplexFloat> Serialize for $ty {
+            type TypeWriter = ComplexWriter<Self>;
 
             fn writer(dtype: &DType) -> Result<Self::TypeWriter, DTypeError> {
-                const SIZE: u64 = 2 * $size;
-
                 match expect_scalar_dtype::<Self>(dtype)? {
-                    &TypeStr { size: SIZE, endianness, type_char: TypeChar::Complex, .. } => {
+                    &TypeStr { size, endianness, type_char: TypeChar::Complex, .. } if size == 2 * F::SIZE => {
                         Ok(ComplexWriter { float: PrimitiveWriter::new(endianness) })
                     },
                     type_str => Err(DTypeError::bad_scalar::<Self>("write", type_str)),
@@ -275,17 +411,22 @@ macro_rules! impl_complex_serializable {
             }
         }
 
-        #[cfg(feature = "complex")]
-        /// _This impl is only available with the **`"complex"`** feature._
-        impl AutoSerialize for Complex<$float> {
+        $(#[$attr])*
+        impl<F: ComplexFloat> AutoSerialize for $ty {
             fn default_dtype() -> DType {
-                DType::new_scalar(TypeStr::with_auto_endianness(TypeChar::Complex, $size, None))
+                DType::new_scalar(TypeStr::with_auto_endianness(TypeChar::Complex, 2 * F::SIZE, None))
             }
         }
     )+};
 }
 
-impl_complex_serializable! { [ 4 f32 ] [ 8 f64 ] }
+impl_complex_serializable! {
+    Complex<F>;
+    (F, F);
+    #[cfg(feature = "complex")]
+    /// _This impl is only available with the **`"complex"`** feature._
+    NumComplex<F>;
+}
 
 
 #[cfg(test)]
@@ -353,8 +494,8 @@ mod tests {
         assert!(DType::parse("'|b2'").is_err());
         let dtype = DType::parse("'|b1'").unwrap();
 
-        assert_eq!(reader_output::<bool>(&dtype, &[0]), false);
-        assert_eq!(reader_output::<bool>(&dtype, &[1]), true);
+        assert!(!reader_output::<bool>(&dtype, &[0]));
+        assert!(reader_output::<bool>(&dtype, &[1]));
         reader_expect_read_err::<bool>(&dtype, &[2]);
         reader_expect_read_err::<bool>(&dtype, &[255]);
 
@@ -371,8 +512,8 @@ mod tests {
         let be_bytes = blob![be(c.re.to_bits()), be(c.im.to_bits())];
         let le_bytes = blob![le(c.re.to_bits()), le(c.im.to_bits())];
 
-        let be = DType::parse(&format!("'>c16'")).unwrap();
-        let le = DType::parse(&format!("'<c16'")).unwrap();
+        let be = DType::parse("'>c16'").unwrap();
+        let le = DType::parse("'<c16'").unwrap();
 
         assert_eq!(reader_output::<Complex64>(&be, &be_bytes), c);
         assert_eq!(reader_output::<Complex64>(&le, &le_bytes), c);
@@ -383,13 +524,43 @@ mod tests {
         let be_bytes = blob![be(c.re.to_bits()), be(c.im.to_bits())];
         let le_bytes = blob![le(c.re.to_bits()), le(c.im.to_bits())];
 
-        let be = DType::parse(&format!("'>c8'")).unwrap();
-        let le = DType::parse(&format!("'<c8'")).unwrap();
+        let be = DType::parse("'>c8'").unwrap();
+        let le = DType::parse("'<c8'").unwrap();
 
         assert_eq!(reader_output::<Complex32>(&be, &be_bytes), c);
         assert_eq!(reader_output::<Complex32>(&le, &le_bytes), c);
         assert_eq!(writer_output::<Complex32>(&be, &c), be_bytes);
         assert_eq!(writer_output::<Complex32>(&le, &c), le_bytes);
+        assert_eq!(Complex32::default_dtype(), DType::parse("'<c8'").unwrap().to_native_endian());
+        assert_eq!(crate::Complex::from(c), crate::Complex::new(42.0, 63.0));
+    }
+
+    #[test]
+    fn complex_without_num_complex() {
+        let c = Complex::new(42.0_f64, 63.0);
+        let be_bytes = blob![be(c.re.to_bits()), be(c.im.to_bits())];
+        let le_bytes = blob![le(c.re.to_bits()), le(c.im.to_bits())];
+
+        let be = DType::parse("'>c16'").unwrap();
+        let le = DType::parse("'<c16'").unwrap();
+
+        assert_eq!(reader_output::<Complex<f64>>(&be, &be_bytes), c);
+        assert_eq!(reader_output::<Complex<f64>>(&le, &le_bytes), c);
+        assert_eq!(writer_output::<Complex<f64>>(&be, &c), be_bytes);
+        assert_eq!(writer_output::<(f64, f64)>(&le, &(42.0, 63.0)), le_bytes);
+        assert_eq!(reader_output::<(f64, f64)>(&be, &be_bytes), (42.0, 63.0));
+
+        let c = Complex::new(42.0_f32, 63.0);
+        let le_bytes = blob![le(c.re.to_bits()), le(c.im.to_bits())];
+        let le = DType::parse("'<c8'").unwrap();
+        assert_eq!(reader_output::<Complex<f32>>(&le, &le_bytes), c);
+        assert_eq!(writer_output::<(f32, f32)>(&le, &(42.0, 63.0)), le_bytes);
+
+        assert_eq!(Complex::<f32>::default_dtype(), DType::parse("'<c8'").unwrap().to_native_endian());
+        assert_eq!(<(f64, f64)>::default_dtype(), DType::parse("'<c16'").unwrap().to_native_endian());
+        reader_expect_err::<Complex<f32>>(&be);
+        reader_expect_err::<(f64, f64)>(&DType::parse("'<f8'").unwrap());
+        writer_expect_err::<Complex<f64>>(&le);
     }
 
     #[test]
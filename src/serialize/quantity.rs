@@ -0,0 +1,107 @@
+//! `uom` quantities.
+
+use std::io;
+use std::marker::PhantomData;
+
+use uom::si::{Dimension, Quantity, Units};
+use uom::num::Num;
+use uom::Conversion;
+
+use crate::header::DType;
+use super::{DTypeError, TypeRead, TypeWrite, Serialize, Deserialize, AutoSerialize, NativeLayout};
+
+#[doc(hidden)]
+pub struct QuantityReader<D: ?Sized, U: ?Sized, V: Deserialize> {
+    value: V::TypeReader,
+    _marker: PhantomData<(PhantomData<D>, PhantomData<U>)>,
+}
+
+#[doc(hidden)]
+pub struct QuantityWriter<D: ?Sized, U: ?Sized, V: Serialize> {
+    value: V::TypeWriter,
+    _marker: PhantomData<(PhantomData<D>, PhantomData<U>)>,
+}
+
+impl<D, U, V> TypeRead for QuantityReader<D, U, V>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V> + Deserialize,
+{
+    type Value = Quantity<D, U, V>;
+
+    #[inline]
+    fn read_one<R: io::Read>(&self, reader: R) -> io::Result<Self::Value> {
+        let value = self.value.read_one(reader)?;
+        Ok(Quantity { dimension: PhantomData, units: PhantomData, value })
+    }
+
+    // Quantity is repr(transparent) over its value.
+    #[inline(always)]
+    fn native_layout(&self) -> Option<NativeLayout> {
+        self.value.native_layout()
+    }
+}
+
+impl<D, U, V> TypeWrite for QuantityWriter<D, U, V>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V> + Serialize,
+{
+    type Value = Quantity<D, U, V>;
+
+    #[inline]
+    fn write_one<W: io::Write>(&self, writer: W, quantity: &Self::Value) -> io::Result<()> {
+        self.value.write_one(writer, &quantity.value)
+    }
+
+    #[inline(always)]
+    fn native_layout(&self) -> Option<NativeLayout> {
+        self.value.native_layout()
+    }
+}
+
+/// The value is read in the base units of `U` (e.g. meters for an [`si::f64::Length`][uom::si::f64::Length]).
+///
+/// _This impl is only available with the **`"uom"`** feature._
+impl<D, U, V> Deserialize for Quantity<D, U, V>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V> + Deserialize,
+{
+    type TypeReader = QuantityReader<D, U, V>;
+
+    fn reader(dtype: &DType) -> Result<Self::TypeReader, DTypeError> {
+        Ok(QuantityReader { value: V::reader(dtype)?, _marker: PhantomData })
+    }
+}
+
+/// The value is written in the base units of `U` (e.g. meters for an [`si::f64::Length`][uom::si::f64::Length]).
+///
+/// _This impl is only available with the **`"uom"`** feature._
+impl<D, U, V> Serialize for Quantity<D, U, V>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V> + Serialize,
+{
+    type TypeWriter = QuantityWriter<D, U, V>;
+
+    fn writer(dtype: &DType) -> Result<Self::TypeWriter, DTypeError> {
+        Ok(QuantityWriter { value: V::writer(dtype)?, _marker: PhantomData })
+    }
+}
+
+/// _This impl is only available with the **`"uom"`** feature._
+impl<D, U, V> AutoSerialize for Quantity<D, U, V>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V> + AutoSerialize,
+{
+    fn default_dtype() -> DType {
+        V::default_dtype()
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::io;
 use std::convert::TryFrom;
+use std::marker::PhantomData;
 
 #[cfg(feature = "arrayvec")]
 use arrayvec::{ArrayVec, ArrayString};
@@ -233,7 +234,7 @@ impl<const N: usize> Deserialize for FixedSizeBytes<N> {
         let type_str = expect_scalar_dtype::<Self>(dtype)?;
         let size = size_field_as_usize(type_str)?;
         if (type_str.type_char, size) != (TypeChar::RawData, N) {
-            return Err(DTypeError::bad_scalar::<Self>("read", &type_str));
+            return Err(DTypeError::bad_scalar::<Self>("read", type_str));
         };
         Ok(FixedSizeBytesReader { })
     }
@@ -258,7 +259,7 @@ impl<const N: usize> Serialize for FixedSizeBytes<N> {
         let type_str = expect_scalar_dtype::<Self>(dtype)?;
         let size = size_field_as_usize(type_str)?;
         if (type_str.type_char, size) != (TypeChar::RawData, N) {
-            return Err(DTypeError::bad_scalar::<Self>("write", &type_str));
+            return Err(DTypeError::bad_scalar::<Self>("write", type_str));
         };
         Ok(FixedSizeBytesWriter { })
     }
@@ -377,7 +378,7 @@ impl TypeRead for Utf32StringReader {
         for _ in 0..self.num_u32s {
             string.push(self.char_reader.read_one(&mut reader)?);
         }
-        while string.chars().next_back() == Some('\0') {
+        while string.ends_with('\0') {
             string.pop();
         }
         Ok(string)
@@ -489,7 +490,7 @@ impl Deserialize for Vec<u32> {
     fn reader(dtype: &DType) -> Result<Self::TypeReader, DTypeError> {
         let type_str = expect_scalar_dtype::<Self>(dtype)?;
         if type_str.type_char != TypeChar::UnicodeStr {
-            return Err(DTypeError::bad_scalar::<Self>("read", &type_str));
+            return Err(DTypeError::bad_scalar::<Self>("read", type_str));
         };
 
         let num_u32s = size_field_as_usize(type_str)?;
@@ -504,7 +505,7 @@ impl Deserialize for Vec<char> {
     fn reader(dtype: &DType) -> Result<Self::TypeReader, DTypeError> {
         let type_str = expect_scalar_dtype::<Self>(dtype)?;
         if type_str.type_char != TypeChar::UnicodeStr {
-            return Err(DTypeError::bad_scalar::<Self>("read", &type_str));
+            return Err(DTypeError::bad_scalar::<Self>("read", type_str));
         };
 
         let num_u32s = size_field_as_usize(type_str)?;
@@ -524,7 +525,7 @@ impl Deserialize for String {
             Ok(StringReader::Utf8(imp?))
         } else {
             let type_str = expect_scalar_dtype::<Self>(dtype)?;
-            Err(DTypeError::bad_scalar::<Self>("read", &type_str))
+            Err(DTypeError::bad_scalar::<Self>("read", type_str))
         }
     }
 }
@@ -539,7 +540,7 @@ impl<const N: usize> Deserialize for ArrayVec<u32, N> {
         let num_u32s_in_dtype = size_field_as_usize(type_str)?;
 
         if type_str.type_char != TypeChar::UnicodeStr {
-            return Err(DTypeError::bad_scalar::<Self>("read", &type_str));
+            return Err(DTypeError::bad_scalar::<Self>("read", type_str));
         };
 
         let codepoint_reader = CodePointReader { int_reader: PrimitiveReader::new(type_str.endianness) };
@@ -557,7 +558,7 @@ impl<const N: usize> Deserialize for ArrayVec<char, N> {
         let num_u32s_in_dtype = size_field_as_usize(type_str)?;
 
         if type_str.type_char != TypeChar::UnicodeStr {
-            return Err(DTypeError::bad_scalar::<Self>("read", &type_str));
+            return Err(DTypeError::bad_scalar::<Self>("read", type_str));
         };
 
         let char_reader = CharReader { int_reader: PrimitiveReader::new(type_str.endianness) };
@@ -575,7 +576,7 @@ impl<const N: usize> Deserialize for ArrayString<N> {
             Some(string_reader) => Ok(Utf8ArrayStringReader { string_reader: string_reader? }),
             None => {
                 let type_str = expect_scalar_dtype::<Self>(dtype)?;
-                Err(DTypeError::bad_scalar::<Self>("read", &type_str))
+                Err(DTypeError::bad_scalar::<Self>("read", type_str))
             },
         }
     }
@@ -703,13 +704,89 @@ impl TypeWrite for StrWriter {
     }
 }
 
+/// Wrapper around a string that truncates it to fit the dtype when writing, instead of failing.
+///
+/// This supports the same dtypes as `String`.  For `UN`, the string is truncated to `N` chars,
+/// and for `SN`, it is truncated to the longest valid UTF-8 prefix of at most `N` bytes.
+/// Shorter strings are padded with nulls, as usual.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{Truncate, WriterBuilder};
+///
+/// let mut bytes = vec![];
+/// let dtype = npyz::DType::parse("'<U4'").unwrap();
+/// let mut writer = npyz::WriteOptions::new().dtype(dtype).shape(&[2]).writer(&mut bytes).begin_nd()?;
+/// writer.extend([Truncate("red"), Truncate("yellow")])?;
+/// writer.finish()?;
+///
+/// let strings = npyz::NpyFile::new(&bytes[..])?.into_vec::<String>()?;
+/// assert_eq!(strings, vec!["red", "yell"]);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Truncate<S>(pub S);
+
+#[doc(hidden)]
+pub struct TruncateWriter<S> {
+    inner: StrWriter,
+    _marker: PhantomData<fn(&S)>,
+}
+
+#[doc(hidden)]
+pub struct TruncateReader(StringReader);
+
+impl<S: AsRef<str>> TypeWrite for TruncateWriter<S> {
+    type Value = Truncate<S>;
+
+    fn write_one<W: io::Write>(&self, w: W, value: &Truncate<S>) -> io::Result<()> {
+        let str = value.0.as_ref();
+        let end = match &self.inner {
+            StrWriter::Utf8(imp) => {
+                let mut end = usize::min(str.len(), imp.num_bytes);
+                while !str.is_char_boundary(end) {
+                    end -= 1;
+                }
+                end
+            },
+            StrWriter::Utf32(imp) => str.char_indices().nth(imp.num_u32s).map_or(str.len(), |(index, _)| index),
+        };
+        self.inner.write_one(w, &str[..end])
+    }
+}
+
+impl TypeRead for TruncateReader {
+    type Value = Truncate<String>;
+
+    fn read_one<R: io::Read>(&self, reader: R) -> io::Result<Truncate<String>> {
+        self.0.read_one(reader).map(Truncate)
+    }
+}
+
+impl<S: AsRef<str>> Serialize for Truncate<S> {
+    type TypeWriter = TruncateWriter<S>;
+
+    fn writer(dtype: &DType) -> Result<Self::TypeWriter, DTypeError> {
+        let inner = str::writer(dtype)?;
+        Ok(TruncateWriter { inner, _marker: PhantomData })
+    }
+}
+
+impl Deserialize for Truncate<String> {
+    type TypeReader = TruncateReader;
+
+    fn reader(dtype: &DType) -> Result<Self::TypeReader, DTypeError> {
+        String::reader(dtype).map(TruncateReader)
+    }
+}
+
 impl Serialize for [u32] {
     type TypeWriter = Utf32WithSurrogatesWriter;
 
     fn writer(dtype: &DType) -> Result<Self::TypeWriter, DTypeError> {
         let type_str = expect_scalar_dtype::<Self>(dtype)?;
         if type_str.type_char != TypeChar::UnicodeStr {
-            return Err(DTypeError::bad_scalar::<Self>("write", &type_str));
+            return Err(DTypeError::bad_scalar::<Self>("write", type_str));
         };
 
         let num_u32s = size_field_as_usize(type_str)?;
@@ -725,7 +802,7 @@ impl Serialize for [char] {
     fn writer(dtype: &DType) -> Result<Self::TypeWriter, DTypeError> {
         let type_str = expect_scalar_dtype::<Self>(dtype)?;
         if type_str.type_char != TypeChar::UnicodeStr {
-            return Err(DTypeError::bad_scalar::<Self>("write", &type_str));
+            return Err(DTypeError::bad_scalar::<Self>("write", type_str));
         };
 
         let num_u32s = size_field_as_usize(type_str)?;
@@ -752,7 +829,7 @@ impl Serialize for str {
                 let type_str = type_str.clone();
                 Ok(StrWriter::Utf8(Utf8StrWriter { type_str, num_bytes }))
             },
-            _ => Err(DTypeError::bad_scalar::<Self>("write", &type_str)),
+            _ => Err(DTypeError::bad_scalar::<Self>("write", type_str)),
         }
     }
 }
@@ -845,7 +922,7 @@ mod tests {
 
     #[test]
     fn bytes_any_endianness() {
-        for ty in vec!["'<S3'", "'>S3'", "'|S3'"] {
+        for ty in ["'<S3'", "'>S3'", "'|S3'"] {
             let ty = DType::parse(ty).unwrap();
             assert_eq!(writer_output::<[u8]>(&ty, &[1, 3, 5][..]), blob![1, 3, 5]);
             assert_eq!(reader_output::<Vec<u8>>(&ty, &[1, 3, 5][..]), blob![1, 3, 5]);
@@ -944,6 +1021,23 @@ mod tests {
         writer_expect_write_err(&v_3, &ArrayVec::<u8, 4>::from_iter([1]));
     }
 
+    #[test]
+    fn write_truncated() {
+        let s_3 = DType::parse("'|S3'").unwrap();
+        let u_3 = DType::parse("'>U3'").unwrap();
+        let v_3 = DType::parse("'|V3'").unwrap();
+
+        assert_eq!(writer_output(&u_3, &Truncate("abcd")), blob![be('a' as u32), be('b' as u32), be('c' as u32)]);
+        assert_eq!(writer_output(&u_3, &Truncate("ab")), blob![be('a' as u32), be('b' as u32), be(0_u32)]);
+        assert_eq!(writer_output(&u_3, &Truncate("\u{2014}\u{2014}\u{2014}\u{2014}")), blob![be(0x2014_u32), be(0x2014_u32), be(0x2014_u32)]);
+        assert_eq!(writer_output(&s_3, &Truncate(String::from("abcd"))), blob![b'a', b'b', b'c']);
+        // a 2-byte char does not fit in the last byte
+        assert_eq!(writer_output(&s_3, &Truncate("ab\u{e9}")), blob![b'a', b'b', 0]);
+        writer_expect_err::<Truncate<&str>>(&v_3);
+
+        assert_eq!(reader_output::<Truncate<String>>(&s_3, &blob![b'a', b'b', 0]), Truncate(String::from("ab")));
+    }
+
     #[test]
     fn fixed_size_bytes_restrictions() {
         let s_3 = DType::parse("'|S3'").unwrap();
@@ -52,8 +52,7 @@ mod helper_funcs {
     pub fn reader_expect_read_ok<T: Deserialize>(dtype: &DType, bytes: &[u8]) {
         let mut reader = bytes;
         T::reader(dtype).unwrap_or_else(|e| panic!("{}", e))
-            .read_one(&mut reader)
-            .ok().expect("reader_expect_read_ok failed!");
+            .read_one(&mut reader).expect("reader_expect_read_ok failed!");
         assert_eq!(reader.len(), 0, "reader did not read all bytes");
     }
     pub fn reader_expect_read_err<T: Deserialize>(dtype: &DType, bytes: &[u8]) {
@@ -78,14 +77,13 @@ mod helper_funcs {
     pub fn writer_expect_write_ok<T: Serialize + ?Sized>(dtype: &DType, value: &T) {
         let mut vec = vec![];
         T::writer(dtype).unwrap_or_else(|e| panic!("{}", e))
-            .write_one(&mut vec, value)
-            .ok().expect("writer_expect_write_ok failed!");
+            .write_one(&mut vec, value).expect("writer_expect_write_ok failed!");
     }
     pub fn writer_expect_write_err<T: Serialize + ?Sized>(dtype: &DType, value: &T) {
         let mut vec = vec![];
         T::writer(dtype).unwrap_or_else(|e| panic!("{}", e))
             .write_one(&mut vec, value)
-            .err().expect("writer_expect_write_err failed!");
+            .expect_err("writer_expect_write_err failed!");
     }
 }
 
@@ -14,7 +14,7 @@ use crate::type_matchup_docs;
 ///
 /// Examples of types that implement this:
 ///
-/// * Primitive integers, floats, `Complex` (with the **`"complex"`** feature)
+/// * Primitive integers, floats, [`Complex`][`crate::Complex`] (and `num_complex::Complex` with the **`"complex"`** feature)
 /// * Owned containers (`Vec<u8>`, `String`)
 ///
 /// _This trait is derivable when enabling the **`"derive"`** feature._ This makes it easier
@@ -47,7 +47,7 @@ pub trait Deserialize: Sized {
 ///
 /// Examples of types that implement this:
 ///
-/// * Primitive integers, floats, `Complex` (with the **`"complex"`** feature)
+/// * Primitive integers, floats, [`Complex`][`crate::Complex`] (and `num_complex::Complex` with the **`"complex"`** feature)
 /// * Slice types (`[u8]`, `str`)
 ///
 /// _This trait is derivable when enabling the **`"derive"`** feature._ This makes it easier
@@ -112,8 +112,24 @@ pub trait TypeRead {
     /// The function.
     fn read_one<R: io::Read>(&self, bytes: R) -> io::Result<Self::Value>
         where Self: Sized;
+
+    /// Returns `Some` if [`Self::read_one`] is equivalent to reading `size_of::<Self::Value>()` bytes
+    /// and reinterpreting them, so that many values can be read with a single copy.
+    #[doc(hidden)]
+    #[inline(always)]
+    fn native_layout(&self) -> Option<NativeLayout> {
+        None
+    }
 }
 
+/// Proof that a [`TypeRead`] or [`TypeWrite`] reads or writes values by copying their bytes unchanged.
+///
+/// This can only be constructed by the builtin readers for primitive types, so that an incorrect
+/// implementation outside of this crate cannot cause undefined behavior.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct NativeLayout(pub(crate) ());
+
 /// Like some sort of `for<W: io::Write> Fn(W, &T) -> io::Result<()>`.
 ///
 /// To obtain one of these, use the [`Serialize`] trait.
@@ -132,6 +148,14 @@ pub trait TypeWrite {
     /// The function.
     fn write_one<W: io::Write>(&self, writer: W, value: &Self::Value) -> io::Result<()>
         where Self: Sized;
+
+    /// Returns `Some` if [`Self::write_one`] is equivalent to writing the `size_of::<Self::Value>()` bytes
+    /// of the value unchanged, so that many values can be written with a single copy.
+    #[doc(hidden)]
+    #[inline(always)]
+    fn native_layout(&self) -> Option<NativeLayout> {
+        None
+    }
 }
 
 /// The proper trait to use for trait objects of [`TypeRead`].
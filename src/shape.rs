@@ -0,0 +1,117 @@
+//! Shapes with a number of dimensions known at compile time.
+
+use std::ops::Deref;
+
+use crate::error::Error;
+
+/// The shape of an array with exactly `N` dimensions.
+///
+/// Get one from a file with [`NpyHeader::shape_as`][`crate::NpyHeader::shape_as`], which fails right away
+/// if the file has a different number of dimensions, so that the lengths can be taken apart by pattern
+/// matching instead of indexing into [`NpyHeader::shape`][`crate::NpyHeader::shape`].  It derefs to
+/// `[u64]`, so it can be passed directly to [`WriterBuilder::shape`][`crate::WriterBuilder::shape`].
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{Shape, WriterBuilder};
+///
+/// let npy = npyz::NpyFile::new(std::fs::File::open("test-data/c-order.npy")?)?;
+/// let Shape([planes, rows, cols]) = npy.shape_as::<3>()?;
+/// assert_eq!((planes, rows, cols), (2, 3, 4));
+/// assert!(npy.shape_as::<2>().is_err());
+///
+/// let shape = npyz::Shape2::new([rows, cols]);
+/// let mut out = vec![];
+/// let mut writer = npyz::WriteOptions::new().default_dtype().shape(&shape).writer(&mut out).begin_nd()?;
+/// writer.extend(0..shape.num_elements() as i32)?;
+/// writer.finish()?;
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Shape<const N: usize>(pub [u64; N]);
+
+/// The shape of a 1-dimensional array.
+pub type Shape1 = Shape<1>;
+/// The shape of a 2-dimensional array.
+pub type Shape2 = Shape<2>;
+/// The shape of a 3-dimensional array.
+pub type Shape3 = Shape<3>;
+
+impl<const N: usize> Shape<N> {
+    /// Construct a shape from the length along each axis.
+    pub fn new(dims: [u64; N]) -> Self {
+        Shape(dims)
+    }
+
+    /// Get the length along each axis.
+    pub fn dims(self) -> [u64; N] {
+        self.0
+    }
+
+    /// Get the total number of elements.  (This is the product of the lengths)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the product overflows a `u64`.  This cannot happen for a shape read from a file.
+    pub fn num_elements(&self) -> u64 {
+        self.0.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim)).expect("number of elements overflows a u64")
+    }
+}
+
+/// Returns [`Error::InvalidInput`] if the slice does not have exactly `N` elements.
+impl<const N: usize> TryFrom<&[u64]> for Shape<N> {
+    type Error = Error;
+
+    fn try_from(shape: &[u64]) -> Result<Self, Error> {
+        match <[u64; N]>::try_from(shape) {
+            Ok(dims) => Ok(Shape(dims)),
+            Err(_) => Err(Error::InvalidInput(format!("expected a {}-dimensional shape, got {:?}", N, shape))),
+        }
+    }
+}
+
+impl<const N: usize> From<[u64; N]> for Shape<N> {
+    fn from(dims: [u64; N]) -> Self {
+        Shape(dims)
+    }
+}
+
+impl<const N: usize> From<Shape<N>> for [u64; N] {
+    fn from(shape: Shape<N>) -> Self {
+        shape.0
+    }
+}
+
+impl<const N: usize> Deref for Shape<N> {
+    type Target = [u64];
+
+    fn deref(&self) -> &[u64] {
+        &self.0
+    }
+}
+
+impl<const N: usize> AsRef<[u64]> for Shape<N> {
+    fn as_ref(&self) -> &[u64] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn conversions() {
+        let shape = Shape::<2>::try_from(&[3, 4][..]).unwrap();
+        assert_eq!(shape, Shape([3, 4]));
+        assert_eq!(&shape[..], &[3, 4]);
+        assert_eq!(shape.num_elements(), 12);
+        assert_eq!(Shape([]).num_elements(), 1);
+        assert_eq!(Shape([5, 0]).num_elements(), 0);
+
+        let err = io::Error::from(Shape::<3>::try_from(&[3, 4][..]).unwrap_err());
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("3-dimensional"), "{}", err);
+    }
+}
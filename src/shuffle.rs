@@ -0,0 +1,226 @@
+//! Shuffling the records of NPY files that do not fit in memory.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::read::{NpyHeader, Order};
+use crate::read_at::truncated;
+
+// More buckets than this could run out of file descriptors.
+const MAX_BUCKETS: u64 = 256;
+
+/// Write the rows of an NPY file to a new NPY file in a random order, using a bounded amount of memory.
+///
+/// The rows are the entries along the first axis, e.g. the records of a 1-D structured array, or the
+/// samples of a `[samples, features]` array.  The output has the same dtype, shape, order and
+/// [extra keys][`NpyHeader::extra_keys`] as the input.  The rows are moved without being decoded, so this works
+/// for any dtype.  The order is determined by `seed`: the same input and seed always give the same output.
+///
+/// If the data fits in `memory_budget` bytes, it is shuffled in memory.  Otherwise, each row is
+/// first appended to one of several temporary files chosen at random, and then each temporary file is
+/// read, shuffled in memory and appended to the output.  Every order of the rows is equally likely either
+/// way.  The temporary files are created next to `output` (named after it, with a `.shuffle-N.tmp` suffix)
+/// and deleted afterwards.  They are each about half of the budget on average, however, no more than
+/// 256 of them are used, so for very small budgets relative to the size of the data, they are larger.
+///
+/// Returns [`Error::InvalidInput`] for a 0-dimensional array or a multidimensional array in Fortran order,
+/// where rows are not contiguous.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::WriterBuilder;
+///
+/// # let dir = std::env::temp_dir();
+/// # let input = dir.join(format!("npyz-shuffle-doctest-in-{}.npy", std::process::id()));
+/// # let output = dir.join(format!("npyz-shuffle-doctest-out-{}.npy", std::process::id()));
+/// let mut writer = npyz::WriteOptions::new().default_dtype().shape(&[100]).writer(std::fs::File::create(&input)?).begin_nd()?;
+/// writer.extend(0..100_i64)?;
+/// writer.finish()?;
+///
+/// npyz::shuffle_file(&input, &output, 42, 1 << 20)?;
+///
+/// let mut shuffled = npyz::NpyFile::new(std::fs::File::open(&output)?)?.into_vec::<i64>()?;
+/// assert_ne!(shuffled, (0..100).collect::<Vec<_>>());
+/// shuffled.sort();
+/// assert_eq!(shuffled, (0..100).collect::<Vec<_>>());
+/// # std::fs::remove_file(&input)?;
+/// # std::fs::remove_file(&output)?;
+/// # Ok(()) }
+/// ```
+pub fn shuffle_file(input: impl AsRef<Path>, output: impl AsRef<Path>, seed: u64, memory_budget: usize) -> io::Result<()> {
+    let output = output.as_ref();
+    let mut input = BufReader::new(File::open(input)?);
+    let header = NpyHeader::from_reader(&mut input)?;
+    let num_rows = match (header.shape(), header.order()) {
+        ([], _) => return Err(Error::InvalidInput("cannot shuffle a 0-dimensional array".to_string()).into()),
+        ([_, _, ..], Order::Fortran) => {
+            return Err(Error::InvalidInput("cannot shuffle the rows of a Fortran-order array".to_string()).into());
+        },
+        (shape, _) => shape[0],
+    };
+    let item_size = header.dtype().num_bytes().expect("size was checked when constructing the header") as u64;
+    let data_len = header.len() * item_size;
+    let row_len = match num_rows {
+        0 => 0,
+        _ => (data_len / num_rows) as usize,
+    };
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    writer.write_all(&crate::write::header_bytes(&header.dtype(), header.order(), header.shape(), header.extra_keys())?)?;
+    let mut rng = SplitMix64(seed);
+    if row_len == 0 {
+        // every order of zero-sized rows is the same
+    } else if data_len <= memory_budget as u64 {
+        let mut rows = vec![0; data_len as usize];
+        input.read_exact(&mut rows).map_err(truncated)?;
+        shuffle_rows(&mut rows, row_len, &mut rng);
+        writer.write_all(&rows)?;
+    } else {
+        let num_buckets = (data_len / (memory_budget as u64 / 2).max(1) + 1).min(MAX_BUCKETS);
+        let paths = (0..num_buckets).map(|i| bucket_path(output, i)).collect::<Vec<_>>();
+        let result = shuffle_with_buckets(&mut input, &mut writer, &paths, num_rows, row_len, memory_budget, &mut rng);
+        for path in &paths {
+            let _ = fs::remove_file(path);
+        }
+        result?;
+    }
+    writer.flush()
+}
+
+fn shuffle_with_buckets(
+    input: &mut impl Read,
+    output: &mut impl Write,
+    paths: &[PathBuf],
+    num_rows: u64,
+    row_len: usize,
+    memory_budget: usize,
+    rng: &mut SplitMix64,
+) -> io::Result<()> {
+    // deal the rows out to the buckets at random
+    let buffer_size = (memory_budget / 2 / paths.len()).clamp(4096, 1 << 16);
+    let mut buckets = paths.iter()
+        .map(|path| Ok(BufWriter::with_capacity(buffer_size, File::create(path)?)))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut row = vec![0; row_len];
+    for _ in 0..num_rows {
+        input.read_exact(&mut row).map_err(truncated)?;
+        buckets[rng.below(paths.len() as u64) as usize].write_all(&row)?;
+    }
+    for bucket in &mut buckets {
+        bucket.flush()?;
+    }
+    drop(buckets);
+
+    // then shuffle each bucket in memory
+    let mut rows = vec![];
+    for path in paths {
+        rows.clear();
+        File::open(path)?.read_to_end(&mut rows)?;
+        shuffle_rows(&mut rows, row_len, rng);
+        output.write_all(&rows)?;
+    }
+    Ok(())
+}
+
+fn bucket_path(output: &Path, index: u64) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".shuffle-{}.tmp", index));
+    output.with_file_name(name)
+}
+
+// Fisher-Yates, on rows of `row_len` bytes.
+fn shuffle_rows(rows: &mut [u8], row_len: usize, rng: &mut SplitMix64) {
+    let num_rows = rows.len() / row_len;
+    for i in (1..num_rows).rev() {
+        let j = rng.below(i as u64 + 1) as usize;
+        if i != j {
+            let (head, tail) = rows.split_at_mut(i * row_len);
+            head[j * row_len..(j + 1) * row_len].swap_with_slice(&mut tail[..row_len]);
+        }
+    }
+}
+
+/// A small, fast generator whose output only depends on the seed.
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // A number in `0..n`, by rejection sampling so that every value is equally likely.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NpyFile, WriteOptions, WriterBuilder};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("npyz-shuffle-{}-{}.npy", name, std::process::id()))
+    }
+
+    #[test]
+    fn shuffle() -> io::Result<()> {
+        let (input, output) = (temp_path("in"), temp_path("out"));
+        let rows = (0..1000_u32).map(|i| [i, i * 2, i * 3]).collect::<Vec<_>>();
+        let mut writer = WriteOptions::new().default_dtype().shape(&[1000, 3]).writer(File::create(&input)?).begin_nd()?;
+        writer.extend(rows.iter().flatten().copied())?;
+        writer.finish()?;
+
+        let mut outputs = vec![];
+        // in memory, with a few buckets, and with the most buckets
+        for budget in [1 << 20, 4000, 0] {
+            shuffle_file(&input, &output, 7, budget)?;
+            let npy = NpyFile::new(File::open(&output)?)?;
+            assert_eq!(npy.shape(), &[1000, 3]);
+            let mut shuffled = npy.into_vec::<u32>()?.chunks(3).map(|row| [row[0], row[1], row[2]]).collect::<Vec<_>>();
+            assert_ne!(shuffled, rows);
+            outputs.push(shuffled.clone());
+            shuffled.sort();
+            assert_eq!(shuffled, rows);
+            assert!(!bucket_path(&output, 0).exists());
+        }
+        shuffle_file(&input, &output, 7, 4000)?;
+        assert_eq!(NpyFile::new(File::open(&output)?)?.into_vec::<u32>()?, outputs[1].concat());
+        shuffle_file(&input, &output, 8, 4000)?;
+        assert_ne!(NpyFile::new(File::open(&output)?)?.into_vec::<u32>()?, outputs[1].concat());
+
+        let mut writer = WriteOptions::new().default_dtype().shape(&[2, 2]).order(Order::Fortran).writer(File::create(&input)?).begin_nd()?;
+        writer.extend([1_u8, 2, 3, 4])?;
+        writer.finish()?;
+        assert_eq!(shuffle_file(&input, &output, 0, 100).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        fs::remove_file(&input)?;
+        fs::remove_file(&output)
+    }
+
+    #[test]
+    fn uniform() {
+        // every permutation of 3 rows should come up about equally often
+        let mut counts = std::collections::HashMap::new();
+        let mut rng = SplitMix64(1);
+        for _ in 0..6000 {
+            let mut rows = [0_u8, 1, 2];
+            shuffle_rows(&mut rows, 1, &mut rng);
+            *counts.entry(rows).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 6);
+        assert!(counts.values().all(|&count| (800..1200).contains(&count)), "{:?}", counts);
+    }
+}
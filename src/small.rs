@@ -0,0 +1,287 @@
+//! Reading and writing many small NPY files with little per-file overhead.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::{self, NpyHeader, Order, ReadOptions};
+use crate::serialize::{AutoSerialize, Deserialize, DTypeError, Serialize, TypeWrite};
+
+/// Reads many small NPY files of the same element type, reusing work between them.
+///
+/// For arrays of a few hundred bytes, the fixed cost of opening an [`NpyFile`][`crate::NpyFile`]
+/// (parsing the header, looking up a reader for the dtype, and the allocations for both) outweighs the cost
+/// of decoding the data.  A `BatchReader` remembers the most recent header along with its reader,
+/// and when the next file begins with exactly the same header bytes, neither is created again.
+/// Files are read into a scratch buffer that is reused, and the `*_into` methods also allow the
+/// output `Vec` to be reused.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = npyz::BatchReader::<i64>::new();
+/// let mut out = vec![];
+/// for _ in 0..3 {
+///     out.clear();
+///     reader.read_file_into("test-data/c-order.npy", &mut out)?;
+///     assert_eq!(out.len(), 24);
+/// }
+/// assert_eq!(reader.header().unwrap().shape(), &[2, 3, 4]);
+/// # Ok(()) }
+/// ```
+///
+/// [Diagnostics][`ReadOptions::on_diagnostic`] are only emitted when a header is actually parsed,
+/// i.e. not for files whose header is the same as that of the previous file.
+pub struct BatchReader<T: Deserialize> {
+    options: ReadOptions,
+    scratch: Vec<u8>,
+    cached: Option<(NpyHeader, T::TypeReader)>,
+}
+
+impl<T: Deserialize> BatchReader<T> {
+    /// Construct a reader with the default [`ReadOptions`].
+    pub fn new() -> Self {
+        Self::with_options(ReadOptions::default())
+    }
+
+    /// Construct a reader that checks every header against the given [`ReadOptions`].
+    pub fn with_options(options: ReadOptions) -> Self {
+        BatchReader { options, scratch: vec![], cached: None }
+    }
+
+    /// Get the header of the most recently read file.
+    pub fn header(&self) -> Option<&NpyHeader> {
+        self.cached.as_ref().map(|(header, _)| header)
+    }
+
+    /// Read all elements of an NPY file that is already in memory.
+    pub fn read_bytes(&mut self, bytes: &[u8]) -> io::Result<Vec<T>> {
+        let mut out = vec![];
+        self.read_bytes_into(bytes, &mut out)?;
+        Ok(out)
+    }
+
+    /// Read all elements of an NPY file that is already in memory, appending them to `out`.
+    ///
+    /// On error, `out` may contain some of the elements.
+    pub fn read_bytes_into(&mut self, bytes: &[u8], out: &mut Vec<T>) -> io::Result<()> {
+        let (header, type_reader) = self.prepare(bytes)?;
+        let data = &bytes[header.raw_bytes().expect("header was read from bytes").len()..];
+        read::read_all_records(header, type_reader, data, out)
+    }
+
+    /// Read all elements of an NPY file on the filesystem.
+    pub fn read_file(&mut self, path: impl AsRef<Path>) -> io::Result<Vec<T>> {
+        let mut out = vec![];
+        self.read_file_into(path, &mut out)?;
+        Ok(out)
+    }
+
+    /// Read all elements of an NPY file on the filesystem, appending them to `out`.
+    ///
+    /// The file is read in its entirety with a single buffer that is kept for the next call.
+    pub fn read_file_into(&mut self, path: impl AsRef<Path>, out: &mut Vec<T>) -> io::Result<()> {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        let result = File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut scratch))
+            .and_then(|_| self.read_bytes_into(&scratch, out));
+        self.scratch = scratch;
+        result
+    }
+
+    // Get the header and type reader for a file, parsing the header only if it differs from the last one.
+    fn prepare(&mut self, bytes: &[u8]) -> io::Result<(&NpyHeader, &T::TypeReader)> {
+        let reusable = match &self.cached {
+            Some((header, _)) => bytes.starts_with(header.raw_bytes().expect("header was read from bytes")),
+            None => false,
+        };
+        if !reusable {
+            // forget the old header first, so that it is not reused for a later file after an error
+            self.cached = None;
+            let header = NpyHeader::from_reader_with_options(bytes, &self.options)?;
+            let type_reader = match T::reader(&header.dtype()) {
+                Ok(type_reader) => type_reader,
+                Err(e) => return Err(Error::dtype_mismatch::<T>(&header.dtype(), e).into()),
+            };
+            self.cached = Some((header, type_reader));
+        }
+        let (header, type_reader) = self.cached.as_ref().expect("was just set");
+        Ok((header, type_reader))
+    }
+}
+
+impl<T: Deserialize> Default for BatchReader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Deserialize> fmt::Debug for BatchReader<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BatchReader")
+            .field("options", &self.options)
+            .field("dtype", &self.header().map(|header| header.dtype()))
+            .field("shape", &self.header().map(|header| header.shape()))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Writes many small C-order NPY files with the same dtype, reusing work between them.
+///
+/// The writer for the dtype is created once, the header is only regenerated when the shape changes,
+/// and each file is assembled in a reusable buffer and written with a single call.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let mut writer = npyz::BatchWriter::<f32>::new();
+/// let mut bytes = vec![];
+/// writer.write_to_vec(&[1.0, 2.0, 3.0, 4.0], &[2, 2], &mut bytes)?;
+///
+/// let npy = npyz::NpyFile::new(&bytes[..])?;
+/// assert_eq!(npy.shape(), &[2, 2]);
+/// assert_eq!(npy.into_vec::<f32>()?, vec![1.0, 2.0, 3.0, 4.0]);
+/// # Ok(()) }
+/// ```
+pub struct BatchWriter<T: Serialize> {
+    dtype: DType,
+    item_size: Option<usize>,
+    type_writer: T::TypeWriter,
+    scratch: Vec<u8>,
+    // the shape of the last file, and its header
+    cached: Option<(Vec<u64>, Vec<u8>)>,
+}
+
+impl<T: AutoSerialize> BatchWriter<T> {
+    /// Construct a writer that uses [`AutoSerialize::default_dtype`].
+    pub fn new() -> Self {
+        Self::with_dtype(T::default_dtype()).expect("default dtype is always supported")
+    }
+}
+
+impl<T: AutoSerialize> Default for BatchWriter<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Serialize> BatchWriter<T> {
+    /// Construct a writer for files with the given dtype.
+    pub fn with_dtype(dtype: DType) -> Result<Self, DTypeError> {
+        let type_writer = T::writer(&dtype)?;
+        Ok(BatchWriter { item_size: dtype.num_bytes(), dtype, type_writer, scratch: vec![], cached: None })
+    }
+
+    /// Append a complete NPY file for an array of the given shape to `out`.
+    ///
+    /// The number of elements must match the shape.
+    pub fn write_to_vec(&mut self, data: &[T], shape: &[u64], out: &mut Vec<u8>) -> io::Result<()> {
+        if let DType::Array(..) = self.dtype {
+            return Err(Error::InvalidInput(format!("the outermost dtype cannot be an array (got: {:?})", self.dtype)).into());
+        }
+        let expected_num_items = shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim));
+        if expected_num_items != Some(data.len() as u64) {
+            return Err(Error::InvalidInput(format!("shape {:?} does not match the {} item(s) given", shape, data.len())).into());
+        }
+
+        let reusable = matches!(&self.cached, Some((cached_shape, _)) if cached_shape == shape);
+        if !reusable {
+            let header = crate::write::header_bytes(&self.dtype, Order::C, shape, &[])?;
+            self.cached = Some((shape.to_vec(), header));
+        }
+        out.extend_from_slice(&self.cached.as_ref().expect("was just set").1);
+
+        let size = std::mem::size_of::<T>();
+        match self.type_writer.native_layout().filter(|_| self.item_size == Some(size) && size > 0) {
+            Some(_proof) => {
+                // SAFETY: NativeLayout guarantees that T has no padding, so all of its bytes are initialized.
+                let bytes = unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) };
+                out.extend_from_slice(bytes);
+            },
+            None => data.iter().try_for_each(|value| self.type_writer.write_one(&mut *out, value))?,
+        }
+        Ok(())
+    }
+
+    /// Write a complete NPY file for an array of the given shape to the filesystem.
+    ///
+    /// The file is assembled in a buffer that is kept for the next call, and written with a single call.
+    pub fn write_file(&mut self, path: impl AsRef<Path>, data: &[T], shape: &[u64]) -> io::Result<()> {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        let result = self.write_to_vec(data, shape, &mut scratch).and_then(|()| std::fs::write(path, &scratch));
+        self.scratch = scratch;
+        result
+    }
+}
+
+impl<T: Serialize> fmt::Debug for BatchWriter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BatchWriter")
+            .field("dtype", &self.dtype)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NpyFile;
+
+    #[test]
+    fn reuses_header() -> io::Result<()> {
+        let a = std::fs::read("test-data/c-order.npy")?;
+        let b = std::fs::read("test-data/f-order.npy")?;
+        let mut reader = BatchReader::<i64>::new();
+        for bytes in [&a, &a, &b, &a] {
+            assert_eq!(reader.read_bytes(bytes)?, NpyFile::new(&bytes[..])?.into_vec::<i64>()?);
+            assert_eq!(reader.header().unwrap().raw_bytes(), NpyFile::new(&bytes[..])?.raw_bytes());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn read_errors() -> io::Result<()> {
+        let bytes = std::fs::read("test-data/c-order.npy")?;
+        let mut reader = BatchReader::<i64>::new();
+        reader.read_bytes(&bytes)?;
+        let err = reader.read_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(reader.read_bytes(b"not an npy file").is_err());
+        assert!(reader.header().is_none());
+
+        let err = BatchReader::<f32>::new().read_bytes(&bytes).unwrap_err();
+        assert!(matches!(Error::from(err).root(), Error::DTypeMismatch { .. }));
+        Ok(())
+    }
+
+    #[test]
+    fn non_native() -> io::Result<()> {
+        let mut bytes = vec![];
+        let mut writer = BatchWriter::<i32>::with_dtype(DType::new_scalar(">i4".parse().unwrap())).unwrap();
+        writer.write_to_vec(&[1, -2, 3], &[3], &mut bytes)?;
+        assert_eq!(BatchReader::<i32>::new().read_bytes(&bytes)?, vec![1, -2, 3]);
+        assert_eq!(&bytes[bytes.len() - 4..], &[0, 0, 0, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn write_many() -> io::Result<()> {
+        let mut writer = BatchWriter::<u16>::new();
+        let mut reader = BatchReader::<u16>::new();
+        let mut bytes = vec![];
+        for (data, shape) in [(&[1, 2][..], &[2][..]), (&[3, 4], &[2]), (&[5, 6], &[1, 2]), (&[], &[0, 4])] {
+            bytes.clear();
+            writer.write_to_vec(data, shape, &mut bytes)?;
+            assert_eq!(reader.read_bytes(&bytes)?, data);
+            assert_eq!(reader.header().unwrap().shape(), shape);
+            let mut expected = vec![];
+            crate::write::to_writer_nd(&mut expected, data, shape)?;
+            assert_eq!(bytes, expected);
+        }
+        assert_eq!(writer.write_to_vec(&[1, 2, 3], &[2], &mut bytes).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        Ok(())
+    }
+}
@@ -23,9 +23,12 @@
 //! }
 //! ```
 //!
-//! No methods are provided on these types beyond reading and writing.  If you want to do sparse
-//! matrix math, then you should use the data you have read to construct a matrix type from a
-//! dedicated sparse matrix library.
+//! Beyond reading and writing, the only methods provided on these types check that they are well-formed
+//! (e.g. [`CsrBase::validate`]), convert between the COO, CSR and CSC formats (e.g. [`CooBase::to_csr`]),
+//! or convert to a dense array (e.g. [`SparseBase::to_dense`]).  If you want to do sparse matrix math,
+//! then you should use the data you have read to construct a matrix type from a dedicated sparse matrix
+//! library.  With the **`"nalgebra"`** feature, the COO, CSR and CSC types convert to and from those of
+//! [`nalgebra_sparse`](https://docs.rs/nalgebra-sparse) with [`TryFrom`] and [`From`].
 //!
 //! For instance, an example of how to use this module to save and load CSR matrices from the
 //! [`sprs`](https://crates.io/crates/sprs) crate can be found
@@ -34,15 +37,20 @@
 //! _This module requires the **`"npz"`** feature._
 
 use std::io;
-use std::ops::Deref;
+use std::marker::PhantomData;
+use std::ops::{AddAssign, Deref};
 
 use zip::read::ZipFile;
 
 use crate::serialize::{Deserialize, AutoSerialize};
-use crate::read::{Order, NpyFile};
+use crate::read::{Order, NpyFile, NpyReader};
+use crate::cast::{Casting, CastReader, CastTarget};
 use crate::write::{WriterBuilder};
+use crate::diagnostics::{self, Diagnostic};
 use crate::npz::{NpzArchive, NpzWriter};
+use crate::npz_parallel::SharedReader;
 use crate::header::DType;
+use crate::type_str::TypeChar;
 
 // =============================================================================
 // Types
@@ -272,6 +280,7 @@ impl<T: Deserialize> Csr<T> {
         let shape = extract_shape(npz, "shape")?;
         let indices = extract_indices(npz, "indices")?;
         let indptr = extract_usize_indices(npz, "indptr")?;
+        check_sorted(npz, "csr", &indices, &indptr);
         let data = extract_1d::<T, _>(npz, "data")?;
         Ok(Csr { data, shape, indices, indptr })
     }
@@ -284,6 +293,7 @@ impl<T: Deserialize> Csc<T> {
         let shape = extract_shape(npz, "shape")?;
         let indices = extract_indices(npz, "indices")?;
         let indptr = extract_usize_indices(npz, "indptr")?;
+        check_sorted(npz, "csc", &indices, &indptr);
         let data = extract_1d::<T, _>(npz, "data")?;
         Ok(Csc { data, shape, indices, indptr })
     }
@@ -307,12 +317,127 @@ impl<T: Deserialize> Bsr<T> {
         let shape = extract_shape(npz, "shape")?;
         let indices = extract_indices(npz, "indices")?;
         let indptr = extract_usize_indices(npz, "indptr")?;
+        check_sorted(npz, "bsr", &indices, &indptr);
         let (data, data_shape) = extract_nd::<T, _>(npz, "data", 3)?;
         let blocksize = [data_shape[1], data_shape[2]];
         Ok(Bsr { data, shape, indices, indptr, blocksize })
     }
 }
 
+impl<T: Deserialize> Csr<T> {
+    /// Prepare to read a sparse `csr_matrix` saved by `scipy.sparse.save_npz` one row at a time.
+    ///
+    /// Only `indptr` is loaded into memory; the column indices and elements of each row are decoded
+    /// as the rows are iterated over.  See [`CsrRowReader`].
+    pub fn rows_from_npz<R: io::Read + io::Seek>(npz: NpzArchive<R>) -> io::Result<CsrRowReader<T, R>> {
+        CsrRowReader::new(npz)
+    }
+}
+
+/// Reads the rows of a sparse `csr_matrix` in an NPZ file one at a time, for matrices too large to load.
+///
+/// Created by [`Csr::rows_from_npz`].  The archive is consumed because the column indices and elements are
+/// read side by side, through separate handles to the underlying reader.
+///
+/// ```rust
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::sparse::Csr;
+///
+/// let npz = npyz::npz::NpzArchive::open("test-data/sparse/csr.npz")?;
+/// let mut reader = Csr::<i64>::rows_from_npz(npz)?;
+/// assert_eq!(reader.shape(), [3, 6]);
+/// for row in reader.rows()? {
+///     let (row_index, cols, data) = row?;
+///     println!("row {}: columns {:?} have values {:?}", row_index, cols, data);
+/// }
+/// # Ok(()) }
+/// ```
+pub struct CsrRowReader<T, R: io::Read + io::Seek> {
+    shape: [u64; 2],
+    indptr: Vec<usize>,
+    indices_npz: NpzArchive<SharedReader<R>>,
+    data_npz: NpzArchive<SharedReader<R>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Deserialize, R: io::Read + io::Seek> CsrRowReader<T, R> {
+    fn new(mut npz: NpzArchive<R>) -> io::Result<Self> {
+        expect_format(&mut npz, "csr")?;
+        let shape = extract_shape(&mut npz, "shape")?;
+        let indptr = extract_usize_indices(&mut npz, "indptr")?;
+        if indptr.is_empty() || indptr.windows(2).any(|w| w[0] > w[1]) {
+            return Err(invalid_data("csr matrix: indptr must be nonempty and nondecreasing to read it by rows"));
+        }
+        let indices_npz = npz.into_shared()?;
+        let data_npz = indices_npz.clone();
+        Ok(CsrRowReader { shape, indptr, indices_npz, data_npz, _marker: PhantomData })
+    }
+
+    /// Dimensions of the matrix `[nrow, ncol]`.
+    pub fn shape(&self) -> [u64; 2] {
+        self.shape
+    }
+
+    /// The `indptr` of the matrix, which gives the number of elements in each row.  See [`CsrBase::indptr`].
+    pub fn indptr(&self) -> &[usize] {
+        &self.indptr
+    }
+
+    /// Iterate over the rows, from the first, decoding each when it is reached.
+    ///
+    /// Each item is the index of a row, along with the column indices and values of its elements.
+    /// This can be called again to restart from the first row.
+    pub fn rows(&mut self) -> io::Result<CsrRows<'_, T>> {
+        let CsrRowReader { indptr, indices_npz, data_npz, .. } = self;
+        let (start, end) = (indptr[0], indptr[indptr.len() - 1]);
+        let indices = cast_indices(extract_with_min_len(indices_npz, "indices", end)?, "indices")?;
+        let data = extract_with_min_len(data_npz, "data", end)?.data::<T>().map_err(invalid_data)?;
+        let mut rows = CsrRows { indptr, row: 0, indices, data };
+        rows.indices.skip_records(start as u64)?;
+        rows.data.skip_records(start as u64)?;
+        Ok(rows)
+    }
+}
+
+/// Iterator over the rows of a CSR matrix, returned by [`CsrRowReader::rows`].
+pub struct CsrRows<'a, T: Deserialize> {
+    indptr: &'a [usize],
+    row: usize,
+    indices: CastReader<u64, ZipFile<'a>>,
+    data: NpyReader<T, ZipFile<'a>>,
+}
+
+impl<T: Deserialize> CsrRows<'_, T> {
+    fn read_row(&mut self, len: usize) -> io::Result<(Vec<u64>, Vec<T>)> {
+        let indices = self.indices.by_ref().take(len).collect::<io::Result<Vec<_>>>()?;
+        let data = self.data.by_ref().take(len).collect::<io::Result<Vec<_>>>()?;
+        Ok((indices, data))
+    }
+}
+
+impl<T: Deserialize> Iterator for CsrRows<'_, T> {
+    type Item = io::Result<(usize, Vec<u64>, Vec<T>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.row;
+        let len = self.indptr.get(row + 1)? - self.indptr[row];
+        self.row += 1;
+        match self.read_row(len) {
+            Ok((indices, data)) => Some(Ok((row, indices, data))),
+            Err(e) => {
+                // the readers are in an unknown position
+                self.row = self.indptr.len();
+                Some(Err(e))
+            },
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.indptr.len().saturating_sub(self.row + 1);
+        (remaining, Some(remaining))
+    }
+}
+
 // -----
 
 fn show_format(format: &[u8]) -> String {
@@ -325,6 +450,12 @@ fn show_format(format: &[u8]) -> String {
     format!("'{}'", str)
 }
 
+fn check_sorted<R: io::Read + io::Seek>(npz: &NpzArchive<R>, format: &'static str, indices: &[u64], indptr: &[usize]) {
+    if !diagnostics::indices_are_sorted(indices, indptr) {
+        npz.read_options().emit(Diagnostic::UnsortedSparseIndices { format });
+    }
+}
+
 fn expect_format<R: io::Read + io::Seek>(npz: &mut NpzArchive<R>, expected: &str) -> io::Result<()> {
     let format: Vec<u8> = extract_scalar(npz, "format")?;
     if format != expected.as_bytes() {
@@ -347,34 +478,29 @@ fn extract_shape<R: io::Read + io::Seek>(npz: &mut NpzArchive<R>, name: &str) ->
 }
 
 fn extract_usize_indices<R: io::Read + io::Seek>(npz: &mut NpzArchive<R>, name: &str) -> io::Result<Vec<usize>> {
-    Ok(extract_indices(npz, name)?.into_iter().map(|x| x as usize).collect())
+    extract_integers(npz, name)
 }
 
-// Read indices from npz which may be i32 or i64, but are nonnegative.
-// FIXME: in the future we may allow automatic widening during deserialization, in which case
-//        this can be simplified extract_1d::<u64>
-fn extract_indices<R: io::Read + io::Seek>(npz: &mut NpzArchive<R, >, name: &str) -> io::Result<Vec<u64>> {
-    let npy = extract_and_check_ndim(npz, name, 1)?;
-    match npy.try_data::<i32>() {
-        Ok(data) => data.map(|result| result.map(|x| x as u64)).collect(),
-        Err(npy) => match npy.try_data::<i64>() {
-            Ok(data) => data.map(|result| result.map(|x| x as u64)).collect(),
-            Err(npy) => Err(invalid_data(format_args!("invalid dtype for '{}' in sparse matrix: {}", name, npy.dtype().descr()))),
-        },
-    }
+fn extract_indices<R: io::Read + io::Seek>(npz: &mut NpzArchive<R>, name: &str) -> io::Result<Vec<u64>> {
+    extract_integers(npz, name)
 }
 
-// Read indices from npz which may be i32 or i64.
-// FIXME: in the future we may allow automatic widening during deserialization, in which case
-//        this can be replaced with extract_1d::<i64>
 fn extract_signed_indices<R: io::Read + io::Seek>(npz: &mut NpzArchive<R>, name: &str) -> io::Result<Vec<i64>> {
+    extract_integers(npz, name)
+}
+
+// Read a 1D array of any integer dtype, whose elements must fit in `I`.
+fn extract_integers<I: CastTarget, R: io::Read + io::Seek>(npz: &mut NpzArchive<R>, name: &str) -> io::Result<Vec<I>> {
     let npy = extract_and_check_ndim(npz, name, 1)?;
-    match npy.try_data::<i32>() {
-        Ok(data) => data.map(|result| result.map(|x| x as i64)).collect(),
-        Err(npy) => match npy.try_data::<i64>() {
-            Ok(data) => data.collect(),
-            Err(npy) => Err(invalid_data(format_args!("invalid dtype for '{}' in sparse matrix: {}", name, npy.dtype().descr()))),
-        },
+    cast_indices(npy, name)?.collect()
+}
+
+// Stream indices of any integer dtype.  Scipy itself writes i32 or i64, but other tools may not.
+fn cast_indices<I: CastTarget, R: io::Read>(npy: NpyFile<R>, name: &str) -> io::Result<CastReader<I, R>> {
+    let dtype = npy.dtype();
+    match dtype {
+        DType::Plain(ref ty) if matches!(ty.type_char(), TypeChar::Int | TypeChar::Uint) => npy.data_cast(Casting::SameKind),
+        _ => Err(invalid_data(format_args!("invalid dtype for '{}' in sparse matrix: {}", name, dtype.descr()))),
     }
 }
 
@@ -385,11 +511,20 @@ fn extract_1d<T: Deserialize, R: io::Read + io::Seek>(npz: &mut NpzArchive<R>, n
 
 fn extract_nd<T: Deserialize, R: io::Read + io::Seek>(npz: &mut NpzArchive<R>, name: &str, expected_ndim: usize) -> io::Result<(Vec<T>, Vec<usize>)> {
     let npy = extract_and_check_ndim(npz, name, expected_ndim)?;
-    if npy.order() != Order::C {
-        return Err(invalid_data(format_args!("fortran order is not currently supported for array '{}' in sparse NPZ file", name)));
-    }
     let shape = npy.shape().iter().map(|&x| x as usize).collect();
-    let data = npy.into_vec::<T>()?;
+    let data = match npy.order() {
+        Order::C => npy.into_vec::<T>()?,
+        Order::Fortran => {
+            // the shape was already small enough to read
+            let indices = npy.tiled_indices();
+            let mut stored = npy.into_vec::<T>()?.into_iter().map(Some).collect::<Vec<_>>();
+            let mut reordered = (0..stored.len()).map(|_| None).collect::<Vec<_>>();
+            for (from, to) in indices {
+                reordered[to as usize] = stored[from as usize].take();
+            }
+            reordered.into_iter().map(|x| x.expect("every element is visited once")).collect()
+        },
+    };
     Ok((data, shape))
 }
 
@@ -402,8 +537,16 @@ fn extract_and_check_ndim<'a, R: io::Read + io::Seek>(npz: &'a mut NpzArchive<R>
     Ok(npy)
 }
 
+fn extract_with_min_len<'a, R: io::Read + io::Seek>(npz: &'a mut NpzArchive<R>, name: &str, min_len: usize) -> io::Result<NpyFile<ZipFile<'a>>> {
+    let npy = extract_and_check_ndim(npz, name, 1)?;
+    if npy.len() < min_len as u64 {
+        return Err(invalid_data(format_args!("invalid length for '{}' (got {}, but indptr ends at {})", name, npy.len(), min_len)));
+    }
+    Ok(npy)
+}
+
 fn invalid_data<S: ToString>(s: S) -> io::Error {
-    io::Error::new(io::ErrorKind::InvalidData, s.to_string())
+    crate::Error::InvalidData(s.to_string()).into()
 }
 
 // =============================================================================
@@ -418,6 +561,8 @@ where
     Offsets: AsRef<[i64]>
 {
     /// Write a sparse matrix, like `scipy.sparse.save_npz`.
+    ///
+    /// The arrays are compressed as set by [`NpzWriter::with_compression`].
     pub fn write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
         match self {
             SparseBase::Coo(m) => m.write_npz(npz),
@@ -427,6 +572,18 @@ where
             SparseBase::Bsr(m) => m.write_npz(npz),
         }
     }
+
+    /// Write a sparse matrix like [`Self::write_npz`], but return an error instead of panicking
+    /// if its parts have inconsistent lengths.
+    pub fn try_write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
+        match self {
+            SparseBase::Coo(m) => m.try_write_npz(npz),
+            SparseBase::Csc(m) => m.try_write_npz(npz),
+            SparseBase::Csr(m) => m.try_write_npz(npz),
+            SparseBase::Dia(m) => m.try_write_npz(npz),
+            SparseBase::Bsr(m) => m.try_write_npz(npz),
+        }
+    }
 }
 
 impl<T, Data, Indices> CooBase<T, Data, Indices>
@@ -441,15 +598,23 @@ where
     ///
     /// This method does not currently perform any significant validation of input,
     /// but validation (with panics) may be added later in a future semver major bump.
+    /// Use [`Self::try_write_npz`] to check the input without panicking.
     pub fn write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
         let CooBase { data, shape, row, col } = self;
         write_format(npz, "coo")?;
         write_shape(npz, shape)?;
         write_indices(npz, "row", row.as_ref().iter().map(|&x| x as i64))?;
         write_indices(npz, "col", col.as_ref().iter().map(|&x| x as i64))?;
-        write_data(npz, &data, &[data.len() as u64])?;
+        write_data(npz, data, &[data.len() as u64])?;
         Ok(())
     }
+
+    /// Like [`Self::write_npz`], but returns [`Error::InvalidInput`][`crate::Error::InvalidInput`] instead of
+    /// panicking or writing a malformed file if the matrix fails [`Self::validate`].
+    pub fn try_write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
+        self.validate().map_err(|e| invalid_input(format_args!("coo matrix: {}", e)))?;
+        self.write_npz(npz)
+    }
 }
 
 impl<T, Data, Indices, Indptr> CsrBase<T, Data, Indices, Indptr>
@@ -465,15 +630,23 @@ where
     ///
     /// This method does not currently perform any significant validation of input,
     /// but validation (with panics) may be added later in a future semver major bump.
+    /// Use [`Self::try_write_npz`] to check the input without panicking.
     pub fn write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
         let CsrBase { data, shape, indices, indptr } = self;
         write_format(npz, "csr")?;
         write_shape(npz, shape)?;
         write_indices(npz, "indices", indices.as_ref().iter().map(|&x| x as i64))?;
         write_indices(npz, "indptr", indptr.as_ref().iter().map(|&x| x as i64))?;
-        write_data(npz, &data, &[data.len() as u64])?;
+        write_data(npz, data, &[data.len() as u64])?;
         Ok(())
     }
+
+    /// Like [`Self::write_npz`], but returns [`Error::InvalidInput`][`crate::Error::InvalidInput`] instead of
+    /// panicking or writing a malformed file if the matrix fails [`Self::validate`].
+    pub fn try_write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
+        self.validate().map_err(|e| invalid_input(format_args!("csr matrix: {}", e)))?;
+        self.write_npz(npz)
+    }
 }
 
 impl<T, Data, Indices, Indptr> CscBase<T, Data, Indices, Indptr>
@@ -489,15 +662,23 @@ where
     ///
     /// This method does not currently perform any significant validation of input,
     /// but validation (with panics) may be added later in a future semver major bump.
+    /// Use [`Self::try_write_npz`] to check the input without panicking.
     pub fn write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
         let CscBase { data, shape, indices, indptr } = self;
         write_format(npz, "csc")?;
         write_shape(npz, shape)?;
         write_indices(npz, "indices", indices.as_ref().iter().map(|&x| x as i64))?;
         write_indices(npz, "indptr", indptr.as_ref().iter().map(|&x| x as i64))?;
-        write_data(npz, &data, &[data.len() as u64])?;
+        write_data(npz, data, &[data.len() as u64])?;
         Ok(())
     }
+
+    /// Like [`Self::write_npz`], but returns [`Error::InvalidInput`][`crate::Error::InvalidInput`] instead of
+    /// panicking or writing a malformed file if the matrix fails [`Self::validate`].
+    pub fn try_write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
+        self.validate().map_err(|e| invalid_input(format_args!("csc matrix: {}", e)))?;
+        self.write_npz(npz)
+    }
 }
 
 impl<T, Data, Offsets> DiaBase<T, Data, Offsets>
@@ -510,7 +691,8 @@ where
     ///
     /// # Panics
     ///
-    /// Panics if `data.len()` is not a multiple of `offsets.len()`.
+    /// Panics if `data.len()` is not a multiple of `offsets.len()` (or with no offsets, if `data` is not empty).
+    /// See [`Self::try_write_npz`].
     pub fn write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
         let DiaBase { data, shape, offsets } = self;
         write_format(npz, "dia")?;
@@ -518,11 +700,26 @@ where
         write_indices(npz, "offsets", offsets.as_ref().iter().copied())?;
 
         let num_offsets = offsets.as_ref().len();
-        assert_eq!(data.len() % num_offsets, 0);
-        let length = data.len() / num_offsets;
-        write_data(npz, &data, &[length as u64, num_offsets as u64])?;
+        let length = match num_offsets {
+            0 => {
+                assert!(data.is_empty());
+                0
+            },
+            _ => {
+                assert_eq!(data.len() % num_offsets, 0);
+                data.len() / num_offsets
+            },
+        };
+        write_data(npz, data, &[length as u64, num_offsets as u64])?;
         Ok(())
     }
+
+    /// Like [`Self::write_npz`], but returns [`Error::InvalidInput`][`crate::Error::InvalidInput`] instead of
+    /// panicking or writing a malformed file if the matrix fails [`Self::validate`].
+    pub fn try_write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
+        self.validate().map_err(|e| invalid_input(format_args!("dia matrix: {}", e)))?;
+        self.write_npz(npz)
+    }
 }
 
 impl<T, Data, Indices, Indptr> BsrBase<T, Data, Indices, Indptr>
@@ -536,7 +733,7 @@ where
     ///
     /// # Panics
     ///
-    /// Panics if `data.len()` is not equal to `indices.len() * blocksize[0] * blocksize[1]`.
+    /// Panics if `data.len()` is not equal to `indices.len() * blocksize[0] * blocksize[1]`.  See [`Self::try_write_npz`].
     pub fn write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
         let BsrBase { data, shape, indices, indptr, blocksize } = self;
         write_format(npz, "bsr")?;
@@ -545,20 +742,27 @@ where
         write_indices(npz, "indptr", indptr.as_ref().iter().map(|&x| x as i64))?;
 
         assert_eq!(data.len(), indices.as_ref().len() * blocksize[0] * blocksize[1]);
-        write_data(npz, &data, &[indices.as_ref().len() as u64, blocksize[0] as u64, blocksize[1] as u64])?;
+        write_data(npz, data, &[indices.as_ref().len() as u64, blocksize[0] as u64, blocksize[1] as u64])?;
         Ok(())
     }
+
+    /// Like [`Self::write_npz`], but returns [`Error::InvalidInput`][`crate::Error::InvalidInput`] instead of
+    /// panicking or writing a malformed file if the matrix fails [`Self::validate`].
+    pub fn try_write_npz<W: io::Write + io::Seek>(&self, npz: &mut NpzWriter<W>) -> io::Result<()> {
+        self.validate().map_err(|e| invalid_input(format_args!("bsr matrix: {}", e)))?;
+        self.write_npz(npz)
+    }
 }
 
 // -----
 
-fn zip_file_options() -> zip::write::FileOptions {
-    Default::default()
+fn invalid_input<S: ToString>(s: S) -> io::Error {
+    crate::Error::InvalidInput(s.to_string()).into()
 }
 
 fn write_format<W: io::Write + io::Seek>(npz: &mut NpzWriter<W>, format: &str) -> io::Result<()> {
-    npz.array("format", zip_file_options())?
-        .dtype(DType::Plain("|S3".parse().unwrap()))
+    npz.array("format", npz.file_options())?
+        .dtype(DType::scalar(TypeChar::ByteStr, 3).build().unwrap())
         .shape(&[])
         .begin_nd()?
         .push(format.as_bytes())
@@ -566,7 +770,7 @@ fn write_format<W: io::Write + io::Seek>(npz: &mut NpzWriter<W>, format: &str) -
 
 fn write_shape<W: io::Write + io::Seek>(npz: &mut NpzWriter<W>, shape: &[u64]) -> io::Result<()> {
     assert_eq!(shape.len(), 2);
-    npz.array("shape", zip_file_options())?
+    npz.array("shape", npz.file_options())?
         .default_dtype()
         .shape(&[2])
         .begin_nd()?
@@ -578,14 +782,14 @@ fn write_indices<W: io::Write + io::Seek>(npz: &mut NpzWriter<W>, name: &str, da
     let (min, max) = most_negative_and_positive(data.clone());
     if (i32::MIN as i64) <= min && max <= (i32::MAX as i64) {
         // small indices
-        npz.array(name, zip_file_options())?
+        npz.array(name, npz.file_options())?
             .default_dtype()
             .shape(&[data.len() as u64])
             .begin_nd()?
             .extend(data.map(|x| x as i32))
     } else {
         // long indices
-        npz.array(name, zip_file_options())?
+        npz.array(name, npz.file_options())?
             .default_dtype()
             .shape(&[data.len() as u64])
             .begin_nd()?
@@ -605,9 +809,650 @@ fn most_negative_and_positive(data: impl ExactSizeIterator<Item=i64>) -> (i64, i
 }
 
 fn write_data<W: io::Write + io::Seek, T: AutoSerialize>(npz: &mut NpzWriter<W>, data: &[T], shape: &[u64]) -> io::Result<()> {
-    npz.array("data", zip_file_options())?
+    npz.array("data", npz.file_options())?
         .default_dtype()
         .shape(shape)
         .begin_nd()?
         .extend(data)
 }
+
+// =============================================================================
+// Validation
+
+/// Why a sparse matrix is malformed, as found by e.g. [`CsrBase::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SparseError {
+    /// A vector has the wrong length for the shape of the matrix or the length of the other vectors.
+    LengthMismatch {
+        /// The name of the field, e.g. `"indices"`.
+        name: &'static str,
+        /// Its length.
+        len: usize,
+        /// The length it should have.
+        expected: usize,
+    },
+    /// The length of `data` in a DIA matrix is not a multiple of the number of offsets.
+    DiagonalLengthMismatch {
+        /// The length of `data`.
+        len: usize,
+        /// The number of offsets.
+        num_offsets: usize,
+    },
+    /// An index is out of bounds for the shape of the matrix.
+    IndexOutOfBounds {
+        /// The name of the field, e.g. `"row"`.
+        name: &'static str,
+        /// The position of the index in the field.
+        position: usize,
+        /// The index.
+        index: u64,
+        /// The exclusive upper bound for the index.
+        bound: u64,
+    },
+    /// The elements of `indptr` decrease, so `indptr[position] > indptr[position + 1]`.
+    IndptrNotMonotone {
+        /// The position of the first element that is greater than the next one.
+        position: usize,
+    },
+    /// The first element of `indptr` is not 0, or the last one is greater than the number of elements.
+    IndptrOutOfRange {
+        /// The first element of `indptr`.
+        first: usize,
+        /// The last element of `indptr`.
+        last: usize,
+        /// The number of elements (or blocks, for BSR).
+        len: usize,
+    },
+    /// The length of `data` in a BSR matrix is not the number of blocks times the size of a block.
+    BlocksizeMismatch {
+        /// The size of each block.
+        blocksize: [usize; 2],
+        /// The number of blocks, i.e. the length of `indices`.
+        num_blocks: usize,
+        /// The length of `data`.
+        data_len: usize,
+    },
+    /// The blocksize of a BSR matrix does not evenly divide its shape (or is zero).
+    ShapeNotDivisible {
+        /// Dimensions of the matrix.
+        shape: [u64; 2],
+        /// The size of each block.
+        blocksize: [usize; 2],
+    },
+}
+
+impl std::fmt::Display for SparseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SparseError::LengthMismatch { name, len, expected } => write!(f, "{} has length {}, expected {}", name, len, expected),
+            SparseError::DiagonalLengthMismatch { len, num_offsets } => write!(f, "data has length {}, which is not a multiple of the {} offsets", len, num_offsets),
+            SparseError::IndexOutOfBounds { name, position, index, bound } => write!(f, "{}[{}] is {}, which is out of bounds for dimension {}", name, position, index, bound),
+            SparseError::IndptrNotMonotone { position } => write!(f, "indptr decreases after position {}", position),
+            SparseError::IndptrOutOfRange { first, last, len } => write!(f, "indptr goes from {} to {}, but must start at 0 and end at most at {}", first, last, len),
+            SparseError::BlocksizeMismatch { blocksize, num_blocks, data_len } => write!(f, "data has length {}, but there are {} blocks of size {:?}", data_len, num_blocks, blocksize),
+            SparseError::ShapeNotDivisible { shape, blocksize } => write!(f, "blocksize {:?} does not evenly divide shape {:?}", blocksize, shape),
+        }
+    }
+}
+
+impl std::error::Error for SparseError {}
+
+impl<T, Data, Indices, Indptr, Offsets> SparseBase<T, Data, Indices, Indptr, Offsets>
+where
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+    Offsets: AsRef<[i64]>,
+{
+    /// Check that the parts of the matrix are consistent with each other and with its shape.
+    ///
+    /// A matrix that passes can be written without panicking, and read back by scipy.
+    pub fn validate(&self) -> Result<(), SparseError> {
+        match self {
+            SparseBase::Coo(m) => m.validate(),
+            SparseBase::Csr(m) => m.validate(),
+            SparseBase::Csc(m) => m.validate(),
+            SparseBase::Dia(m) => m.validate(),
+            SparseBase::Bsr(m) => m.validate(),
+        }
+    }
+}
+
+impl<T, Data, Indices> CooBase<T, Data, Indices>
+where
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+{
+    /// Check that `row`, `col` and `data` have the same length, and that every index is within `shape`.
+    pub fn validate(&self) -> Result<(), SparseError> {
+        let CooBase { data, shape, row, col } = self;
+        check_len("row", row.as_ref().len(), data.len())?;
+        check_len("col", col.as_ref().len(), data.len())?;
+        check_bounds("row", row.as_ref(), shape[0])?;
+        check_bounds("col", col.as_ref(), shape[1])?;
+        Ok(())
+    }
+}
+
+impl<T, Data, Indices, Indptr> CsrBase<T, Data, Indices, Indptr>
+where
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+{
+    /// Check that `indices` has the length of `data`, that `indptr` has length `nrow + 1` and is a
+    /// nondecreasing sequence from 0 to at most `nnz`, and that every column index is within `shape`.
+    ///
+    /// Like scipy, this does not require the column indices within each row to be sorted or unique.
+    pub fn validate(&self) -> Result<(), SparseError> {
+        let CsrBase { data, shape, indices, indptr } = self;
+        validate_compressed(data.len(), indices.as_ref(), indptr.as_ref(), shape[0], shape[1])
+    }
+}
+
+impl<T, Data, Indices, Indptr> CscBase<T, Data, Indices, Indptr>
+where
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+{
+    /// Check that `indices` has the length of `data`, that `indptr` has length `ncol + 1` and is a
+    /// nondecreasing sequence from 0 to at most `nnz`, and that every row index is within `shape`.
+    ///
+    /// Like scipy, this does not require the row indices within each column to be sorted or unique.
+    pub fn validate(&self) -> Result<(), SparseError> {
+        let CscBase { data, shape, indices, indptr } = self;
+        validate_compressed(data.len(), indices.as_ref(), indptr.as_ref(), shape[1], shape[0])
+    }
+}
+
+impl<T, Data, Offsets> DiaBase<T, Data, Offsets>
+where
+    Data: Deref<Target=[T]>,
+    Offsets: AsRef<[i64]>,
+{
+    /// Check that the length of `data` is a multiple of the number of offsets.
+    pub fn validate(&self) -> Result<(), SparseError> {
+        let DiaBase { data, offsets, .. } = self;
+        let num_offsets = offsets.as_ref().len();
+        if (num_offsets == 0 && !data.is_empty()) || (num_offsets != 0 && data.len() % num_offsets != 0) {
+            return Err(SparseError::DiagonalLengthMismatch { len: data.len(), num_offsets });
+        }
+        Ok(())
+    }
+}
+
+impl<T, Data, Indices, Indptr> BsrBase<T, Data, Indices, Indptr>
+where
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+{
+    /// Check that the blocks evenly divide the matrix, that `data` holds one block for each of `indices`,
+    /// that `indptr` has one more element than there are rows of blocks and is a nondecreasing sequence
+    /// from 0 to at most `nnzb`, and that every column index is within the columns of blocks.
+    pub fn validate(&self) -> Result<(), SparseError> {
+        let BsrBase { data, shape, indices, indptr, blocksize } = self;
+        if blocksize.contains(&0) || shape[0] % blocksize[0] as u64 != 0 || shape[1] % blocksize[1] as u64 != 0 {
+            return Err(SparseError::ShapeNotDivisible { shape: *shape, blocksize: *blocksize });
+        }
+        let num_blocks = indices.as_ref().len();
+        let block_len = blocksize[0].checked_mul(blocksize[1]);
+        if block_len.and_then(|n| n.checked_mul(num_blocks)) != Some(data.len()) {
+            return Err(SparseError::BlocksizeMismatch { blocksize: *blocksize, num_blocks, data_len: data.len() });
+        }
+        let [nrow, ncol] = [shape[0] / blocksize[0] as u64, shape[1] / blocksize[1] as u64];
+        validate_compressed(num_blocks, indices.as_ref(), indptr.as_ref(), nrow, ncol)
+    }
+}
+
+// -----
+
+// Validate a CSR-like format with `n_major` rows and `n_minor` columns.
+fn validate_compressed(nnz: usize, indices: &[u64], indptr: &[usize], n_major: u64, n_minor: u64) -> Result<(), SparseError> {
+    check_len("indices", indices.len(), nnz)?;
+    check_len("indptr", indptr.len(), usize::try_from(n_major).unwrap_or(usize::MAX).saturating_add(1))?;
+    if let Some(position) = indptr.windows(2).position(|w| w[0] > w[1]) {
+        return Err(SparseError::IndptrNotMonotone { position });
+    }
+    let (first, last) = (indptr[0], indptr[indptr.len() - 1]);
+    if first != 0 || last > nnz {
+        return Err(SparseError::IndptrOutOfRange { first, last, len: nnz });
+    }
+    check_bounds("indices", indices, n_minor)
+}
+
+fn check_len(name: &'static str, len: usize, expected: usize) -> Result<(), SparseError> {
+    match len == expected {
+        true => Ok(()),
+        false => Err(SparseError::LengthMismatch { name, len, expected }),
+    }
+}
+
+fn check_bounds(name: &'static str, indices: &[u64], bound: u64) -> Result<(), SparseError> {
+    match indices.iter().position(|&index| index >= bound) {
+        None => Ok(()),
+        Some(position) => Err(SparseError::IndexOutOfBounds { name, position, index: indices[position], bound }),
+    }
+}
+
+// =============================================================================
+// Conversion
+
+impl<T, Data, Indices> CooBase<T, Data, Indices>
+where
+    T: Clone,
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+{
+    /// Convert to CSR format, like scipy's `coo_matrix.tocsr()`.
+    ///
+    /// The elements of each row keep their order from the COO matrix.  Unlike scipy, duplicate
+    /// entries are not summed; they are kept as separate entries, which CSR allows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row`, `col` and `data` differ in length, or if a row index is out of bounds.
+    pub fn to_csr(&self) -> Csr<T> {
+        let CooBase { data, shape, row, col } = self;
+        let (data, indices, indptr) = compress(shape[0], row.as_ref(), col.as_ref(), data);
+        Csr { shape: *shape, data, indices, indptr }
+    }
+
+    /// Convert to CSC format, like scipy's `coo_matrix.tocsc()`.
+    ///
+    /// The elements of each column keep their order from the COO matrix.  Unlike scipy, duplicate
+    /// entries are not summed; they are kept as separate entries, which CSC allows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row`, `col` and `data` differ in length, or if a column index is out of bounds.
+    pub fn to_csc(&self) -> Csc<T> {
+        let CooBase { data, shape, row, col } = self;
+        let (data, indices, indptr) = compress(shape[1], col.as_ref(), row.as_ref(), data);
+        Csc { shape: *shape, data, indices, indptr }
+    }
+}
+
+impl<T, Data, Indices, Indptr> CsrBase<T, Data, Indices, Indptr>
+where
+    T: Clone,
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+{
+    /// Convert to COO format, like scipy's `csr_matrix.tocoo()`.
+    ///
+    /// The elements are sorted by row, in the order they are stored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indptr` does not partition `indices` and `data`.
+    pub fn to_coo(&self) -> Coo<T> {
+        let CsrBase { data, shape, indices, indptr } = self;
+        let (data, row, col) = decompress(data, indices.as_ref(), indptr.as_ref());
+        Coo { shape: *shape, data, row, col }
+    }
+
+    /// Convert to CSC format, like scipy's `csr_matrix.tocsc()`.
+    ///
+    /// The row indices within each column of the output are sorted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indptr` does not partition `indices` and `data`, or if a column index is out of bounds.
+    pub fn to_csc(&self) -> Csc<T> {
+        let CsrBase { data, shape, indices, indptr } = self;
+        let (data, row, col) = decompress(data, indices.as_ref(), indptr.as_ref());
+        let (data, indices, indptr) = compress(shape[1], &col, &row, &data);
+        Csc { shape: *shape, data, indices, indptr }
+    }
+}
+
+impl<T, Data, Indices, Indptr> CscBase<T, Data, Indices, Indptr>
+where
+    T: Clone,
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+{
+    /// Convert to COO format, like scipy's `csc_matrix.tocoo()`.
+    ///
+    /// The elements are sorted by column, in the order they are stored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indptr` does not partition `indices` and `data`.
+    pub fn to_coo(&self) -> Coo<T> {
+        let CscBase { data, shape, indices, indptr } = self;
+        let (data, col, row) = decompress(data, indices.as_ref(), indptr.as_ref());
+        Coo { shape: *shape, data, row, col }
+    }
+
+    /// Convert to CSR format, like scipy's `csc_matrix.tocsr()`.
+    ///
+    /// The column indices within each row of the output are sorted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indptr` does not partition `indices` and `data`, or if a row index is out of bounds.
+    pub fn to_csr(&self) -> Csr<T> {
+        let CscBase { data, shape, indices, indptr } = self;
+        let (data, col, row) = decompress(data, indices.as_ref(), indptr.as_ref());
+        let (data, indices, indptr) = compress(shape[0], &row, &col, &data);
+        Csr { shape: *shape, data, indices, indptr }
+    }
+}
+
+// -----
+
+// Stably sort entries by their major index (the row for CSR), giving the data, indices and indptr
+// of the compressed format.
+fn compress<T: Clone>(n_major: u64, major: &[u64], minor: &[u64], data: &[T]) -> (Vec<T>, Vec<u64>, Vec<usize>) {
+    assert_eq!(major.len(), data.len());
+    assert_eq!(minor.len(), data.len());
+
+    let mut indptr = vec![0; n_major as usize + 1];
+    for &m in major {
+        assert!(m < n_major, "index {} out of bounds for dimension {}", m, n_major);
+        indptr[m as usize + 1] += 1;
+    }
+    for i in 0..n_major as usize {
+        indptr[i + 1] += indptr[i];
+    }
+
+    let mut next = indptr.clone();
+    let mut order = vec![0; data.len()];
+    for (i, &m) in major.iter().enumerate() {
+        order[next[m as usize]] = i;
+        next[m as usize] += 1;
+    }
+    let data = order.iter().map(|&i| data[i].clone()).collect();
+    let indices = order.iter().map(|&i| minor[i]).collect();
+    (data, indices, indptr)
+}
+
+// Expand a compressed format into the data, major indices and minor indices of each entry.
+fn decompress<T: Clone>(data: &[T], indices: &[u64], indptr: &[usize]) -> (Vec<T>, Vec<u64>, Vec<u64>) {
+    let mut out_data = vec![];
+    let mut major = vec![];
+    let mut minor = vec![];
+    for (m, bounds) in indptr.windows(2).enumerate() {
+        let range = bounds[0]..bounds[1];
+        out_data.extend_from_slice(&data[range.clone()]);
+        minor.extend_from_slice(&indices[range.clone()]);
+        major.extend(std::iter::repeat(m as u64).take(range.len()));
+    }
+    (out_data, major, minor)
+}
+
+// =============================================================================
+// Dense conversion
+
+impl<T, Data, Indices, Indptr, Offsets> SparseBase<T, Data, Indices, Indptr, Offsets>
+where
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+    Offsets: AsRef<[i64]>,
+{
+    /// Get the dimensions `[nrow, ncol]` of the matrix, whatever its format.
+    pub fn shape(&self) -> [u64; 2] {
+        match self {
+            SparseBase::Coo(m) => m.shape,
+            SparseBase::Csr(m) => m.shape,
+            SparseBase::Csc(m) => m.shape,
+            SparseBase::Dia(m) => m.shape,
+            SparseBase::Bsr(m) => m.shape,
+        }
+    }
+}
+
+impl<T, Data, Indices, Indptr, Offsets> SparseBase<T, Data, Indices, Indptr, Offsets>
+where
+    T: Clone + Default + AddAssign,
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+    Offsets: AsRef<[i64]>,
+{
+    /// Get the C-order data of a dense array of shape [`Self::shape`], like scipy's `toarray()`.
+    ///
+    /// Elements that are not stored are [`T::default()`][`Default::default`], and duplicate entries are summed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the matrix is malformed (e.g. an index is out of bounds), or if the dense array
+    /// would have more than `usize::MAX` elements.
+    pub fn to_dense(&self) -> Vec<T> {
+        let mut out = vec![T::default(); dense_len(self.shape())];
+        self.fill_dense(&mut out);
+        out
+    }
+
+    /// Like [`Self::to_dense`], but overwrite a buffer of length `nrow * ncol` instead of allocating one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the matrix is malformed, or if the buffer has the wrong length.
+    pub fn fill_dense(&self, out: &mut [T]) {
+        match self {
+            SparseBase::Coo(m) => m.fill_dense(out),
+            SparseBase::Csr(m) => m.fill_dense(out),
+            SparseBase::Csc(m) => m.fill_dense(out),
+            SparseBase::Dia(m) => m.fill_dense(out),
+            SparseBase::Bsr(m) => m.fill_dense(out),
+        }
+    }
+}
+
+impl<T, Data, Indices> CooBase<T, Data, Indices>
+where
+    T: Clone + Default + AddAssign,
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+{
+    /// Get the C-order data of a dense array of shape `shape`, like scipy's `coo_matrix.toarray()`.
+    ///
+    /// Elements that are not stored are [`T::default()`][`Default::default`], and duplicate entries are summed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row`, `col` and `data` differ in length, if an index is out of bounds, or if the
+    /// dense array would have more than `usize::MAX` elements.
+    pub fn to_dense(&self) -> Vec<T> {
+        let mut out = vec![T::default(); dense_len(self.shape)];
+        self.fill_dense(&mut out);
+        out
+    }
+
+    /// Like [`Self::to_dense`], but overwrite a buffer of length `nrow * ncol` instead of allocating one.
+    pub fn fill_dense(&self, out: &mut [T]) {
+        let CooBase { data, shape, row, col } = self;
+        let mut dense = Dense::new(out, *shape);
+        assert_eq!(row.as_ref().len(), data.len());
+        assert_eq!(col.as_ref().len(), data.len());
+        for ((&r, &c), x) in row.as_ref().iter().zip(col.as_ref()).zip(data.iter()) {
+            dense.add(r, c, x);
+        }
+    }
+}
+
+impl<T, Data, Indices, Indptr> CsrBase<T, Data, Indices, Indptr>
+where
+    T: Clone + Default + AddAssign,
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+{
+    /// Get the C-order data of a dense array of shape `shape`, like scipy's `csr_matrix.toarray()`.
+    ///
+    /// Elements that are not stored are [`T::default()`][`Default::default`], and duplicate entries are summed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indptr` does not partition `indices` and `data`, if an index is out of bounds,
+    /// or if the dense array would have more than `usize::MAX` elements.
+    pub fn to_dense(&self) -> Vec<T> {
+        let mut out = vec![T::default(); dense_len(self.shape)];
+        self.fill_dense(&mut out);
+        out
+    }
+
+    /// Like [`Self::to_dense`], but overwrite a buffer of length `nrow * ncol` instead of allocating one.
+    pub fn fill_dense(&self, out: &mut [T]) {
+        let CsrBase { data, shape, indices, indptr } = self;
+        let mut dense = Dense::new(out, *shape);
+        for (r, bounds) in indptr.as_ref().windows(2).enumerate() {
+            for k in bounds[0]..bounds[1] {
+                dense.add(r as u64, indices.as_ref()[k], &data[k]);
+            }
+        }
+    }
+}
+
+impl<T, Data, Indices, Indptr> CscBase<T, Data, Indices, Indptr>
+where
+    T: Clone + Default + AddAssign,
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+{
+    /// Get the C-order data of a dense array of shape `shape`, like scipy's `csc_matrix.toarray()`.
+    ///
+    /// Elements that are not stored are [`T::default()`][`Default::default`], and duplicate entries are summed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indptr` does not partition `indices` and `data`, if an index is out of bounds,
+    /// or if the dense array would have more than `usize::MAX` elements.
+    pub fn to_dense(&self) -> Vec<T> {
+        let mut out = vec![T::default(); dense_len(self.shape)];
+        self.fill_dense(&mut out);
+        out
+    }
+
+    /// Like [`Self::to_dense`], but overwrite a buffer of length `nrow * ncol` instead of allocating one.
+    pub fn fill_dense(&self, out: &mut [T]) {
+        let CscBase { data, shape, indices, indptr } = self;
+        let mut dense = Dense::new(out, *shape);
+        for (c, bounds) in indptr.as_ref().windows(2).enumerate() {
+            for k in bounds[0]..bounds[1] {
+                dense.add(indices.as_ref()[k], c as u64, &data[k]);
+            }
+        }
+    }
+}
+
+impl<T, Data, Offsets> DiaBase<T, Data, Offsets>
+where
+    T: Clone + Default + AddAssign,
+    Data: Deref<Target=[T]>,
+    Offsets: AsRef<[i64]>,
+{
+    /// Get the C-order data of a dense array of shape `shape`, like scipy's `dia_matrix.toarray()`.
+    ///
+    /// Elements that are not stored are [`T::default()`][`Default::default`], and duplicate diagonals are summed.
+    /// Values in `data` that lie outside of the matrix are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `data` is not a multiple of the length of `offsets`, or if the dense
+    /// array would have more than `usize::MAX` elements.
+    pub fn to_dense(&self) -> Vec<T> {
+        let mut out = vec![T::default(); dense_len(self.shape)];
+        self.fill_dense(&mut out);
+        out
+    }
+
+    /// Like [`Self::to_dense`], but overwrite a buffer of length `nrow * ncol` instead of allocating one.
+    pub fn fill_dense(&self, out: &mut [T]) {
+        let DiaBase { data, shape, offsets } = self;
+        let mut dense = Dense::new(out, *shape);
+        let offsets = offsets.as_ref();
+        if offsets.is_empty() {
+            return;
+        }
+        assert_eq!(data.len() % offsets.len(), 0, "dia matrix: data length is not a multiple of the number of offsets");
+        let length = data.len() / offsets.len();
+        for (&offset, diagonal) in offsets.iter().zip(data.chunks(length.max(1))) {
+            for (c, x) in diagonal.iter().enumerate().take(shape[1] as usize) {
+                let r = c as i128 - offset as i128;
+                if 0 <= r && r < shape[0] as i128 {
+                    dense.add(r as u64, c as u64, x);
+                }
+            }
+        }
+    }
+}
+
+impl<T, Data, Indices, Indptr> BsrBase<T, Data, Indices, Indptr>
+where
+    T: Clone + Default + AddAssign,
+    Data: Deref<Target=[T]>,
+    Indices: AsRef<[u64]>,
+    Indptr: AsRef<[usize]>,
+{
+    /// Get the C-order data of a dense array of shape `shape`, like scipy's `bsr_matrix.toarray()`.
+    ///
+    /// Elements that are not stored are [`T::default()`][`Default::default`], and duplicate blocks are summed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indptr` does not partition `indices`, if `data` does not have one block for each of
+    /// `indices`, if an index is out of bounds, or if the dense array would have more than `usize::MAX` elements.
+    pub fn to_dense(&self) -> Vec<T> {
+        let mut out = vec![T::default(); dense_len(self.shape)];
+        self.fill_dense(&mut out);
+        out
+    }
+
+    /// Like [`Self::to_dense`], but overwrite a buffer of length `nrow * ncol` instead of allocating one.
+    pub fn fill_dense(&self, out: &mut [T]) {
+        let BsrBase { data, shape, indices, indptr, blocksize } = self;
+        let mut dense = Dense::new(out, *shape);
+        let [block_nrow, block_ncol] = *blocksize;
+        assert_eq!(data.len(), indices.as_ref().len() * block_nrow * block_ncol);
+        for (superrow, bounds) in indptr.as_ref().windows(2).enumerate() {
+            for b in bounds[0]..bounds[1] {
+                let supercol = indices.as_ref()[b];
+                let block = &data[b * block_nrow * block_ncol..][..block_nrow * block_ncol];
+                for (i, block_row) in block.chunks(block_ncol).enumerate() {
+                    for (j, x) in block_row.iter().enumerate() {
+                        let r = (superrow * block_nrow + i) as u64;
+                        let c = supercol * block_ncol as u64 + j as u64;
+                        dense.add(r, c, x);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// -----
+
+fn dense_len(shape: [u64; 2]) -> usize {
+    shape[0].checked_mul(shape[1])
+        .and_then(|len| usize::try_from(len).ok())
+        .unwrap_or_else(|| panic!("a dense matrix of shape {:?} is too large", shape))
+}
+
+// A C-order dense matrix being filled in from sparse entries.
+struct Dense<'a, T> {
+    out: &'a mut [T],
+    shape: [u64; 2],
+}
+
+impl<'a, T: Clone + Default + AddAssign> Dense<'a, T> {
+    fn new(out: &'a mut [T], shape: [u64; 2]) -> Self {
+        assert_eq!(out.len(), dense_len(shape), "buffer length does not match shape {:?}", shape);
+        out.fill(T::default());
+        Dense { out, shape }
+    }
+
+    fn add(&mut self, row: u64, col: u64, value: &T) {
+        assert!(row < self.shape[0] && col < self.shape[1], "index {:?} out of bounds for shape {:?}", [row, col], self.shape);
+        self.out[(row * self.shape[1] + col) as usize] += value.clone();
+    }
+}
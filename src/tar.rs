@@ -0,0 +1,320 @@
+//! Reading NPY files from tar archives.
+
+use std::io::{self, Read};
+
+use crate::error::Error;
+use crate::read::{NpyFile, ReadOptions};
+
+const BLOCK_SIZE: u64 = 512;
+const GZIP_MAGIC: &[u8; 2] = b"\x1f\x8b";
+
+/// Reads the `.npy` files in a tar archive one after another, without extracting them.
+///
+/// The archive is read sequentially from any [`io::Read`], so the arrays can only be read in the order
+/// they are stored, and each one only once.  Each array is named after its path in the archive without
+/// the `.npy` extension (and without a leading `./`), e.g. `"train/images"`.  Members that are not
+/// `.npy` files, including directories and links, are skipped.  Archives in the ustar, GNU and pax
+/// formats are supported, including long file names.
+///
+/// When the **`"gzip"`** feature is also enabled, gzip-compressed archives (`.tar.gz`, `.tgz`) are
+/// detected and decompressed automatically.
+///
+/// *This is only available with the **`"tar"`** feature.*
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::NpyTarReader;
+///
+/// let file = std::fs::File::open("test-data/arrays.tar")?;
+/// let mut tar = NpyTarReader::new(std::io::BufReader::new(file))?;
+/// while let Some((name, npy)) = tar.next_array()? {
+///     println!("{}: {:?}", name, npy.shape());
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct NpyTarReader<R: Read> {
+    reader: Source<R>,
+    options: ReadOptions,
+    // the bytes of the current member that have not been read, and the padding after it
+    remaining: u64,
+    padding: u64,
+    finished: bool,
+}
+
+/// The data of an array in a tar archive, read by [`NpyTarReader`].
+#[derive(Debug)]
+pub struct TarMember<'a, R: Read> {
+    reader: &'a mut Source<R>,
+    remaining: &'a mut u64,
+}
+
+#[derive(Debug)]
+enum Source<R: Read> {
+    Plain(io::Chain<io::Cursor<Vec<u8>>, R>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::read::MultiGzDecoder<io::Chain<io::Cursor<Vec<u8>>, R>>),
+}
+
+impl<R: Read> NpyTarReader<R> {
+    /// Start reading a tar archive, checking whether it is compressed.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = vec![0; GZIP_MAGIC.len()];
+        let len = read_fully(&mut reader, &mut magic)?;
+        magic.truncate(len);
+        let is_gzip = &magic[..] == GZIP_MAGIC;
+        let reader = io::Cursor::new(magic).chain(reader);
+        let reader = match is_gzip {
+            #[cfg(feature = "gzip")]
+            true => Source::Gzip(flate2::read::MultiGzDecoder::new(reader)),
+            #[cfg(not(feature = "gzip"))]
+            true => {
+                let msg = "the archive is gzip-compressed, which requires the \"gzip\" feature".to_string();
+                return Err(Error::InvalidInput(msg).into());
+            },
+            false => Source::Plain(reader),
+        };
+        Ok(NpyTarReader { reader, options: ReadOptions::default(), remaining: 0, padding: 0, finished: false })
+    }
+
+    /// Set the options used when reading the arrays.
+    pub fn with_read_options(mut self, options: ReadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Read the header of the next array, returning its name along with an [`NpyFile`] for reading its data.
+    ///
+    /// Returns `Ok(None)` at the end of the archive.  Any data of the previous array that was not read
+    /// is skipped over.
+    pub fn next_array(&mut self) -> io::Result<Option<(String, NpyFile<TarMember<'_, R>>)>> {
+        while !self.finished {
+            self.skip(self.remaining + self.padding)?;
+            let header = match self.next_header()? {
+                Some(header) => header,
+                None => break,
+            };
+            self.remaining = header.size;
+            self.padding = padding(header.size);
+
+            let path = header.path.strip_prefix("./").unwrap_or(&header.path);
+            let name = match crate::npz::array_name_from_file_name(path) {
+                Some(name) if header.is_file && !name.is_empty() => name.to_string(),
+                _ => continue,
+            };
+            let member = TarMember { reader: &mut self.reader, remaining: &mut self.remaining };
+            let npy = NpyFile::with_options(member, &self.options).map_err(|e| Error::in_member(e, &name))?;
+            let npy = npy.with_member_name(&name);
+            return Ok(Some((name, npy)));
+        }
+        self.finished = true;
+        Ok(None)
+    }
+
+    // Read the header of the next member, following any headers that describe it.
+    //
+    // Returns `None` at the end of the archive.
+    fn next_header(&mut self) -> io::Result<Option<Header>> {
+        let mut long_path = None;
+        let mut pax_path = None;
+        let mut pax_size = None;
+        loop {
+            let mut block = [0; BLOCK_SIZE as usize];
+            match read_fully(&mut self.reader, &mut block)? {
+                // an archive that is missing its end-of-archive blocks
+                0 => return Ok(None),
+                n if n < block.len() => return Err(Error::Truncated.into()),
+                _ => {},
+            }
+            if block.iter().all(|&b| b == 0) {
+                return Ok(None);
+            }
+            check_checksum(&block)?;
+            let size = parse_number(&block[124..136])?;
+            match block[156] {
+                // GNU long name, for the next member
+                b'L' => {
+                    let data = self.read_data(size)?;
+                    long_path = Some(String::from_utf8_lossy(until_nul(&data)).into_owned());
+                },
+                // pax extended header, for the next member
+                b'x' => {
+                    let data = self.read_data(size)?;
+                    for (key, value) in parse_pax(&data)? {
+                        match key {
+                            "path" => pax_path = Some(value.to_string()),
+                            "size" => pax_size = Some(value.parse().map_err(|_| bad_header(format!("invalid pax size {:?}", value)))?),
+                            _ => {},
+                        }
+                    }
+                },
+                // pax global header, GNU long link name, and other metadata
+                b'g' | b'K' => self.skip(size + padding(size))?,
+                kind => {
+                    let path = pax_path.or(long_path).unwrap_or_else(|| {
+                        let name = String::from_utf8_lossy(until_nul(&block[..100]));
+                        let prefix = String::from_utf8_lossy(until_nul(&block[345..500]));
+                        match &block[257..263] == b"ustar\0" && !prefix.is_empty() {
+                            true => format!("{}/{}", prefix, name),
+                            false => name.into_owned(),
+                        }
+                    });
+                    let is_file = kind == b'0' || kind == b'\0' || kind == b'7';
+                    return Ok(Some(Header { path, size: pax_size.unwrap_or(size), is_file }));
+                },
+            }
+        }
+    }
+
+    // Read the data of a metadata member.
+    fn read_data(&mut self, size: u64) -> io::Result<Vec<u8>> {
+        if size > 1 << 20 {
+            return Err(bad_header(format!("metadata of {} bytes is too large", size)));
+        }
+        let mut data = vec![0; size as usize];
+        if read_fully(&mut self.reader, &mut data)? < data.len() {
+            return Err(Error::Truncated.into());
+        }
+        self.skip(padding(size))?;
+        Ok(data)
+    }
+
+    fn skip(&mut self, len: u64) -> io::Result<()> {
+        if io::copy(&mut (&mut self.reader).take(len), &mut io::sink())? < len {
+            return Err(Error::Truncated.into());
+        }
+        self.remaining = 0;
+        self.padding = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for TarMember<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if *self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let len = buf.len().min(usize::try_from(*self.remaining).unwrap_or(usize::MAX));
+        let n = self.reader.read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(Error::Truncated.into());
+        }
+        *self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read> Read for Source<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Source::Plain(reader) => reader.read(buf),
+            #[cfg(feature = "gzip")]
+            Source::Gzip(reader) => reader.read(buf),
+        }
+    }
+}
+
+struct Header {
+    path: String,
+    size: u64,
+    is_file: bool,
+}
+
+fn padding(size: u64) -> u64 {
+    (BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE
+}
+
+fn bad_header(msg: String) -> io::Error {
+    Error::InvalidData(format!("invalid tar header: {}", msg)).into()
+}
+
+fn until_nul(bytes: &[u8]) -> &[u8] {
+    match bytes.iter().position(|&b| b == 0) {
+        Some(end) => &bytes[..end],
+        None => bytes,
+    }
+}
+
+fn check_checksum(block: &[u8]) -> io::Result<()> {
+    let expected = parse_number(&block[148..156])?;
+    // the checksum is computed with its own field filled with spaces
+    let sum = block.iter().enumerate()
+        .map(|(i, &b)| if (148..156).contains(&i) { b' ' as u64 } else { b as u64 })
+        .sum::<u64>();
+    if sum != expected {
+        return Err(bad_header("the checksum does not match (this may not be a tar file)".to_string()));
+    }
+    Ok(())
+}
+
+// Parse a numeric field, which is either octal text or, for large values, a big-endian binary number.
+fn parse_number(field: &[u8]) -> io::Result<u64> {
+    if field[0] & 0x80 != 0 {
+        if field[0] != 0x80 || field[1..field.len() - 8].iter().any(|&b| b != 0) {
+            return Err(bad_header("a number is out of range".to_string()));
+        }
+        return Ok(u64::from_be_bytes(field[field.len() - 8..].try_into().unwrap()));
+    }
+    let text = std::str::from_utf8(until_nul(field)).ok().map(|s| s.trim_matches(' '));
+    match text {
+        Some("") => Ok(0),
+        Some(text) => u64::from_str_radix(text, 8).map_err(|_| bad_header(format!("invalid number {:?}", text))),
+        None => Err(bad_header(format!("invalid number {:?}", String::from_utf8_lossy(field)))),
+    }
+}
+
+// Parse the records of a pax extended header, each of the form "<len> <key>=<value>\n".
+fn parse_pax(mut data: &[u8]) -> io::Result<Vec<(&str, &str)>> {
+    let mut records = vec![];
+    while !data.is_empty() {
+        let invalid = || bad_header("invalid pax record".to_string());
+        let space = data.iter().position(|&b| b == b' ').ok_or_else(invalid)?;
+        let len = std::str::from_utf8(&data[..space]).ok().and_then(|s| s.parse::<usize>().ok()).ok_or_else(invalid)?;
+        if len <= space + 1 || len > data.len() || data[len - 1] != b'\n' {
+            return Err(invalid());
+        }
+        let record = std::str::from_utf8(&data[space + 1..len - 1]).map_err(|_| invalid())?;
+        records.push(record.split_once('=').ok_or_else(invalid)?);
+        data = &data[len..];
+    }
+    Ok(records)
+}
+
+// Read until the buffer is full or the stream ends, returning the number of bytes read.
+fn read_fully(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number(b"0000644\0").unwrap(), 0o644);
+        assert_eq!(parse_number(b"   12 \0\0").unwrap(), 0o12);
+        assert_eq!(parse_number(b"\0\0\0\0").unwrap(), 0);
+        let mut binary = [0u8; 12];
+        binary[0] = 0x80;
+        binary[4..].copy_from_slice(&(1u64 << 40).to_be_bytes());
+        assert_eq!(parse_number(&binary).unwrap(), 1 << 40);
+        assert!(parse_number(b"0009\0").is_err());
+    }
+
+    #[test]
+    fn pax() {
+        let data = b"30 mtime=1350244992.023960108\n24 path=a/long/name.npy\n";
+        assert_eq!(parse_pax(data).unwrap(), vec![("mtime", "1350244992.023960108"), ("path", "a/long/name.npy")]);
+        assert!(parse_pax(b"5 a=b").is_err());
+        assert!(parse_pax(b"99 path=x\n").is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Transposing 2-D arrays that do not fit in memory.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::error::Error;
+use crate::read::{NpyHeader, Order};
+
+/// Write the transpose of the 2-D array in one NPY file to a new NPY file, using a bounded amount of memory.
+///
+/// The output has the same dtype, [order][`Order`] and [extra keys][`NpyHeader::extra_keys`] as the input,
+/// and the shape `[n, m]` for an input of shape `[m, n]`.  The data is rearranged without being decoded,
+/// so this works for any dtype.
+///
+/// The array is processed in square-ish tiles, each of which is read from the input, transposed in memory and
+/// written to the output.  At most about `memory_budget` bytes are used for buffers, however the tiles
+/// are at least one element.  Larger budgets mean fewer and longer reads and writes.  The output file is
+/// extended to its final length before any data is written.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::WriterBuilder;
+///
+/// # let dir = std::env::temp_dir();
+/// # let input = dir.join(format!("npyz-transpose-doctest-in-{}.npy", std::process::id()));
+/// # let output = dir.join(format!("npyz-transpose-doctest-out-{}.npy", std::process::id()));
+/// let mut writer = npyz::WriteOptions::new().default_dtype().shape(&[2, 3]).writer(std::fs::File::create(&input)?).begin_nd()?;
+/// writer.extend([1_i32, 2, 3, 4, 5, 6])?;
+/// writer.finish()?;
+///
+/// npyz::transpose_file(&input, &output, 1 << 20)?;
+///
+/// let npy = npyz::NpyFile::new(std::fs::File::open(&output)?)?;
+/// assert_eq!(npy.shape(), &[3, 2]);
+/// assert_eq!(npy.into_vec::<i32>()?, vec![1, 4, 2, 5, 3, 6]);
+/// # std::fs::remove_file(&input)?;
+/// # std::fs::remove_file(&output)?;
+/// # Ok(()) }
+/// ```
+pub fn transpose_file(input: impl AsRef<Path>, output: impl AsRef<Path>, memory_budget: usize) -> io::Result<()> {
+    let mut input = File::open(input)?;
+    let header = NpyHeader::from_reader(io::BufReader::new(&mut input))?;
+    let (rows, cols) = match *header.shape() {
+        [m, n] => match header.order() {
+            // the dimensions of the data as it is laid out in the file
+            Order::C => (m, n),
+            Order::Fortran => (n, m),
+        },
+        _ => return Err(Error::InvalidInput(format!("expected a 2-D array, got shape {:?}", header.shape())).into()),
+    };
+    let item_size = header.dtype().num_bytes().expect("size was checked when constructing the header");
+    let input_start = header.raw_bytes().expect("header was read from a file").len() as u64;
+    let data_len = header.len() * item_size as u64;
+    if input.metadata()?.len() < input_start + data_len {
+        return Err(Error::Truncated.into());
+    }
+
+    let [m, n] = [header.shape()[0], header.shape()[1]];
+    let new_header = crate::write::header_bytes(&header.dtype(), header.order(), &[n, m], header.extra_keys())?;
+    let output_start = new_header.len() as u64;
+    let mut output = OpenOptions::new().write(true).create(true).truncate(true).open(output)?;
+    output.write_all(&new_header)?;
+    output.set_len(output_start + data_len)?;
+    if data_len == 0 {
+        return output.flush();
+    }
+
+    let (tile_rows, tile_cols) = tile_shape(rows, cols, (memory_budget / 2 / item_size.max(1)) as u64);
+    let mut tile = vec![0; (tile_rows * tile_cols) as usize * item_size];
+    let mut transposed = vec![0; tile.len()];
+    for row_start in (0..rows).step_by(tile_rows as usize) {
+        for col_start in (0..cols).step_by(tile_cols as usize) {
+            let (height, width) = (tile_rows.min(rows - row_start), tile_cols.min(cols - col_start));
+            let (height_bytes, width_bytes) = (height as usize * item_size, width as usize * item_size);
+
+            for i in 0..height {
+                let offset = ((row_start + i) * cols + col_start) * item_size as u64;
+                input.seek(SeekFrom::Start(input_start + offset))?;
+                let start = i as usize * width_bytes;
+                input.read_exact(&mut tile[start..start + width_bytes]).map_err(truncated)?;
+            }
+            for i in 0..height as usize {
+                for j in 0..width as usize {
+                    let from = (i * width as usize + j) * item_size;
+                    let to = (j * height as usize + i) * item_size;
+                    transposed[to..to + item_size].copy_from_slice(&tile[from..from + item_size]);
+                }
+            }
+            for j in 0..width {
+                // row `col_start + j` of the output
+                let offset = ((col_start + j) * rows + row_start) * item_size as u64;
+                output.seek(SeekFrom::Start(output_start + offset))?;
+                let start = j as usize * height_bytes;
+                output.write_all(&transposed[start..start + height_bytes])?;
+            }
+        }
+    }
+    output.flush()
+}
+
+// Choose the largest tile of about `max_items` elements that is as square as the array allows.
+fn tile_shape(rows: u64, cols: u64, max_items: u64) -> (u64, u64) {
+    let max_items = max_items.max(1);
+    let side = (max_items as f64).sqrt() as u64;
+    let tile_rows = side.clamp(1, rows);
+    let tile_cols = (max_items / tile_rows).clamp(1, cols);
+    // a narrow array leaves room for more rows
+    let tile_rows = (max_items / tile_cols).clamp(1, rows);
+    (tile_rows, tile_cols)
+}
+
+fn truncated(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => Error::Truncated.into(),
+        _ => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NpyFile, WriteOptions, WriterBuilder};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("npyz-transpose-{}-{}.npy", name, std::process::id()))
+    }
+
+    #[test]
+    fn tiles() {
+        assert_eq!(tile_shape(1000, 1000, 100), (10, 10));
+        assert_eq!(tile_shape(2, 1000, 100), (2, 50));
+        assert_eq!(tile_shape(1000, 3, 100), (33, 3));
+        assert_eq!(tile_shape(1000, 1000, 0), (1, 1));
+        assert_eq!(tile_shape(5, 5, 1000), (5, 5));
+    }
+
+    #[test]
+    fn transpose() -> io::Result<()> {
+        let (input, output) = (temp_path("in"), temp_path("out"));
+        for (m, n) in [(1, 1), (7, 13), (40, 3), (0, 5)] {
+            let data = (0..m * n).map(|x| x as i32).collect::<Vec<_>>();
+            for order in [Order::C, Order::Fortran] {
+                let mut writer = WriteOptions::new().default_dtype().shape(&[m, n]).order(order).writer(File::create(&input)?).begin_nd()?;
+                writer.extend_from_slice(&data)?;
+                writer.finish()?;
+
+                // the logical transpose, in the same order
+                let expected = NpyFile::new(File::open(&input)?)?.tiled_indices().with_tile_size(1).map(|(i, _)| data[i as usize]).collect::<Vec<_>>();
+                for budget in [0, 16, 100, 1 << 20] {
+                    transpose_file(&input, &output, budget)?;
+                    let npy = NpyFile::new(File::open(&output)?)?;
+                    assert_eq!(npy.shape(), &[n, m]);
+                    assert_eq!(npy.order(), order);
+                    assert_eq!(npy.into_vec::<i32>()?, expected, "{}x{} {:?} budget {}", m, n, order, budget);
+                }
+            }
+        }
+        std::fs::remove_file(&input)?;
+        std::fs::remove_file(&output)
+    }
+
+    #[test]
+    fn errors() -> io::Result<()> {
+        let output = temp_path("error");
+        let err = transpose_file("test-data/c-order.npy", &output, 1 << 20).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let input = temp_path("truncated");
+        let mut writer = WriteOptions::new().default_dtype().shape(&[2, 2]).writer(File::create(&input)?).begin_nd()?;
+        writer.extend([1_u8, 2, 3, 4])?;
+        writer.finish()?;
+        let bytes = std::fs::read(&input)?;
+        std::fs::write(&input, &bytes[..bytes.len() - 1])?;
+        assert_eq!(transpose_file(&input, &output, 1 << 20).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        std::fs::remove_file(&input)?;
+        let _ = std::fs::remove_file(&output);
+        Ok(())
+    }
+}
@@ -30,7 +30,10 @@ The rust types `f32`, `f64` use type code `f`.
 
 ### Complex
 
-When the **`"complex"`** feature is enabled, rust types [`Complex32`] and [`Complex64`] may use type code `c`.
+The rust types [`Complex<f32>`][`Complex`] and [`Complex<f64>`][`Complex`] use type code `c`, as do the pairs
+`(f32, f32)` and `(f64, f64)` of the real and imaginary parts.
+
+When the **`"complex"`** feature is enabled, rust types [`Complex32`] and [`Complex64`] may also use type code `c`.
 
 **Notice:** numpy does have have complex numbers backed by 128-bit floats, but this is not supported by `npyz`.
 
@@ -42,7 +45,8 @@ The rust type `bool` may be serialized as `|b1`.
 
 In all of the above cases, npyz uses the machine endianness by default when serializing, but
 supports serializing and deserializing as any endianness.  The size of the datatype in the file
-must match the size of the rust type used.
+must match the size of the rust type used.  To read numbers of a different size or kind, such as
+`<f4` data as `f64`, use [`NpyFile::data_cast`].
 
 ## Date and time
 
@@ -54,7 +58,10 @@ There are two type codes used by numpy for time and date.
 Both of these are represented as 8-byte signed integers, and therefore can use **`i64`** in rust.
 
 The type strings for these types must specify units in square brackets, e.g. `"<m8[ns]"` for
-nanoseconds.
+nanoseconds.  To keep the units along with the value, use [`DateTime64`] for `M` and [`TimeDelta64`]
+for `m`.  These can be converted to [`std::time::SystemTime`] and [`std::time::Duration`] respectively.
+Like `i64`, they hold NaT as `i64::MIN`.  They do not implement [`AutoSerialize`], and when writing,
+their units must match those of the dtype.
 
 ## String and blob types
 
@@ -72,6 +79,7 @@ The following support matrix shows how various rust types may serialize as these
 | rust type               | feature      | `VM` | `SM`/`aM` | `UM` |  [`AutoSerialize`] dtype | notes |
 |:---------               | ------------ | --- | ------- | --- | ------------------ | :--- |
 | `String`/`str`          |              | ❌ | ✅ | ✅ | ➖   | |
+| [`Truncate`]`<String>`  |              | ❌ | ✅ | ✅ | ➖   | truncates when writing (any `AsRef<str>`) |
 | `Vec<u8>`/`[u8]`        |              | ✅ | ✅ | ❌ | ➖   | length must `== M` when writing `V` |
 | `Vec<u32>`/`[u32]`      |              | ❌ | ❌ | ✅ | ➖   | most general type to read `U` |
 | `Vec<char>`/`[char]`    |              | ❌ | ❌ | ✅ | ➖   | |
@@ -113,6 +121,10 @@ The following Rust types are supported:
 * `Vec<char>`, which will fail on reading surrogates.
 * `String`, which will fail on reading surrogates.
 
+Reading a `U` value into a `String` removes the trailing nulls.  When writing a `String` or `str`,
+shorter strings are padded with nulls, and strings with more than `N` chars produce an error.
+To instead cut them down to the first `N` chars, wrap them in [`Truncate`].
+
 Notice that `String` also alternatively supports `|SN` if you want a more compressed representation
 in the file, however this is a non-standard convention (see the section on `|SN` for more details).
 
@@ -193,7 +205,7 @@ This type can deserialize the numpy dtype `np.dtype([('a', '<i4', (4, 3))])`.
 **/
 
 #[allow(unused)] // used by docstring
-use crate::{FixedSizeBytes, TypeStr, Deserialize, Serialize, AutoSerialize, DType};
+use crate::{NpyFile, Complex, DateTime64, TimeDelta64, FixedSizeBytes, Truncate, TypeStr, Deserialize, Serialize, AutoSerialize, DType};
 
 #[cfg(feature = "arrayvec")]
 #[allow(unused)] // used by docstring
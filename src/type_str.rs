@@ -51,6 +51,86 @@ impl TypeStr {
     ///
     /// If this value would overflow the platform's `usize` type, returns `None`.
     pub fn num_bytes(&self) -> Option<usize> { type_str_num_bytes_as_usize(self) }
+
+    /// Get the alignment of a single value in bytes, as numpy would compute it.
+    pub fn alignment(&self) -> usize {
+        match self.type_char {
+            TypeChar::Bool |
+            TypeChar::Int |
+            TypeChar::Uint |
+            TypeChar::Float |
+            TypeChar::TimeDelta |
+            TypeChar::DateTime => self.size as usize,
+
+            // aligned like the real part
+            TypeChar::Complex => self.size as usize / 2,
+
+            TypeChar::UnicodeStr => 4,
+
+            TypeChar::ByteStr |
+            TypeChar::RawData => 1,
+        }
+    }
+
+    /// Returns `true` if values of this type are stored in the byte order of the current machine,
+    /// so that they can be used without swapping bytes.
+    ///
+    /// This is always `true` for types where byte order is irrelevant, such as `|u1` or `>S3`.
+    pub fn is_native_endian(&self) -> bool {
+        !self.has_byte_order() || !self.endianness.requires_swap(Endianness::of_machine())
+    }
+
+    /// Get the canonical spelling of this type string's byte order, which is what numpy would write
+    /// for `dtype.str`.
+    ///
+    /// This is `|` for types where byte order is irrelevant (e.g. `<u1` becomes `|u1`), and `<` or `>`
+    /// for all other types.  (`=` is already replaced by an explicit byte order during parsing)
+    ///
+    /// ```
+    /// # fn main() -> Result<(), npyz::ParseTypeStrError> {
+    /// let normalize = |s: &str| s.parse::<npyz::TypeStr>().map(|ty| ty.normalize_byte_order().to_string());
+    /// assert_eq!(normalize("<u1")?, "|u1");
+    /// assert_eq!(normalize(">S3")?, "|S3");
+    /// assert_eq!(normalize(">i4")?, ">i4");
+    /// # Ok(()) }
+    /// ```
+    pub fn normalize_byte_order(&self) -> TypeStr {
+        let endianness = match self.has_byte_order() {
+            true => self.endianness,
+            false => Endianness::Irrelevant,
+        };
+        TypeStr { endianness, ..self.clone() }
+    }
+
+    /// Get the same type in the byte order of the current machine.  The result is normalized as
+    /// in [`TypeStr::normalize_byte_order`].
+    pub fn to_native_endian(&self) -> TypeStr {
+        TypeStr { endianness: Endianness::of_machine(), ..self.clone() }.normalize_byte_order()
+    }
+
+    // Get the same type in the opposite byte order, if it has one.
+    pub(crate) fn swapped_byte_order(&self) -> TypeStr {
+        let endianness = match (self.has_byte_order(), self.endianness) {
+            (true, Endianness::Little) => Endianness::Big,
+            (true, Endianness::Big) => Endianness::Little,
+            (_, endianness) => endianness,
+        };
+        TypeStr { endianness, ..self.clone() }
+    }
+
+    // Whether the bytes of a value depend on the byte order.
+    pub(crate) fn has_byte_order(&self) -> bool {
+        match self.type_char {
+            TypeChar::ByteStr | TypeChar::RawData => false,
+            _ => self.type_char.requires_endianness(self.size),
+        }
+    }
+
+    // not part of stable API, used by the output of `dtype!` after it has validated the type string
+    #[doc(hidden)]
+    pub fn __from_validated_parts(endianness: Endianness, type_char: TypeChar, size: u64, time_units: Option<TimeUnits>) -> Self {
+        TypeStr { endianness, type_char, size, time_units }
+    }
 }
 
 /// Represents the first character in a [`TypeStr`], which describes endianness.
@@ -68,10 +148,13 @@ pub enum Endianness {
 
 impl Endianness {
     /// Parse the endianness character.
+    ///
+    /// numpy's code `=` for native byte order is accepted, and produces [`Endianness::of_machine`].
     pub fn from_char(s: char) -> Option<Self> {
         match s {
             '<' => Some(Endianness::Little),
             '>' => Some(Endianness::Big),
+            '=' => Some(Endianness::of_machine()),
             '|' => Some(Endianness::Irrelevant),
             _ => None,
         }
@@ -100,12 +183,7 @@ impl Endianness {
     /// Returns `true` if byteorder swapping is required to convert data from this endianness to
     /// another.
     pub(crate) fn requires_swap(self, other: Endianness) -> bool {
-        match (self, other) {
-            (Endianness::Little, Endianness::Big) |
-            (Endianness::Big, Endianness::Little) => true,
-
-            _ => false,
-        }
+        matches!((self, other), (Endianness::Little, Endianness::Big) | (Endianness::Big, Endianness::Little))
     }
 }
 
@@ -214,7 +292,7 @@ impl TypeChar {
 
 impl TypeChar {
     // `None` means all sizes are valid.
-    fn valid_sizes(self) -> Option<&'static [u64]> {
+    pub(crate) fn valid_sizes(self) -> Option<&'static [u64]> {
         match self {
             TypeChar::Bool => Some(&[1]),
 
@@ -240,7 +318,7 @@ impl TypeChar {
     }
 
     /// Returns `true` if `|` endianness is illegal.
-    fn requires_endianness(self, size: u64) -> bool {
+    pub(crate) fn requires_endianness(self, size: u64) -> bool {
         match self {
             TypeChar::Bool |
             TypeChar::Int |
@@ -258,13 +336,8 @@ impl TypeChar {
     }
 
     /// Returns `true` if this dtype must have time units.
-    fn has_units(self) -> bool {
-        match self {
-            TypeChar::TimeDelta |
-            TypeChar::DateTime => true,
-
-            _ => false,
-        }
+    pub(crate) fn has_units(self) -> bool {
+        matches!(self, TypeChar::TimeDelta | TypeChar::DateTime)
     }
 }
 
@@ -331,6 +404,7 @@ pub enum TimeUnits {
 
 impl TimeUnits {
     /// Parse a time unit string (without the surrounding brackets).
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<TimeUnits> {
         match s {
             "Y" => Some(TimeUnits::Year),
@@ -682,4 +756,27 @@ mod tests {
         check_roundtrip!("<m8[D]");
         check_roundtrip!(">m8[ms]");
     }
+
+    #[test]
+    fn byte_order() {
+        let native = Endianness::of_machine().to_str();
+        let foreign = match Endianness::of_machine() {
+            Endianness::Little => ">",
+            _ => "<",
+        };
+        let parse = |s: String| s.parse::<TypeStr>().unwrap();
+
+        assert_eq!(parse("=i4".to_string()), parse(format!("{}i4", native)));
+        assert!(parse("=f8".to_string()).is_native_endian());
+        assert!(!parse(format!("{}f8", foreign)).is_native_endian());
+        assert!(parse(format!("{}u1", foreign)).is_native_endian());
+        assert!(parse(format!("{}S3", foreign)).is_native_endian());
+        assert!(!parse(format!("{}U3", foreign)).is_native_endian());
+
+        assert_eq!(parse("=u1".to_string()).normalize_byte_order().to_string(), "|u1");
+        assert_eq!(parse("<V4".to_string()).normalize_byte_order().to_string(), "|V4");
+        assert_eq!(parse(format!("{}c16", foreign)).normalize_byte_order().to_string(), format!("{}c16", foreign));
+        assert_eq!(parse(format!("{}c16", foreign)).to_native_endian().to_string(), format!("{}c16", native));
+        assert_eq!(parse(format!("{}b1", foreign)).to_native_endian().to_string(), "|b1");
+    }
 }
@@ -0,0 +1,287 @@
+//! Recording the units of `uom` quantities in the header.
+//!
+//! _This module is only available with the **`"uom"`** feature._
+
+use std::io;
+
+use py_literal::Value;
+use uom::num::Num;
+use uom::si::{Dimension, Quantity, Unit, Units};
+use uom::typenum::Integer;
+use uom::Conversion;
+
+use crate::error::Error;
+use crate::read::NpyHeader;
+
+/// Name of the header key that holds the [`FieldUnits`] of an array.
+pub const UNITS_HEADER_KEY: &str = "units";
+
+/// A type whose values are stored in a known unit, such as a [`uom::si::Quantity`].
+///
+/// _This trait is only available with the **`"uom"`** feature._
+pub trait QuantityUnit {
+    /// The unit that values are stored in, as a product of powers of base units. (e.g. `"m s^-1"`)
+    ///
+    /// This is `"1"` for a dimensionless quantity.
+    fn unit() -> String;
+}
+
+/// A quantity is stored in the base units of `U`.  For [`uom::si`], those are the SI base units.
+impl<D, U, V> QuantityUnit for Quantity<D, U, V>
+where
+    D: Dimension + ?Sized,
+    U: Units<V> + ?Sized,
+    V: Num + Conversion<V>,
+{
+    fn unit() -> String {
+        let factors = [
+            (U::length::abbreviation(), D::L::to_i32()),
+            (U::mass::abbreviation(), D::M::to_i32()),
+            (U::time::abbreviation(), D::T::to_i32()),
+            (U::electric_current::abbreviation(), D::I::to_i32()),
+            (U::thermodynamic_temperature::abbreviation(), D::Th::to_i32()),
+            (U::amount_of_substance::abbreviation(), D::N::to_i32()),
+            (U::luminous_intensity::abbreviation(), D::J::to_i32()),
+        ];
+        let parts = factors.iter().filter(|&&(_, power)| power != 0).map(|&(unit, power)| match power {
+            1 => unit.to_string(),
+            _ => format!("{}^{}", unit, power),
+        }).collect::<Vec<_>>();
+
+        match parts.is_empty() {
+            true => "1".to_string(),
+            false => parts.join(" "),
+        }
+    }
+}
+
+/// The units of the fields of an array, as recorded in the `'units'` key of the header.
+///
+/// For an array of plain quantities (rather than structs), the unit is recorded for the field `""`.
+/// The key is written by [`WriterBuilder::units`][crate::WriterBuilder::units], and checked when reading
+/// with [`FieldUnits::check`].
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::{FieldUnits, WriterBuilder};
+/// use npyz::uom::si::f64::{Length, Time};
+/// use npyz::uom::si::length::kilometer;
+///
+/// let lengths = [Length::new::<kilometer>(1.5), Length::new::<kilometer>(2.0)];
+///
+/// let mut bytes = vec![];
+/// let mut writer = {
+///     npyz::WriteOptions::new()
+///         .default_dtype()
+///         .shape(&[2])
+///         .units(&FieldUnits::of::<Length>())
+///         .writer(&mut bytes)
+///         .begin_nd()?
+/// };
+/// writer.extend(lengths.iter())?;
+/// writer.finish()?;
+///
+/// let npy = npyz::NpyFile::new(&bytes[..])?;
+/// FieldUnits::of::<Length>().check(npy.header())?;
+/// assert!(FieldUnits::of::<Time>().check(npy.header()).is_err());
+///
+/// assert_eq!(npy.into_vec::<Length>()?, lengths);
+/// # Ok(()) }
+/// ```
+///
+/// _This type is only available with the **`"uom"`** feature._
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldUnits {
+    fields: Vec<(String, String)>,
+}
+
+impl FieldUnits {
+    /// Create an empty set of units.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The units of an array whose elements are all of type `Q`.
+    pub fn of<Q: QuantityUnit>() -> Self {
+        Self::new().field::<Q>("")
+    }
+
+    /// Add a field of type `Q`, replacing any previous unit of the field.
+    pub fn field<Q: QuantityUnit>(self, name: &str) -> Self {
+        self.field_unit(name, &Q::unit())
+    }
+
+    /// Add a field with the given unit, replacing any previous unit of the field.
+    pub fn field_unit(mut self, name: &str, unit: &str) -> Self {
+        match self.fields.iter_mut().find(|(field, _)| field == name) {
+            Some((_, old)) => *old = unit.to_string(),
+            None => self.fields.push((name.to_string(), unit.to_string())),
+        }
+        self
+    }
+
+    /// Get the unit of a field.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields.iter().find(|(field, _)| field == name).map(|(_, unit)| &unit[..])
+    }
+
+    /// Iterate over the fields and their units, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.fields.iter().map(|(field, unit)| (&field[..], &unit[..]))
+    }
+
+    /// Get the units recorded in a header, or `None` if it has no `'units'` key.
+    ///
+    /// Returns [`Error::InvalidData`] if the key is not a dict of strings.
+    pub fn from_header(header: &NpyHeader) -> io::Result<Option<Self>> {
+        let literal = match header.extra_keys().iter().find(|(key, _)| key == UNITS_HEADER_KEY) {
+            Some((_, literal)) => literal,
+            None => return Ok(None),
+        };
+        let bad = || -> io::Error { Error::InvalidData(format!("'{}' is not a dict of strings: {}", UNITS_HEADER_KEY, literal)).into() };
+
+        let dict = match literal.parse::<Value>() {
+            Ok(Value::Dict(dict)) => dict,
+            _ => return Err(bad()),
+        };
+        let mut units = FieldUnits::new();
+        for (field, unit) in &dict {
+            match (field, unit) {
+                (Value::String(field), Value::String(unit)) => units = units.field_unit(field, unit),
+                _ => return Err(bad()),
+            }
+        }
+        Ok(Some(units))
+    }
+
+    /// Check that a header records these units for each of these fields.
+    ///
+    /// The header may record units for other fields as well.
+    /// Returns [`Error::InvalidData`] if the header has no units, or if any unit is missing or different.
+    pub fn check(&self, header: &NpyHeader) -> io::Result<()> {
+        let recorded = Self::from_header(header)?.ok_or_else(|| {
+            Error::InvalidData(format!("the header has no '{}' key", UNITS_HEADER_KEY))
+        })?;
+        for (field, expected) in self.iter() {
+            match recorded.get(field) {
+                Some(unit) if unit == expected => {},
+                Some(unit) => return Err(Error::InvalidData(format!(
+                    "{} has unit '{}', but '{}' was expected", describe_field(field), unit, expected,
+                )).into()),
+                None => return Err(Error::InvalidData(format!(
+                    "{} has no recorded unit, but '{}' was expected", describe_field(field), expected,
+                )).into()),
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn to_python_literal(&self) -> String {
+        let dict = self.fields.iter().map(|(field, unit)| {
+            (Value::String(field.clone()), Value::String(unit.clone()))
+        }).collect();
+        Value::Dict(dict).to_string()
+    }
+}
+
+fn describe_field(field: &str) -> String {
+    match field {
+        "" => "the array".to_string(),
+        _ => format!("field '{}'", field),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NpyFile, WriteOptions, WriterBuilder};
+    use uom::si::f32::Velocity as Velocity32;
+    use uom::si::f64::{Length, Ratio, Time, Velocity};
+    use uom::si::length::{centimeter, meter};
+
+    #[test]
+    fn unit_strings() {
+        assert_eq!(Length::unit(), "m");
+        assert_eq!(Velocity::unit(), "m s^-1");
+        assert_eq!(Velocity32::unit(), "m s^-1");
+        assert_eq!(uom::si::f64::Force::unit(), "m kg s^-2");
+        assert_eq!(Ratio::unit(), "1");
+    }
+
+    fn write_with_units(units: Option<&FieldUnits>) -> Vec<u8> {
+        let mut bytes = vec![];
+        let options = WriteOptions::<Length>::new().default_dtype().shape(&[2]);
+        let options = match units {
+            Some(units) => options.units(units),
+            None => options,
+        };
+        let mut writer = options.writer(&mut bytes).begin_nd().unwrap();
+        writer.push(&Length::new::<centimeter>(150.0)).unwrap();
+        writer.push(&Length::new::<meter>(2.0)).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn roundtrip_in_base_units() {
+        let bytes = write_with_units(Some(&FieldUnits::of::<Length>()));
+        let npy = NpyFile::new(&bytes[..]).unwrap();
+        assert_eq!(npy.header().extra_keys(), &[("units".to_string(), "{'': 'm'}".to_string())]);
+        FieldUnits::of::<Length>().check(npy.header()).unwrap();
+
+        // the numbers in the file are in meters
+        let raw = NpyFile::new(&bytes[..]).unwrap().into_vec::<f64>().unwrap();
+        assert_eq!(raw, vec![1.5, 2.0]);
+        let lengths = npy.into_vec::<Length>().unwrap();
+        assert_eq!(lengths, vec![Length::new::<meter>(1.5), Length::new::<meter>(2.0)]);
+    }
+
+    #[test]
+    fn check_rejects_other_units() {
+        let bytes = write_with_units(Some(&FieldUnits::of::<Length>()));
+        let npy = NpyFile::new(&bytes[..]).unwrap();
+
+        let err = FieldUnits::of::<Time>().check(npy.header()).unwrap_err();
+        assert_eq!(err.to_string(), "the array has unit 'm', but 's' was expected");
+
+        let err = FieldUnits::new().field::<Velocity>("speed").check(npy.header()).unwrap_err();
+        assert!(err.to_string().contains("field 'speed' has no recorded unit"), "{}", err);
+    }
+
+    #[test]
+    fn check_requires_units() {
+        let bytes = write_with_units(None);
+        let npy = NpyFile::new(&bytes[..]).unwrap();
+        assert_eq!(FieldUnits::from_header(npy.header()).unwrap(), None);
+
+        let err = FieldUnits::of::<Length>().check(npy.header()).unwrap_err();
+        assert!(err.to_string().contains("no 'units' key"), "{}", err);
+    }
+
+    #[test]
+    fn field_units() {
+        let units = {
+            FieldUnits::new()
+                .field::<Length>("position")
+                .field::<Velocity>("speed")
+                .field_unit("position", "cm")
+        };
+        assert_eq!(units.get("position"), Some("cm"));
+        assert_eq!(units.to_python_literal(), "{'position': 'cm', 'speed': 'm s^-1'}");
+    }
+
+    #[test]
+    fn bad_units_key() {
+        let mut bytes = vec![];
+        let writer = {
+            WriteOptions::<f64>::new().default_dtype().shape(&[0])
+                .extra_header_key("units", "['m']")
+                .writer(&mut bytes).begin_nd().unwrap()
+        };
+        writer.finish().unwrap();
+
+        let npy = NpyFile::new(&bytes[..]).unwrap();
+        let err = FieldUnits::from_header(npy.header()).unwrap_err();
+        assert!(err.to_string().contains("'units' is not a dict of strings"), "{}", err);
+    }
+}
@@ -0,0 +1,519 @@
+//! File I/O through io_uring.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::error::Error;
+use crate::read_at::ReadAtMany;
+use crate::write::Preallocate;
+
+const DEFAULT_QUEUE_DEPTH: u32 = 64;
+// the largest length of a single operation, as in Linux's MAX_RW_COUNT
+const MAX_OP_LEN: usize = 0x7fff_f000;
+
+/// A file whose reads and writes go through an [io_uring](https://man7.org/linux/man-pages/man7/io_uring.7.html)
+/// submission queue.
+///
+/// This is a drop-in replacement for a [`File`] as the source of an [`NpyFile`][`crate::NpyFile`] or the
+/// destination of a writer.  Its advantage is in [`ReadAtMany`]: [`NpyReader::read_many_at`][`crate::NpyReader::read_many_at`]
+/// and [`NpyReader::read_ranges_at`][`crate::NpyReader::read_ranges_at`] submit up to the queue depth of reads
+/// with a single system call, and the device works on all of them at once.  For many small scattered reads on
+/// an NVMe drive, this is much faster than one `pread` after another.
+///
+/// The ordinary [`Read`] and [`Write`] impls perform one operation per call, so for small sequential
+/// reads and writes, wrap it in a [`io::BufReader`] or [`io::BufWriter`] as you would a [`File`].
+///
+/// *This is only available with the **`"io-uring"`** feature, on Linux.*
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// let file = match npyz::UringFile::open("test-data/c-order.npy") {
+///     Ok(file) => file,
+///     // e.g. a kernel older than 5.6, or a sandbox that forbids io_uring
+///     Err(_) => return Ok(()),
+/// };
+/// let mut reader = npyz::NpyFile::new(file)?.data::<i64>().unwrap();
+/// assert_eq!(reader.read_ranges_at(&[0..2, 10..14])?, vec![vec![1, 1], vec![3, 3, 4, 4]]);
+/// # Ok(()) }
+/// ```
+pub struct UringFile {
+    file: File,
+    ring: Ring,
+    pos: u64,
+}
+
+impl UringFile {
+    /// Open a file for reading, with a queue depth of 64.
+    ///
+    /// Fails if the kernel does not support io_uring.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::with_queue_depth(File::open(path)?, DEFAULT_QUEUE_DEPTH)
+    }
+
+    /// Create a file with [`File::create`], with a queue depth of 64.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::with_queue_depth(File::create(path)?, DEFAULT_QUEUE_DEPTH)
+    }
+
+    /// Use an open file, with room for `depth` operations in flight at once.
+    ///
+    /// The kernel rounds the depth up to a power of two.  The position starts at the current position of
+    /// the file.
+    pub fn with_queue_depth(mut file: File, depth: u32) -> io::Result<Self> {
+        let pos = file.stream_position()?;
+        let ring = Ring::new(depth.max(1))?;
+        Ok(UringFile { file, ring, pos })
+    }
+
+    /// Get the underlying file.
+    pub fn get_ref(&self) -> &File {
+        &self.file
+    }
+
+    /// Take back the underlying file, at the position of this reader.
+    pub fn into_inner(self) -> io::Result<File> {
+        let UringFile { mut file, pos, .. } = self;
+        file.seek(SeekFrom::Start(pos))?;
+        Ok(file)
+    }
+
+    // Perform a single read or write at the current position.
+    fn single_op(&mut self, opcode: u8, addr: *mut u8, len: usize) -> io::Result<usize> {
+        let sqe = Sqe::new(opcode, self.file.as_raw_fd(), self.pos, addr, len.min(MAX_OP_LEN), 0);
+        loop {
+            let res = self.ring.run(&[sqe])?[0].res;
+            match res {
+                res if res >= 0 => {
+                    self.pos += res as u64;
+                    return Ok(res as usize);
+                },
+                res if retryable(res) => {},
+                res => return Err(io::Error::from_raw_os_error(-res)),
+            }
+        }
+    }
+}
+
+impl Read for UringFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.single_op(IORING_OP_READ, buf.as_mut_ptr(), buf.len())
+    }
+}
+
+impl Write for UringFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // the kernel only reads from the buffer of a write
+        self.single_op(IORING_OP_WRITE, buf.as_ptr() as *mut u8, buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for UringFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.file.metadata()?.len().checked_add_signed(delta),
+        };
+        self.pos = new_pos.ok_or_else(|| Error::InvalidInput(format!("invalid seek to {:?}", pos)))?;
+        Ok(self.pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl ReadAtMany for UringFile {
+    fn read_exact_at_many(&mut self, requests: &mut [(u64, &mut [u8])]) -> io::Result<()> {
+        let fd = self.file.as_raw_fd();
+        // (index of request, bytes read so far) for each unfinished request
+        let mut pending = (0..requests.len()).filter(|&i| !requests[i].1.is_empty()).map(|i| (i, 0)).collect::<Vec<_>>();
+        let mut sqes = vec![];
+        while !pending.is_empty() {
+            let batch_len = pending.len().min(self.ring.entries as usize);
+            sqes.clear();
+            for (slot, &(i, done)) in pending[..batch_len].iter().enumerate() {
+                let (offset, buf) = &mut requests[i];
+                let remaining = &mut buf[done..];
+                sqes.push(Sqe::new(IORING_OP_READ, fd, *offset + done as u64, remaining.as_mut_ptr(), remaining.len().min(MAX_OP_LEN), slot as u64));
+            }
+            for cqe in self.ring.run(&sqes)? {
+                let (i, done) = &mut pending[cqe.user_data as usize];
+                match cqe.res {
+                    0 => return Err(Error::Truncated.into()),
+                    res if res > 0 => *done += res as usize,
+                    res if retryable(res) => {},
+                    res => return Err(io::Error::from_raw_os_error(-res)),
+                }
+                if *done == requests[*i].1.len() {
+                    // mark as finished
+                    *i = usize::MAX;
+                }
+            }
+            // unfinished requests in the batch (e.g. short reads) are resubmitted with the rest
+            pending.retain(|&(i, _)| i != usize::MAX);
+        }
+        Ok(())
+    }
+}
+
+impl Preallocate for UringFile {
+    fn preallocate(&mut self, len: u64) -> io::Result<()> {
+        let end = self.pos.checked_add(len)
+            .ok_or_else(|| Error::InvalidInput(format!("cannot preallocate {} bytes", len)))?;
+        if self.file.metadata()?.len() < end {
+            self.file.set_len(end)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for UringFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("UringFile")
+            .field("file", &self.file)
+            .field("pos", &self.pos)
+            .field("queue_depth", &self.ring.entries)
+            .finish_non_exhaustive()
+    }
+}
+
+fn retryable(res: i32) -> bool {
+    res == -libc::EINTR || res == -libc::EAGAIN
+}
+
+// The raw interface, from linux/io_uring.h
+
+const IORING_OP_READ: u8 = 22;
+const IORING_OP_WRITE: u8 = 23;
+const IORING_ENTER_GETEVENTS: u32 = 1;
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x800_0000;
+const IORING_OFF_SQES: libc::off_t = 0x1000_0000;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqRingOffsets,
+    cq_off: CqRingOffsets,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    rw_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    pad: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+const _: () = assert!(std::mem::size_of::<Params>() == 120);
+const _: () = assert!(std::mem::size_of::<Sqe>() == 64);
+const _: () = assert!(std::mem::size_of::<Cqe>() == 16);
+
+impl Sqe {
+    fn new(opcode: u8, fd: i32, off: u64, addr: *mut u8, len: usize, user_data: u64) -> Self {
+        Sqe {
+            opcode, flags: 0, ioprio: 0, fd, off, addr: addr as u64, len: len as u32, rw_flags: 0,
+            user_data, buf_index: 0, personality: 0, splice_fd_in: 0, addr3: 0, pad: 0,
+        }
+    }
+}
+
+// A memory-mapped region shared with the kernel.
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Mapping {
+    fn new(fd: i32, len: usize, offset: libc::off_t) -> io::Result<Self> {
+        // SAFETY: A null address lets the kernel choose where to map.  The kernel checks the length and offset.
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED | libc::MAP_POPULATE, fd, offset)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping { ptr: ptr as *mut u8, len })
+    }
+
+    // SAFETY: `offset` must be within the mapping and aligned for a u32.
+    unsafe fn atomic(&self, offset: u32) -> &AtomicU32 {
+        &*(self.ptr.add(offset as usize) as *const AtomicU32)
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: This is the range returned by mmap, and no references to it outlive self.
+        unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.len) };
+    }
+}
+
+struct Ring {
+    fd: i32,
+    entries: u32,
+    sq: Mapping,
+    cq: Mapping,
+    sqes: Mapping,
+    params: Params,
+    // set if operations may still be in flight after an error, in which case the ring must not be used
+    broken: bool,
+}
+
+// The mappings are only accessed through `&mut Ring`.
+unsafe impl Send for Ring {}
+unsafe impl Sync for Ring {}
+
+impl Ring {
+    fn new(entries: u32) -> io::Result<Self> {
+        let mut params = Params::default();
+        // SAFETY: `params` is a valid io_uring_params, which the kernel fills in.
+        let fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, entries, &mut params as *mut Params) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = fd as i32;
+        let mapped = (|| {
+            let sq_len = params.sq_off.array as usize + params.sq_entries as usize * std::mem::size_of::<u32>();
+            let cq_len = params.cq_off.cqes as usize + params.cq_entries as usize * std::mem::size_of::<Cqe>();
+            let sqes_len = params.sq_entries as usize * std::mem::size_of::<Sqe>();
+            Ok((
+                Mapping::new(fd, sq_len, IORING_OFF_SQ_RING)?,
+                Mapping::new(fd, cq_len, IORING_OFF_CQ_RING)?,
+                Mapping::new(fd, sqes_len, IORING_OFF_SQES)?,
+            ))
+        })();
+        match mapped {
+            Ok((sq, cq, sqes)) => Ok(Ring { fd, entries: params.sq_entries, sq, cq, sqes, params, broken: false }),
+            Err(e) => {
+                // SAFETY: The fd was returned by io_uring_setup and is not used again.
+                unsafe { libc::close(fd) };
+                Err(e)
+            },
+        }
+    }
+
+    // Submit the operations and wait for all of them to complete, returning their completions in any order.
+    //
+    // The buffers of the operations must stay valid until this returns.
+    fn run(&mut self, sqes: &[Sqe]) -> io::Result<Vec<Cqe>> {
+        assert!(sqes.len() <= self.entries as usize);
+        if self.broken {
+            return Err(io::Error::other("io_uring is unusable after an earlier error"));
+        }
+        let (sq_off, cq_off) = (&self.params.sq_off, &self.params.cq_off);
+        // SAFETY: The offsets were given by the kernel for these mappings.
+        let (sq_tail, sq_mask, cq_head, cq_tail, cq_mask) = unsafe {(
+            self.sq.atomic(sq_off.tail),
+            self.sq.atomic(sq_off.ring_mask).load(Ordering::Relaxed),
+            self.cq.atomic(cq_off.head),
+            self.cq.atomic(cq_off.tail),
+            self.cq.atomic(cq_off.ring_mask).load(Ordering::Relaxed),
+        )};
+
+        // Every earlier operation has completed, so the submission queue is empty.
+        let mut tail = sq_tail.load(Ordering::Relaxed);
+        for sqe in sqes {
+            let index = tail & sq_mask;
+            // SAFETY: The index is within the ring, and the kernel only reads entries after the tail is published.
+            unsafe {
+                (self.sqes.ptr as *mut Sqe).add(index as usize).write(*sqe);
+                (self.sq.ptr.add(sq_off.array as usize) as *mut u32).add(index as usize).write(index);
+            }
+            tail = tail.wrapping_add(1);
+        }
+        sq_tail.store(tail, Ordering::Release);
+
+        let mut submitted = 0;
+        let mut completions = Vec::with_capacity(sqes.len());
+        let mut error = None;
+        while completions.len() < submitted || (error.is_none() && submitted < sqes.len()) {
+            // after an error, only wait for the operations in flight, because they may still use their buffers
+            let to_submit = match error {
+                Some(_) => 0,
+                None => sqes.len() - submitted,
+            };
+            let wait_for = submitted + to_submit - completions.len();
+            // SAFETY: The submitted entries refer to buffers that outlive this call.
+            let ret = unsafe {
+                libc::syscall(libc::SYS_io_uring_enter, self.fd, to_submit as u32, wait_for as u32, IORING_ENTER_GETEVENTS, std::ptr::null::<libc::c_void>(), 0)
+            };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(libc::EINTR | libc::EAGAIN | libc::EBUSY) => {},
+                    _ if error.is_none() => error = Some(err),
+                    // the buffers cannot be given back while the kernel may write to them
+                    _ => std::process::abort(),
+                }
+            } else {
+                submitted += ret as usize;
+            }
+
+            let mut head = cq_head.load(Ordering::Relaxed);
+            let end = cq_tail.load(Ordering::Acquire);
+            while head != end {
+                let index = head & cq_mask;
+                // SAFETY: Entries between the head and the tail have been written by the kernel.
+                completions.push(unsafe { (self.cq.ptr.add(cq_off.cqes as usize) as *const Cqe).add(index as usize).read() });
+                head = head.wrapping_add(1);
+            }
+            cq_head.store(head, Ordering::Release);
+        }
+        if let Some(err) = error {
+            // entries that were never submitted are still in the queue
+            self.broken = submitted < sqes.len();
+            return Err(err);
+        }
+        Ok(completions)
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        // The mappings are dropped after this, which is allowed once the fd is closed.
+        // SAFETY: The fd was returned by io_uring_setup and is not used again.
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NpyFile, WriteOptions, WriterBuilder};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("npyz-uring-{}-{}.npy", name, std::process::id()))
+    }
+
+    // io_uring may be unavailable where the tests run, e.g. in a container that forbids it.
+    fn open(path: &Path) -> Option<UringFile> {
+        match UringFile::with_queue_depth(File::open(path).unwrap(), 4) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("skipping io_uring test: {}", e);
+                None
+            },
+        }
+    }
+
+    #[test]
+    fn batched_reads() -> io::Result<()> {
+        let path = temp_path("read");
+        let values = (0..1000).collect::<Vec<u32>>();
+        crate::to_file_1d(&path, values.clone())?;
+
+        let mut file = match open(&path) {
+            Some(file) => file,
+            None => return std::fs::remove_file(&path),
+        };
+        let start = file.seek(SeekFrom::Start(0))?;
+        let mut reader = NpyFile::new(&mut file)?.data::<u32>().unwrap();
+        assert_eq!(reader.next().unwrap()?, 0);
+
+        // more requests than the queue depth
+        let indices = (0..50).map(|i| i * 37 % 1000).collect::<Vec<u64>>();
+        let expected = indices.iter().map(|&i| i as u32).collect::<Vec<_>>();
+        assert_eq!(reader.read_many_at(&indices)?, expected);
+        let ranges = [990..1000, 0..0, 5..7, 5..7, 300..700];
+        let expected = ranges.iter().map(|range| values[range.start as usize..range.end as usize].to_vec()).collect::<Vec<_>>();
+        assert_eq!(reader.read_ranges_at(&ranges)?, expected);
+        assert_eq!(reader.next().unwrap()?, 1);
+
+        let err = reader.read_many_at(&[3, 1000]).unwrap_err();
+        assert!(matches!(Error::from(err), Error::IndexOutOfBounds { index: 1000, len: 1000 }));
+        drop(reader);
+        assert!(file.stream_position()? > start);
+
+        let bytes = std::fs::read(&path)?;
+        std::fs::write(&path, &bytes[..bytes.len() - 1])?;
+        let file = open(&path).unwrap();
+        let mut reader = NpyFile::new(file)?.data::<u32>().unwrap();
+        assert_eq!(reader.read_ranges_at(&[0..1, 998..999])?, vec![vec![0], vec![998]]);
+        assert_eq!(reader.read_ranges_at(&[0..1, 999..1000]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        std::fs::remove_file(&path)
+    }
+
+    #[test]
+    fn write() -> io::Result<()> {
+        let path = temp_path("write");
+        let file = match UringFile::create(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("skipping io_uring test: {}", e);
+                return std::fs::remove_file(&path);
+            },
+        };
+        let mut writer = WriteOptions::new().default_dtype().shape(&[3, 2]).writer(io::BufWriter::new(file)).begin_nd_preallocated()?;
+        writer.extend(0..6_i16)?;
+        writer.finish()?;
+
+        let npy = NpyFile::new(io::BufReader::new(open(&path).unwrap()))?;
+        assert_eq!(npy.shape(), &[3, 2]);
+        assert_eq!(npy.into_vec::<i16>()?, (0..6).collect::<Vec<_>>());
+        std::fs::remove_file(&path)
+    }
+}
@@ -0,0 +1,197 @@
+//! Zero-copy access to the data of an NPY file that is already in memory.
+
+use std::io;
+use std::ops::Deref;
+
+use crate::error::Error;
+use crate::header::DType;
+use crate::read::{NpyFile, NpyHeader, Order};
+use crate::serialize::{Deserialize, DTypeError};
+
+/// A borrowed view of the data of an NPY file whose bytes are in memory, such as a memory-mapped file.
+///
+/// This derefs to `&[T]` over all elements in the order they are stored in the file
+/// (i.e. the reverse of the usual index order if the array is [Fortran order][`Order::Fortran`]),
+/// without copying them.  This is only possible when `T` can be read by reinterpreting the bytes in the
+/// file, i.e. when it is a primitive or complex type with the same size as the dtype, in the native byte
+/// order.  Otherwise, [`Error::DTypeMismatch`] is returned, and the data can be read with [`NpyFile`] instead.
+/// Files in the other byte order can first be converted with [`crate::byteswap_file`].
+///
+/// The data must also be aligned for `T` in memory.  This is always the case for a memory map, because
+/// maps begin on a page boundary and numpy aligns the data to 64 bytes within the file; but it may not be
+/// the case for e.g. a `Vec<u8>`, whose bytes are only aligned for `u8`.
+///
+/// This crate does not itself map files.  The following uses [`memmap2`](https://docs.rs/memmap2)
+/// (not compiled here, since `memmap2` is not a dependency of npyz):
+///
+/// ```ignore
+/// let file = std::fs::File::open("big.npy")?;
+/// // SAFETY: The file must not be modified while it is mapped.
+/// let map = unsafe { memmap2::Mmap::map(&file)? };
+/// let view = npyz::NpyView::<f32>::new(&map)?;
+/// let total: f32 = view.iter().sum();
+/// ```
+///
+/// Reading bytes that are already in memory:
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// // the bytes of an NPY file, aligned like a memory map
+/// let mut bytes = npyz::AlignedVec::new(64);
+/// std::fs::read("test-data/c-order.npy")?.into_iter().for_each(|b| bytes.push(b));
+///
+/// let view = npyz::NpyView::<i64>::new(&bytes)?;
+/// assert_eq!(view.shape(), &[2, 3, 4]);
+/// let copied = npyz::NpyFile::new(&bytes[..])?.into_vec::<i64>()?;
+/// assert_eq!(view[..], copied[..]);
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct NpyView<'a, T> {
+    header: NpyHeader,
+    data: &'a [T],
+}
+
+impl<'a, T: Deserialize> NpyView<'a, T> {
+    /// Read the header from the bytes of an NPY file, and view the data that follows it.
+    ///
+    /// The bytes must be the whole file: fails if the data is truncated or followed by more bytes,
+    /// if it is not aligned for `T`, or if `T` cannot be viewed in it (see the type-level docs).
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+        NpyFile::new(bytes)?.into_view()
+    }
+}
+
+impl<'a> NpyFile<&'a [u8]> {
+    /// View the data of a file that is in memory without copying it.  See [`NpyView`].
+    pub fn into_view<T: Deserialize>(self) -> io::Result<NpyView<'a, T>> {
+        let header = self.header().clone();
+        let dtype = header.dtype();
+        let reader = self.data::<T>().map_err(|e| Error::dtype_mismatch::<T>(&dtype, e))?;
+        match reader.records_as_slice()? {
+            Some(data) => Ok(NpyView { header, data }),
+            None => {
+                let reason = "the data cannot be viewed without converting it (it must be in the native byte order, with the same size as the type)";
+                Err(Error::dtype_mismatch::<T>(&dtype, DTypeError::custom(reason)).into())
+            },
+        }
+    }
+}
+
+impl<'a, T> NpyView<'a, T> {
+    /// Get the header of the file.
+    pub fn header(&self) -> &NpyHeader {
+        &self.header
+    }
+
+    /// Get the dtype as written in the file.
+    pub fn dtype(&self) -> DType {
+        self.header.dtype()
+    }
+
+    /// Get the shape as written in the file.
+    pub fn shape(&self) -> &[u64] {
+        self.header.shape()
+    }
+
+    /// Get the order in which the elements are stored.
+    pub fn order(&self) -> Order {
+        self.header.order()
+    }
+
+    /// Get the elements, with the lifetime of the bytes they are in rather than of the view.
+    pub fn as_slice(&self) -> &'a [T] {
+        self.data
+    }
+}
+
+impl<T> Deref for NpyView<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.data
+    }
+}
+
+impl<T> std::fmt::Debug for NpyView<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("NpyView")
+            .field("dtype", &self.header.dtype())
+            .field("shape", &self.header.shape())
+            .field("order", &self.header.order())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlignedVec, AutoSerialize, WriteOptions, WriterBuilder};
+
+    // A copy of the bytes that begins `offset` bytes after an alignment of 64.
+    fn aligned(bytes: &[u8], offset: usize) -> AlignedVec<u8> {
+        let mut out = AlignedVec::new(64);
+        std::iter::repeat(0).take(offset).chain(bytes.iter().copied()).for_each(|b| out.push(b));
+        out
+    }
+
+    fn npy<T: AutoSerialize>(dtype: &DType, shape: &[u64], data: &[T]) -> Vec<u8> {
+        let mut out = vec![];
+        let mut writer = WriteOptions::new().dtype(dtype.clone()).shape(shape).writer(&mut out).begin_nd().unwrap();
+        writer.extend(data).unwrap();
+        writer.finish().unwrap();
+        out
+    }
+
+    #[test]
+    fn view() {
+        let data = (0..12).map(|x| x as f64 / 2.0).collect::<Vec<_>>();
+        let bytes = aligned(&npy(&f64::default_dtype(), &[3, 4], &data), 0);
+        let view = NpyView::<f64>::new(&bytes).unwrap();
+        assert_eq!(view.shape(), &[3, 4]);
+        assert_eq!(view.order(), Order::C);
+        assert_eq!(&view[..], &data[..]);
+        // the elements are borrowed from the bytes
+        assert_eq!(view.as_ptr() as usize, bytes.as_ptr() as usize + view.header().data_offset().unwrap() as usize);
+    }
+
+    #[test]
+    fn empty() {
+        let bytes = aligned(&npy::<i32>(&i32::default_dtype(), &[0, 5], &[]), 0);
+        let view = NpyView::<i32>::new(&bytes).unwrap();
+        assert!(view.is_empty());
+    }
+
+    #[test]
+    fn not_viewable() {
+        // the other byte order
+        let swapped = DType::parse(if cfg!(target_endian = "little") { "'>i4'" } else { "'<i4'" }).unwrap();
+        let bytes = aligned(&npy(&swapped, &[2], &[1i32, 2]), 0);
+        let err = NpyView::<i32>::new(&bytes).unwrap_err();
+        assert!(matches!(Error::from(err), Error::DTypeMismatch { .. }));
+
+        // a dtype that is widened when read
+        let bytes = aligned(&npy(&i32::default_dtype(), &[2], &[1i32, 2]), 0);
+        let err = NpyView::<i64>::new(&bytes).unwrap_err();
+        assert!(matches!(Error::from(err), Error::DTypeMismatch { .. }));
+    }
+
+    #[test]
+    fn bad_bytes() {
+        let file = npy(&u32::default_dtype(), &[4], &[1u32, 2, 3, 4]);
+
+        let bytes = aligned(&file[..file.len() - 1], 0);
+        let err = NpyView::<u32>::new(&bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut longer = file.clone();
+        longer.push(0);
+        let bytes = aligned(&longer, 0);
+        let err = NpyView::<u32>::new(&bytes).unwrap_err();
+        assert!(err.to_string().contains("1 trailing bytes"), "{}", err);
+
+        let bytes = aligned(&file, 1);
+        let err = NpyView::<u32>::new(&bytes[1..]).unwrap_err();
+        assert!(err.to_string().contains("not aligned"), "{}", err);
+    }
+}
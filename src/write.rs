@@ -5,21 +5,42 @@ use std::marker::PhantomData;
 
 use byteorder::{WriteBytesExt, LittleEndian};
 
-use crate::serialize::{AutoSerialize, Serialize, TypeWrite};
+use crate::error::Error;
+use crate::serialize::{AutoSerialize, Serialize, TypeWrite, NativeLayout};
 use crate::header::{self, DType, VersionProps, HeaderSizeType, HeaderEncoding};
-use crate::read::Order;
+use crate::read::{Order, NpyHeader, STANDARD_KEYS};
 
 // Long enough to accomodate a large integer followed by ",), }".
 // Used when no shape is provided.
-const FILLER_FOR_UNKNOWN_SIZE: &'static [u8] = &[b'*'; 19];
+const FILLER_FOR_UNKNOWN_SIZE: &[u8] = &[b'*'; 19];
 
 struct DataFromBuilder<T: ?Sized> {
     order: Order,
     dtype: DType,
     shape: Option<Vec<u64>>,
+    header_template: write_options::HeaderTemplate,
+    buffer_size: usize,
+    flush_policy: FlushPolicy,
     _marker: PhantomData<fn(&T)>, // contravariant
 }
 
+/// When an [`NpyWriter`] calls [`Write::flush`] on its output stream.
+///
+/// Set with [`WriterBuilder::flush_policy`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum FlushPolicy {
+    /// Only flush when the writer is finished.  This is the default.
+    #[default]
+    OnFinish,
+    /// Flush after every write to the output stream, so that data reaches e.g. a pipe or socket as soon
+    /// as possible.
+    ///
+    /// With a [buffer][`WriterBuilder::buffer_size`], this happens whenever the buffer is written out;
+    /// without one, it happens after every row (or every chunk of rows that is written at once).
+    EveryWrite,
+}
+
 pub use write_options::{WriteOptions, WriterBuilder};
 pub mod write_options {
     //! Types and traits related to the implementation of [`WriteOptions`].
@@ -41,6 +62,9 @@ pub mod write_options {
     #[derive(Debug)]
     pub struct WriteOptions<T: ?Sized> {
         order: Order,
+        buffer_size: usize,
+        flush_policy: FlushPolicy,
+        header_template: HeaderTemplate,
         _marker: PhantomData<fn(&T)>, // contravariant
     }
 
@@ -48,16 +72,33 @@ pub mod write_options {
         /// Construct an almost empty Writer configuration.
         pub fn new() -> Self { WriteOptions {
             order: Order::C,
+            buffer_size: 0,
+            flush_policy: FlushPolicy::OnFinish,
+            header_template: HeaderTemplate::default(),
             _marker: PhantomData,
         }}
     }
 
+    /// Parts of the header that are carried over by [`WriterBuilder::preserve_header`].
+    #[doc(hidden)]
+    #[derive(Debug, Clone, Default)]
+    pub struct HeaderTemplate {
+        pub(super) extra_keys: Vec<(String, String)>,
+        pub(super) raw_bytes: Option<Vec<u8>>,
+    }
+
     impl<T: ?Sized> Default for WriteOptions<T> {
         fn default() -> Self { Self::new() }
     }
 
     impl<T: ?Sized> Clone for WriteOptions<T> {
-        fn clone(&self) -> Self { WriteOptions { order: self.order.clone(), _marker: self._marker }}
+        fn clone(&self) -> Self { WriteOptions {
+            order: self.order,
+            buffer_size: self.buffer_size,
+            flush_policy: self.flush_policy,
+            header_template: self.header_template.clone(),
+            _marker: self._marker,
+        }}
     }
 
     /// Trait that provides methods on [`WriteOptions`].
@@ -91,16 +132,138 @@ pub mod write_options {
             Self: MissingWriter,
         { WithWriter { inner: self, writer } }
 
+        /// Append the output to a `Vec<u8>`.
+        ///
+        /// This is [`Self::writer`] with a [`VecSink`], which implements [`Seek`] so that [`Self::begin_1d`]
+        /// can be used as well.
+        ///
+        /// ```
+        /// # fn main() -> std::io::Result<()> {
+        /// use npyz::WriterBuilder;
+        ///
+        /// let mut bytes = vec![];
+        /// let mut writer = npyz::WriteOptions::new().default_dtype().writer_vec(&mut bytes).begin_1d()?;
+        /// writer.extend([1_i64, 2, 3])?;
+        /// writer.finish()?;
+        ///
+        /// assert_eq!(npyz::NpyFile::new(&bytes[..])?.into_vec::<i64>()?, vec![1, 2, 3]);
+        /// # Ok(()) }
+        /// ```
+        fn writer_vec(self, vec: &mut Vec<u8>) -> WithWriter<VecSink<'_>, Self>
+        where
+            Self: MissingWriter,
+        { self.writer(VecSink::new(vec)) }
+
         /// Set the data order for arrays with more than one dimension.
         ///
-        /// If this is not called, `Order::C` is assumed.
+        /// If this is not called, `Order::C` is assumed.  With [`Order::Fortran`], the header says
+        /// `'fortran_order': True`, and the elements written are taken to be in column-major order.
+        ///
+        /// ```
+        /// # fn main() -> std::io::Result<()> {
+        /// use npyz::WriterBuilder;
+        ///
+        /// // the matrix [[1, 2, 3], [4, 5, 6]], one column at a time
+        /// let mut bytes = vec![];
+        /// let mut writer = {
+        ///     npyz::WriteOptions::new()
+        ///         .default_dtype()
+        ///         .shape(&[2, 3])
+        ///         .order(npyz::Order::Fortran)
+        ///         .writer(&mut bytes)
+        ///         .begin_nd()?
+        /// };
+        /// writer.extend([1_i32, 4, 2, 5, 3, 6])?;
+        /// writer.finish()?;
+        ///
+        /// let npy = npyz::NpyFile::new(&bytes[..])?;
+        /// assert_eq!(npy.order(), npyz::Order::Fortran);
+        /// assert_eq!(npy.strides(), &[1, 2]);
+        /// # Ok(()) }
+        /// ```
         fn order(self, order: Order) -> Self;
 
+        /// Collect the output in an internal buffer of about this many bytes, and only write to the
+        /// [`writer`][Self::writer] when it is full.
+        ///
+        /// By default, nothing is buffered and each element is written to the writer individually, which
+        /// is slow unless the writer does its own buffering (e.g. an [`io::BufWriter`]).
+        ///
+        /// The header is buffered as well, and when a large chunk of data is written (e.g. by
+        /// [`NpyWriter::extend_from_slice`]), it is combined with the buffered data in a single vectored write.
+        fn buffer_size(self, bytes: usize) -> Self;
+
+        /// Set when the [`writer`][Self::writer] is flushed.  The default is [`FlushPolicy::OnFinish`].
+        fn flush_policy(self, policy: FlushPolicy) -> Self;
+
+        /// Add a key to the header dict, in addition to the standard `'descr'`, `'fortran_order'` and `'shape'`.
+        ///
+        /// The value must be the source text of a Python literal.  (e.g. `"'hello'"` or `"[1, 2]"`)
+        /// An error will be returned by `begin_*` if it cannot be parsed, or if the key is one of the standard keys.
+        ///
+        /// Be aware that `numpy.load` refuses to read files with extra keys.
+        fn extra_header_key(mut self, key: &str, python_literal: &str) -> Self {
+            let template = self.__header_template_mut();
+            template.extra_keys.push((key.to_string(), python_literal.to_string()));
+            template.raw_bytes = None;
+            self
+        }
+
+        /// Record the units of quantities in the `'units'` key of the header.
+        ///
+        /// They can be checked when reading with [`FieldUnits::check`][crate::FieldUnits::check].
+        /// Like any extra key, this makes the file unreadable by `numpy.load`.
+        ///
+        /// _This method is only available with the **`"uom"`** feature._
+        #[cfg(feature = "uom")]
+        fn units(self, units: &crate::FieldUnits) -> Self {
+            self.extra_header_key(crate::UNITS_HEADER_KEY, &units.to_python_literal())
+        }
+
+        /// Reuse parts of a header read from another file, for lossless round trips.
+        ///
+        /// This carries over the [extra keys][`NpyHeader::extra_keys`] of the header.  Furthermore, if the
+        /// header has [`raw_bytes`][`NpyHeader::raw_bytes`] and the dtype, shape and order being written
+        /// are the same as the header's, those bytes are written verbatim, so that a file whose data is copied
+        /// unchanged will be byte-for-byte identical to the original.
+        ///
+        /// ```
+        /// # fn main() -> std::io::Result<()> {
+        /// use npyz::WriterBuilder;
+        ///
+        /// let original = std::fs::read("test-data/c-order.npy")?;
+        /// let npy = npyz::NpyFile::new(&original[..])?;
+        ///
+        /// let mut copy = vec![];
+        /// let mut writer = {
+        ///     npyz::WriteOptions::new()
+        ///         .dtype(npy.dtype())
+        ///         .shape(npy.shape())
+        ///         .preserve_header(npy.header())
+        ///         .writer(&mut copy)
+        ///         .begin_nd()?
+        /// };
+        /// writer.extend(npy.data::<i64>().unwrap().collect::<Result<Vec<_>, _>>()?)?;
+        /// writer.finish()?;
+        ///
+        /// assert_eq!(original, copy);
+        /// # Ok(()) }
+        /// ```
+        fn preserve_header(mut self, header: &NpyHeader) -> Self {
+            let template = self.__header_template_mut();
+            template.extra_keys = header.extra_keys().to_vec();
+            template.raw_bytes = header.raw_bytes().map(|bytes| bytes.to_vec());
+            self
+        }
+
         // getters for properties not encoded in typestate
         #[doc(hidden)] fn __get_order(&self) -> Order;
+        #[doc(hidden)] fn __get_buffer_size(&self) -> usize;
+        #[doc(hidden)] fn __get_flush_policy(&self) -> FlushPolicy;
+        #[doc(hidden)] fn __header_template_mut(&mut self) -> &mut HeaderTemplate;
 
         /// Begin writing an array of the previously supplied [`shape`][Self::shape].
-        fn begin_nd(self) -> io::Result<NpyWriter<T, <Self as HasWriter>::Writer>>
+        fn begin_nd(mut self) -> io::Result<NpyWriter<T, <Self as HasWriter>::Writer>>
         where
             Self: HasDType + HasWriter + HasShape,
             <Self as HasWriter>::Writer: Write,
@@ -109,10 +272,44 @@ pub mod write_options {
                 dtype: self.__get_dtype(),
                 order: self.__get_order(),
                 shape: Some(self.__get_shape()),
+                header_template: std::mem::take(self.__header_template_mut()),
+                buffer_size: self.__get_buffer_size(),
+                flush_policy: self.__get_flush_policy(),
                 _marker: PhantomData,
             }, MaybeSeek::Isnt(self.__into_writer()))
         }
 
+        /// Like [`Self::begin_nd`], but first reserve the final size of the file in the output stream.
+        ///
+        /// For a [`File`], this extends the file to its final length with [`File::set_len`] before
+        /// any data is written, so that the filesystem can allocate it contiguously, and so that a full disk is
+        /// more likely to be noticed at the start rather than near the end.  How much space is actually reserved
+        /// depends on the filesystem; some create a sparse file instead.  A file that is already longer is left
+        /// as is.  If writing stops early, the rest of the file will be filled with zeros.
+        ///
+        /// ```
+        /// # fn main() -> std::io::Result<()> {
+        /// use npyz::WriterBuilder;
+        ///
+        /// # let path = std::env::temp_dir().join(format!("npyz-preallocate-doctest-{}.npy", std::process::id()));
+        /// let file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        /// let mut writer = npyz::WriteOptions::new().default_dtype().shape(&[1000]).writer(file).begin_nd_preallocated()?;
+        /// assert_eq!(std::fs::metadata(&path)?.len(), 128 + 8 * 1000);
+        /// writer.extend(0..1000_i64)?;
+        /// writer.finish()?;
+        /// # std::fs::remove_file(&path)?;
+        /// # Ok(()) }
+        /// ```
+        fn begin_nd_preallocated(self) -> io::Result<NpyWriter<T, <Self as HasWriter>::Writer>>
+        where
+            Self: HasDType + HasWriter + HasShape,
+            <Self as HasWriter>::Writer: Write + Preallocate,
+        {
+            let mut writer = self.begin_nd()?;
+            writer.preallocate()?;
+            Ok(writer)
+        }
+
         /// Begin writing a 1d array, of length to be inferred from the number of elements written.
         ///
         /// Notice that, in contrast to [`Self::begin_nd`], this method requires [`Seek`].  If you have
@@ -120,7 +317,7 @@ pub mod write_options {
         ///
         /// **Note:** At present, any [`shape`][Self::shape] you *did* happen to provide will be ignored and not
         /// validated against the number of elements written.  This may change in the future.
-        fn begin_1d(self) -> io::Result<NpyWriter<T, <Self as HasWriter>::Writer>>
+        fn begin_1d(mut self) -> io::Result<NpyWriter<T, <Self as HasWriter>::Writer>>
         where
             Self: HasDType + HasWriter,
             <Self as HasWriter>::Writer: Write + Seek,
@@ -129,9 +326,90 @@ pub mod write_options {
                 dtype: self.__get_dtype(),
                 order: self.__get_order(),
                 shape: None,
+                header_template: std::mem::take(self.__header_template_mut()),
+                buffer_size: self.__get_buffer_size(),
+                flush_policy: self.__get_flush_policy(),
                 _marker: PhantomData,
             }, MaybeSeek::new_seek(self.__into_writer()))
         }
+
+        /// Write an [`ndarray`] array (or view) as a complete file, using its shape.
+        ///
+        /// _This method is only available with the **`"ndarray"`** feature._
+        ///
+        /// The array may have any memory layout; its elements are written in the logical order of
+        /// the [`order`][Self::order] given to the builder.  Any [`shape`][Self::shape] given to the
+        /// builder is ignored.
+        ///
+        /// ```
+        /// # fn main() -> std::io::Result<()> {
+        /// use npyz::WriterBuilder;
+        ///
+        /// let array = ndarray::Array::from_shape_fn((4, 6), |(i, j)| (10 * i + j) as i32);
+        /// let view = array.slice(ndarray::s![.., ..;2]);
+        ///
+        /// let mut bytes = vec![];
+        /// npyz::WriteOptions::new().default_dtype().writer(&mut bytes).write_array(&view)?;
+        ///
+        /// let npy = npyz::NpyFile::new(&bytes[..])?;
+        /// assert_eq!(npy.shape(), &[4, 3]);
+        /// assert_eq!(npy.into_array::<i32, ndarray::Ix2>()?, view);
+        /// # Ok(()) }
+        /// ```
+        #[cfg(feature = "ndarray")]
+        fn write_array<S, D>(self, array: &ndarray::ArrayBase<S, D>) -> io::Result<()>
+        where
+            Self: HasDType + HasWriter,
+            <Self as HasWriter>::Writer: Write,
+            T: Sized,
+            S: ndarray::Data<Elem = T>,
+            D: ndarray::Dimension,
+        {
+            let shape = array.shape().iter().map(|&n| n as u64).collect::<Vec<_>>();
+            let order = self.__get_order();
+            let mut writer = self.shape(&shape).begin_nd()?;
+            crate::ndarray_feature::write_elements(&mut writer, array, order)?;
+            writer.finish()
+        }
+
+        /// Write an [`nalgebra`] matrix (or view) as a complete 2-D file, using its shape.
+        ///
+        /// _This method is only available with the **`"nalgebra"`** feature._
+        ///
+        /// The elements are written in the [`order`][Self::order] given to the builder; the default of C order
+        /// matches numpy, but Fortran order matches the column-major storage of `nalgebra`.  Any
+        /// [`shape`][Self::shape] given to the builder is ignored.
+        ///
+        /// ```
+        /// # fn main() -> std::io::Result<()> {
+        /// use npyz::WriterBuilder;
+        ///
+        /// let matrix = nalgebra::dmatrix![1.0, 2.0, 3.0; 4.0, 5.0, 6.0];
+        ///
+        /// let mut bytes = vec![];
+        /// npyz::WriteOptions::new().default_dtype().writer(&mut bytes).write_matrix(&matrix)?;
+        ///
+        /// let npy = npyz::NpyFile::new(&bytes[..])?;
+        /// assert_eq!(npy.shape(), &[2, 3]);
+        /// assert_eq!(npy.into_vec::<f64>()?, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        /// # Ok(()) }
+        /// ```
+        #[cfg(feature = "nalgebra")]
+        fn write_matrix<R, C, S>(self, matrix: &nalgebra::Matrix<T, R, C, S>) -> io::Result<()>
+        where
+            Self: HasDType + HasWriter,
+            <Self as HasWriter>::Writer: Write,
+            T: Sized,
+            R: nalgebra::Dim,
+            C: nalgebra::Dim,
+            S: nalgebra::RawStorage<T, R, C>,
+        {
+            let shape = [matrix.nrows() as u64, matrix.ncols() as u64];
+            let order = self.__get_order();
+            let mut writer = self.shape(&shape).begin_nd()?;
+            crate::nalgebra_feature::write_elements(&mut writer, matrix, order)?;
+            writer.finish()
+        }
     }
 
     /// Return type of [`WriterBuilder::writer`].  It represents a config with a known output stream.
@@ -217,22 +495,42 @@ pub mod write_options {
 
     impl<T: Serialize + ?Sized> WriterBuilder<T> for WriteOptions<T> {
         fn order(mut self, order: Order) -> Self { self.order = order; self }
+        fn buffer_size(mut self, bytes: usize) -> Self { self.buffer_size = bytes; self }
+        fn flush_policy(mut self, policy: FlushPolicy) -> Self { self.flush_policy = policy; self }
         fn __get_order(&self) -> Order { self.order }
+        fn __get_buffer_size(&self) -> usize { self.buffer_size }
+        fn __get_flush_policy(&self) -> FlushPolicy { self.flush_policy }
+        fn __header_template_mut(&mut self) -> &mut HeaderTemplate { &mut self.header_template }
     }
 
     impl<W, T: Serialize + ?Sized, B: WriterBuilder<T>> WriterBuilder<T> for WithWriter<W, B> {
         fn order(mut self, order: Order) -> Self { self.inner = self.inner.order(order); self }
+        fn buffer_size(mut self, bytes: usize) -> Self { self.inner = self.inner.buffer_size(bytes); self }
+        fn flush_policy(mut self, policy: FlushPolicy) -> Self { self.inner = self.inner.flush_policy(policy); self }
         fn __get_order(&self) -> Order { self.inner.__get_order() }
+        fn __get_buffer_size(&self) -> usize { self.inner.__get_buffer_size() }
+        fn __get_flush_policy(&self) -> FlushPolicy { self.inner.__get_flush_policy() }
+        fn __header_template_mut(&mut self) -> &mut HeaderTemplate { self.inner.__header_template_mut() }
     }
 
     impl<T: Serialize + ?Sized, B: WriterBuilder<T>> WriterBuilder<T> for WithDType<B> {
         fn order(mut self, order: Order) -> Self { self.inner = self.inner.order(order); self }
+        fn buffer_size(mut self, bytes: usize) -> Self { self.inner = self.inner.buffer_size(bytes); self }
+        fn flush_policy(mut self, policy: FlushPolicy) -> Self { self.inner = self.inner.flush_policy(policy); self }
         fn __get_order(&self) -> Order { self.inner.__get_order() }
+        fn __get_buffer_size(&self) -> usize { self.inner.__get_buffer_size() }
+        fn __get_flush_policy(&self) -> FlushPolicy { self.inner.__get_flush_policy() }
+        fn __header_template_mut(&mut self) -> &mut HeaderTemplate { self.inner.__header_template_mut() }
     }
 
     impl<T: Serialize + ?Sized, B: WriterBuilder<T>> WriterBuilder<T> for WithShape<B> {
         fn order(mut self, order: Order) -> Self { self.inner = self.inner.order(order); self }
+        fn buffer_size(mut self, bytes: usize) -> Self { self.inner = self.inner.buffer_size(bytes); self }
+        fn flush_policy(mut self, policy: FlushPolicy) -> Self { self.inner = self.inner.flush_policy(policy); self }
         fn __get_order(&self) -> Order { self.inner.__get_order() }
+        fn __get_buffer_size(&self) -> usize { self.inner.__get_buffer_size() }
+        fn __get_flush_policy(&self) -> FlushPolicy { self.inner.__get_flush_policy() }
+        fn __header_template_mut(&mut self) -> &mut HeaderTemplate { self.inner.__header_template_mut() }
     }
 
     // Now the silly part where we have to write O(n^2) trait impls
@@ -321,6 +619,12 @@ pub struct NpyWriter<Row: Serialize + ?Sized, W: Write> {
     shape_info: ShapeInfo,
     num_items: u64,
     fw: MaybeSeek<W>,
+    // output that has not yet been written to `fw`, if buffering is enabled
+    buf: Vec<u8>,
+    buffer_size: usize,
+    flush_policy: FlushPolicy,
+    item_size: Option<usize>,
+    order: Order,
     writer: <Row as Serialize>::TypeWriter,
     version_props: VersionProps,
 }
@@ -333,6 +637,149 @@ enum ShapeInfo {
     Known { expected_num_items: u64 },
 }
 
+/// An output stream that appends to a `Vec<u8>`, and can seek within the bytes it has appended.
+///
+/// Positions are relative to the length of the `Vec` when the `VecSink` was created, so that
+/// existing contents are never overwritten.  Writing at a position before the end overwrites bytes
+/// in place, like an [`io::Cursor`].
+///
+/// With the `"bytes"` feature, it can also append to a [`bytes::BytesMut`]; see [`SinkBuffer`].
+///
+/// See [`WriterBuilder::writer_vec`] and, with the `"npz"` feature, [`NpzWriter::new_vec`][crate::npz::NpzWriter::new_vec].
+#[derive(Debug)]
+pub struct VecSink<'a, B: SinkBuffer = Vec<u8>> {
+    vec: &'a mut B,
+    start: usize,
+    pos: usize,
+}
+
+impl<'a, B: SinkBuffer> VecSink<'a, B> {
+    /// Begin appending to a `Vec` (or another [`SinkBuffer`]).
+    pub fn new(vec: &'a mut B) -> Self {
+        let start = vec.len();
+        VecSink { vec, start, pos: 0 }
+    }
+}
+
+/// A growable buffer of bytes that a [`VecSink`] can append to.
+///
+/// This is implemented for `Vec<u8>` and, with the **`"bytes"`** feature, for [`bytes::BytesMut`].
+/// It is sealed, so that methods can be added without a breaking change.
+pub trait SinkBuffer: sink_buffer::Sealed {}
+
+mod sink_buffer {
+    pub trait Sealed: std::ops::DerefMut<Target = [u8]> {
+        // Grow the buffer to `len` bytes, filling it with zeros.
+        fn grow_zeroed(&mut self, len: usize);
+        fn append(&mut self, bytes: &[u8]);
+    }
+
+    impl Sealed for Vec<u8> {
+        fn grow_zeroed(&mut self, len: usize) {
+            self.resize(len, 0);
+        }
+
+        fn append(&mut self, bytes: &[u8]) {
+            self.extend_from_slice(bytes);
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    impl Sealed for bytes::BytesMut {
+        fn grow_zeroed(&mut self, len: usize) {
+            self.resize(len, 0);
+        }
+
+        fn append(&mut self, bytes: &[u8]) {
+            self.extend_from_slice(bytes);
+        }
+    }
+}
+
+impl SinkBuffer for Vec<u8> {}
+
+#[cfg(feature = "bytes")]
+impl SinkBuffer for bytes::BytesMut {}
+
+impl<B: SinkBuffer> Write for VecSink<'_, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // (positions are only limited to `usize` by `seek`)
+        let offset = self.start.checked_add(self.pos).filter(|offset| offset.checked_add(buf.len()).is_some())
+            .ok_or_else(|| Error::InvalidInput("write at an overflowing position".to_string()))?;
+        if offset > self.vec.len() {
+            // seeking past the end leaves a gap, as it would in a file
+            self.vec.grow_zeroed(offset);
+        }
+        let overwritten = buf.len().min(self.vec.len() - offset);
+        self.vec[offset..offset + overwritten].copy_from_slice(&buf[..overwritten]);
+        self.vec.append(&buf[overwritten..]);
+        self.pos += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<B: SinkBuffer> Seek for VecSink<'_, B> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let len = (self.vec.len() - self.start) as u64;
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(delta) => (self.pos as u64).checked_add_signed(delta),
+            SeekFrom::End(delta) => len.checked_add_signed(delta),
+        };
+        let new_pos = new_pos.and_then(|pos| usize::try_from(pos).ok())
+            .ok_or_else(|| Error::InvalidInput("invalid seek to a negative or overflowing position".to_string()))?;
+        self.pos = new_pos;
+        Ok(new_pos as u64)
+    }
+}
+
+/// Output streams that can reserve space for data before it is written.
+///
+/// Used by [`WriterBuilder::begin_nd_preallocated`].
+pub trait Preallocate {
+    /// Make room for `len` bytes after the current position.
+    fn preallocate(&mut self, len: u64) -> io::Result<()>;
+}
+
+impl Preallocate for File {
+    fn preallocate(&mut self, len: u64) -> io::Result<()> {
+        preallocate_file(self, len)
+    }
+}
+
+impl Preallocate for &File {
+    fn preallocate(&mut self, len: u64) -> io::Result<()> {
+        preallocate_file(self, len)
+    }
+}
+
+impl<W: Write + Preallocate> Preallocate for BufWriter<W> {
+    fn preallocate(&mut self, len: u64) -> io::Result<()> {
+        // the buffered bytes come before the inner writer's position
+        let buffered = self.buffer().len() as u64;
+        self.get_mut().preallocate(buffered + len)
+    }
+}
+
+impl<W: Preallocate + ?Sized> Preallocate for &mut W {
+    fn preallocate(&mut self, len: u64) -> io::Result<()> {
+        (**self).preallocate(len)
+    }
+}
+
+fn preallocate_file(mut file: &File, len: u64) -> io::Result<()> {
+    let end = file.stream_position()?.checked_add(len)
+        .ok_or_else(|| Error::InvalidInput(format!("cannot preallocate {} bytes", len)))?;
+    if file.metadata()?.len() < end {
+        file.set_len(end)?;
+    }
+    Ok(())
+}
+
 /// [`NpyWriter`] that writes an entire file.
 #[deprecated(since = "0.5.0", note = "Doesn't carry its weight.  Use to_file_1d instead, or replicate the original behavior with Builder::new().default_dtype().begin_1d(std::io::BufWriter::new(std::fs::File::create(path)?))")]
 pub type OutFile<Row> = NpyWriter<Row, BufWriter<File>>;
@@ -363,89 +810,275 @@ impl<Row: Serialize> OutFile<Row> {
 
 impl<Row: Serialize + ?Sized , W: Write> NpyWriter<Row, W> {
     fn _begin(builder: DataFromBuilder<Row>, mut fw: MaybeSeek<W>) -> io::Result<Self> {
-        let DataFromBuilder { dtype, order, shape, _marker } = builder;
+        let DataFromBuilder { dtype, order, shape, header_template, buffer_size, flush_policy, _marker } = builder;
 
         let start_pos = match fw {
-            MaybeSeek::Is(ref mut fw) => Some(fw.seek(SeekFrom::Current(0))?),
+            MaybeSeek::Is(ref mut fw) => Some(fw.stream_position()?),
             MaybeSeek::Isnt(_) => None,
         };
 
         if let DType::Array(..) = dtype {
-            panic!("the outermost dtype cannot be an array (got: {:?})", dtype);
+            return Err(Error::InvalidInput(format!("the outermost dtype cannot be an array (got: {:?})", dtype)).into());
+        }
+        if let Some(shape) = &shape {
+            if shape.iter().try_fold(1u64, |acc, &dim| acc.checked_mul(dim)).is_none() {
+                return Err(Error::InvalidInput(format!("the number of elements in shape {:?} overflows", shape)).into());
+            }
         }
 
-        let (dict_text, shape_info) = create_dict(&dtype, order, shape.as_deref());
-        let (header_text, version, version_props) = determine_required_version_and_pad_header(dict_text);
-
-        fw.write_all(&[0x93u8])?;
-        fw.write_all(b"NUMPY")?;
-        fw.write_all(&[version.0, version.1])?;
-
-        assert_eq!((header_text.len() + version_props.bytes_before_text()) % 16, 0);
-        match version_props.header_size_type {
-            HeaderSizeType::U16 => {
-                assert!(header_text.len() <= u16::MAX as usize);
-                fw.write_u16::<LittleEndian>(header_text.len() as u16)?;
+        let reusable_raw_bytes = header_template.raw_bytes.filter(|raw_bytes| {
+            raw_header_matches(raw_bytes, &dtype, order, shape.as_deref())
+        });
+        let (header_bytes, shape_info, version_props) = match reusable_raw_bytes {
+            Some(raw_bytes) => {
+                let version_props = header::get_version_props((raw_bytes[6], raw_bytes[7]))?;
+                let expected_num_items = shape.as_ref().expect("checked by raw_header_matches").iter().product();
+                (raw_bytes, ShapeInfo::Known { expected_num_items }, version_props)
             },
-            HeaderSizeType::U32 => {
-                assert!(header_text.len() <= u32::MAX as usize);
-                fw.write_u32::<LittleEndian>(header_text.len() as u32)?;
+            None => {
+                validate_extra_keys(&header_template.extra_keys)?;
+                let (dict_text, shape_info) = create_dict(&dtype, order, shape.as_deref(), &header_template.extra_keys);
+                let (header_bytes, version_props) = encode_header(dict_text);
+                (header_bytes, shape_info, version_props)
             },
-        }
-        fw.write_all(&header_text)?;
+        };
 
         let writer = match Row::writer(&dtype) {
             Ok(writer) => writer,
-            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+            Err(e) => return Err(Error::dtype_mismatch::<Row>(&dtype, e).into()),
         };
 
-        Ok(NpyWriter {
+        let mut npy_writer = NpyWriter {
             start_pos,
             shape_info,
             num_items: 0,
             fw,
+            buf: Vec::with_capacity(buffer_size),
+            buffer_size,
+            flush_policy,
+            item_size: dtype.num_bytes(),
+            order,
             writer,
             version_props,
-        })
+        };
+        // with a buffer, the header is written together with the first data
+        npy_writer.write_bytes(&header_bytes)?;
+        Ok(npy_writer)
     }
 
     /// Append a single row to the file
     pub fn push(&mut self, row: &Row) -> io::Result<()> {
         self.num_items += 1;
-        self.writer.write_one(&mut self.fw, row)
+        if self.buffer_size == 0 {
+            self.writer.write_one(&mut self.fw, row)?;
+            return self.after_write();
+        }
+        self.writer.write_one(&mut self.buf, row)?;
+        if self.buf.len() >= self.buffer_size {
+            self.flush_buffer()?;
+        }
+        Ok(())
+    }
+
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            self.fw.write_all(&self.buf)?;
+            self.buf.clear();
+            self.after_write()?;
+        }
+        Ok(())
     }
 
     /// Write an iterator to the file
+    ///
+    /// For primitive numbers in the native byte order, the rows are collected into chunks that are each
+    /// written at once, like [`Self::extend_from_slice`].
     pub fn extend(&mut self, rows: impl IntoIterator<Item=Row>) -> io::Result<()> where Row: Sized {
-        rows.into_iter().try_for_each(|row| self.push(&row))
+        if self.native_layout().is_none() {
+            return rows.into_iter().try_for_each(|row| self.push(&row));
+        }
+        let chunk_len = (crate::read::DEFAULT_BUFFER_SIZE / std::mem::size_of::<Row>()).max(1);
+        let mut chunk = Vec::with_capacity(chunk_len);
+        for row in rows {
+            chunk.push(row);
+            if chunk.len() == chunk_len {
+                self.extend_from_slice(&chunk)?;
+                chunk.clear();
+            }
+        }
+        self.extend_from_slice(&chunk)
+    }
+
+    /// Write a slice of rows to the file.
+    ///
+    /// For primitive numbers in the native byte order, the bytes of the slice are written with a single
+    /// copy instead of serializing each element.
+    pub fn extend_from_slice(&mut self, rows: &[Row]) -> io::Result<()> where Row: Sized {
+        match self.native_layout() {
+            Some(_proof) => {
+                // SAFETY: NativeLayout guarantees that Row has no padding, so all of its bytes are initialized.
+                let bytes = unsafe { std::slice::from_raw_parts(rows.as_ptr() as *const u8, std::mem::size_of_val(rows)) };
+                self.num_items += rows.len() as u64;
+                self.write_bytes(bytes)
+            },
+            None => rows.iter().try_for_each(|row| self.push(row)),
+        }
+    }
+
+    /// Write the elements of a strided array in foreign memory, such as a buffer owned by a C library,
+    /// without first copying them into a `Vec`.
+    ///
+    /// `shape` and `strides` have one entry per axis, and the strides are counted in elements (not bytes),
+    /// so that the element at index `[i, j, ...]` is at `ptr.offset(i * strides[0] + j * strides[1] + ...)`.
+    /// Strides may be negative or zero.  The elements are written in the [order][`WriterBuilder::order`] of
+    /// the file, i.e. with the last axis varying fastest for [`Order::C`] and the first for [`Order::Fortran`].
+    /// An empty `shape` writes the single element at `ptr`.
+    ///
+    /// Runs of contiguous elements along the fastest axis are written like [`Self::extend_from_slice`].
+    ///
+    /// Returns [`Error::InvalidInput`] if `shape` and `strides` have different lengths.
+    ///
+    /// # Safety
+    ///
+    /// For every index within `shape`, the pointer computed as above must be valid for reads of an
+    /// initialized, properly aligned `Row`, and lie within the same allocation as `ptr`.  The memory must not
+    /// be mutated during the call.  Nothing is read if any dimension is zero.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use npyz::WriterBuilder;
+    ///
+    /// // a 2x3 view of every other column of a 2x6 buffer
+    /// let buffer = [0.0_f32, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0];
+    /// let mut bytes = vec![];
+    /// let mut writer = npyz::WriteOptions::new().default_dtype().shape(&[2, 3]).writer(&mut bytes).begin_nd()?;
+    /// unsafe { writer.extend_from_raw_parts(buffer.as_ptr(), &[2, 3], &[6, 2])? };
+    /// writer.finish()?;
+    ///
+    /// assert_eq!(npyz::NpyFile::new(&bytes[..])?.into_vec::<f32>()?, vec![0.0, 2.0, 4.0, 6.0, 8.0, 10.0]);
+    /// # Ok(()) }
+    /// ```
+    pub unsafe fn extend_from_raw_parts(&mut self, ptr: *const Row, shape: &[usize], strides: &[isize]) -> io::Result<()> where Row: Sized {
+        if shape.len() != strides.len() {
+            return Err(Error::InvalidInput(format!("shape {:?} and strides {:?} have different lengths", shape, strides)).into());
+        }
+        if shape.contains(&0) {
+            return Ok(());
+        }
+        // the axes from slowest to fastest
+        let axes = match self.order {
+            Order::C => (0..shape.len()).collect::<Vec<_>>(),
+            Order::Fortran => (0..shape.len()).rev().collect(),
+        };
+        let (inner, outer) = match axes.split_last() {
+            Some((&inner, outer)) => (inner, outer),
+            // SAFETY: the caller guarantees that the single element is readable
+            None => return self.push(unsafe { &*ptr }),
+        };
+        let (inner_len, inner_stride) = (shape[inner], strides[inner]);
+        let contiguous = self.native_layout().is_some() && (inner_stride == 1 || inner_len == 1);
+
+        let mut index = vec![0; outer.len()];
+        let mut offset = 0_isize;
+        loop {
+            // SAFETY: the caller guarantees that every element within the shape is readable
+            unsafe {
+                let run = ptr.offset(offset);
+                match contiguous {
+                    true => self.extend_from_slice(std::slice::from_raw_parts(run, inner_len))?,
+                    false => (0..inner_len).try_for_each(|i| self.push(&*run.offset(i as isize * inner_stride)))?,
+                }
+            }
+
+            // advance to the next run, like an odometer
+            let mut done = true;
+            for (i, &axis) in index.iter_mut().zip(outer).rev() {
+                *i += 1;
+                offset += strides[axis];
+                if *i < shape[axis] {
+                    done = false;
+                    break;
+                }
+                *i = 0;
+                offset -= strides[axis] * shape[axis] as isize;
+            }
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    // `Some` if rows can be written by copying their bytes.
+    fn native_layout(&self) -> Option<NativeLayout> where Row: Sized {
+        let size = std::mem::size_of::<Row>();
+        self.writer.native_layout().filter(|_| self.item_size == Some(size) && size > 0)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if bytes.len() >= self.buffer_size {
+            // too large to buffer; write it out together with anything already buffered
+            write_all_vectored(&mut self.fw, [&self.buf, bytes])?;
+            self.buf.clear();
+            return self.after_write();
+        }
+        if self.buf.len() + bytes.len() > self.buffer_size {
+            self.flush_buffer()?;
+        }
+        self.buf.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn after_write(&mut self) -> io::Result<()> {
+        match self.flush_policy {
+            FlushPolicy::OnFinish => Ok(()),
+            FlushPolicy::EveryWrite => self.fw.flush(),
+        }
     }
 
     fn finish_(&mut self) -> io::Result<()> {
+        self.flush_buffer()?;
         match self.shape_info {
             ShapeInfo::Known { expected_num_items } => {
                 if expected_num_items != self.num_items {
-                    return Err(io::Error::new(io::ErrorKind::InvalidData, {
+                    return Err(Error::InvalidData({
                         format!("shape has {} item(s), but {} item(s) were written!", expected_num_items, self.num_items)
-                    }));
+                    }).into());
                 }
             },
             ShapeInfo::Automatic { offset_in_header_text } => {
                 // Write the size to the header
                 let shape_pos = self.start_pos.unwrap() + self.version_props.bytes_before_text() as u64 + offset_in_header_text;
-                let end_pos = self.fw.seek(SeekFrom::Current(0))?;
-
-                self.fw.seek(SeekFrom::Start(shape_pos))?;
-                let length = format!("{}", self.num_items);
-                self.fw.write_all(length.as_bytes())?;
-                self.fw.write_all(&b",), }"[..])?;
-                self.fw.write_all(&::std::iter::repeat(b' ').take(FILLER_FOR_UNKNOWN_SIZE.len() - length.len()).collect::<Vec<_>>())?;
-                self.fw.seek(SeekFrom::Start(end_pos))?;
+                write_unknown_len(&mut self.fw, shape_pos, self.num_items)?;
             },
         }
         self.fw.flush()?;
         Ok(())
     }
 
+    // Reserve space for the data, and for the header if it is still in the buffer.  (called before any data is written)
+    fn preallocate(&mut self) -> io::Result<()> where W: Preallocate {
+        let expected_num_items = match self.shape_info {
+            ShapeInfo::Known { expected_num_items } => expected_num_items,
+            ShapeInfo::Automatic { .. } => return Ok(()),
+        };
+        let data_len = self.item_size.and_then(|size| expected_num_items.checked_mul(size as u64))
+            .ok_or_else(|| Error::InvalidInput(format!("the size of {} item(s) overflows", expected_num_items)))?;
+        match &mut self.fw {
+            MaybeSeek::Isnt(w) => w.preallocate(self.buf.len() as u64 + data_len),
+            MaybeSeek::Is(_) => unreachable!("preallocation requires a known shape"),
+        }
+    }
+
+    /// Write out any buffered rows and flush the underlying stream, regardless of the
+    /// [flush policy][`WriterBuilder::flush_policy`].
+    pub fn flush(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            self.fw.write_all(&self.buf)?;
+            self.buf.clear();
+        }
+        self.fw.flush()
+    }
+
     /// Finish writing the file.
     ///
     /// If no shape was provided, this will update the header to reflect the number of
@@ -458,7 +1091,103 @@ impl<Row: Serialize + ?Sized , W: Write> NpyWriter<Row, W> {
     }
 }
 
-fn create_dict(dtype: &DType, order: Order, shape: Option<&[u64]>) -> (Vec<u8>, ShapeInfo) {
+// Like the unstable `Write::write_all_vectored`.
+fn write_all_vectored<const N: usize>(mut writer: impl Write, mut bufs: [&[u8]; N]) -> io::Result<()> {
+    while bufs.iter().any(|buf| !buf.is_empty()) {
+        match writer.write_vectored(&bufs.map(io::IoSlice::new)) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(mut n) => for buf in &mut bufs {
+                let advance = n.min(buf.len());
+                *buf = &buf[advance..];
+                n -= advance;
+            },
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Prepend the magic string, version and length to the text of a header dict, padding it as required.
+fn encode_header(dict_text: Vec<u8>) -> (Vec<u8>, VersionProps) {
+    let (header_text, version, version_props) = determine_required_version_and_pad_header(dict_text);
+
+    let mut out = vec![0x93u8];
+    out.extend(b"NUMPY");
+    out.extend([version.0, version.1]);
+
+    assert_eq!((header_text.len() + version_props.bytes_before_text()) % 16, 0);
+    match version_props.header_size_type {
+        HeaderSizeType::U16 => {
+            assert!(header_text.len() <= u16::MAX as usize);
+            out.write_u16::<LittleEndian>(header_text.len() as u16).unwrap();
+        },
+        HeaderSizeType::U32 => {
+            assert!(header_text.len() <= u32::MAX as usize);
+            out.write_u32::<LittleEndian>(header_text.len() as u32).unwrap();
+        },
+    }
+    out.extend(header_text);
+    (out, version_props)
+}
+
+/// Get the complete bytes of the header for an array of known shape.
+pub(crate) fn header_bytes(dtype: &DType, order: Order, shape: &[u64], extra_keys: &[(String, String)]) -> io::Result<Vec<u8>> {
+    validate_extra_keys(extra_keys)?;
+    let (dict_text, _) = create_dict(dtype, order, Some(shape), extra_keys);
+    Ok(encode_header(dict_text).0)
+}
+
+/// Get the complete bytes of the header for a C-order 1-D array whose length is not known yet, along
+/// with the position of the length in the header, for [`write_unknown_len`].
+pub(crate) fn header_bytes_unknown_len(dtype: &DType, extra_keys: &[(String, String)]) -> io::Result<(Vec<u8>, u64)> {
+    validate_extra_keys(extra_keys)?;
+    match create_dict(dtype, Order::C, None, extra_keys) {
+        (dict_text, ShapeInfo::Automatic { offset_in_header_text }) => {
+            let (header_bytes, version_props) = encode_header(dict_text);
+            Ok((header_bytes, version_props.bytes_before_text() as u64 + offset_in_header_text))
+        },
+        (_, ShapeInfo::Known { .. }) => unreachable!("(BUG!) no shape was given"),
+    }
+}
+
+/// Fill in the length of a header from [`header_bytes_unknown_len`], whose length was placed at
+/// `shape_pos` in the stream.  The stream is left at its current position.
+pub(crate) fn write_unknown_len(fw: &mut (impl Write + Seek), shape_pos: u64, len: u64) -> io::Result<()> {
+    let end_pos = fw.stream_position()?;
+    fw.seek(SeekFrom::Start(shape_pos))?;
+    let length = format!("{}", len);
+    fw.write_all(length.as_bytes())?;
+    fw.write_all(&b",), }"[..])?;
+    fw.write_all(&vec![b' '; FILLER_FOR_UNKNOWN_SIZE.len() - length.len()])?;
+    fw.seek(SeekFrom::Start(end_pos))?;
+    Ok(())
+}
+
+/// Returns `true` if the raw bytes of a header describe exactly this array.
+fn raw_header_matches(raw_bytes: &[u8], dtype: &DType, order: Order, shape: Option<&[u64]>) -> bool {
+    let mut reader = raw_bytes;
+    match (NpyHeader::from_reader(&mut reader), shape) {
+        (Ok(header), Some(shape)) => {
+            reader.is_empty() && &header.dtype() == dtype && header.order() == order && header.shape() == shape
+        },
+        _ => false,
+    }
+}
+
+fn validate_extra_keys(extra_keys: &[(String, String)]) -> io::Result<()> {
+    for (key, value) in extra_keys {
+        if STANDARD_KEYS.contains(&&key[..]) {
+            return Err(Error::InvalidInput(format!("cannot use {:?} as an extra header key", key)).into());
+        }
+        if let Err(e) = header::parse_python_literal(value) {
+            return Err(Error::InvalidInput(format!("value for header key {:?}: {}", key, e)).into());
+        }
+    }
+    Ok(())
+}
+
+fn create_dict(dtype: &DType, order: Order, shape: Option<&[u64]>, extra_keys: &[(String, String)]) -> (Vec<u8>, ShapeInfo) {
     let mut header: Vec<u8> = vec![];
     header.extend(&b"{'descr': "[..]);
     header.extend(dtype.descr().as_bytes());
@@ -467,6 +1196,10 @@ fn create_dict(dtype: &DType, order: Order, shape: Option<&[u64]>) -> (Vec<u8>,
         Order::C => header.extend(&b"False"[..]),
         Order::Fortran => header.extend(&b"True"[..]),
     }
+    // these go before the shape, because an automatic shape is patched in at the very end of the dict
+    for (key, value) in extra_keys {
+        write!(header, ", {}: {}", header::PyUtf8StringLiteral(key), value).unwrap();
+    }
     header.extend(&b", 'shape': ("[..]);
     let shape_info = match shape {
         Some(shape) => {
@@ -533,7 +1266,7 @@ fn determine_required_version_and_pad_header(mut header_utf8: Vec<u8>) -> (Vec<u
     const ALIGN_TO: usize = 64;
 
     let bytes_before_text = actual_props.bytes_before_text();
-    header_utf8.extend(&::std::iter::repeat(b' ').take(ALIGN_TO - 1 - ((header_utf8.len() + bytes_before_text) % ALIGN_TO)).collect::<Vec<_>>());
+    header_utf8.extend(&vec![b' '; ALIGN_TO - 1 - ((header_utf8.len() + bytes_before_text) % ALIGN_TO)]);
     header_utf8.push(b'\n');
     assert_eq!((header_utf8.len() + bytes_before_text) % ALIGN_TO, 0);
 
@@ -564,9 +1297,7 @@ where
 {
     #![allow(deprecated)]
     let mut of = OutFile::open(filename)?;
-    for row in data {
-        of.push(&row)?;
-    }
+    of.extend(data)?;
     of.close()
 }
 
@@ -606,6 +1337,13 @@ mod maybe_seek {
             }
         }
 
+        fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+            match self {
+                MaybeSeek::Is(w) => (*w).write_vectored(bufs),
+                MaybeSeek::Isnt(w) => w.write_vectored(bufs),
+            }
+        }
+
         fn flush(&mut self) -> io::Result<()> {
             match self {
                 MaybeSeek::Is(w) => (*w).flush(),
@@ -680,6 +1418,7 @@ pub(crate) fn to_writer_1d_with_seeking<W: io::Write + io::Seek, T: AutoSerializ
 }
 
 #[cfg(test)]
+#[allow(clippy::zero_prefixed_literal)]
 mod tests {
     use super::*;
     use std::io::{self, Cursor};
@@ -721,12 +1460,74 @@ mod tests {
 
         // check the bytes written by `OutFile`
         let written_bytes = &raw_buffer[prefix.len()..raw_buffer.len() - suffix.len()];
-        let reader = NpyFile::new(&written_bytes[..])?;
+        let reader = NpyFile::new(written_bytes)?;
         assert_eq!(reader.into_vec::<f64>()?, vec![1.0, 3.0, 5.0]);
 
         Ok(())
     }
 
+    #[test]
+    fn vec_sink() -> io::Result<()> {
+        let mut vec = b"prefix".to_vec();
+        let mut sink = VecSink::new(&mut vec);
+        sink.write_all(b"abcdef")?;
+        assert_eq!(sink.seek(SeekFrom::Current(-4))?, 2);
+        sink.write_all(b"XY")?;
+        assert_eq!(sink.seek(SeekFrom::End(2))?, 8);
+        sink.write_all(b"!")?;
+        assert!(sink.seek(SeekFrom::Current(-10)).is_err());
+        assert_eq!(vec, b"prefixabXYef\0\0!");
+
+        let mut sink = VecSink::new(&mut vec);
+        sink.seek(SeekFrom::Start(u64::MAX))?;
+        let err = sink.write(b"x").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        // 1d arrays, whose length is written at the end, go after the existing contents
+        let mut vec = b"prefix".to_vec();
+        let mut writer = WriteOptions::new().default_dtype().writer_vec(&mut vec).begin_1d()?;
+        writer.extend([1_u16, 2, 3])?;
+        writer.finish()?;
+        assert_eq!(&vec[..6], b"prefix");
+        assert_eq!(vec[6..], to_bytes_1d(&[1_u16, 2, 3])?);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "bytes")]
+    fn bytes_sink() -> io::Result<()> {
+        let mut buf = bytes::BytesMut::from(&b"prefix"[..]);
+        let mut writer = WriteOptions::new().default_dtype().writer(VecSink::new(&mut buf)).begin_1d()?;
+        writer.extend([1_u16, 2, 3])?;
+        writer.finish()?;
+        let bytes = buf.freeze();
+        assert_eq!(&bytes[..6], b"prefix");
+        assert_eq!(bytes[6..], to_bytes_1d(&[1_u16, 2, 3])?);
+        Ok(())
+    }
+
+    #[test]
+    fn preallocate() -> io::Result<()> {
+        let path = std::env::temp_dir().join(format!("npyz-preallocate-{}.npy", std::process::id()));
+        for buffer_size in [0, 100, 1 << 16] {
+            let file = File::create(&path)?;
+            let mut writer = WriteOptions::new().default_dtype().shape(&[10, 20]).buffer_size(buffer_size).writer(&file).begin_nd_preallocated()?;
+            let expected_len = file.metadata()?.len();
+            writer.extend((0..200).map(|x| x as f32))?;
+            writer.finish()?;
+            assert_eq!(file.metadata()?.len(), expected_len);
+            assert_eq!(crate::NpyFile::new(File::open(&path)?)?.into_vec::<f32>()?, (0..200).map(|x| x as f32).collect::<Vec<_>>());
+        }
+
+        // a longer file is not truncated
+        std::fs::write(&path, vec![0; 10_000])?;
+        let mut file = std::fs::OpenOptions::new().write(true).open(&path)?;
+        let writer = WriteOptions::<u8>::new().default_dtype().shape(&[1]).writer(&mut file).begin_nd_preallocated()?;
+        drop(writer);
+        assert_eq!(file.metadata()?.len(), 10_000);
+        std::fs::remove_file(&path)
+    }
+
     #[test]
     fn implicit_finish() -> io::Result<()> {
         let mut cursor = Cursor::new(vec![]);
@@ -756,6 +1557,152 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn buffered() -> io::Result<()> {
+        // counts the calls to the underlying writer
+        struct CountWrites<W> { inner: W, count: usize }
+        impl<W: Write> Write for CountWrites<W> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.count += 1; self.inner.write(buf) }
+            fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
+        }
+        impl<W: Seek> Seek for CountWrites<W> {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> { self.inner.seek(pos) }
+        }
+
+        let data = (0..1000).collect::<Vec<i32>>();
+        let mut unbuffered = CountWrites { inner: Cursor::new(vec![]), count: 0 };
+        let mut writer = WriteOptions::new().default_dtype().writer(&mut unbuffered).begin_1d()?;
+        writer.extend(&data)?;
+        writer.finish()?;
+
+        let mut buffered = CountWrites { inner: Cursor::new(vec![]), count: 0 };
+        let mut writer = WriteOptions::new().default_dtype().buffer_size(1024).writer(&mut buffered).begin_1d()?;
+        writer.extend(&data)?;
+        writer.finish()?;
+
+        assert_eq!(buffered.inner.get_ref(), unbuffered.inner.get_ref());
+        assert!(unbuffered.count >= 1000);
+        assert!(buffered.count < 20, "{}", buffered.count);
+
+        // the buffer is also written out when the writer is dropped
+        let mut bytes = vec![];
+        let mut writer = WriteOptions::new().default_dtype().shape(&[3]).buffer_size(1 << 16).writer(&mut bytes).begin_nd()?;
+        writer.extend(vec![1.0, 2.0, 3.0])?;
+        drop(writer);
+        assert_eq!(NpyFile::new(&bytes[..])?.into_vec::<f64>()?, vec![1.0, 2.0, 3.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn vectored_and_flush_policy() -> io::Result<()> {
+        #[derive(Default)]
+        struct Recorder { bytes: Vec<u8>, writes: usize, flushes: usize }
+        impl Write for Recorder {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.write_vectored(&[io::IoSlice::new(buf)]) }
+            fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+                self.writes += 1;
+                bufs.iter().for_each(|buf| self.bytes.extend_from_slice(buf));
+                Ok(bufs.iter().map(|buf| buf.len()).sum())
+            }
+            fn flush(&mut self) -> io::Result<()> { self.flushes += 1; Ok(()) }
+        }
+
+        let data = (0..10_000).collect::<Vec<i64>>();
+        let mut expected = vec![];
+        to_writer_nd(&mut expected, &data, &[100, 100])?;
+
+        // the header is buffered and then written along with the data
+        let mut out = Recorder::default();
+        let mut writer = WriteOptions::new().default_dtype().shape(&[100, 100]).buffer_size(4096).writer(&mut out).begin_nd()?;
+        writer.extend_from_slice(&data)?;
+        writer.finish()?;
+        assert_eq!(out.writes, 1);
+        assert_eq!(out.bytes, expected);
+
+        let mut out = Recorder::default();
+        let builder = WriteOptions::new().default_dtype().shape(&[100, 100]).buffer_size(8000).flush_policy(FlushPolicy::EveryWrite);
+        let mut writer = builder.writer(&mut out).begin_nd()?;
+        writer.extend(data.iter().copied())?;
+        writer.finish()?;
+        assert!(out.writes > 1);
+        assert!(out.flushes > out.writes);
+        assert_eq!(out.bytes, expected);
+
+        let mut out = Recorder::default();
+        let mut writer = WriteOptions::new().default_dtype().shape(&[3]).flush_policy(FlushPolicy::EveryWrite).writer(&mut out).begin_nd()?;
+        [1, 2, 3].iter().try_for_each(|x| writer.push(x))?;
+        writer.finish()?;
+        assert_eq!(out.writes, 4);
+        assert!(out.flushes > 4);
+        Ok(())
+    }
+
+    type VecWriter<'a> = NpyWriter<f64, &'a mut Vec<u8>>;
+
+    #[test]
+    fn bulk_write() -> io::Result<()> {
+        let data = (0..100_000).map(|x| x as f64 / 3.0).collect::<Vec<_>>();
+        let other_endian = match crate::Endianness::of_machine() {
+            crate::Endianness::Little => ">f8",
+            _ => "<f8",
+        };
+        for dtype in [f64::default_dtype(), DType::parse(&format!("'{}'", other_endian)).unwrap()] {
+            for buffer_size in [0, 100, 1 << 20] {
+                let write = |how: &dyn Fn(&mut VecWriter) -> io::Result<()>| -> io::Result<Vec<u8>> {
+                    let mut bytes = vec![];
+                    let builder = WriteOptions::new().dtype(dtype.clone()).shape(&[data.len() as u64]).buffer_size(buffer_size);
+                    let mut writer = builder.writer(&mut bytes).begin_nd()?;
+                    how(&mut writer)?;
+                    writer.finish()?;
+                    Ok(bytes)
+                };
+                let expected = write(&|w| data.iter().try_for_each(|x| w.push(x)))?;
+                assert_eq!(write(&|w| w.extend_from_slice(&data))?, expected);
+                assert_eq!(write(&|w| w.extend(data.iter().copied()))?, expected);
+                assert_eq!(write(&|w| {
+                    w.extend_from_slice(&data[..10])?;
+                    w.push(&data[10])?;
+                    w.extend(data[11..].iter().copied())
+                })?, expected);
+                assert_eq!(NpyFile::new(&expected[..])?.into_vec::<f64>()?, data);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn raw_parts() -> io::Result<()> {
+        // a 4x5 buffer
+        let buffer = (0..20).collect::<Vec<i32>>();
+        let write = |order: Order, offset: usize, shape: &[usize], strides: &[isize]| -> io::Result<Vec<i32>> {
+            let mut outputs = vec![];
+            // both the native byte order (copied in runs) and the other one (serialized one by one)
+            for dtype in ["'<i4'", "'>i4'"] {
+                let mut bytes = vec![];
+                let mut writer = WriteOptions::new().dtype(DType::parse(dtype).unwrap()).order(order).writer(Cursor::new(&mut bytes)).begin_1d()?;
+                unsafe { writer.extend_from_raw_parts(buffer.as_ptr().add(offset), shape, strides)? };
+                writer.finish()?;
+                outputs.push(NpyFile::new(&bytes[..])?.into_vec::<i32>()?);
+            }
+            assert_eq!(outputs[0], outputs[1]);
+            Ok(outputs.remove(0))
+        };
+
+        assert_eq!(write(Order::C, 0, &[2, 3], &[5, 1])?, vec![0, 1, 2, 5, 6, 7]);
+        assert_eq!(write(Order::Fortran, 0, &[2, 3], &[5, 1])?, vec![0, 5, 1, 6, 2, 7]);
+        // every other column, reversed rows, broadcast along a middle axis
+        assert_eq!(write(Order::C, 15, &[2, 2, 3], &[-5, 0, 2])?, vec![15, 17, 19, 15, 17, 19, 10, 12, 14, 10, 12, 14]);
+        assert_eq!(write(Order::C, 0, &[4, 1], &[5, 7])?, vec![0, 5, 10, 15]);
+        assert_eq!(write(Order::C, 7, &[], &[])?, vec![7]);
+        assert_eq!(write(Order::C, 0, &[3, 0], &[5, 1])?, vec![]);
+
+        let mut bytes = vec![];
+        let mut writer = WriteOptions::new().default_dtype().writer(Cursor::new(&mut bytes)).begin_1d()?;
+        let err = unsafe { writer.extend_from_raw_parts(buffer.as_ptr(), &[2, 3], &[5]) }.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        Ok(())
+    }
+
     #[test]
     fn write_nd_wrong_len() -> io::Result<()> {
         let try_writing = |elems: &[i32]| -> io::Result<()> {
@@ -773,4 +1720,83 @@ mod tests {
 
         Ok(())
     }
+
+    fn header_with_text(dict_text: &str) -> Vec<u8> {
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend((dict_text.len() as u16).to_le_bytes());
+        bytes.extend(dict_text.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn extra_header_keys() -> io::Result<()> {
+        let mut buf = vec![];
+        let mut writer = {
+            WriteOptions::new().default_dtype().writer(Cursor::new(&mut buf))
+                .extra_header_key("units", "'meters'")
+                .extra_header_key("axes", "['x', 'y']")
+                .begin_1d()?
+        };
+        writer.extend(vec![1_i32, 2, 3])?;
+        writer.finish()?;
+
+        let npy = NpyFile::new(&buf[..])?;
+        assert_eq!(npy.shape(), &[3]);
+        assert_eq!(npy.extra_keys(), &[
+            ("units".to_string(), "'meters'".to_string()),
+            ("axes".to_string(), "['x', 'y']".to_string()),
+        ]);
+
+        let try_key = |key: &str, value: &str| {
+            WriteOptions::<i32>::new().default_dtype().shape(&[0]).writer(vec![])
+                .extra_header_key(key, value)
+                .begin_nd()
+                .map(|_| ())
+        };
+        assert!(try_key("shape", "(3,)").is_err());
+        assert!(try_key("foo", "(3,").is_err());
+        assert!(try_key("foo", "(3,)").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn preserve_header() -> io::Result<()> {
+        // unusual formatting that we would never produce ourselves
+        let mut original = header_with_text("{'shape':(2,),'fortran_order':False,'descr':'<i2','extra':1}   \n");
+        original.extend([1, 0, 2, 0]);
+
+        let npy = NpyFile::new(&original[..])?;
+        let rewrite = |shape: &[u64], data: &[i16]| -> io::Result<Vec<u8>> {
+            let mut buf = vec![];
+            let mut writer = {
+                WriteOptions::new().dtype(npy.dtype()).shape(shape)
+                    .preserve_header(npy.header())
+                    .writer(&mut buf)
+                    .begin_nd()?
+            };
+            writer.extend(data.iter().copied())?;
+            writer.finish()?;
+            Ok(buf)
+        };
+        assert_eq!(rewrite(&[2], &[1, 2])?, original);
+
+        // if the header has to change, a fresh one is written, but extra keys are kept
+        let changed = rewrite(&[3], &[1, 2, 3])?;
+        assert_ne!(changed[..original.len() - 4], original[..original.len() - 4]);
+        let npy = NpyFile::new(&changed[..])?;
+        assert_eq!(npy.shape(), &[3]);
+        assert_eq!(npy.extra_keys(), &[("extra".to_string(), "1".to_string())]);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_dtype_or_shape() {
+        let begin = |dtype: DType, shape: &[u64]| {
+            let err = WriteOptions::<i32>::new().dtype(dtype).shape(shape).writer(vec![]).begin_nd().err().unwrap();
+            matches!(Error::from(err), Error::InvalidInput(_))
+        };
+        let int = DType::parse("'<i4'").unwrap();
+        assert!(begin(DType::Array(3, Box::new(int.clone())), &[2]));
+        assert!(begin(int, &[u64::MAX, 2]));
+    }
 }
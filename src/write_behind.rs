@@ -0,0 +1,284 @@
+//! Writing to a stream on a background thread.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::thread::{self, JoinHandle};
+
+const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// A writer that passes its output to a background thread, which writes it to the underlying writer.
+///
+/// This is the counterpart of [`ReadAhead`][`crate::ReadAhead`].  While the caller serializes one chunk,
+/// the thread writes the previous chunks, so that the time spent serializing overlaps with the time spent in
+/// the underlying writer.  This pays off when the underlying writer does expensive work of its own, such as
+/// a [`CompressedWriter`][`crate::CompressedWriter`]: the total time becomes about the larger of the two
+/// instead of their sum.
+///
+/// The `depth` is the number of chunks that may wait to be written.  When the queue is full, writes block
+/// until the thread catches up.  Memory use is at most about `(depth + 2) * chunk_size`.
+///
+/// ```
+/// # fn main() -> std::io::Result<()> {
+/// use npyz::WriterBuilder;
+///
+/// let mut out = npyz::WriteBehind::spawn(vec![], 2);
+/// let mut writer = npyz::WriteOptions::new().default_dtype().shape(&[3]).writer(&mut out).begin_nd()?;
+/// writer.extend([1_i32, 2, 3])?;
+/// writer.finish()?;
+/// let bytes = out.finish()?;
+///
+/// assert_eq!(npyz::NpyFile::new(&bytes[..])?.into_vec::<i32>()?, vec![1, 2, 3]);
+/// # Ok(()) }
+/// ```
+///
+/// An error from the underlying writer is returned by a later call to `write`, [`Write::flush`] or
+/// [`Self::finish`]; after that, every write fails.  [`Write::flush`] waits until the thread has written
+/// and flushed everything.  Dropping a `WriteBehind` waits for the thread to write the remaining data,
+/// ignoring any errors.
+pub struct WriteBehind<W> {
+    state: State<W>,
+    recycled: Receiver<Vec<u8>>,
+    current: Vec<u8>,
+    chunk_size: usize,
+}
+
+enum State<W> {
+    Running {
+        messages: SyncSender<Message>,
+        worker: JoinHandle<(W, io::Result<()>)>,
+    },
+    // `writer` is `None` once it has been given back
+    Stopped { writer: Option<W>, failed: bool },
+}
+
+enum Message {
+    Data(Vec<u8>),
+    Flush(Sender<()>),
+}
+
+impl<W: Write + Send + 'static> WriteBehind<W> {
+    /// Start writing in chunks of 1 MiB, with up to `depth` chunks waiting.
+    pub fn spawn(writer: W, depth: usize) -> Self {
+        Self::spawn_with_chunk_size(writer, depth, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Start writing in chunks of the given size in bytes, with up to `depth` chunks waiting.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn spawn_with_chunk_size(writer: W, depth: usize, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be nonzero");
+        let (messages, received) = mpsc::sync_channel(depth);
+        let (recycle, recycled) = mpsc::channel();
+        let worker = thread::Builder::new()
+            .name("npyz-write-behind".to_string())
+            .spawn(move || write_chunks(writer, received, recycle))
+            .expect("failed to spawn write-behind thread");
+        WriteBehind { state: State::Running { messages, worker }, recycled, current: vec![], chunk_size }
+    }
+}
+
+// Runs on the background thread until the WriteBehind is finished or an error occurs.
+fn write_chunks<W: Write>(mut writer: W, messages: Receiver<Message>, recycle: Sender<Vec<u8>>) -> (W, io::Result<()>) {
+    for message in messages {
+        let result = match message {
+            Message::Data(mut chunk) => {
+                let result = writer.write_all(&chunk);
+                chunk.clear();
+                let _ = recycle.send(chunk);
+                result
+            },
+            Message::Flush(done) => writer.flush().map(|()| {
+                let _ = done.send(());
+            }),
+        };
+        if result.is_err() {
+            return (writer, result);
+        }
+    }
+    let result = writer.flush();
+    (writer, result)
+}
+
+impl<W> WriteBehind<W> {
+    /// Write any remaining data, wait for the thread to finish, and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let (writer, result) = self.finish_();
+        result.map(|()| writer.expect("finished only once"))
+    }
+
+    // Returns the writer (unless it was already taken) and the first error, if any.
+    pub(crate) fn finish_(&mut self) -> (Option<W>, io::Result<()>) {
+        let result = self.send_current().and_then(|()| self.stop());
+        match &mut self.state {
+            State::Stopped { writer, failed } => {
+                let result = match failed {
+                    true => result.and(Err(stopped())),
+                    false => result,
+                };
+                (writer.take(), result)
+            },
+            State::Running { .. } => unreachable!("the thread was stopped"),
+        }
+    }
+
+    // Wait for the thread to write everything that was sent, returning its error, if any.
+    fn stop(&mut self) -> io::Result<()> {
+        let stopped = State::Stopped { writer: None, failed: false };
+        match std::mem::replace(&mut self.state, stopped) {
+            // dropping the sender ends the thread's loop
+            State::Running { messages, worker } => {
+                drop(messages);
+                let (writer, result) = worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                self.state = State::Stopped { writer: Some(writer), failed: result.is_err() };
+                result
+            },
+            state => {
+                self.state = state;
+                Ok(())
+            },
+        }
+    }
+
+    fn send_current(&mut self) -> io::Result<()> {
+        if self.current.is_empty() {
+            return Ok(());
+        }
+        let next = self.recycled.try_recv().unwrap_or_else(|_| Vec::with_capacity(self.chunk_size));
+        let chunk = std::mem::replace(&mut self.current, next);
+        self.send(Message::Data(chunk))
+    }
+
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        match &self.state {
+            State::Running { messages, .. } => match messages.send(message) {
+                Ok(()) => Ok(()),
+                // the thread stopped because of an error, which is returned by joining it
+                Err(_) => self.stop().and(Err(stopped())),
+            },
+            State::Stopped { .. } => Err(stopped()),
+        }
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the background writer stopped after an earlier error")
+}
+
+impl<W> Write for WriteBehind<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let State::Stopped { .. } = self.state {
+            return Err(stopped());
+        }
+        let len = buf.len().min(self.chunk_size - self.current.len());
+        self.current.extend_from_slice(&buf[..len]);
+        if self.current.len() == self.chunk_size {
+            self.send_current()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_current()?;
+        let (done, flushed) = mpsc::channel();
+        self.send(Message::Flush(done))?;
+        match flushed.recv() {
+            Ok(()) => Ok(()),
+            // the flush failed, and the thread stopped
+            Err(_) => self.stop(),
+        }
+    }
+}
+
+impl<W> Drop for WriteBehind<W> {
+    fn drop(&mut self) {
+        let _ = self.finish_(); // Ignore the errors
+    }
+}
+
+impl<W> fmt::Debug for WriteBehind<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WriteBehind")
+            .field("buffered", &self.current.len())
+            .field("chunk_size", &self.chunk_size)
+            .field("stopped", &matches!(self.state, State::Stopped { .. }))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_source() -> io::Result<()> {
+        let bytes = (0..100_000).map(|x| (x * 7 % 251) as u8).collect::<Vec<_>>();
+        for (depth, chunk_size) in [(0, 1), (1, 7), (4, 4096), (2, 100_000), (2, 1 << 20)] {
+            let mut writer = WriteBehind::spawn_with_chunk_size(vec![], depth, chunk_size);
+            for piece in bytes.chunks(1000) {
+                writer.write_all(piece)?;
+            }
+            writer.flush()?;
+            writer.write_all(b"end")?;
+            let out = writer.finish()?;
+            assert_eq!(out[..bytes.len()], bytes[..], "depth {} chunk size {}", depth, chunk_size);
+            assert_eq!(&out[bytes.len()..], b"end");
+        }
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct Failing(usize);
+    impl Write for Failing {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self.0 {
+                0 => Err(io::Error::other("disk on fire")),
+                _ => {
+                    let len = buf.len().min(self.0);
+                    self.0 -= len;
+                    Ok(len)
+                },
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn error() {
+        let mut writer = WriteBehind::spawn_with_chunk_size(Failing(10), 1, 4);
+        writer.write_all(&[0; 8]).unwrap();
+        let err = writer.flush().and_then(|()| writer.write_all(&[0; 100])).and_then(|()| writer.flush()).unwrap_err();
+        assert_eq!(err.to_string(), "disk on fire");
+        assert_eq!(writer.write(&[0]).unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+
+        let mut writer = WriteBehind::spawn_with_chunk_size(Failing(10), 1, 4);
+        writer.write_all(&[0; 11]).unwrap();
+        assert_eq!(writer.finish().unwrap_err().to_string(), "disk on fire");
+    }
+
+    #[test]
+    fn drop_writes_remaining_data() {
+        let (sender, receiver) = mpsc::channel();
+        struct Sink(Sender<Vec<u8>>);
+        impl Write for Sink {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.send(buf.to_vec()).unwrap();
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut writer = WriteBehind::spawn_with_chunk_size(Sink(sender), 2, 16);
+        writer.write_all(&[1; 40]).unwrap();
+        drop(writer);
+        assert_eq!(receiver.iter().flatten().count(), 40);
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn npyz(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_npyz")).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn inspect_npy() {
+    let out = stdout(&npyz(&["inspect", "test-data/c-order.npy"]));
+    assert_eq!(out, "\
+shape:    (2, 3, 4)
+dtype:    dtype('int64')
+order:    C
+version:  1.0
+elements: 24
+");
+}
+
+#[test]
+fn inspect_npz() {
+    let out = stdout(&npyz(&["inspect", "test-data/sparse/csr.npz"]));
+    let lines = out.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("NAME"));
+    assert!(lines.iter().any(|line| line.starts_with("indptr") && line.contains("(4,)")));
+    assert_eq!(lines.len(), 6);
+}
+
+#[test]
+fn cat_formats() {
+    let cat = |args: &[&str]| stdout(&npyz(&[&["cat", "test-data/structured.npy"], args].concat()));
+    assert_eq!(cat(&[]), "(1, 2.5, 4)\n(2, 3.1, 5)\n");
+    assert_eq!(cat(&["--format", "csv"]), "a,b,c\n1,2.5,4\n2,3.1,5\n");
+    assert_eq!(cat(&["--format=json", "--head", "1"]), "{\"a\": 1, \"b\": 2.5, \"c\": 4}\n");
+}
+
+#[test]
+fn cat_fortran_order() {
+    let c_order = stdout(&npyz(&["cat", "test-data/c-order.npy", "--format", "csv"]));
+    let f_order = stdout(&npyz(&["cat", "test-data/f-order.npy", "--format", "csv"]));
+    assert_eq!(c_order, f_order);
+    assert_eq!(c_order.lines().count(), 2);
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("npyz-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn diff() {
+    let dir = temp_dir("diff");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    npyz::to_file_1d(path("a.npy"), vec![1.0, 2.0, 3.0]).unwrap();
+    npyz::to_file_1d(path("b.npy"), vec![1.0, 2.0, 3.001]).unwrap();
+
+    let out = stdout(&npyz(&["diff", "test-data/c-order.npy", "test-data/f-order.npy"]));
+    assert_eq!(out, "arrays are equal (24 elements compared)\n");
+    stdout(&npyz(&["diff", &path("a.npy"), &path("b.npy"), "--rtol", "1e-3"]));
+
+    let output = npyz(&["diff", &path("a.npy"), &path("b.npy"), "--rtol", "1e-6"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("1 of 3 elements differ\nfirst mismatch at [2]"));
+
+    let output = npyz(&["diff", &path("a.npy"), &path("b.npy"), "--exact", "-q"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+
+    assert_eq!(npyz(&["diff", &path("a.npy"), "missing.npy"]).status.code(), Some(2));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn npz_subcommands() {
+    let dir = temp_dir("npz");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+    let ls = stdout(&npyz(&["npz", "ls", "test-data/compressed.npz"]));
+    assert!(ls.lines().any(|line| line.starts_with("ints ")));
+
+    stdout(&npyz(&["npz", "extract", "test-data/compressed.npz", "ints", "-o", &path("ints.npy")]));
+    let expected = npyz::npz::NpzArchive::open("test-data/compressed.npz").unwrap()
+        .by_name("ints").unwrap().unwrap().into_vec::<i64>().unwrap();
+    let extracted = npyz::NpyFile::new(std::fs::File::open(path("ints.npy")).unwrap()).unwrap().into_vec::<i64>().unwrap();
+    assert_eq!(extracted, expected);
+
+    stdout(&npyz(&["npz", "add", &path("new.npz"), "a", &path("ints.npy")]));
+    stdout(&npyz(&["npz", "add", &path("new.npz"), "b", &path("ints.npy"), "--compress"]));
+    assert_eq!(npyz(&["npz", "add", &path("new.npz"), "b", &path("ints.npy")]).status.code(), Some(2));
+    assert_eq!(npyz(&["npz", "add", &path("new.npz"), "c", "Cargo.toml"]).status.code(), Some(2));
+
+    let mut archive = npyz::npz::NpzArchive::open(path("new.npz")).unwrap();
+    let mut names = archive.array_names().map(String::from).collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["a", "b"]);
+    assert_eq!(archive.by_name("b").unwrap().unwrap().into_vec::<i64>().unwrap(), expected);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sparse_info() {
+    let out = stdout(&npyz(&["sparse", "info", "test-data/sparse/bsr.npz"]));
+    assert_eq!(out, "\
+format:   bsr
+shape:    (3, 6)
+stored:   10
+density:  0.555556
+data:     dtype('int64')
+indices:  dtype('int32')
+blocks:   5 of size (1, 2)
+");
+    assert_eq!(npyz(&["sparse", "info", "test-data/compressed.npz"]).status.code(), Some(2));
+}
+
+#[test]
+fn sparse_convert() {
+    let dir = temp_dir("sparse");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    let read = |path: &str| npyz::sparse::Sparse::<i64>::from_npz(&mut npyz::npz::NpzArchive::open(path).unwrap()).unwrap();
+
+    for input in ["coo", "csr", "csc", "dia"] {
+        stdout(&npyz(&["sparse", "convert", "--to", "csr", &format!("test-data/sparse/{}.npz", input), &path("out.npz")]));
+        assert_eq!(read(&path("out.npz")), read("test-data/sparse/csr.npz"), "from {}", input);
+    }
+    stdout(&npyz(&["sparse", "convert", "--to=csc", "test-data/sparse/csr.npz", &path("out.npz")]));
+    assert_eq!(read(&path("out.npz")), read("test-data/sparse/csc.npz"));
+    stdout(&npyz(&["sparse", "convert", "--to=coo", "test-data/sparse/csr.npz", &path("out.npz")]));
+    assert_eq!(read(&path("out.npz")), read("test-data/sparse/coo.npz"));
+
+    // explicit zeros in the blocks are kept
+    stdout(&npyz(&["sparse", "convert", "--to", "coo", "test-data/sparse/bsr.npz", &path("out.npz")]));
+    match read(&path("out.npz")) {
+        npyz::sparse::Sparse::Coo(coo) => assert_eq!(coo.data, vec![1, 0, 4, 0, 0, 2, 6, 0, 7, 0]),
+        other => panic!("{:?}", other),
+    }
+
+    assert_eq!(npyz(&["sparse", "convert", "--to", "dia", "test-data/sparse/csr.npz", &path("out.npz")]).status.code(), Some(2));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn usage_errors() {
+    let output = npyz(&["inspect"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Usage: npyz inspect"));
+
+    let output = npyz(&["frobnicate"]);
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -1,5 +1,8 @@
 // This test is responsible for making sure that the derive macros access
 
+// (the types only need to exist, and the field names are arbitrary)
+#![allow(dead_code, clippy::disallowed_names)]
+
 #[no_implicit_prelude]
 mod not_root {
     #[derive(::npyz_derive::Serialize, ::npyz_derive::Deserialize, ::npyz_derive::AutoSerialize)]
@@ -10,6 +13,10 @@ mod not_root {
 
     #[derive(::npyz_derive::Serialize, ::npyz_derive::Deserialize, ::npyz_derive::AutoSerialize)]
     struct LocalType;
+
+    fn dtype() -> ::npyz::DType {
+        ::npyz_derive::dtype!({ a: "<i4", b: ("|u1", [2]), c: { d: ">m8[ns]" } })
+    }
 }
 
 fn main() {}
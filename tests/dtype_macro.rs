@@ -0,0 +1,29 @@
+use npyz::{dtype, DType};
+
+#[test]
+fn plain() {
+    assert_eq!(dtype!("<i8"), DType::parse("'<i8'").unwrap());
+    assert_eq!(dtype!("|S3"), DType::parse("'|S3'").unwrap());
+    assert_eq!(dtype!(">M8[us]"), DType::parse("'>M8[us]'").unwrap());
+    assert_eq!(dtype!("=f8"), DType::parse("'=f8'").unwrap());
+}
+
+#[test]
+fn record() {
+    let dtype = dtype!({
+        id: "<i8",
+        pos: ("<f4", [3]),
+        "funny name": { a: "|u1", b: ("<U2", [2, 1]) },
+        r#type: ("|b1", [0]),
+    });
+    let expected = DType::parse(
+        "[('id', '<i8'), ('pos', '<f4', (3,)), ('funny name', [('a', '|u1'), ('b', '<U2', (2, 1))]), ('type', '|b1', (0,))]"
+    ).unwrap();
+    assert_eq!(dtype, expected);
+}
+
+#[test]
+fn nested_record_array() {
+    let dtype = dtype!({ parent: ({ child: "<i4" }, [2]) });
+    assert_eq!(dtype, DType::parse("[('parent', [('child', '<i4')], (2,))]").unwrap());
+}
@@ -1,6 +1,6 @@
 use std::io;
 use npyz::WriterBuilder;
-use npyz::npz::{NpzArchive, NpzWriter};
+use npyz::npz::{NpzArchive, NpzStreamReader, NpzWriter, SalvagedNpz};
 
 #[test]
 fn read_uncompressed() {
@@ -10,6 +10,11 @@ fn read_uncompressed() {
 fn read_compressed() {
     test_basic_read(NpzArchive::open("test-data/compressed.npz").unwrap())
 }
+#[test]
+fn read_with_buffer_size() {
+    let options = npyz::ReadOptions::new().buffer_size(16);
+    test_basic_read(NpzArchive::open_with_options("test-data/compressed.npz", &options).unwrap())
+}
 
 // Python code to create NPZs:
 //   import numpy as np
@@ -31,6 +36,34 @@ fn test_basic_read(mut npz: NpzArchive<impl io::Read + io::Seek>) {
     assert!(matches!(npz.by_name("non-existent"), Ok(None)));
 }
 
+#[test]
+fn error_context_names_member() {
+    let mut npz = NpzArchive::open("test-data/uncompressed.npz").unwrap();
+    let floats = npz.by_name("floats").unwrap().unwrap();
+    let err = npyz::Error::from(floats.into_vec::<String>().unwrap_err());
+    assert!(matches!(err.root(), npyz::Error::DTypeMismatch { .. }));
+    assert_eq!(err.context().unwrap().member(), Some("floats"));
+}
+
+#[test]
+fn cached_headers() {
+    let mut npz = NpzArchive::open("test-data/compressed.npz").unwrap();
+    assert_eq!(npz.header("floats").unwrap().unwrap().shape(), &[2, 1]);
+    assert!(npz.header("non-existent").unwrap().is_none());
+    for _ in 0..2 {
+        let floats = npz.by_name("floats").unwrap().unwrap();
+        assert_eq!(floats.shape(), &[2, 1]);
+        assert_eq!(floats.into_vec::<f64>().unwrap(), vec![1.0, 2.0]);
+        let ints = npz.by_name("ints").unwrap().unwrap();
+        assert_eq!(ints.into_vec::<i64>().unwrap(), vec![1, 2, 3, 4]);
+    }
+
+    // a cached header still names the member in errors
+    let floats = npz.by_name("floats").unwrap().unwrap();
+    let err = npyz::Error::from(floats.into_vec::<String>().unwrap_err());
+    assert_eq!(err.context().unwrap().member(), Some("floats"));
+}
+
 #[test]
 fn basic_write() {
     let mut buf = io::Cursor::new(vec![]);
@@ -54,3 +87,510 @@ fn basic_write() {
     let bytes = buf.into_inner();
     test_basic_read(NpzArchive::new(io::Cursor::new(&bytes[..])).unwrap());
 }
+
+#[test]
+fn empty_arrays() {
+    let shapes: [&[u64]; 4] = [&[0], &[0, 128], &[128, 0], &[3, 0, 2]];
+    let mut buf = io::Cursor::new(vec![]);
+    let mut npz = NpzWriter::new(&mut buf);
+    for (i, shape) in shapes.iter().enumerate() {
+        let method = match i % 2 {
+            0 => zip::CompressionMethod::Stored,
+            _ => zip::CompressionMethod::Deflated,
+        };
+        npz.array::<f32>(&format!("a{}", i), zip::write::FileOptions::default().compression_method(method)).unwrap()
+            .default_dtype()
+            .shape(shape)
+            .begin_nd().unwrap()
+            .finish().unwrap();
+    }
+    drop(npz);
+
+    let bytes = buf.into_inner();
+    let mut npz = NpzArchive::new(io::Cursor::new(&bytes[..])).unwrap();
+    for (i, shape) in shapes.iter().enumerate() {
+        let npy = npz.by_name(&format!("a{}", i)).unwrap().unwrap();
+        assert_eq!(npy.shape(), *shape);
+        assert!(npy.is_empty());
+        let reader = npy.data::<f32>().unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(reader.collect::<io::Result<Vec<_>>>().unwrap(), Vec::<f32>::new());
+    }
+}
+
+fn write_ints(npz: &mut NpzWriter<impl io::Write + io::Seek>, name: &str, data: &[i64]) {
+    npz.array(name, Default::default()).unwrap()
+        .default_dtype()
+        .shape(&[data.len() as u64])
+        .begin_nd().unwrap()
+        .extend(data.iter().copied()).unwrap();
+}
+
+#[test]
+fn manifest_roundtrip() {
+    let mut npz = NpzWriter::new(io::Cursor::new(vec![])).with_manifest();
+    write_ints(&mut npz, "a", &[1, 2, 3]);
+    write_ints(&mut npz, "b", &[]);
+    let bytes = npz.finish().unwrap().into_inner();
+
+    let mut npz = NpzArchive::new(io::Cursor::new(bytes)).unwrap();
+    let mut names = npz.array_names().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["a", "b"]);
+
+    let manifest = npz.verify_manifest().unwrap();
+    assert_eq!(manifest.entries.iter().map(|entry| &entry.name[..]).collect::<Vec<_>>(), vec!["a", "b"]);
+    assert_eq!(manifest.get("a").unwrap().shape, vec![3]);
+    assert_eq!(manifest.get("b").unwrap().dtype, npyz::DType::parse("'<i8'").unwrap());
+
+    // written on drop, too
+    let mut buf = io::Cursor::new(vec![]);
+    let mut npz = NpzWriter::new(&mut buf).with_manifest();
+    write_ints(&mut npz, "a", &[1, 2, 3]);
+    drop(npz);
+    NpzArchive::new(buf).unwrap().verify_manifest().unwrap();
+}
+
+#[test]
+fn manifest_detects_changes() {
+    let mut npz = NpzWriter::new(io::Cursor::new(vec![])).with_manifest();
+    write_ints(&mut npz, "a", &[1, 2, 3]);
+    let bytes = npz.finish().unwrap().into_inner();
+    let manifest = NpzArchive::new(io::Cursor::new(bytes)).unwrap().manifest().unwrap().unwrap();
+
+    // an archive with the same manifest but different contents
+    type MemoryNpzWriter = NpzWriter<io::Cursor<Vec<u8>>>;
+    let tampered = |write: &dyn Fn(&mut MemoryNpzWriter)| {
+        let mut npz = NpzWriter::new(io::Cursor::new(vec![]));
+        write(&mut npz);
+        npz.zip_writer().start_file(npyz::npz::MANIFEST_FILE_NAME, Default::default()).unwrap();
+        io::Write::write_all(npz.zip_writer(), manifest.to_json().as_bytes()).unwrap();
+        let bytes = npz.finish().unwrap().into_inner();
+        let err = NpzArchive::new(io::Cursor::new(bytes)).unwrap().verify_manifest().unwrap_err();
+        npyz::Error::from(err)
+    };
+
+    let err = tampered(&|npz| write_ints(npz, "a", &[1, 2, 4]));
+    assert!(err.to_string().contains("SHA-256"), "{}", err);
+    assert_eq!(err.context().unwrap().member(), Some("a"));
+
+    let err = tampered(&|npz| write_ints(npz, "a", &[1, 2]));
+    assert!(err.to_string().contains("shape"), "{}", err);
+
+    let err = tampered(&|npz| {
+        write_ints(npz, "a", &[1, 2, 3]);
+        write_ints(npz, "extra", &[1]);
+    });
+    assert_eq!(err.context().unwrap().member(), Some("extra"));
+
+    let err = tampered(&|_| {});
+    assert_eq!(err.context().unwrap().member(), Some("a"));
+
+    let err = NpzArchive::open("test-data/compressed.npz").unwrap().verify_manifest().unwrap_err();
+    assert!(err.to_string().contains("no manifest"));
+}
+
+#[test]
+fn salvage_intact() {
+    let mut npz = SalvagedNpz::open("test-data/compressed.npz").unwrap();
+    assert_eq!(npz.array_names().collect::<Vec<_>>(), vec!["ints", "floats"]);
+    assert_eq!(npz.skipped(), &[]);
+    assert_eq!(npz.by_name("floats").unwrap().unwrap().into_vec::<f64>().unwrap(), vec![1.0, 2.0]);
+    assert_eq!(npz.by_name("ints").unwrap().unwrap().into_vec::<i64>().unwrap(), vec![1, 2, 3, 4]);
+    assert!(npz.by_name("non-existent").unwrap().is_none());
+}
+
+#[test]
+fn salvage_truncated() {
+    let mut npz = NpzWriter::new(io::Cursor::new(vec![]));
+    write_ints(&mut npz, "a", &[1, 2, 3]);
+    write_ints(&mut npz, "b", &[4, 5]);
+    // pseudorandom, so that it stays large after compression
+    write_ints(&mut npz, "c", &(0..1000_i64).map(|x| x.wrapping_mul(0x1E37_79B9_7F4A_7C15)).collect::<Vec<_>>());
+    let bytes = npz.finish().unwrap().into_inner();
+    // cut off in the middle of "c"
+    let truncated = bytes[..bytes.len() - 5000].to_vec();
+    assert!(NpzArchive::new(io::Cursor::new(&truncated)).is_err());
+
+    let mut npz = SalvagedNpz::scan(io::Cursor::new(truncated)).unwrap();
+    assert_eq!(npz.array_names().collect::<Vec<_>>(), vec!["a", "b"]);
+    assert_eq!(npz.skipped().len(), 1);
+    assert_eq!(npz.by_name("b").unwrap().unwrap().into_vec::<i64>().unwrap(), vec![4, 5]);
+}
+
+#[test]
+fn salvage_corrupted_member() {
+    // flip a byte in the data of "ints" (which starts at offset 0 and is stored uncompressed)
+    let mut bytes = std::fs::read("test-data/uncompressed.npz").unwrap();
+    bytes[150] ^= 1;
+
+    let mut npz = SalvagedNpz::scan(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(npz.array_names().collect::<Vec<_>>(), vec!["floats"]);
+    assert_eq!(npz.skipped(), &[std::ops::Range { start: 0, end: 218 }]);
+    assert_eq!(npz.by_name("floats").unwrap().unwrap().into_vec::<f64>().unwrap(), vec![1.0, 2.0]);
+}
+
+#[test]
+fn copy_array_raw() {
+    let mut source = NpzArchive::open("test-data/compressed.npz").unwrap();
+    let mut npz = NpzWriter::new(io::Cursor::new(vec![])).with_manifest();
+    for name in ["ints", "floats"] {
+        npz.copy_array(name, Default::default(), source.by_name(name).unwrap().unwrap()).unwrap();
+    }
+    let bytes = npz.finish().unwrap().into_inner();
+
+    let mut copy = NpzArchive::new(io::Cursor::new(bytes)).unwrap();
+    copy.verify_manifest().unwrap();
+    for name in ["ints", "floats"] {
+        assert_eq!(read_member(&mut copy, name), read_member(&mut source, name));
+    }
+}
+
+#[test]
+fn copy_entry_without_recompressing() {
+    let mut source = NpzArchive::open("test-data/compressed.npz").unwrap();
+    let mut npz = NpzWriter::new(io::Cursor::new(vec![])).with_pipeline(2);
+    write_ints(&mut npz, "new", &[1, 2, 3]);
+    for name in ["ints", "floats"] {
+        npz.copy_entry_from(&mut source, name).unwrap();
+    }
+    let err = npz.copy_entry_from(&mut source, "missing").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let bytes = npz.finish().unwrap().into_inner();
+
+    let mut copy = NpzArchive::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(copy.by_name("new").unwrap().unwrap().into_vec::<i64>().unwrap(), vec![1, 2, 3]);
+    for name in ["ints", "floats"] {
+        let file_name = format!("{}.npy", name);
+        let original = source.zip_archive().by_name(&file_name).unwrap();
+        let (method, compressed_size, crc) = (original.compression(), original.compressed_size(), original.crc32());
+        drop(original);
+        let copied = copy.zip_archive().by_name(&file_name).unwrap();
+        assert_eq!((copied.compression(), copied.compressed_size(), copied.crc32()), (method, compressed_size, crc));
+        drop(copied);
+        assert_eq!(read_member(&mut copy, name), read_member(&mut source, name));
+    }
+}
+
+#[test]
+fn append_to_archive() {
+    let mut bytes = vec![];
+    let mut npz = NpzWriter::new(io::Cursor::new(&mut bytes));
+    write_ints(&mut npz, "a", &[1, 2]);
+    npz.finish().unwrap();
+    let original_len = bytes.len();
+
+    let mut npz = NpzWriter::new_append(io::Cursor::new(&mut bytes)).unwrap();
+    write_ints(&mut npz, "b", &[3]);
+    write_ints(&mut npz, "c", &[4, 5, 6]);
+    npz.finish().unwrap();
+    assert!(bytes.len() > original_len);
+
+    let mut npz = NpzArchive::new(io::Cursor::new(&bytes)).unwrap();
+    let mut names = npz.array_names().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["a", "b", "c"]);
+    for (name, expected) in [("a", vec![1, 2]), ("b", vec![3]), ("c", vec![4, 5, 6])] {
+        assert_eq!(npz.by_name(name).unwrap().unwrap().into_vec::<i64>().unwrap(), expected);
+    }
+
+    // not a zip file
+    assert!(NpzWriter::new_append(io::Cursor::new(vec![0u8; 100])).is_err());
+}
+
+#[test]
+fn append_to_archive_with_manifest() {
+    let mut bytes = vec![];
+    let mut npz = NpzWriter::new(io::Cursor::new(&mut bytes)).with_manifest();
+    write_ints(&mut npz, "a", &[1, 2]);
+    npz.finish().unwrap();
+    let original = bytes.clone();
+
+    let err = NpzWriter::new_append(io::Cursor::new(&mut bytes)).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("manifest"), "{}", err);
+    assert_eq!(bytes, original);
+    NpzArchive::new(io::Cursor::new(&bytes)).unwrap().verify_manifest().unwrap();
+
+    // without the manifest, arrays can be appended
+    let mut copy = NpzArchive::new(io::Cursor::new(&bytes)).unwrap().remove("a", io::Cursor::new(vec![])).unwrap().into_inner();
+    let mut npz = NpzWriter::new_append(io::Cursor::new(&mut copy)).unwrap();
+    write_ints(&mut npz, "b", &[3]);
+    npz.finish().unwrap();
+    let mut npz = NpzArchive::new(io::Cursor::new(&copy)).unwrap();
+    assert!(npz.manifest().unwrap().is_none());
+    assert_eq!(npz.array_names().collect::<Vec<_>>(), vec!["b"]);
+}
+
+#[test]
+#[should_panic(expected = "a manifest cannot be added")]
+fn append_with_new_manifest() {
+    let mut bytes = vec![];
+    let mut npz = NpzWriter::new(io::Cursor::new(&mut bytes));
+    write_ints(&mut npz, "a", &[1, 2]);
+    npz.finish().unwrap();
+
+    let _ = NpzWriter::new_append(io::Cursor::new(&mut bytes)).unwrap().with_manifest();
+}
+
+#[test]
+fn pipelined_write() {
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let big = (0..300_000).map(|x| x * x % 1009).collect::<Vec<i64>>();
+    let mut source = NpzArchive::open("test-data/compressed.npz").unwrap();
+
+    let mut npz = NpzWriter::new(io::Cursor::new(vec![])).with_manifest().with_pipeline(2);
+    let mut writer = npz.array("big", options).unwrap().default_dtype().shape(&[300_000]).begin_nd().unwrap();
+    writer.extend_from_slice(&big).unwrap();
+    writer.finish().unwrap();
+    write_ints(&mut npz, "small", &[1, 2, 3]);
+    npz.copy_array("ints", options, source.by_name("ints").unwrap().unwrap()).unwrap();
+    write_ints(&mut npz, "empty", &[]);
+    io::Write::write_all(npz.zip_writer(), b"").unwrap();
+    let bytes = npz.finish().unwrap().into_inner();
+
+    let mut npz = NpzArchive::new(io::Cursor::new(bytes)).unwrap();
+    let manifest = npz.verify_manifest().unwrap();
+    assert_eq!(manifest.entries.iter().map(|entry| &entry.name[..]).collect::<Vec<_>>(), vec!["big", "small", "ints", "empty"]);
+    assert_eq!(npz.by_name("big").unwrap().unwrap().into_vec::<i64>().unwrap(), big);
+    assert_eq!(npz.by_name("small").unwrap().unwrap().into_vec::<i64>().unwrap(), vec![1, 2, 3]);
+    assert_eq!(read_member(&mut npz, "ints"), read_member(&mut source, "ints"));
+
+    // finished on drop, too
+    let path = std::env::temp_dir().join(format!("npyz-pipelined-{}.npz", std::process::id()));
+    let mut npz = NpzWriter::create(&path).unwrap().with_pipeline(0);
+    write_ints(&mut npz, "a", &[4, 5]);
+    drop(npz);
+    assert_eq!(NpzArchive::open(&path).unwrap().by_name("a").unwrap().unwrap().into_vec::<i64>().unwrap(), vec![4, 5]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+fn read_member(npz: &mut NpzArchive<impl io::Read + io::Seek>, name: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    io::Read::read_to_end(&mut npz.zip_archive().by_name(&format!("{}.npy", name)).unwrap(), &mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn load_all_parallel() {
+    let arrays = NpzArchive::open("test-data/uncompressed.npz").unwrap().load_all_parallel::<i64>();
+    // `floats` cannot be read as i64
+    let err = npyz::Error::from(arrays.unwrap_err());
+    assert_eq!(err.context().unwrap().member(), Some("floats"));
+
+    let mut npz = NpzWriter::new(io::Cursor::new(vec![]));
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for i in 0..50 {
+        let data = (0..1000).map(|x| x * i).collect::<Vec<i64>>();
+        let mut writer = npz.array::<i64>(&format!("a{}", i), options).unwrap().default_dtype().shape(&[10, 100]).begin_nd().unwrap();
+        writer.extend(data).unwrap();
+        writer.finish().unwrap();
+    }
+    let bytes = npz.finish().unwrap().into_inner();
+
+    let arrays = NpzArchive::new(io::Cursor::new(&bytes)).unwrap().load_all_parallel::<i64>().unwrap();
+    assert_eq!(arrays.len(), 50);
+    for i in 0..50 {
+        let array = &arrays[&format!("a{}", i)];
+        assert_eq!(array.header.shape(), &[10, 100]);
+        assert_eq!(array.data, (0..1000).map(|x| x * i).collect::<Vec<i64>>());
+    }
+
+    // a budget smaller than one array loads them one at a time
+    for budget in [0, 1000, 20_000] {
+        let options = npyz::ReadOptions::new().memory_budget(budget);
+        let arrays = NpzArchive::new(io::Cursor::new(&bytes)).unwrap().with_read_options(options).load_all_parallel::<i64>().unwrap();
+        assert_eq!(arrays.len(), 50);
+        assert_eq!(arrays["a7"].data, (0..1000).map(|x| x * 7).collect::<Vec<i64>>());
+    }
+}
+
+#[test]
+fn random_fixture() {
+    let dtype = npyz::DType::parse("[('a', '<i4'), ('b', '>f8')]").unwrap();
+    let mut buf = io::Cursor::new(vec![]);
+    let mut npz = NpzWriter::new(&mut buf);
+    npyz::generate::write_random_to_npz(&mut npz, "x", Default::default(), &dtype, &[3, 50], 9).unwrap();
+    drop(npz);
+
+    let bytes = buf.into_inner();
+    let mut npz = NpzArchive::new(io::Cursor::new(&bytes[..])).unwrap();
+    let mut copy = vec![];
+    npz.by_name("x").unwrap().unwrap().copy_raw_to(&mut copy).unwrap();
+    assert_eq!(copy, npyz::generate::random(&dtype, &[3, 50], 9).unwrap());
+}
+
+#[test]
+fn duplicate_names() {
+    use npyz::npz::DuplicateNames;
+
+    let mut buf = io::Cursor::new(vec![]);
+    let mut npz = NpzWriter::new(&mut buf);
+    write_ints(&mut npz, "a", &[1, 2]);
+    write_ints(&mut npz, "b", &[3]);
+    write_ints(&mut npz, "a", &[4, 5, 6]);
+    npz.finish().unwrap();
+    let bytes = buf.into_inner();
+
+    let read_a = |npz: &mut NpzArchive<_>| npz.by_name("a").unwrap().unwrap().into_vec::<i64>().unwrap();
+    let mut npz = NpzArchive::new(io::Cursor::new(&bytes[..])).unwrap();
+    assert_eq!(npz.array_names().filter(|&n| n == "a").count(), 1);
+    assert_eq!((npz.occurrences("a"), npz.occurrences("b"), npz.occurrences("c")), (2, 1, 0));
+    assert_eq!(read_a(&mut npz), vec![4, 5, 6]);
+
+    let mut npz = npz.with_duplicate_names(DuplicateNames::First);
+    assert_eq!(read_a(&mut npz), vec![1, 2]);
+    assert_eq!(npz.header("a").unwrap().unwrap().shape(), &[2]);
+
+    let mut npz = npz.with_duplicate_names(DuplicateNames::Error);
+    let err = npz.by_name("a").err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("array 'a'"), "{}", err);
+    assert_eq!(npz.by_name("b").unwrap().unwrap().into_vec::<i64>().unwrap(), vec![3]);
+
+    let copies = (0..).map_while(|n| npz.by_name_nth("a", n).unwrap().map(|npy| npy.into_vec::<i64>().unwrap()));
+    assert_eq!(copies.collect::<Vec<_>>(), vec![vec![1, 2], vec![4, 5, 6]]);
+    assert!(npz.by_name_nth("b", 1).unwrap().is_none());
+}
+
+#[test]
+fn entries() {
+    use npyz::{DType, Order};
+    use npyz::npz::NpzEntry;
+
+    let mut npz = NpzArchive::open("test-data/compressed.npz").unwrap();
+    let entries = npz.entries().unwrap();
+    assert_eq!(entries, vec![
+        NpzEntry {
+            name: "ints".to_string(), dtype: DType::parse("'<i8'").unwrap(), shape: vec![4], order: Order::C,
+            compression: npyz::zip::CompressionMethod::Deflated, compressed_size: 80, size: 160,
+        },
+        NpzEntry {
+            name: "floats".to_string(), dtype: DType::parse("'<f8'").unwrap(), shape: vec![2, 1], order: Order::C,
+            compression: npyz::zip::CompressionMethod::Deflated, compressed_size: 78, size: 144,
+        },
+    ]);
+    assert_eq!(npz.header("floats").unwrap().unwrap().shape(), &[2, 1]);
+
+    let mut buf = io::Cursor::new(vec![]);
+    let mut npz = NpzWriter::new(&mut buf).with_manifest();
+    write_ints(&mut npz, "a", &[1, 2]);
+    write_ints(&mut npz, "a", &[3]);
+    npz.finish().unwrap();
+    let mut npz = NpzArchive::new(io::Cursor::new(buf.into_inner())).unwrap();
+    let shapes = npz.entries().unwrap().into_iter().map(|entry| (entry.name, entry.shape)).collect::<Vec<_>>();
+    assert_eq!(shapes, vec![("a".to_string(), vec![2]), ("a".to_string(), vec![1])]);
+}
+
+#[test]
+fn remove_and_replace() {
+    let mut npz = NpzWriter::new(io::Cursor::new(vec![])).with_manifest();
+    write_ints(&mut npz, "a", &[1, 2]);
+    write_ints(&mut npz, "b", &[3]);
+    write_ints(&mut npz, "a", &[4, 5, 6]);
+    let bytes = npz.finish().unwrap().into_inner();
+    let read = |npz: &mut NpzArchive<_>, name| npz.by_name(name).unwrap().map(|npy| npy.into_vec::<i64>().unwrap());
+
+    let mut source = NpzArchive::new(io::Cursor::new(&bytes[..])).unwrap();
+    let removed = source.remove("a", io::Cursor::new(vec![])).unwrap().into_inner();
+    let mut npz = NpzArchive::new(io::Cursor::new(removed)).unwrap();
+    assert_eq!(npz.array_names().collect::<Vec<_>>(), vec!["b"]);
+    assert_eq!(read(&mut npz, "b"), Some(vec![3]));
+    assert!(npz.manifest().unwrap().is_none());
+
+    let mut replacement = vec![];
+    let mut writer = npyz::WriteOptions::new().default_dtype().writer(&mut replacement).shape(&[1]).begin_nd().unwrap();
+    writer.extend([7_i64]).unwrap();
+    writer.finish().unwrap();
+    let replaced = source.replace("a", npyz::NpyFile::new(&replacement[..]).unwrap(), io::Cursor::new(vec![])).unwrap().into_inner();
+    let mut npz = NpzArchive::new(io::Cursor::new(replaced)).unwrap();
+    assert_eq!((npz.occurrences("a"), npz.occurrences("b")), (1, 1));
+    assert_eq!(read(&mut npz, "a"), Some(vec![7]));
+    assert_eq!(read(&mut npz, "b"), Some(vec![3]));
+
+    let err = source.remove("c", io::Cursor::new(vec![])).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+// encrypted.npz holds the members of uncompressed.npz, made with:
+//   zip -X -P secret test-data/encrypted.npz ints.npy floats.npy
+#[cfg(feature = "npz-crypto")]
+#[test]
+fn encrypted() {
+    let open = || NpzArchive::open("test-data/encrypted.npz").unwrap();
+    test_basic_read(open().with_password("secret"));
+
+    let err = open().by_name("ints").err().unwrap();
+    assert!(matches!(npyz::Error::from(err).root(), npyz::Error::Zip(_)));
+    assert!(open().with_password("wrong").by_name("ints").is_err());
+
+    // the arrays are decrypted, but the floats cannot be read as i64
+    let err = open().with_password(b"secret").load_all_parallel::<i64>().unwrap_err();
+    assert!(matches!(npyz::Error::from(err).root(), npyz::Error::DTypeMismatch { .. }));
+    let mut npz = open().with_password(b"secret");
+    assert_eq!(npz.header("floats").unwrap().unwrap().shape(), &[2, 1]);
+}
+
+#[test]
+fn stream_read() {
+    for path in ["test-data/uncompressed.npz", "test-data/compressed.npz"] {
+        let bytes = std::fs::read(path).unwrap();
+        let mut npz = NpzStreamReader::new(&bytes[..]);
+        let mut arrays = vec![];
+        while let Some((name, npy)) = npz.next_array().unwrap() {
+            let shape = npy.shape().to_vec();
+            // only read part of the ints, to check that the rest is skipped
+            let first = match &name[..] {
+                "ints" => npy.data::<i64>().unwrap().next().unwrap().unwrap() as f64,
+                _ => npy.into_vec::<f64>().unwrap()[0],
+            };
+            arrays.push((name, shape, first));
+        }
+        arrays.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(arrays, vec![("floats".to_string(), vec![2, 1], 1.0), ("ints".to_string(), vec![4], 1.0)]);
+        assert!(npz.next_array().unwrap().is_none());
+    }
+}
+
+#[test]
+fn stream_read_skips_other_members() {
+    let mut buf = io::Cursor::new(vec![]);
+    let mut npz = NpzWriter::new(&mut buf).with_manifest();
+    write_ints(&mut npz, "a", &[1, 2]);
+    write_ints(&mut npz, "b", &[3]);
+    npz.finish().unwrap();
+    let bytes = buf.into_inner();
+
+    let mut npz = NpzStreamReader::new(&bytes[..]);
+    let mut names = vec![];
+    while let Some((name, npy)) = npz.next_array().unwrap() {
+        names.push((name, npy.into_vec::<i64>().unwrap()));
+    }
+    assert_eq!(names, vec![("a".to_string(), vec![1, 2]), ("b".to_string(), vec![3])]);
+
+    // a stream that ends within a member
+    let mut npz = NpzStreamReader::new(&bytes[..40]);
+    assert!(npz.next_array().is_err());
+}
+
+#[test]
+fn read_from_range_source() {
+    struct Remote { bytes: Vec<u8>, requests: usize }
+
+    impl npyz::RangeSource for Remote {
+        fn size(&mut self) -> io::Result<u64> {
+            Ok(self.bytes.len() as u64)
+        }
+
+        fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+            self.requests += 1;
+            buf.copy_from_slice(&self.bytes[offset as usize..][..buf.len()]);
+            Ok(())
+        }
+    }
+
+    let mut remote = Remote { bytes: std::fs::read("test-data/compressed.npz").unwrap(), requests: 0 };
+    test_basic_read(NpzArchive::new(npyz::RangeReader::new(&mut remote).unwrap()).unwrap());
+    // a few blocks, rather than one request for each of the small reads made by the zip crate
+    assert!(remote.requests <= 4, "{}", remote.requests);
+}
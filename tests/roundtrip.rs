@@ -36,6 +36,7 @@ struct Array {
 
 #[derive(Serialize, Deserialize, AutoSerialize)]
 #[derive(Debug, PartialEq, Clone)]
+#[allow(dead_code)]
 struct Version3 {
     v1: f32,
     v2: f32,
@@ -115,7 +116,7 @@ fn roundtrip() {
             v_i8: i as i8,
             v_i16: i as i16,
             v_i32: i as i32,
-            v_i64: i as i64,
+            v_i64: i,
             v_u8: i as u8,
             v_u16: i as u16,
             v_u32: i as u32,
@@ -422,7 +423,7 @@ fn nested_array_of_struct() {
 
     let expected_data_bytes = {
         let mut buf = vec![];
-        for x in vec![1.0, 2.0, 3.0] {
+        for x in [1.0, 2.0, 3.0] {
             buf.extend_from_slice(&f64::to_bits(x).to_le_bytes());
         }
         buf
@@ -441,6 +442,47 @@ fn nested_array_of_struct() {
     assert_eq!(data.into_vec::<Outer>().unwrap(), vec![row]);
 }
 
+#[test]
+fn array_of_non_copy_struct() {
+    // neither Copy nor Default
+    #[derive(npyz::Deserialize, npyz::Serialize)]
+    #[derive(Debug, PartialEq, Clone)]
+    struct Outer {
+        points: [Point; 2],
+        names: [String; 2],
+    }
+
+    #[derive(npyz::Deserialize, npyz::Serialize)]
+    #[derive(Debug, PartialEq, Clone)]
+    struct Point {
+        xyz: [f32; 3],
+        label: Vec<u8>,
+    }
+
+    let point = |x: f32, label: &str| Point { xyz: [x, x + 1.0, x + 2.0], label: label.as_bytes().to_vec() };
+    let rows = vec![
+        Outer { points: [point(1.0, "a"), point(4.0, "bc")], names: ["first".into(), "second".into()] },
+        Outer { points: [point(7.0, ""), point(10.0, "d")], names: ["".into(), "third".into()] },
+    ];
+
+    let dtype = DType::Record(vec![
+        Field { name: "points".into(), dtype: DType::Array(2, Box::new(DType::Record(vec![
+            Field { name: "xyz".into(), dtype: DType::Array(3, Box::new(DType::Plain("<f4".parse().unwrap()))) },
+            plain_field("label", "|S2"),
+        ])))},
+        Field { name: "names".into(), dtype: DType::Array(2, Box::new(DType::Plain("<U6".parse().unwrap()))) },
+    ]);
+
+    let mut buffer = vec![];
+    let mut writer = npyz::WriteOptions::new().dtype(dtype.clone()).shape(&[2]).writer(&mut buffer).begin_nd().unwrap();
+    writer.extend(rows.clone()).unwrap();
+    writer.finish().unwrap();
+
+    let data = npyz::NpyFile::new(&buffer[..]).unwrap();
+    assert_eq!(data.dtype(), dtype);
+    assert_eq!(data.into_vec::<Outer>().unwrap(), rows);
+}
+
 #[test]
 fn roundtrip_zero_length_array_member() {
     // Similar to:
@@ -565,6 +607,75 @@ fn roundtrip_version3() {
     assert_eq!(data.into_vec::<Row>().unwrap(), vec![row]);
 }
 
+#[test]
+fn error_context_names_field() {
+    #[derive(npyz::Serialize, npyz::Deserialize, npyz::AutoSerialize)]
+    #[derive(Debug, PartialEq, Clone)]
+    struct Outer {
+        id: u8,
+        inner: Inner,
+    }
+
+    #[derive(npyz::Serialize, npyz::Deserialize, npyz::AutoSerialize)]
+    #[derive(Debug, PartialEq, Clone)]
+    struct Inner {
+        flag: bool,
+    }
+
+    let mut cursor = Cursor::new(vec![]);
+    let mut writer = npyz::WriteOptions::new().default_dtype().writer(&mut cursor).shape(&[2]).begin_nd().unwrap();
+    writer.push(&Outer { id: 1, inner: Inner { flag: true } }).unwrap();
+    writer.push(&Outer { id: 2, inner: Inner { flag: false } }).unwrap();
+    writer.finish().unwrap();
+
+    // corrupt the last bool
+    let mut buffer = cursor.into_inner();
+    *buffer.last_mut().unwrap() = 2;
+
+    let err = npyz::NpyFile::new(&buffer[..]).unwrap().into_vec::<Outer>().unwrap_err();
+    let err = npyz::Error::from(err);
+    assert!(matches!(err.root(), npyz::Error::InvalidData(_)));
+    let context = err.context().unwrap();
+    assert_eq!(context.index(), Some(1));
+    assert_eq!(context.field(), ["inner", "flag"]);
+    assert_eq!(context.byte_offset(), Some(buffer.len() as u64 - 2));
+}
+
+#[cfg(feature = "uom")]
+#[test]
+fn roundtrip_quantity_fields() {
+    use npyz::FieldUnits;
+    use npyz::uom::si::f64::{Length, Velocity};
+    use npyz::uom::si::length::kilometer;
+    use npyz::uom::si::velocity::kilometer_per_hour;
+
+    #[derive(npyz::Serialize, npyz::Deserialize, npyz::AutoSerialize)]
+    #[derive(Debug, PartialEq, Clone)]
+    struct Sample {
+        id: u32,
+        position: Length,
+        speed: Velocity,
+    }
+
+    let units = FieldUnits::new().field::<Length>("position").field::<Velocity>("speed");
+    let sample = Sample { id: 3, position: Length::new::<kilometer>(1.0), speed: Velocity::new::<kilometer_per_hour>(36.0) };
+
+    let mut buffer = vec![];
+    let mut writer = npyz::WriteOptions::new().default_dtype().units(&units).writer(&mut buffer).shape(&[1]).begin_nd().unwrap();
+    writer.push(&sample).unwrap();
+    writer.finish().unwrap();
+
+    let npy = npyz::NpyFile::new(&buffer[..]).unwrap();
+    assert_eq!(npy.dtype().descr(), "[('id', '<u4'), ('position', '<f8'), ('speed', '<f8'), ]");
+    units.check(npy.header()).unwrap();
+    let err = FieldUnits::new().field::<Velocity>("position").check(npy.header()).unwrap_err();
+    assert_eq!(err.to_string(), "field 'position' has unit 'm', but 'm s^-1' was expected");
+
+    let read = npy.into_vec::<Sample>().unwrap();
+    assert_eq!(read, vec![sample]);
+    assert_eq!(read[0].position.value, 1000.0);
+}
+
 #[track_caller]
 fn assert_version(npy_bytes: &[u8], expected: (u8, u8)) {
     assert_eq!(&npy_bytes[6..8], &[expected.0, expected.1]);
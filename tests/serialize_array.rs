@@ -79,7 +79,7 @@ fn read_write_nested() {
     let dtype = DType::parse(ARRAY23_DESCR_LE).unwrap();
     let value = Array23 { field: [[1, 3, 5], [7, 9, 11]] };
     let mut bytes = vec![];
-    for n in vec![1, 3, 5, 7, 9, 11] {
+    for n in [1, 3, 5, 7, 9, 11] {
         bytes.extend_from_slice(&i32::to_le_bytes(n));
     }
 
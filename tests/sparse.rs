@@ -178,6 +178,16 @@ fn read_sparse_dynamic() {
     test_writing_sparse!(Sparse<i64>, Sparse::Bsr(example_bsr()));
 }
 
+#[test] fn write_sparse_empty() {
+    use sparse::Sparse;
+
+    test_writing_sparse!(Sparse<i64>, Sparse::Coo(sparse::Coo { shape: [0, 0], data: vec![], row: vec![], col: vec![] }));
+    test_writing_sparse!(Sparse<i64>, Sparse::Csr(sparse::Csr { shape: [0, 6], data: vec![], indices: vec![], indptr: vec![0] }));
+    test_writing_sparse!(Sparse<i64>, Sparse::Csc(sparse::Csc { shape: [3, 0], data: vec![], indices: vec![], indptr: vec![0] }));
+    test_writing_sparse!(Sparse<i64>, Sparse::Dia(sparse::Dia { shape: [3, 6], data: vec![], offsets: vec![] }));
+    test_writing_sparse!(Sparse<i64>, Sparse::Bsr(sparse::Bsr { shape: [4, 4], data: vec![], indices: vec![], indptr: vec![0, 0, 0], blocksize: [2, 2] }));
+}
+
 
 #[test]
 fn read_wrong_format_err() {
@@ -218,7 +228,24 @@ fn sparse_csr_unsorted() {
 }
 
 #[test]
-fn read_fortran_order_err() {
+fn sparse_unsorted_diagnostic() {
+    use std::sync::{Arc, Mutex};
+
+    let found = Arc::new(Mutex::new(vec![]));
+    let options = npyz::ReadOptions::new().on_diagnostic({
+        let found = found.clone();
+        move |d| found.lock().unwrap().push(d.clone())
+    });
+
+    sparse::Csr::<i64>::from_npz(&mut open_test_npz("csr.npz").with_read_options(options.clone())).unwrap();
+    assert_eq!(*found.lock().unwrap(), vec![]);
+
+    sparse::Csr::<i64>::from_npz(&mut open_test_npz("csr-unsorted.npz").with_read_options(options)).unwrap();
+    assert_eq!(*found.lock().unwrap(), vec![npyz::Diagnostic::UnsortedSparseIndices { format: "csr" }]);
+}
+
+#[test]
+fn read_fortran_order() {
     // python:
     //   import numpy as np
     //   npz = np.load('test-data/sparse/bsr.npz')
@@ -229,8 +256,25 @@ fn read_fortran_order_err() {
     //   assert mats['data'].flags['F_CONTIGUOUS']
     //
     //   np.savez('test-data/sparse/bsr-f-order.npz', **mats)
-    let err = sparse::Bsr::<i64>::from_npz(&mut open_test_npz("bsr-f-order.npz")).unwrap_err();
-    assert!(err.to_string().contains("ortran"));
+    let m = sparse::Bsr::<i64>::from_npz(&mut open_test_npz("bsr-f-order.npz")).unwrap();
+    assert_eq!(m, example_bsr());
+
+    let m = sparse::Dia::<i64>::from_npz(&mut dia_npz_with_f_order_data()).unwrap();
+    assert_eq!(m, example_dia());
+}
+
+fn dia_npz_with_f_order_data() -> NpzArchive<std::io::Cursor<Vec<u8>>> {
+    use npyz::WriterBuilder;
+
+    let mut source = open_test_npz("dia.npz");
+    let mut npz = NpzWriter::new(std::io::Cursor::new(vec![]));
+    for name in ["format", "shape", "offsets"] {
+        npz.copy_entry_from(&mut source, name).unwrap();
+    }
+    npz.array::<i64>("data", Default::default()).unwrap()
+        .default_dtype().shape(&[3, 3]).order(npyz::Order::Fortran).begin_nd().unwrap()
+        .extend(vec![6, 1, 0, 0, 2, 0, 0, 7, 4]).unwrap();
+    NpzArchive::new(std::io::Cursor::new(npz.finish().unwrap().into_inner())).unwrap()
 }
 
 #[test]
@@ -244,3 +288,238 @@ fn read_bad_dimension_err() {
     let err = sparse::Bsr::<i64>::from_npz(&mut open_test_npz("bsr-bad-ndim.npz")).unwrap_err();
     assert!(err.to_string().contains("ndim"));
 }
+
+#[test]
+fn try_write_inconsistent_err() {
+    let try_write = |matrix: sparse::Sparse<i64>| {
+        let mut buf = std::io::Cursor::new(vec![]);
+        let result = matrix.try_write_npz(&mut NpzWriter::new(&mut buf));
+        matches!(result.map_err(npyz::Error::from), Err(npyz::Error::InvalidInput(_)))
+    };
+
+    let mut csr = example_csr();
+    csr.indptr.pop();
+    assert!(try_write(sparse::Sparse::Csr(csr)));
+
+    let mut dia = example_dia();
+    dia.offsets.clear();
+    assert!(try_write(sparse::Sparse::Dia(dia)));
+
+    let mut bsr = example_bsr();
+    bsr.data.pop();
+    assert!(try_write(sparse::Sparse::Bsr(bsr)));
+
+    let mut bsr = example_bsr();
+    bsr.blocksize = [0, 2];
+    assert!(try_write(sparse::Sparse::Bsr(bsr)));
+
+    let mut coo = example_coo();
+    coo.row[0] = 3;
+    assert!(try_write(sparse::Sparse::Coo(coo)));
+
+    let mut buf = std::io::Cursor::new(vec![]);
+    example_coo().try_write_npz(&mut NpzWriter::new(&mut buf)).unwrap();
+}
+
+#[test]
+fn convert_formats() {
+    assert_eq!(example_coo().to_csr(), example_csr());
+    assert_eq!(example_coo().to_csc(), example_csc());
+    assert_eq!(example_csr().to_coo(), example_coo());
+    assert_eq!(example_csr().to_csc(), example_csc());
+    assert_eq!(example_csc().to_csr(), example_csr());
+    assert_eq!(example_csc().to_coo().to_csr(), example_csr());
+
+    // duplicates are kept
+    let csr = example_coo_dupes().to_csr();
+    assert_eq!(csr.indptr, vec![0, 0, 0, 2, 2, 2]);
+    assert_eq!(csr.to_coo(), example_coo_dupes());
+
+    // converting between CSR and CSC sorts the indices
+    let csr = example_csr_unsorted().to_csc().to_csr();
+    assert_eq!(csr.indices, vec![0, 2, 1, 0, 2]);
+    assert_eq!(csr.indptr, example_csr_unsorted().indptr);
+
+    // slices can be converted too
+    let csr = example_csr();
+    let borrowed = sparse::CsrBase { shape: csr.shape, data: &csr.data[..], indices: &csr.indices[..], indptr: &csr.indptr[..] };
+    assert_eq!(borrowed.to_csc(), example_csc());
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn convert_out_of_bounds() {
+    let mut coo = example_coo();
+    coo.row[0] = 3;
+    coo.to_csr();
+}
+
+#[test]
+fn to_dense() {
+    let expected = vec![
+        1, 0, 4, 0, 0, 0,
+        0, 2, 0, 0, 0, 0,
+        6, 0, 7, 0, 0, 0,
+    ];
+    assert_eq!(example_coo().to_dense(), expected);
+    assert_eq!(example_csr().to_dense(), expected);
+    assert_eq!(example_csc().to_dense(), expected);
+    assert_eq!(example_dia().to_dense(), expected);
+    assert_eq!(example_bsr().to_dense(), expected);
+    assert_eq!(example_csr_unsorted().to_dense(), vec![2, 0, 2, 0, 1, 0, 2, 0, 2]);
+
+    let sparse = sparse::Sparse::Bsr(example_bsr());
+    assert_eq!(sparse.shape(), [3, 6]);
+    assert_eq!(sparse.to_dense(), expected);
+
+    // duplicates are summed, and the buffer is overwritten
+    let mut buf = vec![-1; 25];
+    example_coo_dupes().fill_dense(&mut buf);
+    assert_eq!(buf.iter().sum::<i64>(), 30);
+    assert_eq!(buf[2 * 5 + 3], 30);
+}
+
+#[test]
+#[should_panic(expected = "buffer length")]
+fn fill_dense_wrong_len() {
+    example_csr().fill_dense(&mut [0; 17]);
+}
+
+#[test]
+fn read_csr_rows() {
+    let expected = example_csr();
+    let mut reader = sparse::Csr::<i64>::rows_from_npz(open_test_npz("csr.npz")).unwrap();
+    assert_eq!(reader.shape(), expected.shape);
+    assert_eq!(reader.indptr(), &expected.indptr[..]);
+
+    let rows = reader.rows().unwrap().collect::<std::io::Result<Vec<_>>>().unwrap();
+    assert_eq!(rows, vec![
+        (0, vec![0, 2], vec![1, 4]),
+        (1, vec![1], vec![2]),
+        (2, vec![0, 2], vec![6, 7]),
+    ]);
+
+    // rows can be read again
+    let mut rows = reader.rows().unwrap();
+    assert_eq!(rows.size_hint(), (3, Some(3)));
+    assert_eq!(rows.next().unwrap().unwrap(), (0, vec![0, 2], vec![1, 4]));
+}
+
+#[test]
+fn read_csr_rows_err() {
+    let read_rows = |matrix: sparse::Csr<i64>| {
+        let mut buf = std::io::Cursor::new(vec![]);
+        matrix.write_npz(&mut NpzWriter::new(&mut buf)).unwrap();
+        let npz = NpzArchive::new(std::io::Cursor::new(buf.into_inner())).unwrap();
+        let mut reader = sparse::Csr::<i64>::rows_from_npz(npz)?;
+        let rows = reader.rows()?.collect::<std::io::Result<Vec<_>>>();
+        rows
+    };
+
+    let mut csr = example_csr();
+    csr.data.pop();
+    assert!(read_rows(csr).unwrap_err().to_string().contains("indptr ends at 5"));
+
+    let mut csr = example_csr();
+    csr.indptr = vec![0, 3, 2, 5];
+    assert!(read_rows(csr).unwrap_err().to_string().contains("nondecreasing"));
+}
+
+#[test]
+fn validate() {
+    use sparse::SparseError;
+
+    assert_eq!(example_coo().validate(), Ok(()));
+    assert_eq!(example_csr().validate(), Ok(()));
+    assert_eq!(example_csc().validate(), Ok(()));
+    assert_eq!(example_dia().validate(), Ok(()));
+    assert_eq!(example_bsr().validate(), Ok(()));
+    assert_eq!(example_coo_dupes().validate(), Ok(()));
+    assert_eq!(example_csr_unsorted().validate(), Ok(()));
+
+    let mut coo = example_coo();
+    coo.col[3] = 6;
+    assert_eq!(coo.validate(), Err(SparseError::IndexOutOfBounds { name: "col", position: 3, index: 6, bound: 6 }));
+
+    let mut csr = example_csr();
+    csr.indices.pop();
+    assert_eq!(csr.validate(), Err(SparseError::LengthMismatch { name: "indices", len: 4, expected: 5 }));
+
+    let mut csr = example_csr();
+    csr.indptr = vec![0, 3, 2, 5];
+    assert_eq!(csr.validate(), Err(SparseError::IndptrNotMonotone { position: 1 }));
+
+    let mut csc = example_csc();
+    csc.indptr = vec![1, 2, 3, 5, 5, 5, 6];
+    assert_eq!(csc.validate(), Err(SparseError::IndptrOutOfRange { first: 1, last: 6, len: 5 }));
+
+    let mut bsr = example_bsr();
+    bsr.blocksize = [2, 2];
+    assert_eq!(bsr.validate(), Err(SparseError::ShapeNotDivisible { shape: [3, 6], blocksize: [2, 2] }));
+
+    let mut bsr = example_bsr();
+    bsr.data.pop();
+    assert_eq!(bsr.validate(), Err(SparseError::BlocksizeMismatch { blocksize: [1, 2], num_blocks: 5, data_len: 9 }));
+
+    let mut bsr = example_bsr();
+    bsr.indices[0] = 3;
+    assert!(matches!(sparse::Sparse::Bsr(bsr).validate(), Err(SparseError::IndexOutOfBounds { bound: 3, .. })));
+
+    let mut dia = example_dia();
+    dia.data.pop();
+    assert_eq!(dia.validate(), Err(SparseError::DiagonalLengthMismatch { len: 8, num_offsets: 3 }));
+}
+
+// An NPZ file with the contents of `example_csr`, but with the given `indices` and `indptr`.
+fn csr_npz_with<I: npyz::AutoSerialize, P: npyz::AutoSerialize>(indices: Vec<I>, indptr: Vec<P>) -> NpzArchive<std::io::Cursor<Vec<u8>>> {
+    use npyz::WriterBuilder;
+
+    let mut source = open_test_npz("csr.npz");
+    let mut npz = NpzWriter::new(std::io::Cursor::new(vec![]));
+    for name in ["format", "shape", "data"] {
+        npz.copy_entry_from(&mut source, name).unwrap();
+    }
+    let len = indices.len() as u64;
+    npz.array::<I>("indices", Default::default()).unwrap().default_dtype().shape(&[len]).begin_nd().unwrap().extend(indices).unwrap();
+    let len = indptr.len() as u64;
+    npz.array::<P>("indptr", Default::default()).unwrap().default_dtype().shape(&[len]).begin_nd().unwrap().extend(indptr).unwrap();
+    NpzArchive::new(std::io::Cursor::new(npz.finish().unwrap().into_inner())).unwrap()
+}
+
+#[test]
+fn read_any_index_dtype() {
+    let m = sparse::Csr::<i64>::from_npz(&mut csr_npz_with(vec![0u16, 2, 1, 0, 2], vec![0u8, 2, 3, 5])).unwrap();
+    assert_eq!(m, example_csr());
+    let m = sparse::Csr::<i64>::from_npz(&mut csr_npz_with(vec![0u64, 2, 1, 0, 2], vec![0i16, 2, 3, 5])).unwrap();
+    assert_eq!(m, example_csr());
+
+    let rows = sparse::Csr::<i64>::rows_from_npz(csr_npz_with(vec![0u32, 2, 1, 0, 2], vec![0i8, 2, 3, 5])).unwrap()
+        .rows().unwrap().collect::<std::io::Result<Vec<_>>>().unwrap();
+    assert_eq!(rows[2], (2, vec![0, 2], vec![6, 7]));
+
+    let err = sparse::Csr::<i64>::from_npz(&mut csr_npz_with(vec![0i8, 2, -1, 0, 2], vec![0u8, 2, 3, 5])).unwrap_err();
+    assert!(err.to_string().contains("contains -1, which does not fit in u64"), "{}", err);
+
+    let err = sparse::Csr::<i64>::from_npz(&mut csr_npz_with(vec![0.0f64, 2.0, 1.0, 0.0, 2.0], vec![0u8, 2, 3, 5])).unwrap_err();
+    assert!(err.to_string().contains("invalid dtype for 'indices'"), "{}", err);
+}
+
+#[test]
+fn write_sparse_compression() {
+    let matrix = sparse::Coo::<i64> { shape: [1000, 1000], data: vec![7; 1000], row: (0..1000).collect(), col: (0..1000).collect() };
+    let write = |method| {
+        let mut buf = std::io::Cursor::new(vec![]);
+        let mut npz = NpzWriter::new(&mut buf).with_compression(method, None);
+        matrix.write_npz(&mut npz).unwrap();
+        npz.finish().unwrap();
+        buf.into_inner()
+    };
+
+    let stored = write(zip::CompressionMethod::Stored);
+    let deflated = write(zip::CompressionMethod::Deflated);
+    assert!(deflated.len() < stored.len() / 2, "{} {}", deflated.len(), stored.len());
+
+    let mut npz = NpzArchive::new(std::io::Cursor::new(&deflated)).unwrap();
+    assert_eq!(npz.zip_archive().by_name("data.npy").unwrap().compression(), zip::CompressionMethod::Deflated);
+    assert_eq!(sparse::Coo::<i64>::from_npz(&mut npz).unwrap(), matrix);
+}
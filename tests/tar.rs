@@ -0,0 +1,93 @@
+use std::io;
+use npyz::NpyTarReader;
+
+// The archives contain c-order.npy, plain.npy (in a directory with a long name), structured.npy and a
+// text file, and were created with GNU tar:
+//   tar --sort=name --owner=0 --group=0 --numeric-owner --mtime=@0 --format=gnu -cf test-data/arrays.tar *
+//   tar ... --format=posix --pax-option=delete=atime,delete=ctime -cf test-data/arrays-pax.tar *
+//   gzip -9n -c test-data/arrays.tar > test-data/arrays.tar.gz
+const LONG_DIR: &str = "nested/a_directory_name_that_is_long_enough_that_the_path_does_not_fit_in_the_hundred_bytes_of_a_tar_header";
+
+fn read_all(path: &str) -> Vec<(String, Vec<u8>)> {
+    let mut tar = NpyTarReader::new(std::fs::File::open(path).unwrap()).unwrap();
+    let mut arrays = vec![];
+    while let Some((name, npy)) = tar.next_array().unwrap() {
+        let mut bytes = vec![];
+        npy.copy_raw_to(&mut bytes).unwrap();
+        arrays.push((name, bytes));
+    }
+    assert!(tar.next_array().unwrap().is_none());
+    arrays
+}
+
+fn expected() -> Vec<(String, Vec<u8>)> {
+    vec![
+        ("c-order".to_string(), std::fs::read("test-data/c-order.npy").unwrap()),
+        (format!("{}/plain", LONG_DIR), std::fs::read("test-data/plain.npy").unwrap()),
+        ("structured".to_string(), std::fs::read("test-data/structured.npy").unwrap()),
+    ]
+}
+
+#[test]
+fn read_gnu() {
+    assert_eq!(read_all("test-data/arrays.tar"), expected());
+}
+
+#[test]
+fn read_pax() {
+    assert_eq!(read_all("test-data/arrays-pax.tar"), expected());
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn read_gzip() {
+    assert_eq!(read_all("test-data/arrays.tar.gz"), expected());
+}
+
+#[cfg(not(feature = "gzip"))]
+#[test]
+fn gzip_needs_feature() {
+    let err = NpyTarReader::new(std::fs::File::open("test-data/arrays.tar.gz").unwrap()).unwrap_err();
+    assert!(err.to_string().contains("\"gzip\" feature"), "{}", err);
+}
+
+#[test]
+fn skips_unread_data() {
+    let bytes = std::fs::read("test-data/arrays.tar").unwrap();
+    let mut tar = NpyTarReader::new(&bytes[..]).unwrap();
+    let mut names = vec![];
+    while let Some((name, npy)) = tar.next_array().unwrap() {
+        // read only part of each array
+        let mut reader = npy.data::<f64>().ok();
+        if let Some(reader) = &mut reader {
+            reader.next();
+        }
+        names.push(name);
+    }
+    assert_eq!(names.len(), 3);
+}
+
+fn next_error(bytes: &[u8]) -> io::Error {
+    match NpyTarReader::new(bytes).unwrap().next_array() {
+        Ok(_) => panic!("expected an error"),
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn errors() {
+    let bytes = std::fs::read("test-data/arrays.tar").unwrap();
+
+    // cut off in the middle of the header of c-order.npy
+    let first_npy = bytes.windows(6).position(|w| w == b"\x93NUMPY").unwrap();
+    let err = next_error(&bytes[..first_npy + 100]);
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{}", err);
+
+    let mut corrupt = bytes.clone();
+    corrupt[0] ^= 1;
+    let err = next_error(&corrupt);
+    assert!(err.to_string().contains("checksum"), "{}", err);
+
+    let err = next_error(b"not a tar file");
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{}", err);
+}